@@ -0,0 +1,277 @@
+use anyhow::Result;
+use crate::fielddata::{CastPolicy, FieldData};
+use crate::metadata::Dtype;
+use crate::pointcloud::PointCloud;
+
+/// An elementwise operation on field values, named after the NumPy ufunc it mirrors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldOp {
+    Add(f64),
+    Subtract(f64),
+    Multiply(f64),
+    Divide(f64),
+    Power(f64),
+    Minimum(f64),
+    Maximum(f64),
+    Clip(f64, f64),
+    Abs,
+    Negative,
+    Sqrt,
+    Square,
+    Exp,
+    Log,
+    Log10,
+    Floor,
+    Ceil,
+    Round,
+}
+
+impl FieldOp {
+    /// Parses an operation from a NumPy ufunc name and its scalar arguments, e.g.
+    /// `("multiply", [0.5])` or `("clip", [0.0, 1.0])`.
+    pub fn from_spec(name: &str, args: &[f64]) -> Result<Self> {
+        let unary = |op: FieldOp| -> Result<FieldOp> {
+            anyhow::ensure!(args.is_empty(), "Operation '{}' takes no arguments, got {}", name, args.len());
+            Ok(op)
+        };
+        let binary = |op: fn(f64) -> FieldOp| -> Result<FieldOp> {
+            match args {
+                [v] => Ok(op(*v)),
+                _ => anyhow::bail!("Operation '{}' takes 1 argument, got {}", name, args.len()),
+            }
+        };
+        match name {
+            "add" => binary(FieldOp::Add),
+            "subtract" => binary(FieldOp::Subtract),
+            "multiply" => binary(FieldOp::Multiply),
+            "divide" => binary(FieldOp::Divide),
+            "power" => binary(FieldOp::Power),
+            "minimum" => binary(FieldOp::Minimum),
+            "maximum" => binary(FieldOp::Maximum),
+            "clip" => match args {
+                [lo, hi] if lo <= hi => Ok(FieldOp::Clip(*lo, *hi)),
+                [lo, hi] => anyhow::bail!("Clip bounds must satisfy min <= max, got {} and {}", lo, hi),
+                _ => anyhow::bail!("Operation 'clip' takes 2 arguments, got {}", args.len()),
+            },
+            "abs" => unary(FieldOp::Abs),
+            "negative" => unary(FieldOp::Negative),
+            "sqrt" => unary(FieldOp::Sqrt),
+            "square" => unary(FieldOp::Square),
+            "exp" => unary(FieldOp::Exp),
+            "log" => unary(FieldOp::Log),
+            "log10" => unary(FieldOp::Log10),
+            "floor" => unary(FieldOp::Floor),
+            "ceil" => unary(FieldOp::Ceil),
+            "round" => unary(FieldOp::Round),
+            _ => anyhow::bail!("Unknown operation '{}'", name),
+        }
+    }
+
+    /// Applies the operation to one value. NaN inputs give NaN.
+    pub fn eval(&self, v: f64) -> f64 {
+        match *self {
+            FieldOp::Add(a) => v + a,
+            FieldOp::Subtract(a) => v - a,
+            FieldOp::Multiply(a) => v * a,
+            FieldOp::Divide(a) => v / a,
+            FieldOp::Power(a) => v.powf(a),
+            FieldOp::Minimum(a) => if v.is_nan() { v } else { v.min(a) },
+            FieldOp::Maximum(a) => if v.is_nan() { v } else { v.max(a) },
+            FieldOp::Clip(lo, hi) => if v.is_nan() { v } else { v.clamp(lo, hi) },
+            FieldOp::Abs => v.abs(),
+            FieldOp::Negative => -v,
+            FieldOp::Sqrt => v.sqrt(),
+            FieldOp::Square => v * v,
+            FieldOp::Exp => v.exp(),
+            FieldOp::Log => v.ln(),
+            FieldOp::Log10 => v.log10(),
+            FieldOp::Floor => v.floor(),
+            FieldOp::Ceil => v.ceil(),
+            FieldOp::Round => v.round_ties_even(),
+        }
+    }
+}
+
+/// The range of values that `normalize_field` maps to [0, 1].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Normalization {
+    /// The minimum and maximum values.
+    MinMax,
+    /// The given low and high percentiles (0 to 100), for robustness to outliers.
+    Percentile(f64, f64),
+}
+
+/// Percentile `q` (0 to 100) of `values` with linear interpolation between the closest
+/// ranks, like NumPy's default. Reorders `values`, in linear time.
+fn percentile(values: &mut [f64], q: f64) -> f64 {
+    let pos = q / 100.0 * (values.len() - 1) as f64;
+    let k = pos.floor() as usize;
+    let (_, &mut lo, upper) = values.select_nth_unstable_by(k, f64::total_cmp);
+    let frac = pos - k as f64;
+    if frac == 0.0 || upper.is_empty() {
+        return lo;
+    }
+    let hi = upper.iter().copied().fold(f64::INFINITY, f64::min);
+    lo + (hi - lo) * frac
+}
+
+impl PointCloud {
+    /// Apply `op` to every value of field `name` in place, in parallel.
+    ///
+    /// Float fields that keep their dtype are updated without a copy (unless the data is
+    /// shared with another PointCloud). Otherwise values are computed in f64 and converted
+    /// to `dtype` (the field's own dtype if None), rounding to the nearest integer for
+    /// integer dtypes and handling unrepresentable values according to `policy`. On error
+    /// the field is left unchanged.
+    pub fn apply(&mut self, name: &str, op: FieldOp, dtype: Option<Dtype>, policy: CastPolicy) -> Result<()> {
+        let field = self.fields.get_mut(name)
+            .ok_or_else(|| anyhow::anyhow!("Field '{}' not found", name))?;
+        let dtype = dtype.unwrap_or(field.dtype());
+        match (field, dtype) {
+            (FieldData::F32(arr), Dtype::F32) => arr.par_mapv_inplace(|v| op.eval(v as f64) as f32),
+            (FieldData::F64(arr), Dtype::F64) => arr.par_mapv_inplace(|v| op.eval(v)),
+            (field, dtype) => {
+                let round = !matches!(dtype, Dtype::F32 | Dtype::F64);
+                let mut values = field.get_data::<f64>().into_shared();
+                values.par_mapv_inplace(|v| if round { op.eval(v).round() } else { op.eval(v) });
+                let data = FieldData::F64(values).cast(dtype, policy)
+                    .map_err(|e| anyhow::anyhow!("Field '{}': {}", name, e))?;
+                self.insert_field(name, data)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Normalize the values of field `name` in place: the range chosen by `method` is mapped
+    /// to [0, 1] and values outside it are clipped. The field is stored as `dtype`; integer
+    /// dtypes are scaled to [0, their maximum value], e.g. [0, 255] for U8. Non-finite values
+    /// are left out of the range; NaN stays NaN (or becomes 0 for integer dtypes). Returns
+    /// the range, which is NaN if the field has no finite values.
+    pub fn normalize_field(&mut self, name: &str, method: Normalization, dtype: Dtype) -> Result<(f64, f64)> {
+        let field = self.fields.get(name)
+            .ok_or_else(|| anyhow::anyhow!("Field '{}' not found", name))?;
+        let mut values = field.get_data::<f64>().into_shared();
+        let mut finite: Vec<f64> = values.iter().copied().filter(|v| v.is_finite()).collect();
+        let (lo, hi) = match method {
+            _ if finite.is_empty() => (f64::NAN, f64::NAN),
+            Normalization::MinMax => finite.iter()
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| (lo.min(v), hi.max(v))),
+            Normalization::Percentile(q_lo, q_hi) => {
+                anyhow::ensure!((0.0..=100.0).contains(&q_lo) && (q_lo..=100.0).contains(&q_hi),
+                    "Percentiles must satisfy 0 <= low <= high <= 100, got {} and {}", q_lo, q_hi);
+                (percentile(&mut finite, q_lo), percentile(&mut finite, q_hi))
+            }
+        };
+        let scale = match dtype.get_type() {
+            "U" => 2f64.powi(8 * dtype.get_size() as i32) - 1.0,
+            "I" => 2f64.powi(8 * dtype.get_size() as i32 - 1) - 1.0,
+            _ => 1.0,
+        };
+        let round = scale != 1.0;
+        values.par_mapv_inplace(|v| {
+            let t = if hi > lo { ((v - lo) / (hi - lo)).clamp(0.0, 1.0) } else if v.is_nan() { v } else { 0.0 };
+            if round { (t * scale).round() } else { t }
+        });
+        let data = FieldData::F64(values).cast(dtype, CastPolicy::Saturate)?;
+        self.insert_field(name, data)?;
+        Ok((lo, hi))
+    }
+
+    /// Multiply the values of field `name` by `factor` in place. See `apply`.
+    pub fn scale_field(&mut self, name: &str, factor: f64, dtype: Option<Dtype>, policy: CastPolicy) -> Result<()> {
+        self.apply(name, FieldOp::Multiply(factor), dtype, policy)
+    }
+
+    /// Add `offset` to the values of field `name` in place. See `apply`.
+    pub fn offset_field(&mut self, name: &str, offset: f64, dtype: Option<Dtype>, policy: CastPolicy) -> Result<()> {
+        self.apply(name, FieldOp::Add(offset), dtype, policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array1;
+    use crate::metadata::{FieldSchema, Metadata};
+
+    #[test]
+    fn test_field_ops() {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("z", Dtype::F32, 1), ("intensity", Dtype::U8, 1)]),
+            width: 3,
+            height: 1,
+            npoints: 3,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        for (i, (z, intensity)) in [(1.5f32, 10u8), (f32::NAN, 200), (-4.0, 255)].into_iter().enumerate() {
+            pc.fields.get_mut("z").unwrap().assign_row(i, &Array1::from(vec![z]));
+            pc.fields.get_mut("intensity").unwrap().assign_row(i, &Array1::from(vec![intensity]));
+        }
+        let values = |pc: &PointCloud, name: &str| (0..3).map(|i| pc.fields[name].get_row::<f64>(i)[0]).collect::<Vec<_>>();
+
+        // In-place updates do not affect copies sharing the data
+        let original = pc.clone();
+        pc.offset_field("z", -0.5, None, CastPolicy::Error).unwrap();
+        pc.apply("z", FieldOp::from_spec("clip", &[0.0, 0.75]).unwrap(), None, CastPolicy::Error).unwrap();
+        let z = values(&pc, "z");
+        assert_eq!((z[0], z[2]), (0.75, 0.0));
+        assert!(z[1].is_nan());
+        assert_eq!(values(&original, "z")[0], 1.5);
+
+        // Integer results are rounded, and out-of-range values follow the policy
+        pc.scale_field("intensity", 0.25, None, CastPolicy::Error).unwrap();
+        assert_eq!(values(&pc, "intensity"), [3.0, 50.0, 64.0]);
+        assert!(pc.scale_field("intensity", 5.0, None, CastPolicy::Error).is_err());
+        assert_eq!(values(&pc, "intensity"), [3.0, 50.0, 64.0]);
+        pc.scale_field("intensity", 5.0, None, CastPolicy::Saturate).unwrap();
+        assert_eq!(values(&pc, "intensity"), [15.0, 250.0, 255.0]);
+
+        pc.scale_field("intensity", 1.0 / 255.0, Some(Dtype::F32), CastPolicy::Error).unwrap();
+        assert_eq!(pc.fields["intensity"].dtype(), Dtype::F32);
+        assert_eq!(pc.metadata.read().unwrap().fields[1].dtype, Dtype::F32);
+        assert!((values(&pc, "intensity")[2] - 1.0).abs() < 1e-6);
+
+        assert!(FieldOp::from_spec("sqrt", &[1.0]).is_err());
+        assert!(FieldOp::from_spec("clip", &[1.0, 0.0]).is_err());
+        assert!(FieldOp::from_spec("tan", &[]).is_err());
+        assert!(pc.apply("w", FieldOp::Abs, None, CastPolicy::Error).is_err());
+    }
+
+    #[test]
+    fn test_normalize_field() {
+        let n = 101;
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("intensity", Dtype::U16, 1)]),
+            width: n + 1,
+            height: 1,
+            npoints: n + 1,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        // 0, 10, ..., 990, plus an outlier
+        for i in 0..n {
+            let v = if i == n - 1 { 60000 } else { 10 * i as u16 };
+            pc.fields.get_mut("intensity").unwrap().assign_row(i, &Array1::from(vec![v]));
+        }
+        pc.fields.get_mut("intensity").unwrap().assign_row(n, &Array1::from(vec![500u16]));
+        let value = |pc: &PointCloud, i: usize| pc.fields["intensity"].get_row::<f64>(i)[0];
+
+        let mut minmax = pc.clone();
+        assert_eq!(minmax.normalize_field("intensity", Normalization::MinMax, Dtype::F32).unwrap(), (0.0, 60000.0));
+        assert_eq!(minmax.fields["intensity"].dtype(), Dtype::F32);
+        assert_eq!((value(&minmax, 0), value(&minmax, n - 1)), (0.0, 1.0));
+
+        // The 50th percentile of the 102 values falls between the two 500s
+        let mut robust = pc.clone();
+        let (lo, hi) = robust.normalize_field("intensity", Normalization::Percentile(0.0, 50.0), Dtype::U8).unwrap();
+        assert_eq!((lo, hi), (0.0, 500.0));
+        assert_eq!(robust.fields["intensity"].dtype(), Dtype::U8);
+        assert_eq!((value(&robust, 0), value(&robust, 25), value(&robust, 60)), (0.0, 128.0, 255.0));
+        let (_, hi) = pc.clone().normalize_field("intensity", Normalization::Percentile(0.0, 99.5), Dtype::F64).unwrap();
+        assert!((hi - (990.0 + 0.495 * (60000.0 - 990.0))).abs() < 1e-6);
+
+        assert!(pc.normalize_field("intensity", Normalization::Percentile(60.0, 50.0), Dtype::F32).is_err());
+        assert!(pc.normalize_field("w", Normalization::MinMax, Dtype::F32).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use crate::pointcloud::PointCloud;
+
+/// Return true if `name` matches the wildcard `pattern`, where `*` matches any sequence of
+/// characters and `?` matches a single character.
+pub fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` in the pattern, and the name position it was matched against
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// List the files directly inside `dir` whose names match the wildcard `pattern`
+/// (see `matches_pattern`), sorted by path.
+pub fn list_dir(dir: &Path, pattern: &str) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Cannot read directory '{}'", dir.display()))? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() && matches_pattern(pattern, &entry.file_name().to_string_lossy()) {
+            paths.push(entry.path());
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Read the PCD files at `paths` in parallel, returning the clouds in the same order and
+/// the warnings recovered while reading them, each prefixed with its file path.
+/// Uses a pool of `num_threads` threads, or the global rayon pool if None.
+pub fn read_pcd_files(paths: &[PathBuf], num_threads: Option<usize>) -> Result<(Vec<PointCloud>, Vec<String>)> {
+    let read_all = || paths.par_iter()
+        .map(|path| -> Result<(PointCloud, Vec<String>)> {
+            let file = File::open(path).with_context(|| format!("Cannot read '{}'", path.display()))?;
+            let (pc, warnings) = PointCloud::from_pcd_reader(&mut BufReader::new(file), true)
+                .with_context(|| format!("Cannot read '{}'", path.display()))?;
+            Ok((pc, warnings.into_iter().map(|w| format!("{}: {}", path.display(), w)).collect()))
+        })
+        .collect::<Result<Vec<_>>>();
+    let results = match num_threads {
+        Some(n) => {
+            anyhow::ensure!(n > 0, "num_threads must be positive");
+            rayon::ThreadPoolBuilder::new().num_threads(n).build()?.install(read_all)?
+        }
+        None => read_all()?,
+    };
+    let (clouds, warnings): (Vec<_>, Vec<_>) = results.into_iter().unzip();
+    Ok((clouds, warnings.into_iter().flatten().collect()))
+}
+
+/// Read every PCD file in `dir` whose name matches `pattern`, in parallel and in path order,
+/// as `read_pcd_files`.
+pub fn load_dir(dir: &Path, pattern: &str, num_threads: Option<usize>) -> Result<(Vec<PointCloud>, Vec<String>)> {
+    read_pcd_files(&list_dir(dir, pattern)?, num_threads)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{Dtype, FieldSchema, Metadata};
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("*.pcd", "frame_001.pcd"));
+        assert!(matches_pattern("frame_??.pcd", "frame_01.pcd"));
+        assert!(matches_pattern("*_*.pcd", "a_b_c.pcd"));
+        assert!(matches_pattern("*", ""));
+        assert!(!matches_pattern("*.pcd", "frame.ply"));
+        assert!(!matches_pattern("frame_??.pcd", "frame_001.pcd"));
+    }
+
+    #[test]
+    fn test_load_dir() {
+        let dir = std::env::temp_dir().join("pcdpy_test_load_dir");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for n in 1..=3 {
+            let md = Metadata {
+                fields: FieldSchema::from_iter([("x", Dtype::F32, 1)]),
+                width: n,
+                height: 1,
+                npoints: n,
+                ..Metadata::default()
+            };
+            PointCloud::new(&md).to_pcd_file(&dir.join(format!("frame_{}.pcd", n)).to_string_lossy()).unwrap();
+        }
+        std::fs::write(dir.join("notes.txt"), "not a cloud").unwrap();
+
+        let (clouds, warnings) = load_dir(&dir, "*.pcd", Some(2)).unwrap();
+        assert_eq!(clouds.iter().map(|pc| pc.len()).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert!(warnings.is_empty());
+        assert_eq!(load_dir(&dir, "frame_2*", None).unwrap().0.len(), 1);
+
+        std::fs::write(dir.join("frame_4.pcd"), "VERSION 0.7\nFIELDS x\nSIZE 4\nTYPE F\nCOUNT 1\nWIDTH 1\nHEIGHT 1\nPOINTS 1\nCOLOR red\nDATA ascii\n1\n").unwrap();
+        let (_, warnings) = load_dir(&dir, "frame_4*", None).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with(&dir.join("frame_4.pcd").display().to_string()), "{}", warnings[0]);
+        assert!(load_dir(&dir, "*", None).is_err());
+        assert!(load_dir(&dir, "*.pcd", Some(0)).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,197 @@
+use anyhow::Result;
+use nalgebra::{Matrix3, Vector3};
+use crate::pointcloud::PointCloud;
+
+/// A box aligned with the coordinate axes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AxisAlignedBox {
+    pub min_bound: [f64; 3],
+    pub max_bound: [f64; 3],
+}
+
+impl AxisAlignedBox {
+    /// Center of the box.
+    pub fn center(&self) -> [f64; 3] {
+        std::array::from_fn(|i| (self.min_bound[i] + self.max_bound[i]) / 2.0)
+    }
+
+    /// Side lengths of the box.
+    pub fn extent(&self) -> [f64; 3] {
+        std::array::from_fn(|i| self.max_bound[i] - self.min_bound[i])
+    }
+}
+
+/// A box with an arbitrary orientation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrientedBox {
+    pub center: [f64; 3],
+    /// Rotation whose columns are the box axes, ordered from largest to smallest variance.
+    pub rotation: [[f64; 3]; 3],
+    /// Side lengths of the box along each of its axes.
+    pub extent: [f64; 3],
+}
+
+/// Principal components of a set of points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pca {
+    pub mean: [f64; 3],
+    /// Eigenvalues of the (population) covariance matrix, in descending order.
+    pub eigenvalues: [f64; 3],
+    /// Right-handed rotation whose columns are the eigenvectors matching `eigenvalues`.
+    pub eigenvectors: [[f64; 3]; 3],
+}
+
+impl PointCloud {
+    /// Return the points of the `names` coordinate fields, skipping points with a NaN coordinate.
+    /// Returns an error if no points remain.
+    fn finite_coordinates(&self, names: [&str; 3]) -> Result<Vec<[f64; 3]>> {
+        let points: Vec<[f64; 3]> = self.coordinates(names)?.into_iter()
+            .filter(|p| p.iter().all(|v| !v.is_nan()))
+            .collect();
+        anyhow::ensure!(!points.is_empty(), "PointCloud has no points with valid coordinates");
+        Ok(points)
+    }
+
+    /// Return the axis-aligned bounding box of the `names` coordinate fields, ignoring NaN points.
+    pub fn aabb(&self, names: [&str; 3]) -> Result<AxisAlignedBox> {
+        let points = self.finite_coordinates(names)?;
+        let mut min_bound = [f64::INFINITY; 3];
+        let mut max_bound = [f64::NEG_INFINITY; 3];
+        for p in &points {
+            for i in 0..3 {
+                min_bound[i] = min_bound[i].min(p[i]);
+                max_bound[i] = max_bound[i].max(p[i]);
+            }
+        }
+        Ok(AxisAlignedBox { min_bound, max_bound })
+    }
+
+    /// Return the mean of the `names` coordinate fields, ignoring NaN points.
+    pub fn centroid(&self, names: [&str; 3]) -> Result<[f64; 3]> {
+        let points = self.finite_coordinates(names)?;
+        let sum = points.iter().fold([0.0; 3], |acc, p| std::array::from_fn(|i| acc[i] + p[i]));
+        Ok(sum.map(|v| v / points.len() as f64))
+    }
+
+    /// Return the principal components of the `names` coordinate fields, ignoring NaN points.
+    pub fn pca(&self, names: [&str; 3]) -> Result<Pca> {
+        let points: Vec<Vector3<f64>> = self.finite_coordinates(names)?.iter().map(|p| Vector3::from(*p)).collect();
+        let (mean, axes, eigenvalues) = principal_axes(&points);
+        Ok(Pca {
+            mean: mean.into(),
+            eigenvalues: eigenvalues.into(),
+            eigenvectors: std::array::from_fn(|i| std::array::from_fn(|j| axes[(i, j)])),
+        })
+    }
+
+    /// Return an oriented bounding box of the `names` coordinate fields, ignoring NaN points.
+    /// The box axes are the principal components of the points.
+    pub fn obb(&self, names: [&str; 3]) -> Result<OrientedBox> {
+        let points: Vec<Vector3<f64>> = self.finite_coordinates(names)?.iter().map(|p| Vector3::from(*p)).collect();
+        let (mean, axes, _) = principal_axes(&points);
+
+        let mut min_local = Vector3::repeat(f64::INFINITY);
+        let mut max_local = Vector3::repeat(f64::NEG_INFINITY);
+        for p in &points {
+            let local = axes.transpose() * (p - mean);
+            min_local = min_local.inf(&local);
+            max_local = max_local.sup(&local);
+        }
+        let center = mean + axes * (min_local + max_local) / 2.0;
+        let extent = max_local - min_local;
+
+        Ok(OrientedBox {
+            center: center.into(),
+            rotation: std::array::from_fn(|i| std::array::from_fn(|j| axes[(i, j)])),
+            extent: extent.into(),
+        })
+    }
+}
+
+/// Return the mean, the principal axes (as the columns of a right-handed rotation, by
+/// descending variance) and the matching covariance eigenvalues of a non-empty set of points.
+fn principal_axes(points: &[Vector3<f64>]) -> (Vector3<f64>, Matrix3<f64>, Vector3<f64>) {
+    let n = points.len() as f64;
+    let mean = points.iter().sum::<Vector3<f64>>() / n;
+    let covariance = points.iter()
+        .map(|p| (p - mean) * (p - mean).transpose())
+        .sum::<Matrix3<f64>>() / n;
+
+    let eigen = covariance.symmetric_eigen();
+    let mut order = [0, 1, 2];
+    order.sort_by(|&a, &b| eigen.eigenvalues[b].total_cmp(&eigen.eigenvalues[a]));
+    let mut axes = Matrix3::from_columns(&order.map(|i| eigen.eigenvectors.column(i).into_owned()));
+    if axes.determinant() < 0.0 {
+        axes.set_column(2, &-axes.column(2));
+    }
+    (mean, axes, Vector3::from(order.map(|i| eigen.eigenvalues[i])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{Dtype, FieldSchema, Metadata};
+    use ndarray::Array1;
+
+    fn cloud(points: &[[f32; 3]]) -> PointCloud {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1), ("y", Dtype::F32, 1), ("z", Dtype::F32, 1)]),
+            width: points.len(),
+            height: 1,
+            npoints: points.len(),
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        for (i, p) in points.iter().enumerate() {
+            for (name, v) in ["x", "y", "z"].into_iter().zip(p) {
+                pc.fields.get_mut(name).unwrap().assign_row(i, &Array1::from(vec![*v]));
+            }
+        }
+        pc
+    }
+
+    #[test]
+    fn test_aabb() {
+        let pc = cloud(&[[0.0, -1.0, 2.0], [3.0, 1.0, f32::NAN], [1.0, 4.0, -2.0]]);
+        let aabb = pc.aabb(["x", "y", "z"]).unwrap();
+        assert_eq!(aabb.min_bound, [0.0, -1.0, -2.0]);
+        assert_eq!(aabb.max_bound, [1.0, 4.0, 2.0]);
+        assert_eq!(aabb.extent(), [1.0, 5.0, 4.0]);
+        assert!(cloud(&[]).aabb(["x", "y", "z"]).is_err());
+    }
+
+    #[test]
+    fn test_obb() {
+        // A 4 x 2 x 1 box rotated 90 degrees about z and centered at (1, 2, 3)
+        let mut points = Vec::new();
+        for x in [-2.0, 2.0] {
+            for y in [-1.0, 1.0] {
+                for z in [-0.5, 0.5] {
+                    points.push([1.0 - y, 2.0 + x, 3.0 + z]);
+                }
+            }
+        }
+        let obb = cloud(&points).obb(["x", "y", "z"]).unwrap();
+        for i in 0..3 {
+            assert!((obb.center[i] - [1.0, 2.0, 3.0][i]).abs() < 1e-9);
+            assert!((obb.extent[i] - [4.0, 2.0, 1.0][i]).abs() < 1e-9);
+        }
+        // The longest axis is the world y axis
+        assert!((obb.rotation[1][0].abs() - 1.0).abs() < 1e-9);
+        let r = Matrix3::from_fn(|i, j| obb.rotation[i][j]);
+        assert!((r.determinant() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_centroid_pca() {
+        let pc = cloud(&[[0.0, 0.0, 1.0], [2.0, 0.0, 1.0], [f32::NAN, 5.0, 5.0], [4.0, 0.0, 1.0]]);
+        assert_eq!(pc.centroid(["x", "y", "z"]).unwrap(), [2.0, 0.0, 1.0]);
+
+        let pca = pc.pca(["x", "y", "z"]).unwrap();
+        assert_eq!(pca.mean, [2.0, 0.0, 1.0]);
+        assert!((pca.eigenvalues[0] - 8.0 / 3.0).abs() < 1e-9);
+        assert!(pca.eigenvalues[1].abs() < 1e-9 && pca.eigenvalues[2].abs() < 1e-9);
+        assert!((pca.eigenvectors[0][0].abs() - 1.0).abs() < 1e-9);
+        assert!(pc.centroid(["x", "y", "w"]).is_err());
+    }
+}
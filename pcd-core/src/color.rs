@@ -0,0 +1,79 @@
+use anyhow::Result;
+use ndarray::{ArcArray2, Array2, ArrayView1, Axis, Zip};
+use crate::fielddata::FieldData;
+use crate::pointcloud::PointCloud;
+
+/// Pack 8-bit r, g, b values into a float using the PCL convention, where the bits of the
+/// float are `0x00RRGGBB`.
+pub fn pack_rgb(r: u8, g: u8, b: u8) -> f32 {
+    f32::from_bits(((r as u32) << 16) | ((g as u32) << 8) | b as u32)
+}
+
+/// Unpack a PCL packed rgb float (or rgba integer) into its r, g, b values.
+pub fn unpack_rgb(bits: u32) -> [u8; 3] {
+    [(bits >> 16) as u8, (bits >> 8) as u8, bits as u8]
+}
+
+impl PointCloud {
+    /// Unpack the packed color field `name` (F32 as written by PCL, or U32 rgba) into an
+    /// (npoints, 3) array of r, g, b values.
+    pub fn unpack_rgb(&self, name: &str) -> Result<Array2<u8>> {
+        let field = self.fields.get(name)
+            .ok_or_else(|| anyhow::anyhow!("Field '{}' not found", name))?;
+        anyhow::ensure!(field.count() == 1, "Field '{}' must have a count of 1", name);
+        let bits: Vec<u32> = match field {
+            FieldData::F32(arr) => arr.iter().map(|v| v.to_bits()).collect(),
+            FieldData::U32(arr) => arr.iter().copied().collect(),
+            _ => anyhow::bail!("Field '{}' must be a packed F32 or U32 color, got {}", name, field.dtype()),
+        };
+        let mut colors = Array2::zeros((bits.len(), 3));
+        Zip::from(colors.axis_iter_mut(Axis(0)))
+            .and(&bits)
+            .par_for_each(|mut row, &v| row.assign(&ArrayView1::from(&unpack_rgb(v))));
+        Ok(colors)
+    }
+
+    /// Pack per-point r, g, b values into the F32 field `name`, adding it or replacing an
+    /// existing field of that name.
+    pub fn pack_rgb(&mut self, r: ArrayView1<u8>, g: ArrayView1<u8>, b: ArrayView1<u8>, name: &str) -> Result<()> {
+        let npoints = self.len();
+        for (channel, len) in [("r", r.len()), ("g", g.len()), ("b", b.len())] {
+            anyhow::ensure!(len == npoints,
+                "Channel '{}' length mismatch: expected {}, got {}", channel, npoints, len);
+        }
+        let packed = Zip::from(&r).and(&g).and(&b)
+            .par_map_collect(|&r, &g, &b| pack_rgb(r, g, b));
+        let packed: ArcArray2<f32> = packed.insert_axis(Axis(1)).into_shared();
+        self.insert_field(name, FieldData::F32(packed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{Dtype, FieldSchema, Metadata};
+    use ndarray::Array1;
+
+    #[test]
+    fn test_pack_unpack_rgb() {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1)]),
+            width: 2,
+            height: 1,
+            npoints: 2,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        let (r, g, b) = (Array1::from(vec![255u8, 1]), Array1::from(vec![0u8, 2]), Array1::from(vec![128u8, 3]));
+        pc.pack_rgb(r.view(), g.view(), b.view(), "rgb").unwrap();
+        assert_eq!(pc.metadata.read().unwrap().fields[1].dtype, Dtype::F32);
+        assert_eq!(pc.fields["rgb"].get_row::<f32>(1)[0].to_bits(), 0x010203);
+
+        let colors = pc.unpack_rgb("rgb").unwrap();
+        assert_eq!(colors, ndarray::array![[255, 0, 128], [1, 2, 3]]);
+
+        assert!(pc.pack_rgb(r.view(), g.view(), Array1::from(vec![0u8]).view(), "rgb").is_err());
+        assert!(pc.unpack_rgb("missing").is_err());
+        assert_eq!(unpack_rgb(pack_rgb(9, 8, 7).to_bits()), [9, 8, 7]);
+    }
+}
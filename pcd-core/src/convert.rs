@@ -0,0 +1,215 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use byteorder::{LittleEndian, WriteBytesExt};
+use crate::io::{self, WriteOptions};
+use crate::metadata::Encoding;
+use crate::pointcloud::PcdReader;
+
+/// Temporary file holding the uncompressed point data of a compressed PCD file, laid out
+/// field by field, while it is assembled from chunks. Removed when dropped.
+pub(crate) struct SpillFile {
+    path: PathBuf,
+    pub(crate) file: File,
+}
+
+impl SpillFile {
+    /// Creates the file next to `dst`, named after it with `suffix` appended.
+    pub(crate) fn create(dst: &Path, suffix: &str) -> Result<Self> {
+        let mut name = dst.file_name().unwrap_or_default().to_os_string();
+        name.push(suffix);
+        let path = dst.with_file_name(name);
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&path)?;
+        Ok(Self { path, file })
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Rewrites the PCD file `src` to `dst` with the header overridden by `options` (usually a
+/// new encoding), reading and writing `chunk_size` points at a time so that the whole cloud
+/// is never held in memory.
+///
+/// ASCII and binary output is written chunk by chunk. Compressed encodings lay the data out
+/// field by field, so the data is first assembled in a temporary file next to `dst`, then
+/// compressed from there: binary_compressed_large in 1 GiB blocks and binary_zstd as a
+/// stream, while binary_compressed and binary_lz4, which are a single compressed block,
+/// need the uncompressed data in memory (at most 4 GiB for binary_compressed). Compressed
+/// sources are decompressed in memory by `PcdReader`.
+///
+/// Returns the warnings for header defects of `src` that were recovered.
+pub fn convert(src: &str, dst: &str, options: &WriteOptions, chunk_size: usize) -> Result<Vec<String>> {
+    if Path::new(dst).exists() {
+        anyhow::ensure!(std::fs::canonicalize(src)? != std::fs::canonicalize(dst)?,
+            "Source and destination are the same file");
+    }
+    let reader = PcdReader::open(src, chunk_size)?;
+    let mut md = reader.metadata().clone();
+    let warnings = reader.warnings().to_vec();
+    if options.skip_padding {
+        md.fields.0.retain(|f| !f.is_padding());
+    }
+    let names: Vec<String> = md.fields.iter().map(|f| f.name.clone()).collect();
+    let md = options.apply(&md);
+    anyhow::ensure!(!options.pcl_compatible || md.encoding.is_pcl_compatible(),
+        "Encoding {} is a pcdpy extension that PCL cannot read", md.encoding.as_str());
+    anyhow::ensure!(md.encoding != Encoding::BinaryCompressed || md.data_size() <= u32::MAX as usize,
+        "Point data of {} bytes is too large for binary_compressed encoding (at most {} bytes); \
+        enable large_compressed to write the binary_compressed_large extension", md.data_size(), u32::MAX);
+
+    let mut writer = BufWriter::new(File::create(dst)?);
+    io::write_header(&mut writer, &md)?;
+    let mut chunks = reader
+        .map(|chunk| if options.skip_padding { chunk?.select(&names) } else { chunk })
+        .map(|chunk| chunk?.to_storage());
+    match md.encoding {
+        Encoding::Ascii => for chunk in chunks {
+            io::write_ascii_data(&mut writer, &chunk?, options)?;
+        },
+        Encoding::Binary => for chunk in chunks {
+            io::write_binary_data(&mut writer, &chunk?)?;
+        },
+        encoding => {
+            let mut spill = SpillFile::create(Path::new(dst), ".columns.tmp")?;
+            // Offset of each field's values in the spill file, and the size of one point's values
+            let mut offsets = Vec::with_capacity(md.fields.len());
+            let mut offset = 0;
+            for f in md.fields.iter() {
+                offsets.push((offset, f.get_size() * f.count));
+                offset += f.get_size() * f.count * md.npoints;
+            }
+            let mut position = 0;
+            let mut buf = Vec::new();
+            chunks.try_for_each(|chunk| -> Result<()> {
+                let chunk = chunk?;
+                for (name, &(offset, point_size)) in names.iter().zip(offsets.iter()) {
+                    buf.clear();
+                    chunk.fields[name].extend_le_bytes(&mut buf);
+                    spill.file.seek(SeekFrom::Start((offset + position * point_size) as u64))?;
+                    spill.file.write_all(&buf)?;
+                }
+                position += chunk.len();
+                Ok(())
+            })?;
+            spill.file.seek(SeekFrom::Start(0))?;
+            write_spilled_data(&mut writer, BufReader::new(&spill.file), md.data_size(), encoding, options.compression_level)?;
+        }
+    }
+    writer.flush()?;
+    Ok(warnings)
+}
+
+/// Compresses `size` bytes of point data laid out field by field from `data` and writes
+/// them in `encoding`, streaming where the encoding allows it.
+#[cfg_attr(not(feature = "zstd"), allow(unused_variables))]
+pub(crate) fn write_spilled_data<W: Write + Seek, R: Read>(writer: &mut W, mut data: R, size: usize, encoding: Encoding, level: Option<i32>) -> Result<()> {
+    match encoding {
+        Encoding::BinaryCompressedLarge => write_streamed(writer, size, |writer| {
+            let mut block = Vec::new();
+            let mut remaining = size;
+            while remaining > 0 {
+                let block_size = remaining.min(io::LZF_BLOCK_SIZE);
+                block.resize(block_size, 0);
+                data.read_exact(&mut block)?;
+                let compressed = lzf::compress(&block)
+                    .map_err(|_| anyhow::anyhow!("Compression failed"))?;
+                writer.write_u32::<LittleEndian>(compressed.len() as u32)?;
+                writer.write_u32::<LittleEndian>(block_size as u32)?;
+                writer.write_all(&compressed)?;
+                remaining -= block_size;
+            }
+            Ok(())
+        }),
+        #[cfg(feature = "zstd")]
+        Encoding::BinaryZstd => write_streamed(writer, size, |writer| {
+            let mut encoder = zstd::stream::Encoder::new(writer, level.unwrap_or(zstd::DEFAULT_COMPRESSION_LEVEL))?;
+            encoder.set_pledged_src_size(Some(size as u64))?;
+            let copied = std::io::copy(&mut data, &mut encoder)?;
+            anyhow::ensure!(copied == size as u64, "Spilled point data has {} bytes, expected {}", copied, size);
+            encoder.finish()?;
+            Ok(())
+        }),
+        _ => {
+            let mut buf = Vec::with_capacity(size);
+            data.read_to_end(&mut buf)?;
+            io::write_compressed_block(writer, buf, encoding, level)
+        }
+    }
+}
+
+/// Writes a compressed block with u64 sizes whose data is produced by `write_data`, filling
+/// in the compressed size once the data is written.
+fn write_streamed<W: Write + Seek>(writer: &mut W, size: usize, write_data: impl FnOnce(&mut W) -> Result<()>) -> Result<()> {
+    let start = writer.stream_position()?;
+    writer.write_u64::<LittleEndian>(0)?;
+    writer.write_u64::<LittleEndian>(size as u64)?;
+    write_data(writer)?;
+    let end = writer.stream_position()?;
+    writer.seek(SeekFrom::Start(start))?;
+    writer.write_u64::<LittleEndian>(end - start - 16)?;
+    writer.seek(SeekFrom::Start(end))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array1;
+    use crate::pointcloud::PointCloud;
+    use crate::metadata::{Dtype, FieldSchema, Metadata};
+
+    #[test]
+    fn test_convert_encodings() {
+        let npoints = 7;
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1), ("_", Dtype::U8, 1), ("label", Dtype::U16, 2)]),
+            width: npoints,
+            height: 1,
+            npoints,
+            encoding: Encoding::Ascii,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        for i in 0..npoints {
+            pc.fields.get_mut("x").unwrap().assign_row(i, &Array1::from(vec![i as f32 * 0.5]));
+            pc.fields.get_mut("label").unwrap().assign_row(i, &Array1::from(vec![i as u16, 3 * i as u16]));
+        }
+        let dir = std::env::temp_dir();
+        let src = dir.join("pcdpy_test_convert_src.pcd");
+        let src = src.to_str().unwrap();
+        pc.to_pcd_file(src).unwrap();
+
+        #[cfg_attr(not(any(feature = "zstd", feature = "lz4")), allow(unused_mut))]
+        let mut encodings = vec![Encoding::Binary, Encoding::BinaryCompressed, Encoding::BinaryCompressedLarge, Encoding::Ascii];
+        #[cfg(feature = "zstd")]
+        encodings.push(Encoding::BinaryZstd);
+        #[cfg(feature = "lz4")]
+        encodings.push(Encoding::BinaryLz4);
+        for encoding in encodings {
+            let dst = dir.join(format!("pcdpy_test_convert_{}.pcd", encoding.as_str()));
+            let dst = dst.to_str().unwrap();
+            let options = WriteOptions { encoding: Some(encoding), ..WriteOptions::default() };
+            convert(src, dst, &options, 3).unwrap();
+            let converted = PointCloud::from_pcd_file(dst).unwrap();
+            assert_eq!(converted.metadata.read().unwrap().encoding, encoding);
+            converted.metadata.write().unwrap().encoding = Encoding::Ascii;
+            assert_eq!(converted, pc);
+
+            let options = WriteOptions { skip_padding: true, ..options };
+            convert(src, dst, &options, 3).unwrap();
+            let converted = PointCloud::from_pcd_file(dst).unwrap();
+            std::fs::remove_file(dst).unwrap();
+            assert_eq!(converted.field_names(), ["x", "label"]);
+            assert!(converted.fields["label"].equal_nan(&pc.fields["label"]));
+            assert!(!Path::new(&format!("{}.columns.tmp", dst)).exists());
+        }
+
+        assert!(convert(src, src, &WriteOptions::default(), 3).is_err());
+        std::fs::remove_file(src).unwrap();
+    }
+}
@@ -0,0 +1,118 @@
+use anyhow::Result;
+use rayon::prelude::*;
+use crate::kdtree::KdTree;
+use crate::pointcloud::PointCloud;
+
+impl PointCloud {
+    /// Return the distance from each point to its nearest neighbor in `other`, using the
+    /// `names` coordinate fields of both clouds. If `normals` names normal fields of `other`,
+    /// distances are signed: negative for points behind the normal of their nearest neighbor.
+    /// Points with a NaN coordinate get a NaN distance. Queries run in parallel over a k-d
+    /// tree of `other`.
+    pub fn distance_to(&self, other: &PointCloud, names: [&str; 3], normals: Option<[&str; 3]>) -> Result<Vec<f64>> {
+        // Points of `other` with a NaN coordinate are left out of the tree
+        let (kept, targets): (Vec<usize>, Vec<[f64; 3]>) = other.coordinates(names)?
+            .into_iter()
+            .enumerate()
+            .filter(|(_, q)| !q.iter().any(|v| v.is_nan()))
+            .unzip();
+        let tree = KdTree::new(targets);
+        anyhow::ensure!(!tree.is_empty(), "Target PointCloud has no points without NaN coordinates");
+        let normals = normals.map(|n| other.coordinates(n)).transpose()?;
+        Ok(self.coordinates(names)?
+            .par_iter()
+            .map(|p| {
+                if p.iter().any(|v| v.is_nan()) {
+                    return f64::NAN;
+                }
+                let (index, dist) = tree.query(p, 1)[0];
+                match &normals {
+                    Some(normals) => {
+                        let q = tree.point(index);
+                        let side: f64 = (0..3).map(|i| (p[i] - q[i]) * normals[kept[index]][i]).sum();
+                        if side < 0.0 { -dist } else { dist }
+                    }
+                    None => dist,
+                }
+            })
+            .collect())
+    }
+}
+
+/// Nearest neighbor distances from `a` to `b` and from `b` to `a`, without NaN values.
+fn distances_both_ways(a: &PointCloud, b: &PointCloud, names: [&str; 3]) -> Result<(Vec<f64>, Vec<f64>)> {
+    let without_nan = |d: Vec<f64>| -> Result<Vec<f64>> {
+        let d: Vec<f64> = d.into_iter().filter(|v| !v.is_nan()).collect();
+        anyhow::ensure!(!d.is_empty(), "PointCloud has no points without NaN coordinates");
+        Ok(d)
+    };
+    Ok((without_nan(a.distance_to(b, names, None)?)?, without_nan(b.distance_to(a, names, None)?)?))
+}
+
+/// Chamfer distance between the `names` coordinates of `a` and `b`: the mean distance from
+/// each point of `a` to its nearest neighbor in `b`, plus the same from `b` to `a`. With
+/// `squared`, squared distances are averaged instead. Points with a NaN coordinate are
+/// ignored.
+pub fn chamfer_distance(a: &PointCloud, b: &PointCloud, names: [&str; 3], squared: bool) -> Result<f64> {
+    let (ab, ba) = distances_both_ways(a, b, names)?;
+    let mean = |d: &[f64]| d.iter().map(|v| if squared { v * v } else { *v }).sum::<f64>() / d.len() as f64;
+    Ok(mean(&ab) + mean(&ba))
+}
+
+/// Hausdorff distance between the `names` coordinates of `a` and `b`: the largest distance
+/// from a point of either cloud to its nearest neighbor in the other. Points with a NaN
+/// coordinate are ignored.
+pub fn hausdorff_distance(a: &PointCloud, b: &PointCloud, names: [&str; 3]) -> Result<f64> {
+    let (ab, ba) = distances_both_ways(a, b, names)?;
+    Ok(ab.into_iter().chain(ba).fold(0.0, f64::max))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array1;
+    use crate::metadata::{Dtype, FieldSchema, Metadata};
+
+    fn cloud(points: &[[f64; 3]]) -> PointCloud {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([
+                ("x", Dtype::F64, 1), ("y", Dtype::F64, 1), ("z", Dtype::F64, 1),
+                ("normal_x", Dtype::F32, 1), ("normal_y", Dtype::F32, 1), ("normal_z", Dtype::F32, 1),
+            ]),
+            width: points.len(),
+            height: 1,
+            npoints: points.len(),
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        for (i, p) in points.iter().enumerate() {
+            for (name, v) in ["x", "y", "z"].into_iter().zip(p) {
+                pc.fields.get_mut(name).unwrap().assign_row(i, &Array1::from(vec![*v]));
+            }
+            pc.fields.get_mut("normal_z").unwrap().assign_row(i, &Array1::from(vec![1.0f32]));
+        }
+        pc
+    }
+
+    #[test]
+    fn test_distances() {
+        const XYZ: [&str; 3] = ["x", "y", "z"];
+        let a = cloud(&[[0.0, 0.0, 1.0], [1.0, 0.0, -2.0], [f64::NAN, 0.0, 0.0]]);
+        let b = cloud(&[[0.0, 0.0, 0.0], [f64::NAN; 3], [1.0, 0.0, 0.0], [5.0, 0.0, 0.0]]);
+
+        let d = a.distance_to(&b, XYZ, None).unwrap();
+        assert_eq!(d[..2], [1.0, 2.0]);
+        assert!(d[2].is_nan());
+        let signed = a.distance_to(&b, XYZ, Some(["normal_x", "normal_y", "normal_z"])).unwrap();
+        assert_eq!(signed[..2], [1.0, -2.0]);
+
+        // b to a: 1, sqrt(2) and the distance from (5, 0, 0) to (1, 0, -2)
+        let far = 20.0f64.sqrt();
+        assert!((chamfer_distance(&a, &b, XYZ, false).unwrap() - (1.5 + (1.0 + 2.0f64.sqrt() + far) / 3.0)).abs() < 1e-12);
+        assert!((chamfer_distance(&a, &b, XYZ, true).unwrap() - (2.5 + 23.0 / 3.0)).abs() < 1e-12);
+        assert_eq!(hausdorff_distance(&a, &b, XYZ).unwrap(), far);
+
+        assert!(a.distance_to(&cloud(&[]), XYZ, None).is_err());
+        assert!(a.distance_to(&b, ["x", "y", "w"], None).is_err());
+    }
+}
@@ -0,0 +1,69 @@
+use std::fmt;
+
+/// Kind of a failure that callers may want to handle specifically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The PCD header (or the header of another format) is invalid or unsupported.
+    Header,
+    /// The point data is truncated or does not decode as described by the header.
+    DataCorruption,
+    /// Fields or clouds do not have the expected names, types or sizes.
+    SchemaMismatch,
+    /// A data type has no PCD equivalent.
+    UnsupportedDtype,
+}
+
+/// Error of a known `ErrorKind`. It is usually returned inside an `anyhow::Error`, possibly
+/// with added context; `ErrorKind::of` finds the kind of such an error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PcdError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl PcdError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self { kind, message: message.into() }
+    }
+}
+
+impl fmt::Display for PcdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for PcdError {}
+
+impl ErrorKind {
+    /// Returns the kind of the first `PcdError` in the chain of `error`. Reading past the end
+    /// of the data is reported as `DataCorruption`.
+    pub fn of(error: &anyhow::Error) -> Option<Self> {
+        error.chain().find_map(|e| {
+            if let Some(e) = e.downcast_ref::<PcdError>() {
+                Some(e.kind)
+            } else {
+                e.downcast_ref::<std::io::Error>()
+                    .filter(|e| e.kind() == std::io::ErrorKind::UnexpectedEof)
+                    .map(|_| ErrorKind::DataCorruption)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_error_kind_of() {
+        let err = anyhow::Error::new(PcdError::new(ErrorKind::Header, "Bad header")).context("Cannot read 'a.pcd'");
+        assert_eq!(ErrorKind::of(&err), Some(ErrorKind::Header));
+        assert_eq!(format!("{:#}", err), "Cannot read 'a.pcd': Bad header");
+
+        let eof: anyhow::Result<()> = Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof)).context("Reading data");
+        assert_eq!(ErrorKind::of(&eof.unwrap_err()), Some(ErrorKind::DataCorruption));
+        assert_eq!(ErrorKind::of(&anyhow::anyhow!("Field 'x' not found")), None);
+    }
+}
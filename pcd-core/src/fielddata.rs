@@ -0,0 +1,1329 @@
+use half::f16;
+use num_traits::{AsPrimitive, Bounded, NumCast, ToPrimitive, Zero};
+use ndarray::{Array1, Array2, ArcArray2, Axis, s};
+use crate::metadata::{Data, Dtype};
+use crate::error::{ErrorKind, PcdError};
+
+// =====================================================================
+// Helper Macros for reducing code duplication across variants
+// =====================================================================
+
+macro_rules! match_get_data {
+    ($self:expr, $target:ty) => {
+         match $self {
+             FieldData::Bool(arr) => arr.mapv(|x| <$target>::from(x).unwrap()),
+             FieldData::U8(arr)  => arr.mapv(|x| <$target>::from(x).unwrap()),
+             FieldData::U16(arr) => arr.mapv(|x| <$target>::from(x).unwrap()),
+             FieldData::U32(arr) => arr.mapv(|x| <$target>::from(x).unwrap()),
+             FieldData::U64(arr) => arr.mapv(|x| <$target>::from(x).unwrap()),
+             FieldData::I8(arr)  => arr.mapv(|x| <$target>::from(x).unwrap()),
+             FieldData::I16(arr) => arr.mapv(|x| <$target>::from(x).unwrap()),
+             FieldData::I32(arr) => arr.mapv(|x| <$target>::from(x).unwrap()),
+             FieldData::I64(arr) => arr.mapv(|x| <$target>::from(x).unwrap()),
+             FieldData::F16(arr) => arr.mapv(|x| <$target>::from(x).unwrap()),
+             FieldData::F32(arr) => arr.mapv(|x| <$target>::from(x).unwrap()),
+             FieldData::F64(arr) => arr.mapv(|x| <$target>::from(x).unwrap()),
+         }
+    }
+}
+
+macro_rules! match_get_row {
+    ($self:expr, $row_idx:expr, $target:ty) => {
+         match $self {
+             FieldData::Bool(arr) => arr.slice(s![$row_idx, ..]).mapv(|x| <$target>::from(x).unwrap()),
+             FieldData::U8(arr)  => arr.slice(s![$row_idx, ..]).mapv(|x| <$target>::from(x).unwrap()),
+             FieldData::U16(arr) => arr.slice(s![$row_idx, ..]).mapv(|x| <$target>::from(x).unwrap()),
+             FieldData::U32(arr) => arr.slice(s![$row_idx, ..]).mapv(|x| <$target>::from(x).unwrap()),
+             FieldData::U64(arr) => arr.slice(s![$row_idx, ..]).mapv(|x| <$target>::from(x).unwrap()),
+             FieldData::I8(arr)  => arr.slice(s![$row_idx, ..]).mapv(|x| <$target>::from(x).unwrap()),
+             FieldData::I16(arr) => arr.slice(s![$row_idx, ..]).mapv(|x| <$target>::from(x).unwrap()),
+             FieldData::I32(arr) => arr.slice(s![$row_idx, ..]).mapv(|x| <$target>::from(x).unwrap()),
+             FieldData::I64(arr) => arr.slice(s![$row_idx, ..]).mapv(|x| <$target>::from(x).unwrap()),
+             FieldData::F16(arr) => arr.slice(s![$row_idx, ..]).mapv(|x| <$target>::from(x).unwrap()),
+             FieldData::F32(arr) => arr.slice(s![$row_idx, ..]).mapv(|x| <$target>::from(x).unwrap()),
+             FieldData::F64(arr) => arr.slice(s![$row_idx, ..]).mapv(|x| <$target>::from(x).unwrap()),
+         }
+    }
+}
+
+macro_rules! match_slice {
+    ($self:expr, $start:expr, $stop:expr, $step:expr) => {
+         match $self {
+             FieldData::Bool(arr) => FieldData::Bool(arr.slice(s![$start..$stop;$step, ..]).to_shared()),
+             FieldData::U8(arr)  => FieldData::U8(arr.slice(s![$start..$stop;$step, ..]).to_shared()),
+             FieldData::U16(arr) => FieldData::U16(arr.slice(s![$start..$stop;$step, ..]).to_shared()),
+             FieldData::U32(arr) => FieldData::U32(arr.slice(s![$start..$stop;$step, ..]).to_shared()),
+             FieldData::U64(arr) => FieldData::U64(arr.slice(s![$start..$stop;$step, ..]).to_shared()),
+             FieldData::I8(arr)  => FieldData::I8(arr.slice(s![$start..$stop;$step, ..]).to_shared()),
+             FieldData::I16(arr) => FieldData::I16(arr.slice(s![$start..$stop;$step, ..]).to_shared()),
+             FieldData::I32(arr) => FieldData::I32(arr.slice(s![$start..$stop;$step, ..]).to_shared()),
+             FieldData::I64(arr) => FieldData::I64(arr.slice(s![$start..$stop;$step, ..]).to_shared()),
+             FieldData::F16(arr) => FieldData::F16(arr.slice(s![$start..$stop;$step, ..]).to_shared()),
+             FieldData::F32(arr) => FieldData::F32(arr.slice(s![$start..$stop;$step, ..]).to_shared()),
+             FieldData::F64(arr) => FieldData::F64(arr.slice(s![$start..$stop;$step, ..]).to_shared()),
+         }
+    }
+}
+
+macro_rules! match_assign_row {
+    ($self:expr, $row_idx:expr, $data:expr) => {
+         match $self {
+             FieldData::Bool(arr) => {
+                 for (col, &value) in $data.iter().enumerate() {
+                     arr[[$row_idx, col]] = value.to_f64().is_some_and(|v| v != 0.0) as u8;
+                 }
+             },
+             FieldData::U8(arr) => {
+                 for (col, &value) in $data.iter().enumerate() {
+                     arr[[$row_idx, col]] = NumCast::from(value).unwrap();
+                 }
+             },
+             FieldData::U16(arr) => {
+                 for (col, &value) in $data.iter().enumerate() {
+                     arr[[$row_idx, col]] = NumCast::from(value).unwrap();
+                 }
+             },
+             FieldData::U32(arr) => {
+                 for (col, &value) in $data.iter().enumerate() {
+                     arr[[$row_idx, col]] = NumCast::from(value).unwrap();
+                 }
+             },
+             FieldData::U64(arr) => {
+                 for (col, &value) in $data.iter().enumerate() {
+                     arr[[$row_idx, col]] = NumCast::from(value).unwrap();
+                 }
+             },
+             FieldData::I8(arr) => {
+                 for (col, &value) in $data.iter().enumerate() {
+                     arr[[$row_idx, col]] = NumCast::from(value).unwrap();
+                 }
+             },
+             FieldData::I16(arr) => {
+                 for (col, &value) in $data.iter().enumerate() {
+                     arr[[$row_idx, col]] = NumCast::from(value).unwrap();
+                 }
+             },
+             FieldData::I32(arr) => {
+                 for (col, &value) in $data.iter().enumerate() {
+                     arr[[$row_idx, col]] = NumCast::from(value).unwrap();
+                 }
+             },
+             FieldData::I64(arr) => {
+                 for (col, &value) in $data.iter().enumerate() {
+                     arr[[$row_idx, col]] = NumCast::from(value).unwrap();
+                 }
+             },
+             FieldData::F16(arr) => {
+                 for (col, &value) in $data.iter().enumerate() {
+                     arr[[$row_idx, col]] = NumCast::from(value).unwrap();
+                 }
+             },
+             FieldData::F32(arr) => {
+                 for (col, &value) in $data.iter().enumerate() {
+                     arr[[$row_idx, col]] = NumCast::from(value).unwrap();
+                 }
+             },
+             FieldData::F64(arr) => {
+                 for (col, &value) in $data.iter().enumerate() {
+                     arr[[$row_idx, col]] = NumCast::from(value).unwrap();
+                 }
+             },
+         }
+    }
+}
+
+macro_rules! match_assign_from_buffer {
+    ($self:expr, $buffer:expr) => {{
+         let dsize = $self.dtype().get_size();
+         assert_eq!($buffer.len(), $self.len() * dsize, "Buffer length mismatch");
+         match $self {
+             FieldData::Bool(arr) => {
+                 for (value, &b) in arr.as_slice_mut().unwrap().iter_mut().zip($buffer.iter()) {
+                     *value = (b != 0) as u8;
+                 }
+             },
+             FieldData::U8(arr) => {
+                 arr.as_slice_mut().unwrap().copy_from_slice($buffer);
+             },
+             FieldData::U16(arr) => {
+                 for (i, chunk) in $buffer.chunks_exact(dsize).enumerate() {
+                     arr.as_slice_mut().unwrap()[i] = u16::from_le_bytes(chunk.try_into().unwrap());
+                 }
+             },
+             FieldData::U32(arr) => {
+                 for (i, chunk) in $buffer.chunks_exact(dsize).enumerate() {
+                     arr.as_slice_mut().unwrap()[i] = u32::from_le_bytes(chunk.try_into().unwrap());
+                 }
+             },
+             FieldData::U64(arr) => {
+                 for (i, chunk) in $buffer.chunks_exact(dsize).enumerate() {
+                     arr.as_slice_mut().unwrap()[i] = u64::from_le_bytes(chunk.try_into().unwrap());
+                 }
+             },
+             FieldData::I8(arr) => {
+                 for (i, &b) in $buffer.iter().enumerate() {
+                     arr.as_slice_mut().unwrap()[i] = b as i8;
+                 }
+             },
+             FieldData::I16(arr) => {
+                 for (i, chunk) in $buffer.chunks_exact(dsize).enumerate() {
+                     arr.as_slice_mut().unwrap()[i] = i16::from_le_bytes(chunk.try_into().unwrap());
+                 }
+             },
+             FieldData::I32(arr) => {
+                 for (i, chunk) in $buffer.chunks_exact(dsize).enumerate() {
+                     arr.as_slice_mut().unwrap()[i] = i32::from_le_bytes(chunk.try_into().unwrap());
+                 }
+             },
+             FieldData::I64(arr) => {
+                 for (i, chunk) in $buffer.chunks_exact(dsize).enumerate() {
+                     arr.as_slice_mut().unwrap()[i] = i64::from_le_bytes(chunk.try_into().unwrap());
+                 }
+             },
+             FieldData::F16(arr) => {
+                 for (i, chunk) in $buffer.chunks_exact(dsize).enumerate() {
+                     arr.as_slice_mut().unwrap()[i] = f16::from_le_bytes(chunk.try_into().unwrap());
+                 }
+             },
+             FieldData::F32(arr) => {
+                 for (i, chunk) in $buffer.chunks_exact(dsize).enumerate() {
+                     arr.as_slice_mut().unwrap()[i] = f32::from_le_bytes(chunk.try_into().unwrap());
+                 }
+             },
+             FieldData::F64(arr) => {
+                 for (i, chunk) in $buffer.chunks_exact(dsize).enumerate() {
+                     arr.as_slice_mut().unwrap()[i] = f64::from_le_bytes(chunk.try_into().unwrap());
+                 }
+             },
+         }
+    }}
+}
+
+macro_rules! match_assign_from_interleaved_buffer {
+    ($self:expr, $buffer:expr, $row_stride:expr, $field_offset:expr) => {{
+         let count = $self.count();
+         assert_eq!($buffer.len(), $self.npoints() * $row_stride, "Buffer length mismatch");
+         assert!($field_offset + count * $self.dtype().get_size() <= $row_stride, "Field exceeds row stride");
+         match $self {
+             FieldData::Bool(arr) => decode_interleaved(arr.as_slice_mut().unwrap(), count, $buffer, $row_stride, $field_offset, |b: [u8; 1]| (b[0] != 0) as u8),
+             FieldData::U8(arr) => decode_interleaved(arr.as_slice_mut().unwrap(), count, $buffer, $row_stride, $field_offset, u8::from_le_bytes),
+             FieldData::U16(arr) => decode_interleaved(arr.as_slice_mut().unwrap(), count, $buffer, $row_stride, $field_offset, u16::from_le_bytes),
+             FieldData::U32(arr) => decode_interleaved(arr.as_slice_mut().unwrap(), count, $buffer, $row_stride, $field_offset, u32::from_le_bytes),
+             FieldData::U64(arr) => decode_interleaved(arr.as_slice_mut().unwrap(), count, $buffer, $row_stride, $field_offset, u64::from_le_bytes),
+             FieldData::I8(arr) => decode_interleaved(arr.as_slice_mut().unwrap(), count, $buffer, $row_stride, $field_offset, i8::from_le_bytes),
+             FieldData::I16(arr) => decode_interleaved(arr.as_slice_mut().unwrap(), count, $buffer, $row_stride, $field_offset, i16::from_le_bytes),
+             FieldData::I32(arr) => decode_interleaved(arr.as_slice_mut().unwrap(), count, $buffer, $row_stride, $field_offset, i32::from_le_bytes),
+             FieldData::I64(arr) => decode_interleaved(arr.as_slice_mut().unwrap(), count, $buffer, $row_stride, $field_offset, i64::from_le_bytes),
+             FieldData::F16(arr) => decode_interleaved(arr.as_slice_mut().unwrap(), count, $buffer, $row_stride, $field_offset, f16::from_le_bytes),
+             FieldData::F32(arr) => decode_interleaved(arr.as_slice_mut().unwrap(), count, $buffer, $row_stride, $field_offset, f32::from_le_bytes),
+             FieldData::F64(arr) => decode_interleaved(arr.as_slice_mut().unwrap(), count, $buffer, $row_stride, $field_offset, f64::from_le_bytes),
+         }
+    }}
+}
+
+/// Decode one field from a buffer of interleaved little-endian records into `out`,
+/// which holds `count` values per record.
+fn decode_interleaved<T, const N: usize>(
+    out: &mut [T],
+    count: usize,
+    buffer: &[u8],
+    row_stride: usize,
+    field_offset: usize,
+    from_le_bytes: fn([u8; N]) -> T,
+) {
+    let field_bytes = count * N;
+    for (row, dst) in buffer.chunks_exact(row_stride).zip(out.chunks_exact_mut(count)) {
+        let src = &row[field_offset..field_offset + field_bytes];
+        for (value, bytes) in dst.iter_mut().zip(src.chunks_exact(N)) {
+            *value = from_le_bytes(bytes.try_into().unwrap());
+        }
+    }
+}
+
+macro_rules! match_owned {
+    ($self:expr, $arr:ident => $body:expr) => {
+         match $self {
+             FieldData::Bool($arr) => $body,
+             FieldData::U8($arr)  => $body,
+             FieldData::U16($arr) => $body,
+             FieldData::U32($arr) => $body,
+             FieldData::U64($arr) => $body,
+             FieldData::I8($arr)  => $body,
+             FieldData::I16($arr) => $body,
+             FieldData::I32($arr) => $body,
+             FieldData::I64($arr) => $body,
+             FieldData::F16($arr) => $body,
+             FieldData::F32($arr) => $body,
+             FieldData::F64($arr) => $body,
+         }
+    }
+}
+
+/// Apply `f` to the owned array behind a shared field buffer, copying it first only if the
+/// buffer is shared. The underlying allocation (and its spare capacity) is kept otherwise.
+fn with_owned<A: Clone + num_traits::Zero, R>(arr: &mut ArcArray2<A>, f: impl FnOnce(&mut Array2<A>) -> R) -> R {
+    let mut owned = std::mem::replace(arr, ArcArray2::zeros((0, 0))).into_owned();
+    let result = f(&mut owned);
+    *arr = owned.into_shared();
+    result
+}
+
+macro_rules! match_select_rows {
+    ($self:expr, $indices:expr) => {
+         match $self {
+             FieldData::Bool(arr) => FieldData::Bool(arr.select(Axis(0), $indices).into_shared()),
+             FieldData::U8(arr)  => FieldData::U8(arr.select(Axis(0), $indices).into_shared()),
+             FieldData::U16(arr) => FieldData::U16(arr.select(Axis(0), $indices).into_shared()),
+             FieldData::U32(arr) => FieldData::U32(arr.select(Axis(0), $indices).into_shared()),
+             FieldData::U64(arr) => FieldData::U64(arr.select(Axis(0), $indices).into_shared()),
+             FieldData::I8(arr)  => FieldData::I8(arr.select(Axis(0), $indices).into_shared()),
+             FieldData::I16(arr) => FieldData::I16(arr.select(Axis(0), $indices).into_shared()),
+             FieldData::I32(arr) => FieldData::I32(arr.select(Axis(0), $indices).into_shared()),
+             FieldData::I64(arr) => FieldData::I64(arr.select(Axis(0), $indices).into_shared()),
+             FieldData::F16(arr) => FieldData::F16(arr.select(Axis(0), $indices).into_shared()),
+             FieldData::F32(arr) => FieldData::F32(arr.select(Axis(0), $indices).into_shared()),
+             FieldData::F64(arr) => FieldData::F64(arr.select(Axis(0), $indices).into_shared()),
+         }
+    }
+}
+
+macro_rules! match_assign_rows {
+    ($self:expr, $indices:expr, $new_field:expr) => {
+         match ($self, $new_field) {
+             (FieldData::Bool(orig_arr), FieldData::Bool(new_arr)) => {
+                 for (new_idx, &orig_idx) in $indices.iter().enumerate() {
+                     orig_arr.row_mut(orig_idx).assign(&new_arr.row(new_idx));
+                 }
+             },
+             (FieldData::U8(orig_arr), FieldData::U8(new_arr)) => {
+                 for (new_idx, &orig_idx) in $indices.iter().enumerate() {
+                     orig_arr.row_mut(orig_idx).assign(&new_arr.row(new_idx));
+                 }
+             },
+             (FieldData::U16(orig_arr), FieldData::U16(new_arr)) => {
+                 for (new_idx, &orig_idx) in $indices.iter().enumerate() {
+                     orig_arr.row_mut(orig_idx).assign(&new_arr.row(new_idx));
+                 }
+             },
+             (FieldData::U32(orig_arr), FieldData::U32(new_arr)) => {
+                 for (new_idx, &orig_idx) in $indices.iter().enumerate() {
+                     orig_arr.row_mut(orig_idx).assign(&new_arr.row(new_idx));
+                 }
+             },
+             (FieldData::U64(orig_arr), FieldData::U64(new_arr)) => {
+                 for (new_idx, &orig_idx) in $indices.iter().enumerate() {
+                     orig_arr.row_mut(orig_idx).assign(&new_arr.row(new_idx));
+                 }
+             },
+             (FieldData::I8(orig_arr), FieldData::I8(new_arr)) => {
+                 for (new_idx, &orig_idx) in $indices.iter().enumerate() {
+                     orig_arr.row_mut(orig_idx).assign(&new_arr.row(new_idx));
+                 }
+             },
+             (FieldData::I16(orig_arr), FieldData::I16(new_arr)) => {
+                 for (new_idx, &orig_idx) in $indices.iter().enumerate() {
+                     orig_arr.row_mut(orig_idx).assign(&new_arr.row(new_idx));
+                 }
+             },
+             (FieldData::I32(orig_arr), FieldData::I32(new_arr)) => {
+                 for (new_idx, &orig_idx) in $indices.iter().enumerate() {
+                     orig_arr.row_mut(orig_idx).assign(&new_arr.row(new_idx));
+                 }
+             },
+             (FieldData::I64(orig_arr), FieldData::I64(new_arr)) => {
+                 for (new_idx, &orig_idx) in $indices.iter().enumerate() {
+                     orig_arr.row_mut(orig_idx).assign(&new_arr.row(new_idx));
+                 }
+             },
+             (FieldData::F16(orig_arr), FieldData::F16(new_arr)) => {
+                 for (new_idx, &orig_idx) in $indices.iter().enumerate() {
+                     orig_arr.row_mut(orig_idx).assign(&new_arr.row(new_idx));
+                 }
+             },
+             (FieldData::F32(orig_arr), FieldData::F32(new_arr)) => {
+                 for (new_idx, &orig_idx) in $indices.iter().enumerate() {
+                     orig_arr.row_mut(orig_idx).assign(&new_arr.row(new_idx));
+                 }
+             },
+             (FieldData::F64(orig_arr), FieldData::F64(new_arr)) => {
+                 for (new_idx, &orig_idx) in $indices.iter().enumerate() {
+                     orig_arr.row_mut(orig_idx).assign(&new_arr.row(new_idx));
+                 }
+             },
+             _ => anyhow::bail!(PcdError::new(ErrorKind::SchemaMismatch, "Field types do not match for row assignment")),
+         }
+    }
+}
+
+macro_rules! match_concat {
+    ($first:expr, $rest:expr) => {
+         match $first {
+             FieldData::Bool(first) => {
+                 let mut views = vec![first.view()];
+                 for field in $rest {
+                     match field {
+                         FieldData::Bool(arr) => views.push(arr.view()),
+                         _ => anyhow::bail!(PcdError::new(ErrorKind::SchemaMismatch, "Field types do not match for concatenation")),
+                     }
+                 }
+                 FieldData::Bool(ndarray::concatenate(Axis(0), &views)?.into_shared())
+             },
+             FieldData::U8(first) => {
+                 let mut views = vec![first.view()];
+                 for field in $rest {
+                     match field {
+                         FieldData::U8(arr) => views.push(arr.view()),
+                         _ => anyhow::bail!(PcdError::new(ErrorKind::SchemaMismatch, "Field types do not match for concatenation")),
+                     }
+                 }
+                 FieldData::U8(ndarray::concatenate(Axis(0), &views)?.into_shared())
+             },
+             FieldData::U16(first) => {
+                 let mut views = vec![first.view()];
+                 for field in $rest {
+                     match field {
+                         FieldData::U16(arr) => views.push(arr.view()),
+                         _ => anyhow::bail!(PcdError::new(ErrorKind::SchemaMismatch, "Field types do not match for concatenation")),
+                     }
+                 }
+                 FieldData::U16(ndarray::concatenate(Axis(0), &views)?.into_shared())
+             },
+             FieldData::U32(first) => {
+                 let mut views = vec![first.view()];
+                 for field in $rest {
+                     match field {
+                         FieldData::U32(arr) => views.push(arr.view()),
+                         _ => anyhow::bail!(PcdError::new(ErrorKind::SchemaMismatch, "Field types do not match for concatenation")),
+                     }
+                 }
+                 FieldData::U32(ndarray::concatenate(Axis(0), &views)?.into_shared())
+             },
+             FieldData::U64(first) => {
+                 let mut views = vec![first.view()];
+                 for field in $rest {
+                     match field {
+                         FieldData::U64(arr) => views.push(arr.view()),
+                         _ => anyhow::bail!(PcdError::new(ErrorKind::SchemaMismatch, "Field types do not match for concatenation")),
+                     }
+                 }
+                 FieldData::U64(ndarray::concatenate(Axis(0), &views)?.into_shared())
+             },
+             FieldData::I8(first) => {
+                 let mut views = vec![first.view()];
+                 for field in $rest {
+                     match field {
+                         FieldData::I8(arr) => views.push(arr.view()),
+                         _ => anyhow::bail!(PcdError::new(ErrorKind::SchemaMismatch, "Field types do not match for concatenation")),
+                     }
+                 }
+                 FieldData::I8(ndarray::concatenate(Axis(0), &views)?.into_shared())
+             },
+             FieldData::I16(first) => {
+                 let mut views = vec![first.view()];
+                 for field in $rest {
+                     match field {
+                         FieldData::I16(arr) => views.push(arr.view()),
+                         _ => anyhow::bail!(PcdError::new(ErrorKind::SchemaMismatch, "Field types do not match for concatenation")),
+                     }
+                 }
+                 FieldData::I16(ndarray::concatenate(Axis(0), &views)?.into_shared())
+             },
+             FieldData::I32(first) => {
+                 let mut views = vec![first.view()];
+                 for field in $rest {
+                     match field {
+                         FieldData::I32(arr) => views.push(arr.view()),
+                         _ => anyhow::bail!(PcdError::new(ErrorKind::SchemaMismatch, "Field types do not match for concatenation")),
+                     }
+                 }
+                 FieldData::I32(ndarray::concatenate(Axis(0), &views)?.into_shared())
+             },
+             FieldData::I64(first) => {
+                 let mut views = vec![first.view()];
+                 for field in $rest {
+                     match field {
+                         FieldData::I64(arr) => views.push(arr.view()),
+                         _ => anyhow::bail!(PcdError::new(ErrorKind::SchemaMismatch, "Field types do not match for concatenation")),
+                     }
+                 }
+                 FieldData::I64(ndarray::concatenate(Axis(0), &views)?.into_shared())
+             },
+             FieldData::F16(first) => {
+                 let mut views = vec![first.view()];
+                 for field in $rest {
+                     match field {
+                         FieldData::F16(arr) => views.push(arr.view()),
+                         _ => anyhow::bail!(PcdError::new(ErrorKind::SchemaMismatch, "Field types do not match for concatenation")),
+                     }
+                 }
+                 FieldData::F16(ndarray::concatenate(Axis(0), &views)?.into_shared())
+             },
+             FieldData::F32(first) => {
+                 let mut views = vec![first.view()];
+                 for field in $rest {
+                     match field {
+                         FieldData::F32(arr) => views.push(arr.view()),
+                         _ => anyhow::bail!(PcdError::new(ErrorKind::SchemaMismatch, "Field types do not match for concatenation")),
+                     }
+                 }
+                 FieldData::F32(ndarray::concatenate(Axis(0), &views)?.into_shared())
+             },
+             FieldData::F64(first) => {
+                 let mut views = vec![first.view()];
+                 for field in $rest {
+                     match field {
+                         FieldData::F64(arr) => views.push(arr.view()),
+                         _ => anyhow::bail!(PcdError::new(ErrorKind::SchemaMismatch, "Field types do not match for concatenation")),
+                     }
+                 }
+                 FieldData::F64(ndarray::concatenate(Axis(0), &views)?.into_shared())
+             },
+         }
+    }
+}
+
+/// Run `$body` with `$arr` bound to the array of `$self` and `$row` to the first row of
+/// `$value`, which must have the same variant.
+macro_rules! match_fill {
+    ($self:expr, $value:expr, $arr:ident, $row:ident, $body:expr) => {
+         match ($self, $value) {
+             (FieldData::Bool($arr), FieldData::Bool(value)) => { let $row = value.row(0); $body },
+             (FieldData::U8($arr), FieldData::U8(value)) => { let $row = value.row(0); $body },
+             (FieldData::U16($arr), FieldData::U16(value)) => { let $row = value.row(0); $body },
+             (FieldData::U32($arr), FieldData::U32(value)) => { let $row = value.row(0); $body },
+             (FieldData::U64($arr), FieldData::U64(value)) => { let $row = value.row(0); $body },
+             (FieldData::I8($arr), FieldData::I8(value)) => { let $row = value.row(0); $body },
+             (FieldData::I16($arr), FieldData::I16(value)) => { let $row = value.row(0); $body },
+             (FieldData::I32($arr), FieldData::I32(value)) => { let $row = value.row(0); $body },
+             (FieldData::I64($arr), FieldData::I64(value)) => { let $row = value.row(0); $body },
+             (FieldData::F16($arr), FieldData::F16(value)) => { let $row = value.row(0); $body },
+             (FieldData::F32($arr), FieldData::F32(value)) => { let $row = value.row(0); $body },
+             (FieldData::F64($arr), FieldData::F64(value)) => { let $row = value.row(0); $body },
+             _ => anyhow::bail!(PcdError::new(ErrorKind::SchemaMismatch, "Field types do not match for fill")),
+         }
+    }
+}
+
+/// How values that cannot be represented in the target dtype are handled when casting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CastPolicy {
+    /// Fail the cast.
+    #[default]
+    Error,
+    /// Clamp to the nearest representable value (NaN becomes 0 for integer dtypes).
+    Saturate,
+    /// Convert like a C cast: integers wrap around, and floats are clamped when converted
+    /// to integers (NaN becomes 0).
+    Wrap,
+}
+impl CastPolicy {
+    /// Returns the policy as a string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            CastPolicy::Error => "error",
+            CastPolicy::Saturate => "saturate",
+            CastPolicy::Wrap => "wrap",
+        }
+    }
+
+    /// Creates a `CastPolicy` from a string.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "error" => Some(CastPolicy::Error),
+            "saturate" => Some(CastPolicy::Saturate),
+            "wrap" => Some(CastPolicy::Wrap),
+            _ => None,
+        }
+    }
+}
+
+/// A primitive that can be converted to every field dtype with `as` semantics.
+pub trait CastSource:
+    ToPrimitive + Copy + std::fmt::Display
+    + AsPrimitive<u8> + AsPrimitive<u16> + AsPrimitive<u32> + AsPrimitive<u64>
+    + AsPrimitive<i8> + AsPrimitive<i16> + AsPrimitive<i32> + AsPrimitive<i64>
+    + AsPrimitive<f16> + AsPrimitive<f32> + AsPrimitive<f64>
+{}
+impl<T> CastSource for T where
+    T: ToPrimitive + Copy + std::fmt::Display
+    + AsPrimitive<u8> + AsPrimitive<u16> + AsPrimitive<u32> + AsPrimitive<u64>
+    + AsPrimitive<i8> + AsPrimitive<i16> + AsPrimitive<i32> + AsPrimitive<i64>
+    + AsPrimitive<f16> + AsPrimitive<f32> + AsPrimitive<f64>
+{}
+
+/// Convert a single value according to `policy`, or None if it cannot be represented.
+fn cast_value<S, T>(x: S, policy: CastPolicy) -> Option<T>
+where
+    S: ToPrimitive + AsPrimitive<T>,
+    T: NumCast + Bounded + Zero + Copy + 'static,
+{
+    T::from(x).or_else(|| match policy {
+        CastPolicy::Error => None,
+        CastPolicy::Saturate => match x.to_f64() {
+            Some(v) if v.is_nan() => Some(T::zero()),
+            Some(v) if v < 0.0 => Some(T::min_value()),
+            _ => Some(T::max_value()),
+        },
+        CastPolicy::Wrap => Some(x.as_()),
+    })
+}
+
+/// Convert every value of `arr` according to `policy`.
+fn cast_array<S, T>(arr: &ArcArray2<S>, policy: CastPolicy) -> anyhow::Result<ArcArray2<T>>
+where
+    S: ToPrimitive + AsPrimitive<T> + std::fmt::Display,
+    T: Data + NumCast + Bounded + Zero + 'static,
+{
+    let mut values = Vec::with_capacity(arr.len());
+    for (i, &x) in arr.iter().enumerate() {
+        match cast_value(x, policy) {
+            Some(v) => values.push(v),
+            None => anyhow::bail!("Value {} at point {} cannot be represented as {}", x, i / arr.ncols().max(1), T::DTYPE),
+        }
+    }
+    Ok(Array2::from_shape_vec(arr.raw_dim(), values)?.into_shared())
+}
+
+/// Convert `arr` into a field of `dtype` according to `policy`.
+fn cast_to_dtype<S: CastSource>(arr: &ArcArray2<S>, dtype: Dtype, policy: CastPolicy) -> anyhow::Result<FieldData> {
+    Ok(match dtype {
+        Dtype::Bool => FieldData::Bool(arr.mapv(|x| x.to_f64().is_some_and(|v| v != 0.0) as u8).into_shared()),
+        Dtype::U8  => FieldData::U8(cast_array(arr, policy)?),
+        Dtype::U16 => FieldData::U16(cast_array(arr, policy)?),
+        Dtype::U32 => FieldData::U32(cast_array(arr, policy)?),
+        Dtype::U64 => FieldData::U64(cast_array(arr, policy)?),
+        Dtype::I8  => FieldData::I8(cast_array(arr, policy)?),
+        Dtype::I16 => FieldData::I16(cast_array(arr, policy)?),
+        Dtype::I32 => FieldData::I32(cast_array(arr, policy)?),
+        Dtype::I64 => FieldData::I64(cast_array(arr, policy)?),
+        Dtype::F16 => FieldData::F16(cast_array(arr, policy)?),
+        Dtype::F32 => FieldData::F32(cast_array(arr, policy)?),
+        Dtype::F64 => FieldData::F64(cast_array(arr, policy)?),
+    })
+}
+
+// =====================================================================
+// FieldData Implementation
+// =====================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldData {
+    /// Booleans, held as bytes of 0 or 1 so that they can be viewed as NumPy bool arrays.
+    Bool(ArcArray2<u8>),
+    U8(ArcArray2<u8>),
+    U16(ArcArray2<u16>),
+    U32(ArcArray2<u32>),
+    U64(ArcArray2<u64>),
+    I8(ArcArray2<i8>),
+    I16(ArcArray2<i16>),
+    I32(ArcArray2<i32>),
+    I64(ArcArray2<i64>),
+    F16(ArcArray2<f16>),
+    F32(ArcArray2<f32>),
+    F64(ArcArray2<f64>),
+}
+
+impl FieldData {
+    pub fn new(dtype: Dtype, npoints: usize, count: usize) -> Self {
+        match dtype {
+            Dtype::Bool => FieldData::Bool(ArcArray2::zeros((npoints, count))),
+            Dtype::U8  => FieldData::U8(ArcArray2::zeros((npoints, count))),
+            Dtype::U16 => FieldData::U16(ArcArray2::zeros((npoints, count))),
+            Dtype::U32 => FieldData::U32(ArcArray2::zeros((npoints, count))),
+            Dtype::U64 => FieldData::U64(ArcArray2::zeros((npoints, count))),
+            Dtype::I8  => FieldData::I8(ArcArray2::zeros((npoints, count))),
+            Dtype::I16 => FieldData::I16(ArcArray2::zeros((npoints, count))),
+            Dtype::I32 => FieldData::I32(ArcArray2::zeros((npoints, count))),
+            Dtype::I64 => FieldData::I64(ArcArray2::zeros((npoints, count))),
+            Dtype::F16 => FieldData::F16(ArcArray2::zeros((npoints, count))),
+            Dtype::F32 => FieldData::F32(ArcArray2::zeros((npoints, count))),
+            Dtype::F64 => FieldData::F64(ArcArray2::zeros((npoints, count))),
+        }
+    }
+
+    /// Return true if both fields have the same dtype, shape and values, treating NaN values
+    /// as equal to each other.
+    pub fn equal_nan(&self, other: &FieldData) -> bool {
+        fn same<T: Copy + PartialEq>(a: &ArcArray2<T>, b: &ArcArray2<T>) -> bool {
+            #[allow(clippy::eq_op)]
+            let is_nan = |v: T| v != v;
+            a.shape() == b.shape() && a.iter().zip(b.iter()).all(|(&x, &y)| x == y || (is_nan(x) && is_nan(y)))
+        }
+        match (self, other) {
+            (FieldData::Bool(a), FieldData::Bool(b)) => same(a, b),
+            (FieldData::U8(a), FieldData::U8(b))   => same(a, b),
+            (FieldData::U16(a), FieldData::U16(b)) => same(a, b),
+            (FieldData::U32(a), FieldData::U32(b)) => same(a, b),
+            (FieldData::U64(a), FieldData::U64(b)) => same(a, b),
+            (FieldData::I8(a), FieldData::I8(b))   => same(a, b),
+            (FieldData::I16(a), FieldData::I16(b)) => same(a, b),
+            (FieldData::I32(a), FieldData::I32(b)) => same(a, b),
+            (FieldData::I64(a), FieldData::I64(b)) => same(a, b),
+            (FieldData::F16(a), FieldData::F16(b)) => same(a, b),
+            (FieldData::F32(a), FieldData::F32(b)) => same(a, b),
+            (FieldData::F64(a), FieldData::F64(b)) => same(a, b),
+            _ => false,
+        }
+    }
+
+    /// Stack fields of one column each into a single field, in one pass over each field. The
+    /// result is float32 if every field is float16 or float32, and float64 otherwise.
+    pub fn hstack_float(fields: &[&FieldData]) -> Self {
+        fn fill<A: Data + NumCast + Zero>(fields: &[&FieldData]) -> ArcArray2<A> {
+            let npoints = fields.first().map_or(0, |f| f.npoints());
+            let mut out = Array2::zeros((npoints, fields.len()));
+            for (j, field) in fields.iter().enumerate() {
+                match_owned!(field, arr => out.column_mut(j).zip_mut_with(&arr.column(0), |o, &v| *o = NumCast::from(v).unwrap()))
+            }
+            out.into_shared()
+        }
+        if fields.iter().all(|f| matches!(f.dtype(), Dtype::F16 | Dtype::F32)) {
+            FieldData::F32(fill(fields))
+        } else {
+            FieldData::F64(fill(fields))
+        }
+    }
+
+    /// Return a copy of the columns `start..end` of this field.
+    pub fn columns(&self, start: usize, end: usize) -> Self {
+        match self {
+            FieldData::Bool(arr) => FieldData::Bool(arr.slice(s![.., start..end]).to_shared()),
+            FieldData::U8(arr)  => FieldData::U8(arr.slice(s![.., start..end]).to_shared()),
+            FieldData::U16(arr) => FieldData::U16(arr.slice(s![.., start..end]).to_shared()),
+            FieldData::U32(arr) => FieldData::U32(arr.slice(s![.., start..end]).to_shared()),
+            FieldData::U64(arr) => FieldData::U64(arr.slice(s![.., start..end]).to_shared()),
+            FieldData::I8(arr)  => FieldData::I8(arr.slice(s![.., start..end]).to_shared()),
+            FieldData::I16(arr) => FieldData::I16(arr.slice(s![.., start..end]).to_shared()),
+            FieldData::I32(arr) => FieldData::I32(arr.slice(s![.., start..end]).to_shared()),
+            FieldData::I64(arr) => FieldData::I64(arr.slice(s![.., start..end]).to_shared()),
+            FieldData::F16(arr) => FieldData::F16(arr.slice(s![.., start..end]).to_shared()),
+            FieldData::F32(arr) => FieldData::F32(arr.slice(s![.., start..end]).to_shared()),
+            FieldData::F64(arr) => FieldData::F64(arr.slice(s![.., start..end]).to_shared()),
+        }
+    }
+
+    /// Returns true if the field holds no values.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the length (total number of values) in this field.
+    pub fn len(&self) -> usize {
+        match self {
+            FieldData::Bool(arr) => arr.len(),
+            FieldData::U8(arr)   => arr.len(),
+            FieldData::U16(arr) => arr.len(),
+            FieldData::U32(arr) => arr.len(),
+            FieldData::U64(arr) => arr.len(),
+            FieldData::I8(arr)   => arr.len(),
+            FieldData::I16(arr) => arr.len(),
+            FieldData::I32(arr) => arr.len(),
+            FieldData::I64(arr) => arr.len(),
+            FieldData::F16(arr) => arr.len(),
+            FieldData::F32(arr) => arr.len(),
+            FieldData::F64(arr) => arr.len(),
+        }
+    }
+
+    /// Format the value of component `col` of point `row` as text: booleans as true or
+    /// false, numbers in the shortest form that reads back exactly.
+    ///
+    /// Panics if `row` or `col` is out of bounds.
+    pub fn format_value(&self, row: usize, col: usize) -> String {
+        if let FieldData::Bool(arr) = self {
+            return (arr[[row, col]] != 0).to_string();
+        }
+        match_owned!(self, arr => arr[[row, col]].to_string())
+    }
+
+    /// Return the size in bytes of the values of this field.
+    pub fn nbytes(&self) -> usize {
+        self.len() * self.dtype().get_size()
+    }
+
+    /// Return the number of points in this field.
+    pub fn npoints(&self) -> usize {
+        match self {
+            FieldData::Bool(arr) => arr.shape()[0],
+            FieldData::U8(arr)   => arr.shape()[0],
+            FieldData::U16(arr) => arr.shape()[0],
+            FieldData::U32(arr) => arr.shape()[0],
+            FieldData::U64(arr) => arr.shape()[0],
+            FieldData::I8(arr)   => arr.shape()[0],
+            FieldData::I16(arr) => arr.shape()[0],
+            FieldData::I32(arr) => arr.shape()[0],
+            FieldData::I64(arr) => arr.shape()[0],
+            FieldData::F16(arr) => arr.shape()[0],
+            FieldData::F32(arr) => arr.shape()[0],
+            FieldData::F64(arr) => arr.shape()[0],
+        }
+    }
+
+    /// Return the number of columns in this field.
+    pub fn count(&self) -> usize {
+        match self {
+            FieldData::Bool(arr) => arr.shape()[1],
+            FieldData::U8(arr)   => arr.shape()[1],
+            FieldData::U16(arr) => arr.shape()[1],
+            FieldData::U32(arr) => arr.shape()[1],
+            FieldData::U64(arr) => arr.shape()[1],
+            FieldData::I8(arr)   => arr.shape()[1],
+            FieldData::I16(arr) => arr.shape()[1],
+            FieldData::I32(arr) => arr.shape()[1],
+            FieldData::I64(arr) => arr.shape()[1],
+            FieldData::F16(arr) => arr.shape()[1],
+            FieldData::F32(arr) => arr.shape()[1],
+            FieldData::F64(arr) => arr.shape()[1],
+        }
+    }
+
+    /// Return a mask that is true for each point with a NaN in any of its values.
+    /// Integer fields cannot hold NaN, so their mask is all false.
+    pub fn nan_mask(&self) -> Array1<bool> {
+        match self {
+            FieldData::F16(arr) => arr.map_axis(Axis(1), |row| row.iter().any(|v| v.is_nan())),
+            FieldData::F32(arr) => arr.map_axis(Axis(1), |row| row.iter().any(|v| v.is_nan())),
+            FieldData::F64(arr) => arr.map_axis(Axis(1), |row| row.iter().any(|v| v.is_nan())),
+            _ => Array1::from_elem(self.npoints(), false),
+        }
+    }
+
+    /// Return the data type of this field.
+    pub fn dtype(&self) -> Dtype {
+        match self {
+            FieldData::Bool(_) => Dtype::Bool,
+            FieldData::U8(_)  => Dtype::U8,
+            FieldData::U16(_) => Dtype::U16,
+            FieldData::U32(_) => Dtype::U32,
+            FieldData::U64(_) => Dtype::U64,
+            FieldData::I8(_)  => Dtype::I8,
+            FieldData::I16(_) => Dtype::I16,
+            FieldData::I32(_) => Dtype::I32,
+            FieldData::I64(_) => Dtype::I64,
+            FieldData::F16(_) => Dtype::F16,
+            FieldData::F32(_) => Dtype::F32,
+            FieldData::F64(_) => Dtype::F64,
+        }
+    }
+
+    /// Return the data as a 2D array of the specified type.
+    /// Panics if a value cannot be represented in that type; see `try_get_data`.
+    pub fn get_data<A: Data + NumCast>(&self) -> Array2<A> {
+        match_get_data!(self, A)
+    }
+
+    /// Return the data as a 2D array of the specified type, handling values that cannot be
+    /// represented according to `policy`.
+    pub fn try_get_data<A: Data + NumCast>(&self, policy: CastPolicy) -> anyhow::Result<Array2<A>> {
+        Ok(self.cast(A::DTYPE, policy)?.get_data())
+    }
+
+    /// Return a single row of data as a 1D array of the specified type.
+    /// Panics if a value cannot be represented in that type.
+    pub fn get_row<A: Data + NumCast>(&self, row_idx: usize) -> Array1<A> {
+        match_get_row!(self, row_idx, A)
+    }
+
+    /// Return a sliced version of this field's data (e.g., slice by range).
+    pub fn slice(&self, start: usize, stop: usize, step: usize) -> Self {
+        match_slice!(self, start, stop, step)
+    }
+
+    /// Assign a single row of data to this field, of its dtype or the dtype it is stored as
+    /// (see `Dtype::storage`).
+    pub fn assign_row<A>(&mut self, row_idx: usize, data: &Array1<A>)
+    where
+        A: Data + NumCast,
+    {
+        assert_eq!(self.count(), data.len(), "Data length does not match field count");
+        assert!(A::DTYPE == self.dtype() || A::DTYPE == self.dtype().storage(),
+            "Expected data type {}, got {}", self.dtype(), A::DTYPE);
+        match_assign_row!(self, row_idx, data);
+    }
+
+    /// Assign data from a buffer to this field.
+    pub fn assign_from_buffer(&mut self, buffer: &[u8]) {
+        match_assign_from_buffer!(self, buffer);
+    }
+
+    /// Assign all rows of this field from a buffer of interleaved records, where each record is
+    /// `row_stride` bytes long and this field's values start `field_offset` bytes into it.
+    pub fn assign_from_interleaved_buffer(&mut self, buffer: &[u8], row_stride: usize, field_offset: usize) {
+        match_assign_from_interleaved_buffer!(self, buffer, row_stride, field_offset);
+    }
+
+    /// Write all rows of this field as little-endian values into a buffer of interleaved
+    /// records, the inverse of `assign_from_interleaved_buffer`.
+    pub fn write_to_interleaved_buffer(&self, buffer: &mut [u8], row_stride: usize, field_offset: usize) {
+        let size = self.dtype().get_size();
+        assert_eq!(buffer.len(), self.npoints() * row_stride, "Buffer length mismatch");
+        assert!(field_offset + self.count() * size <= row_stride, "Field exceeds row stride");
+        match_owned!(self, arr => {
+            for (row, record) in arr.rows().into_iter().zip(buffer.chunks_exact_mut(row_stride)) {
+                let dst = &mut record[field_offset..field_offset + row.len() * size];
+                for (value, bytes) in row.iter().zip(dst.chunks_exact_mut(size)) {
+                    bytes.copy_from_slice(&value.to_le_bytes());
+                }
+            }
+        });
+    }
+
+    /// Append the values of this field to `buffer` as little-endian bytes, point by point.
+    pub fn extend_le_bytes(&self, buffer: &mut Vec<u8>) {
+        buffer.reserve(self.len() * self.dtype().get_size());
+        match_owned!(self, arr => arr.iter().for_each(|v| buffer.extend_from_slice(&v.to_le_bytes())));
+    }
+
+    /// Return a copy of this field converted to `dtype`. Values that cannot be represented
+    /// (out of range, or NaN for integer dtypes) are handled according to `policy`, and
+    /// floats are truncated towards zero when converted to integers. Nonzero values
+    /// (including NaN) become true when converted to bool.
+    pub fn cast(&self, dtype: Dtype, policy: CastPolicy) -> anyhow::Result<Self> {
+        if dtype == self.dtype() {
+            return Ok(self.clone());
+        }
+        match self {
+            FieldData::Bool(arr) => cast_to_dtype(arr, dtype, policy),
+            FieldData::U8(arr)  => cast_to_dtype(arr, dtype, policy),
+            FieldData::U16(arr) => cast_to_dtype(arr, dtype, policy),
+            FieldData::U32(arr) => cast_to_dtype(arr, dtype, policy),
+            FieldData::U64(arr) => cast_to_dtype(arr, dtype, policy),
+            FieldData::I8(arr)  => cast_to_dtype(arr, dtype, policy),
+            FieldData::I16(arr) => cast_to_dtype(arr, dtype, policy),
+            FieldData::I32(arr) => cast_to_dtype(arr, dtype, policy),
+            FieldData::I64(arr) => cast_to_dtype(arr, dtype, policy),
+            FieldData::F16(arr) => cast_to_dtype(arr, dtype, policy),
+            FieldData::F32(arr) => cast_to_dtype(arr, dtype, policy),
+            FieldData::F64(arr) => cast_to_dtype(arr, dtype, policy),
+        }
+    }
+
+    /// Reserve room for at least `additional` more points without reallocating.
+    pub fn reserve(&mut self, additional: usize) -> anyhow::Result<()> {
+        match_owned!(self, arr => with_owned(arr, |a| a.reserve_rows(additional))?);
+        Ok(())
+    }
+
+    /// Resize this field to `npoints` points. Shrinking keeps the existing allocation, and
+    /// growing appends zeroed rows with amortized reallocation, so repeated growth is linear.
+    pub fn resize(&mut self, npoints: usize) -> anyhow::Result<()> {
+        let current = self.npoints();
+        if npoints <= current {
+            match_owned!(self, arr => arr.slice_collapse(s![..npoints, ..]));
+        } else {
+            let count = self.count();
+            match_owned!(self, arr => with_owned(arr, |a| a.append(Axis(0), Array2::zeros((npoints - current, count)).view()))?);
+        }
+        Ok(())
+    }
+
+    /// Return a new field containing the rows at `indices`, in that order.
+    ///
+    /// Returns an error if any index is out of bounds.
+    pub fn take_rows(&self, indices: &[usize]) -> anyhow::Result<Self> {
+        self.check_indices(indices)?;
+        Ok(match_select_rows!(self, indices))
+    }
+
+    /// Assign row `i` of `new_field` to row `indices[i]` of self.
+    ///
+    /// Returns an error if any index is out of bounds, if the number of indices differs
+    /// from the number of rows in `new_field`, or if the two FieldData variants differ.
+    pub fn assign_rows(&mut self, indices: &[usize], new_field: &FieldData) -> anyhow::Result<()> {
+        self.check_indices(indices)?;
+        anyhow::ensure!(
+            indices.len() == new_field.npoints(),
+            "Index count mismatch: {} indices, got {} rows", indices.len(), new_field.npoints()
+        );
+        anyhow::ensure!(new_field.count() == self.count(),
+            PcdError::new(ErrorKind::SchemaMismatch, format!("Count mismatch: field has {} values per point, got {}", self.count(), new_field.count())));
+        match_assign_rows!(self, indices, new_field);
+        Ok(())
+    }
+
+    /// Return a new field containing only the rows where `mask` is true.
+    ///
+    /// Returns an error if the mask length does not match the number of points.
+    pub fn select_mask(&self, mask: &[bool]) -> anyhow::Result<Self> {
+        self.take_rows(&self.mask_indices(mask)?)
+    }
+
+    /// Assign the rows of `new_field` to the rows of self where `mask` is true, in order.
+    ///
+    /// Returns an error if the mask length does not match the number of points, if the
+    /// number of selected rows differs from the number of rows in `new_field`,
+    /// or if the two FieldData variants differ.
+    pub fn assign_mask(&mut self, mask: &[bool], new_field: &FieldData) -> anyhow::Result<()> {
+        self.assign_rows(&self.mask_indices(mask)?, new_field)
+    }
+
+    /// Check that all row indices are within bounds.
+    fn check_indices(&self, indices: &[usize]) -> anyhow::Result<()> {
+        let npoints = self.npoints();
+        if let Some(&idx) = indices.iter().find(|&&i| i >= npoints) {
+            anyhow::bail!("Index {} is out of bounds for {} points", idx, npoints);
+        }
+        Ok(())
+    }
+
+    /// Convert a boolean mask over the points of this field into row indices.
+    fn mask_indices(&self, mask: &[bool]) -> anyhow::Result<Vec<usize>> {
+        anyhow::ensure!(
+            mask.len() == self.npoints(),
+            "Mask length mismatch: expected {}, got {}", self.npoints(), mask.len()
+        );
+        Ok(mask.iter().enumerate().filter_map(|(i, &m)| m.then_some(i)).collect())
+    }
+
+    /// Stack this field and `others` along the point axis into a new field.
+    ///
+    /// Returns an error if the FieldData variants or counts differ.
+    pub fn concat(&self, others: &[&FieldData]) -> anyhow::Result<Self> {
+        Ok(match_concat!(self, others.iter().copied()))
+    }
+
+    /// Assign the rows of `values` to the rows from `start` up to `stop` (exclusive) of this
+    /// field, every `step` rows, converting them to the dtype of this field with `policy`.
+    ///
+    /// Returns an error if the number of rows or the count of `values` do not match, or if a
+    /// value cannot be converted.
+    pub fn assign_slice(&mut self, start: usize, stop: usize, step: usize, values: &FieldData, policy: CastPolicy) -> anyhow::Result<()> {
+        anyhow::ensure!(step > 0, "Slice step must be positive");
+        let values = values.cast(self.dtype(), policy)?;
+        self.update_slice_strided(&values, start..stop, step, 0..values.npoints(), 1)
+    }
+
+    /// Set the rows from `start` up to `stop` (exclusive) of this field, every `step` rows, to
+    /// `value`: a single row of either `count` values or one value for every component,
+    /// converted to the dtype of this field with `policy`.
+    pub fn fill_slice(&mut self, start: usize, stop: usize, step: usize, value: &FieldData, policy: CastPolicy) -> anyhow::Result<()> {
+        anyhow::ensure!(step > 0, "Slice step must be positive");
+        anyhow::ensure!(start <= stop && stop <= self.npoints(), "Slice is out of bounds");
+        let value = self.fill_value(value, policy)?;
+        match_fill!(self, &value, arr, row, arr.slice_mut(s![start..stop;step, ..]).assign(&row));
+        Ok(())
+    }
+
+    /// Set the rows `indices` of this field to `value`, as `fill_slice`.
+    pub fn fill_rows(&mut self, indices: &[usize], value: &FieldData, policy: CastPolicy) -> anyhow::Result<()> {
+        self.check_indices(indices)?;
+        let value = self.fill_value(value, policy)?;
+        match_fill!(self, &value, arr, row, for &i in indices {
+            arr.row_mut(i).assign(&row);
+        });
+        Ok(())
+    }
+
+    /// Check that `value` is a single row that can fill the rows of this field, and convert it
+    /// to the dtype of this field.
+    fn fill_value(&self, value: &FieldData, policy: CastPolicy) -> anyhow::Result<FieldData> {
+        anyhow::ensure!(value.npoints() == 1, "Fill value must be a single row, got {} rows", value.npoints());
+        anyhow::ensure!(value.count() == 1 || value.count() == self.count(), PcdError::new(ErrorKind::SchemaMismatch,
+            format!("Count mismatch: field has {} values per point, got {}", self.count(), value.count())));
+        value.cast(self.dtype(), policy)
+    }
+
+    /// Update a strided slice of self with a strided slice from new_field.
+    ///
+    /// - `orig_range`: The range of row indices in self to update.
+    /// - `orig_step`: The step (stride) for the rows in self.
+    /// - `new_range`: The range of row indices in new_field to copy from.
+    /// - `new_step`: The step (stride) for the rows in new_field.
+    ///
+    /// Returns an error if the number of rows in both slices do not match
+    /// or if the two FieldData variants differ.
+    pub fn update_slice_strided(
+        &mut self,
+        new_field: &FieldData,
+        orig_range: std::ops::Range<usize>,
+        orig_step: usize,
+        new_range: std::ops::Range<usize>,
+        new_step: usize,
+    ) -> anyhow::Result<()> {
+        // Calculate the number of rows in each slice.
+        let num_orig_rows = orig_range.end.saturating_sub(orig_range.start).div_ceil(orig_step);
+        let num_new_rows = new_range.end.saturating_sub(new_range.start).div_ceil(new_step);
+        anyhow::ensure!(num_orig_rows == num_new_rows, "Slice lengths do not match: {} rows, got {}", num_orig_rows, num_new_rows);
+        anyhow::ensure!(orig_range.end <= self.npoints() && new_range.end <= new_field.npoints(), "Slice is out of bounds");
+        anyhow::ensure!(new_field.count() == self.count(),
+            PcdError::new(ErrorKind::SchemaMismatch, format!("Count mismatch: field has {} values per point, got {}", self.count(), new_field.count())));
+        // Create slicing specifications for both arrays.
+        let orig_slice = s![orig_range.start..orig_range.end; orig_step, ..];
+        let new_slice = s![new_range.start..new_range.end; new_step, ..];
+
+        // Use ndarray's assign method to update the slice.
+        match (self, new_field) {
+            (FieldData::Bool(ref mut orig_arr), FieldData::Bool(new_arr)) => {
+                orig_arr.slice_mut(orig_slice).assign(&new_arr.slice(new_slice));
+            },
+            (FieldData::U8(ref mut orig_arr), FieldData::U8(new_arr)) => {
+                orig_arr.slice_mut(orig_slice).assign(&new_arr.slice(new_slice));
+            },
+            (FieldData::U16(ref mut orig_arr), FieldData::U16(new_arr)) => {
+                orig_arr.slice_mut(orig_slice).assign(&new_arr.slice(new_slice));
+            },
+            (FieldData::U32(ref mut orig_arr), FieldData::U32(new_arr)) => {
+                orig_arr.slice_mut(orig_slice).assign(&new_arr.slice(new_slice));
+            },
+            (FieldData::U64(ref mut orig_arr), FieldData::U64(new_arr)) => {
+                orig_arr.slice_mut(orig_slice).assign(&new_arr.slice(new_slice));
+            },
+            (FieldData::I8(ref mut orig_arr), FieldData::I8(new_arr)) => {
+                orig_arr.slice_mut(orig_slice).assign(&new_arr.slice(new_slice));
+            },
+            (FieldData::I16(ref mut orig_arr), FieldData::I16(new_arr)) => {
+                orig_arr.slice_mut(orig_slice).assign(&new_arr.slice(new_slice));
+            },
+            (FieldData::I32(ref mut orig_arr), FieldData::I32(new_arr)) => {
+                orig_arr.slice_mut(orig_slice).assign(&new_arr.slice(new_slice));
+            },
+            (FieldData::I64(ref mut orig_arr), FieldData::I64(new_arr)) => {
+                orig_arr.slice_mut(orig_slice).assign(&new_arr.slice(new_slice));
+            },
+            (FieldData::F16(ref mut orig_arr), FieldData::F16(new_arr)) => {
+                orig_arr.slice_mut(orig_slice).assign(&new_arr.slice(new_slice));
+            },
+            (FieldData::F32(ref mut orig_arr), FieldData::F32(new_arr)) => {
+                orig_arr.slice_mut(orig_slice).assign(&new_arr.slice(new_slice));
+            },
+            (FieldData::F64(ref mut orig_arr), FieldData::F64(new_arr)) => {
+                orig_arr.slice_mut(orig_slice).assign(&new_arr.slice(new_slice));
+            },
+            _ => anyhow::bail!(PcdError::new(ErrorKind::SchemaMismatch, "Field types do not match for slice assignment")),
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for FieldData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldData::Bool(arr) => write!(f, "{}", arr.mapv(|v| v != 0)),
+            FieldData::U8(arr)   => write!(f, "{}", arr),
+            FieldData::U16(arr) => write!(f, "{}", arr),
+            FieldData::U32(arr) => write!(f, "{}", arr),
+            FieldData::U64(arr) => write!(f, "{}", arr),
+            FieldData::I8(arr)   => write!(f, "{}", arr),
+            FieldData::I16(arr) => write!(f, "{}", arr),
+            FieldData::I32(arr) => write!(f, "{}", arr),
+            FieldData::I64(arr) => write!(f, "{}", arr),
+            FieldData::F16(arr) => write!(f, "{}", arr),
+            FieldData::F32(arr) => write!(f, "{}", arr),
+            FieldData::F64(arr) => write!(f, "{}", arr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simple () {
+        let arr = Array2::from(vec![[1], [2], [3], [4], [5]]);
+        let field = FieldData::U8(arr.into());
+        assert_eq!(field.npoints(), 5);
+        assert_eq!(field.dtype().get_size(), 1);
+        assert_eq!(field.dtype().get_type(), "U");
+    }
+
+    #[test]
+    fn test_format_value() {
+        let field = FieldData::F32(Array2::from(vec![[0.1f32, f32::NAN]]).into_shared());
+        assert_eq!((field.format_value(0, 0), field.format_value(0, 1)), ("0.1".to_string(), "NaN".to_string()));
+        let field = FieldData::Bool(Array2::from(vec![[1u8], [0]]).into_shared());
+        assert_eq!((field.format_value(0, 0), field.format_value(1, 0)), ("true".to_string(), "false".to_string()));
+    }
+
+    #[test]
+    fn test_slicing () {
+        let arr = Array2::from(vec![[1], [2], [3], [4], [5]]);
+        let field = FieldData::U8(arr.into());
+        let sliced = field.slice(1, 4, 1);
+        assert_eq!(sliced.npoints(), 3);
+        assert_eq!(sliced.dtype().get_size(), 1);
+        assert_eq!(sliced.dtype().get_type(), "U");
+
+        let arr = Array2::from(vec![[1], [2], [3], [4], [5]]);
+        let field = FieldData::U8(arr.into());
+        let sliced = field.slice(0, 5, 2);
+        assert_eq!(sliced.npoints(), 3);
+        assert_eq!(sliced.dtype().get_size(), 1);
+        assert_eq!(sliced.dtype().get_type(), "U");
+    }
+
+    #[test]
+    fn test_construction () {
+        let mut field = FieldData::new(Dtype::U8, 10, 3);
+        assert_eq!(field.npoints(), 10);
+
+        let data = Array1::from(vec![1, 2, 3]);
+        field.assign_row(0, &data);
+        assert_eq!(field.npoints(), 10);
+        assert_eq!(field.get_row::<u8>(0), data);
+    }
+
+    #[test]
+    fn test_mask () {
+        let arr = Array2::from(vec![[1, 10], [2, 20], [3, 30], [4, 40]]);
+        let mut field = FieldData::U8(arr.into());
+        let mask = [true, false, false, true];
+        let selected = field.select_mask(&mask).unwrap();
+        assert_eq!(selected.npoints(), 2);
+        assert_eq!(selected.get_row::<u8>(1), Array1::from(vec![4, 40]));
+
+        let new_field = FieldData::U8(Array2::from(vec![[7, 70], [8, 80]]).into());
+        field.assign_mask(&mask, &new_field).unwrap();
+        assert_eq!(field.get_row::<u8>(0), Array1::from(vec![7, 70]));
+        assert_eq!(field.get_row::<u8>(1), Array1::from(vec![2, 20]));
+        assert_eq!(field.get_row::<u8>(3), Array1::from(vec![8, 80]));
+
+        assert!(field.select_mask(&[true]).is_err());
+        assert!(field.assign_mask(&[true, false, false, false], &new_field).is_err());
+    }
+
+    #[test]
+    fn test_assign_slice () {
+        let mut field = FieldData::U16(Array2::from(vec![[1, 10], [2, 20], [3, 30], [4, 40], [5, 50]]).into());
+        let values = FieldData::F64(Array2::from(vec![[7.0, 70.0], [8.0, 80.0]]).into());
+        field.assign_slice(1, 5, 2, &values, CastPolicy::Error).unwrap();
+        assert_eq!(field.get_data::<u16>(), Array2::from(vec![[1, 10], [7, 70], [3, 30], [8, 80], [5, 50]]));
+        field.assign_slice(3, 5, 1, &values, CastPolicy::Error).unwrap();
+        assert_eq!(field.get_row::<u16>(4), Array1::from(vec![8, 80]));
+
+        assert!(field.assign_slice(0, 5, 1, &values, CastPolicy::Error).is_err());
+        assert!(field.assign_slice(4, 6, 1, &values, CastPolicy::Error).is_err());
+        let negative = FieldData::F64(Array2::from(vec![[-1.0, 0.0], [0.0, 0.0]]).into());
+        assert!(field.assign_slice(0, 2, 1, &negative, CastPolicy::Error).is_err());
+        let err = field.assign_slice(0, 2, 1, &values.columns(0, 1), CastPolicy::Error).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), Some(ErrorKind::SchemaMismatch));
+        assert!(field.assign_rows(&[0, 1], &values.columns(0, 1).cast(Dtype::U16, CastPolicy::Error).unwrap()).is_err());
+        assert_eq!(field.get_row::<u16>(0), Array1::from(vec![1, 10]));
+    }
+
+    #[test]
+    fn test_fill () {
+        let mut field = FieldData::U16(Array2::from(vec![[1, 10], [2, 20], [3, 30], [4, 40], [5, 50]]).into());
+        let scalar = FieldData::I64(Array2::from(vec![[7]]).into());
+        field.fill_slice(1, 5, 2, &scalar, CastPolicy::Error).unwrap();
+        assert_eq!(field.get_data::<u16>(), Array2::from(vec![[1, 10], [7, 7], [3, 30], [7, 7], [5, 50]]));
+        let row = FieldData::F64(Array2::from(vec![[8.0, 80.0]]).into());
+        field.fill_rows(&[0, 4], &row, CastPolicy::Error).unwrap();
+        assert_eq!(field.get_row::<u16>(4), Array1::from(vec![8, 80]));
+        field.fill_slice(0, 5, 1, &scalar, CastPolicy::Error).unwrap();
+        assert!(field.get_data::<u16>().iter().all(|&v| v == 7));
+
+        assert!(field.fill_slice(0, 6, 1, &scalar, CastPolicy::Error).is_err());
+        assert!(field.fill_rows(&[5], &scalar, CastPolicy::Error).is_err());
+        let negative = FieldData::I64(Array2::from(vec![[-1]]).into());
+        assert!(field.fill_rows(&[0], &negative, CastPolicy::Error).is_err());
+        field.fill_rows(&[0], &negative, CastPolicy::Saturate).unwrap();
+        assert_eq!(field.get_row::<u16>(0), Array1::from(vec![0, 0]));
+        let three = FieldData::I64(Array2::from(vec![[1, 2, 3]]).into());
+        assert!(field.fill_rows(&[0], &three, CastPolicy::Error).is_err());
+    }
+
+    #[test]
+    fn test_take_rows () {
+        let arr = Array2::from(vec![[1.0], [2.0], [3.0]]);
+        let mut field = FieldData::F32(arr.into());
+        let taken = field.take_rows(&[2, 0, 2]).unwrap();
+        assert_eq!(taken.get_data::<f32>(), Array2::from(vec![[3.0], [1.0], [3.0]]));
+        assert!(field.take_rows(&[3]).is_err());
+
+        let new_field = FieldData::F32(Array2::from(vec![[5.0], [6.0]]).into());
+        field.assign_rows(&[2, 0], &new_field).unwrap();
+        assert_eq!(field.get_data::<f32>(), Array2::from(vec![[6.0], [2.0], [5.0]]));
+        assert!(field.assign_rows(&[0], &new_field).is_err());
+    }
+
+    #[test]
+    fn test_concat () {
+        let a = FieldData::I16(Array2::from(vec![[1, 2]]).into());
+        let b = FieldData::I16(Array2::from(vec![[3, 4], [5, 6]]).into());
+        let c = a.concat(&[&b]).unwrap();
+        assert_eq!(c.npoints(), 3);
+        assert_eq!(c.get_row::<i16>(2), Array1::from(vec![5, 6]));
+
+        let d = FieldData::U8(Array2::from(vec![[1, 2]]).into());
+        assert!(a.concat(&[&d]).is_err());
+        let e = FieldData::I16(Array2::from(vec![[1]]).into());
+        assert!(a.concat(&[&e]).is_err());
+    }
+
+    #[test]
+    fn test_cast () {
+        let field = FieldData::F32(Array2::from(vec![[1.7], [-3.2], [300.0], [f32::NAN]]).into());
+        assert!(field.cast(Dtype::U8, CastPolicy::Error).is_err());
+
+        let saturated = field.cast(Dtype::U8, CastPolicy::Saturate).unwrap();
+        assert_eq!(saturated.dtype(), Dtype::U8);
+        assert_eq!(saturated.get_data::<u8>(), Array2::from(vec![[1], [0], [255], [0]]));
+
+        let widened = field.cast(Dtype::F64, CastPolicy::Error).unwrap();
+        assert_eq!(widened.get_row::<f64>(0)[0], 1.7f32 as f64);
+
+        let wrapped = FieldData::I16(Array2::from(vec![[-1], [258]]).into());
+        assert!(wrapped.try_get_data::<u8>(CastPolicy::Error).is_err());
+        assert_eq!(wrapped.try_get_data::<u8>(CastPolicy::Wrap).unwrap(), Array2::from(vec![[255], [2]]));
+        assert_eq!(field.try_get_data::<i8>(CastPolicy::Wrap).unwrap(), Array2::from(vec![[1], [-3], [127], [0]]));
+
+        let ints = FieldData::I16(Array2::from(vec![[-1, 2]]).into());
+        let err = ints.cast(Dtype::U16, CastPolicy::Error).unwrap_err().to_string();
+        assert!(err.contains("Value -1 at point 0"), "{}", err);
+        assert_eq!(ints.cast(Dtype::U16, CastPolicy::Saturate).unwrap().get_row::<u16>(0), Array1::from(vec![0, 2]));
+    }
+
+    #[test]
+    fn test_columns () {
+        let field = FieldData::I32(Array2::from(vec![[1, 2, 3], [4, 5, 6]]).into());
+        let cols = field.columns(1, 3);
+        assert_eq!(cols.count(), 2);
+        assert_eq!(cols.get_data::<i32>(), Array2::from(vec![[2, 3], [5, 6]]));
+    }
+
+    #[test]
+    fn test_resize () {
+        let mut field = FieldData::U16(Array2::from(vec![[1, 2], [3, 4], [5, 6]]).into());
+        field.reserve(10).unwrap();
+        field.resize(2).unwrap();
+        assert_eq!(field.npoints(), 2);
+        for n in 3..100 {
+            field.resize(n).unwrap();
+        }
+        assert_eq!(field.npoints(), 99);
+        assert_eq!(field.count(), 2);
+        assert_eq!(field.get_row::<u16>(1), Array1::from(vec![3, 4]));
+        assert_eq!(field.get_row::<u16>(2), Array1::from(vec![0, 0]));
+
+        // Resizing a shared buffer leaves the other handle untouched.
+        let copy = field.clone();
+        field.resize(1).unwrap();
+        field.resize(2).unwrap();
+        assert_eq!(copy.npoints(), 99);
+        assert_eq!(copy.get_row::<u16>(1), Array1::from(vec![3, 4]));
+    }
+
+    #[test]
+    fn test_nan_mask () {
+        let field = FieldData::F32(Array2::from(vec![[1.0, 2.0], [f32::NAN, 0.0], [3.0, f32::NAN]]).into());
+        assert_eq!(field.nan_mask(), Array1::from(vec![false, true, true]));
+        let field = FieldData::I32(Array2::from(vec![[1], [2]]).into());
+        assert_eq!(field.nan_mask(), Array1::from(vec![false, false]));
+    }
+
+    #[test]
+    fn test_assign_from_interleaved_buffer() {
+        // Two records of [u8 tag, u16 x2], stride 5.
+        let buffer = [9u8, 1, 0, 2, 0, 8, 3, 0, 4, 1];
+        let mut field = FieldData::new(Dtype::U16, 2, 2);
+        field.assign_from_interleaved_buffer(&buffer, 5, 1);
+        assert_eq!(field.get_row::<u16>(0), Array1::from(vec![1, 2]));
+        assert_eq!(field.get_row::<u16>(1), Array1::from(vec![3, 260]));
+
+        let mut tags = FieldData::new(Dtype::U8, 2, 1);
+        tags.assign_from_interleaved_buffer(&buffer, 5, 0);
+        assert_eq!(tags.get_row::<u8>(1)[0], 8);
+    }
+}
@@ -0,0 +1,207 @@
+use std::ops::Index;
+use crate::fielddata::FieldData;
+
+/// The field data of a PointCloud, keyed by field name and kept in insertion order, which
+/// is the schema order for clouds built from metadata. Point clouds have few fields, so
+/// lookups scan the list. Lookups take any `&str`-like key, like `HashMap` lookups do.
+#[derive(Debug, Clone, Default)]
+pub struct FieldMap {
+    entries: Vec<(String, FieldData)>,
+}
+
+impl FieldMap {
+    /// Creates an empty `FieldMap`.
+    pub fn new() -> Self {
+        Self { entries: Vec::new() }
+    }
+
+    /// Creates an empty `FieldMap` with room for `capacity` fields.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self { entries: Vec::with_capacity(capacity) }
+    }
+
+    /// Returns the number of fields.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if there are no fields.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.entries.iter().position(|(n, _)| n == name)
+    }
+
+    /// Returns true if there is a field named `name`.
+    pub fn contains_key<Q: AsRef<str> + ?Sized>(&self, name: &Q) -> bool {
+        self.position(name.as_ref()).is_some()
+    }
+
+    /// Returns the data of the field `name`.
+    pub fn get<Q: AsRef<str> + ?Sized>(&self, name: &Q) -> Option<&FieldData> {
+        self.entries.iter().find(|(n, _)| n == name.as_ref()).map(|(_, data)| data)
+    }
+
+    /// Returns the data of the field `name` mutably.
+    pub fn get_mut<Q: AsRef<str> + ?Sized>(&mut self, name: &Q) -> Option<&mut FieldData> {
+        self.entries.iter_mut().find(|(n, _)| n == name.as_ref()).map(|(_, data)| data)
+    }
+
+    /// Returns the data of several distinct fields mutably at once, or None if a field is
+    /// missing or named twice.
+    pub fn get_disjoint_mut<const N: usize>(&mut self, names: [&str; N]) -> Option<[&mut FieldData; N]> {
+        let mut found: [Option<&mut FieldData>; N] = [const { None }; N];
+        for (name, data) in self.entries.iter_mut() {
+            if let Some(i) = names.iter().position(|n| n == name) {
+                found[i] = Some(data);
+            }
+        }
+        let found = found.into_iter().collect::<Option<Vec<_>>>()?;
+        found.try_into().ok()
+    }
+
+    /// Sets the data of the field `name`, returning the previous data. A replaced field
+    /// keeps its position, and a new field is added at the end.
+    pub fn insert(&mut self, name: String, data: FieldData) -> Option<FieldData> {
+        match self.position(&name) {
+            Some(idx) => Some(std::mem::replace(&mut self.entries[idx].1, data)),
+            None => {
+                self.entries.push((name, data));
+                None
+            }
+        }
+    }
+
+    /// Removes the field `name`, keeping the order of the other fields, and returns its data.
+    pub fn remove<Q: AsRef<str> + ?Sized>(&mut self, name: &Q) -> Option<FieldData> {
+        self.position(name.as_ref()).map(|idx| self.entries.remove(idx).1)
+    }
+
+    /// Renames the field `old` to `new` in place. Returns false if `old` is missing or
+    /// `new` already exists.
+    pub fn rename(&mut self, old: &str, new: &str) -> bool {
+        if self.contains_key(new) {
+            return false;
+        }
+        match self.position(old) {
+            Some(idx) => {
+                self.entries[idx].0 = new.to_string();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns an iterator over the field names, in order.
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.entries.iter().map(|(name, _)| name)
+    }
+
+    /// Returns an iterator over the field data, in order.
+    pub fn values(&self) -> impl Iterator<Item = &FieldData> {
+        self.entries.iter().map(|(_, data)| data)
+    }
+
+    /// Returns a mutable iterator over the field data, in order.
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut FieldData> {
+        self.entries.iter_mut().map(|(_, data)| data)
+    }
+
+    /// Returns an iterator over the (name, data) pairs, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &FieldData)> {
+        self.entries.iter().map(|(name, data)| (name, data))
+    }
+
+    /// Returns an iterator over the (name, data) pairs with mutable data, in order.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&String, &mut FieldData)> {
+        self.entries.iter_mut().map(|(name, data)| (&*name, data))
+    }
+}
+
+/// Field maps are equal if they hold the same fields, in any order.
+impl PartialEq for FieldMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(name, data)| other.get(name) == Some(data))
+    }
+}
+
+impl<Q: AsRef<str> + ?Sized> Index<&Q> for FieldMap {
+    type Output = FieldData;
+
+    fn index(&self, name: &Q) -> &Self::Output {
+        let name = name.as_ref();
+        self.get(name).unwrap_or_else(|| panic!("No field named '{}'", name))
+    }
+}
+
+impl Extend<(String, FieldData)> for FieldMap {
+    fn extend<I: IntoIterator<Item = (String, FieldData)>>(&mut self, iter: I) {
+        for (name, data) in iter {
+            self.insert(name, data);
+        }
+    }
+}
+
+impl FromIterator<(String, FieldData)> for FieldMap {
+    fn from_iter<I: IntoIterator<Item = (String, FieldData)>>(iter: I) -> Self {
+        let mut map = FieldMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl IntoIterator for FieldMap {
+    type Item = (String, FieldData);
+    type IntoIter = std::vec::IntoIter<(String, FieldData)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a FieldMap {
+    type Item = (&'a String, &'a FieldData);
+    type IntoIter = std::iter::Map<std::slice::Iter<'a, (String, FieldData)>, fn(&'a (String, FieldData)) -> (&'a String, &'a FieldData)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter().map(|(name, data)| (name, data))
+    }
+}
+
+impl<'a> IntoIterator for &'a mut FieldMap {
+    type Item = (&'a String, &'a mut FieldData);
+    type IntoIter = std::iter::Map<std::slice::IterMut<'a, (String, FieldData)>, fn(&'a mut (String, FieldData)) -> (&'a String, &'a mut FieldData)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter_mut().map(|(name, data)| (&*name, data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::Dtype;
+
+    #[test]
+    fn test_field_map_order() {
+        let mut map: FieldMap = ["z", "x", "y"].into_iter()
+            .map(|name| (name.to_string(), FieldData::new(Dtype::F32, 2, 1)))
+            .collect();
+        assert!(map.insert("x".to_string(), FieldData::new(Dtype::U8, 2, 1)).is_some());
+        assert!(map.rename("z", "w"));
+        assert!(!map.rename("x", "y"));
+        assert_eq!(map.keys().collect::<Vec<_>>(), ["w", "x", "y"]);
+        assert_eq!(map["x"].dtype(), Dtype::U8);
+
+        map.remove("w");
+        map.insert("z".to_string(), FieldData::new(Dtype::F32, 2, 1));
+        assert_eq!(map.keys().collect::<Vec<_>>(), ["x", "y", "z"]);
+        assert!(map.get_disjoint_mut(["x", "z"]).is_some());
+        assert!(map.get_disjoint_mut(["x", "x"]).is_none());
+
+        let reordered: FieldMap = map.clone().into_iter().rev().collect();
+        assert_eq!(reordered, map);
+    }
+}
@@ -0,0 +1,467 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use anyhow::Result;
+use ndarray::{ArcArray2, Axis, Zip};
+use num_traits::PrimInt;
+use rayon::prelude::*;
+use crate::fielddata::FieldData;
+use crate::pointcloud::PointCloud;
+use crate::transform::Matrix4;
+
+impl PointCloud {
+    /// Return a new PointCloud with the points inside the axis-aligned box `[min_bound, max_bound]`
+    /// (bounds inclusive), or outside it when `invert` is set. The box is tested against the
+    /// `names` coordinate fields, which may be of any dtype but must have a count of 1.
+    pub fn crop(&self, min_bound: [f64; 3], max_bound: [f64; 3], invert: bool, names: [&str; 3]) -> Result<Self> {
+        let mut columns = Vec::with_capacity(3);
+        for name in names {
+            let field = self.fields.get(name)
+                .ok_or_else(|| anyhow::anyhow!("Field '{}' not found", name))?;
+            anyhow::ensure!(field.count() == 1, "Field '{}' must have a count of 1", name);
+            columns.push(field.get_data::<f64>());
+        }
+
+        let mut mask = vec![false; self.len()];
+        Zip::from(&mut mask)
+            .and(columns[0].index_axis(Axis(1), 0))
+            .and(columns[1].index_axis(Axis(1), 0))
+            .and(columns[2].index_axis(Axis(1), 0))
+            .par_for_each(|m, &a, &b, &c| {
+                let inside = [a, b, c].iter().enumerate()
+                    .all(|(i, v)| *v >= min_bound[i] && *v <= max_bound[i]);
+                *m = inside != invert;
+            });
+        self.select_mask(&mask)
+    }
+
+    /// Return a new unorganized PointCloud with the points visible from a pinhole camera, and
+    /// their (u, v) pixel coordinates. `extrinsics` maps the `names` coordinates into the
+    /// camera frame (x right, y down, z forward), and `intrinsics` are `[fx, fy, cx, cy]`.
+    /// A point is visible if its camera depth is within `[near, far]` and positive, and it
+    /// projects inside the `[width, height]` image.
+    pub fn crop_frustum(&self, intrinsics: [f64; 4], extrinsics: &Matrix4, image_size: [usize; 2], near: f64, far: f64, names: [&str; 3]) -> Result<(Self, Vec<[f64; 2]>)> {
+        let [fx, fy, cx, cy] = intrinsics;
+        anyhow::ensure!(fx != 0.0 && fy != 0.0, "Focal lengths must be non-zero");
+        anyhow::ensure!(near <= far, "Near plane {} is beyond far plane {}", near, far);
+        let mut columns = Vec::with_capacity(3);
+        for name in names {
+            let field = self.fields.get(name)
+                .ok_or_else(|| anyhow::anyhow!("Field '{}' not found", name))?;
+            anyhow::ensure!(field.count() == 1, "Field '{}' must have a count of 1", name);
+            columns.push(field.get_data::<f64>());
+        }
+        let m = extrinsics;
+        let (width, height) = (image_size[0] as f64, image_size[1] as f64);
+        let pixels = Zip::from(columns[0].column(0))
+            .and(columns[1].column(0))
+            .and(columns[2].column(0))
+            .par_map_collect(|&x, &y, &z| {
+                let [cam_x, cam_y, cam_z] = [0, 1, 2].map(|i| m[i][0] * x + m[i][1] * y + m[i][2] * z + m[i][3]);
+                if !(cam_z > 0.0 && cam_z >= near && cam_z <= far) {
+                    return None;
+                }
+                let (u, v) = (fx * cam_x / cam_z + cx, fy * cam_y / cam_z + cy);
+                (u >= 0.0 && u < width && v >= 0.0 && v < height).then_some([u, v])
+            });
+        let (indices, pixels): (Vec<usize>, Vec<[f64; 2]>) = pixels.into_iter()
+            .enumerate()
+            .filter_map(|(i, pixel)| pixel.map(|pixel| (i, pixel)))
+            .unzip();
+        let pc = self.take_rows(&indices)?;
+        {
+            let mut md = pc.metadata.write().unwrap();
+            md.width = indices.len();
+            md.height = 1;
+        }
+        Ok((pc, pixels))
+    }
+
+    /// Return a mask that is true for each point with a NaN value in any of the `names` fields,
+    /// or in any field if `names` is None.
+    pub fn nan_mask(&self, names: Option<&[&str]>) -> Result<Vec<bool>> {
+        let fields = match names {
+            Some(names) => names.iter()
+                .map(|name| self.fields.get(*name)
+                    .ok_or_else(|| anyhow::anyhow!("Field '{}' not found", name)))
+                .collect::<Result<Vec<_>>>()?,
+            None => self.fields.values().collect(),
+        };
+        let mut mask = vec![false; self.len()];
+        for field in fields {
+            Zip::from(&mut mask)
+                .and(&field.nan_mask())
+                .for_each(|m, &nan| *m |= nan);
+        }
+        Ok(mask)
+    }
+
+    /// Return a new unorganized PointCloud without the points that have a NaN value in any of
+    /// the `names` fields (or in any field if `names` is None), together with the indices of
+    /// the retained points.
+    pub fn remove_nan_points(&self, names: Option<&[&str]>) -> Result<(Self, Vec<usize>)> {
+        let indices: Vec<usize> = self.nan_mask(names)?.iter()
+            .enumerate()
+            .filter_map(|(i, &nan)| (!nan).then_some(i))
+            .collect();
+        let pc = self.take_rows(&indices)?;
+        {
+            let mut md = pc.metadata.write().unwrap();
+            md.width = indices.len();
+            md.height = 1;
+        }
+        Ok((pc, indices))
+    }
+
+    /// Return a new unorganized PointCloud keeping only the first of each set of points with
+    /// duplicate `names` coordinates, together with the indices of the retained points.
+    /// With a `tolerance` of 0, duplicates have equal coordinates; otherwise a point is a
+    /// duplicate if it lies within `tolerance` of an earlier retained point, found by hashing
+    /// points into voxels of that size and checking the neighboring voxels. Points with a NaN
+    /// coordinate are always retained.
+    pub fn remove_duplicates(&self, tolerance: f64, names: [&str; 3]) -> Result<(Self, Vec<usize>)> {
+        anyhow::ensure!(tolerance >= 0.0 && tolerance.is_finite(), "Tolerance must be non-negative and finite, got {}", tolerance);
+        let points = self.coordinates(names)?;
+        let mut indices = Vec::with_capacity(points.len());
+        if tolerance == 0.0 {
+            // Adding 0.0 turns -0.0 into 0.0, so that they compare equal
+            let mut seen = HashSet::with_capacity(points.len());
+            for (i, p) in points.iter().enumerate() {
+                if p.iter().any(|v| v.is_nan()) || seen.insert(p.map(|v| (v + 0.0).to_bits())) {
+                    indices.push(i);
+                }
+            }
+        } else {
+            let voxel = |p: &[f64; 3]| p.map(|v| (v / tolerance).floor() as i64);
+            let mut voxels: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+            for (i, p) in points.iter().enumerate() {
+                if p.iter().any(|v| v.is_nan()) {
+                    indices.push(i);
+                    continue;
+                }
+                let [vx, vy, vz] = voxel(p);
+                let duplicate = (-1..=1).any(|dx| (-1..=1).any(|dy| (-1..=1).any(|dz| {
+                    voxels.get(&[vx + dx, vy + dy, vz + dz]).is_some_and(|kept| kept.iter().any(|&j| {
+                        (0..3).map(|k| (p[k] - points[j][k]).powi(2)).sum::<f64>() <= tolerance * tolerance
+                    }))
+                })));
+                if !duplicate {
+                    voxels.entry([vx, vy, vz]).or_default().push(i);
+                    indices.push(i);
+                }
+            }
+        }
+        let pc = self.take_rows(&indices)?;
+        {
+            let mut md = pc.metadata.write().unwrap();
+            md.width = indices.len();
+            md.height = 1;
+        }
+        Ok((pc, indices))
+    }
+
+    /// Split the PointCloud into one unorganized PointCloud per unique value of the integer
+    /// field `name` (which must have a count of 1), in ascending order of value.
+    /// Points are grouped in a single pass over the field.
+    pub fn split_by(&self, name: &str) -> Result<Vec<(i64, Self)>> {
+        let field = self.fields.get(name)
+            .ok_or_else(|| anyhow::anyhow!("Field '{}' not found", name))?;
+        anyhow::ensure!(field.count() == 1, "Field '{}' must have a count of 1", name);
+        let groups = match field {
+            FieldData::Bool(arr) | FieldData::U8(arr) => group_indices(arr, name)?,
+            FieldData::U16(arr) => group_indices(arr, name)?,
+            FieldData::U32(arr) => group_indices(arr, name)?,
+            FieldData::U64(arr) => group_indices(arr, name)?,
+            FieldData::I8(arr)  => group_indices(arr, name)?,
+            FieldData::I16(arr) => group_indices(arr, name)?,
+            FieldData::I32(arr) => group_indices(arr, name)?,
+            FieldData::I64(arr) => group_indices(arr, name)?,
+            FieldData::F16(_) | FieldData::F32(_) | FieldData::F64(_) => {
+                anyhow::bail!("Field '{}' must have an integer dtype", name)
+            }
+        };
+        groups.into_iter()
+            .map(|(value, indices)| {
+                let pc = self.take_rows(&indices)?;
+                {
+                    let mut md = pc.metadata.write().unwrap();
+                    md.width = indices.len();
+                    md.height = 1;
+                }
+                Ok((value, pc))
+            })
+            .collect()
+    }
+
+    /// Split the PointCloud into tiles of `cell_size[0]` by `cell_size[1]` along the `names`
+    /// x and y fields, returning one unorganized PointCloud per non-empty tile in ascending
+    /// order of its key `(floor((x - origin[0]) / cell_size[0]), floor((y - origin[1]) / cell_size[1]))`.
+    /// Keys depend only on the origin, so tiles of clouds split with the same origin line up.
+    /// Points with a NaN coordinate are left out.
+    pub fn tile(&self, cell_size: [f64; 2], origin: [f64; 2], names: [&str; 2]) -> Result<Vec<((i64, i64), Self)>> {
+        anyhow::ensure!(cell_size.iter().all(|&s| s > 0.0 && s.is_finite()), "Cell size must be positive and finite");
+        let [xs, ys] = self.planar_columns(names)?;
+        let keys = Zip::from(xs.column(0))
+            .and(ys.column(0))
+            .par_map_collect(|&x, &y| {
+                let key = |v: f64, i: usize| ((v - origin[i]) / cell_size[i]).floor() as i64;
+                (!x.is_nan() && !y.is_nan()).then(|| (key(x, 0), key(y, 1)))
+            });
+        self.group_cells(keys.iter())
+    }
+
+    /// Split the PointCloud into a `grid[0]` by `grid[1]` grid of equal cells spanning the
+    /// bounds of the `names` x and y fields, returning one unorganized PointCloud per
+    /// non-empty cell with its (column, row) index, in ascending order. Points on the upper
+    /// bounds fall in the last cells. Points with a NaN coordinate are left out.
+    pub fn split_spatial(&self, grid: [usize; 2], names: [&str; 2]) -> Result<Vec<((usize, usize), Self)>> {
+        anyhow::ensure!(grid.iter().all(|&n| n > 0), "Grid dimensions must be greater than 0");
+        let columns = self.planar_columns(names)?;
+        let bounds = columns.each_ref().map(|c| c.iter()
+            .filter(|v| !v.is_nan())
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| (lo.min(v), hi.max(v))));
+        let [xs, ys] = columns;
+        let keys = Zip::from(xs.column(0))
+            .and(ys.column(0))
+            .par_map_collect(|&x, &y| {
+                let index = |v: f64, i: usize| {
+                    let (lo, hi) = bounds[i];
+                    let cell = (hi - lo) / grid[i] as f64;
+                    if cell > 0.0 { (((v - lo) / cell) as i64).min(grid[i] as i64 - 1) } else { 0 }
+                };
+                (!x.is_nan() && !y.is_nan()).then(|| (index(x, 0), index(y, 1)))
+            });
+        Ok(self.group_cells(keys.iter())?
+            .into_iter()
+            .map(|((i, j), pc)| ((i as usize, j as usize), pc))
+            .collect())
+    }
+
+    /// Return the `names` x and y fields, which must have a count of 1, as f64 columns.
+    fn planar_columns(&self, names: [&str; 2]) -> Result<[ndarray::Array2<f64>; 2]> {
+        let column = |name: &str| {
+            let field = self.fields.get(name)
+                .ok_or_else(|| anyhow::anyhow!("Field '{}' not found", name))?;
+            anyhow::ensure!(field.count() == 1, "Field '{}' must have a count of 1", name);
+            Ok(field.get_data::<f64>())
+        };
+        Ok([column(names[0])?, column(names[1])?])
+    }
+
+    /// Group the points by cell key, in a single pass, and return one unorganized PointCloud
+    /// per key in ascending order. Points without a key are left out.
+    fn group_cells<'a>(&self, keys: impl Iterator<Item = &'a Option<(i64, i64)>>) -> Result<Vec<((i64, i64), Self)>> {
+        let mut groups: BTreeMap<(i64, i64), Vec<usize>> = BTreeMap::new();
+        for (i, key) in keys.enumerate() {
+            if let Some(key) = key {
+                groups.entry(*key).or_default().push(i);
+            }
+        }
+        groups.into_par_iter()
+            .map(|(key, indices)| {
+                let pc = self.take_rows(&indices)?;
+                {
+                    let mut md = pc.metadata.write().unwrap();
+                    md.width = indices.len();
+                    md.height = 1;
+                }
+                Ok((key, pc))
+            })
+            .collect()
+    }
+}
+
+/// Group the row indices of a single-column integer array by value.
+fn group_indices<T: PrimInt>(arr: &ArcArray2<T>, name: &str) -> Result<BTreeMap<i64, Vec<usize>>> {
+    let mut groups: BTreeMap<i64, Vec<usize>> = BTreeMap::new();
+    for (i, &v) in arr.column(0).iter().enumerate() {
+        let key = v.to_i64()
+            .ok_or_else(|| anyhow::anyhow!("Field '{}' value at point {} does not fit in an i64", name, i))?;
+        groups.entry(key).or_default().push(i);
+    }
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{Dtype, FieldSchema, Metadata};
+    use ndarray::Array1;
+
+    #[test]
+    fn test_crop() {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1), ("y", Dtype::F32, 1), ("z", Dtype::I16, 1)]),
+            width: 4,
+            height: 1,
+            npoints: 4,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        for i in 0..4 {
+            pc.fields.get_mut("x").unwrap().assign_row(i, &Array1::from(vec![i as f32]));
+        }
+
+        let inside = pc.crop([0.5, -1.0, -1.0], [2.0, 1.0, 1.0], false, ["x", "y", "z"]).unwrap();
+        assert_eq!(inside.len(), 2);
+        assert_eq!(inside.fields["x"].get_row::<f32>(0)[0], 1.0);
+
+        let outside = pc.crop([0.5, -1.0, -1.0], [2.0, 1.0, 1.0], true, ["x", "y", "z"]).unwrap();
+        assert_eq!(outside.len(), 2);
+        assert_eq!(outside.fields["x"].get_row::<f32>(1)[0], 3.0);
+
+        assert!(pc.crop([0.0; 3], [1.0; 3], false, ["x", "y", "w"]).is_err());
+    }
+
+    #[test]
+    fn test_remove_nan_points() {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1), ("i", Dtype::U8, 1)]),
+            width: 2,
+            height: 2,
+            npoints: 4,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        for (i, x) in [0.0, f32::NAN, 2.0, f32::NAN].into_iter().enumerate() {
+            pc.fields.get_mut("x").unwrap().assign_row(i, &Array1::from(vec![x]));
+            pc.fields.get_mut("i").unwrap().assign_row(i, &Array1::from(vec![i as u8]));
+        }
+
+        let (dense, indices) = pc.remove_nan_points(None).unwrap();
+        assert_eq!(indices, vec![0, 2]);
+        assert_eq!(dense.fields["i"].get_row::<u8>(1)[0], 2);
+        let md = dense.metadata.read().unwrap();
+        assert_eq!((md.width, md.height, md.npoints), (2, 1, 2));
+
+        assert_eq!(pc.remove_nan_points(Some(&["i"])).unwrap().1.len(), 4);
+        assert!(pc.nan_mask(Some(&["w"])).is_err());
+    }
+
+    #[test]
+    fn test_split_by() {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1), ("label", Dtype::I16, 1)]),
+            width: 5,
+            height: 1,
+            npoints: 5,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        for (i, label) in [3, -1, 3, 0, 3].into_iter().enumerate() {
+            pc.fields.get_mut("x").unwrap().assign_row(i, &Array1::from(vec![i as f32]));
+            pc.fields.get_mut("label").unwrap().assign_row(i, &Array1::from(vec![label as i16]));
+        }
+
+        let groups = pc.split_by("label").unwrap();
+        let values: Vec<i64> = groups.iter().map(|(v, _)| *v).collect();
+        assert_eq!(values, vec![-1, 0, 3]);
+        let (_, threes) = &groups[2];
+        assert_eq!(threes.len(), 3);
+        assert_eq!(threes.fields["x"].get_row::<f32>(1)[0], 2.0);
+        assert_eq!(threes.metadata.read().unwrap().width, 3);
+
+        assert!(pc.split_by("x").is_err());
+        assert!(pc.split_by("missing").is_err());
+    }
+
+    fn grid_cloud(points: &[(f32, f32)]) -> PointCloud {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1), ("y", Dtype::F32, 1), ("i", Dtype::U8, 1)]),
+            width: points.len(),
+            height: 1,
+            npoints: points.len(),
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        for (i, &(x, y)) in points.iter().enumerate() {
+            pc.fields.get_mut("x").unwrap().assign_row(i, &Array1::from(vec![x]));
+            pc.fields.get_mut("y").unwrap().assign_row(i, &Array1::from(vec![y]));
+            pc.fields.get_mut("i").unwrap().assign_row(i, &Array1::from(vec![i as u8]));
+        }
+        pc
+    }
+
+    #[test]
+    fn test_tile() {
+        let pc = grid_cloud(&[(0.5, 0.5), (-0.5, 0.5), (1.5, 3.0), (0.9, 0.1), (f32::NAN, 0.0)]);
+        let tiles = pc.tile([1.0, 2.0], [0.0, 0.0], ["x", "y"]).unwrap();
+        let keys: Vec<(i64, i64)> = tiles.iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys, vec![(-1, 0), (0, 0), (1, 1)]);
+        let (_, tile) = &tiles[1];
+        assert_eq!(tile.len(), 2);
+        assert_eq!(tile.fields["i"].get_row::<u8>(1)[0], 3);
+        assert_eq!(tile.metadata.read().unwrap().width, 2);
+
+        let shifted = pc.tile([1.0, 2.0], [0.6, 0.0], ["x", "y"]).unwrap();
+        let keys: Vec<(i64, i64)> = shifted.iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys, vec![(-2, 0), (-1, 0), (0, 0), (0, 1)]);
+
+        assert!(pc.tile([0.0, 1.0], [0.0, 0.0], ["x", "y"]).is_err());
+        assert!(pc.tile([1.0, 1.0], [0.0, 0.0], ["x", "w"]).is_err());
+    }
+
+    #[test]
+    fn test_split_spatial() {
+        let pc = grid_cloud(&[(0.0, 0.0), (4.0, 1.0), (1.9, 1.0), (2.0, 0.0)]);
+        let cells = pc.split_spatial([2, 1], ["x", "y"]).unwrap();
+        let keys: Vec<(usize, usize)> = cells.iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys, vec![(0, 0), (1, 0)]);
+        assert_eq!(cells[0].1.len(), 2);
+        assert_eq!(cells[1].1.fields["i"].get_row::<u8>(0)[0], 1);
+
+        // A flat extent puts every point in the first cell along that axis
+        let flat = grid_cloud(&[(1.0, 0.0), (1.0, 5.0)]).split_spatial([3, 2], ["x", "y"]).unwrap();
+        let keys: Vec<(usize, usize)> = flat.iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys, vec![(0, 0), (0, 1)]);
+
+        assert!(pc.split_spatial([0, 1], ["x", "y"]).is_err());
+    }
+
+    #[test]
+    fn test_crop_frustum() {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1), ("y", Dtype::F32, 1), ("z", Dtype::F32, 1)]),
+            width: 5,
+            height: 1,
+            npoints: 5,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        // In front, behind, beyond far, outside the image, in front after the translation
+        for (i, p) in [[0.0, 0.0, 2.0], [0.0, 0.0, -2.0], [0.0, 0.0, 20.0], [10.0, 0.0, 2.0], [1.0, 0.5, 1.0]].into_iter().enumerate() {
+            for (name, v) in ["x", "y", "z"].into_iter().zip(p) {
+                pc.fields.get_mut(name).unwrap().assign_row(i, &Array1::from(vec![v as f32]));
+            }
+        }
+        let mut extrinsics = crate::transform::identity();
+        extrinsics[2][3] = 1.0;
+        let (visible, pixels) = pc.crop_frustum([100.0, 100.0, 320.0, 240.0], &extrinsics, [640, 480], 0.1, 10.0, ["x", "y", "z"]).unwrap();
+        assert_eq!(visible.len(), 2);
+        assert_eq!(visible.metadata.read().unwrap().width, 2);
+        assert_eq!(visible.fields["x"].get_row::<f32>(1)[0], 1.0);
+        assert_eq!(pixels, vec![[320.0, 240.0], [370.0, 265.0]]);
+
+        assert!(pc.crop_frustum([0.0, 100.0, 0.0, 0.0], &extrinsics, [640, 480], 0.1, 10.0, ["x", "y", "z"]).is_err());
+        assert!(pc.crop_frustum([100.0; 4], &extrinsics, [640, 480], 1.0, 0.5, ["x", "y", "z"]).is_err());
+    }
+
+    #[test]
+    fn test_remove_duplicates() {
+        let points: [[f32; 2]; 6] = [[0.0, 0.0], [1.0, 0.0], [-0.0, 0.0], [f32::NAN, 0.0], [f32::NAN, 0.0], [1.05, 0.0]];
+        let pc = grid_cloud(&points.map(|[x, y]| (x, y)));
+        let mut pc3 = pc.copy();
+        pc3.insert_field("z", FieldData::new(Dtype::F32, points.len(), 1)).unwrap();
+
+        let (unique, indices) = pc3.remove_duplicates(0.0, ["x", "y", "z"]).unwrap();
+        assert_eq!(indices, vec![0, 1, 3, 4, 5]);
+        assert_eq!(unique.fields["i"].get_row::<u8>(4)[0], 5);
+        assert_eq!(unique.metadata.read().unwrap().width, 5);
+
+        let (_, indices) = pc3.remove_duplicates(0.1, ["x", "y", "z"]).unwrap();
+        assert_eq!(indices, vec![0, 1, 3, 4]);
+        let (_, indices) = pc3.remove_duplicates(0.01, ["x", "y", "z"]).unwrap();
+        assert_eq!(indices, vec![0, 1, 3, 4, 5]);
+
+        assert!(pc3.remove_duplicates(-1.0, ["x", "y", "z"]).is_err());
+        assert!(pc.remove_duplicates(0.0, ["x", "y", "z"]).is_err());
+    }
+}
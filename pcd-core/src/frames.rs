@@ -0,0 +1,212 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Cursor, Seek, SeekFrom, Write};
+use anyhow::Result;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use crate::error::{ErrorKind, PcdError};
+use crate::io::{self, ReadOptions, WriteOptions};
+use crate::pointcloud::PointCloud;
+
+/// Magic bytes ending a frame file.
+const MAGIC: &[u8; 8] = b"PCDFRAME";
+/// Length of the trailer at the end of a frame file: index offset, frame count and magic.
+const TRAILER_LEN: u64 = 24;
+/// Length of an index entry: offset, length and timestamp.
+const ENTRY_LEN: u64 = 24;
+
+/// The location and timestamp of one frame in a frame file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameEntry {
+    /// Offset of the frame's PCD data in the file.
+    pub offset: u64,
+    /// Length of the frame's PCD data.
+    pub len: u64,
+    /// Timestamp of the frame, e.g. in nanoseconds since the epoch.
+    pub timestamp: u64,
+}
+
+/// Writes a sequence of PointClouds (frames) to a single frame file, e.g. a lidar recording,
+/// instead of one file per cloud.
+///
+/// A frame file is the frames' PCD files concatenated, followed by an index of their offsets,
+/// lengths and timestamps (u64 little-endian each) and a trailer holding the offset of the
+/// index, the number of frames (u64 little-endian) and the magic bytes `PCDFRAME`. The first
+/// frame can therefore be read as a plain PCD file. The index is written by `finish`; a file
+/// that was not finished cannot be opened by `FrameReader`.
+pub struct FrameWriter {
+    file: BufWriter<File>,
+    options: WriteOptions,
+    entries: Vec<FrameEntry>,
+    position: u64,
+}
+
+impl FrameWriter {
+    /// Creates the frame file at `path`, writing every frame with `options`.
+    pub fn create(path: &str, options: &WriteOptions) -> Result<Self> {
+        let file = BufWriter::new(File::create(path)?);
+        Ok(Self { file, options: options.clone(), entries: Vec::new(), position: 0 })
+    }
+
+    /// Returns the number of frames written so far.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if no frame has been written.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Appends `pc` as a frame recorded at `timestamp`, which cannot be before the timestamp of
+    /// the previous frame.
+    pub fn write_frame(&mut self, pc: &PointCloud, timestamp: u64) -> Result<()> {
+        if let Some(last) = self.entries.last() {
+            anyhow::ensure!(timestamp >= last.timestamp,
+                "Frame timestamp {} is before the timestamp of the previous frame ({})", timestamp, last.timestamp);
+        }
+        let data = pc.to_pcd_bytes_with(&self.options)?;
+        self.file.write_all(&data)?;
+        self.entries.push(FrameEntry { offset: self.position, len: data.len() as u64, timestamp });
+        self.position += data.len() as u64;
+        Ok(())
+    }
+
+    /// Writes the index of the frames and the trailer, completing the file.
+    pub fn finish(mut self) -> Result<()> {
+        for entry in &self.entries {
+            self.file.write_u64::<LittleEndian>(entry.offset)?;
+            self.file.write_u64::<LittleEndian>(entry.len)?;
+            self.file.write_u64::<LittleEndian>(entry.timestamp)?;
+        }
+        self.file.write_u64::<LittleEndian>(self.position)?;
+        self.file.write_u64::<LittleEndian>(self.entries.len() as u64)?;
+        self.file.write_all(MAGIC)?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+/// Reads the frames of a frame file (see `FrameWriter`) in any order, by index or timestamp.
+pub struct FrameReader {
+    file: BufReader<File>,
+    entries: Vec<FrameEntry>,
+}
+
+impl FrameReader {
+    /// Opens a frame file and reads its index. Fails with a `Header` error if the file does
+    /// not end with a frame file trailer (e.g. it was not finished), and with a
+    /// `DataCorruption` error if the index does not fit the file.
+    pub fn open(path: &str) -> Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        let file_len = file.seek(SeekFrom::End(0))?;
+        let not_frame_file = || PcdError::new(ErrorKind::Header,
+            "Not a frame file, or an unfinished one: no frame index at the end of the file");
+        anyhow::ensure!(file_len >= TRAILER_LEN, not_frame_file());
+        let index_end = file_len - TRAILER_LEN;
+        file.seek(SeekFrom::Start(index_end))?;
+        let index_offset = file.read_u64::<LittleEndian>()?;
+        let nframes = file.read_u64::<LittleEndian>()?;
+        let mut magic = [0u8; 8];
+        std::io::Read::read_exact(&mut file, &mut magic)?;
+        anyhow::ensure!(&magic == MAGIC, not_frame_file());
+
+        let corrupted = || PcdError::new(ErrorKind::DataCorruption, "Frame index does not match the file size");
+        let index_len = nframes.checked_mul(ENTRY_LEN).ok_or_else(corrupted)?;
+        anyhow::ensure!(index_offset.checked_add(index_len) == Some(index_end), corrupted());
+        file.seek(SeekFrom::Start(index_offset))?;
+        let mut entries = Vec::with_capacity(nframes as usize);
+        for _ in 0..nframes {
+            let entry = FrameEntry {
+                offset: file.read_u64::<LittleEndian>()?,
+                len: file.read_u64::<LittleEndian>()?,
+                timestamp: file.read_u64::<LittleEndian>()?,
+            };
+            anyhow::ensure!(entry.offset.checked_add(entry.len).is_some_and(|end| end <= index_offset), corrupted());
+            entries.push(entry);
+        }
+        Ok(Self { file, entries })
+    }
+
+    /// Returns the number of frames.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the file holds no frames.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the location and timestamp of each frame, in order.
+    pub fn entries(&self) -> &[FrameEntry] {
+        &self.entries
+    }
+
+    /// Returns the index of the last frame recorded at or before `timestamp`, or None if
+    /// every frame is later.
+    pub fn frame_at(&self, timestamp: u64) -> Option<usize> {
+        self.entries.partition_point(|e| e.timestamp <= timestamp).checked_sub(1)
+    }
+
+    /// Reads the frame at `index` as `PointCloud::from_pcd_reader_with`.
+    pub fn read_frame(&mut self, index: usize, options: &ReadOptions) -> Result<(PointCloud, Vec<String>)> {
+        let entry = *self.entries.get(index)
+            .ok_or_else(|| anyhow::anyhow!("Frame index {} is out of range for {} frames", index, self.entries.len()))?;
+        self.file.seek(SeekFrom::Start(entry.offset))?;
+        let data = io::read_exact_chunk(&mut self.file, entry.len as usize)?;
+        PointCloud::from_pcd_reader_with(&mut Cursor::new(data), options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array1;
+    use crate::metadata::{Dtype, Encoding, FieldSchema, Metadata};
+
+    fn frame(npoints: usize, value: f32) -> PointCloud {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1)]),
+            width: npoints,
+            height: 1,
+            npoints,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        for i in 0..npoints {
+            pc.fields.get_mut("x").unwrap().assign_row(i, &Array1::from(vec![value + i as f32]));
+        }
+        pc
+    }
+
+    #[test]
+    fn test_frames() {
+        let path = std::env::temp_dir().join("pcdpy_test_frames.pcds");
+        let path = path.to_str().unwrap();
+        let options = WriteOptions { encoding: Some(Encoding::BinaryCompressed), ..WriteOptions::default() };
+        let mut writer = FrameWriter::create(path, &options).unwrap();
+        for (i, timestamp) in [100, 200, 200, 350].into_iter().enumerate() {
+            writer.write_frame(&frame(i + 1, i as f32 * 10.0), timestamp).unwrap();
+        }
+        assert!(writer.write_frame(&frame(1, 0.0), 300).is_err());
+        assert_eq!(writer.len(), 4);
+        writer.finish().unwrap();
+
+        let mut reader = FrameReader::open(path).unwrap();
+        assert_eq!(reader.len(), 4);
+        assert_eq!(reader.entries().iter().map(|e| e.timestamp).collect::<Vec<_>>(), [100, 200, 200, 350]);
+        for i in [3, 0, 2] {
+            let (pc, _) = reader.read_frame(i, &ReadOptions::default()).unwrap();
+            assert_eq!(pc.fields, frame(i + 1, i as f32 * 10.0).fields);
+        }
+        assert!(reader.read_frame(4, &ReadOptions::default()).is_err());
+        assert_eq!((reader.frame_at(99), reader.frame_at(100), reader.frame_at(250), reader.frame_at(1000)), (None, Some(0), Some(2), Some(3)));
+        // The first frame is a plain PCD file
+        assert_eq!(PointCloud::from_pcd_file(path).unwrap().fields, frame(1, 0.0).fields);
+
+        let data = std::fs::read(path).unwrap();
+        std::fs::write(path, &data[..data.len() - 1]).unwrap();
+        let err = FrameReader::open(path).err().unwrap();
+        assert_eq!(ErrorKind::of(&err), Some(ErrorKind::Header));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,744 @@
+use std::io::{BufRead, Read, Write};
+use anyhow::Result;
+use half::f16;
+use ndarray::Array1;
+use byteorder::{ReadBytesExt, LittleEndian, WriteBytesExt};
+use crate::metadata::Encoding;
+use crate::error::{ErrorKind, PcdError};
+
+/// Options controlling how PCD data is read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadOptions {
+    /// Raise an error on malformed ASCII values, rather than substituting NaN (or 0 for
+    /// integer fields) and returning a warning.
+    pub strict: bool,
+    /// Names of the fields to load, or None to load all fields. Other fields are skipped
+    /// without being decoded.
+    pub fields: Option<Vec<String>>,
+    /// Index of the first point to load.
+    pub start: usize,
+    /// Number of points to load, or None to load all points from `start`.
+    pub count: Option<usize>,
+    /// Verify the data against the checksum footer written with `WriteOptions::checksum`,
+    /// failing with a `DataCorruption` error if it does not match or is missing. The whole
+    /// data section is read to compute the checksum.
+    pub verify: bool,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        Self { strict: true, fields: None, start: 0, count: None, verify: false }
+    }
+}
+
+impl ReadOptions {
+    /// Returns the (start, count) range of points to load from data holding `npoints` points.
+    pub fn point_range(&self, npoints: usize) -> Result<(usize, usize)> {
+        anyhow::ensure!(self.start <= npoints, "Start point {} is out of range for {} points", self.start, npoints);
+        let count = self.count.unwrap_or(npoints - self.start);
+        anyhow::ensure!(count <= npoints - self.start,
+            "Cannot read {} points from point {}: the data has {} points", count, self.start, npoints);
+        Ok((self.start, count))
+    }
+}
+
+/// How floating point values are written in ASCII data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FloatFormat {
+    /// Shortest representation that reads back to the same value (as produced by Ryu), in
+    /// scientific notation for very small or large magnitudes.
+    #[default]
+    Shortest,
+    /// Fixed notation with the given number of digits after the decimal point.
+    Fixed(usize),
+    /// Scientific notation with the given number of digits after the decimal point.
+    Scientific(usize),
+}
+
+impl FloatFormat {
+    /// Append `v` formatted according to this format to `buf`.
+    pub fn write<T: ryu::Float + std::fmt::Display + std::fmt::LowerExp>(&self, buf: &mut Vec<u8>, v: T) {
+        match *self {
+            FloatFormat::Shortest => buf.extend_from_slice(ryu::Buffer::new().format(v).as_bytes()),
+            // Writing to a Vec cannot fail.
+            FloatFormat::Fixed(precision) => write!(buf, "{:.*}", precision, v).unwrap(),
+            FloatFormat::Scientific(precision) => write!(buf, "{:.*e}", precision, v).unwrap(),
+        }
+    }
+
+    /// Format `v` according to this format.
+    pub fn format<T: ryu::Float + std::fmt::Display + std::fmt::LowerExp>(&self, v: T) -> String {
+        let mut buf = Vec::new();
+        self.write(&mut buf, v);
+        String::from_utf8(buf).unwrap()
+    }
+}
+
+/// Options controlling how a PointCloud is written, overriding its metadata without
+/// modifying it.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct WriteOptions {
+    /// Encoding of the point data, or None to use the encoding in the metadata.
+    pub encoding: Option<Encoding>,
+    /// Header version (e.g. "0.6" for a legacy header), or None to use the metadata version.
+    pub version: Option<String>,
+    /// Formatting of floating point values in ASCII data.
+    pub float_format: FloatFormat,
+    /// zstd compression level for binary_zstd data, or None for the zstd default.
+    pub compression_level: Option<i32>,
+    /// Refuse to write encodings that PCL cannot read (the pcdpy extensions).
+    pub pcl_compatible: bool,
+    /// Write binary_compressed data larger than 4 GiB, which does not fit the format's u32
+    /// sizes, with the binary_compressed_large extension instead of failing.
+    pub large_compressed: bool,
+    /// Leave out padding fields (see `FieldMeta::is_padding`).
+    pub skip_padding: bool,
+    /// Append a CRC32 checksum of the data section after it, as a comment line
+    /// (see `verify_checksum`). PCL ignores the bytes following the data.
+    pub checksum: bool,
+}
+
+impl WriteOptions {
+    /// Return the metadata to write in the header: `md` with the overrides applied, and
+    /// the dtypes of the fields as stored (see `Dtype::storage`).
+    pub fn apply(&self, md: &crate::metadata::Metadata) -> crate::metadata::Metadata {
+        let mut md = md.clone();
+        md.fields.to_storage();
+        if let Some(encoding) = self.encoding {
+            md.encoding = encoding;
+        }
+        if let Some(version) = &self.version {
+            md.version = version.clone();
+        }
+        if self.large_compressed && md.encoding == Encoding::BinaryCompressed && md.data_size() > u32::MAX as usize {
+            md.encoding = Encoding::BinaryCompressedLarge;
+        }
+        md
+    }
+}
+
+/// Reads a non-empty, non-comment line from the given reader.
+/// Skips empty lines and lines starting with '#' and returns the first valid line.
+pub fn read_nonempty_line<R: BufRead>(reader: &mut R) -> Result<String> {
+    let mut line = String::new();
+    loop {
+        let bytes_read = reader.read_line(&mut line)?;
+        if bytes_read == 0 {
+            anyhow::bail!(PcdError::new(ErrorKind::DataCorruption, "Unexpected EOF while reading line"));
+        }
+        let trimmed = line.trim();
+        if !trimmed.is_empty() && !trimmed.starts_with('#') {
+            return Ok(trimmed.to_string());
+        }
+        line.clear();
+    }
+}
+
+/// Reads exactly `size` bytes from the reader and returns them as a Vec<u8>.
+/// Fails with a `DataCorruption` error if the reader ends first.
+pub fn read_exact_chunk<R: BufRead>(reader: &mut R, size: usize) -> Result<Vec<u8>> {
+    let mut buffer = vec![0u8; size];
+    reader.read_exact(&mut buffer).map_err(|e| -> anyhow::Error {
+        match e.kind() {
+            std::io::ErrorKind::UnexpectedEof => PcdError::new(ErrorKind::DataCorruption,
+                format!("Data is truncated: expected {} more bytes", size)).into(),
+            _ => e.into(),
+        }
+    })?;
+    Ok(buffer)
+}
+
+/// Start of the checksum footer line, followed by the CRC32 of the data section as 8
+/// lowercase hex digits and a newline.
+const CHECKSUM_PREFIX: &[u8] = b"# CRC32 ";
+/// Length of the checksum footer line.
+pub const CHECKSUM_FOOTER_LEN: usize = CHECKSUM_PREFIX.len() + 9;
+
+/// A writer computing the CRC32 of the bytes written through it.
+pub struct ChecksumWriter<W: Write> {
+    inner: W,
+    hasher: crc32fast::Hasher,
+}
+
+impl<W: Write> ChecksumWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner, hasher: crc32fast::Hasher::new() }
+    }
+
+    /// Writes the checksum footer of the bytes written so far to the inner writer.
+    pub fn write_footer(self) -> Result<()> {
+        let Self { mut inner, hasher } = self;
+        write_checksum_footer(&mut inner, hasher.finalize())
+    }
+}
+
+/// Writes the checksum footer line for data whose CRC32 is `crc`.
+pub fn write_checksum_footer<W: Write>(writer: &mut W, crc: u32) -> Result<()> {
+    writer.write_all(CHECKSUM_PREFIX)?;
+    writeln!(writer, "{:08x}", crc)?;
+    Ok(())
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Returns the start of the checksum footer ending `body`, if it has one.
+fn checksum_footer_start(body: &[u8]) -> Option<usize> {
+    body.len().checked_sub(CHECKSUM_FOOTER_LEN)
+        .filter(|&start| body[start..].starts_with(CHECKSUM_PREFIX) && body.ends_with(b"\n"))
+}
+
+/// Returns true if `body` (everything after the header) ends with a checksum footer line,
+/// whether or not it matches the data.
+pub fn has_checksum_footer(body: &[u8]) -> bool {
+    checksum_footer_start(body).is_some()
+}
+
+/// Checks the data section in `body` (everything after the header) against the checksum
+/// footer ending it, and returns the data without the footer. Fails with a `DataCorruption`
+/// error if the footer is missing (e.g. the file is truncated) or does not match.
+pub fn verify_checksum(body: &[u8]) -> Result<&[u8]> {
+    let footer = checksum_footer_start(body).map(|start| (start, &body[start..]));
+    let Some((data_len, footer)) = footer else {
+        anyhow::bail!(PcdError::new(ErrorKind::DataCorruption, "No CRC32 checksum footer after the data (truncated, or written without a checksum)"));
+    };
+    let expected = std::str::from_utf8(&footer[CHECKSUM_PREFIX.len()..CHECKSUM_FOOTER_LEN - 1]).ok()
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+        .ok_or_else(|| PcdError::new(ErrorKind::DataCorruption, "Invalid CRC32 checksum footer"))?;
+    let data = &body[..data_len];
+    let actual = crc32fast::hash(data);
+    anyhow::ensure!(actual == expected, PcdError::new(ErrorKind::DataCorruption,
+        format!("Checksum mismatch: the data has CRC32 {:08x}, the footer records {:08x}", actual, expected)));
+    Ok(data)
+}
+
+/// Largest block compressed in one piece by LZF, whose lengths are 32-bit.
+pub const LZF_BLOCK_SIZE: usize = 1 << 30;
+
+/// Largest ratio of uncompressed to compressed size accepted for a block whose uncompressed
+/// size is not known in advance, well above what LZF (about 90) and LZ4 (about 255) reach.
+pub(crate) const MAX_COMPRESSION_RATIO: u64 = 1024;
+
+/// Reads a compressed block from the reader and returns the decompressed data.
+///
+/// binary_compressed blocks (LZF) start with their compressed and uncompressed sizes as u32,
+/// as written by PCL. The binary_zstd, binary_lz4 and binary_compressed_large extensions
+/// store the sizes as u64. The uncompressed size must be `expected_size` (the data size of the
+/// header) if given, or else at most `MAX_COMPRESSION_RATIO` times the compressed size, so
+/// that corrupt sizes fail with a `DataCorruption` error before anything is allocated.
+pub fn read_compressed_buffer<R: BufRead>(reader: &mut R, encoding: Encoding, expected_size: Option<usize>) -> Result<Vec<u8>> {
+    let (compressed_size, uncompressed_size) = if encoding == Encoding::BinaryCompressed {
+        (reader.read_u32::<LittleEndian>()? as u64, reader.read_u32::<LittleEndian>()? as u64)
+    } else {
+        (reader.read_u64::<LittleEndian>()?, reader.read_u64::<LittleEndian>()?)
+    };
+    match expected_size {
+        Some(expected) => anyhow::ensure!(uncompressed_size == expected as u64, PcdError::new(ErrorKind::DataCorruption,
+            format!("Compressed block holds {} bytes, but the header describes {} bytes of points", uncompressed_size, expected))),
+        None => anyhow::ensure!(uncompressed_size <= compressed_size.saturating_mul(MAX_COMPRESSION_RATIO), PcdError::new(ErrorKind::DataCorruption,
+            format!("Compressed block of {} bytes cannot hold {} bytes", compressed_size, uncompressed_size))),
+    }
+    // Read through `take` so that the buffer is bounded by the actual input
+    let mut compressed_buf = Vec::new();
+    reader.by_ref().take(compressed_size).read_to_end(&mut compressed_buf)?;
+    anyhow::ensure!(compressed_buf.len() as u64 == compressed_size, PcdError::new(ErrorKind::DataCorruption,
+        format!("Data is truncated: expected {} bytes of compressed data, got {}", compressed_size, compressed_buf.len())));
+    let uncompressed_size = uncompressed_size as usize;
+    let uncompressed_buf = decompress(encoding, &compressed_buf, uncompressed_size)
+        .map_err(|e| match ErrorKind::of(&e) {
+            Some(_) => e,
+            None => PcdError::new(ErrorKind::DataCorruption, format!("Cannot decompress {} data: {}", encoding.as_str(), e)).into(),
+        })?;
+    anyhow::ensure!(uncompressed_buf.len() == uncompressed_size, PcdError::new(ErrorKind::DataCorruption,
+        format!("Decompressed {} bytes, expected {}", uncompressed_buf.len(), uncompressed_size)));
+    Ok(uncompressed_buf)
+}
+
+/// Decompresses a block of `encoding` data holding `uncompressed_size` bytes.
+fn decompress(encoding: Encoding, data: &[u8], uncompressed_size: usize) -> Result<Vec<u8>> {
+    match encoding {
+        Encoding::BinaryCompressed => lzf::decompress(data, uncompressed_size)
+            .map_err(|e| anyhow::anyhow!(e)),
+        Encoding::BinaryCompressedLarge => decompress_lzf_blocks(data, uncompressed_size),
+        #[cfg(feature = "zstd")]
+        Encoding::BinaryZstd => Ok(zstd::bulk::decompress(data, uncompressed_size)?),
+        #[cfg(feature = "lz4")]
+        Encoding::BinaryLz4 => lz4_flex::block::decompress(data, uncompressed_size)
+            .map_err(|e| anyhow::anyhow!(e)),
+        _ => Err(unsupported_codec(encoding)),
+    }
+}
+
+/// Compresses a block of data for `encoding`. `level` is used by zstd only.
+#[cfg_attr(not(feature = "zstd"), allow(unused_variables))]
+fn compress(encoding: Encoding, data: &[u8], level: Option<i32>) -> Result<Vec<u8>> {
+    match encoding {
+        Encoding::BinaryCompressed => lzf::compress(data)
+            .map_err(|_| anyhow::anyhow!("Compression failed")),
+        Encoding::BinaryCompressedLarge => compress_lzf_blocks(data, LZF_BLOCK_SIZE),
+        #[cfg(feature = "zstd")]
+        Encoding::BinaryZstd => Ok(zstd::bulk::compress(data, level.unwrap_or(zstd::DEFAULT_COMPRESSION_LEVEL))?),
+        #[cfg(feature = "lz4")]
+        Encoding::BinaryLz4 => Ok(lz4_flex::block::compress(data)),
+        _ => Err(unsupported_codec(encoding)),
+    }
+}
+
+/// Compresses `data` as a sequence of LZF blocks of at most `block_size` uncompressed bytes,
+/// each preceded by its compressed and uncompressed sizes as u32 (binary_compressed_large).
+pub fn compress_lzf_blocks(data: &[u8], block_size: usize) -> Result<Vec<u8>> {
+    anyhow::ensure!(block_size > 0 && block_size <= u32::MAX as usize, "Invalid LZF block size {}", block_size);
+    let mut out = Vec::new();
+    for block in data.chunks(block_size) {
+        let compressed = lzf::compress(block)
+            .map_err(|_| anyhow::anyhow!("Compression failed"))?;
+        out.write_u32::<LittleEndian>(compressed.len() as u32)?;
+        out.write_u32::<LittleEndian>(block.len() as u32)?;
+        out.extend_from_slice(&compressed);
+    }
+    Ok(out)
+}
+
+/// Decompresses a sequence of LZF blocks written by `compress_lzf_blocks`.
+pub fn decompress_lzf_blocks(mut data: &[u8], uncompressed_size: usize) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(uncompressed_size);
+    while !data.is_empty() {
+        let compressed_size = data.read_u32::<LittleEndian>()? as usize;
+        let block_size = data.read_u32::<LittleEndian>()? as usize;
+        anyhow::ensure!(compressed_size <= data.len(), PcdError::new(ErrorKind::DataCorruption, "Truncated LZF block"));
+        anyhow::ensure!(block_size <= uncompressed_size - out.len(), PcdError::new(ErrorKind::DataCorruption,
+            format!("LZF blocks hold more than {} bytes", uncompressed_size)));
+        let (block, rest) = data.split_at(compressed_size);
+        out.extend(lzf::decompress(block, block_size).map_err(|e| anyhow::anyhow!(e))?);
+        data = rest;
+    }
+    Ok(out)
+}
+
+/// Error for a compressed encoding whose codec was not enabled at build time.
+fn unsupported_codec(encoding: Encoding) -> anyhow::Error {
+    let feature = match encoding {
+        Encoding::BinaryZstd => "zstd",
+        Encoding::BinaryLz4 => "lz4",
+        _ => return anyhow::anyhow!("Encoding {} is not compressed", encoding.as_str()),
+    };
+    anyhow::anyhow!("Encoding {} requires pcdpy to be built with the \"{}\" feature", encoding.as_str(), feature)
+}
+
+/// Parses the ASCII tokens of one field on one data line.
+/// In strict mode a malformed token is an error; otherwise it is replaced by NaN
+/// (or 0 for integer types) and a warning is pushed to `warnings`.
+fn parse_ascii_values<T>(
+    tokens: &[&str],
+    field_meta: &crate::metadata::FieldMeta,
+    line_no: usize,
+    strict: bool,
+    warnings: &mut Vec<String>,
+) -> Result<Array1<T>>
+where
+    T: std::str::FromStr + num_traits::NumCast + Default,
+{
+    tokens.iter()
+        .map(|token| match token.parse::<T>() {
+            Ok(value) => Ok(value),
+            Err(_) => {
+                let msg = format!(
+                    "Data line {}: invalid value '{}' for field '{}' ({})",
+                    line_no, token, field_meta.name, field_meta.dtype
+                );
+                if strict {
+                    anyhow::bail!(PcdError::new(ErrorKind::DataCorruption, msg));
+                }
+                warnings.push(msg);
+                Ok(num_traits::NumCast::from(f64::NAN).unwrap_or_default())
+            }
+        })
+        .collect::<Result<Vec<T>>>()
+        .map(Array1::from)
+}
+
+/// Reads point data in ASCII format into the fields of the given PointCloud.
+/// Expects one non-empty line per point with whitespace-separated values in metadata field order.
+/// Values of metadata fields missing from `pc.fields` are skipped without being parsed.
+///
+/// Errors report the data line number (counted from the line after the header), the field
+/// name, and the offending token. When `strict` is false, malformed values are replaced by
+/// NaN (or 0 for integer fields) and one warning per substitution is returned instead.
+pub fn read_ascii_data<R: BufRead>(reader: &mut R, pc: &mut crate::pointcloud::PointCloud, strict: bool) -> Result<Vec<String>> {
+    use crate::metadata::Dtype;
+    let md = pc.metadata.read().unwrap();
+    let expected_num_values: usize = md.fields.iter().map(|f| f.count).sum();
+    let mut warnings = Vec::new();
+    let mut line = String::new();
+    let mut line_no = 0;
+    // For each point, read a non-empty line.
+    for row_idx in 0..md.npoints {
+        loop {
+            line.clear();
+            line_no += 1;
+            if reader.read_line(&mut line)? == 0 {
+                anyhow::bail!(PcdError::new(ErrorKind::DataCorruption,
+                    format!("Unexpected EOF at data line {}: expected {} points, got {}", line_no, md.npoints, row_idx)));
+            }
+            let trimmed = line.trim();
+            if !trimmed.is_empty() && !trimmed.starts_with('#') {
+                break;
+            }
+        }
+        let values: Vec<&str> = line.split_ascii_whitespace().collect();
+        if values.len() != expected_num_values {
+            anyhow::bail!(PcdError::new(ErrorKind::DataCorruption,
+                format!("Invalid data line {}: expected {} values, got {}", line_no, expected_num_values, values.len())));
+        }
+        let mut offset = 0;
+        for field_meta in md.fields.iter() {
+            let tokens = &values[offset..offset + field_meta.count];
+            offset += field_meta.count;
+            let Some(field) = pc.fields.get_mut(&field_meta.name) else {
+                continue;
+            };
+            match field_meta.dtype {
+                Dtype::Bool | Dtype::U8 => field.assign_row(row_idx, &parse_ascii_values::<u8>(tokens, field_meta, line_no, strict, &mut warnings)?),
+                Dtype::U16 => field.assign_row(row_idx, &parse_ascii_values::<u16>(tokens, field_meta, line_no, strict, &mut warnings)?),
+                Dtype::U32 => field.assign_row(row_idx, &parse_ascii_values::<u32>(tokens, field_meta, line_no, strict, &mut warnings)?),
+                Dtype::U64 => field.assign_row(row_idx, &parse_ascii_values::<u64>(tokens, field_meta, line_no, strict, &mut warnings)?),
+                Dtype::I8 => field.assign_row(row_idx, &parse_ascii_values::<i8>(tokens, field_meta, line_no, strict, &mut warnings)?),
+                Dtype::I16 => field.assign_row(row_idx, &parse_ascii_values::<i16>(tokens, field_meta, line_no, strict, &mut warnings)?),
+                Dtype::I32 => field.assign_row(row_idx, &parse_ascii_values::<i32>(tokens, field_meta, line_no, strict, &mut warnings)?),
+                Dtype::I64 => field.assign_row(row_idx, &parse_ascii_values::<i64>(tokens, field_meta, line_no, strict, &mut warnings)?),
+                Dtype::F16 => field.assign_row(row_idx, &parse_ascii_values::<f16>(tokens, field_meta, line_no, strict, &mut warnings)?),
+                Dtype::F32 => field.assign_row(row_idx, &parse_ascii_values::<f32>(tokens, field_meta, line_no, strict, &mut warnings)?),
+                Dtype::F64 => field.assign_row(row_idx, &parse_ascii_values::<f64>(tokens, field_meta, line_no, strict, &mut warnings)?),
+            }
+        }
+    }
+    Ok(warnings)
+}
+
+/// Reads point data in binary format into the fields of the given PointCloud.
+/// Each point is stored as a contiguous block of little-endian values in metadata field order.
+/// Metadata fields missing from `pc.fields` are skipped.
+pub fn read_binary_data<R: BufRead>(reader: &mut R, pc: &mut crate::pointcloud::PointCloud) -> Result<()> {
+    let md = pc.metadata.read().unwrap();
+    let row_stride: usize = md.fields.iter().map(|f| f.dtype.get_size() * f.count).sum();
+    let data_buffer = read_exact_chunk(reader, row_stride * md.npoints)?;
+    let mut offset = 0;
+    for field_meta in md.fields.iter() {
+        if let Some(field) = pc.fields.get_mut(&field_meta.name) {
+            field.assign_from_interleaved_buffer(&data_buffer, row_stride, offset);
+        }
+        offset += field_meta.dtype.get_size() * field_meta.count;
+    }
+    Ok(())
+}
+
+/// Assigns points `start..start + n` of decompressed data in a compressed encoding
+/// (binary_compressed, or the binary_zstd, binary_lz4 and binary_compressed_large extensions)
+/// to `fields`. The decompressed buffer stores each field's values contiguously, in the field
+/// order of `md`, which describes the whole buffer. Fields of `md` missing from `fields` are
+/// skipped.
+pub fn assign_compressed_rows(
+    buffer: &[u8],
+    md: &crate::metadata::Metadata,
+    fields: &mut crate::fieldmap::FieldMap,
+    start: usize,
+    n: usize,
+) -> Result<()> {
+    anyhow::ensure!(buffer.len() == md.data_size(), PcdError::new(ErrorKind::DataCorruption,
+        format!("Compressed data holds {} bytes, expected {}", buffer.len(), md.data_size())));
+    anyhow::ensure!(start + n <= md.npoints, "Points {}..{} out of range for {} points", start, start + n, md.npoints);
+    let mut offset = 0;
+    for field_meta in md.fields.iter() {
+        let row_bytes = field_meta.count * field_meta.dtype.get_size();
+        if let Some(field) = fields.get_mut(&field_meta.name) {
+            let begin = offset + start * row_bytes;
+            field.assign_from_buffer(&buffer[begin..begin + n * row_bytes]);
+        }
+        offset += row_bytes * md.npoints;
+    }
+    Ok(())
+}
+
+/// Writes the PCD header to the provided writer using metadata.
+/// The header layout follows `md.version`, after the custom entries of `md` and the units,
+/// descriptions and semantic tags of its fields as comment lines.
+pub fn write_header<W: Write>(writer: &mut W, md: &crate::metadata::Metadata) -> Result<()> {
+    let info = md.fields.iter().flat_map(|f| f.info_entries());
+    for (key, value) in md.custom.iter().cloned().chain(info) {
+        crate::metadata::check_custom_entry(&key, &value)?;
+        writeln!(writer, "# {}: {}", key, value)?;
+    }
+
+    // Build header fields
+    writeln!(writer, "VERSION {}", md.version)?;
+    
+    // Fields, SIZE, TYPE, and COUNT are based on md.fields.
+    let field_names: Vec<&str> = md.fields.iter().map(|f| f.header_name()).collect();
+    let sizes: Vec<String> = md.fields.iter().map(|f| f.dtype.get_size().to_string()).collect();
+    let types: Vec<String> = md.fields.iter().map(|f| f.dtype.get_type().to_string()).collect();
+    let counts: Vec<String> = md.fields.iter().map(|f| f.count.to_string()).collect();
+    
+    writeln!(writer, "FIELDS {}", field_names.join(" "))?;
+    writeln!(writer, "SIZE {}", sizes.join(" "))?;
+    writeln!(writer, "TYPE {}", types.join(" "))?;
+    writeln!(writer, "COUNT {}", counts.join(" "))?;
+    
+    writeln!(writer, "WIDTH {}", md.width)?;
+    writeln!(writer, "HEIGHT {}", md.height)?;
+    // Write viewpoint as 7 floats. PCD 0.6 headers have no VIEWPOINT line.
+    if !md.is_legacy() {
+        writeln!(writer, "VIEWPOINT {} {} {} {} {} {} {}",
+                 md.viewpoint.tx, md.viewpoint.ty, md.viewpoint.tz,
+                 md.viewpoint.qw, md.viewpoint.qx, md.viewpoint.qy, md.viewpoint.qz)?;
+    }
+    writeln!(writer, "POINTS {}", md.npoints)?;
+    
+    // DATA: Write the encoding string (all lowercase)
+    writeln!(writer, "DATA {}", md.encoding.as_str())?;
+    Ok(())
+}
+
+/// Size of the buffer in which ASCII data is formatted before it is written.
+const ASCII_BUFFER_SIZE: usize = 1 << 16;
+
+/// A value that can be formatted into ASCII data without intermediate allocations.
+trait AsciiValue: Copy {
+    fn write_ascii(self, buf: &mut Vec<u8>, float_format: FloatFormat);
+}
+
+macro_rules! impl_ascii_integer {
+    ($($t:ty),*) => {
+        $(impl AsciiValue for $t {
+            fn write_ascii(self, buf: &mut Vec<u8>, _: FloatFormat) {
+                buf.extend_from_slice(itoa::Buffer::new().format(self).as_bytes());
+            }
+        })*
+    };
+}
+impl_ascii_integer!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+impl AsciiValue for f16 {
+    fn write_ascii(self, buf: &mut Vec<u8>, float_format: FloatFormat) {
+        float_format.write(buf, self.to_f32());
+    }
+}
+
+impl AsciiValue for f32 {
+    fn write_ascii(self, buf: &mut Vec<u8>, float_format: FloatFormat) {
+        float_format.write(buf, self);
+    }
+}
+
+impl AsciiValue for f64 {
+    fn write_ascii(self, buf: &mut Vec<u8>, float_format: FloatFormat) {
+        float_format.write(buf, self);
+    }
+}
+
+/// Appends the values of row `row_idx` of `arr` to `buf`, each followed by `separator`.
+fn write_ascii_row<T: AsciiValue>(buf: &mut Vec<u8>, arr: &ndarray::ArcArray2<T>, row_idx: usize, float_format: FloatFormat, separator: u8) {
+    for &v in arr.row(row_idx) {
+        v.write_ascii(buf, float_format);
+        buf.push(separator);
+    }
+}
+
+/// Writes the point cloud data in ASCII format.
+/// For each point, writes one line with the values for each field separated by a space.
+/// Floating point values are formatted according to `options.float_format`.
+pub fn write_ascii_data<W: Write>(writer: &mut W, pc: &crate::pointcloud::PointCloud, options: &WriteOptions) -> Result<()> {
+    write_delimited_data(writer, pc, options.float_format, b' ')
+}
+
+/// Writes one line per point with the values of each field separated by `separator`, with
+/// floating point values formatted according to `float_format` and booleans written as 0 or 1.
+///
+/// Lines are formatted directly from the field arrays into a single reused buffer, which is
+/// flushed to the writer whenever it exceeds `ASCII_BUFFER_SIZE`.
+pub fn write_delimited_data<W: Write>(writer: &mut W, pc: &crate::pointcloud::PointCloud, float_format: FloatFormat, separator: u8) -> Result<()> {
+    use crate::fielddata::FieldData;
+    let md = pc.metadata.read().unwrap();
+    // Fields in metadata order.
+    let fields: Vec<&FieldData> = md.fields.iter().map(|f| &pc.fields[&f.name]).collect();
+    let mut buf = Vec::with_capacity(2 * ASCII_BUFFER_SIZE);
+    for row_idx in 0..md.npoints {
+        for field in fields.iter() {
+            match field {
+                FieldData::Bool(arr) | FieldData::U8(arr) => write_ascii_row(&mut buf, arr, row_idx, float_format, separator),
+                FieldData::U16(arr) => write_ascii_row(&mut buf, arr, row_idx, float_format, separator),
+                FieldData::U32(arr) => write_ascii_row(&mut buf, arr, row_idx, float_format, separator),
+                FieldData::U64(arr) => write_ascii_row(&mut buf, arr, row_idx, float_format, separator),
+                FieldData::I8(arr)  => write_ascii_row(&mut buf, arr, row_idx, float_format, separator),
+                FieldData::I16(arr) => write_ascii_row(&mut buf, arr, row_idx, float_format, separator),
+                FieldData::I32(arr) => write_ascii_row(&mut buf, arr, row_idx, float_format, separator),
+                FieldData::I64(arr) => write_ascii_row(&mut buf, arr, row_idx, float_format, separator),
+                FieldData::F16(arr) => write_ascii_row(&mut buf, arr, row_idx, float_format, separator),
+                FieldData::F32(arr) => write_ascii_row(&mut buf, arr, row_idx, float_format, separator),
+                FieldData::F64(arr) => write_ascii_row(&mut buf, arr, row_idx, float_format, separator),
+            }
+        }
+        // Replace the trailing separator with the line end.
+        match buf.last_mut() {
+            Some(last) if *last == separator => *last = b'\n',
+            _ => buf.push(b'\n'),
+        }
+        if buf.len() >= ASCII_BUFFER_SIZE {
+            writer.write_all(&buf)?;
+            buf.clear();
+        }
+    }
+    writer.write_all(&buf)?;
+    Ok(())
+}
+
+/// Writes the point cloud data in binary format.
+/// For each point (row), writes a contiguous block of bytes (the sum over fields of (dtype size * count))
+/// with little-endian encoding.
+pub fn write_binary_data<W: Write>(writer: &mut W, pc: &crate::pointcloud::PointCloud) -> Result<()> {
+    let md = pc.metadata.read().unwrap();
+    // Total number of bytes per point.
+    let total_size: usize = md.fields.iter().map(|f| f.dtype.get_size() * f.count).sum();
+    for row_idx in 0..md.npoints {
+        let mut row_buffer = vec![0u8; total_size];
+        let mut offset = 0;
+        // Iterate over fields in metadata order.
+        for field_meta in md.fields.iter() {
+            let field = pc.fields.get(&field_meta.name).unwrap();
+            // For each field, match on dtype and write the row's bytes in little-endian order.
+            match field_meta.dtype {
+                crate::metadata::Dtype::Bool | crate::metadata::Dtype::U8 => {
+                    let row = field.get_row::<u8>(row_idx);
+                    for &val in row.iter() {
+                        row_buffer[offset] = val;
+                        offset += 1;
+                    }
+                }
+                crate::metadata::Dtype::U16 => {
+                    let row = field.get_row::<u16>(row_idx);
+                    for &val in row.iter() {
+                        row_buffer[offset..offset+2].copy_from_slice(&val.to_le_bytes());
+                        offset += 2;
+                    }
+                }
+                crate::metadata::Dtype::U32 => {
+                    let row = field.get_row::<u32>(row_idx);
+                    for &val in row.iter() {
+                        row_buffer[offset..offset+4].copy_from_slice(&val.to_le_bytes());
+                        offset += 4;
+                    }
+                }
+                crate::metadata::Dtype::U64 => {
+                    let row = field.get_row::<u64>(row_idx);
+                    for &val in row.iter() {
+                        row_buffer[offset..offset+8].copy_from_slice(&val.to_le_bytes());
+                        offset += 8;
+                    }
+                }
+                crate::metadata::Dtype::I8 => {
+                    let row = field.get_row::<i8>(row_idx);
+                    for &val in row.iter() {
+                        row_buffer[offset] = val as u8;
+                        offset += 1;
+                    }
+                }
+                crate::metadata::Dtype::I16 => {
+                    let row = field.get_row::<i16>(row_idx);
+                    for &val in row.iter() {
+                        row_buffer[offset..offset+2].copy_from_slice(&val.to_le_bytes());
+                        offset += 2;
+                    }
+                }
+                crate::metadata::Dtype::I32 => {
+                    let row = field.get_row::<i32>(row_idx);
+                    for &val in row.iter() {
+                        row_buffer[offset..offset+4].copy_from_slice(&val.to_le_bytes());
+                        offset += 4;
+                    }
+                }
+                crate::metadata::Dtype::I64 => {
+                    let row = field.get_row::<i64>(row_idx);
+                    for &val in row.iter() {
+                        row_buffer[offset..offset+8].copy_from_slice(&val.to_le_bytes());
+                        offset += 8;
+                    }
+                }
+                crate::metadata::Dtype::F16 => {
+                    let row = field.get_row::<f16>(row_idx);
+                    for &val in row.iter() {
+                        row_buffer[offset..offset+2].copy_from_slice(&val.to_le_bytes());
+                        offset += 2;
+                    }
+                }
+                crate::metadata::Dtype::F32 => {
+                    let row = field.get_row::<f32>(row_idx);
+                    for &val in row.iter() {
+                        row_buffer[offset..offset+4].copy_from_slice(&val.to_le_bytes());
+                        offset += 4;
+                    }
+                }
+                crate::metadata::Dtype::F64 => {
+                    let row = field.get_row::<f64>(row_idx);
+                    for &val in row.iter() {
+                        row_buffer[offset..offset+8].copy_from_slice(&val.to_le_bytes());
+                        offset += 8;
+                    }
+                }
+            }
+        }
+        writer.write_all(&row_buffer)?;
+    }
+    Ok(())
+}
+
+/// Writes the point cloud data in a compressed encoding: binary_compressed (LZF), or the
+/// binary_zstd, binary_lz4 and binary_compressed_large extensions, compressed at
+/// `options.compression_level` for zstd.
+/// The uncompressed data is laid out field by field (all values of the first field, then the
+/// next, ...), compressed as a single block, and written after its compressed and
+/// uncompressed sizes (u32 for binary_compressed, u64 for the extensions).
+///
+/// Each field is copied straight from its array into a buffer preallocated to the exact
+/// uncompressed size, which is released before the compressed data is written. Since
+/// binary_compressed stores sizes as u32, data larger than 4 GiB cannot be written in it.
+pub fn write_compressed_data<W: Write>(writer: &mut W, pc: &crate::pointcloud::PointCloud, encoding: Encoding, options: &WriteOptions) -> Result<()> {
+    let md = pc.metadata.read().unwrap();
+    let uncompressed_size = md.data_size();
+    let pcl_block = encoding == Encoding::BinaryCompressed;
+    anyhow::ensure!(!pcl_block || uncompressed_size <= u32::MAX as usize,
+        "Point data of {} bytes is too large for binary_compressed encoding (at most {} bytes); \
+        enable large_compressed to write the binary_compressed_large extension", uncompressed_size, u32::MAX);
+    let mut uncompressed_buf = Vec::with_capacity(uncompressed_size);
+    for field_meta in md.fields.iter() {
+        pc.fields[&field_meta.name].extend_le_bytes(&mut uncompressed_buf);
+    }
+    write_compressed_block(writer, uncompressed_buf, encoding, options.compression_level)
+}
+
+/// Compresses `uncompressed_buf` (point data laid out field by field) as a single block of
+/// `encoding` data and writes it after its compressed and uncompressed sizes. The
+/// uncompressed buffer is released before the compressed data is written.
+pub fn write_compressed_block<W: Write>(writer: &mut W, uncompressed_buf: Vec<u8>, encoding: Encoding, level: Option<i32>) -> Result<()> {
+    let uncompressed_size = uncompressed_buf.len();
+    let pcl_block = encoding == Encoding::BinaryCompressed;
+    let compressed_buf = compress(encoding, &uncompressed_buf, level)?;
+    drop(uncompressed_buf);
+    if pcl_block {
+        anyhow::ensure!(compressed_buf.len() <= u32::MAX as usize, "Compressed data is too large for binary_compressed encoding");
+        writer.write_u32::<LittleEndian>(compressed_buf.len() as u32)?;
+        writer.write_u32::<LittleEndian>(uncompressed_size as u32)?;
+    } else {
+        writer.write_u64::<LittleEndian>(compressed_buf.len() as u64)?;
+        writer.write_u64::<LittleEndian>(uncompressed_size as u64)?;
+    }
+    writer.write_all(&compressed_buf)?;
+    Ok(())
+}
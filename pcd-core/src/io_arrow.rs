@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::sync::Arc;
+use anyhow::Result;
+use arrow::array::{Array, ArrayRef, ArrowPrimitiveType, BooleanArray, FixedSizeListArray, PrimitiveArray, RecordBatch};
+use arrow::datatypes::{
+    DataType, Field, Float16Type, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type,
+    Schema, SchemaRef, UInt16Type, UInt32Type, UInt64Type, UInt8Type,
+};
+use ndarray::ArcArray2;
+use crate::error::{ErrorKind, PcdError};
+use crate::fielddata::FieldData;
+use crate::metadata::{Dtype, FieldMeta, FieldSchema, Metadata, Viewpoint};
+use crate::pointcloud::PointCloud;
+
+/// Schema metadata keys used to carry the PCD header through Arrow/Parquet.
+const WIDTH_KEY: &str = "pcd.width";
+const HEIGHT_KEY: &str = "pcd.height";
+const VIEWPOINT_KEY: &str = "pcd.viewpoint";
+
+/// Returns the Arrow data type corresponding to a `Dtype`.
+pub fn dtype_to_arrow(dtype: Dtype) -> DataType {
+    match dtype {
+        Dtype::Bool => DataType::Boolean,
+        Dtype::U8 => DataType::UInt8,
+        Dtype::U16 => DataType::UInt16,
+        Dtype::U32 => DataType::UInt32,
+        Dtype::U64 => DataType::UInt64,
+        Dtype::I8 => DataType::Int8,
+        Dtype::I16 => DataType::Int16,
+        Dtype::I32 => DataType::Int32,
+        Dtype::I64 => DataType::Int64,
+        Dtype::F16 => DataType::Float16,
+        Dtype::F32 => DataType::Float32,
+        Dtype::F64 => DataType::Float64,
+    }
+}
+
+/// Returns the `Dtype` corresponding to a primitive Arrow data type.
+pub fn dtype_from_arrow(data_type: &DataType) -> Result<Dtype> {
+    match data_type {
+        DataType::Boolean => Ok(Dtype::Bool),
+        DataType::UInt8 => Ok(Dtype::U8),
+        DataType::UInt16 => Ok(Dtype::U16),
+        DataType::UInt32 => Ok(Dtype::U32),
+        DataType::UInt64 => Ok(Dtype::U64),
+        DataType::Int8 => Ok(Dtype::I8),
+        DataType::Int16 => Ok(Dtype::I16),
+        DataType::Int32 => Ok(Dtype::I32),
+        DataType::Int64 => Ok(Dtype::I64),
+        DataType::Float16 => Ok(Dtype::F16),
+        DataType::Float32 => Ok(Dtype::F32),
+        DataType::Float64 => Ok(Dtype::F64),
+        _ => anyhow::bail!(PcdError::new(ErrorKind::UnsupportedDtype, format!("Unsupported Arrow data type: {}", data_type))),
+    }
+}
+
+/// Returns the Arrow field for a PCD field.
+/// Fields with a count greater than 1 are stored as fixed size lists.
+fn field_to_arrow(field_meta: &FieldMeta) -> Field {
+    let data_type = dtype_to_arrow(field_meta.dtype);
+    if field_meta.count == 1 {
+        Field::new(&field_meta.name, data_type, false)
+    } else {
+        let item = Arc::new(Field::new("item", data_type, false));
+        Field::new(&field_meta.name, DataType::FixedSizeList(item, field_meta.count as i32), false)
+    }
+}
+
+/// Returns the PCD field metadata (name, dtype, count) for an Arrow field.
+fn field_from_arrow(field: &Field) -> Result<FieldMeta> {
+    let (dtype, count) = match field.data_type() {
+        DataType::FixedSizeList(item, count) => (dtype_from_arrow(item.data_type())?, *count as usize),
+        data_type => (dtype_from_arrow(data_type)?, 1),
+    };
+    Ok(FieldMeta::new(field.name().as_str(), dtype, count))
+}
+
+/// Returns the Arrow schema for a PointCloud, including the organized shape and viewpoint.
+pub fn schema_to_arrow(md: &Metadata) -> SchemaRef {
+    let fields: Vec<Field> = md.fields.iter().map(field_to_arrow).collect();
+    let metadata = HashMap::from([
+        (WIDTH_KEY.to_string(), md.width.to_string()),
+        (HEIGHT_KEY.to_string(), md.height.to_string()),
+        (VIEWPOINT_KEY.to_string(), md.viewpoint.to_vec().iter().map(|v| v.to_string()).collect::<Vec<_>>().join(" ")),
+    ]);
+    Arc::new(Schema::new_with_metadata(fields, metadata))
+}
+
+/// Build a primitive Arrow array from the row-major values of a field.
+fn values_to_arrow<T: ArrowPrimitiveType>(values: impl Iterator<Item = T::Native>) -> ArrayRef {
+    Arc::new(PrimitiveArray::<T>::from_iter_values(values))
+}
+
+/// Returns the values of a field as a flat, row-major Arrow array.
+fn field_values_to_arrow(field: &FieldData) -> ArrayRef {
+    match field {
+        FieldData::Bool(arr) => Arc::new(arr.iter().map(|&v| Some(v != 0)).collect::<BooleanArray>()),
+        FieldData::U8(arr) => values_to_arrow::<UInt8Type>(arr.iter().copied()),
+        FieldData::U16(arr) => values_to_arrow::<UInt16Type>(arr.iter().copied()),
+        FieldData::U32(arr) => values_to_arrow::<UInt32Type>(arr.iter().copied()),
+        FieldData::U64(arr) => values_to_arrow::<UInt64Type>(arr.iter().copied()),
+        FieldData::I8(arr) => values_to_arrow::<Int8Type>(arr.iter().copied()),
+        FieldData::I16(arr) => values_to_arrow::<Int16Type>(arr.iter().copied()),
+        FieldData::I32(arr) => values_to_arrow::<Int32Type>(arr.iter().copied()),
+        FieldData::I64(arr) => values_to_arrow::<Int64Type>(arr.iter().copied()),
+        FieldData::F16(arr) => values_to_arrow::<Float16Type>(arr.iter().copied()),
+        FieldData::F32(arr) => values_to_arrow::<Float32Type>(arr.iter().copied()),
+        FieldData::F64(arr) => values_to_arrow::<Float64Type>(arr.iter().copied()),
+    }
+}
+
+/// Returns the values of a primitive Arrow array as a Vec.
+fn arrow_to_values<T: ArrowPrimitiveType>(values: &ArrayRef) -> Vec<T::Native> {
+    values.as_any().downcast_ref::<PrimitiveArray<T>>().unwrap().values().to_vec()
+}
+
+/// Returns the values of a boolean Arrow array as bytes of 0 or 1.
+fn arrow_to_bools(values: &ArrayRef) -> Vec<u8> {
+    values.as_any().downcast_ref::<BooleanArray>().unwrap().values().iter().map(u8::from).collect()
+}
+
+/// Build a field of shape (npoints, count) from a flat, row-major Arrow array.
+fn field_values_from_arrow(values: &ArrayRef, dtype: Dtype, npoints: usize, count: usize) -> Result<FieldData> {
+    let shape = (npoints, count);
+    let field = match dtype {
+        Dtype::Bool => FieldData::Bool(ArcArray2::from_shape_vec(shape, arrow_to_bools(values))?),
+        Dtype::U8 => FieldData::U8(ArcArray2::from_shape_vec(shape, arrow_to_values::<UInt8Type>(values))?),
+        Dtype::U16 => FieldData::U16(ArcArray2::from_shape_vec(shape, arrow_to_values::<UInt16Type>(values))?),
+        Dtype::U32 => FieldData::U32(ArcArray2::from_shape_vec(shape, arrow_to_values::<UInt32Type>(values))?),
+        Dtype::U64 => FieldData::U64(ArcArray2::from_shape_vec(shape, arrow_to_values::<UInt64Type>(values))?),
+        Dtype::I8 => FieldData::I8(ArcArray2::from_shape_vec(shape, arrow_to_values::<Int8Type>(values))?),
+        Dtype::I16 => FieldData::I16(ArcArray2::from_shape_vec(shape, arrow_to_values::<Int16Type>(values))?),
+        Dtype::I32 => FieldData::I32(ArcArray2::from_shape_vec(shape, arrow_to_values::<Int32Type>(values))?),
+        Dtype::I64 => FieldData::I64(ArcArray2::from_shape_vec(shape, arrow_to_values::<Int64Type>(values))?),
+        Dtype::F16 => FieldData::F16(ArcArray2::from_shape_vec(shape, arrow_to_values::<Float16Type>(values))?),
+        Dtype::F32 => FieldData::F32(ArcArray2::from_shape_vec(shape, arrow_to_values::<Float32Type>(values))?),
+        Dtype::F64 => FieldData::F64(ArcArray2::from_shape_vec(shape, arrow_to_values::<Float64Type>(values))?),
+    };
+    Ok(field)
+}
+
+/// Converts a PointCloud into an Arrow record batch with one column per field.
+pub fn to_record_batch(pc: &PointCloud) -> Result<RecordBatch> {
+    let md = pc.metadata.read().unwrap();
+    let schema = schema_to_arrow(&md);
+    let columns = md.fields.iter()
+        .map(|field_meta| {
+            let field = pc.fields.get(&field_meta.name)
+                .ok_or_else(|| anyhow::anyhow!("Field '{}' exists in metadata but not in data", field_meta.name))?;
+            let values = field_values_to_arrow(field);
+            if field_meta.count == 1 {
+                Ok(values)
+            } else {
+                let item = Arc::new(Field::new("item", dtype_to_arrow(field_meta.dtype), false));
+                Ok(Arc::new(FixedSizeListArray::try_new(item, field_meta.count as i32, values, None)?) as ArrayRef)
+            }
+        })
+        .collect::<Result<Vec<ArrayRef>>>()?;
+    Ok(RecordBatch::try_new(schema, columns)?)
+}
+
+/// Converts Arrow record batches sharing `schema` into a single PointCloud.
+/// The organized shape and viewpoint are restored from the schema metadata when present.
+pub fn from_record_batches(schema: &Schema, batches: &[RecordBatch]) -> Result<PointCloud> {
+    let fields: FieldSchema = schema.fields().iter()
+        .map(|f| field_from_arrow(f))
+        .collect::<Result<Vec<FieldMeta>>>()?
+        .into_iter()
+        .collect();
+    let npoints: usize = batches.iter().map(|b| b.num_rows()).sum();
+
+    let metadata = schema.metadata();
+    let parse_dim = |key: &str| metadata.get(key).and_then(|v| v.parse::<usize>().ok());
+    let (width, height) = match (parse_dim(WIDTH_KEY), parse_dim(HEIGHT_KEY)) {
+        (Some(w), Some(h)) if w * h == npoints => (w, h),
+        _ => (npoints, 1),
+    };
+    let viewpoint = metadata.get(VIEWPOINT_KEY)
+        .and_then(|v| v.split_ascii_whitespace().map(|x| x.parse::<f32>().ok()).collect::<Option<Vec<f32>>>())
+        .filter(|v| v.len() == 7)
+        .map(Viewpoint::from)
+        .unwrap_or_default();
+
+    let md = Metadata {
+        fields: fields.clone(),
+        width,
+        height,
+        npoints,
+        viewpoint,
+        ..Metadata::default()
+    };
+    let mut pc = PointCloud::empty(&md);
+    for (col_idx, field_meta) in fields.iter().enumerate() {
+        let columns = batches.iter()
+            .map(|b| {
+                let column = b.column(col_idx);
+                match column.data_type() {
+                    DataType::FixedSizeList(..) => {
+                        let list = column.as_any().downcast_ref::<FixedSizeListArray>().unwrap();
+                        list.values().slice(0, list.len() * field_meta.count)
+                    }
+                    _ => column.clone(),
+                }
+            })
+            .collect::<Vec<ArrayRef>>();
+        let refs = columns.iter().map(|c| c.as_ref()).collect::<Vec<&dyn Array>>();
+        let values = if refs.is_empty() {
+            arrow::array::new_empty_array(&dtype_to_arrow(field_meta.dtype))
+        } else {
+            arrow::compute::concat(&refs)?
+        };
+        let field = field_values_from_arrow(&values, field_meta.dtype, npoints, field_meta.count)?;
+        pc.fields.insert(field_meta.name.clone(), field);
+    }
+
+    Ok(pc)
+}
+
+/// Writes the PointCloud to a Parquet file.
+pub fn write_parquet(pc: &PointCloud, path: &str) -> Result<()> {
+    let batch = to_record_batch(pc)?;
+    let file = File::create(path)?;
+    let mut writer = parquet::arrow::ArrowWriter::try_new(file, batch.schema(), None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+/// Reads a Parquet file into a new PointCloud.
+pub fn read_parquet(path: &str) -> Result<PointCloud> {
+    let file = File::open(path)?;
+    let builder = parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder::try_new(file)?;
+    let schema = builder.schema().clone();
+    let batches = builder.build()?.collect::<std::result::Result<Vec<RecordBatch>, _>>()?;
+    from_record_batches(&schema, &batches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parquet_round_trip() {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F64, 1), ("normal", Dtype::F32, 3), ("label", Dtype::U16, 1)]),
+            width: 2,
+            height: 2,
+            npoints: 4,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        pc.fields.get_mut("normal").unwrap().assign_row(2, &ndarray::Array1::from(vec![0.0f32, 1.0, 2.0]));
+        pc.fields.get_mut("label").unwrap().assign_row(3, &ndarray::Array1::from(vec![9u16]));
+
+        let path = std::env::temp_dir().join("pcdpy_test.parquet");
+        let path = path.to_str().unwrap();
+        write_parquet(&pc, path).unwrap();
+        let loaded = read_parquet(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(*loaded.metadata.read().unwrap(), md);
+        assert_eq!(loaded.fields, pc.fields);
+    }
+}
@@ -0,0 +1,140 @@
+use std::io::Cursor;
+use anyhow::Result;
+use byteorder::{ByteOrder, LittleEndian};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use crate::io::{self, ReadOptions, WriteOptions};
+use crate::metadata::Encoding;
+use crate::pointcloud::PointCloud;
+use crate::utils::parse_header;
+
+/// Append at most `n` bytes of `reader` to `buf`, fewer if the reader ends first.
+async fn read_up_to<R: AsyncBufRead + Unpin>(reader: &mut R, n: usize, buf: &mut Vec<u8>) -> Result<usize> {
+    Ok((&mut *reader).take(n as u64).read_to_end(buf).await?)
+}
+
+impl PointCloud {
+    /// Read one PCD cloud (header and body) from an async reader, such as a buffered
+    /// socket, as `from_pcd_reader_with`. Exactly the bytes of the cloud are consumed, so
+    /// several clouds can be read one after the other from the same stream. If
+    /// `options.verify` is set, the checksum footer following the data is read and checked.
+    ///
+    /// The cloud is received into memory without blocking the thread, then decoded on the
+    /// calling task; move the call to `tokio::task::spawn_blocking` if decoding large clouds
+    /// would stall other tasks.
+    pub async fn from_pcd_async<R: AsyncBufRead + Unpin>(reader: &mut R, options: &ReadOptions) -> Result<(Self, Vec<String>)> {
+        let mut buf = Vec::new();
+        loop {
+            let start = buf.len();
+            if reader.read_until(b'\n', &mut buf).await? == 0 {
+                // Incomplete header, reported by the parser
+                break;
+            }
+            if buf[start..].split(|b| b.is_ascii_whitespace()).find(|t| !t.is_empty()) == Some(b"DATA") {
+                break;
+            }
+        }
+        let header_len = buf.len();
+        let (md, _) = parse_header(&mut &buf[..])?;
+
+        match md.encoding {
+            Encoding::Ascii => {
+                // One non-empty, non-comment line per point
+                let mut points = 0;
+                while points < md.npoints {
+                    let start = buf.len();
+                    if reader.read_until(b'\n', &mut buf).await? == 0 {
+                        break;
+                    }
+                    let line = String::from_utf8_lossy(&buf[start..]);
+                    let line = line.trim();
+                    if !line.is_empty() && !line.starts_with('#') {
+                        points += 1;
+                    }
+                }
+            }
+            Encoding::Binary => {
+                read_up_to(reader, md.data_size(), &mut buf).await?;
+            }
+            encoding => {
+                let sizes_len = if encoding == Encoding::BinaryCompressed { 8 } else { 16 };
+                if read_up_to(reader, sizes_len, &mut buf).await? == sizes_len {
+                    let sizes = &buf[header_len..];
+                    let compressed_size = if encoding == Encoding::BinaryCompressed {
+                        LittleEndian::read_u32(sizes) as usize
+                    } else {
+                        LittleEndian::read_u64(sizes) as usize
+                    };
+                    read_up_to(reader, compressed_size, &mut buf).await?;
+                }
+            }
+        }
+        if options.verify {
+            read_up_to(reader, io::CHECKSUM_FOOTER_LEN, &mut buf).await?;
+        }
+        Self::from_pcd_reader_with(&mut Cursor::new(&buf[..]), options)
+    }
+
+    /// Write the PointCloud in PCD format to an async writer, such as a socket, as
+    /// `to_pcd_writer_with`, and flush it. The data is encoded into memory on the calling task
+    /// before being sent without blocking the thread.
+    pub async fn to_pcd_async<W: AsyncWrite + Unpin>(&self, writer: &mut W, options: &WriteOptions) -> Result<()> {
+        let mut buf = Vec::new();
+        self.to_pcd_writer_with(&mut buf, options)?;
+        writer.write_all(&buf).await?;
+        writer.flush().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array1;
+    use crate::error::ErrorKind;
+    use crate::metadata::{Dtype, FieldSchema, Metadata};
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(future)
+    }
+
+    #[test]
+    fn test_async_roundtrip() {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1), ("label", Dtype::U16, 2)]),
+            width: 3,
+            height: 1,
+            npoints: 3,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        for i in 0..3 {
+            pc.fields.get_mut("x").unwrap().assign_row(i, &Array1::from(vec![i as f32 * 0.5]));
+            pc.fields.get_mut("label").unwrap().assign_row(i, &Array1::from(vec![i as u16, 7]));
+        }
+
+        for encoding in [Encoding::Ascii, Encoding::Binary, Encoding::BinaryCompressed, Encoding::BinaryCompressedLarge] {
+            // Two clouds back to back on the same stream, followed by unrelated data
+            let options = WriteOptions { encoding: Some(encoding), ..WriteOptions::default() };
+            let mut stream = Vec::new();
+            block_on(pc.to_pcd_async(&mut stream, &options)).unwrap();
+            block_on(pc.to_pcd_async(&mut stream, &options)).unwrap();
+            stream.extend_from_slice(b"trailer");
+
+            let mut reader = &stream[..];
+            for _ in 0..2 {
+                let (read, warnings) = block_on(PointCloud::from_pcd_async(&mut reader, &ReadOptions::default())).unwrap();
+                assert!(warnings.is_empty());
+                read.metadata.write().unwrap().encoding = pc.metadata.read().unwrap().encoding;
+                assert_eq!(read, pc, "{}", encoding.as_str());
+            }
+            assert_eq!(reader, b"trailer");
+        }
+
+        let mut stream = Vec::new();
+        block_on(pc.to_pcd_async(&mut stream, &WriteOptions { encoding: Some(Encoding::Binary), ..WriteOptions::default() })).unwrap();
+        let err = block_on(PointCloud::from_pcd_async(&mut &stream[..stream.len() - 1], &ReadOptions::default())).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), Some(ErrorKind::DataCorruption));
+        let err = block_on(PointCloud::from_pcd_async(&mut &b"VERSION 0.7\nFIELDS x\n"[..], &ReadOptions::default())).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), Some(ErrorKind::Header));
+    }
+}
@@ -0,0 +1,552 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufReader, ErrorKind, Read};
+use anyhow::Result;
+use byteorder::{LittleEndian, ReadBytesExt};
+use crate::io;
+use crate::io_ros::{self, RosCloud, RosField};
+use crate::pointcloud::PointCloud;
+
+const ROSBAG_MAGIC: &[u8] = b"#ROSBAG V2.0\n";
+const MCAP_MAGIC: &[u8] = b"\x89MCAP0\r\n";
+const ROS1_POINTCLOUD2: &str = "sensor_msgs/PointCloud2";
+const ROS2_POINTCLOUD2: &str = "sensor_msgs/msg/PointCloud2";
+/// Largest decompressed chunk accepted; writers use chunks of a few MiB.
+const MAX_CHUNK_SIZE: u64 = 1 << 32;
+
+/// A PointCloud2 message read from a bag.
+#[derive(Debug, Clone)]
+pub struct BagMessage {
+    /// Time the message was recorded, in nanoseconds since the epoch.
+    pub timestamp: u64,
+    pub pc: PointCloud,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BagFormat {
+    Ros1,
+    Mcap,
+}
+
+/// Serialization of the messages in a bag: ROS1 for .bag files, and ROS1 or CDR (ROS2)
+/// for .mcap files.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MessageEncoding {
+    Ros1,
+    Cdr,
+}
+
+/// Streams the PointCloud2 messages of one topic from a ROS1 bag (format 2.0) or an MCAP
+/// file, in the order they are stored. Compressed chunks need the "lz4" or "zstd" feature;
+/// bz2 chunks are not supported.
+pub struct BagReader {
+    reader: BufReader<File>,
+    format: BagFormat,
+    topic: String,
+    /// Connection (ROS1) or channel (MCAP) ids of the topic, with their message encoding.
+    connections: HashMap<u32, MessageEncoding>,
+    /// Names of the MCAP schemas, by id.
+    schemas: HashMap<u16, String>,
+    /// Messages of the topic read from the current chunk: (timestamp, encoding, data).
+    pending: VecDeque<(u64, MessageEncoding, Vec<u8>)>,
+    done: bool,
+}
+
+impl BagReader {
+    /// Open the bag at `path` to read the PointCloud2 messages published on `topic`.
+    pub fn open(path: &str, topic: &str) -> Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut magic = [0u8; 13];
+        reader.read_exact(&mut magic[..MCAP_MAGIC.len()])?;
+        let format = if &magic[..MCAP_MAGIC.len()] == MCAP_MAGIC {
+            BagFormat::Mcap
+        } else {
+            reader.read_exact(&mut magic[MCAP_MAGIC.len()..])?;
+            anyhow::ensure!(magic == ROSBAG_MAGIC, "Not a ROS1 bag (version 2.0) or MCAP file: {}", path);
+            BagFormat::Ros1
+        };
+        Ok(Self {
+            reader,
+            format,
+            topic: topic.to_string(),
+            connections: HashMap::new(),
+            schemas: HashMap::new(),
+            pending: VecDeque::new(),
+            done: false,
+        })
+    }
+
+    /// Read the next top-level record, queueing the messages of the topic it holds.
+    fn read_record(&mut self) -> Result<()> {
+        match self.format {
+            BagFormat::Ros1 => {
+                let header_len = match self.reader.read_u32::<LittleEndian>() {
+                    Ok(len) => len as u64,
+                    Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                        self.done = true;
+                        return Ok(());
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+                let header = read_vec(&mut self.reader, header_len)?;
+                let data_len = self.reader.read_u32::<LittleEndian>()? as u64;
+                let data = read_vec(&mut self.reader, data_len)?;
+                self.handle_ros1_record(&header, &data)
+            }
+            BagFormat::Mcap => {
+                let opcode = self.reader.read_u8()?;
+                let len = self.reader.read_u64::<LittleEndian>()?;
+                match opcode {
+                    // Footer and DataEnd: the summary that follows repeats the data section
+                    0x02 | 0x0f => {
+                        self.done = true;
+                        Ok(())
+                    }
+                    0x03..=0x06 => {
+                        let content = read_vec(&mut self.reader, len)?;
+                        self.handle_mcap_record(opcode, &content)
+                    }
+                    _ => {
+                        let len = i64::try_from(len).map_err(|_| anyhow::anyhow!("Invalid MCAP record length {}", len))?;
+                        Ok(self.reader.seek_relative(len)?)
+                    }
+                }
+            }
+        }
+    }
+
+    fn handle_ros1_record(&mut self, header: &[u8], data: &[u8]) -> Result<()> {
+        let fields = ros1_header_fields(header)?;
+        let field = |name: &str| fields.get(name).copied()
+            .ok_or_else(|| anyhow::anyhow!("Bag record is missing the '{}' header field", name));
+        let op = field("op")?.first().copied().unwrap_or(0);
+        match op {
+            // Message data
+            0x02 => {
+                let conn = field("conn")?.read_u32::<LittleEndian>()?;
+                if let Some(&encoding) = self.connections.get(&conn) {
+                    let mut time = field("time")?;
+                    let secs = time.read_u32::<LittleEndian>()? as u64;
+                    let nsecs = time.read_u32::<LittleEndian>()? as u64;
+                    self.pending.push_back((secs * 1_000_000_000 + nsecs, encoding, data.to_vec()));
+                }
+            }
+            // Chunk of records
+            0x05 => {
+                let compression = String::from_utf8_lossy(field("compression")?).into_owned();
+                let size = field("size")?.read_u32::<LittleEndian>()? as u64;
+                let records = decompress_chunk(&compression, data, size)?;
+                let mut records = &records[..];
+                while !records.is_empty() {
+                    let header = take_sized(&mut records)?;
+                    let data = take_sized(&mut records)?;
+                    self.handle_ros1_record(header, data)?;
+                }
+            }
+            // Connection
+            0x07 => {
+                let conn = field("conn")?.read_u32::<LittleEndian>()?;
+                if field("topic")? == self.topic.as_bytes() {
+                    let connection = ros1_header_fields(data)?;
+                    let msg_type = connection.get("type").map(|t| String::from_utf8_lossy(t)).unwrap_or_default();
+                    anyhow::ensure!(msg_type == ROS1_POINTCLOUD2,
+                        "Topic '{}' has type {}, not {}", self.topic, msg_type, ROS1_POINTCLOUD2);
+                    self.connections.insert(conn, MessageEncoding::Ros1);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_mcap_record(&mut self, opcode: u8, mut content: &[u8]) -> Result<()> {
+        match opcode {
+            // Schema
+            0x03 => {
+                let id = content.read_u16::<LittleEndian>()?;
+                let name = take_string(&mut content)?;
+                self.schemas.insert(id, name);
+            }
+            // Channel
+            0x04 => {
+                let id = content.read_u16::<LittleEndian>()?;
+                let schema_id = content.read_u16::<LittleEndian>()?;
+                let topic = take_string(&mut content)?;
+                let message_encoding = take_string(&mut content)?;
+                if topic == self.topic {
+                    let schema = self.schemas.get(&schema_id).map(String::as_str).unwrap_or_default();
+                    anyhow::ensure!(schema == ROS1_POINTCLOUD2 || schema == ROS2_POINTCLOUD2,
+                        "Topic '{}' has type {}, not {}", self.topic, schema, ROS2_POINTCLOUD2);
+                    let encoding = match message_encoding.as_str() {
+                        "ros1" => MessageEncoding::Ros1,
+                        "cdr" => MessageEncoding::Cdr,
+                        _ => anyhow::bail!("Unsupported message encoding for topic '{}': {}", self.topic, message_encoding),
+                    };
+                    self.connections.insert(id as u32, encoding);
+                }
+            }
+            // Message
+            0x05 => {
+                let channel_id = content.read_u16::<LittleEndian>()?;
+                if let Some(&encoding) = self.connections.get(&(channel_id as u32)) {
+                    let _sequence = content.read_u32::<LittleEndian>()?;
+                    let log_time = content.read_u64::<LittleEndian>()?;
+                    let _publish_time = content.read_u64::<LittleEndian>()?;
+                    self.pending.push_back((log_time, encoding, content.to_vec()));
+                }
+            }
+            // Chunk of records
+            0x06 => {
+                let _start_time = content.read_u64::<LittleEndian>()?;
+                let _end_time = content.read_u64::<LittleEndian>()?;
+                let size = content.read_u64::<LittleEndian>()?;
+                let _crc = content.read_u32::<LittleEndian>()?;
+                let compression = take_string(&mut content)?;
+                let len = content.read_u64::<LittleEndian>()? as usize;
+                anyhow::ensure!(len <= content.len(), "MCAP chunk is truncated");
+                let records = decompress_chunk(&compression, &content[..len], size)?;
+                let mut records = &records[..];
+                while !records.is_empty() {
+                    let opcode = records.read_u8()?;
+                    let len = records.read_u64::<LittleEndian>()? as usize;
+                    anyhow::ensure!(len <= records.len(), "MCAP record is truncated");
+                    let (record, rest) = records.split_at(len);
+                    self.handle_mcap_record(opcode, record)?;
+                    records = rest;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for BagReader {
+    type Item = Result<BagMessage>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() && !self.done {
+            if let Err(e) = self.read_record() {
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+        let (timestamp, encoding, data) = self.pending.pop_front()?;
+        Some(decode_pointcloud2(&data, encoding).map(|pc| BagMessage { timestamp, pc }))
+    }
+}
+
+/// Reads `len` bytes, allocating no more than the reader actually holds.
+fn read_vec<R: Read>(reader: &mut R, len: u64) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    reader.take(len).read_to_end(&mut buf)?;
+    anyhow::ensure!(buf.len() as u64 == len, "Bag record is truncated");
+    Ok(buf)
+}
+
+/// Split a u32-length-prefixed block off the front of `data`.
+fn take_sized<'a>(data: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let len = data.read_u32::<LittleEndian>()? as usize;
+    anyhow::ensure!(len <= data.len(), "Record is truncated");
+    let (block, rest) = data.split_at(len);
+    *data = rest;
+    Ok(block)
+}
+
+fn take_string(data: &mut &[u8]) -> Result<String> {
+    Ok(String::from_utf8_lossy(take_sized(data)?).into_owned())
+}
+
+/// Parse the `name=value` fields of a ROS1 record header (or connection header).
+fn ros1_header_fields(mut header: &[u8]) -> Result<HashMap<String, &[u8]>> {
+    let mut fields = HashMap::new();
+    while !header.is_empty() {
+        let field = take_sized(&mut header)?;
+        let sep = field.iter().position(|&b| b == b'=')
+            .ok_or_else(|| anyhow::anyhow!("Invalid bag header field"))?;
+        fields.insert(String::from_utf8_lossy(&field[..sep]).into_owned(), &field[sep + 1..]);
+    }
+    Ok(fields)
+}
+
+/// Decompresses a chunk whose header records `size` bytes of records. The size is checked
+/// against the compressed length before anything is allocated.
+#[cfg_attr(not(any(feature = "zstd", feature = "lz4")), allow(unused_variables))]
+fn decompress_chunk(compression: &str, data: &[u8], size: u64) -> Result<Vec<u8>> {
+    if matches!(compression, "" | "none") {
+        return Ok(data.to_vec());
+    }
+    let limit = (data.len() as u64).saturating_mul(io::MAX_COMPRESSION_RATIO).min(MAX_CHUNK_SIZE);
+    anyhow::ensure!(size <= limit, "Invalid chunk size {} for {} compressed bytes", size, data.len());
+    let check = |out: Vec<u8>| -> Result<Vec<u8>> {
+        anyhow::ensure!(out.len() as u64 == size, "Chunk decompressed to {} bytes, expected {}", out.len(), size);
+        Ok(out)
+    };
+    match compression {
+        #[cfg(feature = "zstd")]
+        "zstd" => check(zstd::bulk::decompress(data, size as usize)?),
+        #[cfg(feature = "lz4")]
+        "lz4" => {
+            let mut out = Vec::with_capacity(size as usize);
+            lz4_flex::frame::FrameDecoder::new(data).take(size + 1).read_to_end(&mut out)?;
+            check(out)
+        }
+        _ => anyhow::bail!("Unsupported chunk compression: {}", compression),
+    }
+}
+
+/// Reads the primitives of a serialized message. ROS1 serialization is packed little-endian;
+/// CDR aligns each primitive to its size and may be big-endian.
+struct MessageReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    aligned: bool,
+    big_endian: bool,
+}
+
+impl<'a> MessageReader<'a> {
+    fn take(&mut self, len: usize, align: usize) -> Result<&'a [u8]> {
+        if self.aligned {
+            self.pos = self.pos.next_multiple_of(align);
+        }
+        anyhow::ensure!(self.pos + len <= self.data.len(), "PointCloud2 message is truncated");
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1, 1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.take(4, 4)?.try_into().unwrap();
+        Ok(if self.big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.u32()? as usize;
+        let bytes = self.take(len, 1)?;
+        // CDR strings include their null terminator
+        let bytes = if self.aligned { bytes.strip_suffix(b"\0").unwrap_or(bytes) } else { bytes };
+        Ok(String::from_utf8_lossy(bytes).into_owned())
+    }
+}
+
+/// Decode a serialized sensor_msgs/PointCloud2 message.
+fn decode_pointcloud2(data: &[u8], encoding: MessageEncoding) -> Result<PointCloud> {
+    let mut msg = match encoding {
+        MessageEncoding::Ros1 => MessageReader { data, pos: 0, aligned: false, big_endian: false },
+        MessageEncoding::Cdr => {
+            // Encapsulation header: 0x00 0x00 for big-endian CDR, 0x00 0x01 for little-endian
+            anyhow::ensure!(data.len() >= 4, "PointCloud2 message is truncated");
+            MessageReader { data: &data[4..], pos: 0, aligned: true, big_endian: data[1] == 0 }
+        }
+    };
+    // std_msgs/Header: seq (ROS1 only), stamp, frame_id
+    if encoding == MessageEncoding::Ros1 {
+        msg.u32()?;
+    }
+    msg.u32()?;
+    msg.u32()?;
+    msg.string()?;
+
+    let height = msg.u32()? as usize;
+    let width = msg.u32()? as usize;
+    let nfields = msg.u32()? as usize;
+    let mut fields = Vec::with_capacity(nfields.min(256));
+    for _ in 0..nfields {
+        let name = msg.string()?;
+        let offset = msg.u32()? as usize;
+        let datatype = msg.u8()?;
+        let count = msg.u32()? as usize;
+        fields.push(RosField { name, offset, datatype, count });
+    }
+    let is_bigendian = msg.u8()? != 0;
+    let point_step = msg.u32()? as usize;
+    let row_step = msg.u32()? as usize;
+    let len = msg.u32()? as usize;
+    let points = msg.take(len, 1)?;
+    let is_dense = msg.u8()? != 0;
+    io_ros::read_ros(&RosCloud {
+        fields,
+        height,
+        width,
+        point_step,
+        row_step,
+        is_bigendian,
+        is_dense,
+        data: Cow::Borrowed(points),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{Dtype, FieldSchema, Metadata};
+
+    fn test_cloud(x: f32) -> PointCloud {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1), ("label", Dtype::U8, 1)]),
+            width: 2,
+            height: 1,
+            npoints: 2,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        pc.fields.get_mut("x").unwrap().assign_row(1, &ndarray::Array1::from(vec![x]));
+        pc
+    }
+
+    /// Serialize a PointCloud2 message, in ROS1 format or as little-endian CDR.
+    fn serialize(pc: &PointCloud, cdr: bool) -> Vec<u8> {
+        let cloud = io_ros::write_ros(pc).unwrap();
+        let mut out: Vec<u8> = if cdr { vec![0, 1, 0, 0] } else { Vec::new() };
+        let origin = out.len();
+        let align = |out: &mut Vec<u8>, n: usize| if cdr {
+            out.resize(origin + (out.len() - origin).next_multiple_of(n), 0)
+        };
+        let put_u32 = |out: &mut Vec<u8>, v: usize| {
+            align(out, 4);
+            out.extend_from_slice(&(v as u32).to_le_bytes());
+        };
+        let put_string = |out: &mut Vec<u8>, s: &str| {
+            put_u32(out, s.len() + cdr as usize);
+            out.extend_from_slice(s.as_bytes());
+            if cdr {
+                out.push(0);
+            }
+        };
+        if !cdr {
+            put_u32(&mut out, 7);
+        }
+        put_u32(&mut out, 1);
+        put_u32(&mut out, 2);
+        put_string(&mut out, "lidar");
+        put_u32(&mut out, cloud.height);
+        put_u32(&mut out, cloud.width);
+        put_u32(&mut out, cloud.fields.len());
+        for field in &cloud.fields {
+            put_string(&mut out, &field.name);
+            put_u32(&mut out, field.offset);
+            out.push(field.datatype);
+            put_u32(&mut out, field.count);
+        }
+        out.push(cloud.is_bigendian as u8);
+        put_u32(&mut out, cloud.point_step);
+        put_u32(&mut out, cloud.row_step);
+        put_u32(&mut out, cloud.data.len());
+        out.extend_from_slice(&cloud.data);
+        out.push(cloud.is_dense as u8);
+        out
+    }
+
+    fn ros1_header(fields: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut header = Vec::new();
+        for (name, value) in fields {
+            header.extend_from_slice(&((name.len() + 1 + value.len()) as u32).to_le_bytes());
+            header.extend_from_slice(name.as_bytes());
+            header.push(b'=');
+            header.extend_from_slice(value);
+        }
+        header
+    }
+
+    fn ros1_record(fields: &[(&str, &[u8])], data: &[u8]) -> Vec<u8> {
+        let header = ros1_header(fields);
+        let mut record = (header.len() as u32).to_le_bytes().to_vec();
+        record.extend_from_slice(&header);
+        record.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        record.extend_from_slice(data);
+        record
+    }
+
+    fn mcap_record(opcode: u8, parts: &[&[u8]]) -> Vec<u8> {
+        let content = parts.concat();
+        let mut record = vec![opcode];
+        record.extend_from_slice(&(content.len() as u64).to_le_bytes());
+        record.extend_from_slice(&content);
+        record
+    }
+
+    fn mcap_string(s: &str) -> Vec<u8> {
+        let mut out = (s.len() as u32).to_le_bytes().to_vec();
+        out.extend_from_slice(s.as_bytes());
+        out
+    }
+
+    fn read_all(bytes: &[u8], name: &str, topic: &str) -> Result<Vec<BagMessage>> {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, bytes).unwrap();
+        let messages = BagReader::open(path.to_str().unwrap(), topic)?.collect();
+        std::fs::remove_file(&path).unwrap();
+        messages
+    }
+
+    #[test]
+    fn test_read_ros1_bag() {
+        let conn = 0u32.to_le_bytes();
+        let other = 1u32.to_le_bytes();
+        let connection_header = ros1_header(&[("type", b"sensor_msgs/PointCloud2"), ("md5sum", b"1158d486dd51d683ce2f1be655c3c181")]);
+        let mut chunk = ros1_record(&[("op", &[0x07]), ("conn", &conn), ("topic", b"/points")], &connection_header);
+        chunk.extend(ros1_record(&[("op", &[0x07]), ("conn", &other), ("topic", b"/other")], &connection_header));
+        for (i, x) in [1.5f32, 2.5].into_iter().enumerate() {
+            let time = [(100 + i as u32).to_le_bytes(), 5u32.to_le_bytes()].concat();
+            chunk.extend(ros1_record(&[("op", &[0x02]), ("conn", &conn), ("time", &time)], &serialize(&test_cloud(x), false)));
+            chunk.extend(ros1_record(&[("op", &[0x02]), ("conn", &other), ("time", &time)], &[]));
+        }
+        let mut bag = ROSBAG_MAGIC.to_vec();
+        bag.extend(ros1_record(&[("op", &[0x03])], &[b' '; 16]));
+        bag.extend(ros1_record(&[("op", &[0x05]), ("compression", b"none"), ("size", &(chunk.len() as u32).to_le_bytes())], &chunk));
+
+        let messages = read_all(&bag, "pcdpy_test_read.bag", "/points").unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].timestamp, 101_000_000_005);
+        assert_eq!(messages[1].pc, test_cloud(2.5));
+        assert!(read_all(&bag, "pcdpy_test_read_missing.bag", "/missing").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_read_mcap() {
+        let mut mcap = MCAP_MAGIC.to_vec();
+        mcap.extend(mcap_record(0x01, &[&mcap_string("ros2"), &mcap_string("")]));
+        let mut chunk = mcap_record(0x03, &[&1u16.to_le_bytes(), &mcap_string(ROS2_POINTCLOUD2), &mcap_string("ros2msg"), &mcap_string("")]);
+        chunk.extend(mcap_record(0x03, &[&2u16.to_le_bytes(), &mcap_string("std_msgs/msg/String"), &mcap_string("ros2msg"), &mcap_string("")]));
+        chunk.extend(mcap_record(0x04, &[&1u16.to_le_bytes(), &1u16.to_le_bytes(), &mcap_string("/points"), &mcap_string("cdr"), &0u32.to_le_bytes()]));
+        chunk.extend(mcap_record(0x04, &[&2u16.to_le_bytes(), &2u16.to_le_bytes(), &mcap_string("/chatter"), &mcap_string("cdr"), &0u32.to_le_bytes()]));
+        chunk.extend(mcap_record(0x05, &[&1u16.to_le_bytes(), &0u32.to_le_bytes(), &42u64.to_le_bytes(), &42u64.to_le_bytes(), &serialize(&test_cloud(3.5), true)]));
+        mcap.extend(mcap_record(0x06, &[&0u64.to_le_bytes(), &42u64.to_le_bytes(), &(chunk.len() as u64).to_le_bytes(), &0u32.to_le_bytes(),
+            &mcap_string(""), &(chunk.len() as u64).to_le_bytes(), &chunk]));
+        mcap.extend(mcap_record(0x05, &[&1u16.to_le_bytes(), &1u32.to_le_bytes(), &43u64.to_le_bytes(), &43u64.to_le_bytes(), &serialize(&test_cloud(4.5), true)]));
+        mcap.extend(mcap_record(0x0f, &[&0u32.to_le_bytes()]));
+        mcap.extend(mcap_record(0x02, &[&0u64.to_le_bytes(), &0u64.to_le_bytes(), &0u32.to_le_bytes()]));
+        mcap.extend_from_slice(MCAP_MAGIC);
+
+        let messages = read_all(&mcap, "pcdpy_test_read.mcap", "/points").unwrap();
+        assert_eq!(messages.iter().map(|m| m.timestamp).collect::<Vec<_>>(), [42, 43]);
+        assert_eq!(messages[0].pc, test_cloud(3.5));
+        assert_eq!(messages[1].pc, test_cloud(4.5));
+        assert!(read_all(&mcap, "pcdpy_test_read_chatter.mcap", "/chatter").is_err());
+    }
+
+    #[test]
+    fn test_read_corrupt_sizes() {
+        // Lengths far beyond the file are errors, not allocations
+        let mut bag = ROSBAG_MAGIC.to_vec();
+        bag.extend(u32::MAX.to_le_bytes());
+        assert!(read_all(&bag, "pcdpy_test_read_long_header.bag", "/points").unwrap_err().to_string().contains("truncated"));
+        let mut bag = ROSBAG_MAGIC.to_vec();
+        bag.extend(ros1_record(&[("op", &[0x05]), ("compression", b"lz4"), ("size", &u32::MAX.to_le_bytes())], &[0; 16]));
+        assert!(read_all(&bag, "pcdpy_test_read_large_chunk.bag", "/points").unwrap_err().to_string().contains("Invalid chunk size"));
+
+        let mut mcap = MCAP_MAGIC.to_vec();
+        mcap.push(0x03);
+        mcap.extend(u64::MAX.to_le_bytes());
+        assert!(read_all(&mcap, "pcdpy_test_read_long_record.mcap", "/points").unwrap_err().to_string().contains("truncated"));
+        let mut mcap = MCAP_MAGIC.to_vec();
+        mcap.extend(mcap_record(0x06, &[&0u64.to_le_bytes(), &0u64.to_le_bytes(), &u64::MAX.to_le_bytes(), &0u32.to_le_bytes(),
+            &mcap_string("zstd"), &4u64.to_le_bytes(), &[0; 4]]));
+        assert!(read_all(&mcap, "pcdpy_test_read_large_chunk.mcap", "/points").unwrap_err().to_string().contains("Invalid chunk size"));
+    }
+}
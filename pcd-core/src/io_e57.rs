@@ -0,0 +1,458 @@
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use anyhow::Result;
+use roxmltree::{Document, Node};
+use crate::error::{ErrorKind, PcdError};
+use crate::fielddata::FieldData;
+use crate::metadata::{Metadata, Viewpoint};
+use crate::pointcloud::PointCloud;
+
+const SIGNATURE: &[u8] = b"ASTM-E57";
+/// Size of the CRC checksum at the end of each page.
+const CRC_SIZE: u64 = 4;
+
+/// Reads the logical contents of an E57 file, which is split into pages that each end with
+/// a checksum. Checksums are not verified.
+struct PagedReader {
+    reader: BufReader<File>,
+    page_size: u64,
+    /// Logical length of the file.
+    len: u64,
+}
+
+impl PagedReader {
+    /// Converts a physical file offset (as stored in the file) to a logical offset.
+    fn logical(&self, physical: u64) -> u64 {
+        physical / self.page_size * (self.page_size - CRC_SIZE) + physical % self.page_size
+    }
+
+    fn read(&mut self, logical: u64, len: usize) -> Result<Vec<u8>> {
+        anyhow::ensure!(logical.checked_add(len as u64).is_some_and(|end| end <= self.len), PcdError::new(ErrorKind::DataCorruption,
+            format!("E57 block of {} bytes at offset {} extends past the end of the file", len, logical)));
+        let payload = self.page_size - CRC_SIZE;
+        let mut out = vec![0u8; len];
+        let mut done = 0;
+        while done < len {
+            let pos = logical + done as u64;
+            let n = ((payload - pos % payload) as usize).min(len - done);
+            self.reader.seek(SeekFrom::Start(pos / payload * self.page_size + pos % payload))?;
+            self.reader.read_exact(&mut out[done..done + n])?;
+            done += n;
+        }
+        Ok(out)
+    }
+}
+
+/// How the values of a point record component are stored.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ComponentType {
+    Float { double: bool },
+    Integer { min: i64, max: i64 },
+    ScaledInteger { min: i64, max: i64, scale: f64, offset: f64 },
+}
+
+/// A component of the point record prototype, stored as one bytestream.
+#[derive(Debug, Clone, PartialEq)]
+struct Component {
+    name: String,
+    ty: ComponentType,
+}
+
+impl Component {
+    fn from_xml(node: Node) -> Result<Self> {
+        let name = node.tag_name().name().to_string();
+        let attr_i64 = |key: &str, default: i64| -> Result<i64> {
+            node.attribute(key).map_or(Ok(default), |v| v.trim().parse()
+                .map_err(|_| anyhow::anyhow!("Invalid {} of '{}': {}", key, name, v)))
+        };
+        let attr_f64 = |key: &str, default: f64| -> Result<f64> {
+            node.attribute(key).map_or(Ok(default), |v| v.trim().parse()
+                .map_err(|_| anyhow::anyhow!("Invalid {} of '{}': {}", key, name, v)))
+        };
+        let ty = match node.attribute("type") {
+            Some("Float") => ComponentType::Float { double: node.attribute("precision") != Some("single") },
+            Some("Integer") => ComponentType::Integer {
+                min: attr_i64("minimum", i64::MIN)?,
+                max: attr_i64("maximum", i64::MAX)?,
+            },
+            Some("ScaledInteger") => ComponentType::ScaledInteger {
+                min: attr_i64("minimum", i64::MIN)?,
+                max: attr_i64("maximum", i64::MAX)?,
+                scale: attr_f64("scale", 1.0)?,
+                offset: attr_f64("offset", 0.0)?,
+            },
+            ty => anyhow::bail!(PcdError::new(ErrorKind::UnsupportedDtype, format!("Unsupported type of point component '{}': {}", name, ty.unwrap_or("none")))),
+        };
+        Ok(Self { name, ty })
+    }
+
+    /// The range of values of an integer component.
+    fn range(&self) -> Option<(f64, f64)> {
+        match self.ty {
+            ComponentType::Float { .. } => None,
+            ComponentType::Integer { min, max } => Some((min as f64, max as f64)),
+            ComponentType::ScaledInteger { min, max, scale, offset } => Some((min as f64 * scale + offset, max as f64 * scale + offset)),
+        }
+    }
+
+    /// Decodes the first `n` values of the component's bytestream.
+    fn decode(&self, bytes: &[u8], n: usize) -> Result<Vec<f64>> {
+        let (min, max) = match self.ty {
+            ComponentType::Float { double } => {
+                let size = if double { 8 } else { 4 };
+                anyhow::ensure!(n.checked_mul(size).is_some_and(|len| bytes.len() >= len), "Not enough data for '{}'", self.name);
+                let values = bytes.chunks_exact(size).take(n);
+                return Ok(if double {
+                    values.map(|b| f64::from_le_bytes(b.try_into().unwrap())).collect()
+                } else {
+                    values.map(|b| f32::from_le_bytes(b.try_into().unwrap()) as f64).collect()
+                });
+            }
+            ComponentType::Integer { min, max } | ComponentType::ScaledInteger { min, max, .. } => (min, max),
+        };
+        anyhow::ensure!(min <= max, "Invalid range of '{}': {} to {}", self.name, min, max);
+        // Integers are stored as offsets from the minimum, bit-packed least significant bit first
+        let range = (max as i128 - min as i128) as u128;
+        let bits = (128 - range.leading_zeros()) as usize;
+        anyhow::ensure!(n.checked_mul(bits).is_some_and(|len| bytes.len() * 8 >= len), "Not enough data for '{}'", self.name);
+        let mask = if bits == 64 { u64::MAX } else { (1u64 << bits) - 1 };
+        let values = (0..n).map(|i| {
+            let start = i * bits;
+            let end = (start + bits).div_ceil(8);
+            let window = bytes[start / 8..end].iter().rev().fold(0u128, |w, &b| (w << 8) | b as u128);
+            let value = min as i128 + ((window >> (start % 8)) as u64 & mask) as i128;
+            match self.ty {
+                ComponentType::ScaledInteger { scale, offset, .. } => value as f64 * scale + offset,
+                _ => value as f64,
+            }
+        });
+        Ok(values.collect())
+    }
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|c| c.tag_name().name() == name)
+}
+
+/// Returns the numeric text of the child element `name`.
+fn child_value(node: Node, name: &str) -> Option<f64> {
+    child(node, name)?.text()?.trim().parse().ok()
+}
+
+/// Reads the pose of a scan (rotation quaternion and translation), which transforms its
+/// points into the file's coordinate frame.
+fn read_pose(scan: Node) -> Viewpoint {
+    let mut viewpoint = Viewpoint::default();
+    let Some(pose) = child(scan, "pose") else {
+        return viewpoint;
+    };
+    if let Some(rotation) = child(pose, "rotation") {
+        viewpoint.qw = child_value(rotation, "w").unwrap_or(1.0) as f32;
+        viewpoint.qx = child_value(rotation, "x").unwrap_or(0.0) as f32;
+        viewpoint.qy = child_value(rotation, "y").unwrap_or(0.0) as f32;
+        viewpoint.qz = child_value(rotation, "z").unwrap_or(0.0) as f32;
+    }
+    if let Some(translation) = child(pose, "translation") {
+        viewpoint.tx = child_value(translation, "x").unwrap_or(0.0) as f32;
+        viewpoint.ty = child_value(translation, "y").unwrap_or(0.0) as f32;
+        viewpoint.tz = child_value(translation, "z").unwrap_or(0.0) as f32;
+    }
+    viewpoint
+}
+
+/// Reads the bytestream of each component from the data packets of a compressed vector
+/// binary section.
+fn read_bytestreams(paged: &mut PagedReader, section_offset: u64, ncomponents: usize) -> Result<Vec<Vec<u8>>> {
+    let section_start = paged.logical(section_offset);
+    let header = paged.read(section_start, 32)?;
+    anyhow::ensure!(header[0] == 1, "Expected a compressed vector section at offset {}", section_offset);
+    let section_end = section_start.checked_add(u64::from_le_bytes(header[8..16].try_into().unwrap()))
+        .filter(|&end| end <= paged.len)
+        .ok_or_else(|| PcdError::new(ErrorKind::DataCorruption, format!("Compressed vector section at offset {} extends past the end of the file", section_offset)))?;
+    let mut pos = paged.logical(u64::from_le_bytes(header[16..24].try_into().unwrap()));
+
+    let mut streams = vec![Vec::new(); ncomponents];
+    while pos < section_end {
+        let packet_header = paged.read(pos, 4)?;
+        let len = u16::from_le_bytes([packet_header[2], packet_header[3]]) as usize + 1;
+        // Packet types: 0 = index, 1 = data, 2 = empty
+        if packet_header[0] == 1 {
+            let truncated = || PcdError::new(ErrorKind::DataCorruption, format!("Data packet at offset {} is truncated", pos));
+            let packet = paged.read(pos, len)?;
+            anyhow::ensure!(len >= 6, truncated());
+            let count = u16::from_le_bytes([packet[4], packet[5]]) as usize;
+            anyhow::ensure!(len >= 6 + 2 * count, truncated());
+            anyhow::ensure!(count == ncomponents,
+                "Data packet has {} bytestreams, but the prototype has {} components", count, ncomponents);
+            let mut offset = 6 + 2 * count;
+            for (i, stream) in streams.iter_mut().enumerate() {
+                let n = u16::from_le_bytes([packet[6 + 2 * i], packet[7 + 2 * i]]) as usize;
+                anyhow::ensure!(n <= len - offset, truncated());
+                stream.extend_from_slice(&packet[offset..offset + n]);
+                offset += n;
+            }
+        }
+        pos += len as u64;
+    }
+    Ok(streams)
+}
+
+/// Reads scan `scan_index` of an E57 file. Cartesian coordinates (or spherical ones,
+/// converted to cartesian) become the x, y and z fields, with NaN for points marked
+/// invalid; intensity becomes an F32 "intensity" field, and colors a packed "rgb" field
+/// scaled to 0-255. The scan pose is stored as the viewpoint.
+pub fn read_e57(path: &str, scan_index: usize) -> Result<PointCloud> {
+    let file = File::open(path)?;
+    let file_len = file.metadata()?.len();
+    let mut reader = BufReader::new(file);
+    let mut header = [0u8; 48];
+    reader.read_exact(&mut header)?;
+    anyhow::ensure!(&header[..8] == SIGNATURE, "Not an E57 file: {}", path);
+    let xml_offset = u64::from_le_bytes(header[24..32].try_into().unwrap());
+    let xml_len = u64::from_le_bytes(header[32..40].try_into().unwrap());
+    let page_size = u64::from_le_bytes(header[40..48].try_into().unwrap());
+    anyhow::ensure!(page_size > CRC_SIZE, "Invalid E57 page size {}", page_size);
+    anyhow::ensure!(xml_offset.checked_add(xml_len).is_some_and(|end| end <= file_len), PcdError::new(ErrorKind::DataCorruption,
+        format!("E57 XML section of {} bytes at offset {} extends past the end of the file", xml_len, xml_offset)));
+    let mut paged = PagedReader { reader, page_size, len: 0 };
+    paged.len = paged.logical(file_len);
+
+    let xml = paged.read(paged.logical(xml_offset), xml_len as usize)?;
+    let xml = String::from_utf8(xml)?;
+    let doc = Document::parse(&xml)?;
+    let scans: Vec<Node> = child(doc.root_element(), "data3D")
+        .map(|data3d| data3d.children().filter(|c| c.is_element()).collect())
+        .unwrap_or_default();
+    let scan = *scans.get(scan_index)
+        .ok_or_else(|| anyhow::anyhow!("Scan {} not found: the file has {} scans", scan_index, scans.len()))?;
+
+    let points = child(scan, "points").ok_or_else(|| anyhow::anyhow!("Scan {} has no points", scan_index))?;
+    let attr = |key: &str| -> Result<u64> {
+        points.attribute(key).and_then(|v| v.parse().ok())
+            .ok_or_else(|| anyhow::anyhow!("Scan {} points have no valid {}", scan_index, key))
+    };
+    let npoints = attr("recordCount")? as usize;
+    let section_offset = attr("fileOffset")?;
+    let components = child(points, "prototype")
+        .ok_or_else(|| anyhow::anyhow!("Scan {} points have no prototype", scan_index))?
+        .children()
+        .filter(|c| c.is_element())
+        .map(Component::from_xml)
+        .collect::<Result<Vec<_>>>()?;
+    let streams = read_bytestreams(&mut paged, section_offset, components.len())?;
+    let component = |name: &str| components.iter().position(|c| c.name == name);
+    let values = |name: &str| -> Result<Option<Vec<f64>>> {
+        component(name).map(|i| components[i].decode(&streams[i], npoints)).transpose()
+    };
+
+    let (coordinates, invalid_state) = if let (Some(x), Some(y), Some(z)) = (values("cartesianX")?, values("cartesianY")?, values("cartesianZ")?) {
+        ([x, y, z], values("cartesianInvalidState")?)
+    } else if let (Some(r), Some(az), Some(el)) = (values("sphericalRange")?, values("sphericalAzimuth")?, values("sphericalElevation")?) {
+        let x = (0..npoints).map(|i| r[i] * el[i].cos() * az[i].cos()).collect();
+        let y = (0..npoints).map(|i| r[i] * el[i].cos() * az[i].sin()).collect();
+        let z = (0..npoints).map(|i| r[i] * el[i].sin()).collect();
+        ([x, y, z], values("sphericalInvalidState")?)
+    } else {
+        anyhow::bail!("Scan {} has neither cartesian nor spherical coordinates", scan_index);
+    };
+    let single = ["cartesianX", "cartesianY", "cartesianZ", "sphericalRange"].iter()
+        .filter_map(|name| component(name))
+        .all(|i| components[i].ty == ComponentType::Float { double: false });
+
+    let md = Metadata {
+        width: npoints,
+        height: 1,
+        npoints,
+        viewpoint: read_pose(scan),
+        ..Metadata::default()
+    };
+    let mut pc = PointCloud::empty(&md);
+    for (name, mut column) in ["x", "y", "z"].into_iter().zip(coordinates) {
+        if let Some(state) = &invalid_state {
+            column.iter_mut().zip(state).filter(|(_, &s)| s != 0.0).for_each(|(v, _)| *v = f64::NAN);
+        }
+        let column = ndarray::Array2::from_shape_vec((npoints, 1), column)?;
+        let data = if single { FieldData::F32(column.mapv(|v| v as f32).into_shared()) } else { FieldData::F64(column.into_shared()) };
+        pc.insert_field(name, data)?;
+    }
+    if let Some(intensity) = values("intensity")? {
+        let column = ndarray::Array2::from_shape_vec((npoints, 1), intensity)?;
+        pc.insert_field("intensity", FieldData::F32(column.mapv(|v| v as f32).into_shared()))?;
+    }
+    let channels = ["colorRed", "colorGreen", "colorBlue"];
+    if channels.iter().all(|name| component(name).is_some()) {
+        let limits = child(scan, "colorLimits");
+        let mut rgb = Vec::with_capacity(3);
+        for (channel, limit) in channels.into_iter().zip(["Red", "Green", "Blue"]) {
+            let i = component(channel).unwrap();
+            let (lo, hi) = limits
+                .and_then(|l| Some((child_value(l, &format!("color{}Minimum", limit))?, child_value(l, &format!("color{}Maximum", limit))?)))
+                .or_else(|| components[i].range())
+                .unwrap_or((0.0, 255.0));
+            let scale = if hi > lo { 255.0 / (hi - lo) } else { 0.0 };
+            let values = components[i].decode(&streams[i], npoints)?;
+            rgb.push(ndarray::Array1::from_iter(values.iter().map(|v| ((v - lo) * scale).round().clamp(0.0, 255.0) as u8)));
+        }
+        pc.pack_rgb(rgb[0].view(), rgb[1].view(), rgb[2].view(), "rgb")?;
+    }
+    Ok(pc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::Dtype;
+
+    /// Splits logical contents into 1024-byte pages with (zeroed) checksums.
+    fn paginate(logical: &[u8]) -> Vec<u8> {
+        logical.chunks(1020).flat_map(|page| {
+            let mut page = page.to_vec();
+            page.resize(1024, 0);
+            page
+        }).collect()
+    }
+
+    fn physical(logical: usize) -> u64 {
+        (logical / 1020 * 1024 + logical % 1020) as u64
+    }
+
+    #[test]
+    fn test_decode_bitpacked() {
+        let component = Component { name: "i".to_string(), ty: ComponentType::Integer { min: -2, max: 5 } };
+        // 3-bit values 0, 7, 3, 5 (-2, 5, 1, 3): bits 000 111 110 101
+        let bytes = [0b1111_1000, 0b0000_1010];
+        assert_eq!(component.decode(&bytes, 4).unwrap(), [-2.0, 5.0, 1.0, 3.0]);
+        assert!(component.decode(&bytes, 6).is_err());
+
+        let scaled = Component { name: "s".to_string(), ty: ComponentType::ScaledInteger { min: 0, max: 0, scale: 0.5, offset: 1.0 } };
+        assert_eq!(scaled.decode(&[], 2).unwrap(), [1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_read_e57() {
+        let npoints = 600usize;
+        let xs: Vec<f32> = (0..npoints).map(|i| i as f32 * 0.25).collect();
+        let intensity: Vec<u16> = (0..npoints).map(|i| (i % 1024) as u16).collect();
+        // 10-bit intensity values, packed least significant bit first
+        let mut intensity_bytes = vec![0u8; (npoints * 10).div_ceil(8)];
+        for (i, v) in intensity.iter().enumerate() {
+            for bit in 0..10 {
+                if v >> bit & 1 == 1 {
+                    intensity_bytes[(i * 10 + bit) / 8] |= 1 << ((i * 10 + bit) % 8);
+                }
+            }
+        }
+        let streams: Vec<Vec<u8>> = vec![
+            xs.iter().flat_map(|v| v.to_le_bytes()).collect(),
+            vec![0; npoints * 4],
+            vec![0; npoints * 4],
+            intensity_bytes,
+            // 1-bit invalid state, with point 1 invalid
+            { let mut s = vec![0u8; npoints.div_ceil(8)]; s[0] = 0b10; s },
+            vec![255; npoints],
+            vec![0; npoints],
+            vec![128; npoints],
+        ];
+
+        // Header, then the binary section (spanning pages, split into two data packets),
+        // then the XML
+        let mut logical = vec![0u8; 48];
+        let section_start = logical.len();
+        logical.extend_from_slice(&[0u8; 32]);
+        let data_start = logical.len();
+        for half in [0, 1] {
+            let parts: Vec<&[u8]> = streams.iter()
+                .map(|s| if half == 0 { &s[..s.len() / 2] } else { &s[s.len() / 2..] })
+                .collect();
+            let mut packet = vec![1u8, 0, 0, 0];
+            packet.extend_from_slice(&(parts.len() as u16).to_le_bytes());
+            for part in &parts {
+                packet.extend_from_slice(&(part.len() as u16).to_le_bytes());
+            }
+            parts.iter().for_each(|part| packet.extend_from_slice(part));
+            packet.resize(packet.len().next_multiple_of(4), 0);
+            let len_minus_1 = (packet.len() - 1) as u16;
+            packet[2..4].copy_from_slice(&len_minus_1.to_le_bytes());
+            logical.extend_from_slice(&packet);
+        }
+        let section_len = (logical.len() - section_start) as u64;
+        logical[section_start] = 1;
+        logical[section_start + 8..section_start + 16].copy_from_slice(&section_len.to_le_bytes());
+        logical[section_start + 16..section_start + 24].copy_from_slice(&physical(data_start).to_le_bytes());
+
+        let xml = format!(r#"<?xml version="1.0"?>
+<e57Root type="Structure" xmlns="http://www.astm.org/COMM/E57/2010-e57-v1.0">
+  <data3D type="Vector" allowHeterogeneousChildren="1">
+    <vectorChild type="Structure">
+      <pose type="Structure">
+        <rotation type="Structure"><w type="Float">0.5</w><x type="Float">0.5</x><y type="Float">0.5</y><z type="Float">0.5</z></rotation>
+        <translation type="Structure"><x type="Float">10</x><y type="Float">20</y><z type="Float">30</z></translation>
+      </pose>
+      <colorLimits type="Structure">
+        <colorRedMinimum type="Integer">0</colorRedMinimum><colorRedMaximum type="Integer">255</colorRedMaximum>
+        <colorGreenMinimum type="Integer">0</colorGreenMinimum><colorGreenMaximum type="Integer">255</colorGreenMaximum>
+        <colorBlueMinimum type="Integer">0</colorBlueMinimum><colorBlueMaximum type="Integer">255</colorBlueMaximum>
+      </colorLimits>
+      <points type="CompressedVector" fileOffset="{}" recordCount="{}">
+        <prototype type="Structure">
+          <cartesianX type="Float" precision="single"/>
+          <cartesianY type="Float" precision="single"/>
+          <cartesianZ type="Float" precision="single"/>
+          <intensity type="Integer" minimum="0" maximum="1023"/>
+          <cartesianInvalidState type="Integer" minimum="0" maximum="1"/>
+          <colorRed type="Integer" minimum="0" maximum="255"/>
+          <colorGreen type="Integer" minimum="0" maximum="255"/>
+          <colorBlue type="Integer" minimum="0" maximum="255"/>
+        </prototype>
+      </points>
+    </vectorChild>
+  </data3D>
+</e57Root>"#, physical(section_start), npoints);
+        let xml_start = logical.len();
+        logical.extend_from_slice(xml.as_bytes());
+        logical[..8].copy_from_slice(SIGNATURE);
+        logical[24..32].copy_from_slice(&physical(xml_start).to_le_bytes());
+        logical[32..40].copy_from_slice(&(xml.len() as u64).to_le_bytes());
+        logical[40..48].copy_from_slice(&1024u64.to_le_bytes());
+
+        let path = std::env::temp_dir().join("pcdpy_test_read.e57");
+        std::fs::write(&path, paginate(&logical)).unwrap();
+        let pc = read_e57(path.to_str().unwrap(), 0);
+        let missing = read_e57(path.to_str().unwrap(), 1);
+        // Offsets and lengths beyond the end of the file
+        let mut corrupt = Vec::new();
+        for (start, value) in [(32, u64::MAX), (24, u64::MAX - 1)] {
+            let mut bytes = logical.clone();
+            bytes[start..start + 8].copy_from_slice(&value.to_le_bytes());
+            std::fs::write(&path, paginate(&bytes)).unwrap();
+            corrupt.push(read_e57(path.to_str().unwrap(), 0));
+        }
+        let mut bytes = logical.clone();
+        bytes[section_start + 8..section_start + 16].copy_from_slice(&(1u64 << 40).to_le_bytes());
+        std::fs::write(&path, paginate(&bytes)).unwrap();
+        corrupt.push(read_e57(path.to_str().unwrap(), 0));
+        // Data packets too short for their bytestream counts and lengths
+        for (at, value) in [(2, 0u16), (2, 7), (6, u16::MAX)] {
+            let mut bytes = logical.clone();
+            bytes[data_start + at..data_start + at + 2].copy_from_slice(&value.to_le_bytes());
+            std::fs::write(&path, paginate(&bytes)).unwrap();
+            corrupt.push(read_e57(path.to_str().unwrap(), 0));
+        }
+        std::fs::remove_file(&path).unwrap();
+        for result in corrupt {
+            assert_eq!(ErrorKind::of(&result.unwrap_err()), Some(ErrorKind::DataCorruption));
+        }
+
+        let pc = pc.unwrap();
+        assert!(missing.is_err());
+        assert_eq!(pc.len(), npoints);
+        assert_eq!(pc.field_names(), ["x", "y", "z", "intensity", "rgb"]);
+        assert_eq!(pc.fields["x"].dtype(), Dtype::F32);
+        assert_eq!(pc.fields["x"].get_row::<f32>(599)[0], 149.75);
+        assert!(pc.fields["x"].get_row::<f32>(1)[0].is_nan());
+        assert_eq!(pc.fields["intensity"].get_row::<f32>(423)[0], 423.0);
+        assert_eq!(pc.unpack_rgb("rgb").unwrap().row(7).to_vec(), [255, 0, 128]);
+        let viewpoint = pc.metadata.read().unwrap().viewpoint.clone();
+        assert_eq!((viewpoint.tx, viewpoint.ty, viewpoint.tz, viewpoint.qw), (10.0, 20.0, 30.0, 0.5));
+    }
+}
@@ -0,0 +1,226 @@
+use anyhow::Result;
+use ndarray::ArcArray2;
+use num_traits::NumCast;
+use crate::fielddata::{CastPolicy, FieldData};
+use crate::metadata::{Data, FieldSchema, Metadata};
+use crate::pointcloud::PointCloud;
+
+/// Reads a LAS (or LAZ, with the `laz` feature) file and returns a new PointCloud.
+///
+/// Coordinates are stored as `x`, `y`, `z` (F64) with the header scale and offset applied.
+/// Each point also gets `intensity` (U16), `return_number`, `number_of_returns` and
+/// `classification` (U8). `gps_time` (F64) and `red`, `green`, `blue` (U16) are only added
+/// when the point record format carries them.
+pub fn read_las(path: &str) -> Result<PointCloud> {
+    let mut reader = las::Reader::from_path(path)?;
+    let format = *reader.header().point_format();
+    let points = reader.read_all()?;
+    let npoints = points.len();
+
+    let mut x = Vec::with_capacity(npoints);
+    let mut y = Vec::with_capacity(npoints);
+    let mut z = Vec::with_capacity(npoints);
+    let mut intensity = Vec::with_capacity(npoints);
+    let mut return_number = Vec::with_capacity(npoints);
+    let mut number_of_returns = Vec::with_capacity(npoints);
+    let mut classification = Vec::with_capacity(npoints);
+    let mut gps_time = Vec::with_capacity(if format.has_gps_time { npoints } else { 0 });
+    let mut red = Vec::with_capacity(if format.has_color { npoints } else { 0 });
+    let mut green = Vec::with_capacity(if format.has_color { npoints } else { 0 });
+    let mut blue = Vec::with_capacity(if format.has_color { npoints } else { 0 });
+
+    for point in points.points() {
+        let point = point?;
+        x.push(point.x);
+        y.push(point.y);
+        z.push(point.z);
+        intensity.push(point.intensity);
+        return_number.push(point.return_number);
+        number_of_returns.push(point.number_of_returns);
+        classification.push(u8::from(point.classification));
+        if format.has_gps_time {
+            gps_time.push(point.gps_time.unwrap_or_default());
+        }
+        if format.has_color {
+            let color = point.color.unwrap_or_default();
+            red.push(color.red);
+            green.push(color.green);
+            blue.push(color.blue);
+        }
+    }
+
+    let mut fields = vec![
+        ("x", FieldData::F64(ArcArray2::from_shape_vec((npoints, 1), x)?)),
+        ("y", FieldData::F64(ArcArray2::from_shape_vec((npoints, 1), y)?)),
+        ("z", FieldData::F64(ArcArray2::from_shape_vec((npoints, 1), z)?)),
+        ("intensity", FieldData::U16(ArcArray2::from_shape_vec((npoints, 1), intensity)?)),
+        ("return_number", FieldData::U8(ArcArray2::from_shape_vec((npoints, 1), return_number)?)),
+        ("number_of_returns", FieldData::U8(ArcArray2::from_shape_vec((npoints, 1), number_of_returns)?)),
+        ("classification", FieldData::U8(ArcArray2::from_shape_vec((npoints, 1), classification)?)),
+    ];
+    if format.has_gps_time {
+        fields.push(("gps_time", FieldData::F64(ArcArray2::from_shape_vec((npoints, 1), gps_time)?)));
+    }
+    if format.has_color {
+        fields.push(("red", FieldData::U16(ArcArray2::from_shape_vec((npoints, 1), red)?)));
+        fields.push(("green", FieldData::U16(ArcArray2::from_shape_vec((npoints, 1), green)?)));
+        fields.push(("blue", FieldData::U16(ArcArray2::from_shape_vec((npoints, 1), blue)?)));
+    }
+
+    let schema: FieldSchema = fields.iter()
+        .map(|(name, data)| (*name, data.dtype(), 1))
+        .collect();
+    let md = Metadata {
+        fields: schema,
+        width: npoints,
+        height: 1,
+        npoints,
+        ..Metadata::default()
+    };
+    let mut pc = PointCloud::empty(&md);
+    for (name, data) in fields {
+        pc.fields.insert(name.to_string(), data);
+    }
+
+    Ok(pc)
+}
+
+/// Returns the values of the field `name` (count of 1) as `A`, saturated to its range, or None
+/// if the cloud has no such field.
+fn column<A: Data + NumCast>(pc: &PointCloud, name: &str) -> Result<Option<Vec<A>>> {
+    let Some(field) = pc.fields.get(name) else {
+        return Ok(None);
+    };
+    anyhow::ensure!(field.count() == 1, "Field '{}' must have a count of 1 to be written to LAS, got {}", name, field.count());
+    Ok(Some(field.try_get_data::<A>(CastPolicy::Saturate)?.into_iter().collect()))
+}
+
+/// Writes the PointCloud to a LAS 1.2 file, compressed to LAZ if `path` ends with `.laz` (which
+/// needs the `laz` feature), with point format 0 to 3 depending on the fields present.
+///
+/// The `x`, `y` and `z` fields (any dtype, no NaN) are stored as multiples of `scale` (e.g.
+/// 0.001 for millimeters) from the minimum corner of the cloud. The fields added by `read_las`
+/// give the other attributes, with their values saturated to the attribute types: `intensity`,
+/// `return_number` and `number_of_returns` (1 if missing), `classification`, `gps_time`, and
+/// `red`, `green` and `blue`, or else a packed `rgb` field scaled to 16 bits. Other fields are
+/// not written.
+pub fn write_las(pc: &PointCloud, path: &str, scale: f64) -> Result<()> {
+    anyhow::ensure!(scale > 0.0 && scale.is_finite(), "LAS scale must be positive, got {}", scale);
+    let positions = pc.coordinates(["x", "y", "z"])?;
+    anyhow::ensure!(positions.iter().flatten().all(|v| v.is_finite()), "LAS files cannot store NaN or infinite coordinates");
+    let intensity = column::<u16>(pc, "intensity")?;
+    let return_number = column::<u8>(pc, "return_number")?;
+    let number_of_returns = column::<u8>(pc, "number_of_returns")?;
+    let classification = column::<u8>(pc, "classification")?;
+    let gps_time = column::<f64>(pc, "gps_time")?;
+    let colors: Option<Vec<[u16; 3]>> = match (column::<u16>(pc, "red")?, column::<u16>(pc, "green")?, column::<u16>(pc, "blue")?) {
+        (Some(red), Some(green), Some(blue)) => Some((0..pc.len()).map(|i| [red[i], green[i], blue[i]]).collect()),
+        _ if pc.fields.contains_key("rgb") => Some(pc.unpack_rgb("rgb")?.rows().into_iter()
+            .map(|c| [c[0], c[1], c[2]].map(|v| v as u16 * 257))
+            .collect()),
+        _ => None,
+    };
+
+    let mut builder = las::Builder::from((1, 2));
+    builder.point_format = las::point::Format::new(match (gps_time.is_some(), colors.is_some()) {
+        (false, false) => 0,
+        (true, false) => 1,
+        (false, true) => 2,
+        (true, true) => 3,
+    })?;
+    builder.point_format.is_compressed = path.ends_with(".laz");
+    builder.generating_software = "pcdpy".to_string();
+    let min = [0, 1, 2].map(|c| positions.iter().map(|p| p[c]).fold(f64::INFINITY, f64::min));
+    let transform = |c: usize| las::Transform {
+        scale,
+        offset: if min[c].is_finite() { (min[c] / scale).floor() * scale } else { 0.0 },
+    };
+    builder.transforms = las::Vector { x: transform(0), y: transform(1), z: transform(2) };
+
+    let mut writer = las::Writer::from_path(path, builder.into_header()?)?;
+    for (i, p) in positions.iter().enumerate() {
+        writer.write_point(las::Point {
+            x: p[0],
+            y: p[1],
+            z: p[2],
+            intensity: intensity.as_ref().map_or(0, |v| v[i]),
+            return_number: return_number.as_ref().map_or(1, |v| v[i]),
+            number_of_returns: number_of_returns.as_ref().map_or(1, |v| v[i]),
+            classification: las::point::Classification::new(classification.as_ref().map_or(0, |v| v[i]))?,
+            gps_time: gps_time.as_ref().map(|v| v[i]),
+            color: colors.as_ref().map(|c| las::Color::new(c[i][0], c[i][1], c[i][2])),
+            ..Default::default()
+        })?;
+    }
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::Dtype;
+
+    #[test]
+    fn test_read_las() {
+        let path = std::env::temp_dir().join("pcdpy_test.las");
+        {
+            let mut builder = las::Builder::from((1, 2));
+            builder.point_format = las::point::Format::new(3).unwrap();
+            let mut writer = las::Writer::from_path(&path, builder.into_header().unwrap()).unwrap();
+            writer.write_point(las::Point {
+                x: 1.0,
+                y: 2.0,
+                z: 3.0,
+                intensity: 7,
+                gps_time: Some(1.5),
+                color: Some(las::Color::new(10, 20, 30)),
+                ..Default::default()
+            }).unwrap();
+            writer.close().unwrap();
+        }
+
+        let pc = read_las(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(pc.len(), 1);
+        assert_eq!(pc.fields.len(), 11);
+        assert_eq!(pc.fields["z"].get_row::<f64>(0)[0], 3.0);
+        assert_eq!(pc.fields["intensity"].get_row::<u16>(0)[0], 7);
+        assert_eq!(pc.fields["green"].get_row::<u16>(0)[0], 20);
+        pc.check_pointcloud().unwrap();
+    }
+
+    #[test]
+    fn test_write_las() {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1), ("y", Dtype::F32, 1), ("z", Dtype::F32, 1), ("intensity", Dtype::F32, 1), ("rgb", Dtype::F32, 1)]),
+            width: 2,
+            height: 1,
+            npoints: 2,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        for i in 0..2 {
+            let row = |v: f32| ndarray::Array1::from(vec![v]);
+            pc.fields.get_mut("x").unwrap().assign_row(i, &row(i as f32 + 0.25));
+            pc.fields.get_mut("z").unwrap().assign_row(i, &row(-1.5));
+            pc.fields.get_mut("intensity").unwrap().assign_row(i, &row(if i == 0 { 7.0 } else { 1e6 }));
+            pc.fields.get_mut("rgb").unwrap().assign_row(i, &row(crate::color::pack_rgb(10, 20, 255)));
+        }
+        let path = std::env::temp_dir().join("pcdpy_test_write.las");
+        write_las(&pc, path.to_str().unwrap(), 0.001).unwrap();
+        let read = read_las(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(read.fields["x"].get_data::<f64>().column(0).to_vec(), [0.25, 1.25]);
+        assert_eq!(read.fields["z"].get_row::<f64>(1)[0], -1.5);
+        assert_eq!(read.fields["intensity"].get_data::<u16>().column(0).to_vec(), [7, u16::MAX]);
+        assert_eq!(read.fields["return_number"].get_row::<u8>(0)[0], 1);
+        assert_eq!([read.fields["red"].get_row::<u16>(0)[0], read.fields["blue"].get_row::<u16>(0)[0]], [2570, u16::MAX]);
+        assert!(!read.fields.contains_key("gps_time"));
+
+        pc.fields.get_mut("y").unwrap().assign_row(0, &ndarray::Array1::from(vec![f32::NAN]));
+        assert!(write_las(&pc, path.to_str().unwrap(), 0.001).is_err());
+        assert!(write_las(&pc, path.to_str().unwrap(), 0.0).is_err());
+    }
+}
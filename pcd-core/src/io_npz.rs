@@ -0,0 +1,307 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use anyhow::Result;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+use crate::error::{ErrorKind, PcdError};
+use crate::fielddata::FieldData;
+use crate::io;
+use crate::metadata::{Dtype, FieldMeta, Metadata};
+use crate::pointcloud::PointCloud;
+use crate::utils::parse_header;
+
+/// Name of the archive entry holding the PCD header of the cloud, as a NumPy string.
+pub const METADATA_KEY: &str = "__metadata__";
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+const DTYPES: [Dtype; 12] = [
+    Dtype::Bool, Dtype::U8, Dtype::U16, Dtype::U32, Dtype::U64,
+    Dtype::I8, Dtype::I16, Dtype::I32, Dtype::I64,
+    Dtype::F16, Dtype::F32, Dtype::F64,
+];
+
+/// The parts of a .npy header used here.
+#[derive(Debug, Clone, PartialEq)]
+struct NpyHeader {
+    descr: String,
+    fortran_order: bool,
+    shape: Vec<usize>,
+}
+
+/// Formats a version 1.0 .npy header, padded so that the data is 64-byte aligned.
+fn npy_header(descr: &str, shape: &[usize]) -> Vec<u8> {
+    let shape = match shape {
+        [n] => format!("({},)", n),
+        _ => format!("({})", shape.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(", ")),
+    };
+    let mut dict = format!("{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}", descr, shape);
+    let unpadded = NPY_MAGIC.len() + 4 + dict.len() + 1;
+    dict.push_str(&" ".repeat(unpadded.next_multiple_of(64) - unpadded));
+    dict.push('\n');
+
+    let mut header = NPY_MAGIC.to_vec();
+    header.extend_from_slice(&[1, 0]);
+    header.extend_from_slice(&(dict.len() as u16).to_le_bytes());
+    header.extend_from_slice(dict.as_bytes());
+    header
+}
+
+/// Returns the text following `'key':` in a .npy header dict.
+fn dict_value<'a>(dict: &'a str, key: &str) -> Result<&'a str> {
+    let pattern = format!("'{}':", key);
+    let start = dict.find(&pattern)
+        .ok_or_else(|| anyhow::anyhow!("The .npy header has no '{}' entry", key))?;
+    Ok(dict[start + pattern.len()..].trim_start())
+}
+
+/// Reads the header of a .npy file of `len` bytes, leaving `reader` at the start of the data.
+fn read_npy_header<R: Read>(reader: &mut R, len: u64) -> Result<NpyHeader> {
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic)?;
+    anyhow::ensure!(&magic[..6] == NPY_MAGIC, "Not a .npy file");
+    let dict_len = if magic[6] == 1 {
+        let mut len = [0u8; 2];
+        reader.read_exact(&mut len)?;
+        u16::from_le_bytes(len) as usize
+    } else {
+        let mut len = [0u8; 4];
+        reader.read_exact(&mut len)?;
+        u32::from_le_bytes(len) as usize
+    };
+    anyhow::ensure!(dict_len as u64 <= len, PcdError::new(ErrorKind::DataCorruption,
+        format!("The .npy header of {} bytes is larger than the file", dict_len)));
+    let mut dict = vec![0u8; dict_len];
+    reader.read_exact(&mut dict)?;
+    let dict = String::from_utf8_lossy(&dict);
+
+    let descr = dict_value(&dict, "descr")?;
+    let descr = descr.strip_prefix('\'')
+        .and_then(|d| d.split('\'').next())
+        .ok_or_else(|| PcdError::new(ErrorKind::UnsupportedDtype, format!("Unsupported .npy dtype: {}", descr)))?;
+    let fortran_order = dict_value(&dict, "fortran_order")?.starts_with("True");
+    let shape = dict_value(&dict, "shape")?;
+    let shape = shape.strip_prefix('(')
+        .and_then(|s| s.split(')').next())
+        .ok_or_else(|| anyhow::anyhow!("Invalid .npy shape: {}", shape))?
+        .split(',')
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .map(|n| n.parse::<usize>().map_err(|_| anyhow::anyhow!("Invalid .npy shape: {}", n)))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(NpyHeader { descr: descr.to_string(), fortran_order, shape })
+}
+
+/// Reads a .npy array of shape (npoints,) or (npoints, count) from a file of `len` bytes
+/// into a field.
+fn read_npy_field<R: Read>(reader: &mut R, len: u64) -> Result<FieldData> {
+    let header = read_npy_header(reader, len)?;
+    let (npoints, count) = match header.shape[..] {
+        [n] => (n, 1),
+        [n, c] => (n, c),
+        _ => anyhow::bail!("Expected an array of shape (npoints,) or (npoints, count), got {} dimensions", header.shape.len()),
+    };
+    let (byte_order, typestr) = header.descr.split_at_checked(1).unwrap_or_default();
+    let dtype = DTYPES.into_iter()
+        .find(|d| &d.as_numpy_typestr()[1..] == typestr)
+        .filter(|_| matches!(byte_order, "<" | ">" | "|" | "="))
+        .ok_or_else(|| PcdError::new(ErrorKind::UnsupportedDtype, format!("Unsupported .npy dtype: {}", header.descr)))?;
+
+    let size = dtype.get_size();
+    let nbytes = npoints.checked_mul(count).and_then(|n| n.checked_mul(size))
+        .filter(|&n| n as u64 <= len)
+        .ok_or_else(|| PcdError::new(ErrorKind::DataCorruption, format!("Array of shape {:?} is larger than its .npy file", header.shape)))?;
+    let mut buffer = vec![0u8; nbytes];
+    reader.read_exact(&mut buffer)?;
+    if byte_order == ">" {
+        buffer.chunks_exact_mut(size).for_each(|value| value.reverse());
+    }
+    if header.fortran_order && count > 1 {
+        // Column-major: gather each point's values from the columns
+        let mut rows = Vec::with_capacity(buffer.len());
+        for i in 0..npoints {
+            for j in 0..count {
+                let start = (j * npoints + i) * size;
+                rows.extend_from_slice(&buffer[start..start + size]);
+            }
+        }
+        buffer = rows;
+    }
+    let mut field = FieldData::new(dtype, npoints, count);
+    field.assign_from_buffer(&buffer);
+    Ok(field)
+}
+
+/// Reads a 0-d NumPy unicode string (UTF-32) from a .npy file of `len` bytes.
+fn read_npy_string<R: Read>(reader: &mut R, len: u64) -> Result<String> {
+    let header = read_npy_header(reader, len)?;
+    let nchars: usize = header.descr.strip_prefix("<U")
+        .and_then(|n| n.parse().ok())
+        .filter(|_| header.shape.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("Expected a string, got an array of {}", header.descr))?;
+    let nbytes = nchars.checked_mul(4).filter(|&n| n as u64 <= len)
+        .ok_or_else(|| PcdError::new(ErrorKind::DataCorruption, format!("String of {} characters is larger than its .npy file", nchars)))?;
+    let mut buffer = vec![0u8; nbytes];
+    reader.read_exact(&mut buffer)?;
+    buffer.chunks_exact(4)
+        .map(|c| u32::from_le_bytes(c.try_into().unwrap()))
+        .take_while(|&c| c != 0)
+        .map(|c| char::from_u32(c).ok_or_else(|| anyhow::anyhow!("Invalid character in string")))
+        .collect()
+}
+
+/// Reads a PointCloud from a NumPy .npz archive. If the archive has a `__metadata__` entry
+/// (see `write_npz`), it gives the fields, their order and the rest of the metadata, and
+/// each field must have an array of the declared dtype and count. Otherwise every array
+/// becomes a field, in archive order, of an unorganized cloud.
+pub fn read_npz(path: &str) -> Result<PointCloud> {
+    let mut archive = ZipArchive::new(BufReader::new(File::open(path)?))?;
+    let metadata_file = format!("{}.npy", METADATA_KEY);
+    let md = if archive.index_for_name(&metadata_file).is_some() {
+        let mut file = archive.by_name(&metadata_file)?;
+        let len = file.size();
+        let header = read_npy_string(&mut file, len)?;
+        Some(parse_header(&mut header.as_bytes())?.0)
+    } else {
+        None
+    };
+    let names: Vec<String> = match &md {
+        Some(md) => md.fields.iter().map(|f| f.name.clone()).collect(),
+        None => archive.file_names()
+            .filter_map(|name| name.strip_suffix(".npy"))
+            .map(str::to_string)
+            .collect(),
+    };
+
+    let mut columns = Vec::with_capacity(names.len());
+    for name in names {
+        let mut file = archive.by_name(&format!("{}.npy", name))
+            .map_err(|_| anyhow::anyhow!("Field '{}' has no array in the archive", name))?;
+        let len = file.size();
+        let field = read_npy_field(&mut file, len).map_err(|e| anyhow::anyhow!("Field '{}': {}", name, e))?;
+        columns.push((name, field));
+    }
+    let npoints = columns.first().map_or(0, |(_, field)| field.npoints());
+    let md = md.map(|mut md| {
+        // The header stores booleans as U; keep the dtype of their arrays
+        for (field_meta, (_, field)) in md.fields.0.iter_mut().zip(&columns) {
+            if field.dtype().storage() == field_meta.dtype {
+                field_meta.dtype = field.dtype();
+            }
+        }
+        md
+    });
+    let md = md.unwrap_or_else(|| Metadata {
+        fields: columns.iter().map(|(name, field)| FieldMeta::new(name.as_str(), field.dtype(), field.count())).collect(),
+        width: npoints,
+        height: 1,
+        npoints,
+        ..Metadata::default()
+    });
+
+    let mut pc = PointCloud::empty(&md);
+    pc.fields.extend(columns);
+    pc.check_pointcloud()?;
+    Ok(pc)
+}
+
+/// Writes the PointCloud to a NumPy .npz archive, with one array per field (of shape
+/// (npoints,) for fields with a count of 1, else (npoints, count)) and the PCD header as a
+/// string under `__metadata__`. Entries are deflate-compressed if `compressed` is set, as
+/// with `numpy.savez_compressed`.
+pub fn write_npz(pc: &PointCloud, path: &str, compressed: bool) -> Result<()> {
+    let md = pc.metadata.read().unwrap();
+    anyhow::ensure!(!md.fields.iter().any(|f| f.name == METADATA_KEY), "Field name '{}' is reserved", METADATA_KEY);
+    let method = if compressed { CompressionMethod::Deflated } else { CompressionMethod::Stored };
+    let options = SimpleFileOptions::default()
+        .compression_method(method)
+        .large_file(true);
+    let mut archive = ZipWriter::new(BufWriter::new(File::create(path)?));
+
+    let mut header = Vec::new();
+    io::write_header(&mut header, &md)?;
+    let header = String::from_utf8(header)?;
+    let nchars = header.chars().count();
+    archive.start_file(format!("{}.npy", METADATA_KEY), options)?;
+    archive.write_all(&npy_header(&format!("<U{}", nchars), &[]))?;
+    for c in header.chars() {
+        archive.write_all(&(c as u32).to_le_bytes())?;
+    }
+
+    for field_meta in md.fields.iter() {
+        let field = pc.fields.get(&field_meta.name)
+            .ok_or_else(|| anyhow::anyhow!("Field '{}' missing from PointCloud data", field_meta.name))?;
+        let shape = if field_meta.count == 1 { vec![md.npoints] } else { vec![md.npoints, field_meta.count] };
+        let mut data = npy_header(field_meta.dtype.as_numpy_typestr(), &shape);
+        field.extend_le_bytes(&mut data);
+        archive.start_file(format!("{}.npy", field_meta.name), options)?;
+        archive.write_all(&data)?;
+    }
+    archive.finish()?.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array1;
+    use crate::metadata::FieldSchema;
+
+    #[test]
+    fn test_npy_header() {
+        for shape in [vec![], vec![5], vec![5, 3]] {
+            let header = npy_header("<f4", &shape);
+            assert_eq!(header.len() % 64, 0);
+            let parsed = read_npy_header(&mut &header[..], header.len() as u64).unwrap();
+            assert_eq!(parsed, NpyHeader { descr: "<f4".to_string(), fortran_order: false, shape });
+        }
+
+        // A Fortran-ordered big-endian (2, 2) array [[1, 2], [3, 4]]
+        let dict = "{'descr': '>u2', 'fortran_order': True, 'shape': (2, 2), }\n";
+        let mut data = b"\x93NUMPY\x01\x00".to_vec();
+        data.extend_from_slice(&(dict.len() as u16).to_le_bytes());
+        data.extend_from_slice(dict.as_bytes());
+        for v in [1u16, 3, 2, 4] {
+            data.extend_from_slice(&v.to_be_bytes());
+        }
+        let field = read_npy_field(&mut &data[..], data.len() as u64).unwrap();
+        assert_eq!(field.get_row::<u16>(1), Array1::from(vec![3, 4]));
+
+        // Malformed dtypes are unsupported
+        for descr in ["", "\u{e9}f4", "<"] {
+            let header = npy_header(descr, &[1]);
+            let err = read_npy_field(&mut &header[..], header.len() as u64).unwrap_err();
+            assert_eq!(ErrorKind::of(&err), Some(ErrorKind::UnsupportedDtype), "{}", descr);
+        }
+
+        // Shapes larger than the file are rejected before allocating
+        for shape in [vec![1 << 40], vec![usize::MAX, 2]] {
+            let header = npy_header("<f4", &shape);
+            let err = read_npy_field(&mut &header[..], header.len() as u64).unwrap_err();
+            assert_eq!(ErrorKind::of(&err), Some(ErrorKind::DataCorruption));
+        }
+    }
+
+    #[test]
+    fn test_npz_round_trip() {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F64, 1), ("label", Dtype::I16, 2), ("valid", Dtype::Bool, 1)]),
+            width: 2,
+            height: 2,
+            npoints: 4,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        pc.fields.get_mut("x").unwrap().assign_row(3, &Array1::from(vec![2.5f64]));
+        pc.fields.get_mut("label").unwrap().assign_row(1, &Array1::from(vec![-1i16, 7]));
+        pc.fields.get_mut("valid").unwrap().assign_row(2, &Array1::from(vec![1u8]));
+        pc.metadata.write().unwrap().viewpoint.tx = 1.0;
+
+        for compressed in [false, true] {
+            let path = std::env::temp_dir().join(format!("pcdpy_test_{}.npz", compressed));
+            let path = path.to_str().unwrap();
+            write_npz(&pc, path, compressed).unwrap();
+            let loaded = read_npz(path).unwrap();
+            std::fs::remove_file(path).unwrap();
+            assert_eq!(loaded, pc);
+        }
+    }
+}
@@ -0,0 +1,329 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use anyhow::Result;
+use crate::io;
+use crate::error::{ErrorKind, PcdError};
+use crate::metadata::{Dtype, Encoding, FieldSchema, Metadata};
+use crate::pointcloud::PointCloud;
+use crate::utils;
+
+/// Storage format of the PLY body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+/// A property declared on a PLY element.
+#[derive(Debug, Clone, PartialEq)]
+enum PlyProperty {
+    Scalar { name: String, dtype: Dtype },
+    List { count_dtype: Dtype, item_dtype: Dtype },
+}
+
+/// An element (e.g. `vertex`, `face`) declared in the PLY header.
+#[derive(Debug, Clone, PartialEq)]
+struct PlyElement {
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>,
+}
+
+/// Returns the `Dtype` corresponding to a PLY property type name.
+fn dtype_from_ply(t: &str) -> Result<Dtype> {
+    match t {
+        "char" | "int8" => Ok(Dtype::I8),
+        "uchar" | "uint8" => Ok(Dtype::U8),
+        "short" | "int16" => Ok(Dtype::I16),
+        "ushort" | "uint16" => Ok(Dtype::U16),
+        "int" | "int32" => Ok(Dtype::I32),
+        "uint" | "uint32" => Ok(Dtype::U32),
+        "float" | "float32" => Ok(Dtype::F32),
+        "double" | "float64" => Ok(Dtype::F64),
+        _ => anyhow::bail!(PcdError::new(ErrorKind::UnsupportedDtype, format!("Unsupported PLY property type: {}", t))),
+    }
+}
+
+/// Returns the PLY property type name for a `Dtype`.
+/// PLY has no 64-bit integer types, so `U64` and `I64` cannot be written, nor boolean or half
+/// float types (`write_ply` stores those fields as `Dtype::storage`).
+fn dtype_to_ply(dtype: Dtype) -> Result<&'static str> {
+    match dtype {
+        Dtype::I8 => Ok("char"),
+        Dtype::U8 => Ok("uchar"),
+        Dtype::I16 => Ok("short"),
+        Dtype::U16 => Ok("ushort"),
+        Dtype::I32 => Ok("int"),
+        Dtype::U32 => Ok("uint"),
+        Dtype::F32 => Ok("float"),
+        Dtype::F64 => Ok("double"),
+        Dtype::U64 | Dtype::I64 | Dtype::Bool | Dtype::F16 => anyhow::bail!("Field type {} cannot be stored in a PLY file", dtype),
+    }
+}
+
+/// Parses the PLY header and returns the body format and the declared elements.
+fn read_header(reader: &mut BufReader<File>) -> Result<(PlyFormat, Vec<PlyElement>)> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if line.trim() != "ply" {
+        anyhow::bail!("Not a PLY file: missing 'ply' magic");
+    }
+
+    let mut format: Option<PlyFormat> = None;
+    let mut elements: Vec<PlyElement> = Vec::new();
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            anyhow::bail!("Unexpected EOF while reading PLY header");
+        }
+        let values = line.split_ascii_whitespace().collect::<Vec<&str>>();
+        if values.is_empty() {
+            continue;
+        }
+
+        match values[0] {
+            "format" => {
+                if values.len() != 3 {
+                    anyhow::bail!("Invalid format line: {}", line.trim());
+                }
+                format = Some(match values[1] {
+                    "ascii" => PlyFormat::Ascii,
+                    "binary_little_endian" => PlyFormat::BinaryLittleEndian,
+                    _ => anyhow::bail!(PcdError::new(ErrorKind::Header, format!("Unsupported PLY format: {}", values[1]))),
+                });
+            }
+            "comment" | "obj_info" => continue,
+            "element" => {
+                if values.len() != 3 {
+                    anyhow::bail!("Invalid element line: {}", line.trim());
+                }
+                elements.push(PlyElement {
+                    name: values[1].to_string(),
+                    count: values[2].parse()?,
+                    properties: Vec::new(),
+                });
+            }
+            "property" => {
+                let element = elements.last_mut()
+                    .ok_or_else(|| anyhow::anyhow!("Property declared before any element"))?;
+                let property = match values.get(1) {
+                    Some(&"list") if values.len() == 5 => PlyProperty::List {
+                        count_dtype: dtype_from_ply(values[2])?,
+                        item_dtype: dtype_from_ply(values[3])?,
+                    },
+                    Some(t) if values.len() == 3 => PlyProperty::Scalar {
+                        name: values[2].to_string(),
+                        dtype: dtype_from_ply(t)?,
+                    },
+                    _ => anyhow::bail!("Invalid property line: {}", line.trim()),
+                };
+                element.properties.push(property);
+            }
+            "end_header" => break,
+            _ => anyhow::bail!("Invalid PLY header line: {}", line.trim()),
+        }
+    }
+
+    let format = format.ok_or_else(|| anyhow::anyhow!("Missing PLY format line"))?;
+    Ok((format, elements))
+}
+
+/// Reads a single little-endian integer of the given dtype (used for list lengths). Negative
+/// lengths are a DataCorruption error.
+fn read_list_len(reader: &mut BufReader<File>, dtype: Dtype) -> Result<usize> {
+    let buf = io::read_exact_chunk(reader, dtype.get_size())?;
+    let len = match dtype {
+        Dtype::U8 => buf[0] as i64,
+        Dtype::I8 => buf[0] as i8 as i64,
+        Dtype::U16 => u16::from_le_bytes([buf[0], buf[1]]) as i64,
+        Dtype::I16 => i16::from_le_bytes([buf[0], buf[1]]) as i64,
+        Dtype::U32 => u32::from_le_bytes(buf[..4].try_into()?) as i64,
+        Dtype::I32 => i32::from_le_bytes(buf[..4].try_into()?) as i64,
+        _ => anyhow::bail!("Invalid PLY list length type: {}", dtype),
+    };
+    usize::try_from(len).map_err(|_| PcdError::new(ErrorKind::DataCorruption, format!("Negative PLY list length: {}", len)).into())
+}
+
+/// Skips over all records of an element that precedes the vertex element.
+fn skip_element(reader: &mut BufReader<File>, format: PlyFormat, element: &PlyElement) -> Result<()> {
+    for _ in 0..element.count {
+        match format {
+            PlyFormat::Ascii => {
+                io::read_nonempty_line(reader)?;
+            }
+            PlyFormat::BinaryLittleEndian => {
+                for property in &element.properties {
+                    match property {
+                        PlyProperty::Scalar { dtype, .. } => {
+                            io::read_exact_chunk(reader, dtype.get_size())?;
+                        }
+                        PlyProperty::List { count_dtype, item_dtype } => {
+                            let len = read_list_len(reader, *count_dtype)?;
+                            io::read_exact_chunk(reader, len * item_dtype.get_size())?;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads a PLY file and returns a PointCloud built from its `vertex` element.
+/// Every scalar vertex property becomes a field with a count of 1; properties declared
+/// more than once are renamed as in PCD headers (see `utils::rename_duplicate_fields`).
+/// Other elements (e.g. faces) are ignored.
+pub fn read_ply(path: &str) -> Result<PointCloud> {
+    let file = File::open(path)?;
+    let mut reader = BufReader::new(file);
+
+    let (format, elements) = read_header(&mut reader)?;
+    let vertex_idx = elements.iter().position(|e| e.name == "vertex")
+        .ok_or_else(|| anyhow::anyhow!("PLY file has no vertex element"))?;
+
+    for element in &elements[..vertex_idx] {
+        skip_element(&mut reader, format, element)?;
+    }
+
+    let vertex = &elements[vertex_idx];
+    let mut fields: FieldSchema = vertex.properties.iter()
+        .map(|p| match p {
+            PlyProperty::Scalar { name, dtype } => Ok((name.clone(), *dtype, 1)),
+            PlyProperty::List { .. } => anyhow::bail!("List properties on the vertex element are not supported"),
+        })
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .collect();
+    utils::rename_duplicate_fields(&mut fields, &mut Vec::new());
+
+    let md = Metadata {
+        fields,
+        width: vertex.count,
+        height: 1,
+        npoints: vertex.count,
+        encoding: match format {
+            PlyFormat::Ascii => Encoding::Ascii,
+            PlyFormat::BinaryLittleEndian => Encoding::Binary,
+        },
+        ..Metadata::default()
+    };
+    let mut pc = PointCloud::new(&md);
+
+    match format {
+        PlyFormat::Ascii => {
+            io::read_ascii_data(&mut reader, &mut pc, true)?;
+        }
+        PlyFormat::BinaryLittleEndian => io::read_binary_data(&mut reader, &mut pc)?,
+    }
+
+    Ok(pc)
+}
+
+/// Writes the PointCloud to a PLY file as a single `vertex` element.
+/// Fields with a count greater than 1 are written as one property per column,
+/// named `<field>_<index>`. Boolean fields are written as `uchar` and float16 fields as `float`.
+pub fn write_ply(pc: &PointCloud, path: &str, format: PlyFormat) -> Result<()> {
+    let pc = &pc.to_storage()?;
+    let file = File::create(path)?;
+    let mut writer = std::io::BufWriter::new(file);
+    {
+        let md = pc.metadata.read().unwrap();
+        writeln!(writer, "ply")?;
+        match format {
+            PlyFormat::Ascii => writeln!(writer, "format ascii 1.0")?,
+            PlyFormat::BinaryLittleEndian => writeln!(writer, "format binary_little_endian 1.0")?,
+        }
+        writeln!(writer, "element vertex {}", md.npoints)?;
+        for field_meta in md.fields.iter() {
+            let ply_type = dtype_to_ply(field_meta.dtype)?;
+            if field_meta.count == 1 {
+                writeln!(writer, "property {} {}", ply_type, field_meta.name)?;
+            } else {
+                for i in 0..field_meta.count {
+                    writeln!(writer, "property {} {}_{}", ply_type, field_meta.name, i)?;
+                }
+            }
+        }
+        writeln!(writer, "end_header")?;
+    }
+
+    match format {
+        PlyFormat::Ascii => io::write_ascii_data(&mut writer, pc, &io::WriteOptions::default())?,
+        PlyFormat::BinaryLittleEndian => io::write_binary_data(&mut writer, pc)?,
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dtype_ply_round_trip() {
+        for t in ["char", "uchar", "short", "ushort", "int", "uint", "float", "double"] {
+            let dtype = dtype_from_ply(t).unwrap();
+            assert_eq!(dtype_to_ply(dtype).unwrap(), t);
+        }
+        assert!(dtype_to_ply(Dtype::U64).is_err());
+        assert!(dtype_from_ply("long").is_err());
+    }
+
+    #[test]
+    fn test_read_write_ply() {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1), ("rgb", Dtype::U8, 3)]),
+            width: 2,
+            height: 1,
+            npoints: 2,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        pc.fields.get_mut("x").unwrap().assign_row(1, &ndarray::Array1::from(vec![1.5f32]));
+        pc.fields.get_mut("rgb").unwrap().assign_row(0, &ndarray::Array1::from(vec![1u8, 2, 3]));
+
+        for format in [PlyFormat::Ascii, PlyFormat::BinaryLittleEndian] {
+            let path = std::env::temp_dir().join(format!("pcdpy_test_{:?}.ply", format));
+            let path = path.to_str().unwrap();
+            write_ply(&pc, path, format).unwrap();
+            let loaded = read_ply(path).unwrap();
+            std::fs::remove_file(path).unwrap();
+
+            assert_eq!(loaded.len(), 2);
+            assert_eq!(loaded.fields.len(), 4);
+            assert_eq!(loaded.fields["x"].get_row::<f32>(1)[0], 1.5);
+            assert_eq!(loaded.fields["rgb_2"].get_row::<u8>(0)[0], 3);
+        }
+    }
+
+    #[test]
+    fn test_negative_list_length() {
+        let path = std::env::temp_dir().join("pcdpy_test_negative_list.ply");
+        let header = "ply\nformat binary_little_endian 1.0\nelement face 1\nproperty list int uchar vertex_indices\n\
+            element vertex 1\nproperty float x\nend_header\n";
+        std::fs::write(&path, [header.as_bytes(), &(-1i32).to_le_bytes(), &0f32.to_le_bytes()].concat()).unwrap();
+        let err = read_ply(path.to_str().unwrap()).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(ErrorKind::of(&err), Some(ErrorKind::DataCorruption));
+    }
+
+    #[test]
+    fn test_duplicate_properties() {
+        let header = |format: &str| format!("ply\nformat {} 1.0\nelement vertex 1\nproperty float x\nproperty char x\nend_header\n", format);
+        let files = [
+            ("ascii", [header("ascii").as_bytes(), b"1.5 -2\n"].concat()),
+            ("binary", [header("binary_little_endian").as_bytes(), &1.5f32.to_le_bytes(), &(-2i8).to_le_bytes()].concat()),
+        ];
+        for (name, bytes) in files {
+            let path = std::env::temp_dir().join(format!("pcdpy_test_duplicate_{}.ply", name));
+            std::fs::write(&path, bytes).unwrap();
+            let pc = read_ply(path.to_str().unwrap()).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            pc.check_pointcloud().unwrap();
+            assert_eq!(pc.field_names(), ["x", "x_1"]);
+            assert_eq!(pc.fields["x"].get_row::<f32>(0)[0], 1.5);
+            assert_eq!(pc.fields["x_1"].get_row::<i8>(0)[0], -2);
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::{Arc, OnceLock, RwLock};
+use anyhow::{Context, Result};
+use object_store::aws::AmazonS3Builder;
+use object_store::http::HttpBuilder;
+use object_store::path::Path as ObjectPath;
+use object_store::ObjectStore;
+use tokio::runtime::Runtime;
+use url::Url;
+
+/// Size of the first range requested by a `RemoteReader`, enough for a PCD header.
+const MIN_FETCH: usize = 64 * 1024;
+/// Largest range requested by a `RemoteReader`; the range size doubles up to it while the
+/// object is read sequentially.
+const MAX_FETCH: usize = 16 * 1024 * 1024;
+
+/// Object stores used for URLs starting with a prefix. See `register_store`.
+static STORES: RwLock<Vec<(String, Arc<dyn ObjectStore>)>> = RwLock::new(Vec::new());
+
+/// Use `store` for the URLs starting with `prefix` (e.g. "s3://bucket" or "memory://"),
+/// instead of the default store for their scheme, replacing a store previously registered
+/// for the same prefix. The rest of the URL is the path of the object in the store.
+///
+/// This is the way to read from other object stores, or from S3 with explicit credentials.
+pub fn register_store(prefix: &str, store: Arc<dyn ObjectStore>) {
+    let mut stores = STORES.write().unwrap();
+    stores.retain(|(p, _)| p != prefix);
+    stores.push((prefix.to_string(), store));
+}
+
+/// Whether `path` is a URL read from an object store: it starts with a registered prefix
+/// or is an s3://, http:// or https:// URL.
+pub fn is_url(path: &str) -> bool {
+    STORES.read().unwrap().iter().any(|(prefix, _)| path.starts_with(prefix.as_str()))
+        || ["s3://", "http://", "https://"].iter().any(|scheme| path.starts_with(scheme))
+}
+
+/// Return the store and object path of `url`. s3:// URLs use the credentials and region
+/// of the standard AWS environment variables (unsigned requests with
+/// AWS_SKIP_SIGNATURE=true), and http(s):// URLs are read with plain GET requests.
+fn resolve(url: &str) -> Result<(Arc<dyn ObjectStore>, ObjectPath)> {
+    let registered = STORES.read().unwrap().iter()
+        .filter(|(prefix, _)| url.starts_with(prefix.as_str()))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(prefix, store)| (prefix.len(), store.clone()));
+    if let Some((prefix_len, store)) = registered {
+        return Ok((store, ObjectPath::parse(url[prefix_len..].trim_start_matches('/'))?));
+    }
+
+    let parsed = Url::parse(url)?;
+    let path = ObjectPath::from_url_path(parsed.path())?;
+    let store: Arc<dyn ObjectStore> = match parsed.scheme() {
+        "s3" => Arc::new(AmazonS3Builder::from_env().with_url(url).build()?),
+        "http" | "https" => Arc::new(HttpBuilder::new().with_url(&parsed[..url::Position::BeforePath]).build()?),
+        scheme => anyhow::bail!("Unsupported URL scheme: {}", scheme),
+    };
+    Ok((store, path))
+}
+
+/// The runtime driving object store requests for the blocking readers.
+fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("Failed to start the object store runtime")
+    })
+}
+
+/// A seekable reader over an object in an object store, which fetches the parts that are
+/// read with range requests. Reading a header only fetches the start of the object, and
+/// seeking over data (e.g. the points skipped by `io::ReadOptions::start`) skips fetching it.
+///
+/// Reads block the calling thread, so they must not be made from an async task.
+pub struct RemoteReader {
+    store: Arc<dyn ObjectStore>,
+    path: ObjectPath,
+    len: u64,
+    position: u64,
+    /// The last fetched range, starting at `block_start`.
+    block: Vec<u8>,
+    block_start: u64,
+    fetch_size: usize,
+}
+
+impl RemoteReader {
+    /// Opens the object at `url` (see `is_url`), requesting its size.
+    pub fn open(url: &str) -> Result<Self> {
+        let (store, path) = resolve(url)?;
+        Self::with_store(store, path).with_context(|| format!("Failed to open {}", url))
+    }
+
+    /// Opens the object at `path` in `store`, requesting its size.
+    pub fn with_store(store: Arc<dyn ObjectStore>, path: ObjectPath) -> Result<Self> {
+        let len = runtime().block_on(store.head(&path))?.size;
+        Ok(Self { store, path, len, position: 0, block: Vec::new(), block_start: 0, fetch_size: MIN_FETCH })
+    }
+
+    /// Fetch the range starting at the current position.
+    fn fetch(&mut self) -> io::Result<()> {
+        // Grow the ranges while reading sequentially, start over after a seek
+        self.fetch_size = if self.position == self.block_start + self.block.len() as u64 && !self.block.is_empty() {
+            (self.fetch_size * 2).min(MAX_FETCH)
+        } else {
+            MIN_FETCH
+        };
+        let end = (self.position + self.fetch_size as u64).min(self.len);
+        let bytes = runtime().block_on(self.store.get_range(&self.path, self.position..end))
+            .map_err(|e| match e {
+                object_store::Error::NotFound { .. } => io::Error::new(io::ErrorKind::NotFound, e),
+                e => io::Error::other(e),
+            })?;
+        self.block = bytes.to_vec();
+        self.block_start = self.position;
+        Ok(())
+    }
+}
+
+impl Read for RemoteReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.position >= self.len {
+            return Ok(0);
+        }
+        let block_end = self.block_start + self.block.len() as u64;
+        if self.position < self.block_start || self.position >= block_end {
+            self.fetch()?;
+        }
+        let offset = (self.position - self.block_start) as usize;
+        let n = buf.len().min(self.block.len() - offset);
+        buf[..n].copy_from_slice(&self.block[offset..offset + n]);
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for RemoteReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        self.position = position.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid seek to a negative position"))?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufReader;
+    use ndarray::Array1;
+    use object_store::memory::InMemory;
+    use object_store::PutPayload;
+    use crate::io::{ReadOptions, WriteOptions};
+    use crate::metadata::{Dtype, Encoding, FieldSchema, Metadata};
+    use crate::pointcloud::PointCloud;
+    use crate::utils::read_metadata;
+
+    #[test]
+    fn test_remote_reader() {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1), ("label", Dtype::U16, 2)]),
+            width: 5000,
+            height: 1,
+            npoints: 5000,
+            encoding: Encoding::Binary,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        for i in 0..5000 {
+            pc.fields.get_mut("x").unwrap().assign_row(i, &Array1::from(vec![i as f32]));
+            pc.fields.get_mut("label").unwrap().assign_row(i, &Array1::from(vec![i as u16, 7]));
+        }
+        let mut data = Vec::new();
+        pc.to_pcd_writer_with(&mut data, &WriteOptions::default()).unwrap();
+
+        let store = Arc::new(InMemory::new());
+        runtime().block_on(store.put(&ObjectPath::from("clouds/a.pcd"), PutPayload::from(data.clone()))).unwrap();
+        register_store("memory://test", store);
+        assert!(is_url("memory://test/clouds/a.pcd"));
+        assert!(is_url("https://example.com/a.pcd"));
+        assert!(!is_url("clouds/a.pcd"));
+
+        let mut reader = RemoteReader::open("memory://test/clouds/a.pcd").unwrap();
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).unwrap();
+        assert_eq!(read, data);
+        assert_eq!(reader.seek(SeekFrom::End(-4)).unwrap(), data.len() as u64 - 4);
+        assert!(reader.seek(SeekFrom::Current(-(data.len() as i64))).is_err());
+
+        let (header, _) = read_metadata("memory://test/clouds/a.pcd").unwrap();
+        assert_eq!(header.npoints, 5000);
+
+        let options = ReadOptions { start: 4000, count: Some(2), ..ReadOptions::default() };
+        let reader = RemoteReader::open("memory://test/clouds/a.pcd").unwrap();
+        let (part, _) = PointCloud::from_pcd_reader_with(&mut BufReader::new(reader), &options).unwrap();
+        assert_eq!(part, PointCloud::from_pcd_reader_with(&mut io::Cursor::new(&data), &options).unwrap().0);
+
+        assert!(RemoteReader::open("memory://test/missing.pcd").is_err());
+    }
+}
@@ -0,0 +1,247 @@
+use std::borrow::Cow;
+use anyhow::Result;
+use crate::error::{ErrorKind, PcdError};
+use crate::metadata::{Dtype, FieldMeta, FieldSchema, Metadata};
+use crate::pointcloud::PointCloud;
+
+/// A field of a sensor_msgs/PointCloud2 message (sensor_msgs/PointField).
+#[derive(Debug, Clone, PartialEq)]
+pub struct RosField {
+    pub name: String,
+    /// Byte offset of the field in each point record.
+    pub offset: usize,
+    /// PointField datatype constant (1 = INT8 ... 8 = FLOAT64).
+    pub datatype: u8,
+    pub count: usize,
+}
+
+/// The layout and data of a sensor_msgs/PointCloud2 message. Points are stored as records of
+/// `point_step` bytes, in rows of `row_step` bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RosCloud<'a> {
+    pub fields: Vec<RosField>,
+    pub height: usize,
+    pub width: usize,
+    pub point_step: usize,
+    pub row_step: usize,
+    pub is_bigendian: bool,
+    /// True if no point has a NaN value.
+    pub is_dense: bool,
+    pub data: Cow<'a, [u8]>,
+}
+
+/// Returns the `Dtype` corresponding to a PointField datatype constant.
+fn dtype_from_ros(datatype: u8) -> Result<Dtype> {
+    match datatype {
+        1 => Ok(Dtype::I8),
+        2 => Ok(Dtype::U8),
+        3 => Ok(Dtype::I16),
+        4 => Ok(Dtype::U16),
+        5 => Ok(Dtype::I32),
+        6 => Ok(Dtype::U32),
+        7 => Ok(Dtype::F32),
+        8 => Ok(Dtype::F64),
+        _ => anyhow::bail!(PcdError::new(ErrorKind::UnsupportedDtype, format!("Unsupported PointField datatype: {}", datatype))),
+    }
+}
+
+/// Returns the PointField datatype constant for a `Dtype`.
+/// PointField has no 64-bit integer types, so `U64` and `I64` cannot be written, nor boolean
+/// or half float types (`write_ros` stores those fields as `Dtype::storage`).
+fn dtype_to_ros(dtype: Dtype) -> Result<u8> {
+    match dtype {
+        Dtype::I8 => Ok(1),
+        Dtype::U8 => Ok(2),
+        Dtype::I16 => Ok(3),
+        Dtype::U16 => Ok(4),
+        Dtype::I32 => Ok(5),
+        Dtype::U32 => Ok(6),
+        Dtype::F32 => Ok(7),
+        Dtype::F64 => Ok(8),
+        Dtype::U64 | Dtype::I64 | Dtype::Bool | Dtype::F16 => anyhow::bail!("Field type {} cannot be stored in a PointCloud2 message", dtype),
+    }
+}
+
+/// Reads the points of a PointCloud2 message into a new PointCloud with one field per
+/// PointField, in message order. Bytes not covered by a field (alignment padding, and the
+/// end of rows longer than `width * point_step`) are skipped.
+pub fn read_ros(cloud: &RosCloud) -> Result<PointCloud> {
+    let mut fields = FieldSchema::new();
+    for field in &cloud.fields {
+        anyhow::ensure!(!field.name.is_empty(), "Field name cannot be empty");
+        anyhow::ensure!(!fields.iter().any(|f| f.name == field.name), "Duplicate field name: {}", field.name);
+        // Some publishers leave COUNT at 0 for scalar fields
+        fields.0.push(FieldMeta::new(field.name.as_str(), dtype_from_ros(field.datatype)?, field.count.max(1)));
+    }
+    // The sizes come from the message, so products that overflow mean it is corrupt
+    let overflow = |what: String| -> anyhow::Error {
+        PcdError::new(ErrorKind::DataCorruption, format!("{} overflows in a message of {} x {} points of {} bytes", what, cloud.width, cloud.height, cloud.point_step)).into()
+    };
+    for (field_meta, field) in fields.iter().zip(&cloud.fields) {
+        let end = field_meta.get_size().checked_mul(field_meta.count).and_then(|size| size.checked_add(field.offset))
+            .ok_or_else(|| overflow(format!("End of field '{}'", field.name)))?;
+        anyhow::ensure!(end <= cloud.point_step,
+            "Field '{}' at offset {} exceeds the point step of {} bytes", field.name, field.offset, cloud.point_step);
+    }
+    let npoints = cloud.width.checked_mul(cloud.height).ok_or_else(|| overflow("Number of points".to_string()))?;
+    let record_bytes = cloud.width.checked_mul(cloud.point_step).ok_or_else(|| overflow("Row size".to_string()))?;
+    anyhow::ensure!(cloud.row_step >= record_bytes,
+        "Row step of {} bytes is smaller than {} points of {} bytes", cloud.row_step, cloud.width, cloud.point_step);
+    let data_bytes = cloud.row_step.checked_mul(cloud.height).ok_or_else(|| overflow("Data size".to_string()))?;
+    anyhow::ensure!(cloud.data.len() >= data_bytes,
+        "Expected {} bytes of data, got {}", data_bytes, cloud.data.len());
+
+    let mut records: Cow<[u8]> = if cloud.row_step == record_bytes {
+        Cow::Borrowed(&cloud.data[..npoints * cloud.point_step])
+    } else {
+        Cow::Owned(cloud.data.chunks(cloud.row_step.max(1)).take(cloud.height)
+            .flat_map(|row| &row[..record_bytes])
+            .copied()
+            .collect())
+    };
+    if cloud.is_bigendian {
+        let records = records.to_mut();
+        for (field_meta, field) in fields.iter().zip(&cloud.fields) {
+            let size = field_meta.get_size();
+            for record in records.chunks_exact_mut(cloud.point_step) {
+                let values = &mut record[field.offset..field.offset + size * field_meta.count];
+                values.chunks_exact_mut(size).for_each(|value| value.reverse());
+            }
+        }
+    }
+
+    let md = Metadata {
+        fields,
+        width: cloud.width,
+        height: cloud.height,
+        npoints,
+        ..Metadata::default()
+    };
+    let mut pc = PointCloud::new(&md);
+    for field in &cloud.fields {
+        pc.fields.get_mut(&field.name).unwrap()
+            .assign_from_interleaved_buffer(&records, cloud.point_step, field.offset);
+    }
+    Ok(pc)
+}
+
+/// Writes the PointCloud as a little-endian PointCloud2 message with tightly packed records
+/// in schema order. Padding fields (see `FieldMeta::is_padding`) are left as zeroed gaps
+/// rather than declared as PointFields. Boolean fields are written as UINT8 and float16
+/// fields as FLOAT32.
+pub fn write_ros(pc: &PointCloud) -> Result<RosCloud<'static>> {
+    let pc = &pc.to_storage()?;
+    let md = pc.metadata.read().unwrap();
+    let mut fields = Vec::with_capacity(md.fields.len());
+    let mut offsets = Vec::with_capacity(md.fields.len());
+    let mut point_step = 0;
+    for field_meta in md.fields.iter() {
+        if !field_meta.is_padding() {
+            fields.push(RosField {
+                name: field_meta.name.clone(),
+                offset: point_step,
+                datatype: dtype_to_ros(field_meta.dtype)?,
+                count: field_meta.count,
+            });
+            offsets.push((&field_meta.name, point_step));
+        }
+        point_step += field_meta.get_size() * field_meta.count;
+    }
+
+    let mut data = vec![0u8; md.npoints * point_step];
+    let mut is_dense = true;
+    for (name, offset) in offsets {
+        let field = pc.fields.get(name)
+            .ok_or_else(|| anyhow::anyhow!("Field '{}' missing from PointCloud data", name))?;
+        field.write_to_interleaved_buffer(&mut data, point_step, offset);
+        is_dense &= !field.nan_mask().iter().any(|&nan| nan);
+    }
+    Ok(RosCloud {
+        fields,
+        height: md.height,
+        width: md.width,
+        point_step,
+        row_step: md.width * point_step,
+        is_bigendian: false,
+        is_dense,
+        data: Cow::Owned(data),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array1;
+
+    #[test]
+    fn test_read_ros_layout() {
+        // x: f32 at 0, intensity: u16 at 8 (4 bytes of alignment padding), rows padded to 40 bytes
+        let mut data = Vec::new();
+        for row in 0..2u16 {
+            for col in 0..2u16 {
+                let mut record = [0u8; 12];
+                record[..4].copy_from_slice(&(col as f32 + 0.5).to_be_bytes());
+                record[8..10].copy_from_slice(&(10 * row + col).to_be_bytes());
+                data.extend_from_slice(&record);
+            }
+            data.extend_from_slice(&[0xff; 16]);
+        }
+        let cloud = RosCloud {
+            fields: vec![
+                RosField { name: "x".to_string(), offset: 0, datatype: 7, count: 1 },
+                RosField { name: "intensity".to_string(), offset: 8, datatype: 4, count: 0 },
+            ],
+            height: 2,
+            width: 2,
+            point_step: 12,
+            row_step: 40,
+            is_bigendian: true,
+            is_dense: true,
+            data: Cow::Borrowed(&data),
+        };
+        let pc = read_ros(&cloud).unwrap();
+        assert_eq!(pc.len(), 4);
+        assert!(pc.is_organized());
+        assert_eq!(pc.fields["x"].get_row::<f32>(3)[0], 1.5);
+        assert_eq!(pc.fields["intensity"].get_row::<u16>(3)[0], 11);
+
+        let short = RosCloud { data: Cow::Borrowed(&data[..70]), ..cloud.clone() };
+        assert!(read_ros(&short).is_err());
+        let mut overlapping = cloud.clone();
+        overlapping.fields[1].offset = 11;
+        assert!(read_ros(&overlapping).is_err());
+        for huge in [
+            RosCloud { width: usize::MAX, ..cloud.clone() },
+            RosCloud { height: usize::MAX / 2, row_step: 40, ..cloud.clone() },
+            RosCloud { fields: vec![RosField { offset: usize::MAX, ..cloud.fields[0].clone() }], ..cloud.clone() },
+        ] {
+            assert_eq!(ErrorKind::of(&read_ros(&huge).unwrap_err()), Some(ErrorKind::DataCorruption));
+        }
+    }
+
+    #[test]
+    fn test_ros_round_trip() {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1), ("_", Dtype::U8, 4), ("label", Dtype::U16, 2)]),
+            width: 3,
+            height: 1,
+            npoints: 3,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        pc.fields.get_mut("label").unwrap().assign_row(2, &Array1::from(vec![7u16, 8]));
+
+        let cloud = write_ros(&pc).unwrap();
+        assert_eq!(cloud.point_step, 12);
+        assert_eq!(cloud.fields.iter().map(|f| f.offset).collect::<Vec<_>>(), [0, 8]);
+        assert!(cloud.is_dense);
+        let loaded = read_ros(&cloud).unwrap();
+        assert_eq!(loaded.fields["label"], pc.fields["label"]);
+        assert_eq!(loaded.fields["x"], pc.fields["x"]);
+
+        pc.fields.get_mut("x").unwrap().assign_row(0, &Array1::from(vec![f32::NAN]));
+        assert!(!write_ros(&pc).unwrap().is_dense);
+        pc.cast_field("label", Dtype::U64, crate::fielddata::CastPolicy::Error).unwrap();
+        assert!(write_ros(&pc).is_err());
+    }
+}
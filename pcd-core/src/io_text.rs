@@ -0,0 +1,346 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use anyhow::Result;
+use ndarray::Array2;
+use rayon::prelude::*;
+use crate::fielddata::FieldData;
+use crate::io::{self, FloatFormat};
+use crate::metadata::{FieldMeta, Metadata};
+use crate::pointcloud::PointCloud;
+
+/// Number of lines parsed by each rayon task.
+const LINES_PER_TASK: usize = 1 << 14;
+
+/// How the values of a line are split.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Delimiter {
+    Whitespace,
+    Char(char),
+}
+
+impl Delimiter {
+    fn new(c: char) -> Self {
+        if c.is_whitespace() && c != '\t' { Delimiter::Whitespace } else { Delimiter::Char(c) }
+    }
+
+    /// Picks the delimiter of a line: a comma, tab or semicolon if it contains one, in that
+    /// order, else runs of whitespace.
+    fn infer(line: &str) -> Self {
+        [',', '\t', ';'].into_iter()
+            .find(|&c| line.contains(c))
+            .map_or(Delimiter::Whitespace, Delimiter::Char)
+    }
+
+    fn split<'a>(&self, line: &'a str) -> Tokens<'a> {
+        match *self {
+            Delimiter::Whitespace => Tokens::Whitespace(line.split_whitespace()),
+            Delimiter::Char(c) => Tokens::Char(line.split(c)),
+        }
+    }
+}
+
+/// The values of a line, split by a `Delimiter`.
+enum Tokens<'a> {
+    Whitespace(std::str::SplitWhitespace<'a>),
+    Char(std::str::Split<'a, char>),
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        match self {
+            Tokens::Whitespace(tokens) => tokens.next(),
+            Tokens::Char(tokens) => tokens.next().map(str::trim),
+        }
+    }
+}
+
+/// Values of one column of a chunk of lines. Columns stay integer until a value that is not
+/// an integer is found.
+#[derive(Debug)]
+enum Column {
+    Int(Vec<i64>),
+    Float(Vec<f64>),
+}
+
+impl Column {
+    fn widen(&mut self) {
+        if let Column::Int(values) = self {
+            *self = Column::Float(values.iter().map(|&v| v as f64).collect());
+        }
+    }
+
+    fn push(&mut self, token: &str) -> Option<()> {
+        if let Column::Int(values) = self {
+            if let Ok(v) = token.parse::<i64>() {
+                values.push(v);
+                return Some(());
+            }
+            self.widen();
+        }
+        let Column::Float(values) = self else { unreachable!() };
+        values.push(token.parse::<f64>().ok()?);
+        Some(())
+    }
+
+    fn append(&mut self, other: Column) {
+        match (&mut *self, other) {
+            (Column::Int(a), Column::Int(b)) => a.extend(b),
+            (_, mut other) => {
+                self.widen();
+                other.widen();
+                let (Column::Float(a), Column::Float(b)) = (self, other) else { unreachable!() };
+                a.extend(b);
+            }
+        }
+    }
+}
+
+/// The parsed columns of a chunk of lines, and whether each column has values that lose
+/// precision when stored as f32.
+struct Chunk {
+    columns: Vec<Column>,
+    needs_f64: Vec<bool>,
+}
+
+/// Returns whether `value`, parsed from `token`, survives the round trip through f32, i.e.
+/// whether `token` is as precise as the shortest representation of the nearest f32.
+fn fits_f32(token: &str, value: f64) -> bool {
+    let single = value as f32;
+    // Decimals of up to 6 significant digits and no exponent always survive the round trip
+    token.len() <= 6 && !token.contains(['e', 'E'])
+        || !single.is_finite() && !value.is_finite()
+        || ryu::Buffer::new().format(single).parse::<f64>().is_ok_and(|v| v == value)
+}
+
+fn parse_chunk(lines: &[(usize, &str)], delimiter: Delimiter, ncolumns: usize) -> Result<Chunk> {
+    let mut columns: Vec<Column> = (0..ncolumns).map(|_| Column::Int(Vec::with_capacity(lines.len()))).collect();
+    let mut needs_f64 = vec![false; ncolumns];
+    for &(line_no, line) in lines {
+        let mut n = 0;
+        for token in delimiter.split(line) {
+            anyhow::ensure!(n < ncolumns, "Line {}: expected {} values, found more", line_no, ncolumns);
+            columns[n].push(token)
+                .ok_or_else(|| anyhow::anyhow!("Line {}: invalid number '{}'", line_no, token))?;
+            if !needs_f64[n] {
+                if let Column::Float(values) = &columns[n] {
+                    needs_f64[n] = !fits_f32(token, *values.last().unwrap());
+                }
+            }
+            n += 1;
+        }
+        anyhow::ensure!(n == ncolumns, "Line {}: expected {} values, found {}", line_no, ncolumns, n);
+    }
+    Ok(Chunk { columns, needs_f64 })
+}
+
+/// Converts a parsed column to field data: I32 (or I64 if a value is out of range) for
+/// integer columns, else F32, or F64 if a value needs the extra precision. Coordinate
+/// columns are always floating point.
+fn column_to_field(name: &str, mut column: Column, mut needs_f64: bool) -> Result<FieldData> {
+    if ["x", "y", "z"].iter().any(|c| name.eq_ignore_ascii_case(c)) {
+        if let Column::Int(values) = &column {
+            needs_f64 |= values.iter().any(|v| v.unsigned_abs() > 1 << f32::MANTISSA_DIGITS);
+            column.widen();
+        }
+    }
+    let npoints = match &column {
+        Column::Int(values) => values.len(),
+        Column::Float(values) => values.len(),
+    };
+    Ok(match column {
+        Column::Int(values) if values.iter().all(|&v| i32::try_from(v).is_ok()) =>
+            FieldData::I32(Array2::from_shape_vec((npoints, 1), values.into_iter().map(|v| v as i32).collect())?.into_shared()),
+        Column::Int(values) => FieldData::I64(Array2::from_shape_vec((npoints, 1), values)?.into_shared()),
+        Column::Float(values) if needs_f64 => FieldData::F64(Array2::from_shape_vec((npoints, 1), values)?.into_shared()),
+        Column::Float(values) =>
+            FieldData::F32(Array2::from_shape_vec((npoints, 1), values.into_iter().map(|v| v as f32).collect())?.into_shared()),
+    })
+}
+
+/// Name of column `i` when the file has no header: x, y and z for the first three columns,
+/// then `field_<i>`.
+fn default_name(i: usize) -> String {
+    match i {
+        0 => "x".to_string(),
+        1 => "y".to_string(),
+        2 => "z".to_string(),
+        _ => format!("field_{}", i),
+    }
+}
+
+/// Parses delimited point data (.xyz, .txt, .csv, .tsv, ...) with one point per line.
+///
+/// After the first `skip_rows` lines, blank lines and lines starting with `#` or `//` are
+/// ignored. If `delimiter` is None, it is inferred from the first remaining line (see
+/// `Delimiter::infer`); a whitespace delimiter splits on runs of spaces and tabs. If that
+/// line is not all numbers, it is a header giving the field names, unless `names` are given.
+/// Without either, fields are named x, y, z, field_3, field_4, ...
+///
+/// Each column becomes a field with a count of 1: integer columns are I32 (I64 if out of
+/// range), and others F32, or F64 if any value is more precise than an f32 can hold.
+/// Columns named x, y or z (in any case) are always F32 or F64.
+pub fn parse_text(text: &str, delimiter: Option<char>, names: Option<&[String]>, skip_rows: usize) -> Result<PointCloud> {
+    let mut lines = text.lines()
+        .enumerate()
+        .skip(skip_rows)
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#') && !line.starts_with("//"))
+        .peekable();
+    let delimiter = match (delimiter, lines.peek()) {
+        (Some(c), _) => Delimiter::new(c),
+        (None, Some((_, line))) => Delimiter::infer(line),
+        (None, None) => Delimiter::Whitespace,
+    };
+
+    let header = match lines.peek() {
+        Some((_, line)) if delimiter.split(line).any(|token| token.parse::<f64>().is_err()) => {
+            let header: Vec<String> = delimiter.split(line)
+                .map(|token| token.trim_matches(|c| c == '"' || c == '\'').to_string())
+                .collect();
+            lines.next();
+            Some(header)
+        }
+        _ => None,
+    };
+    let lines: Vec<(usize, &str)> = lines.collect();
+    let ncolumns = match (&header, lines.first()) {
+        (Some(header), _) => header.len(),
+        (None, Some((_, line))) => delimiter.split(line).count(),
+        (None, None) => names.map_or(0, |names| names.len()),
+    };
+    let names: Vec<String> = match (names, header) {
+        (Some(names), _) => {
+            anyhow::ensure!(names.len() == ncolumns, "Expected {} names, one per column, got {}", ncolumns, names.len());
+            names.to_vec()
+        }
+        (None, Some(header)) => header.into_iter()
+            .enumerate()
+            .map(|(i, name)| if name.is_empty() { format!("field_{}", i) } else { name })
+            .collect(),
+        (None, None) => (0..ncolumns).map(default_name).collect(),
+    };
+    for (i, name) in names.iter().enumerate() {
+        anyhow::ensure!(!names[..i].contains(name), "Duplicate field name '{}'", name);
+    }
+
+    let chunks = lines.par_chunks(LINES_PER_TASK)
+        .map(|chunk| parse_chunk(chunk, delimiter, ncolumns))
+        .collect::<Result<Vec<_>>>()?;
+    let mut merged = Chunk {
+        columns: (0..ncolumns).map(|_| Column::Int(Vec::with_capacity(lines.len()))).collect(),
+        needs_f64: vec![false; ncolumns],
+    };
+    for chunk in chunks {
+        for (i, (column, needs_f64)) in chunk.columns.into_iter().zip(chunk.needs_f64).enumerate() {
+            merged.columns[i].append(column);
+            merged.needs_f64[i] |= needs_f64;
+        }
+    }
+
+    let npoints = lines.len();
+    let columns = names.into_iter()
+        .zip(merged.columns.into_iter().zip(merged.needs_f64))
+        .map(|(name, (column, needs_f64))| {
+            let field = column_to_field(&name, column, needs_f64)?;
+            Ok((name, field))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let md = Metadata {
+        fields: columns.iter().map(|(name, field)| FieldMeta::new(name.as_str(), field.dtype(), 1)).collect(),
+        width: npoints,
+        height: 1,
+        npoints,
+        ..Metadata::default()
+    };
+    let mut pc = PointCloud::empty(&md);
+    pc.fields.extend(columns);
+    pc.check_pointcloud()?;
+    Ok(pc)
+}
+
+/// Reads a PointCloud from a delimited text file. See `parse_text`.
+pub fn read_text(path: &str, delimiter: Option<char>, names: Option<&[String]>, skip_rows: usize) -> Result<PointCloud> {
+    let text = std::fs::read_to_string(path)?;
+    parse_text(&text, delimiter, names, skip_rows)
+}
+
+/// Writes the PointCloud as delimited text with one point per line, preceded by a header of
+/// field names if `header` is set. Fields with a count greater than 1 take one column per
+/// element, named `<name>_0`, `<name>_1`, ...
+pub fn write_text<W: Write>(writer: &mut W, pc: &PointCloud, delimiter: u8, header: bool, float_format: FloatFormat) -> Result<()> {
+    if header {
+        let md = pc.metadata.read().unwrap();
+        let columns: Vec<String> = md.fields.iter()
+            .flat_map(|f| match f.count {
+                1 => vec![f.name.clone()],
+                n => (0..n).map(|i| format!("{}_{}", f.name, i)).collect(),
+            })
+            .collect();
+        writer.write_all(columns.join(&(delimiter as char).to_string()).as_bytes())?;
+        writer.write_all(b"\n")?;
+    }
+    io::write_delimited_data(writer, pc, float_format, delimiter)
+}
+
+/// Writes the PointCloud to a delimited text file. See `write_text`.
+pub fn write_text_file(pc: &PointCloud, path: &str, delimiter: u8, header: bool, float_format: FloatFormat) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_text(&mut writer, pc, delimiter, header, float_format)?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::Dtype;
+
+    #[test]
+    fn test_parse_text_inference() {
+        let text = "# exported scan\n1.5 2 3 7\n\n-1e-3 5 6.25 8\n";
+        let pc = parse_text(text, None, None, 0).unwrap();
+        assert_eq!(pc.field_names(), ["x", "y", "z", "field_3"]);
+        assert_eq!(pc.len(), 2);
+        assert_eq!(pc.fields["x"].dtype(), Dtype::F32);
+        assert_eq!(pc.fields["y"].dtype(), Dtype::F32);
+        assert_eq!(pc.fields["field_3"].dtype(), Dtype::I32);
+        let FieldData::F32(x) = &pc.fields["x"] else { panic!() };
+        assert_eq!(x.as_slice().unwrap(), [1.5, -1e-3]);
+
+        // Values beyond f32 or i32 precision widen the column
+        let pc = parse_text("x,y,id\n123456.789,0.1,1\n1,2,5000000000\n", None, None, 0).unwrap();
+        assert_eq!(pc.field_names(), ["x", "y", "id"]);
+        assert_eq!(pc.fields["x"].dtype(), Dtype::F64);
+        assert_eq!(pc.fields["y"].dtype(), Dtype::F32);
+        assert_eq!(pc.fields["id"].dtype(), Dtype::I64);
+    }
+
+    #[test]
+    fn test_parse_text_options() {
+        let text = "created by scanner\nX;Y;Z\n1;2;3\n";
+        let names = ["a".to_string(), "b".to_string(), "c".to_string()];
+        let pc = parse_text(text, Some(';'), Some(&names), 1).unwrap();
+        assert_eq!(pc.field_names(), names);
+        assert_eq!(pc.len(), 1);
+
+        assert!(parse_text(text, None, Some(&names[..2]), 1).is_err());
+        let err = parse_text("1 2 3\n4 5\n", None, None, 0).unwrap_err();
+        assert!(err.to_string().starts_with("Line 2"), "{}", err);
+        assert!(parse_text("x,y,x\n1,2,3\n", None, None, 0).is_err());
+        assert!(parse_text("1,2,3\n4,a,6\n", None, None, 0).is_err());
+    }
+
+    #[test]
+    fn test_write_text_roundtrip() {
+        let pc = parse_text("x\ty\tz\tlabel\n0.5\t1.25\t-2\t3\n1\t2\t3\t4\n", None, None, 0).unwrap();
+        assert_eq!(pc.fields["z"].dtype(), Dtype::F32);
+        let mut buf = Vec::new();
+        write_text(&mut buf, &pc, b',', true, FloatFormat::Shortest).unwrap();
+        assert_eq!(String::from_utf8(buf.clone()).unwrap(), "x,y,z,label\n0.5,1.25,-2.0,3\n1.0,2.0,3.0,4\n");
+        let reread = parse_text(std::str::from_utf8(&buf).unwrap(), None, None, 0).unwrap();
+        assert_eq!(reread.field_names(), pc.field_names());
+        assert_eq!(reread.fields["y"], pc.fields["y"]);
+    }
+}
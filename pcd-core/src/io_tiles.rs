@@ -0,0 +1,200 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use anyhow::Result;
+use rayon::prelude::*;
+use crate::octree::{LodNode, Octree, MAX_DEPTH};
+use crate::pointcloud::PointCloud;
+
+/// Length of the header of a pnts file.
+const PNTS_HEADER_LEN: usize = 28;
+
+/// Options controlling how a PointCloud is written as a 3D Tiles tileset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TilesOptions {
+    /// Largest number of points per tile, except for tiles at the deepest octree level.
+    pub max_points: usize,
+    /// Packed color field (see `PointCloud::unpack_rgb`) giving the colors of the points, or
+    /// None to write no colors. Ignored if the cloud has no such field.
+    pub color_field: Option<String>,
+    /// Column-major 4x4 matrix placing the tileset in the viewer's frame (e.g. from local
+    /// coordinates to Earth-centered coordinates for globe viewers), or None for the identity.
+    pub transform: Option<[f64; 16]>,
+}
+
+impl Default for TilesOptions {
+    fn default() -> Self {
+        Self { max_points: 100_000, color_field: Some("rgb".to_string()), transform: None }
+    }
+}
+
+/// Writes the pnts (point cloud tile) file at `path` holding `positions`, stored as float32
+/// offsets from `center`, and their `colors`.
+fn write_pnts(path: &Path, positions: &[[f64; 3]], colors: Option<&[[u8; 3]]>, center: [f64; 3]) -> Result<()> {
+    let n = positions.len();
+    let mut json = format!(r#"{{"POINTS_LENGTH":{},"RTC_CENTER":[{},{},{}],"POSITION":{{"byteOffset":0}}"#,
+        n, center[0], center[1], center[2]);
+    if colors.is_some() {
+        write!(json, r#","RGB":{{"byteOffset":{}}}"#, 12 * n)?;
+    }
+    json.push('}');
+    // The feature table JSON must end on an 8-byte boundary of the file, padded with spaces
+    while !(PNTS_HEADER_LEN + json.len()).is_multiple_of(8) {
+        json.push(' ');
+    }
+    let mut body = Vec::with_capacity(15 * n + 8);
+    for p in positions {
+        for c in 0..3 {
+            body.extend_from_slice(&((p[c] - center[c]) as f32).to_le_bytes());
+        }
+    }
+    if let Some(colors) = colors {
+        body.extend(colors.iter().flatten());
+    }
+    body.resize(body.len().next_multiple_of(8), 0);
+
+    let len = PNTS_HEADER_LEN + json.len() + body.len();
+    anyhow::ensure!(len <= u32::MAX as usize, "Tile of {} points is too large for a pnts file", n);
+    let mut bytes = Vec::with_capacity(len);
+    bytes.extend_from_slice(b"pnts");
+    for value in [1, len, json.len(), body.len(), 0, 0] {
+        bytes.extend_from_slice(&(value as u32).to_le_bytes());
+    }
+    bytes.extend_from_slice(json.as_bytes());
+    bytes.extend_from_slice(&body);
+    fs::write(path, bytes)?;
+    Ok(())
+}
+
+/// Appends the JSON of the tile of `nodes[index]` and its children to `json`, starting with
+/// the `extra` properties (each followed by a comma).
+fn write_tile_json(json: &mut String, tree: &Octree, nodes: &[LodNode], names: &[String], index: usize, extra: &str) -> Result<()> {
+    let node = &nodes[index];
+    let (corner, side) = tree.cell_bounds(node.code, node.level);
+    let (c, h) = (corner.map(|v| v + side / 2.0), side / 2.0);
+    write!(json, r#"{{{}"boundingVolume":{{"box":[{},{},{},{h},0,0,0,{h},0,0,0,{h}]}},"geometricError":{},"content":{{"uri":"{}.pnts"}}"#,
+        extra, c[0], c[1], c[2], node.spacing, names[index])?;
+    if !node.children.is_empty() {
+        json.push_str(r#","children":["#);
+        for (i, &child) in node.children.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            write_tile_json(json, tree, nodes, names, child, "")?;
+        }
+        json.push(']');
+    }
+    json.push('}');
+    Ok(())
+}
+
+/// Writes the PointCloud as a 3D Tiles tileset in the directory `dir` (created if needed), for
+/// web viewers such as CesiumJS: `tileset.json` describes an octree of tiles whose points are
+/// the `names` coordinates, and each tile is a pnts file named after its path from the root
+/// (`r.pnts`, `r0.pnts`, `r07.pnts`, ...). Tiles refine additively: the root holds an even
+/// sample of the cloud, and each tile adds detail to its parent (see `Octree::lod_nodes`).
+/// Points with a NaN coordinate are left out.
+pub fn write_3d_tiles(pc: &PointCloud, dir: &str, names: [&str; 3], options: &TilesOptions) -> Result<()> {
+    let positions = pc.coordinates(names)?;
+    let colors: Option<Vec<[u8; 3]>> = match options.color_field.as_deref().filter(|name| pc.fields.contains_key(*name)) {
+        Some(name) => Some(pc.unpack_rgb(name)?.rows().into_iter().map(|c| [c[0], c[1], c[2]]).collect()),
+        None => None,
+    };
+    if let Some(transform) = &options.transform {
+        anyhow::ensure!(transform.iter().all(|v| v.is_finite()), "Tileset transform must be finite");
+    }
+    let tree = Octree::new(positions.clone(), MAX_DEPTH)?;
+    anyhow::ensure!(!tree.is_empty(), "Cannot write a tileset without points with finite coordinates");
+    let nodes = tree.lod_nodes(options.max_points)?;
+
+    // Nodes come after their parent
+    let mut tile_names = vec![String::from("r"); nodes.len()];
+    for (i, node) in nodes.iter().enumerate() {
+        for &child in &node.children {
+            tile_names[child] = format!("{}{}", tile_names[i], nodes[child].code & 7);
+        }
+    }
+
+    let dir = Path::new(dir);
+    fs::create_dir_all(dir)?;
+    nodes.par_iter().zip(&tile_names).try_for_each(|(node, name)| {
+        let (corner, side) = tree.cell_bounds(node.code, node.level);
+        let points: Vec<[f64; 3]> = node.points.iter().map(|&i| positions[i]).collect();
+        let colors: Option<Vec<[u8; 3]>> = colors.as_ref().map(|colors| node.points.iter().map(|&i| colors[i]).collect());
+        write_pnts(&dir.join(format!("{}.pnts", name)), &points, colors.as_deref(), corner.map(|v| v + side / 2.0))
+    })?;
+
+    let (lo, hi) = tree.bounds();
+    let mut json = format!(r#"{{"asset":{{"version":"1.0","generator":"pcdpy"}},"geometricError":{},"root":"#, hi[0] - lo[0]);
+    let mut extra = String::from(r#""refine":"ADD","#);
+    if let Some(transform) = &options.transform {
+        write!(extra, r#""transform":[{}],"#, transform.map(|v| v.to_string()).join(","))?;
+    }
+    write_tile_json(&mut json, &tree, &nodes, &tile_names, 0, &extra)?;
+    json.push('}');
+    fs::write(dir.join("tileset.json"), json)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array1;
+    use crate::metadata::{Dtype, FieldSchema, Metadata};
+
+    #[test]
+    fn test_write_3d_tiles() {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1), ("y", Dtype::F32, 1), ("z", Dtype::F32, 1), ("rgb", Dtype::F32, 1)]),
+            width: 1000,
+            height: 1,
+            npoints: 1000,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        for i in 0..1000 {
+            let p = [i % 10, i / 10 % 10, i / 100].map(|v| v as f32);
+            for (c, name) in ["x", "y", "z"].into_iter().enumerate() {
+                pc.fields.get_mut(name).unwrap().assign_row(i, &Array1::from(vec![p[c]]));
+            }
+            pc.fields.get_mut("rgb").unwrap().assign_row(i, &Array1::from(vec![crate::color::pack_rgb(p[0] as u8, 1, 2)]));
+        }
+        let dir = std::env::temp_dir().join("pcdpy_test_3d_tiles");
+        let _ = fs::remove_dir_all(&dir);
+        let options = TilesOptions { max_points: 100, ..TilesOptions::default() };
+        pc.to_3d_tiles(dir.to_str().unwrap(), ["x", "y", "z"], &options).unwrap();
+
+        let tileset = fs::read_to_string(dir.join("tileset.json")).unwrap();
+        assert!(tileset.starts_with(r#"{"asset":{"version":"1.0","generator":"pcdpy"},"geometricError":9,"root":{"refine":"ADD","boundingVolume":{"box":[4.5,4.5,4.5,4.5,0,0,0,4.5,0,0,0,4.5]}"#));
+        assert!(tileset.contains(r#""content":{"uri":"r.pnts"},"children":[{"boundingVolume""#) && tileset.ends_with("]}}"));
+        let mut total = 0;
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_str().unwrap().to_string();
+            if name == "tileset.json" {
+                continue;
+            }
+            assert!(tileset.contains(&format!(r#""uri":"{}""#, name)));
+            let bytes = fs::read(&path).unwrap();
+            let header: Vec<usize> = (1..7).map(|i| u32::from_le_bytes(bytes[4 * i..4 * i + 4].try_into().unwrap()) as usize).collect();
+            assert_eq!((&bytes[..4], header[0], header[1], header[4], header[5]), (&b"pnts"[..], 1, bytes.len(), 0, 0));
+            assert_eq!(((PNTS_HEADER_LEN + header[2]) % 8, header[3] % 8), (0, 0));
+            let json = std::str::from_utf8(&bytes[PNTS_HEADER_LEN..PNTS_HEADER_LEN + header[2]]).unwrap();
+            let n: usize = json.split_once(r#""POINTS_LENGTH":"#).unwrap().1.split(',').next().unwrap().parse().unwrap();
+            assert!(n <= 100 && json.contains(&format!(r#""RGB":{{"byteOffset":{}}}"#, 12 * n)));
+            // The color of each point is its x coordinate
+            let body = &bytes[PNTS_HEADER_LEN + header[2]..];
+            let center_x: f32 = json.split_once(r#""RTC_CENTER":["#).unwrap().1.split(',').next().unwrap().parse().unwrap();
+            for i in 0..n {
+                let x = f32::from_le_bytes(body[12 * i..12 * i + 4].try_into().unwrap()) + center_x;
+                assert_eq!(&body[12 * n + 3 * i..12 * n + 3 * i + 3], [x as u8, 1, 2]);
+            }
+            total += n;
+        }
+        assert_eq!(total, 1000);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let options = TilesOptions { transform: Some([f64::NAN; 16]), ..TilesOptions::default() };
+        assert!(pc.to_3d_tiles(dir.to_str().unwrap(), ["x", "y", "z"], &options).is_err());
+    }
+}
@@ -0,0 +1,182 @@
+use std::collections::BinaryHeap;
+use std::cmp::Ordering;
+use anyhow::Result;
+use crate::pointcloud::PointCloud;
+
+/// A neighbor found by a k-d tree query: the point index and its squared distance.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Neighbor {
+    index: usize,
+    dist2: f64,
+}
+
+impl Eq for Neighbor {}
+
+impl PartialOrd for Neighbor {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Neighbor {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.dist2.total_cmp(&other.dist2)
+    }
+}
+
+/// A static 3D k-d tree over a set of points.
+///
+/// The tree is stored implicitly: `order` is a permutation of the point indices where, for
+/// every range `lo..hi` at depth `d`, the median element splits the range on axis `d % 3`.
+#[derive(Debug, Clone)]
+pub struct KdTree {
+    points: Vec<[f64; 3]>,
+    order: Vec<usize>,
+}
+
+impl KdTree {
+    /// Build a k-d tree over `points`. Query results refer to positions in this vector.
+    pub fn new(points: Vec<[f64; 3]>) -> Self {
+        let mut order: Vec<usize> = (0..points.len()).collect();
+        Self::build(&points, &mut order, 0);
+        Self { points, order }
+    }
+
+    fn build(points: &[[f64; 3]], order: &mut [usize], depth: usize) {
+        if order.len() <= 1 {
+            return;
+        }
+        let axis = depth % 3;
+        let mid = order.len() / 2;
+        order.select_nth_unstable_by(mid, |&a, &b| points[a][axis].total_cmp(&points[b][axis]));
+        let (left, right) = order.split_at_mut(mid);
+        Self::build(points, left, depth + 1);
+        Self::build(points, &mut right[1..], depth + 1);
+    }
+
+    /// Number of points in the tree.
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Returns true if the tree contains no points.
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Coordinates of the point at `index`.
+    pub fn point(&self, index: usize) -> [f64; 3] {
+        self.points[index]
+    }
+
+    fn dist2(&self, index: usize, query: &[f64; 3]) -> f64 {
+        let p = &self.points[index];
+        (0..3).map(|i| (p[i] - query[i]).powi(2)).sum()
+    }
+
+    /// Return the `k` nearest neighbors of `query` as (index, distance) pairs, closest first.
+    /// Fewer than `k` pairs are returned if the tree has fewer than `k` points.
+    pub fn query(&self, query: &[f64; 3], k: usize) -> Vec<(usize, f64)> {
+        let mut heap = BinaryHeap::with_capacity(k + 1);
+        if k > 0 {
+            self.query_knn(0, self.order.len(), 0, query, k, &mut heap);
+        }
+        heap.into_sorted_vec().into_iter()
+            .map(|n| (n.index, n.dist2.sqrt()))
+            .collect()
+    }
+
+    fn query_knn(&self, lo: usize, hi: usize, depth: usize, query: &[f64; 3], k: usize, heap: &mut BinaryHeap<Neighbor>) {
+        if lo >= hi {
+            return;
+        }
+        let mid = lo + (hi - lo) / 2;
+        let index = self.order[mid];
+        let dist2 = self.dist2(index, query);
+        if heap.len() < k {
+            heap.push(Neighbor { index, dist2 });
+        } else if dist2 < heap.peek().unwrap().dist2 {
+            heap.pop();
+            heap.push(Neighbor { index, dist2 });
+        }
+
+        let axis = depth % 3;
+        let diff = query[axis] - self.points[index][axis];
+        let (near, far) = if diff < 0.0 { ((lo, mid), (mid + 1, hi)) } else { ((mid + 1, hi), (lo, mid)) };
+        self.query_knn(near.0, near.1, depth + 1, query, k, heap);
+        if heap.len() < k || diff * diff < heap.peek().unwrap().dist2 {
+            self.query_knn(far.0, far.1, depth + 1, query, k, heap);
+        }
+    }
+
+    /// Return all points within distance `radius` of `query` as (index, distance) pairs, closest first.
+    pub fn query_radius(&self, query: &[f64; 3], radius: f64) -> Vec<(usize, f64)> {
+        let mut found = Vec::new();
+        self.query_range(0, self.order.len(), 0, query, radius * radius, &mut found);
+        found.sort_unstable();
+        found.into_iter()
+            .map(|n| (n.index, n.dist2.sqrt()))
+            .collect()
+    }
+
+    fn query_range(&self, lo: usize, hi: usize, depth: usize, query: &[f64; 3], r2: f64, found: &mut Vec<Neighbor>) {
+        if lo >= hi {
+            return;
+        }
+        let mid = lo + (hi - lo) / 2;
+        let index = self.order[mid];
+        let dist2 = self.dist2(index, query);
+        if dist2 <= r2 {
+            found.push(Neighbor { index, dist2 });
+        }
+
+        let axis = depth % 3;
+        let diff = query[axis] - self.points[index][axis];
+        if diff <= 0.0 || diff * diff <= r2 {
+            self.query_range(lo, mid, depth + 1, query, r2, found);
+        }
+        if diff >= 0.0 || diff * diff <= r2 {
+            self.query_range(mid + 1, hi, depth + 1, query, r2, found);
+        }
+    }
+}
+
+impl PointCloud {
+    /// Build a k-d tree over the `names` coordinate fields (any dtype, count of 1).
+    pub fn build_kdtree(&self, names: [&str; 3]) -> Result<KdTree> {
+        Ok(KdTree::new(self.coordinates(names)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kdtree_queries() {
+        // Deterministic pseudo-random points, checked against brute force.
+        let mut state = 12345u64;
+        let mut next = || {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 11) as f64 / (1u64 << 53) as f64
+        };
+        let points: Vec<[f64; 3]> = (0..500).map(|_| [next(), next(), next()]).collect();
+        let tree = KdTree::new(points.clone());
+        let query = [0.4, 0.5, 0.6];
+
+        let mut brute: Vec<(usize, f64)> = points.iter().enumerate()
+            .map(|(i, p)| (i, (0..3).map(|j| (p[j] - query[j]).powi(2)).sum::<f64>().sqrt()))
+            .collect();
+        brute.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        let knn = tree.query(&query, 5);
+        assert_eq!(knn.iter().map(|n| n.0).collect::<Vec<_>>(), brute[..5].iter().map(|n| n.0).collect::<Vec<_>>());
+
+        let within = tree.query_radius(&query, 0.2);
+        let expected = brute.iter().take_while(|n| n.1 <= 0.2).count();
+        assert_eq!(within.len(), expected);
+        assert!(within.windows(2).all(|w| w[0].1 <= w[1].1));
+
+        assert_eq!(tree.query(&query, 1000).len(), 500);
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use anyhow::Result;
+use ndarray::Array2;
+use crate::fielddata::{CastPolicy, FieldData};
+use crate::metadata::{check_custom_entry, Dtype, Metadata};
+use crate::pointcloud::PointCloud;
+
+/// Prefix of the custom metadata key holding the categories of a label field, written as a
+/// header comment such as `# categories.label: car pedestrian traffic%20light`.
+pub const CATEGORIES_PREFIX: &str = "categories.";
+
+/// Percent-encodes the characters of a category name that cannot appear in the space-separated
+/// list of a header comment.
+fn escape(category: &str) -> String {
+    let mut escaped = String::with_capacity(category.len());
+    for c in category.chars() {
+        match c {
+            '%' | ' ' | '\t' | '\n' | '\r' => escaped.push_str(&format!("%{:02X}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Decodes a category name escaped by `escape`. Malformed escapes are kept as is.
+fn unescape(escaped: &str) -> String {
+    let mut bytes = Vec::with_capacity(escaped.len());
+    let mut rest = escaped.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        let code = (b == b'%').then(|| tail.get(..2)).flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match code {
+            Some(code) => {
+                bytes.push(code);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(b);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Checks that `categories` are distinct and non-empty, and returns the custom entry holding
+/// them for the label field `name`.
+fn categories_entry(name: &str, categories: &[String]) -> Result<(String, String)> {
+    let mut seen = std::collections::HashSet::new();
+    for category in categories {
+        anyhow::ensure!(!category.is_empty(), "Category names cannot be empty");
+        anyhow::ensure!(seen.insert(category), "Category '{}' is given twice", category);
+    }
+    let key = format!("{}{}", CATEGORIES_PREFIX, name);
+    let value = categories.iter().map(|c| escape(c)).collect::<Vec<_>>().join(" ");
+    check_custom_entry(&key, &value)?;
+    Ok((key, value))
+}
+
+impl Metadata {
+    /// Returns the categories of the label field `name`, or None if it is not a label field.
+    pub fn categories(&self, name: &str) -> Option<Vec<String>> {
+        let value = self.custom_value(&format!("{}{}", CATEGORIES_PREFIX, name))?;
+        Some(value.split_ascii_whitespace().map(unescape).collect())
+    }
+
+    /// Makes `name`, an integer field with a count of 1, a label field whose values are codes
+    /// into `categories`, which must be distinct and non-empty. The categories are stored as a
+    /// custom entry (see `CATEGORIES_PREFIX`), so that they are written with the cloud.
+    pub fn set_categories(&mut self, name: &str, categories: &[String]) -> Result<()> {
+        let field = self.fields.iter().find(|f| f.name == name)
+            .ok_or_else(|| anyhow::anyhow!("Field '{}' not found", name))?;
+        anyhow::ensure!(matches!(field.dtype.get_type(), "U" | "I") && field.dtype != Dtype::Bool && field.count == 1,
+            "Label field '{}' must be an integer field with a count of 1, got {} with a count of {}",
+            name, field.dtype.as_numpy_dtype(), field.count);
+        let (key, value) = categories_entry(name, categories)?;
+        self.set_custom(&key, &value)
+    }
+
+    /// Removes the categories of the label field `name`, leaving its codes as a plain integer
+    /// field. Returns the categories, or None if it was not a label field.
+    pub fn remove_categories(&mut self, name: &str) -> Option<Vec<String>> {
+        let categories = self.categories(name)?;
+        self.remove_custom(&format!("{}{}", CATEGORIES_PREFIX, name));
+        Some(categories)
+    }
+}
+
+impl PointCloud {
+    /// Add or replace the label field `name` holding `labels`, one per point. Each point
+    /// stores the code of its label, its index in the categories: `categories` if given, of
+    /// which every label must be one, else the distinct labels in order of first appearance.
+    /// Codes are U8, U16 or U32, the smallest that holds every category.
+    pub fn set_labels<S: AsRef<str>>(&mut self, name: &str, labels: &[S], categories: Option<&[String]>) -> Result<()> {
+        let fixed = categories.is_some();
+        let mut categories: Vec<String> = categories.map(<[String]>::to_vec).unwrap_or_default();
+        let mut index: HashMap<String, usize> = categories.iter().enumerate().map(|(i, c)| (c.clone(), i)).collect();
+        let mut codes = Vec::with_capacity(labels.len());
+        for label in labels {
+            let label = label.as_ref();
+            let code = match index.get(label) {
+                Some(&code) => code,
+                None => {
+                    anyhow::ensure!(!fixed, "Label '{}' is not one of the categories", label);
+                    categories.push(label.to_string());
+                    index.insert(label.to_string(), categories.len() - 1);
+                    categories.len() - 1
+                }
+            };
+            codes.push(code);
+        }
+        let shape = (codes.len(), 1);
+        let field = if categories.len() <= 1 << 8 {
+            FieldData::U8(Array2::from_shape_vec(shape, codes.into_iter().map(|c| c as u8).collect())?.into_shared())
+        } else if categories.len() <= 1 << 16 {
+            FieldData::U16(Array2::from_shape_vec(shape, codes.into_iter().map(|c| c as u16).collect())?.into_shared())
+        } else {
+            FieldData::U32(Array2::from_shape_vec(shape, codes.into_iter().map(|c| c as u32).collect())?.into_shared())
+        };
+        let (key, value) = categories_entry(name, &categories)?;
+        self.insert_field(name, field)?;
+        self.metadata.write().unwrap().set_custom(&key, &value)
+    }
+
+    /// Returns the label of each point of the label field `name`. Fails if `name` is not a
+    /// label field, or if a code is not the index of a category.
+    pub fn labels(&self, name: &str) -> Result<Vec<String>> {
+        let categories = self.metadata.read().unwrap().categories(name)
+            .ok_or_else(|| anyhow::anyhow!("Field '{}' is not a label field", name))?;
+        let field = self.fields.get(name)
+            .ok_or_else(|| anyhow::anyhow!("Field '{}' missing from PointCloud data", name))?;
+        field.try_get_data::<i64>(CastPolicy::Saturate)?.iter()
+            .map(|&code| usize::try_from(code).ok().and_then(|c| categories.get(c)).cloned()
+                .ok_or_else(|| anyhow::anyhow!("Code {} of field '{}' is not a category index (0 to {})", code, name, categories.len() as i64 - 1)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::FieldSchema;
+
+    #[test]
+    fn test_labels() {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1)]),
+            width: 4,
+            height: 1,
+            npoints: 4,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        let labels = ["car", "traffic light", "car", "50%"];
+        pc.set_labels("label", &labels, None).unwrap();
+        assert_eq!(pc.fields["label"].get_data::<u8>().column(0).to_vec(), [0, 1, 0, 2]);
+        assert_eq!(pc.labels("label").unwrap(), labels);
+        assert!(pc.labels("x").is_err());
+
+        let bytes = pc.to_pcd_bytes().unwrap();
+        assert!(String::from_utf8_lossy(&bytes).starts_with("# categories.label: car traffic%20light 50%25\n"));
+        let read = PointCloud::from_pcd_bytes(&bytes).unwrap();
+        assert_eq!(read.labels("label").unwrap(), labels);
+
+        let categories = ["truck", "car", "traffic light", "50%"].map(String::from);
+        pc.set_labels("label", &labels, Some(&categories)).unwrap();
+        assert_eq!(pc.fields["label"].get_data::<u8>().column(0).to_vec(), [1, 2, 1, 3]);
+        assert!(pc.set_labels("label", &["bus"; 4], Some(&categories)).is_err());
+        assert!(pc.set_labels("label", &["car", "", "car", "car"], None).is_err());
+        assert_eq!(pc.labels("label").unwrap(), labels);
+        pc.rename_field("label", "class").unwrap();
+        pc.rename_field("class", "label").unwrap();
+        assert_eq!(pc.labels("label").unwrap(), labels);
+
+        pc.fields.get_mut("label").unwrap().assign_row(0, &ndarray::Array1::from(vec![4u8]));
+        assert!(pc.labels("label").is_err());
+        assert!(pc.metadata.write().unwrap().set_categories("x", &categories).is_err());
+        assert_eq!(pc.metadata.write().unwrap().remove_categories("label"), Some(categories.to_vec()));
+        assert!(pc.labels("label").is_err());
+    }
+}
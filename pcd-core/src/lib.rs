@@ -0,0 +1,71 @@
+//! Reading, writing and processing of point clouds in the PCD file format, without Python
+//! bindings. This is the core of the `pcdpy` Python package.
+//!
+//! A [`PointCloud`] holds one [`FieldData`] array per field, described by its [`Metadata`]:
+//!
+//! ```no_run
+//! use pcd_core::PointCloud;
+//!
+//! let pc = PointCloud::from_pcd_file("cloud.pcd")?;
+//! let xyz = pc.coordinates(["x", "y", "z"])?;
+//! pc.to_pcd_file("copy.pcd")?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! Errors are `anyhow::Error`s; failures that callers may want to handle specifically carry a
+//! [`PcdError`], whose kind is found with [`ErrorKind::of`].
+
+pub mod io;
+pub mod io_ply;
+pub mod io_ros;
+pub mod io_npz;
+pub mod io_text;
+pub mod io_tiles;
+#[cfg(feature = "rosbag")]
+pub mod io_bag;
+#[cfg(feature = "las")]
+pub mod io_las;
+#[cfg(feature = "e57")]
+pub mod io_e57;
+#[cfg(feature = "arrow")]
+pub mod io_arrow;
+#[cfg(feature = "async")]
+pub mod io_async;
+#[cfg(feature = "remote")]
+pub mod io_remote;
+pub mod utils;
+pub mod error;
+pub mod metadata;
+pub mod fielddata;
+pub mod fieldmap;
+pub mod pointcloud;
+pub mod transform;
+pub mod arith;
+pub mod sampling;
+pub mod filter;
+pub mod organized;
+pub mod stats;
+pub mod bbox;
+pub mod color;
+pub mod batch;
+pub mod convert;
+pub mod writer;
+pub mod frames;
+pub mod progress;
+pub mod kdtree;
+pub mod distance;
+pub mod octree;
+pub mod sort;
+pub mod raster;
+pub mod registration;
+pub mod validate;
+pub mod labels;
+pub mod viewer;
+
+pub use error::{ErrorKind, PcdError};
+pub use fielddata::{CastPolicy, FieldData};
+pub use frames::{FrameReader, FrameWriter};
+pub use fieldmap::FieldMap;
+pub use metadata::{Dtype, Encoding, FieldMeta, FieldSchema, Metadata, Viewpoint};
+pub use pointcloud::{PcdReader, PointCloud};
+pub use writer::PcdWriter;
@@ -0,0 +1,789 @@
+use std::{iter::FromIterator, ops::{Index, IndexMut}};
+use std::sync::{Arc, RwLock};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metadata {
+    pub fields: FieldSchema,
+    pub width: usize,
+    pub height: usize,
+    pub npoints: usize,
+    pub viewpoint: Viewpoint,
+    pub encoding: Encoding,
+    pub version: String,
+    /// User key-value entries (e.g. a sensor id or a calibration hash), written in order as
+    /// `# key: value` comment lines at the top of the PCD header and parsed back on read.
+    pub custom: Vec<(String, String)>,
+}
+
+pub type SharedMetadata = Arc<RwLock<Metadata>>;
+
+/// Checks that a custom metadata entry can be written as a `# key: value` header line: the
+/// key must be non-empty without whitespace or ':', and neither may span several lines.
+/// Surrounding whitespace of the value is not preserved.
+pub fn check_custom_entry(key: &str, value: &str) -> anyhow::Result<()> {
+    anyhow::ensure!(!key.is_empty() && !key.contains(|c: char| c.is_whitespace() || c == ':'),
+        "Invalid custom metadata key '{}': it must be non-empty, without whitespace or ':'", key);
+    anyhow::ensure!(!value.contains(['\n', '\r']), "Custom metadata value of '{}' cannot contain line breaks", key);
+    Ok(())
+}
+
+impl Metadata {
+    /// Constructs a new `Metadata` from the provided parameters.
+    ///
+    /// # Parameters
+    /// - `names`: The names of the fields.
+    /// - `types`: The types of the fields as strings (e.g. "U", "I", "F").
+    /// - `sizes`: The size (in bytes) of each field type.
+    /// - `counts`: Optional field counts (defaulting to 1 for each field if not provided).
+    /// - `width`, `height`: Dimensions for organizing the point cloud.
+    /// - `npoints`: Total number of points.
+    /// - `viewpoint`: Optional viewpoint data.
+    /// - `encoding`: Optional encoding type (e.g. "binary_compressed").
+    /// - `version`: Optional version string (defaults to "0.7").
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        names: Vec<String>,
+        types: Vec<String>,
+        sizes: Vec<usize>,
+        counts: Option<Vec<usize>>,
+        width: usize,
+        height: usize,
+        npoints: usize,
+        viewpoint: Option<Vec<f32>>,
+        encoding: Option<&str>,
+        version: Option<&str>,
+    ) -> Self {
+        let fields = names.iter()
+            .zip(types.iter().zip(sizes.iter()))
+            .zip(counts.unwrap_or(vec![1; names.len()]).iter())
+            .map(|((name, (t, s)), c)| {
+                FieldMeta::new(name.as_str(), Dtype::from_type_size(t, s), *c)
+            })
+            .collect();
+        let viewpoint = viewpoint.map(Viewpoint::from).unwrap_or_default();
+        let encoding = Encoding::from_str(encoding.unwrap_or("binary_compressed")).unwrap();
+        Self {
+            fields,
+            width,
+            height,
+            npoints,
+            viewpoint,
+            encoding,
+            version: version.unwrap_or("0.7").to_string(),
+            custom: Vec::new(),
+        }
+    }
+
+    /// Creates a new `Metadata` instance by cloning the contents of a shared metadata reference.
+    pub fn from_shared(shared: SharedMetadata) -> Self {
+        shared.read().unwrap().clone()
+    }
+
+    /// Returns true if the header version predates PCD 0.7 (i.e. has no VIEWPOINT line).
+    pub fn is_legacy(&self) -> bool {
+        matches!(self.version.as_str(), "0.6" | ".6")
+    }
+
+    /// Returns the size in bytes of one point in binary form.
+    pub fn point_size(&self) -> usize {
+        self.fields.iter().map(|f| f.dtype.get_size() * f.count).sum()
+    }
+
+    /// Returns the size in bytes of the point data in binary form.
+    pub fn data_size(&self) -> usize {
+        self.point_size() * self.npoints
+    }
+
+    /// Returns a copy of the metadata with only the fields named in `names`, kept in schema
+    /// order. Returns an error if a name is not a field.
+    pub fn select_fields(&self, names: &[String]) -> anyhow::Result<Metadata> {
+        for name in names {
+            anyhow::ensure!(self.fields.iter().any(|f| &f.name == name), "Field '{}' not found", name);
+        }
+        Ok(Metadata {
+            fields: self.fields.iter().filter(|f| names.contains(&f.name)).cloned().collect(),
+            ..self.clone()
+        })
+    }
+
+    /// Returns the value of the custom entry `key`.
+    pub fn custom_value(&self, key: &str) -> Option<&str> {
+        self.custom.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    /// Sets the custom entry `key` to `value`, replacing its previous value in place or
+    /// appending it. See `check_custom_entry` for the keys and values allowed.
+    pub fn set_custom(&mut self, key: &str, value: &str) -> anyhow::Result<()> {
+        check_custom_entry(key, value)?;
+        match self.custom.iter_mut().find(|(k, _)| k == key) {
+            Some((_, v)) => *v = value.to_string(),
+            None => self.custom.push((key.to_string(), value.to_string())),
+        }
+        Ok(())
+    }
+
+    /// Removes the custom entry `key`, returning its value.
+    pub fn remove_custom(&mut self, key: &str) -> Option<String> {
+        let idx = self.custom.iter().position(|(k, _)| k == key)?;
+        Some(self.custom.remove(idx).1)
+    }
+
+    /// Sets the unit, description and semantic tag of the field `name`, clearing those that
+    /// are None. The unit and description cannot span several lines.
+    pub fn set_field_info(&mut self, name: &str, unit: Option<&str>, description: Option<&str>, semantic: Option<Semantic>) -> anyhow::Result<()> {
+        let field = self.fields.0.iter_mut().find(|f| f.name == name)
+            .ok_or_else(|| anyhow::anyhow!("Field '{}' not found", name))?;
+        let info = FieldMeta {
+            unit: unit.map(str::to_string),
+            description: description.map(str::to_string),
+            semantic,
+            ..field.clone()
+        };
+        for (key, value) in info.info_entries() {
+            check_custom_entry(&key, &value)?;
+        }
+        *field = info;
+        Ok(())
+    }
+
+    /// Moves the custom entries holding field units, descriptions and semantic tags (see
+    /// `FieldMeta::info_entries`) into the fields they describe. Entries for fields that do
+    /// not exist, and unknown semantic tags, are kept as custom entries.
+    pub fn take_field_info(&mut self) {
+        let mut custom = Vec::new();
+        for (key, value) in std::mem::take(&mut self.custom) {
+            let target = key.split_once('.')
+                .and_then(|(kind, name)| Some((kind, self.fields.0.iter_mut().find(|f| f.name == name)?)));
+            match target {
+                Some(("unit", field)) => field.unit = Some(value),
+                Some(("description", field)) => field.description = Some(value),
+                Some(("semantic", field)) if Semantic::from_str(&value).is_some() => field.semantic = Semantic::from_str(&value),
+                _ => custom.push((key, value)),
+            }
+        }
+        self.custom = custom;
+    }
+
+    /// Sets the metadata to that of an unorganized cloud of `n` points (WIDTH `n`, HEIGHT 1),
+    /// e.g. for a selection of points that no longer forms an image.
+    pub fn trim(&mut self, n: usize) {
+        self.npoints = n;
+        self.width = n;
+        self.height = 1;
+    }
+
+    /// Sets the metadata to that of the `n` points taken from point `start` every `step`
+    /// points. The result stays organized, with the same width, if the points are whole rows
+    /// of an organized cloud (`step` is 1 and `start` and `n` are multiples of the width), and
+    /// is unorganized otherwise. `organized` overrides this: `Some(true)` fails unless the
+    /// points are whole rows, and `Some(false)` always makes the result unorganized.
+    pub fn slice(&mut self, start: usize, n: usize, step: usize, organized: Option<bool>) -> anyhow::Result<()> {
+        let whole_rows = self.height > 1 && step == 1 && n > 0
+            && start.is_multiple_of(self.width) && n.is_multiple_of(self.width);
+        if organized == Some(true) && !whole_rows {
+            anyhow::ensure!(self.height > 1, "Cannot keep a slice organized: the PointCloud is not organized");
+            anyhow::bail!("Cannot keep a slice organized: {} points from point {} with step {} are not whole rows of width {}",
+                n, start, step, self.width);
+        }
+        if whole_rows && organized != Some(false) {
+            self.npoints = n;
+            self.height = n / self.width;
+        } else {
+            self.trim(n);
+        }
+        Ok(())
+    }
+}
+
+impl Default for Metadata {
+    fn default() -> Self {
+        Self {
+            fields: FieldSchema::new(),
+            width: 0,
+            height: 1,
+            viewpoint: Viewpoint::default(),
+            npoints: 0,
+            encoding: Encoding::default(),
+            version: "0.7".to_string(),
+            custom: Vec::new(),
+        }
+    }
+}
+
+/// Represents the data type of a field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dtype {
+    /// Boolean, stored as a U8 of 0 or 1 when written.
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    /// Half precision float, widened to `F32` when written.
+    F16,
+    F32,
+    F64,
+}
+impl Dtype {
+    /// Returns the size (in bytes) for this data type.
+    pub fn get_size(&self) -> usize {
+        match self {
+            Dtype::Bool | Dtype::U8 | Dtype::I8 => 1,
+            Dtype::U16 | Dtype::I16 | Dtype::F16 => 2,
+            Dtype::U32 | Dtype::I32 | Dtype::F32 => 4,
+            Dtype::U64 | Dtype::I64 | Dtype::F64 => 8,
+        }
+    }
+
+    /// Returns the type as a string ("U" for unsigned, "I" for integer, "F" for float).
+    pub fn get_type(&self) -> &str {
+        match self {
+            Dtype::Bool | Dtype::U8 | Dtype::U16 | Dtype::U32 | Dtype::U64 => "U",
+            Dtype::I8 | Dtype::I16 | Dtype::I32 | Dtype::I64 => "I",
+            Dtype::F16 | Dtype::F32 | Dtype::F64 => "F",
+        }
+    }
+
+    /// Returns the data type in which PCD files store this one: `Bool` is stored as `U8`, and
+    /// `F16` is widened to `F32` since PCL only reads 4 and 8 byte floats.
+    pub fn storage(&self) -> Dtype {
+        match self {
+            Dtype::Bool => Dtype::U8,
+            Dtype::F16 => Dtype::F32,
+            dtype => *dtype,
+        }
+    }
+
+    /// Constructs a `Dtype` from a type string and size.
+    pub fn from_type_size(t: &str, s: &usize) -> Self {
+        Self::try_from_type_size(t, *s)
+            .unwrap_or_else(|| panic!("Field type {} and size {} is not supported", t, s))
+    }
+
+    /// Constructs a `Dtype` from a type string and size, or `None` if the pair is not supported.
+    pub fn try_from_type_size(t: &str, s: usize) -> Option<Self> {
+        match (t, s) {
+            ("U", 1) => Some(Dtype::U8),
+            ("U", 2) => Some(Dtype::U16),
+            ("U", 4) => Some(Dtype::U32),
+            ("U", 8) => Some(Dtype::U64),
+            ("I", 1) => Some(Dtype::I8),
+            ("I", 2) => Some(Dtype::I16),
+            ("I", 4) => Some(Dtype::I32),
+            ("I", 8) => Some(Dtype::I64),
+            ("F", 2) => Some(Dtype::F16),
+            ("F", 4) => Some(Dtype::F32),
+            ("F", 8) => Some(Dtype::F64),
+            _ => None,
+        }
+    }
+
+    /// Returns a corresponding `Dtype` given a NumPy dtype name.
+    pub fn from_numpy_dtype(dtype: &str) -> Option<Self> {
+        match dtype {
+            "bool" => Some(Dtype::Bool),
+            "uint8" => Some(Dtype::U8),
+            "uint16" => Some(Dtype::U16),
+            "uint32" => Some(Dtype::U32),
+            "uint64" => Some(Dtype::U64),
+            "int8" => Some(Dtype::I8),
+            "int16" => Some(Dtype::I16),
+            "int32" => Some(Dtype::I32),
+            "int64" => Some(Dtype::I64),
+            "float16" => Some(Dtype::F16),
+            "float32" => Some(Dtype::F32),
+            "float64" => Some(Dtype::F64),
+            _ => None,
+        }
+    }
+
+    /// Returns the little-endian NumPy type string (e.g. "<f4") corresponding to this data type.
+    pub fn as_numpy_typestr(&self) -> &'static str {
+        match self {
+            Dtype::Bool => "|b1",
+            Dtype::U8 => "|u1",
+            Dtype::U16 => "<u2",
+            Dtype::U32 => "<u4",
+            Dtype::U64 => "<u8",
+            Dtype::I8 => "|i1",
+            Dtype::I16 => "<i2",
+            Dtype::I32 => "<i4",
+            Dtype::I64 => "<i8",
+            Dtype::F16 => "<f2",
+            Dtype::F32 => "<f4",
+            Dtype::F64 => "<f8",
+        }
+    }
+
+    /// Returns the NumPy dtype string corresponding to this data type.
+    pub fn as_numpy_dtype(&self) -> &'static str {
+        match self {
+            Dtype::Bool => "bool",
+            Dtype::U8 => "uint8",
+            Dtype::U16 => "uint16",
+            Dtype::U32 => "uint32",
+            Dtype::U64 => "uint64",
+            Dtype::I8 => "int8",
+            Dtype::I16 => "int16",
+            Dtype::I32 => "int32",
+            Dtype::I64 => "int64",
+            Dtype::F16 => "float16",
+            Dtype::F32 => "float32",
+            Dtype::F64 => "float64",
+        }
+    }
+
+}
+impl std::fmt::Display for Dtype {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// A trait for data types that can be stored in fields.
+/// The trait requires that the type be copyable and also provides a static dtype.
+pub trait Data 
+where Self: Copy 
+{
+    const DTYPE: Dtype;
+}
+impl Data for u8 { const DTYPE: Dtype = Dtype::U8; }
+impl Data for u16 { const DTYPE: Dtype = Dtype::U16; }
+impl Data for u32 { const DTYPE: Dtype = Dtype::U32; }
+impl Data for u64 { const DTYPE: Dtype = Dtype::U64; }
+impl Data for i8 { const DTYPE: Dtype = Dtype::I8; }
+impl Data for i16 { const DTYPE: Dtype = Dtype::I16; }
+impl Data for i32 { const DTYPE: Dtype = Dtype::I32; }
+impl Data for i64 { const DTYPE: Dtype = Dtype::I64; }
+impl Data for half::f16 { const DTYPE: Dtype = Dtype::F16; }
+impl Data for f32 { const DTYPE: Dtype = Dtype::F32; }
+impl Data for f64 { const DTYPE: Dtype = Dtype::F64; }
+
+/// Represents the viewpoint for the point cloud.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Viewpoint {
+    pub tx: f32,
+    pub ty: f32,
+    pub tz: f32,
+    pub qw: f32,
+    pub qx: f32,
+    pub qy: f32,
+    pub qz: f32,
+}
+impl Viewpoint {
+    /// Constructs a new `Viewpoint` from a vector of 7 values.
+    pub fn from(values: Vec<f32>) -> Self {
+        assert_eq!(values.len(), 7, "Viewpoint must have 7 values, provided {} instead", values.len());
+
+        Self {
+            tx: values[0],
+            ty: values[1],
+            tz: values[2],
+            qw: values[3],
+            qx: values[4],
+            qy: values[5],
+            qz: values[6],
+        }
+    }
+
+    /// Returns the viewpoint as a vector of f32 values.
+    pub fn to_vec(&self) -> Vec<f32> {
+        vec![self.tx, self.ty, self.tz, self.qw, self.qx, self.qy, self.qz]
+    }
+}
+impl Default for Viewpoint {
+    fn default() -> Self {
+        Self {
+            tx: 0.0,
+            ty: 0.0,
+            tz: 0.0,
+            qw: 1.0,
+            qx: 0.0,
+            qy: 0.0,
+            qz: 0.0,
+        }
+    }
+}
+impl std::fmt::Display for Viewpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "tx: {:.2}, ty: {:.2}, tz: {:.2}, qw: {:.2}, qx: {:.2}, qy: {:.2}, qz: {:.2}",
+            self.tx, self.ty, self.tz, self.qw, self.qx, self.qy, self.qz
+        )
+    }
+}
+
+/// Represents the encoding format of the point cloud data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    Ascii,
+    Binary,
+    #[default]
+    BinaryCompressed,
+    /// Extension: data laid out as for binary_compressed, compressed with zstd.
+    BinaryZstd,
+    /// Extension: data laid out as for binary_compressed, compressed with LZ4.
+    BinaryLz4,
+    /// Extension: binary_compressed for data larger than 4 GiB, stored as a sequence of LZF
+    /// blocks behind u64 sizes. Only written when opted in (see `io::WriteOptions`).
+    BinaryCompressedLarge,
+}
+impl Encoding {
+    /// Returns the encoding as a string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Encoding::Ascii => "ascii",
+            Encoding::Binary => "binary",
+            Encoding::BinaryCompressed => "binary_compressed",
+            Encoding::BinaryZstd => "binary_zstd",
+            Encoding::BinaryLz4 => "binary_lz4",
+            Encoding::BinaryCompressedLarge => "binary_compressed_large",
+        }
+    }
+
+    /// Creates an `Encoding` from a string.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "ascii" => Some(Encoding::Ascii),
+            "binary" => Some(Encoding::Binary),
+            "binary_compressed" => Some(Encoding::BinaryCompressed),
+            "binary_zstd" => Some(Encoding::BinaryZstd),
+            "binary_lz4" => Some(Encoding::BinaryLz4),
+            "binary_compressed_large" => Some(Encoding::BinaryCompressedLarge),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the data is stored field by field and compressed as a single block.
+    pub fn is_compressed(&self) -> bool {
+        matches!(self, Encoding::BinaryCompressed | Encoding::BinaryZstd | Encoding::BinaryLz4 | Encoding::BinaryCompressedLarge)
+    }
+
+    /// Returns true if PCL can read data in this encoding (false for pcdpy extensions).
+    pub fn is_pcl_compatible(&self) -> bool {
+        matches!(self, Encoding::Ascii | Encoding::Binary | Encoding::BinaryCompressed)
+    }
+}
+
+/// The kind of quantity a field holds, so that tools can find fields by meaning rather than
+/// by name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Semantic {
+    Position,
+    Normal,
+    Color,
+    Time,
+}
+impl Semantic {
+    /// Returns the semantic tag as a string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Semantic::Position => "position",
+            Semantic::Normal => "normal",
+            Semantic::Color => "color",
+            Semantic::Time => "time",
+        }
+    }
+
+    /// Creates a `Semantic` from a string.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "position" => Some(Semantic::Position),
+            "normal" => Some(Semantic::Normal),
+            "color" => Some(Semantic::Color),
+            "time" => Some(Semantic::Time),
+            _ => None,
+        }
+    }
+}
+
+/// Metadata about a single field in the point cloud.
+///
+/// The optional unit, description and semantic tag are written as `# unit.<name>: ...`,
+/// `# description.<name>: ...` and `# semantic.<name>: ...` header comments, after the custom
+/// entries, and parsed back on read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldMeta {
+    pub name: String,
+    pub dtype: Dtype,
+    pub count: usize,
+    /// Unit of the values, e.g. "m" or "mm".
+    pub unit: Option<String>,
+    /// Free text description of the field.
+    pub description: Option<String>,
+    pub semantic: Option<Semantic>,
+}
+impl FieldMeta {
+    /// Creates a `FieldMeta` without a unit, description or semantic tag.
+    pub fn new(name: impl Into<String>, dtype: Dtype, count: usize) -> Self {
+        Self { name: name.into(), dtype, count, unit: None, description: None, semantic: None }
+    }
+
+    /// Returns true if this field has the same name, dtype and count as `other`, whatever their
+    /// units, descriptions and semantic tags.
+    pub fn same_layout(&self, other: &FieldMeta) -> bool {
+        self.name == other.name && self.dtype == other.dtype && self.count == other.count
+    }
+
+    /// Returns the unit, description and semantic tag of this field as the `(key, value)`
+    /// entries written in the header, e.g. `("unit.z", "m")`.
+    pub fn info_entries(&self) -> Vec<(String, String)> {
+        let mut entries = Vec::new();
+        if let Some(unit) = &self.unit {
+            entries.push((format!("unit.{}", self.name), unit.clone()));
+        }
+        if let Some(description) = &self.description {
+            entries.push((format!("description.{}", self.name), description.clone()));
+        }
+        if let Some(semantic) = self.semantic {
+            entries.push((format!("semantic.{}", self.name), semantic.as_str().to_string()));
+        }
+        entries
+    }
+
+    /// Returns the size (in bytes) of a single value of this field.
+    pub fn get_size(&self) -> usize {
+        self.dtype.get_size()
+    }
+
+    /// Returns the type string of this field.
+    pub fn get_type(&self) -> &str {
+        self.dtype.get_type()
+    }
+
+    /// Returns true for padding fields: fields named `_`, as written by PCL, and the `_1`,
+    /// `_2`, ... names given to the extra padding fields of a header when it is read.
+    pub fn is_padding(&self) -> bool {
+        self.name.strip_prefix('_').is_some_and(|n| n.chars().all(|c| c.is_ascii_digit()))
+    }
+
+    /// Returns the name to write in a PCD header: `_` for padding fields.
+    pub fn header_name(&self) -> &str {
+        if self.is_padding() { "_" } else { &self.name }
+    }
+}
+
+/// A schema representing a collection of field metadata.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FieldSchema(pub Vec<FieldMeta>);
+impl FieldSchema {
+    /// Creates an empty `FieldSchema`.
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Returns true if the schema contains no fields.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the number of fields in the schema.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns an iterator over the field metadata.
+    pub fn iter(&self) -> std::slice::Iter<'_, FieldMeta> {
+        self.0.iter()
+    }
+
+    /// Converts the dtype of each field to the one PCD files store it as (see `Dtype::storage`).
+    pub fn to_storage(&mut self) {
+        for field in self.0.iter_mut() {
+            field.dtype = field.dtype.storage();
+        }
+    }
+
+    /// Returns true if both schemas have the same fields, dtypes and counts, in the same order
+    /// (see `FieldMeta::same_layout`).
+    pub fn same_layout(&self, other: &FieldSchema) -> bool {
+        self.len() == other.len() && self.iter().zip(other.iter()).all(|(a, b)| a.same_layout(b))
+    }
+}
+
+impl std::fmt::Display for FieldSchema {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields = self.0.iter()
+            .map(|fm| match &fm.unit {
+                Some(unit) => format!(" - {}[{}] - {} ({})", fm.name, fm.count, fm.dtype, unit),
+                None => format!(" - {}[{}] - {}", fm.name, fm.count, fm.dtype),
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        write!(f, "{}", fields)
+    }
+}
+
+impl Index<usize> for FieldSchema {
+    type Output = FieldMeta;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.0[index]
+    }
+}
+
+impl IndexMut<usize> for FieldSchema {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.0[index]
+    }
+}
+
+impl IntoIterator for FieldSchema {
+    type Item = FieldMeta;
+    type IntoIter = std::vec::IntoIter<FieldMeta>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a FieldSchema {
+    type Item = &'a FieldMeta;
+    type IntoIter = std::slice::Iter<'a, FieldMeta>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut FieldSchema {
+    type Item = &'a mut FieldMeta;
+    type IntoIter = std::slice::IterMut<'a, FieldMeta>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter_mut()
+    }
+}
+
+impl FromIterator<(String, Dtype, usize)> for FieldSchema {
+    fn from_iter<I: IntoIterator<Item = (String, Dtype, usize)>>(iter: I) -> Self {
+        let schema = iter
+            .into_iter()
+            .map(|(name, dtype, count)| FieldMeta::new(name, dtype, count))
+            .collect();
+        FieldSchema(schema)
+    }
+}
+
+impl FromIterator<FieldMeta> for FieldSchema {
+    fn from_iter<I: IntoIterator<Item = FieldMeta>>(iter: I) -> Self {
+        let schema = iter.into_iter().collect();
+        FieldSchema(schema)
+    }
+}
+
+impl<'a> FromIterator<(&'a str, Dtype, usize)> for FieldSchema {
+    fn from_iter<I: IntoIterator<Item = (&'a str, Dtype, usize)>>(iter: I) -> Self {
+        iter.into_iter()
+            .map(|(name, dtype, count)| (name.to_string(), dtype, count))
+            .collect()
+    }
+}
+
+impl<'a> FromIterator<&'a FieldMeta> for FieldSchema {
+    fn from_iter<I: IntoIterator<Item = &'a FieldMeta>>(iter: I) -> Self {
+        Self(
+            iter.into_iter()
+                .map(|fm| fm.to_owned())
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dtype_get_size() {
+        assert_eq!(Dtype::U8.get_size(), 1);
+        assert_eq!(Dtype::U16.get_size(), 2);
+        assert_eq!(Dtype::U32.get_size(), 4);
+        assert_eq!(Dtype::U64.get_size(), 8);
+        assert_eq!(Dtype::I8.get_size(), 1);
+        assert_eq!(Dtype::I16.get_size(), 2);
+        assert_eq!(Dtype::I32.get_size(), 4);
+        assert_eq!(Dtype::I64.get_size(), 8);
+        assert_eq!(Dtype::F32.get_size(), 4);
+        assert_eq!(Dtype::F64.get_size(), 8);
+    }
+
+    #[test]
+    fn test_dtype_get_type() {
+        assert_eq!(Dtype::U8.get_type(), "U");
+        assert_eq!(Dtype::U16.get_type(), "U");
+        assert_eq!(Dtype::U32.get_type(), "U");
+        assert_eq!(Dtype::U64.get_type(), "U");
+        assert_eq!(Dtype::I8.get_type(), "I");
+        assert_eq!(Dtype::I16.get_type(), "I");
+        assert_eq!(Dtype::I32.get_type(), "I");
+        assert_eq!(Dtype::I64.get_type(), "I");
+        assert_eq!(Dtype::F32.get_type(), "F");
+        assert_eq!(Dtype::F64.get_type(), "F");
+    }
+
+    #[test]
+    fn test_dtype_numpy_typestr() {
+        assert_eq!(Dtype::U8.as_numpy_typestr(), "|u1");
+        assert_eq!(Dtype::I16.as_numpy_typestr(), "<i2");
+        assert_eq!(Dtype::U32.as_numpy_typestr(), "<u4");
+        assert_eq!(Dtype::F64.as_numpy_typestr(), "<f8");
+    }
+
+    #[test]
+    fn test_encoding_as_str() {
+        assert_eq!(Encoding::Ascii.as_str(), "ascii");
+        assert_eq!(Encoding::Binary.as_str(), "binary");
+        assert_eq!(Encoding::BinaryCompressed.as_str(), "binary_compressed");
+    }
+
+    #[test]
+    fn test_encoding_from_str() {
+        assert_eq!(Encoding::from_str("ascii"), Some(Encoding::Ascii));
+        assert_eq!(Encoding::from_str("binary"), Some(Encoding::Binary));
+        assert_eq!(Encoding::from_str("binary_compressed"), Some(Encoding::BinaryCompressed));
+        assert_eq!(Encoding::from_str("foobar"), None);
+    }
+
+    #[test]
+    fn test_metadata_default() {
+        let meta = Metadata::default();
+        assert_eq!(meta.fields, FieldSchema::new());
+        assert_eq!(meta.width, 0);
+        assert_eq!(meta.height, 1);
+        assert_eq!(meta.viewpoint, Viewpoint::default());
+        assert_eq!(meta.npoints, 0);
+        assert_eq!(meta.encoding, Encoding::default());
+    }
+
+    #[test]
+    fn test_metadata_slice() {
+        let organized = Metadata { width: 4, height: 3, npoints: 12, ..Metadata::default() };
+        let shape = |md: &Metadata| (md.width, md.height, md.npoints);
+
+        let mut md = organized.clone();
+        md.slice(4, 8, 1, None).unwrap();
+        assert_eq!(shape(&md), (4, 2, 8));
+        for (start, n, step) in [(0, 5, 1), (1, 4, 1), (0, 6, 2), (0, 0, 1)] {
+            let mut md = organized.clone();
+            md.slice(start, n, step, None).unwrap();
+            assert_eq!(shape(&md), (n, 1, n));
+            assert!(organized.clone().slice(start, n, step, Some(true)).is_err());
+        }
+        let mut md = organized.clone();
+        md.slice(0, 4, 1, Some(false)).unwrap();
+        assert_eq!(shape(&md), (4, 1, 4));
+
+        let mut md = Metadata { width: 12, height: 1, npoints: 12, ..Metadata::default() };
+        md.slice(0, 12, 1, None).unwrap();
+        assert_eq!(shape(&md), (12, 1, 12));
+        assert!(md.slice(0, 12, 1, Some(true)).is_err());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,364 @@
+use std::ops::Range;
+use anyhow::Result;
+use rayon::prelude::*;
+use crate::pointcloud::PointCloud;
+
+/// Deepest octree supported: 21 levels of 3 bits fit in a 64-bit Morton code.
+pub const MAX_DEPTH: usize = 21;
+
+/// Spread the low 21 bits of `v` so that bit `i` moves to bit `3 * i`.
+fn spread_bits(v: u64) -> u64 {
+    let mut v = v & 0x1f_ffff;
+    v = (v | v << 32) & 0x001f_0000_0000_ffff;
+    v = (v | v << 16) & 0x001f_0000_ff00_00ff;
+    v = (v | v << 8) & 0x100f_00f0_0f00_f00f;
+    v = (v | v << 4) & 0x10c3_0c30_c30c_30c3;
+    v = (v | v << 2) & 0x1249_2492_4924_9249;
+    v
+}
+
+/// Inverse of `spread_bits`: gather every third bit of `v`, from bit 0.
+fn compact_bits(v: u64) -> u64 {
+    let mut v = v & 0x1249_2492_4924_9249;
+    v = (v | v >> 2) & 0x10c3_0c30_c30c_30c3;
+    v = (v | v >> 4) & 0x100f_00f0_0f00_f00f;
+    v = (v | v >> 8) & 0x001f_0000_ff00_00ff;
+    v = (v | v >> 16) & 0x001f_0000_0000_ffff;
+    v = (v | v >> 32) & 0x1f_ffff;
+    v
+}
+
+/// Morton code of a cell, interleaving the bits of its x, y and z indices (x lowest).
+pub(crate) fn morton_encode(cell: [u64; 3]) -> u64 {
+    spread_bits(cell[0]) | spread_bits(cell[1]) << 1 | spread_bits(cell[2]) << 2
+}
+
+fn morton_decode(code: u64) -> [u64; 3] {
+    [compact_bits(code), compact_bits(code >> 1), compact_bits(code >> 2)]
+}
+
+/// A node of the level-of-detail hierarchy built by `Octree::lod_nodes`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LodNode {
+    /// Level of the node's cell (0 is the root).
+    pub level: usize,
+    /// Morton code of the node's cell at its level.
+    pub code: u64,
+    /// Indices of the points stored in the node.
+    pub points: Vec<usize>,
+    /// Side of the grid the node's points were sampled on: every point of the cell is within
+    /// about this distance of a point of the node or of its ancestors. 0 for nodes without
+    /// children, which hold all the points of their cell not stored in their ancestors.
+    pub spacing: f64,
+    /// Positions of the node's children in the list of nodes.
+    pub children: Vec<usize>,
+}
+
+/// A static linear octree over a set of points.
+///
+/// The root cell is the cube of side `size` at `origin` enclosing the points. Each point is
+/// given the Morton code of its cell at `depth`, and the points are sorted by code, so the
+/// points of any cell at any level form a contiguous range of `order`. Points with a NaN
+/// coordinate are left out.
+#[derive(Debug, Clone)]
+pub struct Octree {
+    points: Vec<[f64; 3]>,
+    origin: [f64; 3],
+    size: f64,
+    depth: usize,
+    /// Point indices sorted by Morton code, and their codes.
+    order: Vec<usize>,
+    codes: Vec<u64>,
+}
+
+impl Octree {
+    /// Build an octree of `depth` levels below the root over `points`. Query results refer to
+    /// positions in this vector.
+    pub fn new(points: Vec<[f64; 3]>, depth: usize) -> Result<Self> {
+        anyhow::ensure!((1..=MAX_DEPTH).contains(&depth), "Octree depth must be between 1 and {}, got {}", MAX_DEPTH, depth);
+        let valid = |p: &[f64; 3]| p.iter().all(|v| v.is_finite());
+        let mut lo = [f64::INFINITY; 3];
+        let mut hi = [f64::NEG_INFINITY; 3];
+        for p in points.iter().filter(|p| valid(p)) {
+            for i in 0..3 {
+                lo[i] = lo[i].min(p[i]);
+                hi[i] = hi[i].max(p[i]);
+            }
+        }
+        let (origin, size) = if lo[0] <= hi[0] {
+            let size = (0..3).map(|i| hi[i] - lo[i]).fold(0.0, f64::max);
+            (lo, if size > 0.0 { size } else { 1.0 })
+        } else {
+            ([0.0; 3], 1.0)
+        };
+
+        let ncells = 1u64 << depth;
+        let mut keyed: Vec<(u64, usize)> = points.par_iter()
+            .enumerate()
+            .filter(|(_, p)| valid(p))
+            .map(|(i, p)| {
+                let cell = [0, 1, 2].map(|j| (((p[j] - origin[j]) / size * ncells as f64) as u64).min(ncells - 1));
+                (morton_encode(cell), i)
+            })
+            .collect();
+        keyed.par_sort_unstable();
+        let (codes, order) = keyed.into_iter().unzip();
+        Ok(Self { points, origin, size, depth, order, codes })
+    }
+
+    /// Number of points in the tree, excluding points with a NaN coordinate.
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Returns true if the tree contains no points.
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    /// Number of levels below the root.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Minimum and maximum corners of the root cell.
+    pub fn bounds(&self) -> ([f64; 3], [f64; 3]) {
+        (self.origin, self.origin.map(|v| v + self.size))
+    }
+
+    /// Ranges of `order` holding the points of each occupied cell at `level`, with the cell's
+    /// Morton code at that level, in Morton order.
+    fn cells(&self, level: usize) -> Result<Vec<(u64, Range<usize>)>> {
+        anyhow::ensure!(level <= self.depth, "Level {} is deeper than the octree depth {}", level, self.depth);
+        let shift = 3 * (self.depth - level);
+        let mut cells: Vec<(u64, Range<usize>)> = Vec::new();
+        for (i, code) in self.codes.iter().enumerate() {
+            let prefix = code >> shift;
+            match cells.last_mut() {
+                Some((last, range)) if *last == prefix => range.end = i + 1,
+                _ => cells.push((prefix, i..i + 1)),
+            }
+        }
+        Ok(cells)
+    }
+
+    /// Minimum corner and side of the cell with Morton code `code` at `level`.
+    pub fn cell_bounds(&self, code: u64, level: usize) -> ([f64; 3], f64) {
+        let side = self.size / (1u64 << level) as f64;
+        let cell = morton_decode(code);
+        ([0, 1, 2].map(|i| self.origin[i] + cell[i] as f64 * side), side)
+    }
+
+    /// Return the point indices in each occupied cell at `level` (0 is the root), one list
+    /// per cell, in Morton order.
+    pub fn leaf_points(&self, level: usize) -> Result<Vec<Vec<usize>>> {
+        Ok(self.cells(level)?
+            .into_iter()
+            .map(|(_, range)| self.order[range].to_vec())
+            .collect())
+    }
+
+    /// Return one point index per occupied cell at `level`: the point closest to the cell
+    /// center, in Morton order. Successive levels give increasingly detailed subsets.
+    pub fn sample_lod(&self, level: usize) -> Result<Vec<usize>> {
+        Ok(self.cells(level)?
+            .into_par_iter()
+            .map(|(code, range)| {
+                let (corner, side) = self.cell_bounds(code, level);
+                let center = corner.map(|v| v + side / 2.0);
+                self.order[range].iter()
+                    .copied()
+                    .min_by(|&a, &b| dist2(&self.points[a], &center).total_cmp(&dist2(&self.points[b], &center)))
+                    .unwrap()
+            })
+            .collect())
+    }
+
+    /// Split the points into a level-of-detail hierarchy of at most `max_points` points per
+    /// node, for streaming to viewers that refine the cloud as they zoom in: each node holds
+    /// an even sample of the points of its cell (one per cell of the deepest level that has
+    /// at most `max_points` occupied cells) not already stored in its ancestors, and its
+    /// children split the remaining points. Cells at the octree depth hold all their points,
+    /// however many. Nodes are listed depth-first from the root, which comes first; the list
+    /// is empty if the tree is.
+    pub fn lod_nodes(&self, max_points: usize) -> Result<Vec<LodNode>> {
+        anyhow::ensure!(max_points > 0, "max_points must be positive");
+        let mut taken = vec![false; self.len()];
+        let mut nodes = Vec::new();
+        if !self.is_empty() {
+            self.build_lod(0, 0, 0..self.len(), max_points, &mut taken, &mut nodes);
+        }
+        Ok(nodes)
+    }
+
+    /// Add the node of the cell `code` at `level`, whose points are `range` of `order`, and its
+    /// descendants to `nodes`. `taken` marks the positions in `order` stored in a node.
+    fn build_lod(&self, level: usize, code: u64, range: Range<usize>, max_points: usize, taken: &mut [bool], nodes: &mut Vec<LodNode>) {
+        let remaining: Vec<usize> = range.clone().filter(|&i| !taken[i]).collect();
+        let index = nodes.len();
+        nodes.push(LodNode { level, code, points: Vec::new(), spacing: 0.0, children: Vec::new() });
+        if remaining.len() <= max_points || level == self.depth {
+            for &i in &remaining {
+                taken[i] = true;
+            }
+            nodes[index].points = remaining.iter().map(|&i| self.order[i]).collect();
+            return;
+        }
+
+        // Runs of `remaining` in the same cell at `sub`, whose codes are sorted
+        let cells_at = |sub: usize| {
+            let shift = 3 * (self.depth - sub);
+            remaining.chunk_by(move |&a, &b| self.codes[a] >> shift == self.codes[b] >> shift)
+        };
+        let sub = (level..=self.depth).take_while(|&sub| cells_at(sub).count() <= max_points).last().unwrap();
+        let shift = 3 * (self.depth - sub);
+        let mut points = Vec::new();
+        for cell in cells_at(sub) {
+            let (corner, side) = self.cell_bounds(self.codes[cell[0]] >> shift, sub);
+            let center = corner.map(|v| v + side / 2.0);
+            let &i = cell.iter()
+                .min_by(|&&a, &&b| dist2(&self.points[self.order[a]], &center).total_cmp(&dist2(&self.points[self.order[b]], &center)))
+                .unwrap();
+            taken[i] = true;
+            points.push(self.order[i]);
+        }
+        nodes[index].points = points;
+        nodes[index].spacing = self.size / (1u64 << sub) as f64;
+
+        let shift = 3 * (self.depth - level - 1);
+        let codes = &self.codes[range.clone()];
+        let mut start = range.start;
+        for child in 0..8 {
+            let child_code = code << 3 | child;
+            let end = range.start + codes.partition_point(|c| c >> shift <= child_code);
+            if (start..end).any(|i| !taken[i]) {
+                let position = nodes.len();
+                nodes[index].children.push(position);
+                self.build_lod(level + 1, child_code, start..end, max_points, taken, nodes);
+            }
+            start = end;
+        }
+    }
+
+    /// Return the indices of the points inside the axis-aligned box `[min_bound, max_bound]`
+    /// (bounds inclusive), in ascending order.
+    pub fn query_box(&self, min_bound: [f64; 3], max_bound: [f64; 3]) -> Vec<usize> {
+        let mut found = Vec::new();
+        self.collect_box(0, 0, 0..self.len(), &min_bound, &max_bound, &mut found);
+        found.sort_unstable();
+        found
+    }
+
+    fn collect_box(&self, level: usize, code: u64, range: Range<usize>, lo: &[f64; 3], hi: &[f64; 3], found: &mut Vec<usize>) {
+        if range.is_empty() {
+            return;
+        }
+        let (corner, side) = self.cell_bounds(code, level);
+        if (0..3).any(|i| corner[i] > hi[i] || corner[i] + side < lo[i]) {
+            return;
+        }
+        if (0..3).all(|i| corner[i] >= lo[i] && corner[i] + side <= hi[i]) {
+            found.extend_from_slice(&self.order[range]);
+            return;
+        }
+        if level == self.depth {
+            found.extend(self.order[range].iter().filter(|&&i| {
+                let p = &self.points[i];
+                (0..3).all(|j| p[j] >= lo[j] && p[j] <= hi[j])
+            }));
+            return;
+        }
+        let shift = 3 * (self.depth - level - 1);
+        let codes = &self.codes[range.clone()];
+        let mut start = range.start;
+        for child in 0..8 {
+            let child_code = code << 3 | child;
+            let end = range.start + codes.partition_point(|c| c >> shift <= child_code);
+            self.collect_box(level + 1, child_code, start..end, lo, hi, found);
+            start = end;
+        }
+    }
+}
+
+fn dist2(p: &[f64; 3], q: &[f64; 3]) -> f64 {
+    (0..3).map(|i| (p[i] - q[i]).powi(2)).sum()
+}
+
+impl PointCloud {
+    /// Build an octree of `depth` levels over the `names` coordinate fields (any dtype,
+    /// count of 1).
+    pub fn build_octree(&self, depth: usize, names: [&str; 3]) -> Result<Octree> {
+        Octree::new(self.coordinates(names)?, depth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_morton_roundtrip() {
+        for cell in [[0, 0, 0], [1, 0, 0], [0, 1, 0], [0, 0, 1], [5, 17, 1 << 20], [(1 << 21) - 1; 3]] {
+            assert_eq!(morton_decode(morton_encode(cell)), cell);
+        }
+        assert_eq!(morton_encode([1, 1, 1]), 0b111);
+        assert_eq!(morton_encode([2, 0, 1]), 0b1100);
+    }
+
+    #[test]
+    fn test_octree_queries() {
+        let mut state = 777u64;
+        let mut next = || {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 11) as f64 / (1u64 << 53) as f64 * 8.0
+        };
+        let mut points: Vec<[f64; 3]> = (0..400).map(|_| [next(), next(), next()]).collect();
+        points.push([f64::NAN, 0.0, 0.0]);
+        let tree = Octree::new(points.clone(), 5).unwrap();
+        assert_eq!(tree.len(), 400);
+
+        let root = tree.leaf_points(0).unwrap();
+        assert_eq!(root.len(), 1);
+        assert_eq!(root[0].len(), 400);
+        let cells = tree.leaf_points(1).unwrap();
+        assert_eq!(cells.len(), 8);
+        assert_eq!(cells.iter().map(Vec::len).sum::<usize>(), 400);
+        // Each cell of the first level is one octant of the bounds
+        let (lo, hi) = tree.bounds();
+        let mid = [0, 1, 2].map(|i| (lo[i] + hi[i]) / 2.0);
+        for &i in &cells[3] {
+            assert!(points[i][0] >= mid[0] && points[i][1] >= mid[1] && points[i][2] < mid[2]);
+        }
+
+        let lod = tree.sample_lod(2).unwrap();
+        assert_eq!(lod.len(), tree.leaf_points(2).unwrap().len());
+        assert!(tree.sample_lod(6).is_err());
+
+        let (min_bound, max_bound) = ([1.0, 2.5, 0.0], [4.0, 6.0, 3.3]);
+        let expected: Vec<usize> = points.iter().enumerate()
+            .filter(|(_, p)| (0..3).all(|j| p[j] >= min_bound[j] && p[j] <= max_bound[j]))
+            .map(|(i, _)| i)
+            .collect();
+        assert!(!expected.is_empty());
+        assert_eq!(tree.query_box(min_bound, max_bound), expected);
+        assert_eq!(tree.query_box([-1.0; 3], [9.0; 3]).len(), 400);
+
+        let nodes = tree.lod_nodes(50).unwrap();
+        let mut stored: Vec<usize> = nodes.iter().flat_map(|node| node.points.clone()).collect();
+        stored.sort_unstable();
+        assert_eq!(stored, (0..400).collect::<Vec<_>>());
+        assert_eq!((nodes[0].level, nodes[0].points.len()), (0, 8));
+        assert_eq!(nodes[0].spacing, (hi[0] - lo[0]) / 2.0);
+        for node in &nodes {
+            assert!(node.points.len() <= 50);
+            assert_eq!(node.children.is_empty(), node.spacing == 0.0);
+            let (corner, side) = tree.cell_bounds(node.code, node.level);
+            assert!(node.points.iter().all(|&i| (0..3).all(|j| points[i][j] >= corner[j] && points[i][j] <= corner[j] + side + 1e-9)));
+            assert!(node.children.iter().all(|&c| nodes[c].level == node.level + 1 && nodes[c].code >> 3 == node.code));
+        }
+        assert!(tree.lod_nodes(0).is_err());
+
+        assert!(Octree::new(points, 0).is_err());
+        assert!(Octree::new(Vec::new(), 3).unwrap().leaf_points(3).unwrap().is_empty());
+    }
+}
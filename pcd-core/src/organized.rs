@@ -0,0 +1,199 @@
+use anyhow::Result;
+use ndarray::{Array2, Array3, ArrayView2, Zip};
+use crate::fielddata::FieldData;
+use crate::metadata::{Dtype, FieldSchema, Metadata};
+use crate::pointcloud::PointCloud;
+
+/// Axis order of the values of an organized cloud laid out as an image by `to_image`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImageOrder {
+    /// (height, width, count), indexed by row then column: the row-major layout of PCD data,
+    /// as used by PCL and image libraries.
+    #[default]
+    HeightWidth,
+    /// (width, height, count), indexed by column then row.
+    WidthHeight,
+}
+impl ImageOrder {
+    /// Returns the order as a string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            ImageOrder::HeightWidth => "hw",
+            ImageOrder::WidthHeight => "wh",
+        }
+    }
+
+    /// Creates an `ImageOrder` from a string.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "hw" => Some(ImageOrder::HeightWidth),
+            "wh" => Some(ImageOrder::WidthHeight),
+            _ => None,
+        }
+    }
+}
+
+/// Lays out `values`, one row of values per point in row-major point order, as an image of
+/// `width` x `height` points with the axes in `order`.
+pub fn to_image<T: Clone>(values: ArrayView2<T>, width: usize, height: usize, order: ImageOrder) -> Result<Array3<T>> {
+    anyhow::ensure!(values.nrows() == width * height,
+        "Shape {} x {} (width x height) does not match the number of points ({})", width, height, values.nrows());
+    let image = values.to_shape((height, width, values.ncols()))?;
+    Ok(match order {
+        ImageOrder::HeightWidth => image.into_owned(),
+        ImageOrder::WidthHeight => image.permuted_axes([1, 0, 2]).as_standard_layout().into_owned(),
+    })
+}
+
+impl PointCloud {
+    /// Returns true if the PointCloud is organized, i.e. laid out as an image with more than one row.
+    pub fn is_organized(&self) -> bool {
+        self.metadata.read().unwrap().height > 1
+    }
+
+    /// Returns the point index of image coordinates (`row`, `col`) in the row-major point order.
+    pub fn point_index(&self, row: usize, col: usize) -> Result<usize> {
+        let md = self.metadata.read().unwrap();
+        anyhow::ensure!(row < md.height && col < md.width,
+            "Point ({}, {}) is out of bounds for a {}x{} (height x width) PointCloud", row, col, md.height, md.width);
+        Ok(row * md.width + col)
+    }
+
+    /// Return a new organized PointCloud with the points at every (row, col) pair of `rows` x `cols`,
+    /// in that order. The result has a height of `rows.len()` and a width of `cols.len()`.
+    pub fn take_grid(&self, rows: &[usize], cols: &[usize]) -> Result<Self> {
+        let indices = rows.iter()
+            .flat_map(|&row| cols.iter().map(move |&col| self.point_index(row, col)))
+            .collect::<Result<Vec<usize>>>()?;
+        let pc = self.take_rows(&indices)?;
+        {
+            let mut md = pc.metadata.write().unwrap();
+            md.width = cols.len();
+            md.height = rows.len();
+        }
+        Ok(pc)
+    }
+
+    /// Returns the distance of each point from the sensor origin, computed from the `names`
+    /// coordinate fields and laid out as a (height, width) image. NaN coordinates give a NaN range.
+    pub fn range_image(&self, names: [&str; 3]) -> Result<Array2<f64>> {
+        let (width, height) = {
+            let md = self.metadata.read().unwrap();
+            (md.width, md.height)
+        };
+        let points = self.coordinates(names)?;
+        let ranges = points.iter()
+            .map(|p| (p[0] * p[0] + p[1] * p[1] + p[2] * p[2]).sqrt())
+            .collect::<Vec<f64>>();
+        Ok(Array2::from_shape_vec((height, width), ranges)?)
+    }
+
+    /// Back-projects a (height, width) depth image through pinhole `intrinsics` `[fx, fy, cx, cy]`
+    /// into an organized PointCloud with F32 `x`, `y`, and `z` fields. Pixels with a depth that is
+    /// not finite and positive become NaN points, so the image layout is preserved.
+    pub fn from_depth_image(depth: ArrayView2<f64>, intrinsics: [f64; 4]) -> Result<Self> {
+        let [fx, fy, cx, cy] = intrinsics;
+        anyhow::ensure!(fx != 0.0 && fy != 0.0, "Focal lengths must be non-zero");
+        let (height, width) = depth.dim();
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1), ("y", Dtype::F32, 1), ("z", Dtype::F32, 1)]),
+            width,
+            height,
+            npoints: width * height,
+            ..Metadata::default()
+        };
+
+        let points = Zip::indexed(depth).par_map_collect(|(v, u), &d| {
+            if d.is_finite() && d > 0.0 {
+                [((u as f64 - cx) * d / fx) as f32, ((v as f64 - cy) * d / fy) as f32, d as f32]
+            } else {
+                [f32::NAN; 3]
+            }
+        });
+
+        let mut pc = PointCloud::empty(&md);
+        for (i, name) in ["x", "y", "z"].into_iter().enumerate() {
+            let column: Vec<f32> = points.iter().map(|p| p[i]).collect();
+            let column = Array2::from_shape_vec((width * height, 1), column)?;
+            pc.fields.insert(name.to_string(), FieldData::F32(column.into_shared()));
+        }
+        Ok(pc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{Dtype, FieldSchema, Metadata};
+    use ndarray::{Array1, Axis};
+
+    #[test]
+    fn test_take_grid() {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("i", Dtype::U8, 1)]),
+            width: 4,
+            height: 3,
+            npoints: 12,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        for i in 0..12 {
+            pc.fields.get_mut("i").unwrap().assign_row(i, &Array1::from(vec![i as u8]));
+        }
+        assert!(pc.is_organized());
+        assert_eq!(pc.point_index(2, 1).unwrap(), 9);
+        assert!(pc.point_index(3, 0).is_err());
+
+        let sub = pc.take_grid(&[1, 2], &[0, 2, 3]).unwrap();
+        let md = sub.metadata.read().unwrap();
+        assert_eq!((md.width, md.height, md.npoints), (3, 2, 6));
+        let values: Vec<u8> = (0..6).map(|i| sub.fields["i"].get_row::<u8>(i)[0]).collect();
+        assert_eq!(values, vec![4, 6, 7, 8, 10, 11]);
+        assert!(pc.take_grid(&[0], &[4]).is_err());
+    }
+
+    #[test]
+    fn test_to_image() {
+        // An organized cloud read from PCD data, with two values per point
+        let data = "VERSION 0.7\nFIELDS v\nSIZE 2\nTYPE U\nCOUNT 2\nWIDTH 3\nHEIGHT 2\nPOINTS 6\nDATA ascii\n\
+            0 1\n2 3\n4 5\n6 7\n8 9\n10 11\n";
+        let pc = PointCloud::from_pcd_bytes(data.as_bytes()).unwrap();
+        let values = pc.fields["v"].get_data::<u16>();
+
+        let hw = to_image(values.view(), 3, 2, ImageOrder::HeightWidth).unwrap();
+        let wh = to_image(values.view(), 3, 2, ImageOrder::WidthHeight).unwrap();
+        assert_eq!((hw.dim(), wh.dim()), ((2, 3, 2), (3, 2, 2)));
+        for (row, col) in [(0, 0), (0, 2), (1, 0), (1, 1)] {
+            let point = values.row(pc.point_index(row, col).unwrap());
+            assert_eq!(hw.slice(ndarray::s![row, col, ..]), point);
+            assert_eq!(wh.slice(ndarray::s![col, row, ..]), point);
+        }
+
+        // Flattening the rows of the image gives back the points in order
+        let flat = hw.to_shape((6, 2)).unwrap();
+        assert_eq!(flat, values);
+        let flat = wh.view().permuted_axes([1, 0, 2]);
+        assert_eq!(flat.as_standard_layout().to_shape((6, 2)).unwrap(), values);
+        assert_eq!(wh.index_axis(Axis(0), 1).column(0).to_vec(), vec![2, 8]);
+        assert!(to_image(values.view(), 2, 2, ImageOrder::HeightWidth).is_err());
+        assert_eq!(ImageOrder::from_str(ImageOrder::WidthHeight.as_str()), Some(ImageOrder::WidthHeight));
+    }
+
+    #[test]
+    fn test_depth_range_round_trip() {
+        let depth = ndarray::arr2(&[[2.0, 0.0, 2.0], [4.0, f64::NAN, 1.0]]);
+        let pc = PointCloud::from_depth_image(depth.view(), [1.0, 1.0, 1.0, 0.0]).unwrap();
+        let md = pc.metadata.read().unwrap().clone();
+        assert_eq!((md.width, md.height, md.npoints), (3, 2, 6));
+        assert_eq!(pc.fields["x"].get_row::<f32>(0)[0], -2.0);
+        assert_eq!(pc.fields["y"].get_row::<f32>(3)[0], 4.0);
+        pc.check_pointcloud().unwrap();
+
+        let ranges = pc.range_image(["x", "y", "z"]).unwrap();
+        assert_eq!(ranges.dim(), (2, 3));
+        assert!((ranges[[0, 0]] - 8f64.sqrt()).abs() < 1e-6);
+        assert!(ranges[[0, 1]].is_nan() && ranges[[1, 1]].is_nan());
+        assert!(PointCloud::from_depth_image(depth.view(), [0.0, 1.0, 0.0, 0.0]).is_err());
+    }
+}
@@ -0,0 +1,1394 @@
+use std::{fs::File, io::{BufRead, BufReader, BufWriter, Seek, Write}};
+use anyhow::Result;
+use crate::error::{ErrorKind, PcdError};
+use crate::fielddata::{CastPolicy, FieldData};
+use crate::fieldmap::FieldMap;
+use crate::metadata::{Dtype, Metadata, Encoding, FieldMeta, SharedMetadata};
+use crate::utils::parse_header;
+use crate::io;
+use crate::io_ply::{self, PlyFormat};
+use crate::io_npz;
+use crate::io_tiles;
+use crate::io_text;
+use crate::io_ros;
+
+
+#[derive(Debug, Clone)]
+pub struct PointCloud {
+    pub fields: FieldMap,
+    pub metadata: SharedMetadata,
+}
+
+impl PointCloud {
+    /// Creates a new PointCloud from the provided metadata.
+    pub fn new(md: &Metadata) -> Self {
+        let npoints = md.npoints;
+        let shared_md = std::sync::Arc::new(std::sync::RwLock::new(md.clone()));
+        let mut fields_map = FieldMap::with_capacity(md.fields.len());
+        for f in &md.fields {
+            fields_map.insert(f.name.clone(), FieldData::new(f.dtype, npoints, f.count));
+        }
+        Self {
+            fields: fields_map,
+            metadata: shared_md,
+        }
+    }
+
+    /// Creates an empty PointCloud with the given metadata.
+    pub fn empty(md: &Metadata) -> Self {
+        let shared_md = std::sync::Arc::new(std::sync::RwLock::new(md.clone()));
+        Self {
+            fields: FieldMap::new(),
+            metadata: shared_md,
+        }
+    }
+
+    /// Return every mismatch between the metadata and the field data, or an empty Vec if
+    /// they are consistent.
+    pub fn problems(&self) -> Vec<String> {
+        let md = self.metadata.read().unwrap();
+        let mut problems = Vec::new();
+        if md.height.checked_mul(md.width) != Some(md.npoints) {
+            problems.push(format!("Metadata height x width ({} x {}) does not match npoints {}", md.height, md.width, md.npoints));
+        }
+
+        for field_meta in md.fields.iter() {
+            let Some(field_data) = self.fields.get(&field_meta.name) else {
+                problems.push(format!("Field '{}' exists in metadata but not in data", field_meta.name));
+                continue;
+            };
+            if field_data.npoints() != md.npoints {
+                problems.push(format!("Field '{}' has {} points, but npoints is {}", field_meta.name, field_data.npoints(), md.npoints));
+            }
+            if field_data.count() != field_meta.count {
+                problems.push(format!("Field '{}' count {} does not match metadata count {}", field_meta.name, field_data.count(), field_meta.count));
+            }
+            if field_data.dtype() != field_meta.dtype {
+                problems.push(format!("Field '{}' dtype does not match metadata", field_meta.name));
+            }
+        }
+        let mut extra: Vec<&String> = self.fields.keys()
+            .filter(|name| !md.fields.iter().any(|f| f.name == **name))
+            .collect();
+        extra.sort();
+        for field_name in extra {
+            problems.push(format!("Field '{}' exists in data but not in metadata", field_name));
+        }
+        problems
+    }
+
+    /// Check if PointCloud metadata matches field data, reporting all mismatches in the error
+    pub fn check_pointcloud(&self) -> Result<()> {
+        let problems = self.problems();
+        anyhow::ensure!(problems.is_empty(), PcdError::new(ErrorKind::SchemaMismatch, problems.join("; ")));
+        Ok(())
+    }
+
+    /// Recompute the metadata from the field data: npoints from the field shapes, WIDTH and
+    /// HEIGHT (as an unorganized cloud) if they no longer match it, and the COUNT and dtype of
+    /// each field. Fails if the fields disagree on the number of points, or if fields are
+    /// missing from either side.
+    pub fn sync_metadata(&self) -> Result<()> {
+        {
+            let mut md = self.metadata.write().unwrap();
+            let mut npoints = self.fields.values().map(|f| f.npoints());
+            if let Some(n) = npoints.next() {
+                anyhow::ensure!(npoints.all(|m| m == n), PcdError::new(ErrorKind::SchemaMismatch, "Fields have different numbers of points"));
+                md.npoints = n;
+            }
+            if md.width.checked_mul(md.height) != Some(md.npoints) {
+                md.width = md.npoints;
+                md.height = 1;
+            }
+            for field_meta in &mut md.fields {
+                if let Some(field_data) = self.fields.get(&field_meta.name) {
+                    field_meta.count = field_data.count();
+                    field_meta.dtype = field_data.dtype();
+                }
+            }
+        }
+        self.check_pointcloud()
+    }
+
+    /// Return an independent copy of this PointCloud. Field data is shared copy-on-write, and
+    /// the metadata is copied (unlike `clone`, which shares the metadata).
+    pub fn copy(&self) -> Self {
+        let md = Metadata::from_shared(self.metadata.clone());
+        Self {
+            fields: self.fields.clone(),
+            metadata: std::sync::Arc::new(std::sync::RwLock::new(md)),
+        }
+    }
+
+    /// Return the field names in schema order.
+    pub fn field_names(&self) -> Vec<String> {
+        self.metadata.read().unwrap().fields.iter().map(|f| f.name.clone()).collect()
+    }
+
+    /// Return number of points in PointCloud
+    pub fn len(&self) -> usize {
+        let md = self.metadata.read().unwrap();
+        md.npoints
+    }
+
+    /// Returns true if the PointCloud has no points.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return a new PointCloud containing the points at `indices`, in that order.
+    pub fn take_rows(&self, indices: &[usize]) -> Result<Self> {
+        let mut md = Metadata::from_shared(self.metadata.clone());
+        md.trim(indices.len());
+        let mut pc = PointCloud::empty(&md);
+        for (field_name, field_data) in &self.fields {
+            pc.fields.insert(field_name.clone(), field_data.take_rows(indices)?);
+        }
+        Ok(pc)
+    }
+
+    /// Return a new PointCloud containing the points from `start` up to `stop` (exclusive),
+    /// every `step` points. See `Metadata::slice` for whether the result is organized.
+    pub fn slice(&self, start: usize, stop: usize, step: usize, organized: Option<bool>) -> Result<Self> {
+        anyhow::ensure!(step > 0, "Slice step must be positive");
+        anyhow::ensure!(start <= self.len(), "Slice start {} is out of range for {} points", start, self.len());
+        let stop = stop.min(self.len()).max(start);
+        let mut md = Metadata::from_shared(self.metadata.clone());
+        md.slice(start, (stop - start).div_ceil(step), step, organized)?;
+        let mut pc = PointCloud::empty(&md);
+        for (field_name, field_data) in &self.fields {
+            pc.fields.insert(field_name.clone(), field_data.slice(start, stop, step));
+        }
+        Ok(pc)
+    }
+
+    /// Assign point `i` of `other` to point `indices[i]`.
+    /// Only fields present in both PointClouds are updated.
+    pub fn assign_rows(&mut self, indices: &[usize], other: &PointCloud) -> Result<()> {
+        for (field_name, field_data) in &mut self.fields {
+            if let Some(new_field) = other.fields.get(field_name) {
+                field_data.assign_rows(indices, new_field)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Return a new PointCloud containing only the points where `mask` is true.
+    pub fn select_mask(&self, mask: &[bool]) -> Result<Self> {
+        anyhow::ensure!(mask.len() == self.len(), "Mask length mismatch: expected {}, got {}", self.len(), mask.len());
+        let indices: Vec<usize> = mask.iter().enumerate().filter_map(|(i, &m)| m.then_some(i)).collect();
+        self.take_rows(&indices)
+    }
+
+    /// Assign the points of `other` to the points where `mask` is true, in order.
+    /// Only fields present in both PointClouds are updated.
+    pub fn assign_mask(&mut self, mask: &[bool], other: &PointCloud) -> Result<()> {
+        anyhow::ensure!(mask.len() == self.len(), "Mask length mismatch: expected {}, got {}", self.len(), mask.len());
+        let indices: Vec<usize> = mask.iter().enumerate().filter_map(|(i, &m)| m.then_some(i)).collect();
+        self.assign_rows(&indices, other)
+    }
+
+    /// Return the `names` coordinate fields (any dtype, count of 1) as one `[f64; 3]` per point.
+    pub fn coordinates(&self, names: [&str; 3]) -> Result<Vec<[f64; 3]>> {
+        let mut columns = Vec::with_capacity(3);
+        for name in names {
+            let field = self.fields.get(name)
+                .ok_or_else(|| anyhow::anyhow!("Field '{}' not found", name))?;
+            anyhow::ensure!(field.count() == 1, "Field '{}' must have a count of 1", name);
+            columns.push(field.get_data::<f64>().remove_axis(ndarray::Axis(1)));
+        }
+        Ok((0..self.len())
+            .map(|i| [columns[0][i], columns[1][i], columns[2][i]])
+            .collect())
+    }
+
+    /// Detect the names of the coordinate fields: for each of x, y and z, a field with that
+    /// name, else a field renamed to it by the field aliases, else a field with that name in
+    /// another case. Returns None if any of them is missing.
+    pub fn coordinate_fields(&self) -> Option<[String; 3]> {
+        let md = self.metadata.read().unwrap();
+        let aliases = crate::utils::field_aliases();
+        let find = |axis: &str| {
+            md.fields.iter().find(|f| f.name == axis)
+                .or_else(|| md.fields.iter().find(|f| aliases.iter().any(|(from, to)| *from == f.name && to == axis)))
+                .or_else(|| md.fields.iter().find(|f| f.name.eq_ignore_ascii_case(axis)))
+                .map(|f| f.name.clone())
+        };
+        Some([find("x")?, find("y")?, find("z")?])
+    }
+
+    /// Return the coordinate fields `names` as a single (npoints, 3) field, float32 if all
+    /// three are float32 and float64 otherwise.
+    pub fn xyz(&self, names: [&str; 3]) -> Result<FieldData> {
+        let columns = names.iter()
+            .map(|name| {
+                let field = self.fields.get(*name)
+                    .ok_or_else(|| anyhow::anyhow!("Field '{}' not found", name))?;
+                anyhow::ensure!(field.count() == 1, "Field '{}' must have a count of 1", name);
+                Ok(field)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(FieldData::hstack_float(&columns))
+    }
+
+    /// Set the coordinate fields `names` from the columns of an (npoints, 3) field. Existing
+    /// fields keep their dtype (failing if a value cannot be represented in it), and missing
+    /// ones are added with the dtype of `data`. If any column fails, no field is changed.
+    pub fn set_xyz(&mut self, names: [&str; 3], data: &FieldData) -> Result<()> {
+        anyhow::ensure!(data.count() == 3, "Expected 3 columns, got {}", data.count());
+        let columns = names.iter().enumerate()
+            .map(|(j, name)| {
+                let column = data.columns(j, j + 1);
+                match self.fields.get(*name) {
+                    Some(field) => column.cast(field.dtype(), CastPolicy::Error)
+                        .map_err(|e| anyhow::anyhow!("Field '{}': {}", name, e)),
+                    None => Ok(column),
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        anyhow::ensure!(data.npoints() == self.len(),
+            "Array length mismatch: expected {}, got {}", self.len(), data.npoints());
+        for (name, column) in names.into_iter().zip(columns) {
+            self.insert_field(name, column)?;
+        }
+        Ok(())
+    }
+
+    /// Remove a field from the PointCloud and its metadata, returning the removed data.
+    pub fn drop_field(&mut self, name: &str) -> Result<FieldData> {
+        let mut md = self.metadata.write().unwrap();
+        let idx = md.fields.iter().position(|f| f.name == name)
+            .ok_or_else(|| anyhow::anyhow!("Field '{}' not found", name))?;
+        md.fields.0.remove(idx);
+        md.remove_categories(name);
+        self.fields.remove(name)
+            .ok_or_else(|| anyhow::anyhow!("Field '{}' missing from PointCloud data", name))
+    }
+
+    /// Return a new PointCloud with only the fields in `names`, in that order. Field data is
+    /// shared copy-on-write, and the metadata is copied with a trimmed schema.
+    pub fn select(&self, names: &[String]) -> Result<Self> {
+        let mut md = Metadata::from_shared(self.metadata.clone());
+        let mut schema = Vec::with_capacity(names.len());
+        let mut fields = FieldMap::with_capacity(names.len());
+        for name in names {
+            anyhow::ensure!(!fields.contains_key(name), "Field '{}' selected twice", name);
+            let field_meta = md.fields.iter().find(|f| &f.name == name)
+                .ok_or_else(|| anyhow::anyhow!("Field '{}' not found", name))?;
+            let data = self.fields.get(name)
+                .ok_or_else(|| anyhow::anyhow!("Field '{}' missing from PointCloud data", name))?;
+            schema.push(field_meta.clone());
+            fields.insert(name.clone(), data.clone());
+        }
+        md.fields.0 = schema;
+        Ok(Self { fields, metadata: std::sync::Arc::new(std::sync::RwLock::new(md)) })
+    }
+
+    /// Rename a field in both the PointCloud data and its metadata.
+    pub fn rename_field(&mut self, old: &str, new: &str) -> Result<()> {
+        anyhow::ensure!(!new.is_empty(), "Field name cannot be empty");
+        let mut md = self.metadata.write().unwrap();
+        anyhow::ensure!(!md.fields.iter().any(|f| f.name == new), "Field '{}' already exists", new);
+        let idx = md.fields.iter().position(|f| f.name == old)
+            .ok_or_else(|| anyhow::anyhow!("Field '{}' not found", old))?;
+        anyhow::ensure!(self.fields.rename(old, new), "Field '{}' missing from PointCloud data", old);
+        md.fields[idx].name = new.to_string();
+        if let Some(categories) = md.remove_categories(old) {
+            md.set_categories(new, &categories)?;
+        }
+        Ok(())
+    }
+
+    /// Copy every field of `other`, which must have the same number of points, into this
+    /// PointCloud. Fields that already exist are an error unless `overwrite` is set, in which
+    /// case they are replaced (including their dtype and count).
+    pub fn merge_fields(&mut self, other: &PointCloud, overwrite: bool) -> Result<()> {
+        let other_md = Metadata::from_shared(other.metadata.clone());
+        anyhow::ensure!(other_md.npoints == self.len(),
+            "Point count mismatch: expected {}, got {}", self.len(), other_md.npoints);
+        if !overwrite {
+            let existing: Vec<&str> = other_md.fields.iter()
+                .filter(|f| self.fields.contains_key(&f.name))
+                .map(|f| f.name.as_str())
+                .collect();
+            anyhow::ensure!(existing.is_empty(), "Fields already exist: {}", existing.join(", "));
+        }
+        let data = other_md.fields.iter()
+            .map(|f| other.fields.get(&f.name)
+                .ok_or_else(|| anyhow::anyhow!("Field '{}' missing from PointCloud data", f.name)))
+            .collect::<Result<Vec<&FieldData>>>()?;
+
+        for (field_meta, field_data) in other_md.fields.into_iter().zip(data) {
+            self.insert_field(&field_meta.name, field_data.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Add a field, or replace an existing field of the same name (including its dtype and
+    /// count, but keeping its unit, description and semantic tag), updating the metadata to
+    /// match.
+    pub fn insert_field(&mut self, name: &str, data: FieldData) -> Result<()> {
+        anyhow::ensure!(!name.is_empty(), "Field name cannot be empty");
+        let mut md = self.metadata.write().unwrap();
+        anyhow::ensure!(data.npoints() == md.npoints,
+            "Array length mismatch: expected {}, got {}", md.npoints, data.npoints());
+        match md.fields.0.iter_mut().find(|f| f.name == name) {
+            Some(field_meta) => {
+                field_meta.dtype = data.dtype();
+                field_meta.count = data.count();
+            }
+            None => md.fields.0.push(FieldMeta::new(name, data.dtype(), data.count())),
+        }
+        self.fields.insert(name.to_string(), data);
+        Ok(())
+    }
+
+    /// Convert field `name` to `dtype`, handling unrepresentable values according to `policy`.
+    pub fn cast_field(&mut self, name: &str, dtype: Dtype, policy: CastPolicy) -> Result<()> {
+        self.astype(&[(name, dtype)], policy)
+    }
+
+    /// Convert several fields at once. Either every field is converted or, if any conversion
+    /// fails, the PointCloud is left unchanged.
+    pub fn astype(&mut self, dtypes: &[(&str, Dtype)], policy: CastPolicy) -> Result<()> {
+        let converted = dtypes.iter()
+            .map(|(name, dtype)| {
+                let field = self.fields.get(*name)
+                    .ok_or_else(|| anyhow::anyhow!("Field '{}' not found", name))?;
+                let data = field.cast(*dtype, policy)
+                    .map_err(|e| anyhow::anyhow!("Field '{}': {}", name, e))?;
+                Ok((*name, data))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        for (name, data) in converted {
+            self.insert_field(name, data)?;
+        }
+        Ok(())
+    }
+
+    /// Reserve room for at least `additional` more points in every field.
+    pub fn reserve(&mut self, additional: usize) -> Result<()> {
+        for field_data in self.fields.values_mut() {
+            field_data.reserve(additional)?;
+        }
+        Ok(())
+    }
+
+    /// Grow or shrink the PointCloud to `npoints` points. New points are zero-filled.
+    /// The result keeps its width if it is organized and `npoints` is a multiple of the width,
+    /// otherwise it is unorganized (height of 1).
+    pub fn resize(&mut self, npoints: usize) -> Result<()> {
+        for field_data in self.fields.values_mut() {
+            field_data.resize(npoints)?;
+        }
+        let mut md = self.metadata.write().unwrap();
+        md.npoints = npoints;
+        if md.height > 1 && md.width > 0 && npoints.is_multiple_of(md.width) {
+            md.height = npoints / md.width;
+        } else {
+            md.width = npoints;
+            md.height = 1;
+        }
+        Ok(())
+    }
+
+    /// Stack the points of several PointClouds with identical schemas into a new PointCloud.
+    /// The result keeps an organized layout if all inputs are organized with the same width,
+    /// otherwise it is unorganized (height of 1). Other metadata is taken from the first cloud.
+    pub fn concat(clouds: &[&PointCloud]) -> Result<Self> {
+        let (first, rest) = clouds.split_first()
+            .ok_or_else(|| anyhow::anyhow!("Cannot concatenate an empty list of PointClouds"))?;
+        let mut md = Metadata::from_shared(first.metadata.clone());
+        let mds: Vec<Metadata> = rest.iter().map(|pc| Metadata::from_shared(pc.metadata.clone())).collect();
+        for other in &mds {
+            anyhow::ensure!(other.fields.same_layout(&md.fields), PcdError::new(ErrorKind::SchemaMismatch,
+                format!("PointCloud schemas do not match:\n{}\nvs\n{}", md.fields, other.fields)));
+        }
+
+        let organized = md.height > 1 && mds.iter().all(|other| other.height > 1 && other.width == md.width);
+        md.npoints += mds.iter().map(|other| other.npoints).sum::<usize>();
+        if organized {
+            md.height += mds.iter().map(|other| other.height).sum::<usize>();
+        } else {
+            md.width = md.npoints;
+            md.height = 1;
+        }
+
+        let mut pc = PointCloud::empty(&md);
+        for (field_name, field_data) in &first.fields {
+            let others = rest.iter()
+                .map(|other| other.fields.get(field_name)
+                    .ok_or_else(|| anyhow::anyhow!("Field '{}' missing from PointCloud data", field_name)))
+                .collect::<Result<Vec<&FieldData>>>()?;
+            pc.fields.insert(field_name.clone(), field_data.concat(&others)?);
+        }
+        Ok(pc)
+    }
+
+    /// Read data from PCD file and return a new PointCloud
+    pub fn from_pcd_file(path: &str) -> Result<Self> {
+        let file = File::open(path)?;
+        Ok(Self::from_pcd_reader(&mut BufReader::new(file), true)?.0)
+    }
+
+    /// Read PCD data (header and body) from any buffered reader and return a new PointCloud,
+    /// together with warnings for header lines that were recovered (see `utils::parse_header`),
+    /// for a WIDTH x HEIGHT that does not match POINTS (read as an unorganized cloud), and for
+    /// malformed ASCII values that were substituted when `strict` is false
+    /// (see `io::read_ascii_data`).
+    pub fn from_pcd_reader<R: BufRead + Seek>(reader: &mut R, strict: bool) -> Result<(Self, Vec<String>)> {
+        Self::from_pcd_reader_with(reader, &io::ReadOptions { strict, ..Default::default() })
+    }
+
+    /// Read PCD data from any buffered reader as `from_pcd_reader`, according to `options`.
+    /// If `options.fields` is set, only those fields are decoded and stored.
+    ///
+    /// If `options.start` or `options.count` select a subset of the points, the result is an
+    /// unorganized cloud of those points. Binary data is seeked directly to the first point
+    /// and ASCII data skips the preceding lines, while compressed data has to be decompressed
+    /// in full.
+    pub fn from_pcd_reader_with<R: BufRead + Seek>(reader: &mut R, options: &io::ReadOptions) -> Result<(Self, Vec<String>)> {
+        let (md, mut warnings) = read_header(reader)?;
+        let (start, count) = options.point_range(md.npoints)?;
+        let mut selected = match &options.fields {
+            Some(names) => md.select_fields(names)?,
+            None => md.clone(),
+        };
+        if count != md.npoints {
+            selected.width = count;
+            selected.height = 1;
+            selected.npoints = count;
+        }
+        // Decode with the file layout of the selected points; fields that were not selected
+        // are not allocated and are skipped by the readers.
+        let mut pc = PointCloud::new(&selected);
+        *pc.metadata.write().unwrap() = Metadata { fields: md.fields.clone(), ..selected.clone() };
+
+        if options.verify {
+            let mut body = Vec::new();
+            reader.read_to_end(&mut body)?;
+            let data = io::verify_checksum(&body)?;
+            warnings.extend(pc.read_data(&mut std::io::Cursor::new(data), &md, start, count, options.strict)?);
+        } else {
+            warnings.extend(pc.read_data(reader, &md, start, count, options.strict)?);
+        }
+
+        *pc.metadata.write().unwrap() = selected;
+        Ok((pc, warnings))
+    }
+
+    /// Decode `count` points from point `start` of the data section laid out as `md` into
+    /// the fields of this cloud. Returns the warnings of lenient ASCII reads.
+    fn read_data<R: BufRead + Seek>(&mut self, reader: &mut R, md: &Metadata, start: usize, count: usize, strict: bool) -> Result<Vec<String>> {
+        match md.encoding {
+            Encoding::Ascii => {
+                for _ in 0..start {
+                    io::read_nonempty_line(reader)?;
+                }
+                io::read_ascii_data(reader, self, strict)
+            }
+            Encoding::Binary => {
+                reader.seek_relative((start * md.point_size()) as i64)?;
+                io::read_binary_data(reader, self)?;
+                Ok(Vec::new())
+            }
+            Encoding::BinaryCompressed | Encoding::BinaryZstd | Encoding::BinaryLz4 | Encoding::BinaryCompressedLarge => {
+                let buffer = io::read_compressed_buffer(reader, md.encoding, Some(md.data_size()))?;
+                io::assign_compressed_rows(&buffer, md, &mut self.fields, start, count)?;
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    /// Read PCD data from an in-memory buffer and return a new PointCloud
+    pub fn from_pcd_bytes(data: &[u8]) -> Result<Self> {
+        Ok(Self::from_pcd_reader(&mut std::io::Cursor::new(data), true)?.0)
+    }
+
+    /// Writes the PointCloud data to a PCD file.
+    pub fn to_pcd_file(&self, path: &str) -> Result<()> {
+        self.to_pcd_file_with(path, &io::WriteOptions::default())
+    }
+
+    /// Writes the PointCloud data to a PCD file, overriding the metadata with `options`.
+    pub fn to_pcd_file_with(&self, path: &str, options: &io::WriteOptions) -> Result<()> {
+        let file = File::create(path)?;
+        let mut writer = BufWriter::new(file);
+        self.to_pcd_writer_with(&mut writer, options)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes the PointCloud data (header and body) in PCD format to any writer.
+    pub fn to_pcd_writer<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.to_pcd_writer_with(writer, &io::WriteOptions::default())
+    }
+
+    /// Writes the PointCloud data (header and body) in PCD format to any writer, overriding
+    /// the metadata with `options`.
+    pub fn to_pcd_writer_with<W: Write>(&self, writer: &mut W, options: &io::WriteOptions) -> Result<()> {
+        self.check_pointcloud()?;
+        if options.skip_padding && self.metadata.read().unwrap().fields.iter().any(|f| f.is_padding()) {
+            let mut md = Metadata::from_shared(self.metadata.clone());
+            md.fields.0.retain(|f| !f.is_padding());
+            let mut pc = PointCloud::empty(&md);
+            for f in md.fields.iter() {
+                pc.fields.insert(f.name.clone(), self.fields[&f.name].clone());
+            }
+            return pc.to_pcd_writer_with(writer, &io::WriteOptions { skip_padding: false, ..options.clone() });
+        }
+        if self.metadata.read().unwrap().fields.iter().any(|f| f.dtype.storage() != f.dtype) {
+            return self.to_storage()?.to_pcd_writer_with(writer, options);
+        }
+        let md = options.apply(&self.metadata.read().unwrap());
+        anyhow::ensure!(!options.pcl_compatible || md.encoding.is_pcl_compatible(),
+            "Encoding {} is a pcdpy extension that PCL cannot read", md.encoding.as_str());
+        io::write_header(writer, &md)?;
+        if options.checksum {
+            let mut writer = io::ChecksumWriter::new(writer);
+            self.write_data(&mut writer, md.encoding, options)?;
+            writer.write_footer()
+        } else {
+            self.write_data(writer, md.encoding, options)
+        }
+    }
+
+    /// Returns the PointCloud with its fields converted to the dtypes PCD files store them as
+    /// (see `Dtype::storage`). The other fields share their data with this PointCloud.
+    pub fn to_storage(&self) -> Result<Self> {
+        self.check_pointcloud()?;
+        let mut md = Metadata::from_shared(self.metadata.clone());
+        md.fields.to_storage();
+        let mut pc = PointCloud::empty(&md);
+        for f in md.fields.iter() {
+            pc.fields.insert(f.name.clone(), self.fields[&f.name].cast(f.dtype, CastPolicy::Error)?);
+        }
+        Ok(pc)
+    }
+
+    /// Writes the data section of the PointCloud in `encoding`.
+    fn write_data<W: Write>(&self, writer: &mut W, encoding: Encoding, options: &io::WriteOptions) -> Result<()> {
+        match encoding {
+            Encoding::Ascii => io::write_ascii_data(writer, self, options),
+            Encoding::Binary => io::write_binary_data(writer, self),
+            Encoding::BinaryCompressed | Encoding::BinaryZstd | Encoding::BinaryLz4 | Encoding::BinaryCompressedLarge =>
+                io::write_compressed_data(writer, self, encoding, options),
+        }
+    }
+
+    /// Returns the PointCloud encoded as an in-memory PCD file.
+    pub fn to_pcd_bytes(&self) -> Result<Vec<u8>> {
+        self.to_pcd_bytes_with(&io::WriteOptions::default())
+    }
+
+    /// Returns the PointCloud encoded as an in-memory PCD file, overriding the metadata
+    /// with `options`.
+    pub fn to_pcd_bytes_with(&self, options: &io::WriteOptions) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        self.to_pcd_writer_with(&mut buf, options)?;
+        Ok(buf)
+    }
+
+    /// Read vertex data from a PLY file and return a new PointCloud
+    pub fn from_ply_file(path: &str) -> Result<Self> {
+        io_ply::read_ply(path)
+    }
+
+    /// Writes the PointCloud data to a PLY file.
+    pub fn to_ply_file(&self, path: &str, format: PlyFormat) -> Result<()> {
+        io_ply::write_ply(self, path, format)
+    }
+
+    /// Read a PointCloud from a NumPy .npz archive
+    pub fn from_npz_file(path: &str) -> Result<Self> {
+        io_npz::read_npz(path)
+    }
+
+    /// Writes the PointCloud to a NumPy .npz archive, deflate-compressed if `compressed` is set
+    pub fn to_npz_file(&self, path: &str, compressed: bool) -> Result<()> {
+        io_npz::write_npz(self, path, compressed)
+    }
+
+    /// Writes the PointCloud as a 3D Tiles tileset of `names` coordinates in the directory `dir`.
+    pub fn to_3d_tiles(&self, dir: &str, names: [&str; 3], options: &io_tiles::TilesOptions) -> Result<()> {
+        io_tiles::write_3d_tiles(self, dir, names, options)
+    }
+
+    /// Read a PointCloud from a delimited text file (.xyz, .csv, .tsv, ...), inferring the
+    /// delimiter, field names and field types when not given
+    pub fn from_text_file(path: &str, delimiter: Option<char>, names: Option<&[String]>, skip_rows: usize) -> Result<Self> {
+        io_text::read_text(path, delimiter, names, skip_rows)
+    }
+
+    /// Writes the PointCloud to a delimited text file, with a header line of field names if
+    /// `header` is set
+    pub fn to_csv_file(&self, path: &str, delimiter: u8, header: bool, float_format: io::FloatFormat) -> Result<()> {
+        io_text::write_text_file(self, path, delimiter, header, float_format)
+    }
+
+    /// Read the points of a ROS PointCloud2 message and return a new PointCloud
+    pub fn from_ros(cloud: &io_ros::RosCloud) -> Result<Self> {
+        io_ros::read_ros(cloud)
+    }
+
+    /// Convert the PointCloud to a little-endian ROS PointCloud2 message
+    pub fn to_ros(&self) -> Result<io_ros::RosCloud<'static>> {
+        io_ros::write_ros(self)
+    }
+
+    /// Read point records from a LAS/LAZ file and return a new PointCloud
+    #[cfg(feature = "las")]
+    pub fn from_las_file(path: &str) -> Result<Self> {
+        crate::io_las::read_las(path)
+    }
+
+    /// Writes the PointCloud to a LAS file, or LAZ if `path` ends with `.laz`, with
+    /// coordinates stored as multiples of `scale`.
+    #[cfg(feature = "las")]
+    pub fn to_las_file(&self, path: &str, scale: f64) -> Result<()> {
+        crate::io_las::write_las(self, path, scale)
+    }
+
+    /// Read scan `scan_index` of an E57 file and return a new PointCloud
+    #[cfg(feature = "e57")]
+    pub fn from_e57_file(path: &str, scan_index: usize) -> Result<Self> {
+        crate::io_e57::read_e57(path, scan_index)
+    }
+
+    /// Read data from a Parquet file and return a new PointCloud
+    #[cfg(feature = "arrow")]
+    pub fn from_parquet_file(path: &str) -> Result<Self> {
+        crate::io_arrow::read_parquet(path)
+    }
+
+    /// Writes the PointCloud data to a Parquet file.
+    #[cfg(feature = "arrow")]
+    pub fn to_parquet_file(&self, path: &str) -> Result<()> {
+        crate::io_arrow::write_parquet(self, path)
+    }
+}
+
+/// Two PointClouds are equal if their metadata and field values are equal, with NaN values
+/// comparing equal to each other.
+impl PartialEq for PointCloud {
+    fn eq(&self, other: &Self) -> bool {
+        // Metadata shared between clones is equal by definition (and must not be locked twice).
+        let same_metadata = std::sync::Arc::ptr_eq(&self.metadata, &other.metadata)
+            || *self.metadata.read().unwrap() == *other.metadata.read().unwrap();
+        same_metadata
+            && self.fields.len() == other.fields.len()
+            && self.fields.iter().all(|(name, data)| other.fields.get(name).is_some_and(|o| data.equal_nan(o)))
+    }
+}
+
+/// Streaming reader that yields a PCD file's points in chunks of at most `chunk_size`
+/// points. Each chunk is an unorganized PointCloud (height of 1) sharing the file schema.
+///
+/// ASCII and binary bodies are read incrementally. Binary compressed bodies are stored as
+/// a single LZF block, so they are decompressed once up front and chunks are sliced out of
+/// the decompressed buffer.
+pub struct PcdReader {
+    reader: BufReader<File>,
+    metadata: Metadata,
+    warnings: Vec<String>,
+    chunk_size: usize,
+    position: usize,
+    decompressed: Option<Vec<u8>>,
+}
+
+/// Parse a PCD header as `utils::parse_header`, reading clouds whose WIDTH x HEIGHT does not
+/// match POINTS as unorganized clouds of POINTS points, with a warning.
+fn read_header<R: BufRead>(reader: &mut R) -> Result<(Metadata, Vec<String>)> {
+    let (mut md, mut warnings) = parse_header(reader)?;
+    if md.width.checked_mul(md.height) != Some(md.npoints) {
+        warnings.push(format!("WIDTH x HEIGHT ({} x {}) does not match POINTS {}, reading an unorganized cloud",
+            md.width, md.height, md.npoints));
+        md.width = md.npoints;
+        md.height = 1;
+    }
+    Ok((md, warnings))
+}
+
+impl PcdReader {
+    /// Opens a PCD file and parses its header, leaving the reader positioned at the data.
+    pub fn open(path: &str, chunk_size: usize) -> Result<Self> {
+        anyhow::ensure!(chunk_size > 0, "Chunk size must be greater than 0");
+        let file = File::open(path)?;
+        let mut reader = BufReader::new(file);
+        let (metadata, warnings) = read_header(&mut reader)?;
+        Ok(Self {
+            reader,
+            metadata,
+            warnings,
+            chunk_size,
+            position: 0,
+            decompressed: None,
+        })
+    }
+
+    /// Returns the metadata parsed from the file header.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Returns the warnings for header defects that were recovered (see `read_header`).
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Returns the number of points that have not been read yet.
+    pub fn remaining(&self) -> usize {
+        self.metadata.npoints - self.position
+    }
+
+    /// Reads the next `n` points into a new PointCloud.
+    fn read_chunk(&mut self, n: usize) -> Result<PointCloud> {
+        let md = Metadata {
+            width: n,
+            height: 1,
+            npoints: n,
+            ..self.metadata.clone()
+        };
+        let mut pc = PointCloud::new(&md);
+
+        match self.metadata.encoding {
+            Encoding::Ascii => {
+                io::read_ascii_data(&mut self.reader, &mut pc, true)?;
+            }
+            Encoding::Binary => io::read_binary_data(&mut self.reader, &mut pc)?,
+            Encoding::BinaryCompressed | Encoding::BinaryZstd | Encoding::BinaryLz4 | Encoding::BinaryCompressedLarge => {
+                if self.decompressed.is_none() {
+                    self.decompressed = Some(io::read_compressed_buffer(&mut self.reader, self.metadata.encoding, Some(self.metadata.data_size()))?);
+                }
+                let buf = self.decompressed.as_ref().unwrap();
+                io::assign_compressed_rows(buf, &self.metadata, &mut pc.fields, self.position, n)?;
+            }
+        }
+
+        self.position += n;
+        Ok(pc)
+    }
+}
+
+impl Iterator for PcdReader {
+    type Item = Result<PointCloud>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let n = self.remaining().min(self.chunk_size);
+        if n == 0 {
+            return None;
+        }
+        let chunk = self.read_chunk(n);
+        if chunk.is_err() {
+            // Stop iterating after an error, the reader position is no longer reliable.
+            self.position = self.metadata.npoints;
+        }
+        Some(chunk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array1;
+    use crate::metadata::FieldSchema;
+
+    fn test_cloud(npoints: usize) -> PointCloud {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1), ("label", Dtype::U16, 2)]),
+            width: npoints,
+            height: 1,
+            npoints,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        for i in 0..npoints {
+            pc.fields.get_mut("x").unwrap().assign_row(i, &Array1::from(vec![i as f32 * 0.5]));
+            pc.fields.get_mut("label").unwrap().assign_row(i, &Array1::from(vec![i as u16, 2 * i as u16]));
+        }
+        pc
+    }
+
+    #[test]
+    fn test_pcd_reader_chunks() {
+        let pc = test_cloud(5);
+        for encoding in [Encoding::Ascii, Encoding::Binary, Encoding::BinaryCompressed] {
+            pc.metadata.write().unwrap().encoding = encoding;
+            let path = std::env::temp_dir().join(format!("pcdpy_test_reader_{}.pcd", encoding.as_str()));
+            let path = path.to_str().unwrap();
+            pc.to_pcd_file(path).unwrap();
+
+            let chunks = PcdReader::open(path, 2).unwrap()
+                .collect::<Result<Vec<PointCloud>>>()
+                .unwrap();
+            std::fs::remove_file(path).unwrap();
+
+            assert_eq!(chunks.iter().map(|c| c.len()).collect::<Vec<_>>(), vec![2, 2, 1]);
+            assert_eq!(chunks[1].fields["x"].get_row::<f32>(1)[0], 1.5);
+            assert_eq!(chunks[2].fields["label"].get_row::<u16>(0), Array1::from(vec![4u16, 8]));
+        }
+    }
+
+    #[test]
+    fn test_problems() {
+        let mut pc = test_cloud(3);
+        assert!(pc.problems().is_empty());
+        pc.check_pointcloud().unwrap();
+
+        pc.metadata.write().unwrap().width = 2;
+        pc.fields.insert("label".to_string(), FieldData::new(Dtype::U16, 3, 1));
+        pc.fields.insert("extra".to_string(), FieldData::new(Dtype::F32, 2, 1));
+        pc.fields.remove("x");
+        let problems = pc.problems();
+        assert_eq!(problems.len(), 4, "{:?}", problems);
+        assert!(problems[1].contains("'x' exists in metadata"));
+        assert!(problems[2].contains("'label' count 1"));
+        assert!(problems[3].contains("'extra' exists in data"));
+        let err = pc.check_pointcloud().unwrap_err().to_string();
+        assert!(err.contains("height x width") && err.contains("'extra'"));
+
+        // Writers refuse a desynchronized cloud instead of panicking or writing a corrupt file
+        let pc = test_cloud(3);
+        pc.metadata.write().unwrap().fields[0].name = "renamed".to_string();
+        let err = pc.to_pcd_bytes().unwrap_err();
+        assert_eq!(ErrorKind::of(&err), Some(ErrorKind::SchemaMismatch));
+        let pc = test_cloud(3);
+        pc.metadata.write().unwrap().fields[0].dtype = Dtype::F64;
+        assert_eq!(ErrorKind::of(&pc.to_pcd_bytes().unwrap_err()), Some(ErrorKind::SchemaMismatch));
+        assert_eq!(ErrorKind::of(&pc.to_storage().unwrap_err()), Some(ErrorKind::SchemaMismatch));
+    }
+
+    #[test]
+    fn test_sync_metadata() {
+        let mut pc = test_cloud(3);
+        pc.metadata.write().unwrap().npoints = 5;
+        pc.fields.insert("label".to_string(), FieldData::new(Dtype::U8, 3, 1));
+        pc.sync_metadata().unwrap();
+        {
+            let md = pc.metadata.read().unwrap();
+            assert_eq!((md.width, md.height, md.npoints), (3, 1, 3));
+            assert_eq!((md.fields[1].dtype, md.fields[1].count), (Dtype::U8, 1));
+        }
+
+        pc.fields.insert("label".to_string(), FieldData::new(Dtype::U8, 2, 1));
+        assert_eq!(ErrorKind::of(&pc.sync_metadata().unwrap_err()), Some(ErrorKind::SchemaMismatch));
+        pc.fields.remove("label");
+        assert!(pc.sync_metadata().unwrap_err().to_string().contains("'label' exists in metadata"));
+    }
+
+    #[test]
+    fn test_slice() {
+        let pc = test_cloud(12);
+        pc.metadata.write().unwrap().width = 4;
+        pc.metadata.write().unwrap().height = 3;
+        let rows = pc.slice(4, 12, 1, None).unwrap();
+        assert_eq!(rows.fields, pc.take_rows(&(4..12).collect::<Vec<_>>()).unwrap().fields);
+        assert!(rows.is_organized());
+        let shape = |pc: &PointCloud| { let md = pc.metadata.read().unwrap(); (md.width, md.height, md.npoints) };
+        assert_eq!(shape(&rows), (4, 2, 8));
+        let strided = pc.slice(1, 100, 3, None).unwrap();
+        assert_eq!(strided.fields, pc.take_rows(&[1, 4, 7, 10]).unwrap().fields);
+        assert_eq!(shape(&strided), (4, 1, 4));
+        assert_eq!(shape(&pc.slice(5, 2, 1, None).unwrap()), (0, 1, 0));
+        assert!(pc.slice(1, 5, 1, Some(true)).is_err());
+        assert!(pc.slice(13, 14, 1, None).is_err());
+    }
+
+    #[test]
+    fn test_xyz() {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1), ("y", Dtype::F32, 1), ("z", Dtype::F64, 1)]),
+            width: 2,
+            height: 1,
+            npoints: 2,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        pc.fields.get_mut("y").unwrap().assign_row(1, &Array1::from(vec![2.5f32]));
+        let xyz = pc.xyz(["x", "y", "z"]).unwrap();
+        assert_eq!(xyz.dtype(), Dtype::F64);
+        assert_eq!(xyz.get_row::<f64>(1), Array1::from(vec![0.0, 2.5, 0.0]));
+        assert_eq!(pc.xyz(["x", "y", "y"]).unwrap().dtype(), Dtype::F32);
+        assert!(pc.xyz(["x", "y", "w"]).is_err());
+
+        let data = FieldData::F64(ndarray::arr2(&[[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]).into_shared());
+        pc.set_xyz(["x", "y", "w"], &data).unwrap();
+        assert_eq!(pc.fields["y"].dtype(), Dtype::F32);
+        assert_eq!(pc.fields["w"].get_row::<f64>(1)[0], 6.0);
+        assert_eq!(pc.xyz(["x", "y", "w"]).unwrap().get_row::<f64>(0), Array1::from(vec![1.0, 2.0, 3.0]));
+
+        pc.insert_field("i", FieldData::new(Dtype::U8, 2, 1)).unwrap();
+        let too_large = FieldData::F64(ndarray::arr2(&[[0.0, 0.0, 300.0], [0.0, 0.0, 0.0]]).into_shared());
+        assert!(pc.set_xyz(["x", "y", "i"], &too_large).is_err());
+        assert_eq!(pc.fields["x"].get_row::<f32>(0)[0], 1.0);
+    }
+
+    #[test]
+    fn test_coordinate_fields() {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("X", Dtype::F32, 1), ("Y", Dtype::F32, 1), ("z", Dtype::F32, 1), ("Z", Dtype::F32, 1)]),
+            ..Metadata::default()
+        };
+        let pc = PointCloud::new(&md);
+        assert_eq!(pc.coordinate_fields().unwrap(), ["X", "Y", "z"]);
+        assert!(test_cloud(1).coordinate_fields().is_none());
+    }
+
+    #[test]
+    fn test_padding_round_trip() {
+        let mut bytes = b"VERSION 0.7\nFIELDS x _ y _\nSIZE 4 1 4 1\nTYPE F U F U\nCOUNT 1 3 1 3\nWIDTH 2\nHEIGHT 1\nVIEWPOINT 0 0 0 1 0 0 0\nPOINTS 2\nDATA binary\n".to_vec();
+        bytes.extend((0..28).map(|i| i as u8));
+        let pc = PointCloud::from_pcd_bytes(&bytes).unwrap();
+        assert_eq!(pc.fields["_1"].get_row::<u8>(1), Array1::from(vec![25u8, 26, 27]));
+        assert_eq!(pc.to_pcd_bytes().unwrap(), bytes);
+
+        let options = io::WriteOptions { skip_padding: true, ..Default::default() };
+        let written = pc.to_pcd_bytes_with(&options).unwrap();
+        let without = PointCloud::from_pcd_bytes(&written).unwrap();
+        assert_eq!(without.metadata.read().unwrap().point_size(), 8);
+        assert!(written.starts_with(b"VERSION 0.7\nFIELDS x y\n"));
+        assert_eq!(without.fields["y"].get_row::<f32>(0), pc.fields["y"].get_row::<f32>(0));
+    }
+
+    #[test]
+    fn test_f16_widened_on_write() {
+        use half::f16;
+        let values = [0.5, -1.25, 65504.0, f32::NAN];
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("intensity", Dtype::F16, 1), ("label", Dtype::U16, 1)]),
+            width: 4,
+            height: 1,
+            npoints: 4,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        for (i, &v) in values.iter().enumerate() {
+            pc.fields.get_mut("intensity").unwrap().assign_row(i, &Array1::from(vec![f16::from_f32(v)]));
+        }
+        let expected = FieldData::F32(ndarray::Array2::from_shape_vec((4, 1), values.to_vec()).unwrap().into_shared());
+        for encoding in [Encoding::Ascii, Encoding::Binary, Encoding::BinaryCompressed] {
+            let options = io::WriteOptions { encoding: Some(encoding), ..Default::default() };
+            let bytes = pc.to_pcd_bytes_with(&options).unwrap();
+            assert!(String::from_utf8_lossy(&bytes).contains("SIZE 4 2\nTYPE F U\n"));
+            let read = PointCloud::from_pcd_bytes(&bytes).unwrap();
+            assert!(read.fields["intensity"].equal_nan(&expected), "{}", encoding.as_str());
+            assert_eq!(read.fields["label"], pc.fields["label"]);
+        }
+        assert_eq!(pc.fields["intensity"].dtype(), Dtype::F16);
+
+        // Half floats written by other tools are read as such
+        let mut bytes = b"VERSION 0.7\nFIELDS intensity\nSIZE 2\nTYPE F\nCOUNT 1\nWIDTH 2\nHEIGHT 1\nPOINTS 2\nDATA binary\n".to_vec();
+        bytes.extend(f16::from_f32(1.5).to_le_bytes());
+        bytes.extend(f16::from_f32(-2.0).to_le_bytes());
+        let read = PointCloud::from_pcd_bytes(&bytes).unwrap();
+        assert_eq!(read.fields["intensity"].get_data::<f32>().column(0).to_vec(), [1.5, -2.0]);
+        let ascii = b"VERSION 0.7\nFIELDS intensity\nSIZE 2\nTYPE F\nCOUNT 1\nWIDTH 2\nHEIGHT 1\nPOINTS 2\nDATA ascii\n1.5\n-2\n";
+        assert_eq!(PointCloud::from_pcd_bytes(ascii).unwrap().fields, read.fields);
+    }
+
+    #[test]
+    fn test_bool_stored_as_u8() {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1)]),
+            width: 4,
+            height: 1,
+            npoints: 4,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        for (i, v) in [0.0f32, 2.5, -1.0, 0.0].into_iter().enumerate() {
+            pc.fields.get_mut("x").unwrap().assign_row(i, &Array1::from(vec![v]));
+        }
+        pc.insert_field("valid", pc.fields["x"].cast(Dtype::Bool, CastPolicy::Error).unwrap()).unwrap();
+        assert_eq!(pc.fields["valid"].get_data::<u8>().column(0).to_vec(), [0, 1, 1, 0]);
+        assert_eq!(pc.field_stats("valid").unwrap()[0].mean, 0.5);
+
+        let options = io::WriteOptions { encoding: Some(Encoding::Ascii), ..Default::default() };
+        let bytes = pc.to_pcd_bytes_with(&options).unwrap();
+        let text = String::from_utf8_lossy(&bytes);
+        assert!(text.contains("SIZE 4 1\nTYPE F U\n"));
+        assert!(text.trim_end().ends_with("DATA ascii\n0.0 0\n2.5 1\n-1.0 1\n0.0 0"));
+        let read = PointCloud::from_pcd_bytes(&bytes).unwrap();
+        assert_eq!(read.fields["valid"], pc.fields["valid"].cast(Dtype::U8, CastPolicy::Error).unwrap());
+    }
+
+    #[test]
+    fn test_concat() {
+        let a = test_cloud(3);
+        let b = test_cloud(2);
+        let c = PointCloud::concat(&[&a, &b]).unwrap();
+        assert_eq!(c.len(), 5);
+        assert!(c.fields.values().all(|f| f.npoints() == 5));
+        assert_eq!(c.fields["x"].get_row::<f32>(4)[0], 0.5);
+
+        let mut md = Metadata::from_shared(b.metadata.clone());
+        md.fields.0.pop();
+        let d = PointCloud::new(&md);
+        assert!(PointCloud::concat(&[&a, &d]).is_err());
+        assert!(PointCloud::concat(&[]).is_err());
+    }
+
+    #[test]
+    fn test_drop_rename_field() {
+        let mut pc = test_cloud(3);
+        pc.rename_field("label", "class").unwrap();
+        assert!(pc.rename_field("x", "class").is_err());
+        assert!(pc.rename_field("missing", "y").is_err());
+        assert!(pc.fields.contains_key("class"));
+        assert_eq!(pc.metadata.read().unwrap().fields[1].name, "class");
+        assert!(pc.fields.keys().eq(pc.field_names().iter()));
+
+        let dropped = pc.drop_field("x").unwrap();
+        assert_eq!(dropped.npoints(), 3);
+        assert!(pc.drop_field("x").is_err());
+        assert_eq!(pc.fields.len(), 1);
+        assert_eq!(pc.metadata.read().unwrap().fields.len(), 1);
+    }
+
+    #[test]
+    fn test_select() {
+        let pc = test_cloud(3);
+        let selected = pc.select(&["label".to_string(), "x".to_string()]).unwrap();
+        assert_eq!(selected.field_names(), ["label", "x"]);
+        assert!(selected.fields.keys().eq(selected.field_names().iter()));
+        assert_eq!(selected.fields["x"], pc.fields["x"]);
+        assert_eq!(selected.len(), 3);
+        assert!(selected.check_pointcloud().is_ok());
+        assert_eq!(pc.field_names(), ["x", "label"]);
+
+        assert!(pc.select(&["x".to_string(), "x".to_string()]).is_err());
+        assert!(pc.select(&["missing".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_astype() {
+        let mut pc = test_cloud(3);
+        pc.cast_field("label", Dtype::F32, CastPolicy::Error).unwrap();
+        assert_eq!(pc.metadata.read().unwrap().fields[1].dtype, Dtype::F32);
+        assert_eq!(pc.fields["label"].get_row::<f32>(2), Array1::from(vec![2.0, 4.0]));
+
+        pc.fields.get_mut("x").unwrap().assign_row(0, &Array1::from(vec![-1.0f32]));
+        assert!(pc.astype(&[("label", Dtype::U8), ("x", Dtype::U8)], CastPolicy::Error).is_err());
+        assert_eq!(pc.fields["label"].dtype(), Dtype::F32);
+        pc.astype(&[("label", Dtype::U8), ("x", Dtype::U8)], CastPolicy::Saturate).unwrap();
+        assert_eq!(pc.fields["x"].get_row::<u8>(0)[0], 0);
+        assert!(pc.cast_field("missing", Dtype::U8, CastPolicy::Error).is_err());
+    }
+
+    #[test]
+    fn test_send_sync() {
+        // Python wrappers release the GIL around I/O, which requires these to cross threads
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<PointCloud>();
+        fn assert_send<T: Send>() {}
+        assert_send::<PcdReader>();
+    }
+
+    #[test]
+    fn test_copy_eq() {
+        let pc = test_cloud(3);
+        let copy = pc.copy();
+        assert_eq!(pc, copy);
+        copy.metadata.write().unwrap().version = "0.6".to_string();
+        assert_eq!(pc.metadata.read().unwrap().version, "0.7");
+        assert_ne!(pc, copy);
+
+        let mut copy = pc.copy();
+        copy.fields.get_mut("x").unwrap().assign_row(0, &Array1::from(vec![f32::NAN]));
+        assert_ne!(pc, copy);
+        assert_eq!(copy, copy.copy());
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut pc = test_cloud(4);
+        pc.reserve(100).unwrap();
+        pc.resize(10).unwrap();
+        assert_eq!(pc.len(), 10);
+        assert_eq!(pc.fields["label"].npoints(), 10);
+        assert_eq!(pc.fields["x"].get_row::<f32>(9)[0], 0.0);
+
+        {
+            let mut md = pc.metadata.write().unwrap();
+            md.width = 5;
+            md.height = 2;
+        }
+        pc.resize(15).unwrap();
+        assert_eq!((pc.metadata.read().unwrap().width, pc.metadata.read().unwrap().height), (5, 3));
+        pc.resize(7).unwrap();
+        assert_eq!((pc.metadata.read().unwrap().width, pc.metadata.read().unwrap().height), (7, 1));
+        assert_eq!(pc.fields["label"].npoints(), 7);
+
+        // A width of 0 cannot be kept
+        {
+            let mut md = pc.metadata.write().unwrap();
+            md.width = 0;
+            md.height = 2;
+        }
+        pc.resize(0).unwrap();
+        assert_eq!((pc.metadata.read().unwrap().width, pc.metadata.read().unwrap().height), (0, 1));
+    }
+
+    #[test]
+    fn test_merge_fields() {
+        let mut pc = test_cloud(3);
+        let mut other = test_cloud(3);
+        other.rename_field("label", "pred").unwrap();
+        assert!(pc.merge_fields(&other, false).is_err());
+        assert!(pc.merge_fields(&test_cloud(4), true).is_err());
+        assert_eq!(pc.fields.len(), 2);
+
+        other.drop_field("x").unwrap();
+        pc.merge_fields(&other, false).unwrap();
+        assert_eq!(pc.metadata.read().unwrap().fields[2].name, "pred");
+        assert_eq!(pc.fields["pred"].get_row::<u16>(2), Array1::from(vec![2, 4]));
+
+        let replacement = test_cloud(3).take_rows(&[2, 1, 0]).unwrap();
+        pc.merge_fields(&replacement, true).unwrap();
+        assert_eq!(pc.fields.len(), 3);
+        assert_eq!(pc.metadata.read().unwrap().fields.len(), 3);
+        assert_eq!(pc.fields["x"].get_row::<f32>(0)[0], 1.0);
+    }
+
+    #[test]
+    fn test_read_metadata() {
+        let pc = test_cloud(4);
+        let path = std::env::temp_dir().join("pcdpy_test_metadata.pcd");
+        let path = path.to_str().unwrap();
+        pc.to_pcd_file(path).unwrap();
+        let (md, _) = crate::utils::read_metadata(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(md, *pc.metadata.read().unwrap());
+    }
+
+    #[test]
+    fn test_pcd_bytes_round_trip() {
+        let pc = test_cloud(3);
+        for encoding in [Encoding::Ascii, Encoding::Binary, Encoding::BinaryCompressed] {
+            pc.metadata.write().unwrap().encoding = encoding;
+            let bytes = pc.to_pcd_bytes().unwrap();
+            let read = PointCloud::from_pcd_bytes(&bytes).unwrap();
+            assert_eq!(read.fields, pc.fields);
+            assert_eq!(*read.metadata.read().unwrap(), *pc.metadata.read().unwrap());
+        }
+        assert!(PointCloud::from_pcd_bytes(b"VERSION 0.7\n").is_err());
+    }
+
+    #[test]
+    fn test_legacy_round_trip() {
+        let pc = test_cloud(3);
+        let options = io::WriteOptions { version: Some("0.6".to_string()), ..Default::default() };
+        let bytes = pc.to_pcd_bytes_with(&options).unwrap();
+        let header = String::from_utf8_lossy(&bytes);
+        assert!(header.starts_with("VERSION 0.6\n"));
+        assert!(!header.contains("VIEWPOINT"));
+        let read = PointCloud::from_pcd_bytes(&bytes).unwrap();
+        assert_eq!(read.fields, pc.fields);
+        assert!(read.metadata.read().unwrap().is_legacy());
+        assert!(!pc.metadata.read().unwrap().is_legacy());
+    }
+
+    #[test]
+    fn test_write_options() {
+        let pc = test_cloud(3);
+        pc.metadata.write().unwrap().encoding = Encoding::BinaryCompressed;
+        let options = io::WriteOptions { encoding: Some(Encoding::Ascii), float_format: io::FloatFormat::Fixed(2), ..Default::default() };
+        let bytes = pc.to_pcd_bytes_with(&options).unwrap();
+        let text = String::from_utf8(bytes.clone()).unwrap();
+        assert!(text.contains("DATA ascii\n0.00 0 0\n0.50 1 2\n1.00 2 4\n"));
+        assert_eq!(PointCloud::from_pcd_bytes(&bytes).unwrap().fields, pc.fields);
+        assert_eq!(pc.metadata.read().unwrap().encoding, Encoding::BinaryCompressed);
+    }
+
+    #[test]
+    fn test_extension_encodings() {
+        let pc = test_cloud(50);
+        for (encoding, enabled) in [(Encoding::BinaryZstd, cfg!(feature = "zstd")), (Encoding::BinaryLz4, cfg!(feature = "lz4"))] {
+            let options = io::WriteOptions { encoding: Some(encoding), ..Default::default() };
+            match pc.to_pcd_bytes_with(&options) {
+                Ok(bytes) => {
+                    assert!(enabled);
+                    let read = PointCloud::from_pcd_bytes(&bytes).unwrap();
+                    assert_eq!(read.fields, pc.fields);
+                    assert_eq!(read.metadata.read().unwrap().encoding, encoding);
+                }
+                Err(e) => assert!(!enabled && e.to_string().contains("feature"), "{}", e),
+            }
+            let strict = io::WriteOptions { pcl_compatible: true, ..options };
+            assert!(pc.to_pcd_bytes_with(&strict).is_err());
+        }
+    }
+
+    #[test]
+    fn test_large_compressed() {
+        let pc = test_cloud(50);
+        let options = io::WriteOptions { encoding: Some(Encoding::BinaryCompressedLarge), ..Default::default() };
+        let read = PointCloud::from_pcd_bytes(&pc.to_pcd_bytes_with(&options).unwrap()).unwrap();
+        assert_eq!(read.fields, pc.fields);
+
+        let data: Vec<u8> = (0..1000).map(|i| (i % 7) as u8).collect();
+        let blocks = io::compress_lzf_blocks(&data, 300).unwrap();
+        assert_eq!(io::decompress_lzf_blocks(&blocks, data.len()).unwrap(), data);
+
+        // Only clouds that overflow the u32 sizes switch to the extension
+        let mut md = pc.metadata.read().unwrap().clone();
+        let options = io::WriteOptions { large_compressed: true, ..Default::default() };
+        assert_eq!(options.apply(&md).encoding, Encoding::BinaryCompressed);
+        md.npoints = 1 << 30;
+        assert_eq!(options.apply(&md).encoding, Encoding::BinaryCompressedLarge);
+    }
+
+    #[test]
+    fn test_read_selected_fields() {
+        let pc = test_cloud(4);
+        let options = io::ReadOptions { fields: Some(vec!["label".to_string()]), ..Default::default() };
+        for encoding in [Encoding::Ascii, Encoding::Binary, Encoding::BinaryCompressed] {
+            pc.metadata.write().unwrap().encoding = encoding;
+            let bytes = pc.to_pcd_bytes().unwrap();
+            let (read, _) = PointCloud::from_pcd_reader_with(&mut std::io::Cursor::new(&bytes), &options).unwrap();
+            assert_eq!(read.fields.len(), 1);
+            assert_eq!(read.fields["label"], pc.fields["label"]);
+            let md = read.metadata.read().unwrap();
+            assert_eq!((md.fields.len(), md.npoints), (1, 4));
+        }
+        let missing = io::ReadOptions { fields: Some(vec!["w".to_string()]), ..Default::default() };
+        let bytes = pc.to_pcd_bytes().unwrap();
+        assert!(PointCloud::from_pcd_reader_with(&mut std::io::Cursor::new(&bytes), &missing).is_err());
+    }
+
+    #[test]
+    fn test_read_point_range() {
+        let pc = test_cloud(6);
+        let options = io::ReadOptions { start: 2, count: Some(3), ..Default::default() };
+        let expected = pc.take_rows(&[2, 3, 4]).unwrap();
+        for encoding in [Encoding::Ascii, Encoding::Binary, Encoding::BinaryCompressed] {
+            pc.metadata.write().unwrap().encoding = encoding;
+            let bytes = pc.to_pcd_bytes().unwrap();
+            let (read, _) = PointCloud::from_pcd_reader_with(&mut std::io::Cursor::new(&bytes), &options).unwrap();
+            assert_eq!(read.fields, expected.fields);
+            let md = read.metadata.read().unwrap();
+            assert_eq!((md.width, md.height, md.npoints), (3, 1, 3));
+
+            let tail = io::ReadOptions { start: 6, ..Default::default() };
+            let (read, _) = PointCloud::from_pcd_reader_with(&mut std::io::Cursor::new(&bytes), &tail).unwrap();
+            assert_eq!(read.len(), 0);
+            let past_end = io::ReadOptions { start: 4, count: Some(3), ..Default::default() };
+            assert!(PointCloud::from_pcd_reader_with(&mut std::io::Cursor::new(&bytes), &past_end).is_err());
+        }
+    }
+
+    #[test]
+    fn test_ascii_float_round_trip() {
+        let values = [0.1, -1e-30, 3.4028235e38, 123456.79, f64::NAN, f64::NEG_INFINITY, 0.0];
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("f32", Dtype::F32, 1), ("f64", Dtype::F64, 1)]),
+            width: values.len(),
+            height: 1,
+            npoints: values.len(),
+            encoding: Encoding::Ascii,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        for (i, &v) in values.iter().enumerate() {
+            pc.fields.get_mut("f32").unwrap().assign_row(i, &Array1::from(vec![v as f32]));
+            pc.fields.get_mut("f64").unwrap().assign_row(i, &Array1::from(vec![v / 3.0]));
+        }
+        let read = PointCloud::from_pcd_bytes(&pc.to_pcd_bytes().unwrap()).unwrap();
+        assert!(read.fields["f32"].equal_nan(&pc.fields["f32"]));
+        assert!(read.fields["f64"].equal_nan(&pc.fields["f64"]));
+
+        assert_eq!(io::FloatFormat::Shortest.format(0.1f32), "0.1");
+        assert_eq!(io::FloatFormat::Shortest.format(1e-30f64), "1e-30");
+        assert_eq!(io::FloatFormat::Fixed(2).format(1.005f64), "1.00");
+        assert_eq!(io::FloatFormat::Scientific(3).format(12345.0f32), "1.234e4");
+    }
+
+    #[test]
+    fn test_ascii_malformed_values() {
+        let data = b"VERSION 0.7\nFIELDS x label\nSIZE 4 2\nTYPE F U\nCOUNT 1 1\nWIDTH 2\nHEIGHT 1\nPOINTS 2\nDATA ascii\n1.0 3\n\nabc 4x\n";
+        let err = PointCloud::from_pcd_reader(&mut std::io::Cursor::new(&data[..]), true).unwrap_err().to_string();
+        assert!(err.contains("line 3") && err.contains("'abc'") && err.contains("'x'"), "{}", err);
+
+        let (pc, warnings) = PointCloud::from_pcd_reader(&mut std::io::Cursor::new(&data[..]), false).unwrap();
+        assert_eq!(warnings.len(), 2);
+        assert!(pc.fields["x"].get_row::<f32>(1)[0].is_nan());
+        assert_eq!(pc.fields["label"].get_row::<u16>(1)[0], 0);
+        assert_eq!(pc.fields["label"].get_row::<u16>(0)[0], 3);
+    }
+
+    #[test]
+    fn test_points_mismatch_unorganized() {
+        let data = b"VERSION 0.7\nFIELDS x\nSIZE 4\nTYPE F\nCOUNT 1\nWIDTH 2\nHEIGHT 2\nPOINTS 3\nDATA ascii\n1\n2\n3\n";
+        let (pc, warnings) = PointCloud::from_pcd_reader(&mut std::io::Cursor::new(&data[..]), true).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("does not match POINTS 3"), "{}", warnings[0]);
+        let md = pc.metadata.read().unwrap();
+        assert_eq!((md.width, md.height, md.npoints), (3, 1, 3));
+
+        // WIDTH x HEIGHT overflowing does not match POINTS either
+        let data = b"VERSION 0.7\nFIELDS x\nSIZE 4\nTYPE F\nCOUNT 1\nWIDTH 4294967296\nHEIGHT 4294967296\nPOINTS 1\nDATA ascii\n1\n";
+        let (pc, _) = PointCloud::from_pcd_reader(&mut std::io::Cursor::new(&data[..]), true).unwrap();
+        assert_eq!(pc.len(), 1);
+    }
+
+    #[test]
+    fn test_read_error_kinds() {
+        let kind = |data: &[u8]| ErrorKind::of(&PointCloud::from_pcd_bytes(data).unwrap_err());
+        assert_eq!(kind(b"VERSION 0.7\nFIELDS x\nSIZE 4\nTYPE F\nDATA ascii\n"), Some(ErrorKind::Header));
+        let header = b"VERSION 0.7\nFIELDS x\nSIZE 4\nTYPE F\nCOUNT 1\nWIDTH 2\nHEIGHT 1\nPOINTS 2\n";
+        assert_eq!(kind(&[&header[..], b"DATA ascii\n1\n"].concat()), Some(ErrorKind::DataCorruption));
+        assert_eq!(kind(&[&header[..], b"DATA binary\n\0\0\0\0"].concat()), Some(ErrorKind::DataCorruption));
+        assert_eq!(kind(&[&header[..], b"DATA binary_compressed\n\x04\0\0\0\x08\0\0\0abcd"].concat()), Some(ErrorKind::DataCorruption));
+        // Corrupt block sizes fail without allocating them
+        let sizes = |compressed: u64, uncompressed: u64| [compressed.to_le_bytes(), uncompressed.to_le_bytes()].concat();
+        assert_eq!(kind(&[&header[..], b"DATA binary_compressed_large\n", &sizes(1 << 62, 8)].concat()), Some(ErrorKind::DataCorruption));
+        assert_eq!(kind(&[&header[..], b"DATA binary_compressed_large\n", &sizes(8, 1 << 62)].concat()), Some(ErrorKind::DataCorruption));
+        let block = [&8u32.to_le_bytes()[..], &(1u32 << 31).to_le_bytes(), b"abcdefgh"].concat();
+        assert_eq!(kind(&[&header[..], b"DATA binary_compressed_large\n", &sizes(16, 8), &block].concat()), Some(ErrorKind::DataCorruption));
+    }
+
+    #[test]
+    fn test_checksum() {
+        let pc = test_cloud(20);
+        let verify = io::ReadOptions { verify: true, ..Default::default() };
+        for encoding in [Encoding::Ascii, Encoding::Binary, Encoding::BinaryCompressed] {
+            let options = io::WriteOptions { encoding: Some(encoding), checksum: true, ..Default::default() };
+            let data = pc.to_pcd_bytes_with(&options).unwrap();
+            assert!(data.ends_with(b"\n") && data[data.len() - io::CHECKSUM_FOOTER_LEN..].starts_with(b"# CRC32 "));
+
+            let (read, _) = PointCloud::from_pcd_reader_with(&mut std::io::Cursor::new(&data), &verify).unwrap();
+            assert_eq!(read.fields, pc.fields);
+            // Readers that do not verify ignore the footer
+            assert_eq!(PointCloud::from_pcd_bytes(&data).unwrap().fields, pc.fields);
+            let part = io::ReadOptions { start: 5, count: Some(3), ..verify.clone() };
+            assert_eq!(PointCloud::from_pcd_reader_with(&mut std::io::Cursor::new(&data), &part).unwrap().0.len(), 3);
+
+            let mut corrupted = data.clone();
+            let i = corrupted.len() - io::CHECKSUM_FOOTER_LEN - 3;
+            corrupted[i] ^= 0x10;
+            let err = PointCloud::from_pcd_reader_with(&mut std::io::Cursor::new(&corrupted), &verify).unwrap_err();
+            assert_eq!(ErrorKind::of(&err), Some(ErrorKind::DataCorruption));
+            assert!(err.to_string().contains("Checksum mismatch"), "{}", err);
+
+            let err = PointCloud::from_pcd_reader_with(&mut std::io::Cursor::new(&data[..data.len() - 20]), &verify).unwrap_err();
+            assert_eq!(ErrorKind::of(&err), Some(ErrorKind::DataCorruption));
+
+            #[cfg(feature = "async")]
+            {
+                // The async reader consumes the footer of each cloud on the stream
+                let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+                let stream = [&data[..], &data[..]].concat();
+                let mut reader = &stream[..];
+                for _ in 0..2 {
+                    let (read, _) = runtime.block_on(PointCloud::from_pcd_async(&mut reader, &verify)).unwrap();
+                    assert_eq!(read.fields, pc.fields);
+                }
+                assert!(reader.is_empty());
+                let err = runtime.block_on(PointCloud::from_pcd_async(&mut &corrupted[..], &verify)).unwrap_err();
+                assert!(err.to_string().contains("Checksum mismatch"), "{}", err);
+            }
+        }
+        let unchecked = pc.to_pcd_bytes().unwrap();
+        assert!(PointCloud::from_pcd_reader_with(&mut std::io::Cursor::new(&unchecked), &verify).is_err());
+    }
+}
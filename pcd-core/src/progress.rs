@@ -0,0 +1,250 @@
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use anyhow::Result;
+use crate::io::WriteOptions;
+use crate::metadata::Encoding;
+use crate::pointcloud::PointCloud;
+
+/// Bytes transferred between two progress reports, and the largest single read or write
+/// passed through to the underlying stream.
+pub const REPORT_INTERVAL: u64 = 8 << 20;
+
+/// A flag shared between a long operation and the code that may cancel it, possibly from
+/// another thread.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation. Operations using the token stop at their next progress check.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Error returned by an operation stopped through its `Progress`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Operation cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Callback receiving (done, total) progress updates, with `total` None when unknown.
+/// Returning false cancels the operation.
+pub type ProgressCallback<'a> = Box<dyn FnMut(u64, Option<u64>) -> bool + Send + 'a>;
+
+/// Tracks the progress of a long operation: the callback is called at most once every
+/// `REPORT_INTERVAL` units of work and once at the end, and the cancellation token is
+/// checked on every update.
+pub struct Progress<'a> {
+    callback: Option<ProgressCallback<'a>>,
+    token: Option<CancellationToken>,
+    done: u64,
+    total: Option<u64>,
+    last_report: u64,
+    cancelled: bool,
+}
+
+impl<'a> Progress<'a> {
+    pub fn new(callback: Option<ProgressCallback<'a>>, token: Option<CancellationToken>) -> Self {
+        Self { callback, token, done: 0, total: None, last_report: 0, cancelled: false }
+    }
+
+    /// Set the total amount of work, if known.
+    pub fn set_total(&mut self, total: Option<u64>) {
+        self.total = total;
+    }
+
+    /// Returns true if the operation was cancelled by the token or the callback.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    /// Record that `done` units of work are complete, reporting it if `REPORT_INTERVAL`
+    /// units were done since the last report.
+    pub fn update(&mut self, done: u64) -> Result<(), Cancelled> {
+        self.done = done;
+        if self.token.as_ref().is_some_and(CancellationToken::is_cancelled) {
+            self.cancelled = true;
+        } else if done.abs_diff(self.last_report) >= REPORT_INTERVAL {
+            self.report();
+        }
+        if self.cancelled { Err(Cancelled) } else { Ok(()) }
+    }
+
+    /// Report the final amount of work done.
+    pub fn finish(&mut self) -> Result<(), Cancelled> {
+        if !self.cancelled {
+            self.report();
+        }
+        if self.cancelled { Err(Cancelled) } else { Ok(()) }
+    }
+
+    fn report(&mut self) {
+        self.last_report = self.done;
+        if let Some(callback) = self.callback.as_mut() {
+            self.cancelled |= !callback(self.done, self.total);
+        }
+    }
+}
+
+/// Reader reporting its position in the stream to a `Progress`.
+pub struct ProgressReader<'p, 'a, R> {
+    inner: R,
+    position: u64,
+    progress: &'p mut Progress<'a>,
+}
+
+impl<'p, 'a, R> ProgressReader<'p, 'a, R> {
+    pub fn new(inner: R, progress: &'p mut Progress<'a>) -> Self {
+        Self { inner, position: 0, progress }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<'_, '_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(REPORT_INTERVAL as usize);
+        let n = self.inner.read(&mut buf[..len])?;
+        self.position += n as u64;
+        self.progress.update(self.position).map_err(io::Error::other)?;
+        Ok(n)
+    }
+}
+
+impl<R: Seek> Seek for ProgressReader<'_, '_, R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = self.inner.seek(pos)?;
+        self.progress.update(self.position).map_err(io::Error::other)?;
+        Ok(self.position)
+    }
+}
+
+/// Writer reporting the number of bytes written to a `Progress`.
+pub struct ProgressWriter<'p, 'a, W> {
+    inner: W,
+    written: u64,
+    progress: &'p mut Progress<'a>,
+}
+
+impl<'p, 'a, W> ProgressWriter<'p, 'a, W> {
+    pub fn new(inner: W, progress: &'p mut Progress<'a>) -> Self {
+        Self { inner, written: 0, progress }
+    }
+}
+
+impl<W: Write> Write for ProgressWriter<'_, '_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(REPORT_INTERVAL as usize);
+        let n = self.inner.write(&buf[..len])?;
+        self.written += n as u64;
+        self.progress.update(self.written).map_err(io::Error::other)?;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl PointCloud {
+    /// Read PCD data as `from_pcd_reader_with`, reporting the bytes read out of the length
+    /// of the stream to `progress`. Fails with `Cancelled` if the operation is cancelled.
+    pub fn from_pcd_reader_with_progress<R: Read + Seek>(mut reader: R, options: &crate::io::ReadOptions, progress: &mut Progress) -> Result<(Self, Vec<String>)> {
+        let len = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(0))?;
+        progress.set_total(Some(len));
+        let result = Self::from_pcd_reader_with(&mut BufReader::new(ProgressReader::new(reader, progress)), options)?;
+        progress.finish()?;
+        Ok(result)
+    }
+
+    /// Write PCD data as `to_pcd_writer_with`, reporting the bytes written to `progress`.
+    /// The total is known in advance only for binary encoding; compressed encodings compress
+    /// the data before writing it. Fails with `Cancelled` if the operation is cancelled.
+    pub fn to_pcd_writer_with_progress<W: Write>(&self, writer: W, options: &WriteOptions, progress: &mut Progress) -> Result<()> {
+        let mut md = options.apply(&self.metadata.read().unwrap());
+        if md.encoding == Encoding::Binary {
+            if options.skip_padding {
+                md.fields.0.retain(|f| !f.is_padding());
+            }
+            let mut header = Vec::new();
+            crate::io::write_header(&mut header, &md)?;
+            let footer = if options.checksum { crate::io::CHECKSUM_FOOTER_LEN } else { 0 };
+            progress.set_total(Some((header.len() + md.data_size() + footer) as u64));
+        }
+        let mut writer = BufWriter::new(ProgressWriter::new(writer, progress));
+        self.to_pcd_writer_with(&mut writer, options)?;
+        writer.flush()?;
+        drop(writer);
+        progress.finish()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::sync::Mutex;
+    use ndarray::Array1;
+    use crate::io::ReadOptions;
+    use crate::metadata::{Dtype, FieldSchema, Metadata};
+
+    #[test]
+    fn test_progress_and_cancellation() {
+        let npoints = 1_100_000;
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1), ("_", Dtype::U8, 4), ("i", Dtype::U32, 1)]),
+            width: npoints,
+            height: 1,
+            npoints,
+            encoding: Encoding::Binary,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        pc.fields.get_mut("i").unwrap().assign_row(npoints - 1, &Array1::from(vec![7u32]));
+
+        let reports = Mutex::new(Vec::new());
+        let record = || -> ProgressCallback { Box::new(|done, total| { reports.lock().unwrap().push((done, total)); true }) };
+        let options = WriteOptions { skip_padding: true, ..WriteOptions::default() };
+        let mut buf = Vec::new();
+        pc.to_pcd_writer_with_progress(&mut buf, &options, &mut Progress::new(Some(record()), None)).unwrap();
+        {
+            let reports = reports.lock().unwrap();
+            // Several reports ending with the exact total
+            assert!(reports.len() > 1);
+            assert!(reports.windows(2).all(|w| w[0].0 < w[1].0));
+            assert_eq!(*reports.last().unwrap(), (buf.len() as u64, Some(buf.len() as u64)));
+        }
+
+        reports.lock().unwrap().clear();
+        let (read, _) = PointCloud::from_pcd_reader_with_progress(Cursor::new(&buf), &ReadOptions::default(), &mut Progress::new(Some(record()), None)).unwrap();
+        assert_eq!(read.fields["i"], pc.fields["i"]);
+        assert_eq!(*reports.lock().unwrap().last().unwrap(), (buf.len() as u64, Some(buf.len() as u64)));
+
+        // Cancelling through the callback or the token stops the operation
+        let mut progress = Progress::new(Some(Box::new(|done, _| done < REPORT_INTERVAL)), None);
+        let err = PointCloud::from_pcd_reader_with_progress(Cursor::new(&buf), &ReadOptions::default(), &mut progress).unwrap_err();
+        assert!(progress.is_cancelled());
+        assert!(err.to_string().contains("cancelled"));
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let mut progress = Progress::new(None, Some(token.clone()));
+        assert!(pc.to_pcd_writer_with_progress(Vec::new(), &WriteOptions::default(), &mut progress).is_err());
+        assert!(progress.is_cancelled());
+    }
+}
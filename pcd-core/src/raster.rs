@@ -0,0 +1,139 @@
+use anyhow::Result;
+use ndarray::{Array2, Zip};
+use crate::pointcloud::PointCloud;
+
+/// How the values falling in a raster cell are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reducer {
+    Max,
+    Min,
+    Mean,
+    Count,
+}
+
+impl Reducer {
+    /// Parses a reducer name: "max", "min", "mean" or "count".
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "max" => Some(Reducer::Max),
+            "min" => Some(Reducer::Min),
+            "mean" => Some(Reducer::Mean),
+            "count" => Some(Reducer::Count),
+            _ => None,
+        }
+    }
+}
+
+/// A 2D grid of cell values. Cell `[row, col]` covers x in
+/// `[origin[0] + col * cell_size[0], origin[0] + (col + 1) * cell_size[0])` and likewise y
+/// for the row, so row 0 holds the lowest y values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Raster {
+    pub data: Array2<f64>,
+    pub origin: [f64; 2],
+    pub cell_size: [f64; 2],
+}
+
+impl PointCloud {
+    /// Rasterize the values of `field` (count of 1) onto a grid of `cell_size` cells over the
+    /// x and y bounds of the `names` fields, combining the values in each cell with `reducer`.
+    /// Empty cells are NaN, or 0 when counting. Points with a NaN coordinate or value are
+    /// left out. Cell indices are computed in parallel and values accumulated in one pass.
+    pub fn rasterize(&self, cell_size: [f64; 2], field: &str, reducer: Reducer, names: [&str; 2]) -> Result<Raster> {
+        anyhow::ensure!(cell_size.iter().all(|&s| s > 0.0 && s.is_finite()), "Cell size must be positive and finite");
+        let mut columns = Vec::with_capacity(3);
+        for name in [names[0], names[1], field] {
+            let data = self.fields.get(name)
+                .ok_or_else(|| anyhow::anyhow!("Field '{}' not found", name))?;
+            anyhow::ensure!(data.count() == 1, "Field '{}' must have a count of 1", name);
+            columns.push(data.get_data::<f64>());
+        }
+        let (xs, ys, values) = (columns[0].column(0), columns[1].column(0), columns[2].column(0));
+
+        let mut lo = [f64::INFINITY; 2];
+        let mut hi = [f64::NEG_INFINITY; 2];
+        for (&x, &y) in xs.iter().zip(ys.iter()).filter(|(x, y)| !x.is_nan() && !y.is_nan()) {
+            lo = [lo[0].min(x), lo[1].min(y)];
+            hi = [hi[0].max(x), hi[1].max(y)];
+        }
+        if lo[0] > hi[0] {
+            return Ok(Raster { data: Array2::zeros((0, 0)), origin: [0.0; 2], cell_size });
+        }
+        let shape = [0, 1].map(|i| ((hi[i] - lo[i]) / cell_size[i]) as usize + 1);
+
+        // Flat cell index of each point, or usize::MAX to skip it
+        let cells = Zip::from(xs).and(ys).and(values).par_map_collect(|&x, &y, &v| {
+            if x.is_nan() || y.is_nan() || v.is_nan() {
+                return usize::MAX;
+            }
+            let col = (((x - lo[0]) / cell_size[0]) as usize).min(shape[0] - 1);
+            let row = (((y - lo[1]) / cell_size[1]) as usize).min(shape[1] - 1);
+            row * shape[0] + col
+        });
+
+        let empty = if reducer == Reducer::Count { 0.0 } else { f64::NAN };
+        let mut data = Array2::from_elem((shape[1], shape[0]), empty);
+        let grid = data.as_slice_mut().unwrap();
+        let mut counts = vec![0u64; if reducer == Reducer::Mean { grid.len() } else { 0 }];
+        for (&cell, &v) in cells.iter().zip(values.iter()) {
+            if cell == usize::MAX {
+                continue;
+            }
+            let acc = &mut grid[cell];
+            match reducer {
+                Reducer::Max => *acc = if acc.is_nan() { v } else { acc.max(v) },
+                Reducer::Min => *acc = if acc.is_nan() { v } else { acc.min(v) },
+                Reducer::Count => *acc += 1.0,
+                Reducer::Mean => {
+                    *acc = if acc.is_nan() { v } else { *acc + v };
+                    counts[cell] += 1;
+                }
+            }
+        }
+        if reducer == Reducer::Mean {
+            grid.iter_mut().zip(counts).filter(|(_, n)| *n > 0).for_each(|(acc, n)| *acc /= n as f64);
+        }
+        Ok(Raster { data, origin: lo, cell_size })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::{array, Array1};
+    use crate::metadata::{Dtype, FieldSchema, Metadata};
+
+    #[test]
+    fn test_rasterize() {
+        let points: [(f32, f32, f32); 6] = [(0.0, 0.0, 1.0), (0.4, 0.2, 3.0), (1.0, 0.0, 5.0), (2.5, 1.5, -1.0), (f32::NAN, 0.0, 9.0), (2.0, 0.0, f32::NAN)];
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1), ("y", Dtype::F32, 1), ("z", Dtype::F32, 1)]),
+            width: points.len(),
+            height: 1,
+            npoints: points.len(),
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        for (i, (x, y, z)) in points.into_iter().enumerate() {
+            pc.fields.get_mut("x").unwrap().assign_row(i, &Array1::from(vec![x]));
+            pc.fields.get_mut("y").unwrap().assign_row(i, &Array1::from(vec![y]));
+            pc.fields.get_mut("z").unwrap().assign_row(i, &Array1::from(vec![z]));
+        }
+
+        let max = pc.rasterize([1.0, 1.0], "z", Reducer::Max, ["x", "y"]).unwrap();
+        assert_eq!(max.origin, [0.0, 0.0]);
+        assert_eq!(max.data.dim(), (2, 3));
+        let nan_eq = |a: &Array2<f64>, b: &Array2<f64>| a.iter().zip(b.iter()).all(|(a, b)| a == b || a.is_nan() && b.is_nan());
+        assert!(nan_eq(&max.data, &array![[3.0, 5.0, f64::NAN], [f64::NAN, f64::NAN, -1.0]]));
+        let min = pc.rasterize([1.0, 1.0], "z", Reducer::Min, ["x", "y"]).unwrap();
+        assert_eq!(min.data[[0, 0]], 1.0);
+        let mean = pc.rasterize([1.0, 1.0], "z", Reducer::Mean, ["x", "y"]).unwrap();
+        assert_eq!(mean.data[[0, 0]], 2.0);
+        let count = pc.rasterize([1.0, 1.0], "z", Reducer::Count, ["x", "y"]).unwrap();
+        assert_eq!(count.data, array![[2.0, 1.0, 0.0], [0.0, 0.0, 1.0]]);
+
+        assert!(pc.rasterize([0.0, 1.0], "z", Reducer::Max, ["x", "y"]).is_err());
+        assert!(pc.rasterize([1.0, 1.0], "w", Reducer::Max, ["x", "y"]).is_err());
+    }
+}
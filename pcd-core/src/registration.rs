@@ -0,0 +1,186 @@
+use anyhow::Result;
+use nalgebra::{Matrix3, Vector3};
+use crate::kdtree::KdTree;
+use crate::pointcloud::PointCloud;
+use crate::transform::{identity, Matrix4};
+
+/// Outcome of an ICP registration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IcpResult {
+    /// Transform mapping source coordinates onto the target.
+    pub transformation: Matrix4,
+    /// Fraction of source points with a correspondence within the maximum distance.
+    pub fitness: f64,
+    /// Root mean square distance over the inlier correspondences.
+    pub inlier_rmse: f64,
+    /// Number of iterations run.
+    pub iterations: usize,
+    /// True if the RMSE change fell below the tolerance before `max_iterations`.
+    pub converged: bool,
+}
+
+/// Options controlling an ICP registration.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IcpOptions {
+    pub max_iterations: usize,
+    /// Stop once the inlier RMSE changes by less than this between iterations.
+    pub tolerance: f64,
+    /// Ignore correspondences further apart than this.
+    pub max_correspondence_distance: f64,
+}
+
+impl Default for IcpOptions {
+    fn default() -> Self {
+        Self {
+            max_iterations: 30,
+            tolerance: 1e-6,
+            max_correspondence_distance: f64::INFINITY,
+        }
+    }
+}
+
+fn apply(m: &Matrix4, p: &[f64; 3]) -> [f64; 3] {
+    let r = |i: usize| m[i][0] * p[0] + m[i][1] * p[1] + m[i][2] * p[2] + m[i][3];
+    [r(0), r(1), r(2)]
+}
+
+fn compose(a: &Matrix4, b: &Matrix4) -> Matrix4 {
+    let mut m = [[0.0; 4]; 4];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, v) in row.iter_mut().enumerate() {
+            *v = (0..4).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    m
+}
+
+/// Returns the (source index, target index, distance) correspondences within `max_distance`.
+fn correspondences(source: &[[f64; 3]], tree: &KdTree, m: &Matrix4, max_distance: f64) -> Vec<(usize, usize, f64)> {
+    source.iter().enumerate()
+        .filter_map(|(i, p)| {
+            let (j, dist) = tree.query(&apply(m, p), 1)[0];
+            (dist <= max_distance).then_some((i, j, dist))
+        })
+        .collect()
+}
+
+fn rmse(pairs: &[(usize, usize, f64)]) -> f64 {
+    (pairs.iter().map(|p| p.2 * p.2).sum::<f64>() / pairs.len() as f64).sqrt()
+}
+
+/// Least-squares rigid transform mapping `src` onto `dst` (Kabsch / Umeyama without scale).
+fn best_fit_transform(src: &[Vector3<f64>], dst: &[Vector3<f64>]) -> Result<Matrix4> {
+    let n = src.len() as f64;
+    let src_mean = src.iter().sum::<Vector3<f64>>() / n;
+    let dst_mean = dst.iter().sum::<Vector3<f64>>() / n;
+    let h: Matrix3<f64> = src.iter().zip(dst)
+        .map(|(s, d)| (s - src_mean) * (d - dst_mean).transpose())
+        .sum();
+
+    let svd = h.svd(true, true);
+    let (u, v_t) = (svd.u.unwrap(), svd.v_t.unwrap());
+    let mut r = v_t.transpose() * u.transpose();
+    if r.determinant() < 0.0 {
+        // Reflection: flip the axis with the smallest singular value.
+        let mut v = v_t.transpose();
+        v.column_mut(2).neg_mut();
+        r = v * u.transpose();
+    }
+    anyhow::ensure!(r.iter().all(|v| v.is_finite()), "Failed to estimate a rigid transform");
+    let t = dst_mean - r * src_mean;
+
+    let mut m = identity();
+    for i in 0..3 {
+        for j in 0..3 {
+            m[i][j] = r[(i, j)];
+        }
+        m[i][3] = t[i];
+    }
+    Ok(m)
+}
+
+/// Point-to-point ICP aligning the `names` coordinates of `source` to those of `target`.
+pub fn register_icp(source: &PointCloud, target: &PointCloud, options: &IcpOptions, names: [&str; 3]) -> Result<IcpResult> {
+    let src = source.coordinates(names)?;
+    anyhow::ensure!(!src.is_empty(), "Source PointCloud has no points");
+    let tree = target.build_kdtree(names)?;
+    anyhow::ensure!(!tree.is_empty(), "Target PointCloud has no points");
+
+    let mut transformation = identity();
+    let mut pairs = correspondences(&src, &tree, &transformation, options.max_correspondence_distance);
+    let mut prev_rmse = f64::INFINITY;
+    let mut iterations = 0;
+    let mut converged = false;
+
+    while iterations < options.max_iterations {
+        anyhow::ensure!(pairs.len() >= 3, "Too few correspondences ({}) within the maximum distance", pairs.len());
+        let current_rmse = rmse(&pairs);
+        if (prev_rmse - current_rmse).abs() < options.tolerance {
+            converged = true;
+            break;
+        }
+        prev_rmse = current_rmse;
+
+        let (moved, matched): (Vec<_>, Vec<_>) = pairs.iter()
+            .map(|&(i, j, _)| (Vector3::from(apply(&transformation, &src[i])), Vector3::from(tree.point(j))))
+            .unzip();
+        transformation = compose(&best_fit_transform(&moved, &matched)?, &transformation);
+        pairs = correspondences(&src, &tree, &transformation, options.max_correspondence_distance);
+        iterations += 1;
+    }
+
+    Ok(IcpResult {
+        transformation,
+        fitness: pairs.len() as f64 / src.len() as f64,
+        inlier_rmse: if pairs.is_empty() { 0.0 } else { rmse(&pairs) },
+        iterations,
+        converged,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{Dtype, FieldSchema, Metadata};
+    use crate::transform::{quaternion_to_rotation, rotation_matrix};
+    use ndarray::Array1;
+
+    fn cloud(points: &[[f64; 3]]) -> PointCloud {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F64, 1), ("y", Dtype::F64, 1), ("z", Dtype::F64, 1)]),
+            width: points.len(),
+            height: 1,
+            npoints: points.len(),
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        for (i, p) in points.iter().enumerate() {
+            for (j, name) in ["x", "y", "z"].iter().enumerate() {
+                pc.fields.get_mut(*name).unwrap().assign_row(i, &Array1::from(vec![p[j]]));
+            }
+        }
+        pc
+    }
+
+    #[test]
+    fn test_register_icp() {
+        let points: Vec<[f64; 3]> = (0..5).flat_map(|i| (0..5).flat_map(move |j| (0..3).map(move |k| {
+            [i as f64, j as f64 * 1.5, k as f64 * 2.0 + (i * j) as f64 * 0.1]
+        }))).collect();
+        let target = cloud(&points);
+
+        // Small rotation about z plus a translation.
+        let angle = 0.05f64;
+        let r = quaternion_to_rotation([(angle / 2.0).cos(), 0.0, 0.0, (angle / 2.0).sin()]).unwrap();
+        let mut m = rotation_matrix(r);
+        m[0][3] = 0.1;
+        m[2][3] = -0.05;
+        let mut source = cloud(&points);
+        source.transform(&m, ["x", "y", "z"], None).unwrap();
+
+        let result = register_icp(&source, &target, &IcpOptions::default(), ["x", "y", "z"]).unwrap();
+        assert!(result.fitness > 0.99);
+        assert!(result.inlier_rmse < 1e-6, "rmse {}", result.inlier_rmse);
+        assert!(result.converged);
+    }
+}
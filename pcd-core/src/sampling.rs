@@ -0,0 +1,61 @@
+use anyhow::Result;
+use rand::{rngs::StdRng, SeedableRng};
+use crate::pointcloud::PointCloud;
+
+impl PointCloud {
+    /// Return a new PointCloud with `n` points drawn at random without replacement.
+    /// Points keep their original order. Pass a `seed` for reproducible results.
+    pub fn random_sample(&self, n: usize, seed: Option<u64>) -> Result<Self> {
+        let npoints = self.len();
+        anyhow::ensure!(n <= npoints, "Cannot sample {} points from a PointCloud with {} points", n, npoints);
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let mut indices = rand::seq::index::sample(&mut rng, npoints, n).into_vec();
+        indices.sort_unstable();
+        self.take_rows(&indices)
+    }
+
+    /// Return a new PointCloud containing every `every_k`-th point, starting with the first.
+    pub fn uniform_sample(&self, every_k: usize) -> Result<Self> {
+        anyhow::ensure!(every_k > 0, "Sampling step must be greater than 0");
+        let indices: Vec<usize> = (0..self.len()).step_by(every_k).collect();
+        self.take_rows(&indices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{Dtype, FieldSchema, Metadata};
+    use ndarray::Array1;
+
+    #[test]
+    fn test_sampling() {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("i", Dtype::U32, 1)]),
+            width: 10,
+            height: 1,
+            npoints: 10,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        for i in 0..10 {
+            pc.fields.get_mut("i").unwrap().assign_row(i, &Array1::from(vec![i as u32]));
+        }
+
+        let sampled = pc.random_sample(4, Some(7)).unwrap();
+        assert_eq!(sampled.len(), 4);
+        let values: Vec<u32> = (0..4).map(|i| sampled.fields["i"].get_row::<u32>(i)[0]).collect();
+        assert!(values.windows(2).all(|w| w[0] < w[1]));
+        let again = pc.random_sample(4, Some(7)).unwrap();
+        assert_eq!(again.fields["i"], sampled.fields["i"]);
+        assert!(pc.random_sample(11, None).is_err());
+
+        let uniform = pc.uniform_sample(3).unwrap();
+        assert_eq!(uniform.len(), 4);
+        assert_eq!(uniform.fields["i"].get_row::<u32>(3)[0], 9);
+        assert!(pc.uniform_sample(0).is_err());
+    }
+}
@@ -0,0 +1,146 @@
+use std::cmp::Ordering;
+use anyhow::Result;
+use ndarray::ArcArray2;
+use rayon::prelude::*;
+use crate::fielddata::FieldData;
+use crate::octree::{morton_encode, MAX_DEPTH};
+use crate::pointcloud::PointCloud;
+
+/// Compare two values, ordering NaN after every other value in either direction.
+fn compare<T: PartialOrd>(a: &T, b: &T, descending: bool) -> Ordering {
+    let is_nan = |v: &T| v.partial_cmp(v).is_none();
+    is_nan(a).cmp(&is_nan(b)).then_with(|| {
+        let order = a.partial_cmp(b).unwrap_or(Ordering::Equal);
+        if descending { order.reverse() } else { order }
+    })
+}
+
+/// Indices of the rows of a single-column array in sorted order. The sort is stable.
+fn argsort<T: PartialOrd + Sync>(arr: &ArcArray2<T>, descending: bool) -> Vec<usize> {
+    let column = arr.column(0);
+    let mut indices: Vec<usize> = (0..column.len()).collect();
+    indices.par_sort_by(|&a, &b| compare(&column[a], &column[b], descending));
+    indices
+}
+
+impl PointCloud {
+    /// Return a new unorganized PointCloud with the points sorted by the values of the field
+    /// `name` (which must have a count of 1), in ascending order or descending if
+    /// `descending` is set. NaN values come last, and points with equal values keep their
+    /// relative order. Integer fields are compared exactly, without conversion.
+    pub fn sort_by(&self, name: &str, descending: bool) -> Result<Self> {
+        let field = self.fields.get(name)
+            .ok_or_else(|| anyhow::anyhow!("Field '{}' not found", name))?;
+        anyhow::ensure!(field.count() == 1, "Field '{}' must have a count of 1", name);
+        let indices = match field {
+            FieldData::Bool(arr) | FieldData::U8(arr) => argsort(arr, descending),
+            FieldData::U16(arr) => argsort(arr, descending),
+            FieldData::U32(arr) => argsort(arr, descending),
+            FieldData::U64(arr) => argsort(arr, descending),
+            FieldData::I8(arr)  => argsort(arr, descending),
+            FieldData::I16(arr) => argsort(arr, descending),
+            FieldData::I32(arr) => argsort(arr, descending),
+            FieldData::I64(arr) => argsort(arr, descending),
+            FieldData::F16(arr) => argsort(arr, descending),
+            FieldData::F32(arr) => argsort(arr, descending),
+            FieldData::F64(arr) => argsort(arr, descending),
+        };
+        self.take_unorganized(&indices)
+    }
+
+    /// Return a new unorganized PointCloud with the points in Morton (Z-order) order of the
+    /// `names` coordinates, quantized to a grid of 2^21 cells per axis over their bounds, so
+    /// that points close in space are close in the data. Points with a NaN coordinate come
+    /// last, in their original order.
+    pub fn sort_morton(&self, names: [&str; 3]) -> Result<Self> {
+        let points = self.coordinates(names)?;
+        let mut lo = [f64::INFINITY; 3];
+        let mut hi = [f64::NEG_INFINITY; 3];
+        for p in points.iter().filter(|p| p.iter().all(|v| v.is_finite())) {
+            for i in 0..3 {
+                lo[i] = lo[i].min(p[i]);
+                hi[i] = hi[i].max(p[i]);
+            }
+        }
+        let ncells = 1u64 << MAX_DEPTH;
+        let codes: Vec<Option<u64>> = points.par_iter()
+            .map(|p| p.iter().all(|v| v.is_finite()).then(|| {
+                morton_encode([0, 1, 2].map(|i| {
+                    let extent = hi[i] - lo[i];
+                    if extent > 0.0 { (((p[i] - lo[i]) / extent * ncells as f64) as u64).min(ncells - 1) } else { 0 }
+                }))
+            }))
+            .collect();
+        let mut indices: Vec<usize> = (0..points.len()).collect();
+        // Points without a code (NaN coordinates) sort after all others
+        indices.par_sort_by_key(|&i| (codes[i].is_none(), codes[i]));
+        self.take_unorganized(&indices)
+    }
+
+    /// Take the points at `indices` into a new unorganized PointCloud.
+    fn take_unorganized(&self, indices: &[usize]) -> Result<Self> {
+        let pc = self.take_rows(indices)?;
+        {
+            let mut md = pc.metadata.write().unwrap();
+            md.width = indices.len();
+            md.height = 1;
+        }
+        Ok(pc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array1;
+    use crate::metadata::{Dtype, FieldSchema, Metadata};
+
+    fn cloud(points: &[([f32; 3], u64)]) -> PointCloud {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1), ("y", Dtype::F32, 1), ("z", Dtype::F32, 1), ("t", Dtype::U64, 1)]),
+            width: points.len(),
+            height: 1,
+            npoints: points.len(),
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        for (i, (p, t)) in points.iter().enumerate() {
+            for (name, v) in ["x", "y", "z"].into_iter().zip(p) {
+                pc.fields.get_mut(name).unwrap().assign_row(i, &Array1::from(vec![*v]));
+            }
+            pc.fields.get_mut("t").unwrap().assign_row(i, &Array1::from(vec![*t]));
+        }
+        pc
+    }
+
+    fn column<T: crate::metadata::Data + num_traits::NumCast>(pc: &PointCloud, name: &str) -> Vec<T> {
+        (0..pc.len()).map(|i| pc.fields[name].get_row::<T>(i)[0]).collect()
+    }
+
+    #[test]
+    fn test_sort_by() {
+        // Timestamps that differ by less than f64 precision still sort correctly
+        let base = 1_700_000_000_000_000_000u64;
+        let pc = cloud(&[([2.0, 0.0, 0.0], base + 1), ([f32::NAN, 0.0, 0.0], base), ([-1.0, 0.0, 0.0], base + 2), ([2.0, 1.0, 0.0], base)]);
+
+        let by_t = pc.sort_by("t", false).unwrap();
+        assert_eq!(column::<u64>(&by_t, "t"), vec![base, base, base + 1, base + 2]);
+        // Stable: equal timestamps keep their order
+        assert!(column::<f32>(&by_t, "x")[0].is_nan());
+
+        let by_x = pc.sort_by("x", true).unwrap();
+        assert_eq!(column::<f32>(&by_x, "y")[..3], [0.0, 1.0, 0.0]);
+        assert!(column::<f32>(&by_x, "x")[3].is_nan());
+        assert_eq!(by_x.metadata.read().unwrap().width, 4);
+
+        assert!(pc.sort_by("w", false).is_err());
+    }
+
+    #[test]
+    fn test_sort_morton() {
+        let pc = cloud(&[([1.0, 1.0, 1.0], 0), ([f32::NAN, 0.0, 0.0], 1), ([0.0, 0.0, 0.0], 2), ([1.0, 0.0, 0.0], 3), ([0.0, 1.0, 0.0], 4)]);
+        let sorted = pc.sort_morton(["x", "y", "z"]).unwrap();
+        assert_eq!(column::<u64>(&sorted, "t"), vec![2, 3, 4, 0, 1]);
+        assert!(pc.sort_morton(["x", "y", "w"]).is_err());
+    }
+}
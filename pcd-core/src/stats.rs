@@ -0,0 +1,376 @@
+use anyhow::Result;
+use ndarray::{ArcArray2, ArrayView2, Axis};
+use ndarray::parallel::prelude::*;
+use num_traits::ToPrimitive;
+use crate::fielddata::FieldData;
+use crate::pointcloud::PointCloud;
+
+/// Number of points per chunk when statistics are accumulated in parallel.
+const CHUNK_SIZE: usize = 1 << 16;
+
+/// Summary statistics of one component of a field. NaN values are ignored, and
+/// `min`, `max`, `mean` and `std` are NaN if the component has no other values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FieldStats {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    /// Population standard deviation.
+    pub std: f64,
+}
+
+/// Running statistics using Welford's algorithm, mergeable across chunks.
+#[derive(Debug, Clone, Copy)]
+struct Accumulator {
+    count: usize,
+    min: f64,
+    max: f64,
+    mean: f64,
+    m2: f64,
+}
+
+impl Accumulator {
+    fn new() -> Self {
+        Self { count: 0, min: f64::INFINITY, max: f64::NEG_INFINITY, mean: 0.0, m2: 0.0 }
+    }
+
+    fn push(&mut self, v: f64) {
+        if v.is_nan() {
+            return;
+        }
+        self.count += 1;
+        self.min = self.min.min(v);
+        self.max = self.max.max(v);
+        let delta = v - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (v - self.mean);
+    }
+
+    fn merge(self, other: Self) -> Self {
+        if self.count == 0 {
+            return other;
+        }
+        if other.count == 0 {
+            return self;
+        }
+        let count = self.count + other.count;
+        let delta = other.mean - self.mean;
+        Self {
+            count,
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+            mean: self.mean + delta * other.count as f64 / count as f64,
+            m2: self.m2 + other.m2 + delta * delta * (self.count * other.count) as f64 / count as f64,
+        }
+    }
+
+    fn finish(self) -> FieldStats {
+        if self.count == 0 {
+            return FieldStats { count: 0, min: f64::NAN, max: f64::NAN, mean: f64::NAN, std: f64::NAN };
+        }
+        FieldStats {
+            count: self.count,
+            min: self.min,
+            max: self.max,
+            mean: self.mean,
+            std: (self.m2 / self.count as f64).sqrt(),
+        }
+    }
+}
+
+/// Accumulate the statistics of every column of `chunk`.
+fn accumulate<T: ToPrimitive + Copy>(chunk: ArrayView2<T>) -> Vec<Accumulator> {
+    let mut acc = vec![Accumulator::new(); chunk.ncols()];
+    for row in chunk.rows() {
+        for (a, v) in acc.iter_mut().zip(row) {
+            a.push(v.to_f64().unwrap_or(f64::NAN));
+        }
+    }
+    acc
+}
+
+/// Compute the statistics of every column of `arr`, in parallel over chunks of rows.
+fn column_stats<T: ToPrimitive + Copy + Send + Sync>(arr: &ArcArray2<T>) -> Vec<FieldStats> {
+    let ncols = arr.ncols();
+    arr.axis_chunks_iter(Axis(0), CHUNK_SIZE)
+        .into_par_iter()
+        .map(accumulate)
+        .reduce(
+            || vec![Accumulator::new(); ncols],
+            |a, b| a.into_iter().zip(b).map(|(a, b)| a.merge(b)).collect(),
+        )
+        .into_iter()
+        .map(Accumulator::finish)
+        .collect()
+}
+
+/// Count the values of `arr` falling in each of `bins` equal bins over `[lo, hi]`, in
+/// parallel over chunks of rows.
+fn column_histogram<T: ToPrimitive + Copy + Send + Sync>(arr: &ArcArray2<T>, bins: usize, lo: f64, hi: f64) -> Vec<u64> {
+    let scale = bins as f64 / (hi - lo);
+    arr.axis_chunks_iter(Axis(0), CHUNK_SIZE)
+        .into_par_iter()
+        .map(|chunk| {
+            let mut counts = vec![0u64; bins];
+            for v in chunk.iter().filter_map(|v| v.to_f64()) {
+                // NaN fails both comparisons
+                if v >= lo && v <= hi {
+                    counts[(((v - lo) * scale) as usize).min(bins - 1)] += 1;
+                }
+            }
+            counts
+        })
+        .reduce(|| vec![0u64; bins], |a, b| a.into_iter().zip(b).map(|(a, b)| a + b).collect())
+}
+
+impl FieldData {
+    /// Return the statistics of each component of this field, ignoring NaN values. Booleans
+    /// count as 0 and 1, so the mean of a boolean field is the fraction of true values.
+    pub fn stats(&self) -> Vec<FieldStats> {
+        match self {
+            FieldData::Bool(arr) | FieldData::U8(arr) => column_stats(arr),
+            FieldData::U16(arr) => column_stats(arr),
+            FieldData::U32(arr) => column_stats(arr),
+            FieldData::U64(arr) => column_stats(arr),
+            FieldData::I8(arr)  => column_stats(arr),
+            FieldData::I16(arr) => column_stats(arr),
+            FieldData::I32(arr) => column_stats(arr),
+            FieldData::I64(arr) => column_stats(arr),
+            FieldData::F16(arr) => column_stats(arr),
+            FieldData::F32(arr) => column_stats(arr),
+            FieldData::F64(arr) => column_stats(arr),
+        }
+    }
+
+    /// Return the histogram of the values of this field (all components together), with
+    /// `bins` equal bins over `range`, or over the minimum and maximum values if None: the
+    /// count of values in each bin and the `bins + 1` bin edges. Like NumPy, every bin but
+    /// the last is half-open, values outside the range and NaN values are ignored, and an
+    /// empty or single-valued range is widened.
+    pub fn histogram(&self, bins: usize, range: Option<(f64, f64)>) -> Result<(Vec<u64>, Vec<f64>)> {
+        anyhow::ensure!(bins > 0, "Number of bins must be positive");
+        let (lo, hi) = match range {
+            Some((lo, hi)) => {
+                anyhow::ensure!(lo <= hi, "Histogram range must satisfy min <= max, got {} and {}", lo, hi);
+                (lo, hi)
+            }
+            None => {
+                let stats: Vec<FieldStats> = self.stats().into_iter().filter(|s| s.count > 0).collect();
+                if stats.is_empty() {
+                    (0.0, 1.0)
+                } else {
+                    stats.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), s| (lo.min(s.min), hi.max(s.max)))
+                }
+            }
+        };
+        anyhow::ensure!(lo.is_finite() && hi.is_finite(), "Histogram range [{}, {}] is not finite", lo, hi);
+        let (lo, hi) = if lo == hi { (lo - 0.5, hi + 0.5) } else { (lo, hi) };
+        let counts = match self {
+            FieldData::Bool(arr) | FieldData::U8(arr) => column_histogram(arr, bins, lo, hi),
+            FieldData::U16(arr) => column_histogram(arr, bins, lo, hi),
+            FieldData::U32(arr) => column_histogram(arr, bins, lo, hi),
+            FieldData::U64(arr) => column_histogram(arr, bins, lo, hi),
+            FieldData::I8(arr)  => column_histogram(arr, bins, lo, hi),
+            FieldData::I16(arr) => column_histogram(arr, bins, lo, hi),
+            FieldData::I32(arr) => column_histogram(arr, bins, lo, hi),
+            FieldData::I64(arr) => column_histogram(arr, bins, lo, hi),
+            FieldData::F16(arr) => column_histogram(arr, bins, lo, hi),
+            FieldData::F32(arr) => column_histogram(arr, bins, lo, hi),
+            FieldData::F64(arr) => column_histogram(arr, bins, lo, hi),
+        };
+        let edges = (0..=bins).map(|i| lo + (hi - lo) * i as f64 / bins as f64).collect();
+        Ok((counts, edges))
+    }
+}
+
+impl PointCloud {
+    /// Return the statistics of each component of the field `name`, ignoring NaN values.
+    pub fn field_stats(&self, name: &str) -> Result<Vec<FieldStats>> {
+        let field = self.fields.get(name)
+            .ok_or_else(|| anyhow::anyhow!("Field '{}' not found", name))?;
+        Ok(field.stats())
+    }
+
+    /// Return the histogram of the field `name`. See `FieldData::histogram`.
+    pub fn histogram(&self, name: &str, bins: usize, range: Option<(f64, f64)>) -> Result<(Vec<u64>, Vec<f64>)> {
+        let field = self.fields.get(name)
+            .ok_or_else(|| anyhow::anyhow!("Field '{}' not found", name))?;
+        field.histogram(bins, range)
+    }
+
+    /// Return the statistics of every field, in schema order.
+    pub fn describe(&self) -> Result<Vec<(String, Vec<FieldStats>)>> {
+        let md = self.metadata.read().unwrap();
+        md.fields.iter()
+            .map(|f| Ok((f.name.clone(), self.field_stats(&f.name)?)))
+            .collect()
+    }
+
+    /// Return the size in bytes of the values of every field. Fields shared with other clouds
+    /// (see `copy`) are counted in full.
+    pub fn nbytes(&self) -> usize {
+        self.fields.values().map(FieldData::nbytes).sum()
+    }
+
+    /// Return a human-readable summary of the cloud: its shape and memory footprint, then for
+    /// each field its dtype, count, range of values over all components, number of NaN
+    /// values and size in bytes.
+    pub fn info(&self) -> Result<String> {
+        let md = self.metadata.read().unwrap();
+        let mut rows = vec![["#", "Field", "Dtype", "Count", "Min", "Max", "NaN", "Memory"].map(String::from)];
+        for (i, f) in md.fields.iter().enumerate() {
+            let field = self.fields.get(&f.name)
+                .ok_or_else(|| anyhow::anyhow!("Field '{}' missing from PointCloud data", f.name))?;
+            let stats = field.stats();
+            let min = stats.iter().map(|s| s.min).fold(f64::NAN, f64::min);
+            let max = stats.iter().map(|s| s.max).fold(f64::NAN, f64::max);
+            let nan = field.len() - stats.iter().map(|s| s.count).sum::<usize>();
+            rows.push([
+                i.to_string(), f.name.clone(), field.dtype().as_numpy_dtype().to_string(), f.count.to_string(),
+                format_value(min), format_value(max), nan.to_string(), format_bytes(field.nbytes()),
+            ]);
+        }
+        let widths: Vec<usize> = (0..8).map(|j| rows.iter().map(|row| row[j].len()).max().unwrap_or(0)).collect();
+
+        let mut info = format!("PointCloud: {} points (width {}, height {}), {} fields, {}\n",
+            md.npoints, md.width, md.height, md.fields.len(), format_bytes(self.nbytes()));
+        for row in &rows {
+            let line = row.iter().zip(&widths)
+                .map(|(cell, &width)| format!("{:<width$}", cell))
+                .collect::<Vec<_>>()
+                .join("  ");
+            info.push_str(&format!(" {}\n", line.trim_end()));
+        }
+        Ok(info)
+    }
+}
+
+/// Format a value of a field for `PointCloud::info`: integers exactly, other values with 4
+/// decimals.
+fn format_value(v: f64) -> String {
+    if v.fract() == 0.0 && v.abs() < 1e15 {
+        format!("{}", v)
+    } else if v.is_finite() {
+        format!("{:.4}", v)
+    } else {
+        format!("{}", v).to_lowercase()
+    }
+}
+
+/// Format a number of bytes with a binary unit, e.g. "1.5 KiB".
+fn format_bytes(n: usize) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = n as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 { format!("{} B", n) } else { format!("{:.1} {}", size, UNITS[unit]) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{Dtype, FieldSchema, Metadata};
+    use ndarray::Array1;
+
+    #[test]
+    fn test_field_stats() {
+        let n = 3 * CHUNK_SIZE + 7;
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1), ("rgb", Dtype::U8, 2)]),
+            width: n,
+            height: 1,
+            npoints: n,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        for i in 0..n {
+            let x = if i % 2 == 0 { f32::NAN } else { (i % 5) as f32 };
+            pc.fields.get_mut("x").unwrap().assign_row(i, &Array1::from(vec![x]));
+            pc.fields.get_mut("rgb").unwrap().assign_row(i, &Array1::from(vec![(i % 3) as u8, 7]));
+        }
+
+        let x = pc.field_stats("x").unwrap();
+        assert_eq!(x.len(), 1);
+        assert_eq!(x[0].count, n / 2);
+        assert_eq!((x[0].min, x[0].max), (0.0, 4.0));
+
+        let values: Vec<f64> = (0..n).filter(|i| i % 2 == 1).map(|i| (i % 5) as f64).collect();
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        let std = (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64).sqrt();
+        assert!((x[0].mean - mean).abs() < 1e-9);
+        assert!((x[0].std - std).abs() < 1e-9);
+
+        let rgb = pc.field_stats("rgb").unwrap();
+        assert_eq!(rgb.len(), 2);
+        assert_eq!((rgb[0].min, rgb[0].max), (0.0, 2.0));
+        assert_eq!((rgb[1].mean, rgb[1].std), (7.0, 0.0));
+
+        let described = pc.describe().unwrap();
+        assert_eq!(described[0].0, "x");
+        assert!(pc.field_stats("w").is_err());
+
+        let empty = PointCloud::new(&Metadata { npoints: 0, width: 0, ..md });
+        assert!(empty.field_stats("x").unwrap()[0].mean.is_nan());
+    }
+
+    #[test]
+    fn test_histogram() {
+        let n = 2 * CHUNK_SIZE + 3;
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("z", Dtype::F32, 1), ("label", Dtype::U8, 1)]),
+            width: n,
+            height: 1,
+            npoints: n,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        for i in 0..n {
+            let z = if i % 4 == 3 { f32::NAN } else { (i % 4) as f32 };
+            pc.fields.get_mut("z").unwrap().assign_row(i, &Array1::from(vec![z]));
+            pc.fields.get_mut("label").unwrap().assign_row(i, &Array1::from(vec![5u8]));
+        }
+        let per_value = |v: usize| (0..n).filter(|i| i % 4 == v).count() as u64;
+
+        // The maximum falls in the last bin
+        let (counts, edges) = pc.histogram("z", 4, None).unwrap();
+        assert_eq!(edges, [0.0, 0.5, 1.0, 1.5, 2.0]);
+        assert_eq!(counts, [per_value(0), 0, per_value(1), per_value(2)]);
+
+        let (counts, edges) = pc.histogram("z", 2, Some((0.5, 1.5))).unwrap();
+        assert_eq!(edges, [0.5, 1.0, 1.5]);
+        assert_eq!(counts, [0, per_value(1)]);
+
+        let (counts, edges) = pc.histogram("label", 1, None).unwrap();
+        assert_eq!((counts[0], edges), (n as u64, vec![4.5, 5.5]));
+
+        assert!(pc.histogram("z", 0, None).is_err());
+        assert!(pc.histogram("z", 4, Some((1.0, 0.0))).is_err());
+        assert!(pc.histogram("w", 4, None).is_err());
+    }
+
+    #[test]
+    fn test_info() {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("intensity", Dtype::F32, 1), ("rgb", Dtype::U8, 3)]),
+            width: 1000,
+            height: 1,
+            npoints: 1000,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        pc.fields.get_mut("intensity").unwrap().assign_row(3, &Array1::from(vec![f32::NAN]));
+        pc.fields.get_mut("intensity").unwrap().assign_row(4, &Array1::from(vec![0.25f32]));
+        pc.fields.get_mut("rgb").unwrap().assign_row(0, &Array1::from(vec![1u8, 2, 255]));
+        assert_eq!(pc.nbytes(), 7000);
+        assert_eq!(pc.info().unwrap(), "\
+PointCloud: 1000 points (width 1000, height 1), 2 fields, 6.8 KiB
+ #  Field      Dtype    Count  Min  Max     NaN  Memory
+ 0  intensity  float32  1      0    0.2500  1    3.9 KiB
+ 1  rgb        uint8    3      0    255     0    2.9 KiB
+");
+    }
+}
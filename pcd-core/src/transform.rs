@@ -0,0 +1,287 @@
+use anyhow::Result;
+use ndarray::{ArcArray2, Axis, Zip};
+use crate::fielddata::FieldData;
+use crate::metadata::Viewpoint;
+use crate::pointcloud::PointCloud;
+
+/// 4x4 homogeneous transformation matrix in row-major order.
+pub type Matrix4 = [[f64; 4]; 4];
+
+/// Returns the 4x4 identity matrix.
+pub fn identity() -> Matrix4 {
+    let mut m = [[0.0; 4]; 4];
+    for (i, row) in m.iter_mut().enumerate() {
+        row[i] = 1.0;
+    }
+    m
+}
+
+/// Returns a 4x4 matrix that translates by `t`.
+pub fn translation_matrix(t: [f64; 3]) -> Matrix4 {
+    let mut m = identity();
+    for i in 0..3 {
+        m[i][3] = t[i];
+    }
+    m
+}
+
+/// Returns a 4x4 matrix with `r` as the rotation block and no translation.
+pub fn rotation_matrix(r: [[f64; 3]; 3]) -> Matrix4 {
+    let mut m = identity();
+    for i in 0..3 {
+        m[i][..3].copy_from_slice(&r[i]);
+    }
+    m
+}
+
+/// Returns the rotation matrix for a quaternion given as `[w, x, y, z]`.
+/// The quaternion is normalized first.
+pub fn quaternion_to_rotation(q: [f64; 4]) -> Result<[[f64; 3]; 3]> {
+    let norm = q.iter().map(|v| v * v).sum::<f64>().sqrt();
+    anyhow::ensure!(norm > 0.0, "Quaternion must have a non-zero norm");
+    let [w, x, y, z] = q.map(|v| v / norm);
+    Ok([
+        [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - w * z), 2.0 * (x * z + w * y)],
+        [2.0 * (x * y + w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - w * x)],
+        [2.0 * (x * z - w * y), 2.0 * (y * z + w * x), 1.0 - 2.0 * (x * x + y * y)],
+    ])
+}
+
+/// Returns the quaternion `[w, x, y, z]` (with w >= 0) of a rotation matrix.
+pub fn rotation_to_quaternion(r: [[f64; 3]; 3]) -> [f64; 4] {
+    // Compute the largest component first, for numerical stability.
+    let trace = r[0][0] + r[1][1] + r[2][2];
+    let q = if trace > 0.0 {
+        let s = 2.0 * (1.0 + trace).sqrt();
+        [0.25 * s, (r[2][1] - r[1][2]) / s, (r[0][2] - r[2][0]) / s, (r[1][0] - r[0][1]) / s]
+    } else if r[0][0] > r[1][1] && r[0][0] > r[2][2] {
+        let s = 2.0 * (1.0 + r[0][0] - r[1][1] - r[2][2]).sqrt();
+        [(r[2][1] - r[1][2]) / s, 0.25 * s, (r[0][1] + r[1][0]) / s, (r[0][2] + r[2][0]) / s]
+    } else if r[1][1] > r[2][2] {
+        let s = 2.0 * (1.0 + r[1][1] - r[0][0] - r[2][2]).sqrt();
+        [(r[0][2] - r[2][0]) / s, (r[0][1] + r[1][0]) / s, 0.25 * s, (r[1][2] + r[2][1]) / s]
+    } else {
+        let s = 2.0 * (1.0 + r[2][2] - r[0][0] - r[1][1]).sqrt();
+        [(r[1][0] - r[0][1]) / s, (r[0][2] + r[2][0]) / s, (r[1][2] + r[2][1]) / s, 0.25 * s]
+    };
+    if q[0] < 0.0 { q.map(|v| -v) } else { q }
+}
+
+/// Returns the matrix product `a * b` (the transform applying `b`, then `a`).
+pub fn multiply(a: &Matrix4, b: &Matrix4) -> Matrix4 {
+    let mut m = [[0.0; 4]; 4];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, v) in row.iter_mut().enumerate() {
+            *v = (0..4).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    m
+}
+
+impl Viewpoint {
+    /// Returns the sensor pose as a 4x4 matrix mapping sensor coordinates to world coordinates.
+    pub fn to_matrix(&self) -> Result<Matrix4> {
+        let q = [self.qw, self.qx, self.qy, self.qz].map(f64::from);
+        let mut m = rotation_matrix(quaternion_to_rotation(q)?);
+        m[0][3] = self.tx.into();
+        m[1][3] = self.ty.into();
+        m[2][3] = self.tz.into();
+        Ok(m)
+    }
+
+    /// Creates a viewpoint from a rigid 4x4 transform (a rotation and a translation).
+    pub fn from_matrix(m: &Matrix4) -> Result<Self> {
+        const TOLERANCE: f64 = 1e-4;
+        anyhow::ensure!(m[3].iter().zip([0.0, 0.0, 0.0, 1.0]).all(|(a, b)| (a - b).abs() < TOLERANCE),
+            "The last row of a rigid transform must be [0, 0, 0, 1]");
+        let r = [0, 1, 2].map(|i| [m[i][0], m[i][1], m[i][2]]);
+        for i in 0..3 {
+            for j in 0..3 {
+                let dot: f64 = (0..3).map(|k| r[i][k] * r[j][k]).sum();
+                let expected = if i == j { 1.0 } else { 0.0 };
+                anyhow::ensure!((dot - expected).abs() < TOLERANCE, "The upper 3x3 block must be a rotation matrix");
+            }
+        }
+        let det = r[0][0] * (r[1][1] * r[2][2] - r[1][2] * r[2][1])
+            - r[0][1] * (r[1][0] * r[2][2] - r[1][2] * r[2][0])
+            + r[0][2] * (r[1][0] * r[2][1] - r[1][1] * r[2][0]);
+        anyhow::ensure!(det > 0.0, "The upper 3x3 block must be a rotation matrix");
+        let q = rotation_to_quaternion(r);
+        Ok(Viewpoint::from([m[0][3], m[1][3], m[2][3], q[0], q[1], q[2], q[3]].map(|v| v as f32).to_vec()))
+    }
+
+    /// Returns the viewpoint of `self` applied after `other`, i.e. the pose with matrix
+    /// `self.to_matrix() * other.to_matrix()`.
+    pub fn compose(&self, other: &Viewpoint) -> Result<Self> {
+        Viewpoint::from_matrix(&multiply(&self.to_matrix()?, &other.to_matrix()?))
+    }
+
+    /// Returns the inverse pose, mapping world coordinates to sensor coordinates.
+    pub fn inverse(&self) -> Result<Self> {
+        let m = self.to_matrix()?;
+        let mut inv = identity();
+        for i in 0..3 {
+            for j in 0..3 {
+                inv[i][j] = m[j][i];
+            }
+            inv[i][3] = -(0..3).map(|k| m[k][i] * m[k][3]).sum::<f64>();
+        }
+        Viewpoint::from_matrix(&inv)
+    }
+}
+
+/// Applies `m` to every (a, b, c) triple in parallel. When `affine` is false the
+/// translation column is ignored (used for direction vectors such as normals).
+fn apply<T>(a: &mut ArcArray2<T>, b: &mut ArcArray2<T>, c: &mut ArcArray2<T>, m: &Matrix4, affine: bool, cast: fn(f64) -> T)
+where
+    T: Copy + Into<f64> + Send + Sync,
+{
+    let w = if affine { 1.0 } else { 0.0 };
+    Zip::from(a.index_axis_mut(Axis(1), 0))
+        .and(b.index_axis_mut(Axis(1), 0))
+        .and(c.index_axis_mut(Axis(1), 0))
+        .par_for_each(|a, b, c| {
+            let p = [(*a).into(), (*b).into(), (*c).into()];
+            let r = |i: usize| m[i][0] * p[0] + m[i][1] * p[1] + m[i][2] * p[2] + m[i][3] * w;
+            *a = cast(r(0));
+            *b = cast(r(1));
+            *c = cast(r(2));
+        });
+}
+
+impl PointCloud {
+    /// Applies a 4x4 homogeneous transform to the `names` coordinate fields.
+    /// If `normals` are given (and present in the cloud), they are rotated by the upper 3x3 block.
+    ///
+    /// The coordinate fields must be F32 or F64 with a count of 1 and share the same dtype.
+    pub fn transform(&mut self, m: &Matrix4, names: [&str; 3], normals: Option<[&str; 3]>) -> Result<()> {
+        self.transform_fields(m, names, true)?;
+        if let Some(normals) = normals {
+            if normals.iter().all(|n| self.fields.contains_key(*n)) {
+                self.transform_fields(m, normals, false)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Translates the `names` coordinate fields by `t`.
+    pub fn translate(&mut self, t: [f64; 3], names: [&str; 3]) -> Result<()> {
+        self.transform_fields(&translation_matrix(t), names, true)
+    }
+
+    /// Rotates the `names` coordinate fields (and `normals`, if present) by the 3x3 matrix `r`.
+    pub fn rotate(&mut self, r: [[f64; 3]; 3], names: [&str; 3], normals: Option<[&str; 3]>) -> Result<()> {
+        self.transform(&rotation_matrix(r), names, normals)
+    }
+
+    /// Transforms the `names` coordinate fields (and `normals`, if present) by the viewpoint,
+    /// moving the points from the sensor frame into the world frame, and resets the viewpoint.
+    pub fn apply_viewpoint(&mut self, names: [&str; 3], normals: Option<[&str; 3]>) -> Result<()> {
+        let m = self.metadata.read().unwrap().viewpoint.to_matrix()?;
+        self.transform(&m, names, normals)?;
+        self.metadata.write().unwrap().viewpoint = Viewpoint::default();
+        Ok(())
+    }
+
+    fn transform_fields(&mut self, m: &Matrix4, names: [&str; 3], affine: bool) -> Result<()> {
+        anyhow::ensure!(names[0] != names[1] && names[1] != names[2] && names[0] != names[2],
+            "Coordinate field names must be distinct");
+        for name in names {
+            let field = self.fields.get(name)
+                .ok_or_else(|| anyhow::anyhow!("Field '{}' not found", name))?;
+            anyhow::ensure!(field.count() == 1, "Field '{}' must have a count of 1", name);
+        }
+
+        match self.fields.get_disjoint_mut(names).unwrap() {
+            [FieldData::F32(a), FieldData::F32(b), FieldData::F32(c)] => {
+                apply(a, b, c, m, affine, |v| v as f32);
+                Ok(())
+            }
+            [FieldData::F64(a), FieldData::F64(b), FieldData::F64(c)] => {
+                apply(a, b, c, m, affine, |v| v);
+                Ok(())
+            }
+            _ => Err(anyhow::anyhow!("Fields {:?} must all be F32 or all be F64", names)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{Dtype, FieldSchema, Metadata};
+    use ndarray::Array1;
+
+    #[test]
+    fn test_transform() {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([
+                ("x", Dtype::F32, 1), ("y", Dtype::F32, 1), ("z", Dtype::F32, 1),
+                ("normal_x", Dtype::F32, 1), ("normal_y", Dtype::F32, 1), ("normal_z", Dtype::F32, 1),
+            ]),
+            width: 1,
+            height: 1,
+            npoints: 1,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        pc.fields.get_mut("x").unwrap().assign_row(0, &Array1::from(vec![1.0f32]));
+        pc.fields.get_mut("normal_x").unwrap().assign_row(0, &Array1::from(vec![1.0f32]));
+
+        // 90 degrees about z, then translate.
+        let h = std::f64::consts::FRAC_1_SQRT_2;
+        let r = quaternion_to_rotation([h, 0.0, 0.0, h]).unwrap();
+        let mut m = rotation_matrix(r);
+        m[0][3] = 5.0;
+        pc.transform(&m, ["x", "y", "z"], Some(["normal_x", "normal_y", "normal_z"])).unwrap();
+
+        let get = |pc: &PointCloud, name: &str| pc.fields[name].get_row::<f32>(0)[0];
+        assert!((get(&pc, "x") - 5.0).abs() < 1e-6);
+        assert!((get(&pc, "y") - 1.0).abs() < 1e-6);
+        assert!(get(&pc, "normal_x").abs() < 1e-6);
+        assert!((get(&pc, "normal_y") - 1.0).abs() < 1e-6);
+
+        pc.translate([0.0, 0.0, 2.0], ["x", "y", "z"]).unwrap();
+        assert!((get(&pc, "z") - 2.0).abs() < 1e-6);
+        assert!(pc.translate([0.0; 3], ["x", "x", "z"]).is_err());
+    }
+
+    #[test]
+    fn test_viewpoint_matrix() {
+        let h = std::f32::consts::FRAC_1_SQRT_2;
+        let vp = Viewpoint::from(vec![1.0, 2.0, 3.0, h, 0.0, 0.0, h]);
+        let m = vp.to_matrix().unwrap();
+        assert!((m[1][0] - 1.0).abs() < 1e-6 && (m[2][3] - 3.0).abs() < 1e-6);
+        let back = Viewpoint::from_matrix(&m).unwrap();
+        assert!(back.to_vec().iter().zip(vp.to_vec()).all(|(a, b)| (a - b).abs() < 1e-6));
+
+        // 180 degrees about x: the trace is negative.
+        let flipped = Viewpoint::from(vec![0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+        assert_eq!(Viewpoint::from_matrix(&flipped.to_matrix().unwrap()).unwrap(), flipped);
+
+        let composed = vp.compose(&vp.inverse().unwrap()).unwrap();
+        assert!(composed.to_vec().iter().zip(Viewpoint::default().to_vec()).all(|(a, b)| (a - b).abs() < 1e-5));
+
+        let mut scale = identity();
+        scale[0][0] = 2.0;
+        assert!(Viewpoint::from_matrix(&scale).is_err());
+    }
+
+    #[test]
+    fn test_apply_viewpoint() {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F64, 1), ("y", Dtype::F64, 1), ("z", Dtype::F64, 1)]),
+            width: 1,
+            height: 1,
+            npoints: 1,
+            viewpoint: Viewpoint::from(vec![0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]),
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        pc.fields.get_mut("x").unwrap().assign_row(0, &Array1::from(vec![1.0]));
+        pc.apply_viewpoint(["x", "y", "z"], None).unwrap();
+        let get = |name: &str| pc.fields[name].get_row::<f64>(0)[0];
+        assert!((get("x") + 1.0).abs() < 1e-6 && get("y").abs() < 1e-6 && (get("z") - 1.0).abs() < 1e-6);
+        assert_eq!(pc.metadata.read().unwrap().viewpoint, Viewpoint::default());
+    }
+}
@@ -0,0 +1,419 @@
+use crate::metadata::{check_custom_entry, Metadata, Encoding, Dtype, Viewpoint, FieldSchema, FieldMeta};
+use crate::error::{ErrorKind, PcdError};
+use std::fs::File;
+use std::io::BufReader;
+use std::io::prelude::*;
+use std::str::FromStr;
+use std::sync::RwLock;
+use anyhow::Result;
+
+/// PCD header versions that can be read. 0.6 headers have no VIEWPOINT line.
+pub const SUPPORTED_VERSIONS: [&str; 4] = ["0.7", ".7", "0.6", ".6"];
+
+/// Field renames (from, to) applied when parsing PCD headers. See `set_field_aliases`.
+static FIELD_ALIASES: RwLock<Vec<(String, String)>> = RwLock::new(Vec::new());
+
+/// Replace the field renames applied to every PCD header parsed from now on.
+pub fn set_field_aliases(aliases: Vec<(String, String)>) {
+    *FIELD_ALIASES.write().unwrap() = aliases;
+}
+
+/// Return the field renames applied to PCD headers.
+pub fn field_aliases() -> Vec<(String, String)> {
+    FIELD_ALIASES.read().unwrap().clone()
+}
+
+/// Give unique names to fields declared more than once, which would otherwise share their
+/// data: the second `_` padding field becomes `_1`, the third `_2`, and so on. Other duplicate
+/// fields are renamed the same way (`rgb_1`, ...), with a warning.
+pub fn rename_duplicate_fields(schema: &mut FieldSchema, warnings: &mut Vec<String>) {
+    for idx in 1..schema.len() {
+        let name = schema[idx].name.clone();
+        if !schema.iter().take(idx).any(|f| f.name == name) {
+            continue;
+        }
+        let new_name = (1..)
+            .map(|n| if name == "_" { format!("_{}", n) } else { format!("{}_{}", name, n) })
+            .find(|candidate| !schema.iter().any(|f| f.name == *candidate))
+            .unwrap();
+        if name != "_" {
+            warnings.push(format!("Duplicate field '{}' renamed to '{}'", name, new_name));
+        }
+        schema[idx].name = new_name;
+    }
+}
+
+/// Rename the fields of `schema` according to `aliases`. A field is not renamed if the
+/// schema already has a field with the new name; a warning is added instead.
+pub fn apply_field_aliases(schema: &mut FieldSchema, aliases: &[(String, String)], warnings: &mut Vec<String>) {
+    for (from, to) in aliases {
+        let Some(idx) = schema.iter().position(|f| f.name == *from) else {
+            continue;
+        };
+        if schema.iter().any(|f| f.name == *to) {
+            warnings.push(format!("Not renaming field '{}' to '{}', which already exists", from, to));
+        } else {
+            schema[idx].name = to.clone();
+        }
+    }
+}
+
+/// Parses only the header of the PCD file at `path`, without reading the point data. With the
+/// "remote" feature, `path` can also be a URL (see `io_remote::is_url`). Returns the metadata together with any header warnings. See `parse_header`.
+pub fn read_metadata(path: &str) -> Result<(Metadata, Vec<String>)> {
+    #[cfg(feature = "remote")]
+    if crate::io_remote::is_url(path) {
+        return parse_header(&mut BufReader::new(crate::io_remote::RemoteReader::open(path)?));
+    }
+    let file = File::open(path)?;
+    let mut reader = BufReader::new(file);
+    parse_header(&mut reader)
+}
+
+/// Parses the whitespace-separated values of a header line, recording an error for each
+/// value that cannot be parsed. Returns None if any value was invalid.
+fn parse_values<T: FromStr>(key: &str, values: &[&str], errors: &mut Vec<String>) -> Option<Vec<T>> {
+    let mut parsed = Vec::with_capacity(values.len());
+    for value in values {
+        match value.parse() {
+            Ok(v) => parsed.push(v),
+            Err(_) => {
+                errors.push(format!("{}: invalid value '{}'", key, value));
+                return None;
+            }
+        }
+    }
+    Some(parsed)
+}
+
+/// Parses a single-valued header line, recording an error if the value count or value is invalid.
+fn parse_single<T: FromStr>(key: &str, values: &[&str], errors: &mut Vec<String>) -> Option<T> {
+    if values.len() != 1 {
+        errors.push(format!("{}: expected 1 value, got {}", key, values.len()));
+        return None;
+    }
+    parse_values(key, values, errors).and_then(|mut v| v.pop())
+}
+
+/// Parses a PCD header up to and including the DATA line, leaving the reader positioned at
+/// the start of the point data.
+///
+/// The parser is tolerant of common defects in files written by older tools:
+/// - unknown header keys are ignored,
+/// - a missing COUNT line defaults every field to a count of 1,
+/// - a missing VIEWPOINT line (as in PCD 0.6) defaults to the identity viewpoint,
+/// - missing WIDTH and HEIGHT lines (as in some legacy files) describe an unorganized cloud,
+/// - a POINTS line directly after DATA is accepted, and a missing POINTS is inferred from
+///   WIDTH x HEIGHT.
+///
+/// Each recovery is reported in the returned warnings. All malformed lines are collected
+/// and reported together in a single error.
+pub fn parse_header<R: BufRead>(bufreader: &mut R) -> Result<(Metadata, Vec<String>)> {
+    // Initialize metadata fields as None to check if they are all present in the file
+    let mut version: Option<String> = None;
+    let mut fields: Option<Vec<String>> = None;
+    let mut sizes: Option<Vec<usize>> = None;
+    let mut types: Option<Vec<String>> = None;
+    let mut counts: Option<Vec<usize>> = None;
+    let mut width: Option<usize> = None;
+    let mut height: Option<usize> = None;
+    let mut viewpoint: Option<Viewpoint> = None;
+    let mut npoints: Option<usize> = None;
+    let mut encoding: Option<Encoding> = None;
+    let mut custom: Vec<(String, String)> = Vec::new();
+    let mut errors: Vec<String> = Vec::new();
+    let mut warnings: Vec<String> = Vec::new();
+
+    loop {
+        let mut line = String::new();
+        let line_size = bufreader.read_line(&mut line)?;
+
+        // Check for EOF
+        if line_size == 0 {
+            anyhow::bail!(PcdError::new(ErrorKind::Header, "Unexpected EOF while reading metadata"));
+        }
+
+        if let Some((key, value)) = parse_custom_entry(line.trim()) {
+            // Later entries replace earlier ones
+            custom.retain(|(k, _)| *k != key);
+            custom.push((key, value));
+            continue;
+        }
+
+        // Skip comments and empty lines
+        let line = match line.trim().split('#').next() {
+            Some("") | None => continue,
+            Some(s) => s,
+        };
+
+        let values = line.split_ascii_whitespace().collect::<Vec<&str>>();
+        let (key, values) = match values.split_first() {
+            Some((key, values)) => (*key, values),
+            None => continue,
+        };
+
+        // Fill in metadata fields
+        match key {
+            "VERSION" => {
+                if let Some(v) = parse_single::<String>(key, values, &mut errors) {
+                    if !SUPPORTED_VERSIONS.contains(&v.as_str()) {
+                        anyhow::bail!(PcdError::new(ErrorKind::Header, format!("Unsupported PCD version: {}", v)));
+                    }
+                    version = Some(v);
+                }
+            }
+            // COLUMNS is the pre-0.7 name of FIELDS
+            "FIELDS" | "COLUMNS" => {
+                if values.is_empty() {
+                    errors.push(format!("{}: expected at least 1 value", key));
+                } else {
+                    fields = Some(values.iter().map(|s| s.to_string()).collect());
+                }
+            }
+            "SIZE" => sizes = parse_values(key, values, &mut errors),
+            "TYPE" => types = Some(values.iter().map(|s| s.to_string()).collect()),
+            "COUNT" => counts = parse_values(key, values, &mut errors),
+            "WIDTH" => width = parse_single(key, values, &mut errors),
+            "HEIGHT" => height = parse_single(key, values, &mut errors),
+            "VIEWPOINT" => {
+                if values.len() != 7 {
+                    errors.push(format!("VIEWPOINT: expected 7 values, got {}", values.len()));
+                } else {
+                    viewpoint = parse_values(key, values, &mut errors).map(Viewpoint::from);
+                }
+            }
+            "POINTS" => npoints = parse_single(key, values, &mut errors),
+            "DATA" => {
+                if let Some(v) = parse_single::<String>(key, values, &mut errors) {
+                    encoding = Encoding::from_str(&v);
+                    if encoding.is_none() {
+                        errors.push(format!("DATA: invalid encoding '{}'", v));
+                    }
+                }
+                break;
+            }
+            _ => warnings.push(format!("Ignoring unknown header line: {}", line)),
+        }
+    }
+
+    // Some writers emit POINTS after DATA. Accept it if it is the very next line.
+    if npoints.is_none() && bufreader.fill_buf()?.starts_with(b"POINTS") {
+        let mut line = String::new();
+        bufreader.read_line(&mut line)?;
+        let values = line.split_ascii_whitespace().skip(1).collect::<Vec<&str>>();
+        npoints = parse_single("POINTS", &values, &mut errors);
+        warnings.push("POINTS line found after DATA".to_string());
+    }
+
+    // Ensure all required metadata is present
+    let mut require = |value: bool, key: &str| {
+        if !value {
+            errors.push(format!("Missing {}", key));
+        }
+    };
+    require(version.is_some(), "VERSION");
+    require(fields.is_some(), "FIELDS");
+    require(sizes.is_some(), "SIZE");
+    require(types.is_some(), "TYPE");
+
+    if let (Some(fields), Some(sizes), Some(types)) = (&fields, &sizes, &types) {
+        if sizes.len() != fields.len() || types.len() != fields.len() {
+            errors.push(format!(
+                "FIELDS, SIZE, and TYPE lengths differ: {}, {}, {}",
+                fields.len(), sizes.len(), types.len()
+            ));
+        }
+        for ((name, size), t) in fields.iter().zip(sizes).zip(types) {
+            if Dtype::try_from_type_size(t, *size).is_none() {
+                errors.push(format!("Field '{}': type {} and size {} is not supported", name, t, size));
+            }
+        }
+        match counts.as_ref().map(|c| c.len()) {
+            Some(n) if n != fields.len() => {
+                errors.push(format!("COUNT has {} values but there are {} fields", n, fields.len()));
+            }
+            Some(_) => {}
+            None => {
+                warnings.push("Missing COUNT, assuming a count of 1 for every field".to_string());
+                counts = Some(vec![1; fields.len()]);
+            }
+        }
+    }
+
+    if npoints.is_none() {
+        if let (Some(width), Some(height)) = (width, height) {
+            match width.checked_mul(height) {
+                Some(n) => {
+                    warnings.push("Missing POINTS, inferred from WIDTH x HEIGHT".to_string());
+                    npoints = Some(n);
+                }
+                None => errors.push(format!("Missing POINTS, and WIDTH x HEIGHT ({} x {}) overflows", width, height)),
+            }
+        }
+    }
+
+    // Legacy headers may omit WIDTH and HEIGHT, in which case the cloud is unorganized
+    if let (None, None, Some(n)) = (width, height, npoints) {
+        warnings.push("Missing WIDTH and HEIGHT, assuming an unorganized cloud".to_string());
+        width = Some(n);
+        height = Some(1);
+    }
+    if width.is_none() {
+        errors.push("Missing WIDTH".to_string());
+    }
+    if height.is_none() {
+        errors.push("Missing HEIGHT".to_string());
+    }
+
+    if !errors.is_empty() {
+        anyhow::bail!(PcdError::new(ErrorKind::Header, format!("Invalid PCD header:\n - {}", errors.join("\n - "))));
+    }
+
+    let fields = fields.unwrap();
+    let viewpoint = viewpoint.unwrap_or_default();
+
+    // Create field schema by zipping fields, sizes, types, and counts
+    let mut field_schema: FieldSchema = fields.into_iter()
+        .zip(sizes.unwrap())
+        .zip(types.unwrap())
+        .zip(counts.unwrap())
+        .map(|(((name, size), dtype), count)| FieldMeta::new(name, Dtype::from_type_size(&dtype, &size), count))
+        .collect();
+    rename_duplicate_fields(&mut field_schema, &mut warnings);
+
+    // Construct metadata struct
+    let mut metadata = Metadata {
+        version: version.unwrap(),
+        fields: field_schema,
+        width: width.unwrap(),
+        height: height.unwrap(),
+        viewpoint,
+        npoints: npoints.unwrap(),
+        encoding: encoding.unwrap(),
+        custom,
+    };
+    // Field info comments use the names in the file, before aliases are applied
+    metadata.take_field_info();
+    apply_field_aliases(&mut metadata.fields, &FIELD_ALIASES.read().unwrap(), &mut warnings);
+
+    Ok((metadata, warnings))
+}
+
+/// Parses a `# key: value` header comment holding a custom metadata entry (see
+/// `Metadata::custom`). Other comments, such as PCL's `# .PCD v0.7 - Point Cloud Data file
+/// format`, return None.
+fn parse_custom_entry(line: &str) -> Option<(String, String)> {
+    let (key, value) = line.strip_prefix('#')?.split_once(':')?;
+    let key = key.trim();
+    let value = value.trim();
+    check_custom_entry(key, value).ok()?;
+    Some((key.to_string(), value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::Semantic;
+
+    #[test]
+    fn test_parse_header_recovery() {
+        let header = b"# comment\nVERSION 0.7\nFIELDS x y\nSIZE 4 4\nTYPE F F\nFOO bar\nWIDTH 3\nHEIGHT 1\nDATA ascii\nPOINTS 3\n1 2\n";
+        let mut reader = &header[..];
+        let (md, warnings) = parse_header(&mut reader).unwrap();
+        assert_eq!(md.npoints, 3);
+        assert!(md.fields.iter().all(|f| f.count == 1));
+        assert_eq!(md.viewpoint, Viewpoint::default());
+        assert_eq!(warnings.len(), 3);
+        assert_eq!(reader, b"1 2\n");
+    }
+
+    #[test]
+    fn test_rename_duplicate_fields() {
+        let mut schema = FieldSchema::from_iter([
+            ("x", Dtype::F32, 1), ("_", Dtype::U8, 4), ("rgb", Dtype::F32, 1),
+            ("_", Dtype::U8, 4), ("rgb", Dtype::F32, 1), ("_", Dtype::U8, 4),
+        ]);
+        let mut warnings = Vec::new();
+        rename_duplicate_fields(&mut schema, &mut warnings);
+        assert_eq!(schema.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(), ["x", "_", "rgb", "_1", "rgb_1", "_2"]);
+        assert_eq!(warnings.len(), 1);
+        assert!(schema[5].is_padding() && !schema[4].is_padding());
+    }
+
+    #[test]
+    fn test_apply_field_aliases() {
+        let mut schema = FieldSchema::from_iter([("X", Dtype::F32, 1), ("Y", Dtype::F32, 1), ("y", Dtype::F32, 1)]);
+        let aliases = [("X", "x"), ("Y", "y"), ("Z", "z")].map(|(a, b)| (a.to_string(), b.to_string()));
+        let mut warnings = Vec::new();
+        apply_field_aliases(&mut schema, &aliases, &mut warnings);
+        assert_eq!(schema.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(), ["x", "Y", "y"]);
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_legacy_header() {
+        let header = b"VERSION .6\nCOLUMNS x y z\nSIZE 4 4 4\nTYPE F F F\nCOUNT 1 1 1\nPOINTS 5\nDATA binary\n";
+        let (md, warnings) = parse_header(&mut &header[..]).unwrap();
+        assert_eq!(md.version, ".6");
+        assert!(md.is_legacy());
+        assert_eq!(md.fields.len(), 3);
+        assert_eq!((md.width, md.height, md.npoints), (5, 1, 5));
+        assert_eq!(warnings.len(), 1);
+
+        let header = b"VERSION 0.5\nFIELDS x\nSIZE 4\nTYPE F\nPOINTS 1\nDATA ascii\n";
+        assert!(parse_header(&mut &header[..]).is_err());
+    }
+
+    #[test]
+    fn test_parse_header_collects_errors() {
+        let header = b"VERSION 0.7\nFIELDS x\nSIZE four\nTYPE F\nWIDTH -1\nDATA ascii\n";
+        let err = parse_header(&mut &header[..]).unwrap_err().to_string();
+        assert!(err.contains("SIZE: invalid value 'four'"), "{}", err);
+        assert!(err.contains("WIDTH: invalid value '-1'"), "{}", err);
+        assert!(err.contains("Missing HEIGHT"), "{}", err);
+
+        let header = b"VERSION 0.7\nFIELDS x\nSIZE 4\nTYPE F\nWIDTH 4294967296\nHEIGHT 4294967296\nDATA ascii\n";
+        let err = parse_header(&mut &header[..]).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), Some(ErrorKind::Header));
+        assert!(err.to_string().contains("overflows"), "{}", err);
+    }
+
+    #[test]
+    fn test_custom_entries() {
+        let mut md = Metadata { fields: FieldSchema::from_iter([("x", Dtype::F32, 1)]), ..Metadata::default() };
+        md.set_custom("sensor_id", "lidar 0").unwrap();
+        md.set_custom("frame_time", "1700000000.5").unwrap();
+        md.set_custom("sensor_id", "lidar 1").unwrap();
+        assert!(md.set_custom("bad key", "x").is_err());
+        assert!(md.set_custom("calib", "a\nb").is_err());
+
+        let mut header = b"# .PCD v0.7 - Point Cloud Data file format\n".to_vec();
+        crate::io::write_header(&mut header, &md).unwrap();
+        assert!(String::from_utf8_lossy(&header).contains("\n# sensor_id: lidar 1\n# frame_time: 1700000000.5\nVERSION 0.7\n"));
+        let (read, warnings) = parse_header(&mut &header[..]).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(read.custom, [("sensor_id".to_string(), "lidar 1".to_string()), ("frame_time".to_string(), "1700000000.5".to_string())]);
+        assert_eq!(read, md);
+    }
+
+    #[test]
+    fn test_field_info() {
+        let mut md = Metadata { fields: FieldSchema::from_iter([("x", Dtype::F32, 1), ("t", Dtype::F64, 1)]), ..Metadata::default() };
+        md.set_field_info("x", Some("mm"), Some("Distance along the sensor axis"), Some(Semantic::Position)).unwrap();
+        md.set_field_info("t", None, None, Some(Semantic::Time)).unwrap();
+        assert!(md.set_field_info("x", Some("m\n"), None, None).is_err());
+        assert!(md.set_field_info("y", Some("m"), None, None).is_err());
+        md.set_custom("unit.y", "m").unwrap();
+
+        let mut header = Vec::new();
+        crate::io::write_header(&mut header, &md).unwrap();
+        assert!(String::from_utf8_lossy(&header).starts_with(
+            "# unit.y: m\n# unit.x: mm\n# description.x: Distance along the sensor axis\n# semantic.x: position\n# semantic.t: time\n"));
+        let (read, _) = parse_header(&mut &header[..]).unwrap();
+        assert_eq!(read, md);
+        assert_eq!(read.fields[0].unit.as_deref(), Some("mm"));
+        // Entries for missing fields or with unknown tags stay custom entries
+        let (read, _) = parse_header(&mut &b"# semantic.x: depth\nVERSION 0.7\nFIELDS x\nSIZE 4\nTYPE F\nWIDTH 0\nHEIGHT 1\nPOINTS 0\nDATA ascii\n"[..]).unwrap();
+        assert_eq!((read.fields[0].semantic, read.custom_value("semantic.x")), (None, Some("depth")));
+    }
+}
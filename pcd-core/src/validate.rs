@@ -0,0 +1,295 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use anyhow::Result;
+use byteorder::{LittleEndian, ReadBytesExt};
+use crate::io;
+use crate::metadata::{Encoding, Metadata};
+use crate::pointcloud::PointCloud;
+use crate::utils::parse_header;
+
+/// Diagnostics collected while checking PCD data.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationReport {
+    /// Header as parsed, or None if the header is invalid.
+    pub metadata: Option<Metadata>,
+    /// Defects in the header that were recovered (see `utils::parse_header`).
+    pub header_warnings: Vec<String>,
+    /// Problems that make the data invalid.
+    pub issues: Vec<String>,
+    /// Number of complete points found in the body.
+    pub points_in_body: usize,
+    /// True if the body ends in the middle of a point or of the compressed block.
+    pub truncated: bool,
+    /// Number of NaN values in each field of the points found, in schema order.
+    pub nan_counts: Vec<(String, usize)>,
+    /// Whether the data matches its checksum footer, or None if the body has no footer.
+    pub checksum: Option<bool>,
+}
+
+impl ValidationReport {
+    /// Returns true if no issues were found. Recovered header defects are not issues.
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Check the PCD data read from `reader` without stopping at the first defect. Returns the
+/// report and the points that could be recovered, or None if no points can be recovered
+/// (invalid header, or unreadable compressed data).
+pub fn inspect<R: BufRead>(reader: &mut R) -> (ValidationReport, Option<PointCloud>) {
+    let mut report = ValidationReport::default();
+    let (md, warnings) = match parse_header(reader) {
+        Ok(header) => header,
+        Err(e) => {
+            report.issues.push(e.to_string());
+            return (report, None);
+        }
+    };
+    report.header_warnings = warnings;
+    report.metadata = Some(md.clone());
+    match md.width.checked_mul(md.height) {
+        Some(n) if n == md.npoints => {}
+        Some(_) => report.issues.push(format!("WIDTH x HEIGHT ({} x {}) does not match POINTS {}", md.width, md.height, md.npoints)),
+        None => report.issues.push(format!("WIDTH x HEIGHT ({} x {}) overflows", md.width, md.height)),
+    }
+
+    let mut body = Vec::new();
+    let pc = reader.read_to_end(&mut body)
+        .map_err(anyhow::Error::from)
+        .and_then(|_| {
+            // A checksum footer is checked, then left out of the data
+            let mut data = &body[..];
+            if io::has_checksum_footer(&body) {
+                report.checksum = Some(match io::verify_checksum(&body) {
+                    Ok(_) => true,
+                    Err(e) => {
+                        report.issues.push(e.to_string());
+                        false
+                    }
+                });
+                data = &body[..body.len() - io::CHECKSUM_FOOTER_LEN];
+            }
+            match md.encoding {
+                Encoding::Ascii => inspect_ascii(&md, data, &mut report),
+                Encoding::Binary => inspect_binary(&md, data, &mut report),
+                _ => inspect_compressed(&md, data, &mut report),
+            }
+        });
+    let pc = match pc {
+        Ok(pc) => pc,
+        Err(e) => {
+            report.issues.push(e.to_string());
+            return (report, None);
+        }
+    };
+
+    report.points_in_body = pc.len();
+    if report.points_in_body != md.npoints {
+        report.issues.push(format!("Body holds {} points, but POINTS is {}", report.points_in_body, md.npoints));
+    }
+    report.nan_counts = md.fields.iter()
+        .map(|f| {
+            let valid: usize = pc.fields[&f.name].stats().iter().map(|s| s.count).sum();
+            (f.name.clone(), pc.len() * f.count - valid)
+        })
+        .collect();
+    (report, Some(pc))
+}
+
+/// Read the ASCII data lines that hold the expected number of values, reporting the others.
+/// Malformed values are replaced by NaN (or 0 for integer fields) and reported.
+fn inspect_ascii(md: &Metadata, body: &[u8], report: &mut ValidationReport) -> Result<PointCloud> {
+    let text = String::from_utf8_lossy(body);
+    let expected: usize = md.fields.iter().map(|f| f.count).sum();
+    let mut valid_lines = String::new();
+    let mut npoints = 0;
+    let mut last_line_valid = true;
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let n = line.split_ascii_whitespace().count();
+        last_line_valid = n == expected;
+        if !last_line_valid {
+            report.issues.push(format!("Data line {}: expected {} values, got {}", i + 1, expected, n));
+            continue;
+        }
+        valid_lines.push_str(line);
+        valid_lines.push('\n');
+        npoints += 1;
+    }
+    report.truncated = !last_line_valid && !body.ends_with(b"\n");
+
+    let mut pc = PointCloud::new(&Metadata { npoints, ..md.clone() });
+    let warnings = io::read_ascii_data(&mut valid_lines.as_bytes(), &mut pc, false)?;
+    report.issues.extend(warnings);
+    Ok(pc)
+}
+
+/// Read the complete points of binary data, reporting a trailing partial point.
+fn inspect_binary(md: &Metadata, body: &[u8], report: &mut ValidationReport) -> Result<PointCloud> {
+    let point_size = md.point_size();
+    anyhow::ensure!(point_size > 0, "The header has no fields");
+    let npoints = body.len() / point_size;
+    let remainder = body.len() % point_size;
+    if remainder != 0 {
+        report.truncated = true;
+        report.issues.push(format!("Data ends with a partial point of {} bytes", remainder));
+    }
+    let mut pc = PointCloud::new(&Metadata { npoints, ..md.clone() });
+    io::read_binary_data(&mut &body[..npoints * point_size], &mut pc)?;
+    Ok(pc)
+}
+
+/// Decompress the data block and read the points it holds. Fails if the block is
+/// truncated or cannot be split into points.
+fn inspect_compressed(md: &Metadata, body: &[u8], report: &mut ValidationReport) -> Result<PointCloud> {
+    let point_size = md.point_size();
+    anyhow::ensure!(point_size > 0, "The header has no fields");
+    // binary_compressed stores its block sizes as u32, the extensions as u64
+    let sizes_len = if md.encoding == Encoding::BinaryCompressed { 8 } else { 16 };
+    if body.len() < sizes_len {
+        report.truncated = true;
+        anyhow::bail!("Compressed data is truncated: missing block sizes");
+    }
+    let compressed_size = if md.encoding == Encoding::BinaryCompressed {
+        (&body[..4]).read_u32::<LittleEndian>()? as usize
+    } else {
+        (&body[..8]).read_u64::<LittleEndian>()? as usize
+    };
+    let available = body.len() - sizes_len;
+    if compressed_size > available {
+        report.truncated = true;
+        anyhow::bail!("Compressed data is truncated: expected {} bytes, got {}", compressed_size, available);
+    }
+
+    let buffer = io::read_compressed_buffer(&mut &body[..], md.encoding, None)?;
+    anyhow::ensure!(buffer.len().is_multiple_of(point_size),
+        "Decompressed data of {} bytes cannot be split into points of {} bytes", buffer.len(), point_size);
+    let md = Metadata { npoints: buffer.len() / point_size, ..md.clone() };
+    let mut pc = PointCloud::new(&md);
+    io::assign_compressed_rows(&buffer, &md, &mut pc.fields, 0, md.npoints)?;
+    Ok(pc)
+}
+
+/// Check the PCD file at `path`. See `inspect`.
+pub fn validate_file(path: &str) -> Result<ValidationReport> {
+    let mut reader = BufReader::new(File::open(path)?);
+    Ok(inspect(&mut reader).0)
+}
+
+/// Write the points that can be recovered from the PCD file at `path` to `out_path`, with
+/// POINTS set to the number of points found and WIDTH and HEIGHT describing an unorganized
+/// cloud if they do not match it. Malformed ASCII values are written as NaN (or 0 for
+/// integer fields). Returns the report of the original file, or an error if no points can
+/// be recovered.
+pub fn repair_file(path: &str, out_path: &str) -> Result<ValidationReport> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let (report, pc) = inspect(&mut reader);
+    let pc = pc.ok_or_else(|| anyhow::anyhow!("Cannot repair '{}': {}", path, report.issues.join("; ")))?;
+    {
+        let mut md = pc.metadata.write().unwrap();
+        if md.width.checked_mul(md.height) != Some(md.npoints) {
+            md.width = md.npoints;
+            md.height = 1;
+        }
+    }
+    pc.to_pcd_file(out_path)?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{Dtype, FieldSchema};
+
+    fn inspect_bytes(data: &[u8]) -> (ValidationReport, Option<PointCloud>) {
+        inspect(&mut &data[..])
+    }
+
+    #[test]
+    fn test_inspect_ascii() {
+        let data = b"VERSION 0.7\nFIELDS x y\nSIZE 4 4\nTYPE F F\nCOUNT 1 1\nWIDTH 2\nHEIGHT 2\nPOINTS 4\nDATA ascii\n1 2\nnan 3\n4\nabc 5\n6";
+        let (report, pc) = inspect_bytes(data);
+        assert!(!report.is_valid());
+        assert!(report.truncated);
+        assert_eq!(report.points_in_body, 3);
+        assert_eq!(report.nan_counts, vec![("x".to_string(), 2), ("y".to_string(), 0)]);
+        assert!(report.issues.iter().any(|i| i.contains("Data line 3")));
+        assert!(report.issues.iter().any(|i| i.contains("'abc'")));
+        assert!(report.issues.iter().any(|i| i.contains("Body holds 3 points")));
+        assert_eq!(pc.unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_inspect_binary_and_compressed() {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1), ("label", Dtype::U16, 2)]),
+            width: 3,
+            height: 1,
+            npoints: 3,
+            encoding: Encoding::Binary,
+            ..Metadata::default()
+        };
+        let pc = PointCloud::new(&md);
+        let bytes = pc.to_pcd_bytes().unwrap();
+        let (report, _) = inspect_bytes(&bytes);
+        assert!(report.is_valid(), "{:?}", report.issues);
+
+        let (report, recovered) = inspect_bytes(&bytes[..bytes.len() - 3]);
+        assert!(report.truncated);
+        assert_eq!(report.points_in_body, 2);
+        assert_eq!(recovered.unwrap().len(), 2);
+
+        pc.metadata.write().unwrap().encoding = Encoding::BinaryCompressed;
+        let bytes = pc.to_pcd_bytes().unwrap();
+        assert!(inspect_bytes(&bytes).0.is_valid());
+        let (report, recovered) = inspect_bytes(&bytes[..bytes.len() - 1]);
+        assert!(report.truncated && recovered.is_none());
+
+        // The checksum footer is checked and not read as data
+        let options = io::WriteOptions { checksum: true, ..Default::default() };
+        for encoding in [Encoding::Ascii, Encoding::Binary, Encoding::BinaryCompressed] {
+            pc.metadata.write().unwrap().encoding = encoding;
+            let mut bytes = pc.to_pcd_bytes_with(&options).unwrap();
+            let (report, recovered) = inspect_bytes(&bytes);
+            assert!(report.is_valid() && report.checksum == Some(true), "{:?}", report.issues);
+            assert_eq!(recovered.unwrap().len(), 3);
+            let i = bytes.len() - io::CHECKSUM_FOOTER_LEN - 2;
+            bytes[i] ^= 1;
+            let (report, _) = inspect_bytes(&bytes);
+            assert_eq!(report.checksum, Some(false));
+            assert!(report.issues.iter().any(|i| i.contains("Checksum mismatch")), "{:?}", report.issues);
+        }
+
+        let (report, recovered) = inspect_bytes(b"VERSION 0.7\nFIELDS x\n");
+        assert!(report.metadata.is_none() && recovered.is_none());
+    }
+
+    #[test]
+    fn test_repair_file() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("pcdpy_test_repair_in_{}.pcd", std::process::id()));
+        let out_path = dir.join(format!("pcdpy_test_repair_out_{}.pcd", std::process::id()));
+        let data = "VERSION 0.7\nFIELDS x\nSIZE 4\nTYPE F\nCOUNT 1\nWIDTH 2\nHEIGHT 2\nPOINTS 4\nDATA ascii\n1\n2 3\n4\n";
+        std::fs::write(&path, data).unwrap();
+        let (path, out_path) = (path.to_str().unwrap(), out_path.to_str().unwrap());
+
+        let report = repair_file(path, out_path).unwrap();
+        assert!(!report.is_valid());
+        let repaired = validate_file(out_path).unwrap();
+        assert!(repaired.is_valid(), "{:?}", repaired.issues);
+        let md = repaired.metadata.unwrap();
+        assert_eq!((md.width, md.height, md.npoints), (2, 1, 2));
+
+        let data = "VERSION 0.7\nFIELDS x\nSIZE 4\nTYPE F\nCOUNT 1\nWIDTH 4294967296\nHEIGHT 4294967296\nPOINTS 1\nDATA ascii\n1\n";
+        std::fs::write(path, data).unwrap();
+        let report = repair_file(path, out_path).unwrap();
+        assert!(report.issues.iter().any(|i| i.contains("overflows")), "{:?}", report.issues);
+        let md = validate_file(out_path).unwrap().metadata.unwrap();
+        assert_eq!((md.width, md.height, md.npoints), (1, 1, 1));
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(out_path).unwrap();
+    }
+}
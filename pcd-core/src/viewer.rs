@@ -0,0 +1,167 @@
+use anyhow::Result;
+use crate::fielddata::FieldData;
+use crate::pointcloud::PointCloud;
+
+/// Seed of the sample of points plotted, so that plotting a cloud again shows the same points.
+const SAMPLE_SEED: u64 = 0;
+/// The three.js module loaded by the HTML viewer.
+const THREE_URL: &str = "https://esm.sh/three@0.160.0";
+/// Stops of the colormap applied to scalar fields (viridis), from the lowest to the highest value.
+const COLORMAP: [[f64; 3]; 5] = [[68.0, 1.0, 84.0], [59.0, 82.0, 139.0], [33.0, 145.0, 140.0], [94.0, 201.0, 98.0], [253.0, 231.0, 37.0]];
+/// Color of points whose color field is NaN.
+const NAN_COLOR: [u8; 3] = [128, 128, 128];
+
+/// The points of a cloud prepared for display.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlotData {
+    pub positions: Vec<[f64; 3]>,
+    /// 8-bit r, g, b color of each point.
+    pub colors: Vec<[u8; 3]>,
+}
+
+/// Map `v` in `[lo, hi]` through the colormap.
+fn colormap(v: f64, lo: f64, hi: f64) -> [u8; 3] {
+    if v.is_nan() {
+        return NAN_COLOR;
+    }
+    let t = if hi > lo { ((v - lo) / (hi - lo)).clamp(0.0, 1.0) } else { 0.5 };
+    let x = t * (COLORMAP.len() - 1) as f64;
+    let i = (x as usize).min(COLORMAP.len() - 2);
+    let f = x - i as f64;
+    let (a, b) = (COLORMAP[i], COLORMAP[i + 1]);
+    [0, 1, 2].map(|c| (a[c] + (b[c] - a[c]) * f).round() as u8)
+}
+
+impl PointCloud {
+    /// Prepare at most `max_points` points for display, drawn at random (with a fixed seed)
+    /// from larger clouds, with the `names` coordinates. Points are colored by `color_field`,
+    /// or by their height (the third coordinate) if it is None: a packed color field (F32 or
+    /// U32 with a count of 1 named `rgb` or `rgba`) or a U8 field with a count of 3 gives the
+    /// colors, and any other field with a count of 1 is mapped through a colormap over its
+    /// range (NaN values are gray).
+    pub fn plot_data(&self, names: [&str; 3], max_points: usize, color_field: Option<&str>) -> Result<PlotData> {
+        anyhow::ensure!(max_points > 0, "max_points must be positive");
+        let sample;
+        let pc = if self.len() > max_points {
+            sample = self.random_sample(max_points, Some(SAMPLE_SEED))?;
+            &sample
+        } else {
+            self
+        };
+        let positions = pc.coordinates(names)?;
+
+        let name = color_field.unwrap_or(names[2]);
+        let field = pc.fields.get(name)
+            .ok_or_else(|| anyhow::anyhow!("Field '{}' not found", name))?;
+        let colors = match field {
+            FieldData::F32(_) | FieldData::U32(_) if field.count() == 1 && matches!(name, "rgb" | "rgba") => {
+                pc.unpack_rgb(name)?.rows().into_iter().map(|c| [c[0], c[1], c[2]]).collect()
+            }
+            FieldData::U8(arr) if arr.ncols() == 3 => arr.rows().into_iter().map(|c| [c[0], c[1], c[2]]).collect(),
+            _ => {
+                anyhow::ensure!(field.count() == 1, "Color field '{}' must have a count of 1, or be a U8 field with a count of 3", name);
+                let values = field.get_data::<f64>();
+                let (lo, hi) = values.iter().filter(|v| !v.is_nan())
+                    .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+                values.iter().map(|&v| colormap(v, lo, hi)).collect()
+            }
+        };
+        Ok(PlotData { positions, colors })
+    }
+}
+
+/// Encode `data` in base64 (standard alphabet, with padding).
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], chunk.get(1).copied().unwrap_or(0), chunk.get(2).copied().unwrap_or(0)];
+        let bits = ((bytes[0] as u32) << 16) | ((bytes[1] as u32) << 8) | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[((bits >> (18 - 6 * i)) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Render `data` as an HTML snippet showing the points in 3D with three.js (loaded from a
+/// CDN), to be displayed in a notebook or saved as a page. Points are drawn `point_size`
+/// pixels wide; the view orbits around the cloud with the mouse, with z up. Positions are
+/// centered on the mean point before being converted to float32, to keep their precision.
+pub fn plot_html(data: &PlotData, point_size: f32, height: usize) -> String {
+    let n = data.positions.len().max(1) as f64;
+    let center = [0, 1, 2].map(|c| data.positions.iter().map(|p| p[c]).sum::<f64>() / n);
+    let positions: Vec<u8> = data.positions.iter()
+        .flat_map(|p| [0, 1, 2].map(|c| (p[c] - center[c]) as f32))
+        .flat_map(f32::to_le_bytes)
+        .collect();
+    let colors: Vec<u8> = data.colors.iter().flatten().copied().collect();
+    let id = format!("pcdpy-plot-{:016x}", rand::random::<u64>());
+    format!(r#"<div id="{id}" style="width: 100%; height: {height}px;"></div>
+<script type="module">
+import * as THREE from "{THREE_URL}";
+import {{ OrbitControls }} from "{THREE_URL}/examples/jsm/controls/OrbitControls.js";
+const decode = (s) => Uint8Array.from(atob(s), (c) => c.charCodeAt(0)).buffer;
+const container = document.getElementById("{id}");
+const geometry = new THREE.BufferGeometry();
+geometry.setAttribute("position", new THREE.BufferAttribute(new Float32Array(decode("{positions}")), 3));
+geometry.setAttribute("color", new THREE.BufferAttribute(new Uint8Array(decode("{colors}")), 3, true));
+geometry.computeBoundingSphere();
+const scene = new THREE.Scene();
+scene.background = new THREE.Color(0x111111);
+scene.add(new THREE.Points(geometry, new THREE.PointsMaterial({{ size: {point_size}, vertexColors: true, sizeAttenuation: false }})));
+const center = geometry.boundingSphere.center;
+const radius = Math.max(geometry.boundingSphere.radius, 1e-3);
+const camera = new THREE.PerspectiveCamera(60, container.clientWidth / {height}, radius / 1000, radius * 100);
+camera.up.set(0, 0, 1);
+camera.position.set(center.x + 1.5 * radius, center.y - 1.5 * radius, center.z + radius);
+const renderer = new THREE.WebGLRenderer({{ antialias: true }});
+renderer.setPixelRatio(window.devicePixelRatio);
+renderer.setSize(container.clientWidth, {height});
+container.appendChild(renderer.domElement);
+const controls = new OrbitControls(camera, renderer.domElement);
+controls.target.copy(center);
+renderer.setAnimationLoop(() => {{ controls.update(); renderer.render(scene, camera); }});
+</script>"#, positions = base64(&positions), colors = base64(&colors))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{Dtype, FieldSchema, Metadata};
+    use ndarray::Array1;
+
+    #[test]
+    fn test_plot_data() {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("x", Dtype::F32, 1), ("y", Dtype::F32, 1), ("z", Dtype::F64, 1), ("rgb", Dtype::F32, 1)]),
+            width: 10,
+            height: 1,
+            npoints: 10,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        for i in 0..10 {
+            pc.fields.get_mut("x").unwrap().assign_row(i, &Array1::from(vec![i as f32]));
+            pc.fields.get_mut("z").unwrap().assign_row(i, &Array1::from(vec![if i == 9 { f64::NAN } else { i as f64 }]));
+            pc.fields.get_mut("rgb").unwrap().assign_row(i, &Array1::from(vec![crate::color::pack_rgb(i as u8, 2, 3)]));
+        }
+        let names = ["x", "y", "z"];
+
+        let data = pc.plot_data(names, 100, None).unwrap();
+        assert_eq!(data.positions[4], [4.0, 0.0, 4.0]);
+        assert_eq!((data.colors[0], data.colors[8], data.colors[9]), ([68, 1, 84], [253, 231, 37], NAN_COLOR));
+        let data = pc.plot_data(names, 4, Some("rgb")).unwrap();
+        assert_eq!(data.positions.len(), 4);
+        assert!(data.positions.iter().zip(&data.colors).all(|(p, c)| *c == [p[0] as u8, 2, 3]));
+        assert!(pc.plot_data(names, 4, Some("w")).is_err());
+
+        let html = plot_html(&data, 2.0, 400);
+        assert!(html.contains("size: 2, vertexColors: true") && html.contains("height: 400px;"));
+        assert_eq!([base64(b"Man"), base64(b"Ma"), base64(b"M"), base64(b"")], ["TWFu", "TWE=", "TQ==", ""]);
+    }
+}
@@ -0,0 +1,229 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use anyhow::Result;
+use crate::convert::{write_spilled_data, SpillFile};
+use crate::error::{ErrorKind, PcdError};
+use crate::io::{self, WriteOptions};
+use crate::metadata::{Encoding, Metadata};
+use crate::pointcloud::PointCloud;
+
+/// Width to which WIDTH and POINTS are padded in the header of ASCII and binary files, so
+/// that the final counts can be written over them: enough digits for any u64.
+const COUNT_WIDTH: usize = 20;
+
+/// Writes a PCD file incrementally, one chunk of points at a time, for clouds whose number of
+/// points is not known in advance (e.g. points recorded from a sensor). The file holds an
+/// unorganized cloud whose WIDTH and POINTS are filled in by `finish`.
+///
+/// ASCII and binary data are written to the file as chunks arrive. Compressed encodings lay
+/// the data out field by field, so each field is collected in a temporary file next to the
+/// output until `finish` compresses them into the file. binary_compressed and binary_lz4,
+/// which are a single compressed block, then hold the point data in memory.
+///
+/// A writer dropped without `finish` leaves an ASCII or binary file whose header has 0 points,
+/// and an empty compressed file.
+pub struct PcdWriter {
+    file: BufWriter<File>,
+    metadata: Metadata,
+    options: WriteOptions,
+    names: Vec<String>,
+    npoints: usize,
+    /// One temporary file per field, for compressed encodings.
+    spills: Vec<SpillFile>,
+}
+
+impl PcdWriter {
+    /// Creates the PCD file at `path` for points with the fields, viewpoint and encoding of
+    /// `md`, overridden by `options`. The width, height and number of points of `md` are
+    /// ignored.
+    pub fn create(path: &str, md: &Metadata, options: &WriteOptions) -> Result<Self> {
+        let mut md = Metadata { width: 0, height: 1, npoints: 0, ..md.clone() };
+        if options.skip_padding {
+            md.fields.0.retain(|f| !f.is_padding());
+        }
+        let md = options.apply(&md);
+        anyhow::ensure!(!options.pcl_compatible || md.encoding.is_pcl_compatible(),
+            "Encoding {} is a pcdpy extension that PCL cannot read", md.encoding.as_str());
+
+        let path = PathBuf::from(path);
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&path)?;
+        let spills = if md.encoding.is_compressed() {
+            (0..md.fields.len())
+                .map(|i| SpillFile::create(&path, &format!(".field{}.tmp", i)))
+                .collect::<Result<_>>()?
+        } else {
+            Vec::new()
+        };
+        let mut writer = Self {
+            names: md.fields.iter().map(|f| f.name.clone()).collect(),
+            file: BufWriter::new(file),
+            metadata: md,
+            options: options.clone(),
+            npoints: 0,
+            spills,
+        };
+        if !writer.metadata.encoding.is_compressed() {
+            writer.write_header()?;
+        }
+        Ok(writer)
+    }
+
+    /// Returns the metadata written in the header, without the final number of points.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Returns the number of points written so far.
+    pub fn written(&self) -> usize {
+        self.npoints
+    }
+
+    /// Appends the points of `chunk`, which must have the fields of the writer's metadata, with
+    /// the same types and counts, in any order. Other fields of `chunk` are not written.
+    pub fn write_chunk(&mut self, chunk: &PointCloud) -> Result<()> {
+        let chunk = chunk.select(&self.names)
+            .map_err(|e| PcdError::new(ErrorKind::SchemaMismatch, e.to_string()))?
+            .to_storage()?;
+        {
+            let md = chunk.metadata.read().unwrap();
+            anyhow::ensure!(md.fields.same_layout(&self.metadata.fields), PcdError::new(ErrorKind::SchemaMismatch,
+                format!("Chunk schema does not match the file:\n{}\nvs\n{}", md.fields, self.metadata.fields)));
+        }
+        match self.metadata.encoding {
+            Encoding::Ascii => io::write_ascii_data(&mut self.file, &chunk, &self.options)?,
+            Encoding::Binary => io::write_binary_data(&mut self.file, &chunk)?,
+            _ => {
+                let mut buf = Vec::new();
+                for (name, spill) in self.names.iter().zip(self.spills.iter_mut()) {
+                    buf.clear();
+                    chunk.fields[name].extend_le_bytes(&mut buf);
+                    spill.file.write_all(&buf)?;
+                }
+            }
+        }
+        self.npoints += chunk.len();
+        Ok(())
+    }
+
+    /// Completes the file: fills in the number of points of ASCII and binary files, or
+    /// compresses the collected data, and appends the checksum footer if
+    /// `WriteOptions::checksum` is set.
+    pub fn finish(mut self) -> Result<()> {
+        self.metadata.width = self.npoints;
+        self.metadata.npoints = self.npoints;
+        let data_start = if self.metadata.encoding.is_compressed() {
+            // Only now is the data size known, for large_compressed
+            self.metadata = self.options.apply(&self.metadata);
+            anyhow::ensure!(self.metadata.encoding != Encoding::BinaryCompressed || self.metadata.data_size() <= u32::MAX as usize,
+                "Point data of {} bytes is too large for binary_compressed encoding (at most {} bytes); \
+                enable large_compressed to write the binary_compressed_large extension", self.metadata.data_size(), u32::MAX);
+            let data_start = self.write_header()?;
+            for spill in &mut self.spills {
+                spill.file.seek(SeekFrom::Start(0))?;
+            }
+            let data = self.spills.iter()
+                .fold(Box::new(std::io::empty()) as Box<dyn Read + '_>, |data, spill| Box::new(data.chain(&spill.file)));
+            write_spilled_data(&mut self.file, BufReader::new(data), self.metadata.data_size(),
+                self.metadata.encoding, self.options.compression_level)?;
+            data_start
+        } else {
+            let data_start = self.write_header()?;
+            self.file.seek(SeekFrom::End(0))?;
+            data_start
+        };
+        if self.options.checksum {
+            // Read the data back rather than hashing it as it is written, since compressed
+            // sizes are written over their placeholders
+            let data_end = self.file.stream_position()?;
+            self.file.seek(SeekFrom::Start(data_start))?;
+            let mut hasher = crc32fast::Hasher::new();
+            let mut buf = vec![0u8; 1 << 16];
+            let mut data = Read::by_ref(self.file.get_mut()).take(data_end - data_start);
+            loop {
+                let n = data.read(&mut buf)?;
+                if n == 0 {
+                    break;
+                }
+                hasher.update(&buf[..n]);
+            }
+            self.file.seek(SeekFrom::Start(data_end))?;
+            io::write_checksum_footer(&mut self.file, hasher.finalize())?;
+        }
+        self.file.flush()?;
+        Ok(())
+    }
+
+    /// Writes the header at the start of the file, with the counts padded for ASCII and binary
+    /// data so that they can be updated in place. Returns the length of the header.
+    fn write_header(&mut self) -> Result<u64> {
+        let mut header = Vec::new();
+        io::write_header(&mut header, &self.metadata)?;
+        if !self.metadata.encoding.is_compressed() {
+            header = String::from_utf8(header)?.lines()
+                .map(|line| match line.split_once(' ') {
+                    Some((key @ ("WIDTH" | "POINTS"), count)) => format!("{} {:<COUNT_WIDTH$}\n", key, count),
+                    _ => format!("{}\n", line),
+                })
+                .collect::<String>()
+                .into_bytes();
+        }
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&header)?;
+        Ok(header.len() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array1;
+    use crate::metadata::{Dtype, FieldSchema};
+
+    fn test_cloud(offset: usize, npoints: usize) -> PointCloud {
+        let md = Metadata {
+            fields: FieldSchema::from_iter([("label", Dtype::U16, 2), ("x", Dtype::F32, 1)]),
+            width: npoints,
+            height: 1,
+            npoints,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::new(&md);
+        for i in 0..npoints {
+            let v = offset + i;
+            pc.fields.get_mut("x").unwrap().assign_row(i, &Array1::from(vec![v as f32 * 0.5]));
+            pc.fields.get_mut("label").unwrap().assign_row(i, &Array1::from(vec![v as u16, 7]));
+        }
+        pc
+    }
+
+    #[test]
+    fn test_pcd_writer() {
+        let schema = FieldSchema::from_iter([("x", Dtype::F32, 1), ("label", Dtype::U16, 2)]);
+        let md = Metadata { fields: schema.clone(), ..Metadata::default() };
+        for encoding in [Encoding::Ascii, Encoding::Binary, Encoding::BinaryCompressed, Encoding::BinaryCompressedLarge] {
+            let path = std::env::temp_dir().join(format!("pcdpy_test_writer_{}.pcd", encoding.as_str()));
+            let path = path.to_str().unwrap();
+            let options = WriteOptions { encoding: Some(encoding), checksum: true, ..WriteOptions::default() };
+            let mut writer = PcdWriter::create(path, &md, &options).unwrap();
+            for (offset, npoints) in [(0, 3), (3, 0), (3, 4)] {
+                writer.write_chunk(&test_cloud(offset, npoints)).unwrap();
+            }
+            assert_eq!(writer.written(), 7);
+            let err = writer.write_chunk(&test_cloud(0, 1).select(&["x".to_string()]).unwrap()).unwrap_err();
+            assert_eq!(ErrorKind::of(&err), Some(ErrorKind::SchemaMismatch));
+            writer.finish().unwrap();
+
+            let verify = io::ReadOptions { verify: true, ..io::ReadOptions::default() };
+            let mut reader = std::io::BufReader::new(File::open(path).unwrap());
+            let (pc, warnings) = PointCloud::from_pcd_reader_with(&mut reader, &verify).unwrap();
+            assert!(warnings.is_empty());
+            let expected = test_cloud(0, 7).select(&["x".to_string(), "label".to_string()]).unwrap();
+            assert_eq!(pc.fields, expected.fields, "{}", encoding.as_str());
+            let md = pc.metadata.read().unwrap();
+            assert_eq!((md.width, md.height, md.npoints, &md.fields), (7, 1, 7, &schema));
+            drop(md);
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
@@ -1 +1,15 @@
-from pcdpy._core import *
\ No newline at end of file
+import logging
+
+from ._core import AxisAlignedBoundingBox, CancellationToken, CancelledError, CustomMetadata, DataCorruptionError, FieldMeta, FrameReader, FrameWriter, HeaderError, IcpResult, InterchangeBuffer, InterchangeColumn, InterchangeDataFrame, KdTree, Metadata, Octree, OrientedBoundingBox, PcdError, PcdReader, PcdWarning, PcdWriter, PointCloud, PointIterator, Schema, SchemaMismatchError, UnsupportedDtypeError, ValidationReport, chamfer_distance, convert, get_field_aliases, get_log_level, hausdorff_distance, load_dir, open, read_metadata, register_icp, repair, set_auto_sync, set_field_aliases, set_log_level, set_repr_points, validate
+
+__all__ = ["AxisAlignedBoundingBox", "CancellationToken", "CancelledError", "CustomMetadata", "DataCorruptionError", "FieldMeta", "FrameReader", "FrameWriter", "HeaderError", "IcpResult", "InterchangeBuffer", "InterchangeColumn", "InterchangeDataFrame", "KdTree", "Metadata", "Octree", "OrientedBoundingBox", "PcdError", "PcdReader", "PcdWarning", "PcdWriter", "PointCloud", "PointIterator", "Schema", "SchemaMismatchError", "UnsupportedDtypeError", "ValidationReport", "chamfer_distance", "convert", "get_field_aliases", "get_log_level", "hausdorff_distance", "load_dir", "open", "read_metadata", "register_icp", "repair", "set_auto_sync", "set_field_aliases", "set_log_level", "set_repr_points", "validate"]
+
+# Read issues are logged on the "pcdpy" logger; stay silent unless the application configures logging
+logging.getLogger("pcdpy").addHandler(logging.NullHandler())
+
+try:
+    # only present when built with the "rosbag" feature
+    from ._core import BagReader, read_bag
+    __all__ += ["BagReader", "read_bag"]
+except ImportError:
+    pass
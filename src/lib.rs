@@ -1,12 +1,30 @@
 use pyo3::prelude::*;
 
-mod io;
-mod utils;
-mod metadata;
-mod fielddata;
-mod pointcloud;
+// The core modules are used under their pcd-core paths (e.g. `crate::pointcloud`)
+use pcd_core::{arith, batch, bbox, convert, distance, error, fielddata, frames, io, io_ply, io_ros, io_tiles, kdtree, metadata, octree, organized, pointcloud, progress, raster, registration, stats, transform, utils, validate, viewer, writer};
+#[cfg(feature = "arrow")]
+use pcd_core::io_arrow;
+#[cfg(feature = "rosbag")]
+use pcd_core::io_bag;
+#[cfg(feature = "remote")]
+use pcd_core::io_remote;
+
+/// The Rust API, for Rust code that depends on this crate.
+pub use pcd_core;
+
+mod pyfielddata;
 mod pymetadata;
 mod pypointcloud;
+mod pyreader;
+mod pykdtree;
+mod pyoctree;
+mod pyprogress;
+mod pylog;
+mod pyerrors;
+mod pyregistration;
+mod pybbox;
+mod pyvalidate;
+mod pyinterchange;
 
 /// A Python module implemented in Rust. The name of this function must match
 /// the `lib.name` setting in the `Cargo.toml`, else Python will not be able to
@@ -14,6 +32,50 @@ mod pypointcloud;
 #[pymodule]
 fn _core(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<pymetadata::PyMetadata>()?;
+    m.add_class::<pymetadata::PyFieldMeta>()?;
+    m.add_class::<pymetadata::PySchema>()?;
+    m.add_class::<pymetadata::PyCustomMetadata>()?;
     m.add_class::<pypointcloud::PyPointCloud>()?;
+    m.add_class::<pypointcloud::PyPointIterator>()?;
+    m.add_class::<pyreader::PyPcdReader>()?;
+    m.add_class::<pyreader::PyPcdWriter>()?;
+    m.add_class::<pyreader::PyFrameWriter>()?;
+    m.add_class::<pyreader::PyFrameReader>()?;
+    m.add_class::<pykdtree::PyKdTree>()?;
+    m.add_class::<pyoctree::PyOctree>()?;
+    m.add_class::<pyregistration::PyIcpResult>()?;
+    m.add_class::<pybbox::PyAxisAlignedBoundingBox>()?;
+    m.add_class::<pybbox::PyOrientedBoundingBox>()?;
+    m.add_class::<pyvalidate::PyValidationReport>()?;
+    m.add_class::<pyinterchange::PyInterchangeDataFrame>()?;
+    m.add_class::<pyinterchange::PyInterchangeColumn>()?;
+    m.add_class::<pyinterchange::PyInterchangeBuffer>()?;
+    m.add_class::<pyprogress::PyCancellationToken>()?;
+    m.add("CancelledError", m.py().get_type::<pyprogress::CancelledError>())?;
+    m.add("PcdWarning", m.py().get_type::<pylog::PcdWarning>())?;
+    m.add("PcdError", m.py().get_type::<pyerrors::PcdError>())?;
+    m.add("HeaderError", m.py().get_type::<pyerrors::HeaderError>())?;
+    m.add("DataCorruptionError", m.py().get_type::<pyerrors::DataCorruptionError>())?;
+    m.add("SchemaMismatchError", m.py().get_type::<pyerrors::SchemaMismatchError>())?;
+    m.add("UnsupportedDtypeError", m.py().get_type::<pyerrors::UnsupportedDtypeError>())?;
+    #[cfg(feature = "rosbag")]
+    m.add_class::<pyreader::PyBagReader>()?;
+    m.add_function(wrap_pyfunction!(pyreader::open, m)?)?;
+    m.add_function(wrap_pyfunction!(pyreader::load_dir, m)?)?;
+    m.add_function(wrap_pyfunction!(pyreader::convert, m)?)?;
+    #[cfg(feature = "rosbag")]
+    m.add_function(wrap_pyfunction!(pyreader::read_bag, m)?)?;
+    m.add_function(wrap_pyfunction!(pymetadata::read_metadata, m)?)?;
+    m.add_function(wrap_pyfunction!(pymetadata::set_field_aliases, m)?)?;
+    m.add_function(wrap_pyfunction!(pymetadata::get_field_aliases, m)?)?;
+    m.add_function(wrap_pyfunction!(pyregistration::register_icp, m)?)?;
+    m.add_function(wrap_pyfunction!(pyregistration::chamfer_distance, m)?)?;
+    m.add_function(wrap_pyfunction!(pyregistration::hausdorff_distance, m)?)?;
+    m.add_function(wrap_pyfunction!(pypointcloud::set_auto_sync, m)?)?;
+    m.add_function(wrap_pyfunction!(pypointcloud::set_repr_points, m)?)?;
+    m.add_function(wrap_pyfunction!(pylog::set_log_level, m)?)?;
+    m.add_function(wrap_pyfunction!(pylog::get_log_level, m)?)?;
+    m.add_function(wrap_pyfunction!(pyvalidate::validate, m)?)?;
+    m.add_function(wrap_pyfunction!(pyvalidate::repair, m)?)?;
     Ok(())
 }
@@ -0,0 +1,77 @@
+use pyo3::prelude::*;
+use pyo3::types::{PyIterator, PyTuple};
+use numpy::{PyArray1, PyArray2, ToPyArray};
+use ndarray::{Array1, Array2};
+use crate::bbox::{AxisAlignedBox, OrientedBox};
+
+#[pyclass(name = "AxisAlignedBoundingBox", frozen)]
+pub struct PyAxisAlignedBoundingBox {
+    pub aabb: AxisAlignedBox,
+}
+
+#[pymethods]
+impl PyAxisAlignedBoundingBox {
+    /// Minimum (x, y, z) corner
+    #[getter]
+    fn min_bound<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f64>> {
+        Array1::from(self.aabb.min_bound.to_vec()).to_pyarray(py)
+    }
+
+    /// Maximum (x, y, z) corner
+    #[getter]
+    fn max_bound<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f64>> {
+        Array1::from(self.aabb.max_bound.to_vec()).to_pyarray(py)
+    }
+
+    /// Center of the box
+    #[getter]
+    fn center<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f64>> {
+        Array1::from(self.aabb.center().to_vec()).to_pyarray(py)
+    }
+
+    /// Side lengths of the box
+    #[getter]
+    fn extent<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f64>> {
+        Array1::from(self.aabb.extent().to_vec()).to_pyarray(py)
+    }
+
+    /// Iterate over (min_bound, max_bound), so that the box can be unpacked
+    fn __iter__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyIterator>> {
+        PyTuple::new(py, [self.min_bound(py), self.max_bound(py)])?.try_iter()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("AxisAlignedBoundingBox(min_bound={:?}, max_bound={:?})", self.aabb.min_bound, self.aabb.max_bound)
+    }
+}
+
+#[pyclass(name = "OrientedBoundingBox", frozen)]
+pub struct PyOrientedBoundingBox {
+    pub obb: OrientedBox,
+}
+
+#[pymethods]
+impl PyOrientedBoundingBox {
+    /// Center of the box
+    #[getter]
+    fn center<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f64>> {
+        Array1::from(self.obb.center.to_vec()).to_pyarray(py)
+    }
+
+    /// 3x3 rotation whose columns are the box axes, from largest to smallest variance
+    #[getter]
+    fn rotation<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<f64>> {
+        let r = self.obb.rotation;
+        Array2::from_shape_fn((3, 3), |(i, j)| r[i][j]).to_pyarray(py)
+    }
+
+    /// Side lengths of the box along each of its axes
+    #[getter]
+    fn extent<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f64>> {
+        Array1::from(self.obb.extent.to_vec()).to_pyarray(py)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("OrientedBoundingBox(center={:?}, extent={:?})", self.obb.center, self.obb.extent)
+    }
+}
@@ -0,0 +1,39 @@
+use std::fmt::Display;
+use pyo3::prelude::*;
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyIOError, PyValueError};
+use crate::error::ErrorKind;
+
+create_exception!(pcdpy, PcdError, PyException, "Base class of the errors raised for invalid PCD data, schemas or dtypes.");
+create_exception!(pcdpy, HeaderError, PcdError, "Raised when a file header is invalid or unsupported.");
+create_exception!(pcdpy, DataCorruptionError, PcdError, "Raised when point data is truncated or does not match its header.");
+create_exception!(pcdpy, SchemaMismatchError, PcdError, "Raised when fields or clouds do not have the expected names, types or sizes.");
+create_exception!(pcdpy, UnsupportedDtypeError, PcdError, "Raised when a data type has no PCD equivalent.");
+
+/// Convert an error from the Rust layer to the `PcdError` subclass of its kind (with its
+/// context), or with `fallback` if it has no known kind
+pub fn to_pyerr(e: anyhow::Error, fallback: impl FnOnce(anyhow::Error) -> PyErr) -> PyErr {
+    let msg = || format!("{:#}", e);
+    match ErrorKind::of(&e) {
+        Some(ErrorKind::Header) => HeaderError::new_err(msg()),
+        Some(ErrorKind::DataCorruption) => DataCorruptionError::new_err(msg()),
+        Some(ErrorKind::SchemaMismatch) => SchemaMismatchError::new_err(msg()),
+        Some(ErrorKind::UnsupportedDtype) => UnsupportedDtypeError::new_err(msg()),
+        None => fallback(e),
+    }
+}
+
+/// `to_pyerr` falling back to an IOError
+pub fn io_error(e: anyhow::Error) -> PyErr {
+    to_pyerr(e, |e| PyIOError::new_err(format!("{:#}", e)))
+}
+
+/// `to_pyerr` falling back to a ValueError
+pub fn value_error(e: anyhow::Error) -> PyErr {
+    to_pyerr(e, |e| PyValueError::new_err(format!("{:#}", e)))
+}
+
+/// Error for a NumPy dtype with no PCD equivalent
+pub fn unsupported_dtype(dtype: impl Display) -> PyErr {
+    UnsupportedDtypeError::new_err(format!("Unsupported dtype: {}", dtype))
+}
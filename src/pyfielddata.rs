@@ -0,0 +1,217 @@
+use num_traits::{NumCast, ToPrimitive};
+use pyo3::{exceptions::PyValueError, prelude::*, IntoPyObjectExt};
+use ndarray::{ArcArray2, Array2, Axis};
+use numpy::{PyArray1, PyArray2, PyArray3, Element, PyReadonlyArray1, PyReadonlyArray2};
+use half::f16;
+use crate::fielddata::FieldData;
+use crate::metadata::Dtype;
+use crate::organized::{to_image, ImageOrder};
+use crate::pyerrors::{unsupported_dtype, value_error};
+
+/// A trait for elements that can be used in numpy conversions.
+pub trait NumpyElement: Element + NumCast {}
+impl<T: Element + NumCast> NumpyElement for T {}
+
+/// A trait for converting an object into a shaped Python object.
+pub trait IntoPyObjectShaped<'py> {
+    type Target;
+    type Output;
+    type Error;
+
+    fn into_pyobject_shaped(self, py: Python<'py>, width: usize, height: usize, order: ImageOrder)
+        -> Result<Self::Output, Self::Error>;
+}
+
+/// Convert every value of `arr` to `T`, returning an error for the first value that
+/// cannot be represented.
+fn checked_convert<S, T>(arr: &ArcArray2<S>) -> PyResult<Array2<T>>
+where
+    S: ToPrimitive + Copy + std::fmt::Display,
+    T: NumCast,
+{
+    let mut values = Vec::with_capacity(arr.len());
+    for &x in arr.iter() {
+        values.push(T::from(x).ok_or_else(|| PyValueError::new_err(format!(
+            "Value {} cannot be represented in the requested dtype", x
+        )))?);
+    }
+    Array2::from_shape_vec(arr.raw_dim(), values)
+        .map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// The values of a boolean field as bools.
+fn to_bools(arr: &ArcArray2<u8>) -> Array2<bool> {
+    arr.mapv(|v| v != 0)
+}
+
+/// Extract a 2D NumPy array of `T`, or a 1D one of shape (n,) as (n, 1).
+fn extract_array2<T: Element + Clone>(pyarray: &Bound<'_, PyAny>) -> PyResult<ArcArray2<T>> {
+    if let Ok(arr) = pyarray.extract::<PyReadonlyArray1<T>>() {
+        return Ok(arr.as_array().insert_axis(Axis(1)).to_shared());
+    }
+    Ok(pyarray.extract::<PyReadonlyArray2<T>>()?.as_array().to_shared())
+}
+
+/// Conversions between `FieldData` and NumPy arrays.
+pub trait PyFieldData: Sized {
+    /// Create a field of `dtype` from a 2D NumPy array of that dtype, or a 1D one for a field
+    /// with a count of 1.
+    fn from_pyarray(pyarray: &Bound<'_, PyAny>, dtype: Dtype) -> PyResult<Self>;
+
+    /// Create a field from a 1D or 2D NumPy array of any supported dtype, keeping that dtype.
+    fn from_pyarray_any(pyarray: &Bound<'_, PyAny>) -> PyResult<Self>;
+
+    /// Return a NumPy array of the specified type.
+    /// Returns a ValueError if a value cannot be represented in that type.
+    fn to_pyarray<'py, T: NumpyElement>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray2<T>>>;
+
+    /// Return row `row_idx` as a 1D NumPy array of length count, copying only that row.
+    fn row_to_pyarray<'py>(&self, py: Python<'py>, row_idx: usize) -> PyResult<Bound<'py, PyAny>>;
+
+    /// Return a copy of the field as a 2D NumPy array of its own dtype.
+    fn to_pyobject<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>>;
+
+    /// Return a read-only NumPy array that shares this field's buffer without copying.
+    /// The array observes the field as it was at the time of the call.
+    fn to_pyarray_view<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>>;
+}
+
+/// Owns a shared handle to a field's buffer on behalf of numpy arrays that borrow it.
+/// The handle is never mutated, so the borrowed memory stays valid for the lifetime of the
+/// container even if the originating PointCloud replaces or modifies the field
+/// (modifications copy-on-write into a new buffer).
+#[pyclass(frozen)]
+struct FieldBuffer {
+    data: FieldData,
+}
+
+impl PyFieldData for FieldData {
+    fn from_pyarray(pyarray: &Bound<'_, PyAny>, dtype: Dtype) -> PyResult<Self> {
+        match dtype {
+            Dtype::Bool => Ok(FieldData::Bool(extract_array2::<bool>(pyarray)?.mapv(|v| v as u8).into_shared())),
+            Dtype::U8 => Ok(FieldData::U8(extract_array2::<u8>(pyarray)?)),
+            Dtype::U16 => Ok(FieldData::U16(extract_array2::<u16>(pyarray)?)),
+            Dtype::U32 => Ok(FieldData::U32(extract_array2::<u32>(pyarray)?)),
+            Dtype::U64 => Ok(FieldData::U64(extract_array2::<u64>(pyarray)?)),
+            Dtype::I8 => Ok(FieldData::I8(extract_array2::<i8>(pyarray)?)),
+            Dtype::I16 => Ok(FieldData::I16(extract_array2::<i16>(pyarray)?)),
+            Dtype::I32 => Ok(FieldData::I32(extract_array2::<i32>(pyarray)?)),
+            Dtype::I64 => Ok(FieldData::I64(extract_array2::<i64>(pyarray)?)),
+            Dtype::F16 => Ok(FieldData::F16(extract_array2::<f16>(pyarray)?)),
+            Dtype::F32 => Ok(FieldData::F32(extract_array2::<f32>(pyarray)?)),
+            Dtype::F64 => Ok(FieldData::F64(extract_array2::<f64>(pyarray)?)),
+        }
+    }
+
+    fn from_pyarray_any(pyarray: &Bound<'_, PyAny>) -> PyResult<Self> {
+        let dtype_name: String = pyarray.getattr("dtype")?.getattr("name")?.extract()?;
+        let dtype = Dtype::from_numpy_dtype(&dtype_name)
+            .ok_or_else(|| unsupported_dtype(dtype_name))?;
+        Self::from_pyarray(pyarray, dtype)
+    }
+
+    fn to_pyarray<'py, T: NumpyElement>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray2<T>>> {
+        match self {
+            FieldData::Bool(arr) | FieldData::U8(arr) => Ok(PyArray2::from_owned_array(py, checked_convert(arr)?)),
+            FieldData::U16(arr) => Ok(PyArray2::from_owned_array(py, checked_convert(arr)?)),
+            FieldData::U32(arr) => Ok(PyArray2::from_owned_array(py, checked_convert(arr)?)),
+            FieldData::U64(arr) => Ok(PyArray2::from_owned_array(py, checked_convert(arr)?)),
+            FieldData::I8(arr) => Ok(PyArray2::from_owned_array(py, checked_convert(arr)?)),
+            FieldData::I16(arr) => Ok(PyArray2::from_owned_array(py, checked_convert(arr)?)),
+            FieldData::I32(arr) => Ok(PyArray2::from_owned_array(py, checked_convert(arr)?)),
+            FieldData::I64(arr) => Ok(PyArray2::from_owned_array(py, checked_convert(arr)?)),
+            FieldData::F16(arr) => Ok(PyArray2::from_owned_array(py, checked_convert(arr)?)),
+            FieldData::F32(arr) => Ok(PyArray2::from_owned_array(py, checked_convert(arr)?)),
+            FieldData::F64(arr) => Ok(PyArray2::from_owned_array(py, checked_convert(arr)?)),
+        }
+    }
+
+    fn row_to_pyarray<'py>(&self, py: Python<'py>, row_idx: usize) -> PyResult<Bound<'py, PyAny>> {
+        if row_idx >= self.npoints() {
+            return Err(PyValueError::new_err(format!("Row {} is out of bounds for {} points", row_idx, self.npoints())));
+        }
+        match self {
+            FieldData::Bool(arr) => Ok(PyArray1::from_owned_array(py, arr.row(row_idx).mapv(|v| v != 0)).into_any()),
+            FieldData::U8(arr) => Ok(PyArray1::from_array(py, &arr.row(row_idx)).into_any()),
+            FieldData::U16(arr) => Ok(PyArray1::from_array(py, &arr.row(row_idx)).into_any()),
+            FieldData::U32(arr) => Ok(PyArray1::from_array(py, &arr.row(row_idx)).into_any()),
+            FieldData::U64(arr) => Ok(PyArray1::from_array(py, &arr.row(row_idx)).into_any()),
+            FieldData::I8(arr) => Ok(PyArray1::from_array(py, &arr.row(row_idx)).into_any()),
+            FieldData::I16(arr) => Ok(PyArray1::from_array(py, &arr.row(row_idx)).into_any()),
+            FieldData::I32(arr) => Ok(PyArray1::from_array(py, &arr.row(row_idx)).into_any()),
+            FieldData::I64(arr) => Ok(PyArray1::from_array(py, &arr.row(row_idx)).into_any()),
+            FieldData::F16(arr) => Ok(PyArray1::from_array(py, &arr.row(row_idx)).into_any()),
+            FieldData::F32(arr) => Ok(PyArray1::from_array(py, &arr.row(row_idx)).into_any()),
+            FieldData::F64(arr) => Ok(PyArray1::from_array(py, &arr.row(row_idx)).into_any()),
+        }
+    }
+
+    fn to_pyobject<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        match self {
+            FieldData::Bool(arr) => Ok(PyArray2::from_owned_array(py, to_bools(arr)).into_bound_py_any(py)?),
+            FieldData::U8(arr)   => Ok(PyArray2::from_array(py, arr).into_bound_py_any(py)?),
+            FieldData::U16(arr) => Ok(PyArray2::from_array(py, arr).into_bound_py_any(py)?),
+            FieldData::U32(arr) => Ok(PyArray2::from_array(py, arr).into_bound_py_any(py)?),
+            FieldData::U64(arr) => Ok(PyArray2::from_array(py, arr).into_bound_py_any(py)?),
+            FieldData::I8(arr)   => Ok(PyArray2::from_array(py, arr).into_bound_py_any(py)?),
+            FieldData::I16(arr) => Ok(PyArray2::from_array(py, arr).into_bound_py_any(py)?),
+            FieldData::I32(arr) => Ok(PyArray2::from_array(py, arr).into_bound_py_any(py)?),
+            FieldData::I64(arr) => Ok(PyArray2::from_array(py, arr).into_bound_py_any(py)?),
+            FieldData::F16(arr) => Ok(PyArray2::from_array(py, arr).into_bound_py_any(py)?),
+            FieldData::F32(arr) => Ok(PyArray2::from_array(py, arr).into_bound_py_any(py)?),
+            FieldData::F64(arr) => Ok(PyArray2::from_array(py, arr).into_bound_py_any(py)?),
+        }
+    }
+
+    fn to_pyarray_view<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let container = Bound::new(py, FieldBuffer { data: self.clone() })?;
+        // SAFETY: `container` holds its own reference to the buffer and never mutates it,
+        // and numpy keeps `container` alive for as long as the returned array exists.
+        let array = match &container.get().data {
+            FieldData::Bool(arr) => unsafe { PyArray2::borrow_from_array(arr, container.clone().into_any()).into_any() },
+            FieldData::U8(arr) => unsafe { PyArray2::borrow_from_array(arr, container.clone().into_any()).into_any() },
+            FieldData::U16(arr) => unsafe { PyArray2::borrow_from_array(arr, container.clone().into_any()).into_any() },
+            FieldData::U32(arr) => unsafe { PyArray2::borrow_from_array(arr, container.clone().into_any()).into_any() },
+            FieldData::U64(arr) => unsafe { PyArray2::borrow_from_array(arr, container.clone().into_any()).into_any() },
+            FieldData::I8(arr) => unsafe { PyArray2::borrow_from_array(arr, container.clone().into_any()).into_any() },
+            FieldData::I16(arr) => unsafe { PyArray2::borrow_from_array(arr, container.clone().into_any()).into_any() },
+            FieldData::I32(arr) => unsafe { PyArray2::borrow_from_array(arr, container.clone().into_any()).into_any() },
+            FieldData::I64(arr) => unsafe { PyArray2::borrow_from_array(arr, container.clone().into_any()).into_any() },
+            FieldData::F16(arr) => unsafe { PyArray2::borrow_from_array(arr, container.clone().into_any()).into_any() },
+            FieldData::F32(arr) => unsafe { PyArray2::borrow_from_array(arr, container.clone().into_any()).into_any() },
+            FieldData::F64(arr) => unsafe { PyArray2::borrow_from_array(arr, container.clone().into_any()).into_any() },
+        };
+        let kwargs = pyo3::types::PyDict::new(py);
+        kwargs.set_item("write", false)?;
+        array.call_method("setflags", (), Some(&kwargs))?;
+        if let FieldData::Bool(_) = self {
+            // Bools are held as bytes of 0 or 1, so their read-only buffer can be viewed as
+            // NumPy bools
+            return array.call_method1("view", ("bool",));
+        }
+        Ok(array)
+    }
+}
+
+impl<'py> IntoPyObjectShaped<'py> for &FieldData {
+    type Target = PyAny;
+    type Output = Bound<'py, Self::Target>;
+    type Error = PyErr;
+
+    fn into_pyobject_shaped(self, py: Python<'py>, width: usize, height: usize, order: ImageOrder) -> PyResult<Self::Output> {
+        match self {
+            FieldData::Bool(arr) => PyArray3::from_owned_array(py, to_image(to_bools(arr).view(), width, height, order).map_err(value_error)?).into_bound_py_any(py),
+            FieldData::U8(arr) => PyArray3::from_owned_array(py, to_image(arr.view(), width, height, order).map_err(value_error)?).into_bound_py_any(py),
+            FieldData::U16(arr) => PyArray3::from_owned_array(py, to_image(arr.view(), width, height, order).map_err(value_error)?).into_bound_py_any(py),
+            FieldData::U32(arr) => PyArray3::from_owned_array(py, to_image(arr.view(), width, height, order).map_err(value_error)?).into_bound_py_any(py),
+            FieldData::U64(arr) => PyArray3::from_owned_array(py, to_image(arr.view(), width, height, order).map_err(value_error)?).into_bound_py_any(py),
+            FieldData::I8(arr) => PyArray3::from_owned_array(py, to_image(arr.view(), width, height, order).map_err(value_error)?).into_bound_py_any(py),
+            FieldData::I16(arr) => PyArray3::from_owned_array(py, to_image(arr.view(), width, height, order).map_err(value_error)?).into_bound_py_any(py),
+            FieldData::I32(arr) => PyArray3::from_owned_array(py, to_image(arr.view(), width, height, order).map_err(value_error)?).into_bound_py_any(py),
+            FieldData::I64(arr) => PyArray3::from_owned_array(py, to_image(arr.view(), width, height, order).map_err(value_error)?).into_bound_py_any(py),
+            FieldData::F16(arr) => PyArray3::from_owned_array(py, to_image(arr.view(), width, height, order).map_err(value_error)?).into_bound_py_any(py),
+            FieldData::F32(arr) => PyArray3::from_owned_array(py, to_image(arr.view(), width, height, order).map_err(value_error)?).into_bound_py_any(py),
+            FieldData::F64(arr) => PyArray3::from_owned_array(py, to_image(arr.view(), width, height, order).map_err(value_error)?).into_bound_py_any(py),
+        }
+    }
+}
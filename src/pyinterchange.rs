@@ -0,0 +1,310 @@
+use pyo3::exceptions::{PyIndexError, PyKeyError, PyNotImplementedError, PyRuntimeError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use crate::fielddata::FieldData;
+use crate::metadata::Dtype;
+use crate::pointcloud::PointCloud;
+
+/// Kinds of the dataframe interchange protocol (its `DtypeKind` enum)
+const KIND_INT: i32 = 0;
+const KIND_UINT: i32 = 1;
+const KIND_FLOAT: i32 = 2;
+const KIND_BOOL: i32 = 20;
+
+/// Null representations of the dataframe interchange protocol (its `ColumnNullType` enum)
+const NULL_NON_NULLABLE: i32 = 0;
+const NULL_USE_NAN: i32 = 1;
+
+/// The DLPack device type of host memory
+const DLPACK_CPU: i32 = 1;
+
+/// The (kind, bit width, Arrow format string, byte order) of a column of `dtype`
+fn interchange_dtype(dtype: Dtype) -> (i32, usize, &'static str, &'static str) {
+    let (kind, format) = match dtype {
+        Dtype::Bool => (KIND_BOOL, "b"),
+        Dtype::U8 => (KIND_UINT, "C"),
+        Dtype::U16 => (KIND_UINT, "S"),
+        Dtype::U32 => (KIND_UINT, "I"),
+        Dtype::U64 => (KIND_UINT, "L"),
+        Dtype::I8 => (KIND_INT, "c"),
+        Dtype::I16 => (KIND_INT, "s"),
+        Dtype::I32 => (KIND_INT, "i"),
+        Dtype::I64 => (KIND_INT, "l"),
+        Dtype::F16 => (KIND_FLOAT, "e"),
+        Dtype::F32 => (KIND_FLOAT, "f"),
+        Dtype::F64 => (KIND_FLOAT, "g"),
+    };
+    (kind, dtype.get_size() * 8, format, "=")
+}
+
+/// The address of the first value of `field`, and whether its values are contiguous
+fn data_ptr(field: &FieldData) -> (usize, bool) {
+    match field {
+        FieldData::Bool(arr) | FieldData::U8(arr) => (arr.as_ptr() as usize, arr.is_standard_layout()),
+        FieldData::U16(arr) => (arr.as_ptr() as usize, arr.is_standard_layout()),
+        FieldData::U32(arr) => (arr.as_ptr() as usize, arr.is_standard_layout()),
+        FieldData::U64(arr) => (arr.as_ptr() as usize, arr.is_standard_layout()),
+        FieldData::I8(arr) => (arr.as_ptr() as usize, arr.is_standard_layout()),
+        FieldData::I16(arr) => (arr.as_ptr() as usize, arr.is_standard_layout()),
+        FieldData::I32(arr) => (arr.as_ptr() as usize, arr.is_standard_layout()),
+        FieldData::I64(arr) => (arr.as_ptr() as usize, arr.is_standard_layout()),
+        FieldData::F16(arr) => (arr.as_ptr() as usize, arr.is_standard_layout()),
+        FieldData::F32(arr) => (arr.as_ptr() as usize, arr.is_standard_layout()),
+        FieldData::F64(arr) => (arr.as_ptr() as usize, arr.is_standard_layout()),
+    }
+}
+
+/// Split the `len` rows from `start` into `n_chunks` (by default 1) ranges of (start, len)
+fn split_rows(start: usize, len: usize, n_chunks: Option<usize>) -> PyResult<Vec<(usize, usize)>> {
+    let n = n_chunks.unwrap_or(1);
+    if n == 0 {
+        return Err(PyValueError::new_err("n_chunks must be positive"));
+    }
+    let size = len.div_ceil(n);
+    Ok((0..n).map(|i| {
+        let chunk_start = (i * size).min(len);
+        (start + chunk_start, size.min(len - chunk_start))
+    }).collect())
+}
+
+/// A PointCloud exposed through the dataframe interchange protocol (`__dataframe__`), with one
+/// column per field, or `name_0`, `name_1`, ... for the values of a field with a count above 1
+#[pyclass(name = "InterchangeDataFrame", frozen)]
+pub struct PyInterchangeDataFrame {
+    /// Contiguous (npoints, 1) values of each column
+    columns: Vec<(String, FieldData)>,
+    start: usize,
+    len: usize,
+    nan_as_null: bool,
+    allow_copy: bool,
+}
+
+impl PyInterchangeDataFrame {
+    /// Expose the fields of `pc`, sharing the memory of contiguous fields with a count of 1.
+    /// Other fields are copied, unless `allow_copy` is false, which raises a RuntimeError
+    pub fn new(pc: &PointCloud, nan_as_null: bool, allow_copy: bool) -> PyResult<Self> {
+        let md = pc.metadata.read().unwrap();
+        let mut columns = Vec::new();
+        for field_meta in md.fields.iter() {
+            let field = pc.fields.get(&field_meta.name)
+                .ok_or_else(|| PyValueError::new_err(format!("Field '{}' exists in metadata but not in data", field_meta.name)))?;
+            if field.count() == 1 && data_ptr(field).1 {
+                columns.push((field_meta.name.clone(), field.clone()));
+                continue;
+            }
+            if !allow_copy {
+                return Err(PyRuntimeError::new_err(format!(
+                    "Field '{}' is not stored as contiguous columns, and allow_copy is False", field_meta.name)));
+            }
+            for i in 0..field.count() {
+                let name = if field.count() == 1 { field_meta.name.clone() } else { format!("{}_{}", field_meta.name, i) };
+                columns.push((name, field.columns(i, i + 1)));
+            }
+        }
+        Ok(Self { columns, start: 0, len: md.npoints, nan_as_null, allow_copy })
+    }
+
+    fn column(&self, index: usize) -> PyInterchangeColumn {
+        PyInterchangeColumn {
+            data: self.columns[index].1.clone(),
+            start: self.start,
+            len: self.len,
+            nan_as_null: self.nan_as_null,
+        }
+    }
+
+    fn with_columns(&self, indices: Vec<usize>) -> Self {
+        Self {
+            columns: indices.into_iter().map(|i| self.columns[i].clone()).collect(),
+            start: self.start,
+            len: self.len,
+            nan_as_null: self.nan_as_null,
+            allow_copy: self.allow_copy,
+        }
+    }
+
+    fn column_index(&self, name: &str) -> PyResult<usize> {
+        self.columns.iter().position(|(n, _)| n == name)
+            .ok_or_else(|| PyKeyError::new_err(format!("No column named '{}'", name)))
+    }
+
+    fn check_index(&self, index: usize) -> PyResult<usize> {
+        if index >= self.columns.len() {
+            return Err(PyIndexError::new_err(format!("Column {} is out of range for {} columns", index, self.columns.len())));
+        }
+        Ok(index)
+    }
+}
+
+#[pymethods]
+impl PyInterchangeDataFrame {
+    /// Version of the interchange protocol
+    #[classattr]
+    fn version() -> i32 {
+        0
+    }
+
+    #[pyo3(signature = (nan_as_null=false, allow_copy=true))]
+    fn __dataframe__(&self, nan_as_null: bool, allow_copy: bool) -> Self {
+        Self { nan_as_null, allow_copy, ..self.with_columns((0..self.columns.len()).collect()) }
+    }
+
+    #[getter]
+    fn metadata<'py>(&self, py: Python<'py>) -> Bound<'py, PyDict> {
+        PyDict::new(py)
+    }
+
+    fn num_columns(&self) -> usize {
+        self.columns.len()
+    }
+
+    fn num_rows(&self) -> usize {
+        self.len
+    }
+
+    fn num_chunks(&self) -> usize {
+        1
+    }
+
+    fn column_names(&self) -> Vec<String> {
+        self.columns.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    fn get_column(&self, i: usize) -> PyResult<PyInterchangeColumn> {
+        Ok(self.column(self.check_index(i)?))
+    }
+
+    fn get_column_by_name(&self, name: &str) -> PyResult<PyInterchangeColumn> {
+        Ok(self.column(self.column_index(name)?))
+    }
+
+    fn get_columns(&self) -> Vec<PyInterchangeColumn> {
+        (0..self.columns.len()).map(|i| self.column(i)).collect()
+    }
+
+    fn select_columns(&self, indices: Vec<usize>) -> PyResult<Self> {
+        let indices = indices.into_iter().map(|i| self.check_index(i)).collect::<PyResult<_>>()?;
+        Ok(self.with_columns(indices))
+    }
+
+    fn select_columns_by_name(&self, names: Vec<String>) -> PyResult<Self> {
+        let indices = names.iter().map(|name| self.column_index(name)).collect::<PyResult<_>>()?;
+        Ok(self.with_columns(indices))
+    }
+
+    /// Split the rows into `n_chunks` dataframes sharing the same memory
+    #[pyo3(signature = (n_chunks=None))]
+    fn get_chunks(&self, n_chunks: Option<usize>) -> PyResult<Vec<Self>> {
+        Ok(split_rows(self.start, self.len, n_chunks)?.into_iter()
+            .map(|(start, len)| Self { start, len, ..self.with_columns((0..self.columns.len()).collect()) })
+            .collect())
+    }
+}
+
+/// A column of an `InterchangeDataFrame`
+#[pyclass(name = "InterchangeColumn", frozen)]
+pub struct PyInterchangeColumn {
+    data: FieldData,
+    start: usize,
+    len: usize,
+    nan_as_null: bool,
+}
+
+#[pymethods]
+impl PyInterchangeColumn {
+    fn size(&self) -> usize {
+        self.len
+    }
+
+    /// Offset of the first value in the data buffer, which already starts at it
+    #[getter]
+    fn offset(&self) -> usize {
+        0
+    }
+
+    #[getter]
+    fn dtype(&self) -> (i32, usize, &'static str, &'static str) {
+        interchange_dtype(self.data.dtype())
+    }
+
+    #[getter]
+    fn describe_categorical(&self) -> PyResult<()> {
+        Err(PyTypeError::new_err("Point cloud columns are not categorical"))
+    }
+
+    /// Columns have no missing values, except NaN with `nan_as_null`
+    #[getter]
+    fn describe_null(&self) -> (i32, Option<i32>) {
+        if self.uses_nan() { (NULL_USE_NAN, None) } else { (NULL_NON_NULLABLE, None) }
+    }
+
+    /// 0, or None (not computed) when NaN values are missing values
+    #[getter]
+    fn null_count(&self) -> Option<usize> {
+        (!self.uses_nan()).then_some(0)
+    }
+
+    #[getter]
+    fn metadata<'py>(&self, py: Python<'py>) -> Bound<'py, PyDict> {
+        PyDict::new(py)
+    }
+
+    fn num_chunks(&self) -> usize {
+        1
+    }
+
+    #[pyo3(signature = (n_chunks=None))]
+    fn get_chunks(&self, n_chunks: Option<usize>) -> PyResult<Vec<Self>> {
+        Ok(split_rows(self.start, self.len, n_chunks)?.into_iter()
+            .map(|(start, len)| Self { data: self.data.clone(), start, len, nan_as_null: self.nan_as_null })
+            .collect())
+    }
+
+    fn get_buffers<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let buffers = PyDict::new(py);
+        let data = PyInterchangeBuffer { data: self.data.clone(), start: self.start, len: self.len };
+        buffers.set_item("data", (data, self.dtype()))?;
+        buffers.set_item("validity", py.None())?;
+        buffers.set_item("offsets", py.None())?;
+        Ok(buffers)
+    }
+}
+
+impl PyInterchangeColumn {
+    fn uses_nan(&self) -> bool {
+        self.nan_as_null && matches!(self.data.dtype(), Dtype::F16 | Dtype::F32 | Dtype::F64)
+    }
+}
+
+/// The contiguous memory of an `InterchangeColumn`, kept alive by this object
+#[pyclass(name = "InterchangeBuffer", frozen)]
+pub struct PyInterchangeBuffer {
+    data: FieldData,
+    start: usize,
+    len: usize,
+}
+
+#[pymethods]
+impl PyInterchangeBuffer {
+    #[getter]
+    fn bufsize(&self) -> usize {
+        self.len * self.data.dtype().get_size()
+    }
+
+    #[getter]
+    fn ptr(&self) -> usize {
+        data_ptr(&self.data).0 + self.start * self.data.dtype().get_size()
+    }
+
+    fn __dlpack__(&self) -> PyResult<()> {
+        Err(PyNotImplementedError::new_err("DLPack export is not supported"))
+    }
+
+    fn __dlpack_device__(&self) -> (i32, Option<i32>) {
+        (DLPACK_CPU, None)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("InterchangeBuffer(bufsize={}, ptr={:#x})", self.bufsize(), self.ptr())
+    }
+}
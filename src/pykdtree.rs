@@ -0,0 +1,90 @@
+use pyo3::prelude::*;
+use pyo3::exceptions::PyValueError;
+use numpy::{PyArray1, PyArray2, PyArrayMethods, PyReadonlyArray2, PyUntypedArrayMethods, ToPyArray};
+use ndarray::{Array2, Axis, Zip};
+use crate::kdtree::KdTree;
+
+/// (distances, indices) returned by `KdTree.query`.
+type KnnResult<'py> = (Bound<'py, PyArray2<f64>>, Bound<'py, PyArray2<i64>>);
+/// (distances, indices) returned by `KdTree.query_radius`, one array per query point.
+type RadiusResult<'py> = (Vec<Bound<'py, PyArray1<f64>>>, Vec<Bound<'py, PyArray1<i64>>>);
+
+#[pyclass(name = "KdTree", frozen)]
+pub struct PyKdTree {
+    pub tree: KdTree,
+}
+
+#[pymethods]
+impl PyKdTree {
+    fn __len__(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// Find the `k` nearest neighbors of each query point.
+    /// Returns (distances, indices), each of shape (npoints, k), closest first.
+    #[pyo3(signature = (points, k=1))]
+    fn query<'py>(&self, points: &Bound<'py, PyAny>, k: usize) -> PyResult<KnnResult<'py>> {
+        let py = points.py();
+        if k == 0 || k > self.tree.len() {
+            return Err(PyValueError::new_err(format!("k must be between 1 and {}, got {}", self.tree.len(), k)));
+        }
+        let points = as_points(points)?;
+        let points = points.as_array();
+
+        let mut distances = Array2::<f64>::zeros((points.nrows(), k));
+        let mut indices = Array2::<i64>::zeros((points.nrows(), k));
+        py.allow_threads(|| {
+            Zip::from(distances.rows_mut())
+                .and(indices.rows_mut())
+                .and(points.rows())
+                .par_for_each(|mut d, mut i, p| {
+                    for (j, (index, dist)) in self.tree.query(&[p[0], p[1], p[2]], k).into_iter().enumerate() {
+                        d[j] = dist;
+                        i[j] = index as i64;
+                    }
+                });
+        });
+        Ok((distances.to_pyarray(py), indices.to_pyarray(py)))
+    }
+
+    /// Find all neighbors within distance `r` of each query point.
+    /// Returns (distances, indices) as lists with one 1D array per query point, closest first.
+    fn query_radius<'py>(&self, points: &Bound<'py, PyAny>, r: f64) -> PyResult<RadiusResult<'py>> {
+        let py = points.py();
+        if r.is_nan() || r < 0.0 {
+            return Err(PyValueError::new_err(format!("Radius must be non-negative, got {}", r)));
+        }
+        let points = as_points(points)?;
+        let points = points.as_array();
+
+        let mut results = vec![Vec::new(); points.nrows()];
+        py.allow_threads(|| {
+            Zip::from(&mut results)
+                .and(points.axis_iter(Axis(0)))
+                .par_for_each(|res, p| *res = self.tree.query_radius(&[p[0], p[1], p[2]], r));
+        });
+        let distances = results.iter()
+            .map(|res| PyArray1::from_iter(py, res.iter().map(|n| n.1)))
+            .collect();
+        let indices = results.iter()
+            .map(|res| PyArray1::from_iter(py, res.iter().map(|n| n.0 as i64)))
+            .collect();
+        Ok((distances, indices))
+    }
+}
+
+// Helper functions //
+
+/// Convert an array-like of shape (3,) or (npoints, 3) to a float64 array of shape (npoints, 3).
+fn as_points<'py>(points: &Bound<'py, PyAny>) -> PyResult<PyReadonlyArray2<'py, f64>> {
+    let np = points.py().import("numpy")?;
+    let arr = np.call_method1("asarray", (points, "float64"))?;
+    let ndim: usize = arr.getattr("ndim")?.extract()?;
+    let arr = if ndim == 1 { arr.call_method1("reshape", (1, -1))? } else { arr };
+    let arr = arr.downcast_into::<PyArray2<f64>>()
+        .map_err(|_| PyValueError::new_err("Query points must have shape (3,) or (npoints, 3)"))?;
+    if arr.shape()[1] != 3 {
+        return Err(PyValueError::new_err(format!("Query points must have 3 columns, got {}", arr.shape()[1])));
+    }
+    Ok(arr.readonly())
+}
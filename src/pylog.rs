@@ -0,0 +1,66 @@
+use std::sync::atomic::{AtomicI32, Ordering};
+use pyo3::prelude::*;
+use pyo3::create_exception;
+use pyo3::exceptions::{PyUserWarning, PyValueError};
+
+create_exception!(pcdpy, PcdWarning, PyUserWarning, "Warning category for anomalies recovered while reading PCD data.");
+
+/// Level of the `logging` module at or above which read issues are reported.
+const WARNING: i32 = 30;
+static LOG_LEVEL: AtomicI32 = AtomicI32::new(WARNING);
+
+const LEVEL_NAMES: [(&str, i32); 6] = [("NOTSET", 0), ("DEBUG", 10), ("INFO", 20), ("WARNING", 30), ("ERROR", 40), ("CRITICAL", 50)];
+
+/// Set the level below which pcdpy stops reporting issues: an int or a `logging` level name
+/// ("DEBUG", "INFO", "WARNING", "ERROR", "CRITICAL"). Read issues are warnings, so a level
+/// above "WARNING" silences both the `PcdWarning` and the "pcdpy" logger records. The
+/// "pcdpy" logger is also set to the level. Returns the previous level
+#[pyfunction]
+pub fn set_log_level(py: Python<'_>, level: &Bound<'_, PyAny>) -> PyResult<i32> {
+    let level = match level.extract::<i32>() {
+        Ok(level) => level,
+        Err(_) => {
+            let name: String = level.extract()?;
+            LEVEL_NAMES.iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(&name))
+                .map(|&(_, level)| level)
+                .ok_or_else(|| PyValueError::new_err(format!("Unknown log level '{}'", name)))?
+        }
+    };
+    logger(py)?.call_method1("setLevel", (level,))?;
+    Ok(LOG_LEVEL.swap(level, Ordering::Relaxed))
+}
+
+/// Return the level set with `set_log_level`
+#[pyfunction]
+pub fn get_log_level() -> i32 {
+    LOG_LEVEL.load(Ordering::Relaxed)
+}
+
+fn logger(py: Python<'_>) -> PyResult<Bound<'_, PyAny>> {
+    py.import("logging")?.call_method1("getLogger", ("pcdpy",))
+}
+
+/// Report the issues recovered while reading PCD data (header defects, point counts that
+/// were fixed up, values substituted in non-strict mode): each one is logged on the "pcdpy"
+/// logger, and a single `PcdWarning` summarizes them. Nothing is reported if the level set
+/// with `set_log_level` is above WARNING
+pub fn warn_read_issues(py: Python<'_>, warnings: &[String]) -> PyResult<()> {
+    const MAX_LISTED: usize = 10;
+    if warnings.is_empty() || get_log_level() > WARNING {
+        return Ok(());
+    }
+    let logger = logger(py)?;
+    for warning in warnings {
+        logger.call_method1("warning", ("%s", warning))?;
+    }
+    let mut msg = format!("{} issue(s) while reading PCD data:", warnings.len());
+    for warning in warnings.iter().take(MAX_LISTED) {
+        msg.push_str(&format!("\n - {}", warning));
+    }
+    if warnings.len() > MAX_LISTED {
+        msg.push_str(&format!("\n ... and {} more", warnings.len() - MAX_LISTED));
+    }
+    let msg = std::ffi::CString::new(msg)?;
+    PyErr::warn(py, &py.get_type::<PcdWarning>(), &msg, 1)
+}
@@ -1,17 +1,227 @@
 use pyo3::prelude::*;
-use pyo3::exceptions::PyValueError;
-use crate::metadata::{SharedMetadata, Encoding};
+use std::collections::HashMap;
+use pyo3::types::{IntoPyDict, PyDict, PyIterator, PyList};
+use pyo3::exceptions::{PyKeyError, PyValueError};
+use numpy::{PyArray2, ToPyArray};
+use ndarray::Array2;
+use crate::metadata::{check_custom_entry, Dtype, Encoding, FieldMeta, FieldSchema, Metadata, Semantic, SharedMetadata, Viewpoint};
+use crate::pointcloud::PointCloud;
+use crate::pyerrors::{io_error, unsupported_dtype, value_error};
+use crate::pypointcloud::PyPointCloud;
 
-#[pyclass(name = "Metadata")]
+/// Picklable representation of Metadata: (fields as (name, numpy dtype, count), width, height,
+/// viewpoint, npoints, encoding, version, custom entries). Field units, descriptions and
+/// semantic tags are stored with the custom entries, as in a PCD header.
+pub type MetadataState = (Vec<(String, String, usize)>, usize, usize, Vec<f32>, usize, String, String, Vec<(String, String)>);
+
+/// Description of a single field: its name, NumPy dtype and count (values per point), and
+/// optionally its unit (e.g. "m"), a description and a semantic tag ("position", "normal",
+/// "color" or "time"), which are saved as header comments.
+#[pyclass(name = "FieldMeta", module = "pcdpy._core", frozen)]
+#[derive(Clone)]
+pub struct PyFieldMeta {
+    pub inner: FieldMeta,
+}
+
+#[pymethods]
+impl PyFieldMeta {
+    #[new]
+    #[pyo3(signature = (name, dtype, count=1, *, unit=None, description=None, semantic=None))]
+    fn new(name: String, dtype: &str, count: usize, unit: Option<&str>, description: Option<&str>, semantic: Option<&str>) -> PyResult<Self> {
+        let inner = FieldMeta {
+            unit: unit.map(str::to_string),
+            description: description.map(str::to_string),
+            semantic: semantic.map(parse_semantic).transpose()?,
+            ..field_meta(name, dtype, count)?
+        };
+        for (key, value) in inner.info_entries() {
+            check_custom_entry(&key, &value).map_err(value_error)?;
+        }
+        Ok(PyFieldMeta { inner })
+    }
+
+    #[getter]
+    fn name(&self) -> String {
+        self.inner.name.clone()
+    }
+
+    #[getter]
+    fn dtype(&self) -> &'static str {
+        self.inner.dtype.as_numpy_dtype()
+    }
+
+    #[getter]
+    fn count(&self) -> usize {
+        self.inner.count
+    }
+
+    #[getter]
+    fn unit(&self) -> Option<String> {
+        self.inner.unit.clone()
+    }
+
+    #[getter]
+    fn description(&self) -> Option<String> {
+        self.inner.description.clone()
+    }
+
+    #[getter]
+    fn semantic(&self) -> Option<&str> {
+        self.inner.semantic.as_ref().map(Semantic::as_str)
+    }
+
+    fn __eq__(&self, other: PyRef<'_, Self>) -> bool {
+        self.inner == other.inner
+    }
+
+    fn __repr__(&self) -> String {
+        let mut repr = format!("FieldMeta(name='{}', dtype='{}', count={}", self.inner.name, self.inner.dtype.as_numpy_dtype(), self.inner.count);
+        if let Some(unit) = &self.inner.unit {
+            repr.push_str(&format!(", unit='{}'", unit));
+        }
+        if let Some(description) = &self.inner.description {
+            repr.push_str(&format!(", description='{}'", description));
+        }
+        if let Some(semantic) = self.semantic() {
+            repr.push_str(&format!(", semantic='{}'", semantic));
+        }
+        repr + ")"
+    }
+}
+
+/// An ordered list of fields, used to describe the fields of a new PointCloud.
+#[pyclass(name = "Schema", module = "pcdpy._core")]
+#[derive(Clone)]
+pub struct PySchema {
+    pub inner: FieldSchema,
+}
+
+#[pymethods]
+impl PySchema {
+    /// Create a schema from a list of FieldMeta objects or (name, dtype, count) tuples
+    #[new]
+    #[pyo3(signature = (fields=None))]
+    fn new(fields: Option<&Bound<'_, PyAny>>) -> PyResult<Self> {
+        let inner = match fields {
+            Some(fields) => extract_schema(fields)?,
+            None => FieldSchema::new(),
+        };
+        Ok(PySchema { inner })
+    }
+
+    /// Append a field and return the schema, so that calls can be chained
+    #[pyo3(signature = (name, dtype, count=1))]
+    fn add<'py>(mut slf: PyRefMut<'py, Self>, name: String, dtype: &str, count: usize) -> PyResult<PyRefMut<'py, Self>> {
+        if slf.inner.iter().any(|f| f.name == name) {
+            return Err(PyValueError::new_err(format!("Duplicate field name: {}", name)));
+        }
+        let field = field_meta(name, dtype, count)?;
+        slf.inner.0.push(field);
+        Ok(slf)
+    }
+
+    #[getter]
+    fn names(&self) -> Vec<String> {
+        self.inner.iter().map(|f| f.name.clone()).collect()
+    }
+
+    #[getter]
+    fn fields(&self) -> Vec<PyFieldMeta> {
+        self.inner.iter().map(|f| PyFieldMeta { inner: f.clone() }).collect()
+    }
+
+    fn __len__(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn __eq__(&self, other: PyRef<'_, Self>) -> bool {
+        self.inner == other.inner
+    }
+
+    fn __repr__(&self) -> String {
+        let fields = self.inner.iter()
+            .map(|f| format!("('{}', '{}', {})", f.name, f.dtype.as_numpy_dtype(), f.count))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("Schema([{}])", fields)
+    }
+}
+
+#[pyclass(name = "Metadata", module = "pcdpy._core")]
 pub struct PyMetadata {
     pub inner: SharedMetadata,
+    /// PointCloud whose metadata this is, whose field data the schema must keep matching
+    pub cloud: Option<Py<PyPointCloud>>,
 }
 
 #[pymethods]
 impl PyMetadata {
+    /// Create metadata for a cloud of `width` x `height` points (an empty, unorganized cloud
+    /// by default). `fields` is a list of FieldMeta objects or (name, dtype, count) tuples,
+    /// where dtype is a NumPy dtype name (e.g. "float32") and count defaults to 1.
+    /// `fields` may also be a Schema. `viewpoint` is (tx, ty, tz, qw, qx, qy, qz), and
+    /// `custom` a dict of custom entries (see `custom`)
+    #[new]
+    #[pyo3(signature = (fields=None, width=0, height=1, viewpoint=None, encoding=None, version=None, custom=None))]
+    fn new(
+        fields: Option<&Bound<'_, PyAny>>,
+        width: usize,
+        height: usize,
+        viewpoint: Option<(f32, f32, f32, f32, f32, f32, f32)>,
+        encoding: Option<&str>,
+        version: Option<&str>,
+        custom: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<Self> {
+        let mut md = PyMetadata::from_metadata(Metadata {
+            width,
+            height,
+            npoints: width * height,
+            ..Metadata::default()
+        });
+        if let Some(fields) = fields {
+            md.set_schema(fields)?;
+        }
+        if let Some(viewpoint) = viewpoint {
+            md.set_viewpoint(viewpoint);
+        }
+        if let Some(encoding) = encoding {
+            md.set_encoding(encoding)?;
+        }
+        if let Some(version) = version {
+            md.set_version(version)?;
+        }
+        if let Some(custom) = custom {
+            md.set_custom(custom)?;
+        }
+        Ok(md)
+    }
+
+    /// Return an independent copy of this Metadata
+    fn __copy__(&self) -> Self {
+        PyMetadata::from_metadata(Metadata::from_shared(self.inner.clone()))
+    }
+
+    fn __deepcopy__(&self, _memo: &Bound<'_, PyAny>) -> Self {
+        self.__copy__()
+    }
+
+    fn __eq__(&self, other: PyRef<'_, Self>) -> bool {
+        std::sync::Arc::ptr_eq(&self.inner, &other.inner)
+            || *self.inner.read().unwrap() == *other.inner.read().unwrap()
+    }
+
+    fn __getstate__(&self) -> MetadataState {
+        metadata_to_state(&self.inner.read().unwrap())
+    }
+
+    fn __setstate__(&mut self, state: MetadataState) -> PyResult<()> {
+        *self.inner.write().unwrap() = metadata_from_state(state)?;
+        Ok(())
+    }
+
     fn __repr__(&self) -> String {
         let md = self.inner.read().unwrap();
-        format!("PointCloud Metadata\n Fields:\n{}\n Points: {}, Width: {}, Height: {}\n Viewpoint: {}\n Encoding: {}\n Version: {}",
+        let mut repr = format!("PointCloud Metadata\n Fields:\n{}\n Points: {}, Width: {}, Height: {}\n Viewpoint: {}\n Encoding: {}\n Version: {}",
             md.fields,
             md.npoints,
             md.width,
@@ -19,7 +229,12 @@ impl PyMetadata {
             md.viewpoint,
             md.encoding.as_str(),
             md.version,
-        )
+        );
+        if !md.custom.is_empty() {
+            let entries = md.custom.iter().map(|(k, v)| format!("{}: {}", k, v)).collect::<Vec<_>>();
+            repr.push_str(&format!("\n Custom: {}", entries.join(", ")));
+        }
+        repr
     }
 
     #[getter]
@@ -40,6 +255,97 @@ impl PyMetadata {
         Ok(())
     }
 
+    /// The fields as FieldMeta objects. Setting it replaces the whole schema, from FieldMeta
+    /// objects or (name, dtype, count) tuples, or from a Schema. On the metadata of a
+    /// PointCloud, the new schema must match the cloud's field data
+    #[getter]
+    fn get_schema(&self) -> Vec<PyFieldMeta> {
+        let md = self.inner.read().unwrap();
+        md.fields.iter().map(|f| PyFieldMeta { inner: f.clone() }).collect()
+    }
+
+    #[setter]
+    fn set_schema(&mut self, fields: &Bound<'_, PyAny>) -> PyResult<()> {
+        let schema = extract_schema(fields)?;
+        let mut md = self.inner.write().unwrap();
+        self.check_cloud(fields.py(), &md, &schema)?;
+        md.fields = schema;
+        Ok(())
+    }
+
+    /// Return the FieldMeta of the field `name`
+    fn field(&self, name: &str) -> PyResult<PyFieldMeta> {
+        let md = self.inner.read().unwrap();
+        md.fields.iter()
+            .find(|f| f.name == name)
+            .map(|f| PyFieldMeta { inner: f.clone() })
+            .ok_or_else(|| PyKeyError::new_err(format!("Field '{}' not found", name)))
+    }
+
+    /// Set the unit (e.g. "m" or "mm"), description and semantic tag ("position", "normal",
+    /// "color" or "time") of the field `name`, clearing those not given. They are saved as
+    /// `# unit.<name>: ...` (and so on) header comments and read back on load
+    #[pyo3(signature = (name, *, unit=None, description=None, semantic=None))]
+    fn set_field_info(&mut self, name: &str, unit: Option<&str>, description: Option<&str>, semantic: Option<&str>) -> PyResult<()> {
+        let semantic = semantic.map(parse_semantic).transpose()?;
+        let mut md = self.inner.write().unwrap();
+        if !md.fields.iter().any(|f| f.name == name) {
+            return Err(PyKeyError::new_err(format!("Field '{}' not found", name)));
+        }
+        md.set_field_info(name, unit, description, semantic)
+            .map_err(value_error)
+    }
+
+    /// The NumPy dtype name of each field. On the metadata of a PointCloud, the dtypes set
+    /// must match the cloud's field data (use `PointCloud.astype` to convert fields)
+    #[getter]
+    fn get_dtypes(&self) -> Vec<&'static str> {
+        let md = self.inner.read().unwrap();
+        md.fields.iter().map(|f| f.dtype.as_numpy_dtype()).collect()
+    }
+
+    #[setter]
+    fn set_dtypes(&mut self, py: Python<'_>, dtypes: Vec<String>) -> PyResult<()> {
+        let mut md = self.inner.write().unwrap();
+        if dtypes.len() != md.fields.len() {
+            return Err(PyValueError::new_err("Length mismatch with existing field schema"));
+        }
+        let dtypes = dtypes.iter().map(|d| parse_dtype(d)).collect::<PyResult<Vec<_>>>()?;
+        let mut fields = md.fields.clone();
+        for (field, dtype) in (&mut fields).into_iter().zip(dtypes) {
+            field.dtype = dtype;
+        }
+        self.check_cloud(py, &md, &fields)?;
+        md.fields = fields;
+        Ok(())
+    }
+
+    /// The count (number of values per point) of each field. On the metadata of a
+    /// PointCloud, the counts set must match the cloud's field data
+    #[getter]
+    fn get_counts(&self) -> Vec<usize> {
+        let md = self.inner.read().unwrap();
+        md.fields.iter().map(|f| f.count).collect()
+    }
+
+    #[setter]
+    fn set_counts(&mut self, py: Python<'_>, counts: Vec<usize>) -> PyResult<()> {
+        let mut md = self.inner.write().unwrap();
+        if counts.len() != md.fields.len() {
+            return Err(PyValueError::new_err("Length mismatch with existing field schema"));
+        }
+        if counts.contains(&0) {
+            return Err(PyValueError::new_err("Field counts must be positive"));
+        }
+        let mut fields = md.fields.clone();
+        for (field, count) in (&mut fields).into_iter().zip(counts) {
+            field.count = count;
+        }
+        self.check_cloud(py, &md, &fields)?;
+        md.fields = fields;
+        Ok(())
+    }
+
     #[getter]
     fn get_width(&self) -> usize {
         self.inner.read().unwrap().width
@@ -91,6 +397,80 @@ impl PyMetadata {
         md.viewpoint.qz = value.6;
     }
 
+    /// Return the viewpoint as a 4x4 matrix mapping sensor coordinates to world coordinates
+    fn viewpoint_to_matrix<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray2<f64>>> {
+        let m = self.inner.read().unwrap().viewpoint.to_matrix()
+            .map_err(value_error)?;
+        Ok(Array2::from_shape_fn((4, 4), |(i, j)| m[i][j]).to_pyarray(py))
+    }
+
+    /// Set the viewpoint from a rigid 4x4 transform (a rotation and a translation)
+    fn viewpoint_from_matrix(&mut self, matrix: [[f64; 4]; 4]) -> PyResult<()> {
+        let viewpoint = Viewpoint::from_matrix(&matrix)
+            .map_err(value_error)?;
+        self.inner.write().unwrap().viewpoint = viewpoint;
+        Ok(())
+    }
+
+    /// Apply a rigid 4x4 transform after the viewpoint, e.g. the result of aligning this
+    /// scan with `register_icp`, so that the viewpoint becomes `matrix @ viewpoint`
+    fn compose_viewpoint(&mut self, matrix: [[f64; 4]; 4]) -> PyResult<()> {
+        let mut md = self.inner.write().unwrap();
+        let viewpoint = Viewpoint::from_matrix(&matrix)
+            .and_then(|vp| vp.compose(&md.viewpoint))
+            .map_err(value_error)?;
+        md.viewpoint = viewpoint;
+        Ok(())
+    }
+
+    /// Return the 4x4 transform mapping coordinates in the sensor frame of this scan to the
+    /// sensor frame of the scan described by `other`, computed from the two viewpoints
+    fn relative_viewpoint<'py>(&self, py: Python<'py>, other: PyRef<'_, Self>) -> PyResult<Bound<'py, PyArray2<f64>>> {
+        let viewpoint = self.inner.read().unwrap().viewpoint.clone();
+        let m = other.inner.read().unwrap().viewpoint.inverse()
+            .and_then(|inv| inv.compose(&viewpoint))
+            .and_then(|vp| vp.to_matrix())
+            .map_err(value_error)?;
+        Ok(Array2::from_shape_fn((4, 4), |(i, j)| m[i][j]).to_pyarray(py))
+    }
+
+    /// User key-value entries (e.g. a sensor id or a calibration hash), saved in order as
+    /// `# key: value` comment lines at the top of the PCD header and read back on load. This
+    /// is a live mapping: `metadata.custom["sensor"] = "lidar0"` updates the metadata. Keys
+    /// cannot contain whitespace or ':', and values are stored as their str()
+    #[getter]
+    fn get_custom(&self) -> PyCustomMetadata {
+        PyCustomMetadata { inner: self.inner.clone() }
+    }
+
+    /// Replace the custom entries with those of a dict
+    #[setter]
+    fn set_custom(&mut self, entries: &Bound<'_, PyAny>) -> PyResult<()> {
+        let mut custom = Metadata::default();
+        for item in entries.call_method0("items")?.try_iter()? {
+            let (key, value): (String, Bound<'_, PyAny>) = item?.extract()?;
+            custom.set_custom(&key, &value.str()?.to_cow()?)
+                .map_err(value_error)?;
+        }
+        self.inner.write().unwrap().custom = custom.custom;
+        Ok(())
+    }
+
+    #[getter]
+    fn get_version(&self) -> String {
+        self.inner.read().unwrap().version.clone()
+    }
+
+    /// Set the PCD header version ("0.7", or "0.6" to write legacy headers without VIEWPOINT)
+    #[setter]
+    fn set_version(&mut self, val: &str) -> PyResult<()> {
+        if !crate::utils::SUPPORTED_VERSIONS.contains(&val) {
+            return Err(PyValueError::new_err(format!("Unsupported PCD version: {}", val)));
+        }
+        self.inner.write().unwrap().version = val.to_string();
+        Ok(())
+    }
+
     #[getter]
     fn get_encoding(&self) -> String {
         self.inner.read().unwrap().encoding.as_str().to_string()
@@ -103,4 +483,228 @@ impl PyMetadata {
             .ok_or_else(|| PyValueError::new_err("Invalid encoding value"))?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// The custom entries of a Metadata, as a mapping of strings to strings (see
+/// `Metadata.custom`). Changes are made to the Metadata itself
+#[pyclass(name = "CustomMetadata", module = "pcdpy._core", mapping)]
+pub struct PyCustomMetadata {
+    inner: SharedMetadata,
+}
+
+#[pymethods]
+impl PyCustomMetadata {
+    fn __getitem__(&self, key: &str) -> PyResult<String> {
+        self.inner.read().unwrap().custom_value(key)
+            .map(str::to_string)
+            .ok_or_else(|| PyKeyError::new_err(key.to_string()))
+    }
+
+    fn __setitem__(&self, key: &str, value: &Bound<'_, PyAny>) -> PyResult<()> {
+        let value = value.str()?;
+        self.inner.write().unwrap().set_custom(key, &value.to_cow()?)
+            .map_err(value_error)
+    }
+
+    fn __delitem__(&self, key: &str) -> PyResult<()> {
+        self.inner.write().unwrap().remove_custom(key)
+            .map(|_| ())
+            .ok_or_else(|| PyKeyError::new_err(key.to_string()))
+    }
+
+    fn __len__(&self) -> usize {
+        self.inner.read().unwrap().custom.len()
+    }
+
+    fn __contains__(&self, key: &str) -> bool {
+        self.inner.read().unwrap().custom_value(key).is_some()
+    }
+
+    fn __iter__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyIterator>> {
+        PyList::new(py, self.keys())?.try_iter()
+    }
+
+    /// Keys of the entries, in order
+    fn keys(&self) -> Vec<String> {
+        self.inner.read().unwrap().custom.iter().map(|(k, _)| k.clone()).collect()
+    }
+
+    /// Values of the entries, in order
+    fn values(&self) -> Vec<String> {
+        self.inner.read().unwrap().custom.iter().map(|(_, v)| v.clone()).collect()
+    }
+
+    /// (key, value) pairs of the entries, in order
+    fn items(&self) -> Vec<(String, String)> {
+        self.inner.read().unwrap().custom.clone()
+    }
+
+    /// Value of `key`, or `default` if there is no such entry
+    #[pyo3(signature = (key, default=None))]
+    fn get(&self, key: &str, default: Option<String>) -> Option<String> {
+        self.inner.read().unwrap().custom_value(key).map(str::to_string).or(default)
+    }
+
+    /// Equal to a CustomMetadata or dict with the same entries, in any order
+    fn __eq__(&self, other: &Bound<'_, PyAny>) -> bool {
+        let other: Option<HashMap<String, String>> = match other.downcast::<Self>() {
+            Ok(other) => Some(other.borrow().items().into_iter().collect()),
+            Err(_) => other.extract().ok(),
+        };
+        other.is_some_and(|other| other == self.items().into_iter().collect())
+    }
+
+    fn __repr__(&self, py: Python<'_>) -> PyResult<String> {
+        Ok(format!("CustomMetadata({})", self.items().into_py_dict(py)?.repr()?))
+    }
+}
+
+impl PyMetadata {
+    /// Wrap metadata that is not shared with any PointCloud
+    pub fn from_metadata(md: Metadata) -> Self {
+        PyMetadata {
+            inner: std::sync::Arc::new(std::sync::RwLock::new(md)),
+            cloud: None,
+        }
+    }
+
+    /// Fail with a SchemaMismatchError if `fields` would not match the field data of the
+    /// PointCloud this metadata belongs to. `md` is the current metadata.
+    fn check_cloud(&self, py: Python<'_>, md: &Metadata, fields: &FieldSchema) -> PyResult<()> {
+        let Some(cloud) = &self.cloud else {
+            return Ok(());
+        };
+        let candidate = PointCloud {
+            fields: cloud.borrow(py).pc.fields.clone(),
+            metadata: std::sync::Arc::new(std::sync::RwLock::new(Metadata { fields: fields.clone(), ..md.clone() })),
+        };
+        candidate.check_pointcloud()
+            .map_err(value_error)
+    }
+}
+
+fn parse_dtype(dtype: &str) -> PyResult<Dtype> {
+    Dtype::from_numpy_dtype(dtype)
+        .ok_or_else(|| unsupported_dtype(dtype))
+}
+
+fn parse_semantic(semantic: &str) -> PyResult<Semantic> {
+    Semantic::from_str(semantic)
+        .ok_or_else(|| PyValueError::new_err(format!("Invalid semantic tag '{}', expected 'position', 'normal', 'color' or 'time'", semantic)))
+}
+
+fn field_meta(name: String, dtype: &str, count: usize) -> PyResult<FieldMeta> {
+    if name.is_empty() {
+        return Err(PyValueError::new_err("Field name cannot be empty"));
+    }
+    if count == 0 {
+        return Err(PyValueError::new_err(format!("Field '{}' count must be positive", name)));
+    }
+    Ok(FieldMeta::new(name, parse_dtype(dtype)?, count))
+}
+
+/// Extract a field description from a FieldMeta or a (name, dtype) or (name, dtype, count) tuple
+fn extract_field_meta(obj: &Bound<'_, PyAny>) -> PyResult<FieldMeta> {
+    if let Ok(field) = obj.downcast::<PyFieldMeta>() {
+        return Ok(field.get().inner.clone());
+    }
+    if let Ok((name, dtype, count)) = obj.extract::<(String, String, usize)>() {
+        return field_meta(name, &dtype, count);
+    }
+    if let Ok((name, dtype)) = obj.extract::<(String, String)>() {
+        return field_meta(name, &dtype, 1);
+    }
+    Err(PyValueError::new_err("Fields must be FieldMeta objects or (name, dtype, count) tuples"))
+}
+
+/// Extract a schema from a Schema or a list of field descriptions (see `extract_field_meta`)
+pub fn extract_schema(obj: &Bound<'_, PyAny>) -> PyResult<FieldSchema> {
+    if let Ok(schema) = obj.downcast::<PySchema>() {
+        return Ok(schema.borrow().inner.clone());
+    }
+    let fields = obj.extract::<Vec<Bound<'_, PyAny>>>()?
+        .iter()
+        .map(extract_field_meta)
+        .collect::<PyResult<Vec<_>>>()?;
+    for (i, field) in fields.iter().enumerate() {
+        if fields[..i].iter().any(|f| f.name == field.name) {
+            return Err(PyValueError::new_err(format!("Duplicate field name: {}", field.name)));
+        }
+    }
+    Ok(FieldSchema(fields))
+}
+
+/// Convert Metadata to its picklable representation
+pub fn metadata_to_state(md: &Metadata) -> MetadataState {
+    (
+        md.fields.iter().map(|f| (f.name.clone(), f.dtype.as_numpy_dtype().to_string(), f.count)).collect(),
+        md.width,
+        md.height,
+        md.viewpoint.to_vec(),
+        md.npoints,
+        md.encoding.as_str().to_string(),
+        md.version.clone(),
+        md.custom.iter().cloned().chain(md.fields.iter().flat_map(|f| f.info_entries())).collect(),
+    )
+}
+
+/// Rebuild Metadata from its picklable representation
+pub fn metadata_from_state(state: MetadataState) -> PyResult<Metadata> {
+    let (fields, width, height, viewpoint, npoints, encoding, version, custom) = state;
+    let fields = fields.into_iter()
+        .map(|(name, dtype, count)| Ok(FieldMeta::new(name, parse_dtype(&dtype)?, count)))
+        .collect::<PyResult<Vec<FieldMeta>>>()?;
+    if viewpoint.len() != 7 {
+        return Err(PyValueError::new_err(format!("Viewpoint must have 7 values, got {}", viewpoint.len())));
+    }
+    let encoding = Encoding::from_str(&encoding)
+        .ok_or_else(|| PyValueError::new_err(format!("Invalid encoding value: {}", encoding)))?;
+    let mut md = Metadata {
+        fields: FieldSchema(fields),
+        width,
+        height,
+        viewpoint: Viewpoint::from(viewpoint),
+        npoints,
+        encoding,
+        version,
+        custom,
+    };
+    md.take_field_info();
+    Ok(md)
+}
+
+/// Read only the header of a PCD file and return its Metadata.
+/// Recovered header defects are reported as a PcdWarning.
+#[pyfunction]
+pub fn read_metadata(py: Python<'_>, path: &str) -> PyResult<PyMetadata> {
+    let (md, warnings) = crate::utils::read_metadata(path)
+        .map_err(io_error)?;
+    crate::pylog::warn_read_issues(py, &warnings)?;
+    Ok(PyMetadata::from_metadata(md))
+}
+
+/// Rename fields when reading PCD headers from now on, given a dict mapping names found in
+/// files to the names to use (e.g. {"X": "x"}). Replaces the previous aliases; pass an empty
+/// dict to disable renaming. A field is not renamed if the file already has a field with the
+/// new name.
+#[pyfunction]
+pub fn set_field_aliases(aliases: &Bound<'_, PyDict>) -> PyResult<()> {
+    let aliases = aliases.iter()
+        .map(|(from, to)| Ok((from.extract::<String>()?, to.extract::<String>()?)))
+        .collect::<PyResult<Vec<_>>>()?;
+    if aliases.iter().any(|(from, to)| from.is_empty() || to.is_empty()) {
+        return Err(PyValueError::new_err("Field name cannot be empty"));
+    }
+    crate::utils::set_field_aliases(aliases);
+    Ok(())
+}
+
+/// Return the field aliases applied when reading PCD headers, as a dict
+#[pyfunction]
+pub fn get_field_aliases(py: Python<'_>) -> PyResult<Bound<'_, PyDict>> {
+    let aliases = PyDict::new(py);
+    for (from, to) in crate::utils::field_aliases() {
+        aliases.set_item(from, to)?;
+    }
+    Ok(aliases)
+}
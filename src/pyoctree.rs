@@ -0,0 +1,53 @@
+use pyo3::prelude::*;
+use numpy::PyArray1;
+use crate::octree::Octree;
+use crate::pyerrors::value_error;
+
+#[pyclass(name = "Octree", frozen)]
+pub struct PyOctree {
+    pub tree: Octree,
+}
+
+#[pymethods]
+impl PyOctree {
+    fn __len__(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// Number of levels below the root
+    #[getter]
+    fn depth(&self) -> usize {
+        self.tree.depth()
+    }
+
+    /// (min_bound, max_bound) corners of the root cell, a cube enclosing the points
+    #[getter]
+    fn bounds(&self) -> ([f64; 3], [f64; 3]) {
+        self.tree.bounds()
+    }
+
+    /// Point indices of each occupied cell at `depth` (0 is the root), as a list of arrays
+    /// with one array per cell, in Morton (Z-order) order
+    fn leaf_points<'py>(&self, py: Python<'py>, depth: usize) -> PyResult<Vec<Bound<'py, PyArray1<i64>>>> {
+        let cells = py.allow_threads(|| self.tree.leaf_points(depth))
+            .map_err(value_error)?;
+        Ok(cells.into_iter()
+            .map(|cell| PyArray1::from_iter(py, cell.into_iter().map(|i| i as i64)))
+            .collect())
+    }
+
+    /// Indices of the points inside the axis-aligned box `[min_bound, max_bound]` (bounds
+    /// inclusive), in ascending order
+    fn query_box<'py>(&self, py: Python<'py>, min_bound: [f64; 3], max_bound: [f64; 3]) -> Bound<'py, PyArray1<i64>> {
+        let indices = py.allow_threads(|| self.tree.query_box(min_bound, max_bound));
+        PyArray1::from_iter(py, indices.into_iter().map(|i| i as i64))
+    }
+
+    /// Indices of one point per occupied cell at `depth`, the point closest to the cell
+    /// center, giving a level-of-detail subset of the cloud: `pc[octree.sample_lod(d)]`
+    fn sample_lod<'py>(&self, py: Python<'py>, depth: usize) -> PyResult<Bound<'py, PyArray1<i64>>> {
+        let indices = py.allow_threads(|| self.tree.sample_lod(depth))
+            .map_err(value_error)?;
+        Ok(PyArray1::from_iter(py, indices.into_iter().map(|i| i as i64)))
+    }
+}
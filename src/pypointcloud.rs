@@ -1,49 +1,1474 @@
-use pyo3::{exceptions::{PyKeyError, PyValueError}, prelude::*, types::PySlice, IntoPyObjectExt};
-use numpy::{PyArray2, PyArrayMethods, ToPyArray};
+use pyo3::{exceptions::{PyIndexError, PyKeyError, PyTypeError, PyValueError}, prelude::*, types::{PyByteArray, PyBytes, PyDict, PyFloat, PyInt, PyList, PySlice, PyString, PyTuple}, IntoPyObjectExt};
+use std::{borrow::Cow, collections::HashMap, path::PathBuf, sync::atomic::{AtomicBool, AtomicUsize, Ordering}};
+use numpy::{PyArray1, PyArray2, PyArrayMethods, PyArrayDescrMethods, PyReadonlyArray1, PyReadonlyArray2, PyUntypedArray, PyUntypedArrayMethods, ToPyArray};
 use ndarray::s;
-use crate::{fielddata::{FieldData, IntoPyObjectShaped}, pointcloud::PointCloud};
-use crate::pymetadata::PyMetadata;
-use crate::metadata::{FieldMeta, Dtype};
+use crate::{fielddata::{CastPolicy, FieldData}, pointcloud::PointCloud};
+use crate::pyfielddata::{IntoPyObjectShaped, PyFieldData};
+use crate::pymetadata::{extract_schema, metadata_from_state, metadata_to_state, MetadataState, PyMetadata};
+use crate::metadata::{FieldMeta, Dtype, Encoding, Metadata};
+use crate::organized::ImageOrder;
+use crate::io;
+use crate::io_ply::PlyFormat;
+use crate::io_tiles;
+use crate::io_ros::{RosCloud, RosField};
+use crate::transform;
+use crate::arith::{FieldOp, Normalization};
+use crate::stats::FieldStats;
+use crate::pykdtree::PyKdTree;
+use crate::pyoctree::PyOctree;
+use crate::pylog::warn_read_issues;
+use crate::pyprogress::{with_progress, PyCancellationToken};
+use crate::raster::Reducer;
+use crate::pybbox::{PyAxisAlignedBoundingBox, PyOrientedBoundingBox};
+use crate::pyinterchange::PyInterchangeDataFrame;
+use crate::pyerrors::{io_error, to_pyerr, unsupported_dtype, value_error, SchemaMismatchError};
 
-#[pyclass(name = "PointCloud")]
+const NORMAL_FIELDS: [&str; 3] = ["normal_x", "normal_y", "normal_z"];
+
+/// Whether mutating PointCloud methods resynchronize and check the metadata (see `set_auto_sync`).
+static AUTO_SYNC: AtomicBool = AtomicBool::new(false);
+
+/// Number of points previewed at the start and at the end of a PointCloud's repr (see
+/// `set_repr_points`).
+static REPR_POINTS: AtomicUsize = AtomicUsize::new(5);
+
+/// (eigenvalues, eigenvectors) returned by `PointCloud.pca`.
+type PcaResult<'py> = (Bound<'py, PyArray1<f64>>, Bound<'py, PyArray2<f64>>);
+
+/// (counts, edges) returned by `PointCloud.histogram`.
+type HistogramResult<'py> = (Bound<'py, PyArray1<i64>>, Bound<'py, PyArray1<f64>>);
+
+/// A row of the table previewing a PointCloud in its repr: the index and values of a point,
+/// or None for the points left out.
+type PreviewRow = Option<(usize, Vec<String>)>;
+
+#[pyclass(name = "PointCloud", module = "pcdpy._core")]
 pub struct PyPointCloud {
     pub pc: PointCloud,
 }
 
-#[pymethods]
-impl PyPointCloud {
-    #[staticmethod]
-    pub fn from_file(path: &str) -> PyResult<Self> {
-        let pc = PointCloud::from_pcd_file(path)
-            .map_err(|e| pyo3::exceptions::PyIOError::new_err(e.to_string()))?;
-        Ok(PyPointCloud { pc })
+/// Picklable representation of a PointCloud: its metadata, and its points encoded as a
+/// binary_compressed PCD file (None if the cloud has no fields).
+type PointCloudState = (MetadataState, Option<Vec<u8>>);
+
+#[pymethods]
+impl PyPointCloud {
+    /// Create an empty PointCloud with no fields and no points
+    #[new]
+    fn new() -> Self {
+        PyPointCloud { pc: PointCloud::new(&Metadata::default()) }
+    }
+
+    /// Return a copy of this PointCloud with its own metadata. Field data is copied lazily,
+    /// when either cloud modifies it
+    fn __copy__(&self) -> Self {
+        PyPointCloud { pc: self.pc.copy() }
+    }
+
+    fn __deepcopy__(&self, _memo: &Bound<'_, PyAny>) -> Self {
+        self.__copy__()
+    }
+
+    /// PointClouds are equal if their metadata and field values are equal (NaN equals NaN)
+    fn __eq__(&self, other: PyRef<'_, Self>) -> bool {
+        self.pc == other.pc
+    }
+
+    fn __getstate__(&self, py: Python<'_>) -> PyResult<PointCloudState> {
+        let md = metadata_to_state(&self.pc.metadata.read().unwrap());
+        if self.pc.fields.is_empty() {
+            return Ok((md, None));
+        }
+        let options = io::WriteOptions {
+            encoding: Some(Encoding::BinaryCompressed),
+            version: Some("0.7".to_string()),
+            ..Default::default()
+        };
+        let data = py.allow_threads(|| self.pc.to_pcd_bytes_with(&options))
+            .map_err(value_error)?;
+        Ok((md, Some(data)))
+    }
+
+    fn __setstate__(&mut self, py: Python<'_>, state: PointCloudState) -> PyResult<()> {
+        let (md, data) = state;
+        let md = metadata_from_state(md)?;
+        let pc = match data {
+            Some(data) => py.allow_threads(|| PointCloud::from_pcd_bytes(&data))
+                .map_err(value_error)?,
+            None => PointCloud::new(&md),
+        };
+        *pc.metadata.write().unwrap() = md;
+        self.pc = pc;
+        Ok(())
+    }
+
+    /// Read a PointCloud from a PCD file path, a URL or a binary file-like object.
+    /// s3:// and http(s):// URLs are read with range requests when pcdpy is built with the
+    /// "remote" feature, so `fields`, `start` and `count` only fetch the data they need from
+    /// uncompressed files. S3 credentials and region are taken from the AWS environment
+    /// variables.
+    /// If `strict` is False, malformed ASCII values are replaced by NaN (or 0 for integer
+    /// fields) instead of raising. Substitutions and recovered header defects are summarized
+    /// in a single UserWarning.
+    /// If `fields` is given, only those fields are loaded; the others are skipped without
+    /// being decoded. `start` and `count` load only `count` points (all remaining points if
+    /// None) from point index `start`, as an unorganized cloud; binary data is read directly
+    /// from the first requested point.
+    /// If `verify` is set, the data is checked against the CRC32 footer written by
+    /// `save(checksum=True)`, raising DataCorruptionError if it does not match or is missing.
+    /// `progress` is called as `progress(bytes_read, total_bytes)` every 8 MiB and at the end;
+    /// an exception raised by it stops the read. Cancelling the CancellationToken `cancel`
+    /// raises CancelledError.
+    #[staticmethod]
+    #[pyo3(signature = (file, strict=true, fields=None, start=0, count=None, verify=false, progress=None, cancel=None))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_file(file: &Bound<'_, PyAny>, strict: bool, fields: Option<Vec<String>>, start: usize, count: Option<usize>, verify: bool, progress: Option<PyObject>, cancel: Option<PyRef<'_, PyCancellationToken>>) -> PyResult<Self> {
+        let py = file.py();
+        let options = io::ReadOptions { strict, fields, start, count, verify };
+        let (pc, warnings) = if let Some(url) = extract_url(file) {
+            read_url(py, &url, &options, progress, cancel.as_deref())?
+        } else if let Ok(path) = file.extract::<PathBuf>() {
+            with_progress(py, progress, cancel.as_deref(), |p| PointCloud::from_pcd_reader_with_progress(std::fs::File::open(path)?, &options, p), io_error)?
+        } else {
+            let data = file.call_method0("read")?;
+            let data = data.downcast::<PyBytes>()?.as_bytes();
+            with_progress(py, progress, cancel.as_deref(), |p| PointCloud::from_pcd_reader_with_progress(std::io::Cursor::new(data), &options, p), io_error)?
+        };
+        warn_read_issues(py, &warnings)?;
+        Ok(PyPointCloud { pc })
+    }
+
+    /// Read a PointCloud from the contents of a PCD file held in memory.
+    /// Takes the same options as `from_file`.
+    #[staticmethod]
+    #[pyo3(signature = (data, strict=true, fields=None, start=0, count=None, verify=false))]
+    pub fn from_bytes(py: Python<'_>, data: &[u8], strict: bool, fields: Option<Vec<String>>, start: usize, count: Option<usize>, verify: bool) -> PyResult<Self> {
+        let options = io::ReadOptions { strict, fields, start, count, verify };
+        let (pc, warnings) = py.allow_threads(|| PointCloud::from_pcd_reader_with(&mut std::io::Cursor::new(data), &options))
+            .map_err(io_error)?;
+        warn_read_issues(py, &warnings)?;
+        Ok(PyPointCloud { pc })
+    }
+
+    /// Create a PointCloud of zero-filled points with the fields and shape described by
+    /// `metadata` (e.g. a Metadata built from scratch)
+    #[staticmethod]
+    pub fn from_metadata(metadata: &Bound<'_, PyMetadata>) -> PyResult<Self> {
+        // Cache metadata by acquiring a read lock once.
+        let meta = {
+            let borrowed = metadata.borrow();
+            let guard = borrowed.inner.read().unwrap();
+            guard.clone()
+        };
+        let pc = PointCloud::new(&meta);
+        Ok(PyPointCloud { pc })
+    }
+
+    /// Save the PointCloud as PCD to a file path or a binary file-like object.
+    /// `encoding` ("ascii", "binary", "binary_compressed", or the "binary_zstd" and
+    /// "binary_lz4" extensions) overrides the encoding in the metadata, and if `legacy` is
+    /// set, a PCD 0.6 header (without VIEWPOINT) is written. The metadata itself is not
+    /// modified. `compression_level` sets the zstd level for "binary_zstd", and
+    /// `pcl_compatible` refuses the extension encodings, which PCL cannot read.
+    ///
+    /// binary_compressed stores sizes as 32-bit integers, so data larger than 4 GiB cannot
+    /// be written in it. With `large_compressed`, such data is written with the
+    /// "binary_compressed_large" extension (LZF blocks behind 64-bit sizes) instead.
+    ///
+    /// Floating point values in ASCII data are written with `float_format`:
+    /// - "shortest" (the default): the shortest text that reads back to the same value,
+    /// - "fixed": `precision` digits after the decimal point (6 if not given),
+    /// - "scientific": scientific notation with `precision` digits after the decimal point.
+    ///
+    /// Giving only `precision` selects "fixed".
+    ///
+    /// Padding fields (named "_" in the file, and "_1", "_2", ... when read if there are
+    /// several) are written back as "_", or left out if `skip_padding` is set.
+    ///
+    /// With `checksum`, a CRC32 of the data is appended after it as a comment line, which
+    /// `from_file(verify=True)` checks to detect corrupted transfers. PCL ignores it.
+    ///
+    /// `progress` is called as `progress(bytes_written, total_bytes)` every 8 MiB and at the
+    /// end, with `total_bytes` None except for binary encoding; an exception raised by it
+    /// stops the write. Cancelling the CancellationToken `cancel` raises CancelledError. A
+    /// file stopped early is left incomplete.
+    #[pyo3(signature = (file, legacy=false, encoding=None, float_format=None, precision=None, compression_level=None, pcl_compatible=false, large_compressed=false, skip_padding=false, checksum=false, progress=None, cancel=None))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn save(&self, file: &Bound<'_, PyAny>, legacy: bool, encoding: Option<&str>, float_format: Option<&str>, precision: Option<usize>, compression_level: Option<i32>, pcl_compatible: bool, large_compressed: bool, skip_padding: bool, checksum: bool, progress: Option<PyObject>, cancel: Option<PyRef<'_, PyCancellationToken>>) -> PyResult<()> {
+        let py = file.py();
+        let options = io::WriteOptions {
+            checksum,
+            ..write_options(legacy, encoding, float_format, precision, compression_level, pcl_compatible, large_compressed, skip_padding)?
+        };
+        if let Ok(path) = file.extract::<PathBuf>() {
+            with_progress(py, progress, cancel.as_deref(), |p| self.pc.to_pcd_writer_with_progress(std::fs::File::create(path)?, &options, p), io_error)?;
+        } else {
+            let buf = with_progress(py, progress, cancel.as_deref(), |p| {
+                let mut buf = Vec::new();
+                self.pc.to_pcd_writer_with_progress(&mut buf, &options, p)?;
+                Ok(buf)
+            }, io_error)?;
+            file.call_method1("write", (PyBytes::new(py, &buf),))?;
+        }
+        Ok(())
+    }
+
+    /// Return the PointCloud encoded as the contents of a PCD file.
+    /// Takes the same options as `save`.
+    #[pyo3(signature = (legacy=false, encoding=None, float_format=None, precision=None, compression_level=None, pcl_compatible=false, large_compressed=false, skip_padding=false, checksum=false))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn to_bytes<'py>(&self, py: Python<'py>, legacy: bool, encoding: Option<&str>, float_format: Option<&str>, precision: Option<usize>, compression_level: Option<i32>, pcl_compatible: bool, large_compressed: bool, skip_padding: bool, checksum: bool) -> PyResult<Bound<'py, PyBytes>> {
+        let options = io::WriteOptions {
+            checksum,
+            ..write_options(legacy, encoding, float_format, precision, compression_level, pcl_compatible, large_compressed, skip_padding)?
+        };
+        let buf = py.allow_threads(|| self.pc.to_pcd_bytes_with(&options))
+            .map_err(io_error)?;
+        Ok(PyBytes::new(py, &buf))
+    }
+
+    /// Read a PointCloud from the vertex element of a PLY file
+    #[staticmethod]
+    pub fn from_ply(py: Python<'_>, path: &str) -> PyResult<Self> {
+        let pc = py.allow_threads(|| PointCloud::from_ply_file(path))
+            .map_err(io_error)?;
+        Ok(PyPointCloud { pc })
+    }
+
+    /// Read a PointCloud from a LAS file (or LAZ, when built with the `laz` feature)
+    #[cfg(feature = "las")]
+    #[staticmethod]
+    pub fn from_las(py: Python<'_>, path: &str) -> PyResult<Self> {
+        let pc = py.allow_threads(|| PointCloud::from_las_file(path))
+            .map_err(io_error)?;
+        Ok(PyPointCloud { pc })
+    }
+
+    /// Save the PointCloud as a LAS 1.2 file, or as LAZ if `path` ends with ".laz" (when built
+    /// with the `laz` feature). x, y and z are stored as multiples of `scale`, and the fields
+    /// read by `from_las` (intensity, return_number, number_of_returns, classification,
+    /// gps_time, red/green/blue or a packed rgb field) as the matching LAS attributes, with
+    /// values saturated to their types. Other fields are not written
+    #[cfg(feature = "las")]
+    #[pyo3(signature = (path, scale=0.001))]
+    pub fn save_las(&self, py: Python<'_>, path: &str, scale: f64) -> PyResult<()> {
+        py.allow_threads(|| self.pc.to_las_file(path, scale))
+            .map_err(io_error)
+    }
+
+    /// Read scan `scan_index` of an E57 file. Cartesian (or spherical) coordinates become the
+    /// x, y and z fields, with NaN for invalid points, and intensity and color become
+    /// "intensity" and packed "rgb" fields. The scan pose is loaded into the viewpoint
+    #[cfg(feature = "e57")]
+    #[staticmethod]
+    #[pyo3(signature = (path, scan_index=0))]
+    pub fn from_e57(py: Python<'_>, path: &str, scan_index: usize) -> PyResult<Self> {
+        let pc = py.allow_threads(|| PointCloud::from_e57_file(path, scan_index))
+            .map_err(io_error)?;
+        Ok(PyPointCloud { pc })
+    }
+
+    /// Read a PointCloud from a Parquet file
+    #[cfg(feature = "arrow")]
+    #[staticmethod]
+    pub fn from_parquet(py: Python<'_>, path: &str) -> PyResult<Self> {
+        let pc = py.allow_threads(|| PointCloud::from_parquet_file(path))
+            .map_err(io_error)?;
+        Ok(PyPointCloud { pc })
+    }
+
+    /// Save the PointCloud as a Parquet file
+    #[cfg(feature = "arrow")]
+    pub fn save_parquet(&self, py: Python<'_>, path: &str) -> PyResult<()> {
+        py.allow_threads(|| self.pc.to_parquet_file(path))
+            .map_err(io_error)?;
+        Ok(())
+    }
+
+    /// Return the PointCloud as a pyarrow.Table with one column per field
+    #[cfg(feature = "arrow")]
+    pub fn to_arrow(&self, py: Python<'_>) -> PyResult<PyObject> {
+        use arrow::pyarrow::ToPyArrow;
+        let batch = crate::io_arrow::to_record_batch(&self.pc)
+            .map_err(value_error)?;
+        let batch = batch.to_pyarrow(py)?;
+        let table = py.import("pyarrow")?.getattr("Table")?.call_method1("from_batches", (vec![batch],))?;
+        Ok(table.unbind())
+    }
+
+    /// Create a PointCloud from a pyarrow.Table, RecordBatch, or any Arrow stream
+    #[cfg(feature = "arrow")]
+    #[staticmethod]
+    pub fn from_arrow(data: &Bound<'_, PyAny>) -> PyResult<Self> {
+        use arrow::array::RecordBatchReader;
+        use arrow::pyarrow::FromPyArrow;
+        let (schema, batches) = if let Ok(batch) = arrow::array::RecordBatch::from_pyarrow_bound(data) {
+            (batch.schema(), vec![batch])
+        } else {
+            let reader = arrow::ffi_stream::ArrowArrayStreamReader::from_pyarrow_bound(data)?;
+            let schema = reader.schema();
+            let batches = reader.collect::<Result<Vec<_>, _>>()
+                .map_err(|e| value_error(e.into()))?;
+            (schema, batches)
+        };
+        let pc = crate::io_arrow::from_record_batches(&schema, &batches)
+            .map_err(value_error)?;
+        Ok(PyPointCloud { pc })
+    }
+
+    /// Save the PointCloud as a PLY file (binary_little_endian unless `ascii` is set)
+    #[pyo3(signature = (path, ascii=false))]
+    pub fn save_ply(&self, py: Python<'_>, path: &str, ascii: bool) -> PyResult<()> {
+        let format = if ascii { PlyFormat::Ascii } else { PlyFormat::BinaryLittleEndian };
+        py.allow_threads(|| self.pc.to_ply_file(path, format))
+            .map_err(io_error)?;
+        Ok(())
+    }
+
+    /// Read a PointCloud from a NumPy .npz archive. Archives written by `save_npz` keep their
+    /// metadata; for others, each array of shape (npoints,) or (npoints, count) becomes a field
+    #[staticmethod]
+    pub fn from_npz(py: Python<'_>, path: &str) -> PyResult<Self> {
+        let pc = py.allow_threads(|| PointCloud::from_npz_file(path))
+            .map_err(io_error)?;
+        Ok(PyPointCloud { pc })
+    }
+
+    /// Save the PointCloud as a NumPy .npz archive readable with `numpy.load`, with one array
+    /// per field and the PCD header as a string under "__metadata__". Arrays are compressed
+    /// unless `compressed` is False
+    #[pyo3(signature = (path, compressed=true))]
+    pub fn save_npz(&self, py: Python<'_>, path: &str, compressed: bool) -> PyResult<()> {
+        py.allow_threads(|| self.pc.to_npz_file(path, compressed))
+            .map_err(io_error)
+    }
+
+    /// Save the PointCloud as a 3D Tiles tileset in the directory `dir`, for web viewers such
+    /// as CesiumJS: a tileset.json and one .pnts file per tile of an octree, each tile holding
+    /// at most `max_points_per_tile` points of the coordinate `fields` that add detail to its
+    /// parent's. Points are colored by the packed `color_field` if present (None to skip
+    /// colors), and `transform` is a 4x4 homogeneous matrix placing the tileset in the
+    /// viewer's frame, such as the local-to-Earth-centered transform of georeferenced clouds
+    #[pyo3(signature = (dir, max_points_per_tile=100_000, color_field=Some("rgb"), transform=None, fields=None))]
+    pub fn save_3d_tiles(&self, py: Python<'_>, dir: &str, max_points_per_tile: usize, color_field: Option<&str>,
+                         transform: Option<[[f64; 4]; 4]>, fields: Option<(String, String, String)>) -> PyResult<()> {
+        let fields = self.coordinate_names(fields);
+        let options = io_tiles::TilesOptions {
+            max_points: max_points_per_tile,
+            color_field: color_field.map(String::from),
+            transform: transform.map(|m| std::array::from_fn(|i| m[i % 4][i / 4])),
+        };
+        py.allow_threads(|| self.pc.to_3d_tiles(dir, [&fields.0, &fields.1, &fields.2], &options))
+            .map_err(io_error)
+    }
+
+    /// Read a PointCloud from a text file with one point per line, such as .xyz, .csv or
+    /// .tsv files. The delimiter is inferred (comma, tab, semicolon, else whitespace) unless
+    /// given, and a first line that is not all numbers is read as a header of field names.
+    /// `names` overrides the header; without either, fields are named x, y, z, field_3, ...
+    /// The first `skip_rows` lines are ignored, as are blank lines and lines starting with
+    /// "#" or "//". Integer columns become int32 fields (int64 if needed) and others float32
+    /// fields, or float64 if float32 would lose precision
+    #[staticmethod]
+    #[pyo3(signature = (path, delimiter=None, names=None, skip_rows=0))]
+    pub fn from_text(py: Python<'_>, path: &str, delimiter: Option<char>, names: Option<Vec<String>>, skip_rows: usize) -> PyResult<Self> {
+        let pc = py.allow_threads(|| PointCloud::from_text_file(path, delimiter, names.as_deref(), skip_rows))
+            .map_err(io_error)?;
+        Ok(PyPointCloud { pc })
+    }
+
+    /// Save the PointCloud as delimited text with one point per line, preceded by a header of
+    /// field names unless `header` is False. Fields with a count greater than 1 are written
+    /// as one column per element, named "<name>_0", "<name>_1", ... `float_format` and
+    /// `precision` format floating point values as in `save`
+    #[pyo3(signature = (path, delimiter=',', header=true, float_format=None, precision=None))]
+    pub fn save_csv(&self, py: Python<'_>, path: &str, delimiter: char, header: bool, float_format: Option<&str>, precision: Option<usize>) -> PyResult<()> {
+        if !delimiter.is_ascii() || delimiter.is_ascii_alphanumeric() || matches!(delimiter, '\n' | '\r' | '.' | '-' | '+') {
+            return Err(PyValueError::new_err(format!("Invalid delimiter '{}'", delimiter.escape_default())));
+        }
+        let float_format = write_options(false, None, float_format, precision, None, false, false, false)?.float_format;
+        py.allow_threads(|| self.pc.to_csv_file(path, delimiter as u8, header, float_format))
+            .map_err(io_error)
+    }
+
+    /// Create a PointCloud from the contents of a sensor_msgs/PointCloud2 message. `fields`
+    /// holds its PointFields, as objects with name, offset, datatype and count attributes,
+    /// dicts with those keys, or (name, offset, datatype, count) tuples. `data` is any
+    /// bytes-like object of interleaved point records. `is_dense` is accepted so that the
+    /// dict from `to_ros_msg_dict` can be passed back as keyword arguments, and is not used
+    #[staticmethod]
+    #[pyo3(signature = (fields, data, point_step, width, height=1, row_step=None, is_bigendian=false, is_dense=None))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_ros_msg(
+        py: Python<'_>,
+        fields: Vec<Bound<'_, PyAny>>,
+        data: &Bound<'_, PyAny>,
+        point_step: usize,
+        width: usize,
+        height: usize,
+        row_step: Option<usize>,
+        is_bigendian: bool,
+        is_dense: Option<bool>,
+    ) -> PyResult<Self> {
+        let _ = is_dense;
+        let fields = fields.iter().map(extract_ros_field).collect::<PyResult<Vec<_>>>()?;
+        let data = match data.downcast::<PyBytes>() {
+            Ok(bytes) => bytes.clone(),
+            Err(_) => py.get_type::<PyBytes>().call1((data,))?.downcast_into::<PyBytes>()?,
+        };
+        let cloud = RosCloud {
+            fields,
+            height,
+            width,
+            point_step,
+            row_step: row_step.unwrap_or(width * point_step),
+            is_bigendian,
+            is_dense: true,
+            data: Cow::Borrowed(data.as_bytes()),
+        };
+        let pc = PointCloud::from_ros(&cloud)
+            .map_err(value_error)?;
+        Ok(PyPointCloud { pc })
+    }
+
+    /// Return the PointCloud as a dict with the fields of a little-endian
+    /// sensor_msgs/PointCloud2 message (height, width, fields, is_bigendian, point_step,
+    /// row_step, data, is_dense), with each PointField as a dict of name, offset, datatype and
+    /// count. Records are tightly packed, and padding fields are left as gaps
+    pub fn to_ros_msg_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let cloud = py.allow_threads(|| self.pc.to_ros())
+            .map_err(value_error)?;
+        let fields = PyList::empty(py);
+        for field in &cloud.fields {
+            let dict = PyDict::new(py);
+            dict.set_item("name", &field.name)?;
+            dict.set_item("offset", field.offset)?;
+            dict.set_item("datatype", field.datatype)?;
+            dict.set_item("count", field.count)?;
+            fields.append(dict)?;
+        }
+        let msg = PyDict::new(py);
+        msg.set_item("height", cloud.height)?;
+        msg.set_item("width", cloud.width)?;
+        msg.set_item("fields", fields)?;
+        msg.set_item("is_bigendian", cloud.is_bigendian)?;
+        msg.set_item("point_step", cloud.point_step)?;
+        msg.set_item("row_step", cloud.row_step)?;
+        msg.set_item("data", PyBytes::new(py, &cloud.data))?;
+        msg.set_item("is_dense", cloud.is_dense)?;
+        Ok(msg)
+    }
+
+    /// Return the PointCloud as a NumPy structured array with one named column per field.
+    /// Fields with count > 1 become subarray columns of shape (count,).
+    pub fn to_structured_array<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let np = py.import("numpy")?;
+        let descr = {
+            let md = self.pc.metadata.read().unwrap();
+            let descr = PyList::empty(py);
+            for field_meta in md.fields.iter() {
+                let typestr = field_meta.dtype.as_numpy_typestr();
+                if field_meta.count == 1 {
+                    descr.append((field_meta.name.as_str(), typestr))?;
+                } else {
+                    descr.append((field_meta.name.as_str(), typestr, (field_meta.count,)))?;
+                }
+            }
+            descr
+        };
+        let dtype = np.call_method1("dtype", (descr,))?;
+
+        // The binary PCD record layout matches a packed little-endian structured dtype.
+        let mut buf = Vec::new();
+        io::write_binary_data(&mut buf, &self.pc)
+            .map_err(value_error)?;
+        np.call_method1("frombuffer", (PyByteArray::new(py, &buf), dtype))
+    }
+
+    /// Implement the NumPy array protocol: `np.asarray(pc)` returns `to_structured_array()`,
+    /// converted to `dtype` if given. The array is always a copy, so `copy=False` raises a
+    /// ValueError
+    #[pyo3(signature = (dtype=None, copy=None))]
+    pub fn __array__<'py>(&self, py: Python<'py>, dtype: Option<&Bound<'py, PyAny>>, copy: Option<bool>) -> PyResult<Bound<'py, PyAny>> {
+        if copy == Some(false) {
+            return Err(PyValueError::new_err("A PointCloud cannot be viewed as an array without a copy"));
+        }
+        let array = self.to_structured_array(py)?;
+        match dtype {
+            Some(dtype) => array.call_method1("astype", (dtype,)),
+            None => Ok(array),
+        }
+    }
+
+    /// Implement the dataframe interchange protocol, so that dataframe libraries can import
+    /// the PointCloud (e.g. `pandas.api.interchange.from_dataframe(pc)` or
+    /// `polars.from_dataframe(pc)`). There is one column per field, or `name_0`, `name_1`, ...
+    /// for the values of a field with a count above 1. Fields with a count of 1 share the
+    /// memory of the PointCloud; the others are copied, or raise a RuntimeError if
+    /// `allow_copy` is False. With `nan_as_null`, NaN values are reported as missing
+    #[pyo3(signature = (nan_as_null=false, allow_copy=true))]
+    pub fn __dataframe__(&self, nan_as_null: bool, allow_copy: bool) -> PyResult<PyInterchangeDataFrame> {
+        PyInterchangeDataFrame::new(&self.pc, nan_as_null, allow_copy)
+    }
+
+    /// Create a PointCloud from a NumPy structured array, with one field per named column.
+    #[staticmethod]
+    pub fn from_structured_array(arr: &Bound<'_, PyAny>) -> PyResult<Self> {
+        let py = arr.py();
+        let np = py.import("numpy")?;
+        let dtype = arr.getattr("dtype")?;
+        let names: Vec<String> = dtype.getattr("names")?
+            .extract::<Option<Vec<String>>>()?
+            .ok_or_else(|| PyValueError::new_err("Expected a structured array with named fields"))?;
+        let npoints: usize = arr.len()?;
+
+        let md = Metadata {
+            width: npoints,
+            height: 1,
+            npoints,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::empty(&md);
+        for name in names {
+            // Reshape each column (and subarray column) to (npoints, count).
+            let count: usize = dtype.get_item(name.as_str())?.getattr("shape")?
+                .extract::<Vec<usize>>()?
+                .iter()
+                .product();
+            let column = np.call_method1("ascontiguousarray", (arr.get_item(name.as_str())?,))?
+                .call_method1("reshape", (npoints, count))?;
+            infer_and_store_field(&mut pc, &name, &column)?;
+        }
+        Ok(PyPointCloud { pc })
+    }
+
+    /// Create an unorganized PointCloud of `npoints` zero-filled points with the fields of
+    /// `schema` (a Schema, or a list of FieldMeta objects or (name, dtype, count) tuples)
+    #[staticmethod]
+    pub fn zeros(schema: &Bound<'_, PyAny>, npoints: usize) -> PyResult<Self> {
+        let md = Metadata {
+            fields: extract_schema(schema)?,
+            width: npoints,
+            height: 1,
+            npoints,
+            ..Metadata::default()
+        };
+        Ok(PyPointCloud { pc: PointCloud::new(&md) })
+    }
+
+    /// Create an unorganized PointCloud from a dict mapping field names to NumPy arrays of
+    /// shape (npoints,) or (npoints, count), keeping the dtype of each array and the order
+    /// of the dict.
+    #[staticmethod]
+    pub fn from_arrays(arrays: &Bound<'_, PyDict>) -> PyResult<Self> {
+        let np = arrays.py().import("numpy")?;
+        let mut columns = Vec::with_capacity(arrays.len());
+        for (name, array) in arrays.iter() {
+            let name: String = name.extract()?;
+            let array = np.call_method1("ascontiguousarray", (array,))?;
+            let shape: Vec<usize> = array.getattr("shape")?.extract()?;
+            let array = match shape[..] {
+                [n] => array.call_method1("reshape", (n, 1))?,
+                [_, _] => array,
+                _ => return Err(PyValueError::new_err(format!(
+                    "Field '{}': expected an array of shape (npoints,) or (npoints, count), got {} dimensions",
+                    name, shape.len()))),
+            };
+            columns.push((name, shape[0], array));
+        }
+
+        let npoints = columns.first().map_or(0, |c| c.1);
+        let md = Metadata {
+            width: npoints,
+            height: 1,
+            npoints,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::empty(&md);
+        for (name, _, array) in columns {
+            if pc.fields.contains_key(&name) {
+                return Err(PyValueError::new_err(format!("Duplicate field name: {}", name)));
+            }
+            infer_and_store_field(&mut pc, &name, &array)
+                .map_err(|e| PyValueError::new_err(format!("Field '{}': {}", name, e)))?;
+        }
+        Ok(PyPointCloud { pc })
+    }
+
+    /// Return the PointCloud as an open3d.geometry.PointCloud. Points come from the coordinate
+    /// `fields`, normals from normal_x/normal_y/normal_z if present, and colors from the
+    /// packed `color_field` if present (None to skip colors)
+    #[pyo3(signature = (fields=None, color_field=Some("rgb")))]
+    pub fn to_open3d<'py>(&self, py: Python<'py>, fields: Option<(String, String, String)>, color_field: Option<&str>) -> PyResult<Bound<'py, PyAny>> {
+        let o3d = py.import("open3d")?;
+        let vector3d = o3d.getattr("utility")?.getattr("Vector3dVector")?;
+        let cloud = o3d.getattr("geometry")?.getattr("PointCloud")?.call0()?;
+
+        let fields = self.coordinate_names(fields);
+        let points = self.pc.xyz([&fields.0, &fields.1, &fields.2])
+            .map_err(|e| PyKeyError::new_err(e.to_string()))?;
+        cloud.setattr("points", vector3d.call1((points.get_data::<f64>().to_pyarray(py),))?)?;
+        if NORMAL_FIELDS.iter().all(|name| self.pc.fields.contains_key(name)) {
+            let normals = self.pc.xyz(NORMAL_FIELDS)
+                .map_err(value_error)?;
+            cloud.setattr("normals", vector3d.call1((normals.get_data::<f64>().to_pyarray(py),))?)?;
+        }
+        if let Some(color_field) = color_field.filter(|name| self.pc.fields.contains_key(name)) {
+            let colors = self.pc.unpack_rgb(color_field)
+                .map_err(value_error)?
+                .mapv(|c| c as f64 / 255.0);
+            cloud.setattr("colors", vector3d.call1((colors.to_pyarray(py),))?)?;
+        }
+        Ok(cloud)
+    }
+
+    /// Create an unorganized PointCloud from an open3d.geometry.PointCloud, with fields x, y, z,
+    /// normal_x, normal_y, normal_z (if it has normals) of `dtype` ("float32" or "float64"),
+    /// and a packed "rgb" field (if it has colors)
+    #[staticmethod]
+    #[pyo3(signature = (cloud, dtype="float32"))]
+    pub fn from_open3d(cloud: &Bound<'_, PyAny>, dtype: &str) -> PyResult<Self> {
+        let dtype = match Dtype::from_numpy_dtype(dtype) {
+            Some(dtype @ (Dtype::F32 | Dtype::F64)) => dtype,
+            _ => return Err(PyValueError::new_err(format!("Expected float32 or float64, got {}", dtype))),
+        };
+        let np = cloud.py().import("numpy")?;
+        // The Vector3dVector buffers are exposed to NumPy without a copy.
+        let vectors = |name: &str| -> PyResult<FieldData> {
+            let array = np.call_method1("asarray", (cloud.getattr(name)?, dtype.as_numpy_typestr()))?;
+            FieldData::from_pyarray(&array, dtype)
+        };
+
+        let points = vectors("points")?;
+        let npoints = points.npoints();
+        let md = Metadata {
+            width: npoints,
+            height: 1,
+            npoints,
+            ..Metadata::default()
+        };
+        let mut pc = PointCloud::empty(&md);
+        pc.set_xyz(["x", "y", "z"], &points)
+            .map_err(value_error)?;
+        if cloud.call_method0("has_normals")?.extract()? {
+            pc.set_xyz(NORMAL_FIELDS, &vectors("normals")?)
+                .map_err(value_error)?;
+        }
+        if cloud.call_method0("has_colors")?.extract()? {
+            let colors = np.call_method1("asarray", (cloud.getattr("colors")?,))?;
+            let colors = colors.extract::<PyReadonlyArray2<f64>>()?.as_array()
+                .mapv(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+            pc.pack_rgb(colors.column(0), colors.column(1), colors.column(2), "rgb")
+                .map_err(value_error)?;
+        }
+        Ok(PyPointCloud { pc })
+    }
+
+    /// Concatenate PointClouds with identical fields into a new PointCloud.
+    #[staticmethod]
+    pub fn concat(clouds: Vec<PyRef<'_, PyPointCloud>>) -> PyResult<Self> {
+        let refs: Vec<&PointCloud> = clouds.iter().map(|c| &c.pc).collect();
+        let pc = PointCloud::concat(&refs)
+            .map_err(value_error)?;
+        Ok(PyPointCloud { pc })
+    }
+
+    fn __add__(&self, other: PyRef<'_, PyPointCloud>) -> PyResult<Self> {
+        let pc = PointCloud::concat(&[&self.pc, &other.pc])
+            .map_err(value_error)?;
+        Ok(PyPointCloud { pc })
+    }
+
+    /// Check that the metadata matches the field data (WIDTH x HEIGHT against POINTS, and
+    /// the point count, COUNT and dtype of each field). Returns the list of problems found,
+    /// or raises a SchemaMismatchError listing all of them if `strict` is set.
+    #[pyo3(signature = (strict=false))]
+    pub fn validate(&self, strict: bool) -> PyResult<Vec<String>> {
+        if strict {
+            self.pc.check_pointcloud()
+                .map_err(value_error)?;
+        }
+        Ok(self.pc.problems())
+    }
+
+    /// Recompute npoints, WIDTH and HEIGHT (as an unorganized cloud, if they no longer match),
+    /// and the COUNT and dtype of each field from the field data, then check that the metadata
+    /// matches it. Raises a SchemaMismatchError listing the remaining problems (fields that
+    /// disagree on the number of points, or that are missing from either side)
+    pub fn sync_metadata(&self) -> PyResult<()> {
+        self.pc.sync_metadata()
+            .map_err(value_error)
+    }
+
+    /// Add a new field from a NumPy array of shape (npoints, count), or (npoints,) for a count of 1.
+    pub fn add_field(&mut self, name: &str, array: &Bound<'_, PyAny>) -> PyResult<()> {
+        if self.pc.fields.contains_key(name) {
+            return Err(PyValueError::new_err(format!("Field '{}' already exists", name)));
+        }
+        infer_and_store_field(&mut self.pc, name, array)?;
+        self.auto_sync()
+    }
+
+    /// Remove a field from the PointCloud.
+    pub fn drop_field(&mut self, name: &str) -> PyResult<()> {
+        self.pc.drop_field(name)
+            .map_err(|e| PyKeyError::new_err(e.to_string()))?;
+        self.auto_sync()
+    }
+
+    /// Return a new PointCloud with only the named fields, in the given order (e.g. to slim a
+    /// cloud before saving). Field data is copied lazily, when either cloud modifies it
+    pub fn select(&self, fields: Vec<String>) -> PyResult<Self> {
+        let pc = self.pc.select(&fields).map_err(|e| {
+            if fields.iter().all(|name| self.pc.fields.contains_key(name)) {
+                PyValueError::new_err(e.to_string())
+            } else {
+                PyKeyError::new_err(e.to_string())
+            }
+        })?;
+        Ok(PyPointCloud { pc })
+    }
+
+    /// Return a new PointCloud of the points selected as by `pc[start:stop:step]`. The result
+    /// stays organized if it is whole rows of an organized cloud (a step of 1, starting and
+    /// ending on row boundaries), and is unorganized otherwise. `organized=True` raises a
+    /// ValueError unless the points are whole rows, and `organized=False` always returns an
+    /// unorganized cloud
+    #[pyo3(signature = (start=None, stop=None, step=None, organized=None))]
+    pub fn slice(&self, py: Python<'_>, start: Option<isize>, stop: Option<isize>, step: Option<isize>, organized: Option<bool>) -> PyResult<Self> {
+        let slice = py.get_type::<PySlice>().call1((start, stop, step))?;
+        let pc = self.slice_points(slice.downcast::<PySlice>()?, organized)?;
+        Ok(PyPointCloud { pc })
+    }
+
+    /// Copy the fields of another PointCloud with the same number of points into this one.
+    /// Existing fields are replaced only if `overwrite` is set.
+    #[pyo3(signature = (other, overwrite=false))]
+    pub fn merge_fields(&mut self, other: PyRef<'_, PyPointCloud>, overwrite: bool) -> PyResult<()> {
+        self.pc.merge_fields(&other.pc, overwrite)
+            .map_err(value_error)?;
+        self.auto_sync()
+    }
+
+    /// Convert a field to another NumPy dtype (e.g. "float32"). Values that cannot be
+    /// represented raise a ValueError with `policy="error"`, are clamped with `policy="saturate"`,
+    /// or wrap around like a C cast with `policy="wrap"`
+    #[pyo3(signature = (field, dtype, policy="error"))]
+    pub fn cast_field(&mut self, field: &str, dtype: &str, policy: &str) -> PyResult<()> {
+        if !self.pc.fields.contains_key(field) {
+            return Err(PyKeyError::new_err(format!("Field '{}' not found", field)));
+        }
+        self.astype(HashMap::from([(field.to_string(), dtype.to_string())]), policy)
+    }
+
+    /// Convert several fields at once, given a dict mapping field names to NumPy dtypes.
+    /// If any conversion fails, no field is changed. See `cast_field`
+    #[pyo3(signature = (dtypes, policy="error"))]
+    pub fn astype(&mut self, dtypes: HashMap<String, String>, policy: &str) -> PyResult<()> {
+        let policy = parse_cast_policy(policy)?;
+        let dtypes = dtypes.iter()
+            .map(|(name, dtype)| {
+                let dtype = Dtype::from_numpy_dtype(dtype)
+                    .ok_or_else(|| unsupported_dtype(dtype))?;
+                Ok((name.as_str(), dtype))
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+        self.pc.astype(&dtypes, policy)
+            .map_err(value_error)?;
+        self.auto_sync()
+    }
+
+    /// Multiply the values of a field by `factor` in place. Integer fields are rounded to the
+    /// nearest integer; pass `dtype` (e.g. "float32") to store the result as another dtype.
+    /// Out-of-range values are handled according to `policy`, as in `cast_field`
+    #[pyo3(signature = (field, factor, dtype=None, policy="error"))]
+    pub fn scale_field(&mut self, py: Python<'_>, field: &str, factor: f64, dtype: Option<&str>, policy: &str) -> PyResult<()> {
+        self.apply_op(py, field, FieldOp::Multiply(factor), dtype, policy)
+    }
+
+    /// Add `offset` to the values of a field in place. See `scale_field`
+    #[pyo3(signature = (field, offset, dtype=None, policy="error"))]
+    pub fn offset_field(&mut self, py: Python<'_>, field: &str, offset: f64, dtype: Option<&str>, policy: &str) -> PyResult<()> {
+        self.apply_op(py, field, FieldOp::Add(offset), dtype, policy)
+    }
+
+    /// Apply an elementwise operation to a field in place, natively and in parallel, given
+    /// a NumPy ufunc name and its scalar arguments: "add", "subtract", "multiply", "divide",
+    /// "power", "minimum", "maximum" (one argument), "clip" (min and max), or "abs",
+    /// "negative", "sqrt", "square", "exp", "log", "log10", "floor", "ceil", "round".
+    /// For example `pc.apply("intensity", "clip", 0, 1)`. See `scale_field` for `dtype`
+    /// and `policy`
+    #[pyo3(signature = (field, op, *args, dtype=None, policy="error"))]
+    pub fn apply(&mut self, py: Python<'_>, field: &str, op: &str, args: Vec<f64>, dtype: Option<&str>, policy: &str) -> PyResult<()> {
+        let op = FieldOp::from_spec(op, &args)
+            .map_err(value_error)?;
+        self.apply_op(py, field, op, dtype, policy)
+    }
+
+    /// Normalize a field in place to [0, 1], clipping values outside the range, and return
+    /// the (low, high) range used. With `method="minmax"` the range is the minimum and maximum,
+    /// and with `method="percentile"` it is the `percentiles` (low, high, 0 to 100), which is
+    /// robust to outliers. The field is stored as `out_dtype`; integer dtypes are scaled to
+    /// their maximum value, e.g. [0, 255] for "uint8". Non-finite values are ignored
+    #[pyo3(signature = (field, method="minmax", percentiles=(2.0, 98.0), out_dtype="float32"))]
+    pub fn normalize_field(&mut self, py: Python<'_>, field: &str, method: &str, percentiles: (f64, f64), out_dtype: &str) -> PyResult<(f64, f64)> {
+        if !self.pc.fields.contains_key(field) {
+            return Err(PyKeyError::new_err(format!("Field '{}' not found", field)));
+        }
+        let method = match method {
+            "minmax" => Normalization::MinMax,
+            "percentile" => Normalization::Percentile(percentiles.0, percentiles.1),
+            _ => return Err(PyValueError::new_err(format!("Invalid method '{}', expected 'minmax' or 'percentile'", method))),
+        };
+        let dtype = Dtype::from_numpy_dtype(out_dtype)
+            .ok_or_else(|| unsupported_dtype(out_dtype))?;
+        let pc = &mut self.pc;
+        let range = py.allow_threads(|| pc.normalize_field(field, method, dtype))
+            .map_err(value_error)?;
+        self.auto_sync()?;
+        Ok(range)
+    }
+
+    /// Rename an existing field.
+    pub fn rename_field(&mut self, old: &str, new: &str) -> PyResult<()> {
+        if !self.pc.fields.contains_key(old) {
+            return Err(PyKeyError::new_err(format!("Field '{}' not found", old)));
+        }
+        self.pc.rename_field(old, new)
+            .map_err(value_error)?;
+        self.auto_sync()
+    }
+
+    /// Add or replace the label field `name` from a sequence of strings, one per point (e.g.
+    /// class names). The field holds the index of each label in its categories, `categories`
+    /// if given, else the distinct labels in order of first appearance. The categories are
+    /// written with the cloud, as a `# categories.<name>: ...` header comment
+    #[pyo3(signature = (name, labels, categories=None))]
+    pub fn set_labels(&mut self, name: &str, labels: Vec<String>, categories: Option<Vec<String>>) -> PyResult<()> {
+        self.pc.set_labels(name, &labels, categories.as_deref())
+            .map_err(value_error)?;
+        self.auto_sync()
+    }
+
+    /// Return the label of each point of the label field `name`, as a list of strings
+    pub fn get_labels(&self, name: &str) -> PyResult<Vec<String>> {
+        if !self.pc.fields.contains_key(name) {
+            return Err(PyKeyError::new_err(format!("Field '{}' not found", name)));
+        }
+        self.pc.labels(name)
+            .map_err(value_error)
+    }
+
+    /// Return the categories of the label field `name`, or None if it is not a label field
+    pub fn categories(&self, name: &str) -> Option<Vec<String>> {
+        self.pc.metadata.read().unwrap().categories(name)
+    }
+
+    /// Make the integer field `name` a label field whose values index `categories`, or make it
+    /// a plain field again if `categories` is None
+    #[pyo3(signature = (name, categories))]
+    pub fn set_categories(&mut self, name: &str, categories: Option<Vec<String>>) -> PyResult<()> {
+        let mut md = self.pc.metadata.write().unwrap();
+        match categories {
+            Some(categories) => md.set_categories(name, &categories).map_err(value_error)?,
+            None => {
+                md.remove_categories(name);
+            }
+        }
+        drop(md);
+        self.auto_sync()
+    }
+
+    /// Grow or shrink the PointCloud in place to `n` points. New points are zero-filled.
+    pub fn resize(&mut self, n: usize) -> PyResult<()> {
+        self.pc.resize(n)
+            .map_err(value_error)?;
+        self.auto_sync()
+    }
+
+    /// Reserve room for at least `additional` more points, so that repeated growth
+    /// with `resize` does not reallocate every time.
+    pub fn reserve(&mut self, additional: usize) -> PyResult<()> {
+        self.pc.reserve(additional)
+            .map_err(value_error)
+    }
+
+    /// Unpack a PCL-style packed color field into an (npoints, 3) uint8 NumPy array of r, g, b.
+    /// If `as_fields` is set, the channels are instead stored as uint8 fields "r", "g" and "b"
+    /// and None is returned
+    #[pyo3(signature = (field="rgb", as_fields=false))]
+    pub fn unpack_rgb<'py>(&mut self, py: Python<'py>, field: &str, as_fields: bool) -> PyResult<Option<Bound<'py, PyArray2<u8>>>> {
+        if !self.pc.fields.contains_key(field) {
+            return Err(PyKeyError::new_err(format!("Field '{}' not found", field)));
+        }
+        let colors = self.pc.unpack_rgb(field)
+            .map_err(value_error)?;
+        if !as_fields {
+            return Ok(Some(colors.to_pyarray(py)));
+        }
+        for (i, name) in ["r", "g", "b"].into_iter().enumerate() {
+            let channel = colors.slice(s![.., i..i + 1]).to_shared();
+            self.pc.insert_field(name, FieldData::U8(channel))
+                .map_err(value_error)?;
+        }
+        self.auto_sync()?;
+        Ok(None)
+    }
+
+    /// Pack uint8 arrays `r`, `g` and `b` into a PCL-style float32 color field, adding it or
+    /// replacing an existing field
+    #[pyo3(signature = (r, g, b, field="rgb"))]
+    pub fn pack_rgb(&mut self, r: PyReadonlyArray1<'_, u8>, g: PyReadonlyArray1<'_, u8>, b: PyReadonlyArray1<'_, u8>, field: &str) -> PyResult<()> {
+        self.pc.pack_rgb(r.as_array(), g.as_array(), b.as_array(), field)
+            .map_err(value_error)?;
+        self.auto_sync()
+    }
+
+    /// Names of the coordinate fields, detected as the fields named x, y and z, else renamed
+    /// to them by the field aliases, else named X, Y and Z (or in another case). None if any
+    /// is missing. Geometric methods use these fields when `fields` is not given
+    #[getter]
+    fn coordinate_fields(&self) -> Option<(String, String, String)> {
+        self.pc.coordinate_fields().map(|[x, y, z]| (x, y, z))
+    }
+
+    /// The coordinate fields (see `coordinate_fields`) as an (npoints, 3) array, float32 if
+    /// all three are float16 or float32 and float64 otherwise. Setting it assigns the columns of an
+    /// (npoints, 3) array to them. See `get_xyz` and `set_xyz` to choose the fields
+    #[getter(xyz)]
+    fn xyz_property<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        self.get_xyz(py, None)
+    }
+
+    #[setter(xyz)]
+    fn set_xyz_property(&mut self, array: &Bound<'_, PyAny>) -> PyResult<()> {
+        self.set_xyz(array, None)
+    }
+
+    /// Return the coordinate `fields` as an (npoints, 3) array, assembled in one pass
+    #[pyo3(signature = (fields=None))]
+    pub fn get_xyz<'py>(&self, py: Python<'py>, fields: Option<(String, String, String)>) -> PyResult<Bound<'py, PyAny>> {
+        let fields = self.coordinate_names(fields);
+        let xyz = self.pc.xyz([&fields.0, &fields.1, &fields.2])
+            .map_err(|e| PyKeyError::new_err(e.to_string()))?;
+        match xyz {
+            FieldData::F32(arr) => arr.to_pyarray(py).into_bound_py_any(py),
+            FieldData::F64(arr) => arr.to_pyarray(py).into_bound_py_any(py),
+            _ => unreachable!("hstack_float returns float32 or float64"),
+        }
+    }
+
+    /// Set the coordinate `fields` from the columns of an (npoints, 3) array. Existing fields
+    /// keep their dtype (raising a ValueError if a value cannot be represented in it), and
+    /// missing ones are added with the dtype of the array
+    #[pyo3(signature = (array, fields=None))]
+    pub fn set_xyz(&mut self, array: &Bound<'_, PyAny>, fields: Option<(String, String, String)>) -> PyResult<()> {
+        let fields = self.coordinate_names(fields);
+        let data = FieldData::from_pyarray_any(array)?;
+        self.pc.set_xyz([&fields.0, &fields.1, &fields.2], &data)
+            .map_err(value_error)?;
+        self.auto_sync()
+    }
+
+    /// Apply a 4x4 homogeneous transform in place to the coordinate fields
+    /// (and to normal_x/normal_y/normal_z, if present and `normals` is set)
+    #[pyo3(signature = (matrix, normals=true, fields=None))]
+    pub fn transform(&mut self, matrix: [[f64; 4]; 4], normals: bool, fields: Option<(String, String, String)>) -> PyResult<()> {
+        let fields = self.coordinate_names(fields);
+        self.pc.transform(&matrix, [&fields.0, &fields.1, &fields.2], normals.then_some(NORMAL_FIELDS))
+            .map_err(value_error)
+    }
+
+    /// Transform the coordinate fields (and the normals, if present and `normals` is set) in
+    /// place by the viewpoint, moving the points from the sensor frame into the world frame,
+    /// and reset the viewpoint
+    #[pyo3(signature = (normals=true, fields=None))]
+    pub fn apply_viewpoint(&mut self, normals: bool, fields: Option<(String, String, String)>) -> PyResult<()> {
+        let fields = self.coordinate_names(fields);
+        self.pc.apply_viewpoint([&fields.0, &fields.1, &fields.2], normals.then_some(NORMAL_FIELDS))
+            .map_err(value_error)
+    }
+
+    /// Translate the coordinate fields in place by [dx, dy, dz]
+    #[pyo3(signature = (offset, fields=None))]
+    pub fn translate(&mut self, offset: [f64; 3], fields: Option<(String, String, String)>) -> PyResult<()> {
+        let fields = self.coordinate_names(fields);
+        self.pc.translate(offset, [&fields.0, &fields.1, &fields.2])
+            .map_err(value_error)
+    }
+
+    /// Rotate the coordinate fields in place by a quaternion [w, x, y, z] or a 3x3 rotation matrix
+    #[pyo3(signature = (rotation, normals=true, fields=None))]
+    pub fn rotate(&mut self, rotation: &Bound<'_, PyAny>, normals: bool, fields: Option<(String, String, String)>) -> PyResult<()> {
+        let fields = self.coordinate_names(fields);
+        let r = if let Ok(q) = rotation.extract::<[f64; 4]>() {
+            transform::quaternion_to_rotation(q)
+                .map_err(value_error)?
+        } else if let Ok(r) = rotation.extract::<[[f64; 3]; 3]>() {
+            r
+        } else {
+            return Err(PyValueError::new_err("Rotation must be a quaternion [w, x, y, z] or a 3x3 matrix"));
+        };
+        self.pc.rotate(r, [&fields.0, &fields.1, &fields.2], normals.then_some(NORMAL_FIELDS))
+            .map_err(value_error)
+    }
+
+    /// Return a new PointCloud with points drawn at random without replacement.
+    /// `n` is either a number of points (int) or a fraction of the cloud (float in [0, 1]).
+    /// If `ignore_nan` is set, points with a NaN value in any field are removed before sampling.
+    #[pyo3(signature = (n, seed=None, ignore_nan=false))]
+    pub fn random_sample(&self, n: &Bound<'_, PyAny>, seed: Option<u64>, ignore_nan: bool) -> PyResult<Self> {
+        let pc = self.without_nan(ignore_nan)?;
+        let n = if let Ok(n) = n.extract::<usize>() {
+            n
+        } else {
+            let fraction = n.extract::<f64>()?;
+            if !(0.0..=1.0).contains(&fraction) {
+                return Err(PyValueError::new_err(format!("Sample fraction must be between 0 and 1, got {}", fraction)));
+            }
+            (fraction * pc.len() as f64).round() as usize
+        };
+        let pc = pc.random_sample(n, seed)
+            .map_err(value_error)?;
+        Ok(PyPointCloud { pc })
+    }
+
+    /// Return a new PointCloud containing every `every_k`-th point.
+    /// If `ignore_nan` is set, points with a NaN value in any field are removed before sampling.
+    #[pyo3(signature = (every_k, ignore_nan=false))]
+    pub fn uniform_sample(&self, every_k: usize, ignore_nan: bool) -> PyResult<Self> {
+        let pc = self.without_nan(ignore_nan)?.uniform_sample(every_k)
+            .map_err(value_error)?;
+        Ok(PyPointCloud { pc })
+    }
+
+    /// Return a new unorganized PointCloud without the points that have a NaN value in any of
+    /// `fields` (all fields if None), and a NumPy array of the indices of the retained points
+    #[pyo3(signature = (fields=None))]
+    pub fn remove_nan_points<'py>(&self, py: Python<'py>, fields: Option<Vec<String>>) -> PyResult<(Self, Bound<'py, PyArray1<usize>>)> {
+        let names: Option<Vec<&str>> = fields.as_ref().map(|f| f.iter().map(String::as_str).collect());
+        let (pc, indices) = self.pc.remove_nan_points(names.as_deref())
+            .map_err(|e| PyKeyError::new_err(e.to_string()))?;
+        Ok((PyPointCloud { pc }, PyArray1::from_vec(py, indices)))
+    }
+
+    /// Return a new unorganized PointCloud keeping only the first of each set of points with
+    /// duplicate coordinates, and a NumPy array of the indices of the retained points. With a
+    /// `tolerance` above 0, points within that distance of an earlier retained point are
+    /// duplicates. All fields of a removed point are dropped; points with a NaN coordinate
+    /// are always kept
+    #[pyo3(signature = (tolerance=0.0, fields=None))]
+    pub fn remove_duplicates<'py>(&self, py: Python<'py>, tolerance: f64, fields: Option<(String, String, String)>) -> PyResult<(Self, Bound<'py, PyArray1<usize>>)> {
+        let fields = self.coordinate_names(fields);
+        let (pc, indices) = py.allow_threads(|| self.pc.remove_duplicates(tolerance, [&fields.0, &fields.1, &fields.2]))
+            .map_err(value_error)?;
+        Ok((PyPointCloud { pc }, PyArray1::from_vec(py, indices)))
+    }
+
+    /// Return a new unorganized PointCloud with the points sorted by the values of `field`
+    /// (count of 1), ascending or descending. NaN values come last and points with equal
+    /// values keep their relative order
+    #[pyo3(signature = (field, descending=false))]
+    pub fn sort_by(&self, py: Python<'_>, field: &str, descending: bool) -> PyResult<Self> {
+        if !self.pc.fields.contains_key(field) {
+            return Err(PyKeyError::new_err(format!("Field '{}' not found", field)));
+        }
+        let pc = py.allow_threads(|| self.pc.sort_by(field, descending))
+            .map_err(value_error)?;
+        Ok(PyPointCloud { pc })
+    }
+
+    /// Return a new unorganized PointCloud with the points in Morton (Z-order) order of the
+    /// coordinate fields, so that points close in space are stored close together, which
+    /// improves compression and query locality. Points with a NaN coordinate come last
+    #[pyo3(signature = (fields=None))]
+    pub fn sort_morton(&self, py: Python<'_>, fields: Option<(String, String, String)>) -> PyResult<Self> {
+        let fields = self.coordinate_names(fields);
+        let pc = py.allow_threads(|| self.pc.sort_morton([&fields.0, &fields.1, &fields.2]))
+            .map_err(value_error)?;
+        Ok(PyPointCloud { pc })
+    }
+
+    /// Split the PointCloud by the values of an integer field, returning a dict mapping each
+    /// unique value to an unorganized PointCloud of the points with that value
+    pub fn split_by<'py>(&self, py: Python<'py>, field: &str) -> PyResult<Bound<'py, PyDict>> {
+        if !self.pc.fields.contains_key(field) {
+            return Err(PyKeyError::new_err(format!("Field '{}' not found", field)));
+        }
+        let groups = self.pc.split_by(field)
+            .map_err(value_error)?;
+        let dict = PyDict::new(py);
+        for (value, pc) in groups {
+            dict.set_item(value, PyPointCloud { pc })?;
+        }
+        Ok(dict)
+    }
+
+    /// Split the PointCloud into tiles of `cell_size` (a float, or an (x, y) pair) along the
+    /// x and y coordinates, returning a dict mapping each non-empty tile's (i, j) key, where
+    /// i = floor((x - origin[0]) / cell_size[0]) and likewise for j, to an unorganized
+    /// PointCloud of its points. Points with a NaN coordinate are left out. `fields` names
+    /// the (x, y) fields, by default the first two coordinate fields
+    #[pyo3(signature = (cell_size, origin=(0.0, 0.0), fields=None))]
+    pub fn tile<'py>(&self, py: Python<'py>, cell_size: &Bound<'py, PyAny>, origin: (f64, f64), fields: Option<(String, String)>) -> PyResult<Bound<'py, PyDict>> {
+        let cell_size = match cell_size.extract::<f64>() {
+            Ok(size) => [size, size],
+            Err(_) => cell_size.extract::<(f64, f64)>().map(|(x, y)| [x, y])
+                .map_err(|_| PyValueError::new_err("cell_size must be a float or an (x, y) pair of floats"))?,
+        };
+        let (x, y) = self.planar_names(fields);
+        let tiles = py.allow_threads(|| self.pc.tile(cell_size, [origin.0, origin.1], [&x, &y]))
+            .map_err(value_error)?;
+        let dict = PyDict::new(py);
+        for (key, pc) in tiles {
+            dict.set_item(key, PyPointCloud { pc })?;
+        }
+        Ok(dict)
+    }
+
+    /// Split the PointCloud into a grid of `grid` = (nx, ny) equal cells spanning the x and y
+    /// bounds of the points, returning a dict mapping each non-empty cell's (i, j) index to
+    /// an unorganized PointCloud of its points. Points with a NaN coordinate are left out.
+    /// `fields` names the (x, y) fields, by default the first two coordinate fields
+    #[pyo3(signature = (grid, fields=None))]
+    pub fn split_spatial<'py>(&self, py: Python<'py>, grid: (usize, usize), fields: Option<(String, String)>) -> PyResult<Bound<'py, PyDict>> {
+        let (x, y) = self.planar_names(fields);
+        let cells = py.allow_threads(|| self.pc.split_spatial([grid.0, grid.1], [&x, &y]))
+            .map_err(value_error)?;
+        let dict = PyDict::new(py);
+        for (key, pc) in cells {
+            dict.set_item(key, PyPointCloud { pc })?;
+        }
+        Ok(dict)
+    }
+
+    /// Rasterize `field` onto a 2D grid of `cell_size` cells (a float, or an (x, y) pair) over
+    /// the x and y bounds of the points, combining the values in each cell with `reducer`
+    /// ("max", "min", "mean" or "count"). Returns (grid, info): grid is a float64 array of
+    /// shape (rows, cols) where row 0 holds the lowest y values, with NaN for empty cells
+    /// (0 when counting); info is a dict with the "origin" (x, y) of the grid corner at
+    /// grid[0, 0], the "cell_size" and the GDAL-style affine "transform"
+    /// (origin_x, cell_x, 0, origin_y, 0, cell_y) mapping (col, row) to (x, y). Points with a
+    /// NaN coordinate or value are left out
+    #[pyo3(signature = (cell_size, field="z", reducer="max", fields=None))]
+    pub fn rasterize<'py>(&self, py: Python<'py>, cell_size: &Bound<'py, PyAny>, field: &str, reducer: &str, fields: Option<(String, String)>) -> PyResult<(Bound<'py, PyArray2<f64>>, Bound<'py, PyDict>)> {
+        let cell_size = match cell_size.extract::<f64>() {
+            Ok(size) => [size, size],
+            Err(_) => cell_size.extract::<(f64, f64)>().map(|(x, y)| [x, y])
+                .map_err(|_| PyValueError::new_err("cell_size must be a float or an (x, y) pair of floats"))?,
+        };
+        let reducer = Reducer::from_str(reducer)
+            .ok_or_else(|| PyValueError::new_err(format!("Invalid reducer '{}', expected 'max', 'min', 'mean' or 'count'", reducer)))?;
+        if !self.pc.fields.contains_key(field) {
+            return Err(PyKeyError::new_err(format!("Field '{}' not found", field)));
+        }
+        let (x, y) = self.planar_names(fields);
+        let raster = py.allow_threads(|| self.pc.rasterize(cell_size, field, reducer, [&x, &y]))
+            .map_err(value_error)?;
+        let [ox, oy] = raster.origin;
+        let [sx, sy] = raster.cell_size;
+        let info = PyDict::new(py);
+        info.set_item("origin", (ox, oy))?;
+        info.set_item("cell_size", (sx, sy))?;
+        info.set_item("transform", (ox, sx, 0.0, oy, 0.0, sy))?;
+        Ok((raster.data.to_pyarray(py), info))
+    }
+
+    /// Return the points visible from a pinhole camera and their pixel coordinates, as
+    /// (cloud, pixels) where pixels is a float64 array of shape (npoints, 2) holding (u, v).
+    /// `intrinsics` are (fx, fy, cx, cy) or a 3x3 camera matrix, `extrinsics` the 4x4 matrix
+    /// mapping points into the camera frame (x right, y down, z forward), or None if they are
+    /// already in it, and `image_size` is (width, height). Points are kept if their depth is
+    /// positive and within [near, far] and they project inside the image
+    #[pyo3(signature = (intrinsics, extrinsics, image_size, near=0.0, far=f64::INFINITY, fields=None))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn crop_frustum<'py>(
+        &self,
+        py: Python<'py>,
+        intrinsics: &Bound<'py, PyAny>,
+        extrinsics: Option<[[f64; 4]; 4]>,
+        image_size: (usize, usize),
+        near: f64,
+        far: f64,
+        fields: Option<(String, String, String)>,
+    ) -> PyResult<(Self, Bound<'py, PyArray2<f64>>)> {
+        let intrinsics = extract_intrinsics(intrinsics)?;
+        let extrinsics = extrinsics.unwrap_or_else(transform::identity);
+        let fields = self.coordinate_names(fields);
+        let (pc, pixels) = py.allow_threads(|| self.pc.crop_frustum(intrinsics, &extrinsics, [image_size.0, image_size.1], near, far, [&fields.0, &fields.1, &fields.2]))
+            .map_err(value_error)?;
+        let pixels = ndarray::Array2::from_shape_vec((pixels.len(), 2), pixels.into_iter().flatten().collect())
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok((PyPointCloud { pc }, pixels.to_pyarray(py)))
+    }
+
+    /// Return a new PointCloud with the points inside (or outside, if `invert`) an axis-aligned box
+    #[pyo3(signature = (min_bound, max_bound, invert=false, fields=None))]
+    pub fn crop(&self, min_bound: [f64; 3], max_bound: [f64; 3], invert: bool, fields: Option<(String, String, String)>) -> PyResult<Self> {
+        let fields = self.coordinate_names(fields);
+        let pc = self.pc.crop(min_bound, max_bound, invert, [&fields.0, &fields.1, &fields.2])
+            .map_err(value_error)?;
+        Ok(PyPointCloud { pc })
+    }
+
+    /// Minimum of a field, ignoring NaN values. Returns a float, or a list with one value
+    /// per component if the field has a count greater than 1
+    fn min<'py>(&self, py: Python<'py>, field: &str) -> PyResult<Bound<'py, PyAny>> {
+        self.field_stat(py, field, |s| s.min)
+    }
+
+    /// Maximum of a field, ignoring NaN values. See `min`
+    fn max<'py>(&self, py: Python<'py>, field: &str) -> PyResult<Bound<'py, PyAny>> {
+        self.field_stat(py, field, |s| s.max)
+    }
+
+    /// Mean of a field, ignoring NaN values. See `min`
+    fn mean<'py>(&self, py: Python<'py>, field: &str) -> PyResult<Bound<'py, PyAny>> {
+        self.field_stat(py, field, |s| s.mean)
+    }
+
+    /// Population standard deviation of a field, ignoring NaN values. See `min`
+    fn std<'py>(&self, py: Python<'py>, field: &str) -> PyResult<Bound<'py, PyAny>> {
+        self.field_stat(py, field, |s| s.std)
+    }
+
+    /// Histogram of a field, computed natively without copying the field: returns the counts
+    /// (int64 array of length `bins`) and the bin edges (float64 array of length `bins + 1`).
+    /// `range` defaults to the minimum and maximum values. Follows `numpy.histogram`, except
+    /// that NaN values are ignored
+    #[pyo3(signature = (field, bins=256, range=None))]
+    fn histogram<'py>(&self, py: Python<'py>, field: &str, bins: usize, range: Option<(f64, f64)>) -> PyResult<HistogramResult<'py>> {
+        if !self.pc.fields.contains_key(field) {
+            return Err(PyKeyError::new_err(format!("Field '{}' not found", field)));
+        }
+        let (counts, edges) = py.allow_threads(|| self.pc.histogram(field, bins, range))
+            .map_err(value_error)?;
+        Ok((PyArray1::from_iter(py, counts.into_iter().map(|c| c as i64)), PyArray1::from_vec(py, edges)))
     }
 
-    #[staticmethod]
-    pub fn from_metadata(metadata: &Bound<'_, PyMetadata>) -> PyResult<Self> {
-        // Cache metadata by acquiring a read lock once.
-        let meta = {
-            let borrowed = metadata.borrow();
-            let guard = borrowed.inner.read().unwrap();
-            guard.clone()
+    /// Print a summary of the cloud to `file` (sys.stdout by default): its shape and memory
+    /// footprint, then each field's dtype, count, minimum and maximum over all components,
+    /// number of NaN values and size in bytes
+    #[pyo3(signature = (file=None))]
+    fn info(&self, py: Python<'_>, file: Option<&Bound<'_, PyAny>>) -> PyResult<()> {
+        let info = py.allow_threads(|| self.pc.info())
+            .map_err(value_error)?;
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("end", "")?;
+        if let Some(file) = file {
+            kwargs.set_item("file", file)?;
+        }
+        py.import("builtins")?.getattr("print")?.call((info,), Some(&kwargs))?;
+        Ok(())
+    }
+
+    /// Show the cloud in 3D in a Jupyter notebook, drawing at most `max_points` points (a fixed
+    /// random sample of larger clouds) from the coordinate `fields`. Points are colored by
+    /// `color_field`: a packed rgb/rgba field or uint8 colors with a count of 3 as is, any
+    /// other field through a colormap, by default the height. `backend` is "k3d" (the default
+    /// if k3d is installed), returning a k3d plot, or "html", returning an IPython HTML object
+    /// (or the HTML string without IPython) with a built-in three.js viewer. `point_size` is
+    /// in cloud units for k3d (by default 1/500 of the bounding box diagonal) and in pixels
+    /// for html (by default 2)
+    #[pyo3(signature = (max_points=500_000, color_field=None, point_size=None, backend=None, fields=None))]
+    fn plot<'py>(&self, py: Python<'py>, max_points: usize, color_field: Option<&str>, point_size: Option<f32>,
+                 backend: Option<&str>, fields: Option<(String, String, String)>) -> PyResult<Bound<'py, PyAny>> {
+        let fields = self.coordinate_names(fields);
+        let names = [fields.0.as_str(), fields.1.as_str(), fields.2.as_str()];
+        if let Some(name) = names.into_iter().chain(color_field).find(|name| !self.pc.fields.contains_key(*name)) {
+            return Err(PyKeyError::new_err(format!("Field '{}' not found", name)));
+        }
+        let k3d = match backend {
+            None => py.import("k3d").ok(),
+            Some("k3d") => Some(py.import("k3d")?),
+            Some("html") => None,
+            Some(backend) => return Err(PyValueError::new_err(format!("Unknown backend '{}', expected 'k3d' or 'html'", backend))),
         };
-        let pc = PointCloud::new(&meta);
-        Ok(PyPointCloud { pc })
+        let data = py.allow_threads(|| self.pc.plot_data(names, max_points, color_field))
+            .map_err(value_error)?;
+
+        let Some(k3d) = k3d else {
+            let html = crate::viewer::plot_html(&data, point_size.unwrap_or(2.0), 500);
+            return match py.import("IPython.display") {
+                Ok(display) => display.getattr("HTML")?.call1((html,)),
+                Err(_) => html.into_bound_py_any(py),
+            };
+        };
+        let point_size = point_size.unwrap_or_else(|| {
+            let (lo, hi) = data.positions.iter().fold(([f64::INFINITY; 3], [f64::NEG_INFINITY; 3]), |(lo, hi), p| {
+                ([0, 1, 2].map(|c| lo[c].min(p[c])), [0, 1, 2].map(|c| hi[c].max(p[c])))
+            });
+            let diagonal = (0..3).map(|c| (hi[c] - lo[c]).powi(2)).sum::<f64>().sqrt();
+            if diagonal.is_finite() && diagonal > 0.0 { (diagonal / 500.0) as f32 } else { 1.0 }
+        });
+        let positions: Vec<f32> = data.positions.iter().flat_map(|p| p.map(|v| v as f32)).collect();
+        let positions = PyArray1::from_vec(py, positions).reshape([data.positions.len(), 3])?;
+        let colors = PyArray1::from_iter(py, data.colors.iter().map(|c| (c[0] as u32) << 16 | (c[1] as u32) << 8 | c[2] as u32));
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("colors", colors)?;
+        kwargs.set_item("point_size", point_size)?;
+        kwargs.set_item("shader", "flat")?;
+        let plot = k3d.call_method0("plot")?;
+        plot.call_method1("__iadd__", (k3d.call_method("points", (positions,), Some(&kwargs))?,))?;
+        Ok(plot)
     }
 
-    pub fn save(&self, path: &str) -> PyResult<()> {
-        self.pc.to_pcd_file(path)
-            .map_err(|e| pyo3::exceptions::PyIOError::new_err(e.to_string()))?;
-        Ok(())
+    /// Size in bytes of the values of every field. Fields shared with a copy of the cloud are
+    /// counted in full
+    #[getter]
+    fn nbytes(&self) -> usize {
+        self.pc.nbytes()
+    }
+
+    /// Return a dict mapping each field name to a dict of its "count", "min", "max", "mean"
+    /// and "std", ignoring NaN values. See `min` for the type of each value
+    fn describe<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let described = self.pc.describe()
+            .map_err(value_error)?;
+        let dict = PyDict::new(py);
+        for (name, stats) in described {
+            let field = PyDict::new(py);
+            field.set_item("count", stats_to_py(py, &stats, |s| s.count)?)?;
+            field.set_item("min", stats_to_py(py, &stats, |s| s.min)?)?;
+            field.set_item("max", stats_to_py(py, &stats, |s| s.max)?)?;
+            field.set_item("mean", stats_to_py(py, &stats, |s| s.mean)?)?;
+            field.set_item("std", stats_to_py(py, &stats, |s| s.std)?)?;
+            dict.set_item(name, field)?;
+        }
+        Ok(dict)
+    }
+
+    /// Return the axis-aligned bounding box of the coordinate fields, ignoring NaN points.
+    /// The box unpacks as (min_bound, max_bound)
+    #[pyo3(signature = (fields=None))]
+    pub fn get_aabb(&self, fields: Option<(String, String, String)>) -> PyResult<PyAxisAlignedBoundingBox> {
+        let fields = self.coordinate_names(fields);
+        let aabb = self.pc.aabb([&fields.0, &fields.1, &fields.2])
+            .map_err(value_error)?;
+        Ok(PyAxisAlignedBoundingBox { aabb })
+    }
+
+    /// Return an oriented bounding box of the coordinate fields, aligned with their principal
+    /// components and ignoring NaN points
+    #[pyo3(signature = (fields=None))]
+    pub fn get_obb(&self, fields: Option<(String, String, String)>) -> PyResult<PyOrientedBoundingBox> {
+        let fields = self.coordinate_names(fields);
+        let obb = self.pc.obb([&fields.0, &fields.1, &fields.2])
+            .map_err(value_error)?;
+        Ok(PyOrientedBoundingBox { obb })
+    }
+
+    /// Return the mean of the coordinate fields as a NumPy array of shape (3,), ignoring NaN points
+    #[pyo3(signature = (fields=None))]
+    pub fn centroid<'py>(&self, py: Python<'py>, fields: Option<(String, String, String)>) -> PyResult<Bound<'py, PyArray1<f64>>> {
+        let fields = self.coordinate_names(fields);
+        let centroid = self.pc.centroid([&fields.0, &fields.1, &fields.2])
+            .map_err(value_error)?;
+        Ok(PyArray1::from_slice(py, &centroid))
+    }
+
+    /// Principal component analysis of the coordinate fields, ignoring NaN points.
+    /// Returns (eigenvalues, eigenvectors): the covariance eigenvalues in descending order,
+    /// and a 3x3 rotation whose columns are the matching eigenvectors
+    #[pyo3(signature = (fields=None))]
+    pub fn pca<'py>(&self, py: Python<'py>, fields: Option<(String, String, String)>) -> PyResult<PcaResult<'py>> {
+        let fields = self.coordinate_names(fields);
+        let pca = self.pc.pca([&fields.0, &fields.1, &fields.2])
+            .map_err(value_error)?;
+        let v = pca.eigenvectors;
+        let eigenvectors = ndarray::Array2::from_shape_fn((3, 3), |(i, j)| v[i][j]).to_pyarray(py);
+        Ok((PyArray1::from_slice(py, &pca.eigenvalues), eigenvectors))
+    }
+
+    /// Build a k-d tree spatial index over the coordinate fields
+    #[pyo3(signature = (fields=None))]
+    pub fn build_kdtree(&self, fields: Option<(String, String, String)>) -> PyResult<PyKdTree> {
+        let fields = self.coordinate_names(fields);
+        let tree = self.pc.build_kdtree([&fields.0, &fields.1, &fields.2])
+            .map_err(value_error)?;
+        Ok(PyKdTree { tree })
+    }
+
+    /// Distance from each point to its nearest neighbor in `other`, as a float64 array, using
+    /// the coordinate `fields` of both clouds (detected from this cloud if not given). With
+    /// `signed`, distances are negative for points behind the normal (normal_x, normal_y,
+    /// normal_z of `other`) of their nearest neighbor. Points with a NaN coordinate get NaN
+    #[pyo3(signature = (other, signed=false, fields=None))]
+    pub fn distance_to<'py>(&self, py: Python<'py>, other: PyRef<'py, Self>, signed: bool, fields: Option<(String, String, String)>) -> PyResult<Bound<'py, PyArray1<f64>>> {
+        let fields = self.coordinate_names(fields);
+        if signed && !NORMAL_FIELDS.iter().all(|name| other.pc.fields.contains_key(name)) {
+            return Err(PyKeyError::new_err("Signed distances need normal_x, normal_y and normal_z fields in the other PointCloud"));
+        }
+        let other = &other.pc;
+        let distances = py.allow_threads(|| self.pc.distance_to(other, [&fields.0, &fields.1, &fields.2], signed.then_some(NORMAL_FIELDS)))
+            .map_err(value_error)?;
+        Ok(PyArray1::from_vec(py, distances))
+    }
+
+    /// Build an octree of `depth` levels over the coordinate fields, for level-of-detail
+    /// sampling and box queries. Points with a NaN coordinate are left out
+    #[pyo3(signature = (depth=10, fields=None))]
+    pub fn build_octree(&self, py: Python<'_>, depth: usize, fields: Option<(String, String, String)>) -> PyResult<PyOctree> {
+        let fields = self.coordinate_names(fields);
+        let tree = py.allow_threads(|| self.pc.build_octree(depth, [&fields.0, &fields.1, &fields.2]))
+            .map_err(value_error)?;
+        Ok(PyOctree { tree })
+    }
+
+    /// Names of the fields, in schema (header) order
+    #[getter]
+    fn field_names(&self) -> Vec<String> {
+        self.pc.field_names()
+    }
+
+    /// True if the PointCloud is organized (laid out as an image with more than one row)
+    #[getter]
+    fn is_organized(&self) -> bool {
+        self.pc.is_organized()
+    }
+
+    /// Return the point at image coordinates (`row`, `col`) as a dict mapping each field name
+    /// to a 1D NumPy array of its values
+    fn at<'py>(&self, py: Python<'py>, row: usize, col: usize) -> PyResult<Bound<'py, PyDict>> {
+        let idx = self.pc.point_index(row, col)
+            .map_err(|e| PyIndexError::new_err(e.to_string()))?;
+        self.point_dict(py, idx)
+    }
+
+    /// Return point `i` (negative values count from the end) as a dict mapping each field
+    /// name to a 1D NumPy array of its values
+    fn point<'py>(&self, py: Python<'py>, i: isize) -> PyResult<Bound<'py, PyDict>> {
+        let npoints = self.pc.len();
+        let idx = if i < 0 { i + npoints as isize } else { i };
+        if idx < 0 || idx >= npoints as isize {
+            return Err(PyIndexError::new_err(format!("Index {} is out of bounds for {} points", i, npoints)));
+        }
+        self.point_dict(py, idx as usize)
+    }
+
+    /// Return an iterator over the points, each a dict as returned by `point`
+    fn points(slf: Bound<'_, Self>) -> PyPointIterator {
+        PyPointIterator { cloud: slf.unbind(), index: 0 }
+    }
+
+    /// Return a list of all points, each a dict as returned by `point`
+    fn to_points<'py>(&self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        (0..self.pc.len()).map(|i| self.point_dict(py, i)).collect()
+    }
+
+    fn __iter__(slf: Bound<'_, Self>) -> PyPointIterator {
+        Self::points(slf)
     }
 
     fn __len__(&self) -> usize {
         self.pc.len()
     }
 
+    /// Summary of the metadata, followed by a table of the first and last points (see
+    /// `set_repr_points`)
     fn __repr__(&self) -> String {
         let md = self.pc.metadata.read().unwrap();
-        format!("PointCloud\n Fields:\n{}\n Points: {}, Width: {}, Height: {}\n Viewpoint: {}\n Encoding: {}\n Version: {}",
+        let mut repr = format!("PointCloud\n Fields:\n{}\n Points: {}, Width: {}, Height: {}\n Viewpoint: {}\n Encoding: {}\n Version: {}",
             md.fields,
             md.npoints,
             md.width,
@@ -51,82 +1476,187 @@ impl PyPointCloud {
             md.viewpoint,
             md.encoding.as_str(),
             md.version,
-        )
+        );
+        drop(md);
+        let (columns, rows) = preview_table(&self.pc);
+        if !rows.is_empty() {
+            let ncols = columns.len() + 1;
+            let cells: Vec<Vec<String>> = std::iter::once(std::iter::once(String::new()).chain(columns).collect())
+                .chain(rows.into_iter().map(|row| match row {
+                    Some((index, values)) => std::iter::once(index.to_string()).chain(values).collect(),
+                    None => vec!["...".to_string(); ncols],
+                }))
+                .collect();
+            let widths: Vec<usize> = (0..cells[0].len())
+                .map(|j| cells.iter().map(|row| row[j].chars().count()).max().unwrap_or(0))
+                .collect();
+            repr.push('\n');
+            for row in cells {
+                let line = row.iter().zip(&widths)
+                    .map(|(cell, &width)| format!("{:>width$}", cell))
+                    .collect::<Vec<_>>()
+                    .join("  ");
+                repr.push_str(&format!("\n {}", line));
+            }
+        }
+        repr
+    }
+
+    /// HTML summary and table of the first and last points, shown by Jupyter
+    fn _repr_html_(&self) -> String {
+        let md = self.pc.metadata.read().unwrap();
+        let mut html = format!("<p><b>PointCloud</b>: {} points (width {}, height {}), {} fields</p>",
+            md.npoints, md.width, md.height, md.fields.len());
+        drop(md);
+        let (columns, rows) = preview_table(&self.pc);
+        if rows.is_empty() {
+            return html;
+        }
+        html.push_str("<table>\n<thead><tr><th></th>");
+        for column in &columns {
+            html.push_str(&format!("<th>{}</th>", escape_html(column)));
+        }
+        html.push_str("</tr></thead>\n<tbody>\n");
+        for row in rows {
+            match row {
+                Some((index, values)) => {
+                    html.push_str(&format!("<tr><th>{}</th>", index));
+                    for value in values {
+                        html.push_str(&format!("<td>{}</td>", value));
+                    }
+                }
+                None => html.push_str(&format!("<tr><th>...</th>{}", "<td>...</td>".repeat(columns.len()))),
+            }
+            html.push_str("</tr>\n");
+        }
+        html.push_str("</tbody>\n</table>");
+        html
     }
 
     #[getter]
-    pub fn metadata(&self) -> PyMetadata {
+    pub fn metadata(slf: &Bound<'_, Self>) -> PyMetadata {
         PyMetadata {
-            inner: self.pc.metadata.clone()
+            inner: slf.borrow().pc.metadata.clone(),
+            cloud: Some(slf.clone().unbind()),
         }
     }
 
     /// Get a field by name
     /// Returns None if field does not exist
     /// Returns a 2D Numpy array if field exists (npoints, count)
-    fn get_field<'py>(&self, py: Python<'py>, field_name: &str) -> PyResult<Option<Bound<'py, PyAny>>> {
-        if let Some(field_data) = self.pc.fields.get(field_name) {
-            Ok(Some(field_data.into_pyobject(py)?))
+    /// If `copy` is False, the array is a read-only view sharing the field's memory
+    /// If `dtype` is given, a converted copy is returned, with values that cannot be represented
+    /// handled according to `policy` ("error", "saturate" or "wrap")
+    /// If `squeeze` is True, a field with a count of 1 is returned as a 1D array (npoints,)
+    #[pyo3(signature = (field_name, copy=true, dtype=None, policy="error", squeeze=false))]
+    fn get_field<'py>(&self, py: Python<'py>, field_name: &str, copy: bool, dtype: Option<&str>, policy: &str, squeeze: bool) -> PyResult<Option<Bound<'py, PyAny>>> {
+        let Some(field_data) = self.pc.fields.get(field_name) else {
+            return Ok(None);
+        };
+        let array = if let Some(dtype) = dtype {
+            cast_field_data(field_data, dtype, policy)?.to_pyobject(py)?
+        } else if copy {
+            field_data.to_pyobject(py)?
         } else {
-            Ok(None)
+            field_data.to_pyarray_view(py)?
+        };
+        if squeeze && field_data.count() == 1 {
+            // A view of the same memory, so a read-only view stays read-only
+            return Ok(Some(array.call_method1("reshape", (field_data.npoints(),))?));
         }
+        Ok(Some(array))
     }
     
-    /// Get a field by name, reshaped to (width, height)
+    /// Get a field by name, laid out as an image
     /// Returns None if field does not exist
-    /// Returns a 3D Numpy array if field exists (height, width, count)
-    fn get_field_shaped<'py>(&self, py: Python<'py>, field_name: &str) -> PyResult<Option<Bound<'py, PyAny>>> {
+    /// Returns a 3D Numpy array if field exists, of shape (height, width, count) indexed by
+    /// row then column with `order="hw"` (the row-major layout of PCD data, as in PCL), or
+    /// (width, height, count) with `order="wh"`
+    /// `dtype` and `policy` convert the values as in `get_field`
+    /// Raises a ValueError if width x height does not match the number of points
+    #[pyo3(signature = (field_name, dtype=None, policy="error", order="hw"))]
+    fn get_field_shaped<'py>(&self, py: Python<'py>, field_name: &str, dtype: Option<&str>, policy: &str, order: &str) -> PyResult<Option<Bound<'py, PyAny>>> {
+        let order = ImageOrder::from_str(order)
+            .ok_or_else(|| PyValueError::new_err(format!("Invalid order '{}', expected 'hw' or 'wh'", order)))?;
         let (width, height) = {
             let md = self.pc.metadata.read().unwrap();
             (md.width, md.height)
         };
         if let Some(field_data) = self.pc.fields.get(field_name) {
-            Ok(Some(field_data.into_pyobject_shaped(py, width, height)?))
+            if width * height != field_data.npoints() {
+                return Err(PyValueError::new_err(format!(
+                    "Cannot shape field '{}': width x height ({} x {}) does not match its {} points; \
+                    call sync_metadata() to make the PointCloud unorganized", field_name, width, height, field_data.npoints())));
+            }
+            if let Some(dtype) = dtype {
+                return Ok(Some(cast_field_data(field_data, dtype, policy)?.into_pyobject_shaped(py, width, height, order)?));
+            }
+            Ok(Some(field_data.into_pyobject_shaped(py, width, height, order)?))
         } else {
             Ok(None)
         }
     }
 
+    /// Return the distance of each point from the sensor origin as a (height, width) array,
+    /// computed from the coordinate fields. NaN coordinates give a NaN range.
+    #[pyo3(signature = (fields=None))]
+    fn to_range_image<'py>(&self, py: Python<'py>, fields: Option<(String, String, String)>) -> PyResult<Bound<'py, PyArray2<f64>>> {
+        let fields = self.coordinate_names(fields);
+        let ranges = self.pc.range_image([&fields.0, &fields.1, &fields.2])
+            .map_err(value_error)?;
+        Ok(PyArray2::from_owned_array(py, ranges))
+    }
+
+    /// Create an organized PointCloud with x, y, z fields by back-projecting a (height, width)
+    /// depth image through pinhole intrinsics, given as (fx, fy, cx, cy) or a 3x3 camera matrix.
+    /// Pixels without a finite, positive depth become NaN points.
+    #[staticmethod]
+    fn from_depth_image(depth: &Bound<'_, PyAny>, intrinsics: &Bound<'_, PyAny>) -> PyResult<Self> {
+        let intrinsics = extract_intrinsics(intrinsics)?;
+        let depth = depth.call_method1("astype", ("float64",))?;
+        let depth = depth.extract::<PyReadonlyArray2<f64>>()?;
+        let pc = PointCloud::from_depth_image(depth.as_array(), intrinsics)
+            .map_err(value_error)?;
+        Ok(PyPointCloud { pc })
+    }
+
     /// Implement __getitem__ in Python:
     ///   - If key is a str or list/tuple of str => treat as field(s).
     ///   - If key is a slice => return a *new* sliced PointCloud.
+    ///   - If key is a (rows, cols) tuple of slices/ints with at least one slice => return a *new* organized sub-cloud.
+    ///   - If key is a 1D (or (n, 1), e.g. a boolean field) boolean NumPy array => return a *new* PointCloud of the selected points.
+    ///   - If key is a 1D integer NumPy array or list of ints => return a *new* PointCloud of those points, in order.
     ///   - If key is a list/tuple of strings => return a combined 2D NumPy array.
     fn __getitem__<'py>(&self, key: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyAny>> {
         let py = key.py();
         // Check if key is a slice object => return a sliced PointCloud
         if let Ok(slice) = key.downcast::<PySlice>() {
-            let indices = slice.indices(self.pc.len() as isize)?;
-            let start = indices.start as usize;
-            let stop = indices.stop as usize;
-            let step = indices.step as usize;
-
-            // Create a new, sliced PointCloud
-            let md = {
-                let md_read = self.pc.metadata.read().unwrap();
-                md_read.clone()
-            };
-            let mut new_md = md.clone();
-            new_md.trim((stop - start) / step);
-            let mut new_pc = PointCloud::empty(&new_md);
+            let new_pc = self.slice_points(slice, None)?;
+            PyPointCloud { pc: new_pc }.into_bound_py_any(py)
+        }
 
-            for (field_name, field_data) in &self.pc.fields {
-                let data_slice = field_data.slice(start, stop, step);
-                new_pc.fields.insert(field_name.clone(), data_slice);
-            }
+        // Check if key is a (rows, cols) pair with at least one slice => return an organized sub-cloud
+        else if let Some((rows, cols)) = self.extract_grid(key)? {
+            let new_pc = self.pc.take_grid(&rows, &cols)
+                .map_err(|e| PyIndexError::new_err(e.to_string()))?;
+            PyPointCloud { pc: new_pc }.into_bound_py_any(py)
+        }
 
-            return Ok(PyPointCloud { pc: new_pc }.into_bound_py_any(py)?);
+        // Check if key is a boolean mask => return a filtered PointCloud
+        else if let Some(mask) = extract_mask(key) {
+            let new_pc = self.pc.select_mask(&mask)
+                .map_err(value_error)?;
+            PyPointCloud { pc: new_pc }.into_bound_py_any(py)
         }
 
         // Check if key is a string => return one field as a Numpy array
         else if let Ok(field_name) = key.extract::<String>() {
             if let Some(field_data) = self.pc.fields.get(&field_name) {
                 // Return the field as a NumPy array
-                let arr = field_data.into_pyobject(py)?;
-                return Ok(arr.into_bound_py_any(py)?);
+                let arr = field_data.to_pyobject(py)?;
+                arr.into_bound_py_any(py)
             } else { // Python KeyError
-                return Err(
-                    PyKeyError::new_err(format!("No field named '{}'", field_name)
-                ));
+                Err(PyKeyError::new_err(format!("No field named '{}'", field_name)))
             }
         }
 
@@ -143,7 +1673,7 @@ impl PyPointCloud {
             for field_name in field_names {
                 if let Some(field_data) = self.pc.fields.get(&field_name) {
                     let ncols_field = field_data.count();
-                    let source_array = field_data.into_pyarray(py)?;
+                    let source_array = field_data.to_pyarray(py)?;
                     let source_view = unsafe { source_array.as_array() };
             
                     // Copy values using ndarray slice assignment
@@ -155,71 +1685,145 @@ impl PyPointCloud {
                     return Err(PyKeyError::new_err(format!("No field named '{}'", field_name)));
                 }
             }
-            return Ok(arr2d.into_bound_py_any(py)?);
+            arr2d.into_bound_py_any(py)
+        }
+
+        // Check if key is an integer array => return a PointCloud of the indexed points
+        else if let Some(indices) = extract_indices(key, self.pc.len())? {
+            let new_pc = self.pc.take_rows(&indices)
+                .map_err(value_error)?;
+            PyPointCloud { pc: new_pc }.into_bound_py_any(py)
         }
 
         else {
-            return Err(PyKeyError::new_err("Invalid key type. Must be a str, list/tuple of str, or slice."));
+            Err(PyKeyError::new_err("Invalid key type. Must be a str, list/tuple of str, slice, boolean mask, or integer array."))
         }
     }
 
     /// Implement __setitem__:
-    ///   - If key is a string => set/update a field with dtype inference
+    ///   - If key is a string => set/update a field with dtype inference, from an (npoints, count) or (npoints,) array
     ///   - If key is a list/tuple of strings => update each of those fields from a combined 2D NumPy array.
     ///   - If key is a slice => update the corresponding rows of the PointCloud from a provided PyPointCloud.
+    ///   - If key is a 1D boolean NumPy array => update the selected rows from a provided PyPointCloud.
+    ///   - If key is a 1D integer NumPy array or list of ints => update those rows from a provided PyPointCloud.
+    ///   - If key is a (str, rows) tuple with rows a slice, boolean mask or integer array => update those rows
+    ///     of one field from a NumPy array, converted to the field's dtype.
+    ///   - If value is a scalar and key is a str or (str, rows) tuple => set every selected value of that
+    ///     existing field to the scalar, converted to the field's dtype.
+    /// Field values can also be lists, tuples or other array-likes, converted through NumPy (then to the
+    /// dtype of an existing field).
     fn __setitem__<'py>(&mut self, key: &Bound<'py, PyAny>, value: &Bound<'py, PyAny>) -> PyResult<()> {
+        self.set_item(key, value)?;
+        self.auto_sync()
+    }
+}
+
+impl PyPointCloud {
+    /// Return the given coordinate field names, or the detected ones (see
+    /// `coordinate_fields`), falling back to x, y and z.
+    pub fn coordinate_names(&self, fields: Option<(String, String, String)>) -> (String, String, String) {
+        fields.unwrap_or_else(|| {
+            let [x, y, z] = self.pc.coordinate_fields()
+                .unwrap_or_else(|| ["x", "y", "z"].map(String::from));
+            (x, y, z)
+        })
+    }
+
+    /// The (x, y) field names to use for planar operations: `fields` if given, else the first
+    /// two coordinate fields.
+    fn planar_names(&self, fields: Option<(String, String)>) -> (String, String) {
+        fields.unwrap_or_else(|| {
+            let (x, y, _) = self.coordinate_names(None);
+            (x, y)
+        })
+    }
+
+    fn apply_op(&mut self, py: Python<'_>, field: &str, op: FieldOp, dtype: Option<&str>, policy: &str) -> PyResult<()> {
+        if !self.pc.fields.contains_key(field) {
+            return Err(PyKeyError::new_err(format!("Field '{}' not found", field)));
+        }
+        let dtype = dtype
+            .map(|d| Dtype::from_numpy_dtype(d).ok_or_else(|| unsupported_dtype(d)))
+            .transpose()?;
+        let policy = parse_cast_policy(policy)?;
+        let pc = &mut self.pc;
+        py.allow_threads(|| pc.apply(field, op, dtype, policy))
+            .map_err(value_error)?;
+        self.auto_sync()
+    }
+
+    /// Resynchronize and check the metadata after a mutation, if auto-sync is enabled.
+    fn auto_sync(&self) -> PyResult<()> {
+        if AUTO_SYNC.load(Ordering::Relaxed) {
+            self.sync_metadata()?;
+        }
+        Ok(())
+    }
+
+    fn set_item<'py>(&mut self, key: &Bound<'py, PyAny>, value: &Bound<'py, PyAny>) -> PyResult<()> {
+        // If key is a (field, rows) pair: update some points of a single field.
+        if let Some((field_name, rows)) = extract_field_rows(key)? {
+            self.set_field_rows(&field_name, Some(&rows), value)
+        }
+
+        // If key is a string and value a scalar: set every point of an existing field.
+        else if let (Ok(field_name), true) = (key.extract::<String>(), is_scalar(value)) {
+            self.set_field_rows(&field_name, None, value)
+        }
+
         // If key is a string: update a single field.
-        if let Ok(field_name) = key.extract::<String>() {
-            // Infer dtype from Numpy array and store it in PointCloud fields
-            infer_and_store_field(&mut self.pc, &field_name, value)?;
-            Ok(())
+        else if let Ok(field_name) = key.extract::<String>() {
+            if value.downcast::<PyUntypedArray>().is_err() && self.pc.fields.contains_key(&field_name) {
+                // Array-likes get NumPy's default dtype, so convert their values to the field's
+                self.set_field_rows(&field_name, None, value)
+            } else {
+                // Infer dtype from Numpy array and store it in PointCloud fields
+                infer_and_store_field(&mut self.pc, &field_name, &as_ndarray(value)?)
+            }
         }
         
         // If key is a list/tuple of strings.
         else if let Ok(field_names) = key.extract::<Vec<String>>() {
-            let py = key.py();
-            // Expect value to be a NumPy array of shape (npoints, total_columns)
-            let arr = value.downcast::<PyArray2<f64>>()?;
-            let arr = unsafe { arr.as_array() };
+            // Expect value to be a NumPy array of any supported dtype, of shape (npoints, total_columns).
+            // Each field's columns are converted to the field's dtype.
+            let arr = FieldData::from_pyarray_any(&as_ndarray(value)?)?;
             let npoints = self.pc.len();
-            let arr_shape = arr.shape();
-            if arr_shape[0] != npoints {
+            if arr.npoints() != npoints {
                 return Err(PyValueError::new_err(format!(
                     "Row count mismatch: expected {} rows, got {}",
-                    npoints, arr_shape[0]
-                )));
-            }
-            let md = self.pc.metadata.read().unwrap();
-            let total_expected_columns: usize = md.fields.iter()
-                .filter_map(|f| if field_names.contains(&f.name) { Some(f.count) } else { None })
-                .sum();
-            if arr_shape[1] != total_expected_columns {
-                return Err(PyValueError::new_err(format!(
-                    "Column count mismatch: expected {} columns, got {}",
-                    total_expected_columns, arr_shape[1]
+                    npoints, arr.npoints()
                 )));
             }
+            let new_fields = {
+                let md = self.pc.metadata.read().unwrap();
+                let total_expected_columns: usize = md.fields.iter()
+                    .filter_map(|f| if field_names.contains(&f.name) { Some(f.count) } else { None })
+                    .sum();
+                if arr.count() != total_expected_columns {
+                    return Err(PyValueError::new_err(format!(
+                        "Column count mismatch: expected {} columns, got {}",
+                        total_expected_columns, arr.count()
+                    )));
+                }
 
-            let mut col_start: usize = 0;
-            for field_name in field_names {
-                // Look up metadata for this field.
-                if let Some(field_meta) = md.fields.iter().find(|f| f.name == field_name) {
+                // Convert every field before assigning any, so that a failed conversion leaves
+                // the PointCloud unchanged.
+                let mut new_fields = Vec::with_capacity(field_names.len());
+                let mut col_start: usize = 0;
+                for field_name in field_names {
+                    let field_meta = md.fields.iter().find(|f| f.name == field_name)
+                        .ok_or_else(|| PyKeyError::new_err(format!("No field named '{}'", field_name)))?;
                     let col_end = col_start + field_meta.count;
-                    // Extract the slice corresponding to this field (shape: [npoints, count]).
-                    let field_slice = arr.slice(s![.., col_start..col_end]).to_owned();
-                    // Convert the ndarray into a Python object.
-                    let field_slice_py = field_slice.to_pyarray(py);
-                    // Convert that Python object into FieldData using the dtype from metadata.
-                    let new_field_data = FieldData::from_pyarray(field_slice_py.as_ref(), field_meta.dtype)
-                        .map_err(|e| PyValueError::new_err(e.to_string()))?;
-                    // Update the field in the PointCloud.
-                    self.pc.fields.insert(field_name, new_field_data);
+                    let new_field_data = arr.columns(col_start, col_end)
+                        .cast(field_meta.dtype, CastPolicy::Error)
+                        .map_err(|e| PyValueError::new_err(format!("Field '{}': {}", field_name, e)))?;
+                    new_fields.push((field_name, new_field_data));
                     col_start = col_end;
-                } else {
-                    return Err(PyKeyError::new_err(format!("No field named '{}'", field_name)));
                 }
-            }
-            return Ok(());
+                new_fields
+            };
+            self.pc.fields.extend(new_fields);
+            Ok(())
         }
 
         // If key is a slice.
@@ -238,7 +1842,7 @@ impl PyPointCloud {
                     new_pc.pc.len()
                 )));
             }
-            let orig_range = std::ops::Range{ start: start, end: stop };
+            let orig_range = std::ops::Range{ start, end: stop };
             let orig_step = step;
             let new_range =  std::ops::Range{ start: 0, end: slice_len };
 
@@ -249,16 +1853,396 @@ impl PyPointCloud {
                         .map_err(|e| PyValueError::new_err(e.to_string()))?;
                 }
             }
-            return Ok(());
+            Ok(())
+        }
+        
+        // If key is a boolean mask.
+        else if let Some(mask) = extract_mask(key) {
+            let new_pc = value.downcast::<PyPointCloud>()?.borrow();
+            self.pc.assign_mask(&mask, &new_pc.pc)
+                .map_err(value_error)?;
+            Ok(())
+        }
+        
+        // If key is an integer array.
+        else if let Some(indices) = extract_indices(key, self.pc.len())? {
+            let new_pc = value.downcast::<PyPointCloud>()?.borrow();
+            self.pc.assign_rows(&indices, &new_pc.pc)
+                .map_err(value_error)?;
+            Ok(())
         }
         
         else {
-            return Err(PyKeyError::new_err("Invalid key type. Must be a str, list/tuple of str, or slice."));
+            Err(PyKeyError::new_err("Invalid key type. Must be a str, list/tuple of str, slice, boolean mask, integer array, or (str, rows) tuple."))
+        }
+    }
+
+    /// Assign `value` to the n points of field `field_name` selected by `rows` (a slice, a
+    /// boolean mask, an integer array, or None for every point): a NumPy array of shape
+    /// (n, count) (or (n,) for a field with a count of 1), or a scalar set on every point. The
+    /// values are converted to the dtype of the field.
+    fn set_field_rows(&mut self, field_name: &str, rows: Option<&Bound<'_, PyAny>>, value: &Bound<'_, PyAny>) -> PyResult<()> {
+        let npoints = self.pc.len();
+        let field = self.pc.fields.get_mut(field_name)
+            .ok_or_else(|| PyKeyError::new_err(format!("No field named '{}'", field_name)))?;
+        let rows = match rows {
+            None => FieldRows::Slice(0, npoints, 1),
+            Some(rows) => FieldRows::extract(rows, npoints)?,
+        };
+        let field_error = |e: anyhow::Error| to_pyerr(e.context(format!("Field '{}'", field_name)),
+            |e| PyValueError::new_err(format!("{:#}", e)));
+
+        if let Some(scalar) = extract_scalar(value)? {
+            return match rows {
+                FieldRows::Slice(start, stop, step) => field.fill_slice(start, stop, step, &scalar, CastPolicy::Error),
+                FieldRows::Indices(indices) => field.fill_rows(&indices, &scalar, CastPolicy::Error),
+            }.map_err(field_error);
+        }
+        let values = FieldData::from_pyarray_any(&as_ndarray(value)?)?;
+        match rows {
+            FieldRows::Slice(start, stop, step) => field.assign_slice(start, stop, step, &values, CastPolicy::Error),
+            FieldRows::Indices(indices) => values.cast(field.dtype(), CastPolicy::Error)
+                .and_then(|values| field.assign_rows(&indices, &values)),
+        }.map_err(field_error)
+    }
+}
+
+/// The points of one field selected by the rows of a (field, rows) key
+enum FieldRows {
+    /// Points from `start` up to `stop` (exclusive), every `step` points
+    Slice(usize, usize, usize),
+    Indices(Vec<usize>),
+}
+
+impl FieldRows {
+    /// Extract a slice, boolean mask or integer array over `npoints` points
+    fn extract(rows: &Bound<'_, PyAny>, npoints: usize) -> PyResult<Self> {
+        if let Ok(slice) = rows.downcast::<PySlice>() {
+            let indices = slice.indices(npoints as isize)?;
+            if indices.step > 0 {
+                let start = indices.start as usize;
+                return Ok(FieldRows::Slice(start, (indices.stop as usize).max(start), indices.step as usize));
+            }
+            // A negative step selects the points in reverse order
+            return Ok(FieldRows::Indices((0..indices.slicelength)
+                .map(|i| (indices.start + i as isize * indices.step) as usize)
+                .collect()));
+        }
+        if let Some(mask) = extract_mask(rows) {
+            if mask.len() != npoints {
+                return Err(PyValueError::new_err(format!("Mask length mismatch: expected {}, got {}", npoints, mask.len())));
+            }
+            return Ok(FieldRows::Indices(mask.iter().enumerate().filter_map(|(i, &m)| m.then_some(i)).collect()));
+        }
+        extract_indices(rows, npoints)?
+            .map(FieldRows::Indices)
+            .ok_or_else(|| PyKeyError::new_err("Invalid rows in (field, rows) key. Must be a slice, boolean mask, or integer array."))
+    }
+}
+
+/// Convert an array-like `value` (a list, tuple or object implementing `__array__`) to a
+/// NumPy array. NumPy arrays are returned as is
+fn as_ndarray<'py>(value: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyAny>> {
+    if value.downcast::<PyUntypedArray>().is_ok() {
+        return Ok(value.clone());
+    }
+    let array = value.py().import("numpy")?.call_method1("asarray", (value,))?;
+    if !matches!(array.downcast::<PyUntypedArray>()?.dtype().kind(), b'b' | b'i' | b'u' | b'f') {
+        return Err(PyTypeError::new_err(format!(
+            "Cannot assign a {} to a field: expected numbers, or an array of numbers", value.get_type().name()?)));
+    }
+    Ok(array)
+}
+
+/// Extract a boolean mask from a 1D boolean NumPy array, or an (n, 1) one such as a boolean
+/// field (`pc[pc["valid"]]`). Returns None for any other key
+fn extract_mask(key: &Bound<'_, PyAny>) -> Option<Vec<bool>> {
+    if let Ok(mask) = key.extract::<PyReadonlyArray1<bool>>() {
+        return Some(mask.as_array().to_vec());
+    }
+    let mask = key.extract::<PyReadonlyArray2<bool>>().ok()?;
+    (mask.shape()[1] == 1).then(|| mask.as_array().column(0).to_vec())
+}
+
+/// Whether `value` is a Python or NumPy scalar (int, float or bool), or a 0-d NumPy array
+fn is_scalar(value: &Bound<'_, PyAny>) -> bool {
+    value.is_instance_of::<PyInt>() || value.is_instance_of::<PyFloat>()
+        || value.getattr("ndim").and_then(|ndim| ndim.extract::<usize>()).is_ok_and(|ndim| ndim == 0)
+}
+
+/// Extract a scalar (see `is_scalar`) as a single-value field of I64, U64 (for integers
+/// above i64::MAX) or F64. Returns None for any other value.
+fn extract_scalar(value: &Bound<'_, PyAny>) -> PyResult<Option<FieldData>> {
+    if !is_scalar(value) {
+        return Ok(None);
+    }
+    let scalar = if let Ok(v) = value.extract::<i64>() {
+        FieldData::I64(ndarray::arr2(&[[v]]).into_shared())
+    } else if let Ok(v) = value.extract::<u64>() {
+        FieldData::U64(ndarray::arr2(&[[v]]).into_shared())
+    } else {
+        FieldData::F64(ndarray::arr2(&[[value.extract::<f64>()?]]).into_shared())
+    };
+    Ok(Some(scalar))
+}
+
+/// Extract a (field name, rows) key, as in `pc["x", 10:20]`. Returns None for any other key,
+/// including a pair of field names.
+fn extract_field_rows<'py>(key: &Bound<'py, PyAny>) -> PyResult<Option<(String, Bound<'py, PyAny>)>> {
+    let Ok(tuple) = key.downcast::<PyTuple>() else {
+        return Ok(None);
+    };
+    if tuple.len() != 2 {
+        return Ok(None);
+    }
+    let rows = tuple.get_item(1)?;
+    if rows.is_instance_of::<PyString>() {
+        return Ok(None);
+    }
+    Ok(tuple.get_item(0)?.extract::<String>().ok().map(|name| (name, rows)))
+}
+
+/// The columns (one per value of a point, named `name` or `name[i]` for fields with a count
+/// above 1) and rows of the table previewing `pc` in its repr: the index and values of the
+/// first and last `REPR_POINTS` points, with None standing for the points in between
+fn preview_table(pc: &PointCloud) -> (Vec<String>, Vec<PreviewRow>) {
+    let md = pc.metadata.read().unwrap();
+    let columns = md.fields.iter()
+        .flat_map(|f| (0..f.count).map(move |i| if f.count == 1 { f.name.clone() } else { format!("{}[{}]", f.name, i) }))
+        .collect();
+    let fields: Vec<&FieldData> = md.fields.iter().filter_map(|f| pc.fields.get(&f.name)).collect();
+    if fields.len() != md.fields.len() {
+        // Out of sync: only the summary can be shown
+        return (columns, Vec::new());
+    }
+    let row = |i: usize| Some((i, fields.iter()
+        .flat_map(|field| (0..field.count()).map(move |j| field.format_value(i, j)))
+        .collect()));
+    let n = REPR_POINTS.load(Ordering::Relaxed);
+    let npoints = pc.len();
+    let rows = if n == 0 {
+        Vec::new()
+    } else if npoints <= 2 * n {
+        (0..npoints).map(row).collect()
+    } else {
+        (0..n).map(row).chain([None]).chain((npoints - n..npoints).map(row)).collect()
+    };
+    (columns, rows)
+}
+
+/// Escape the characters of `text` that are special in HTML
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Set the number of points previewed at the start and at the end of the repr of a PointCloud
+/// (5 by default), in the terminal and in Jupyter. 0 shows only the summary. Returns the
+/// previous setting.
+#[pyfunction]
+pub fn set_repr_points(n: usize) -> usize {
+    REPR_POINTS.swap(n, Ordering::Relaxed)
+}
+
+/// Enable or disable auto-sync, a debugging mode in which every PointCloud method that
+/// modifies a cloud (including item assignment) then calls `sync_metadata`, so that a
+/// metadata inconsistency raises where it is introduced. Returns the previous setting.
+#[pyfunction]
+pub fn set_auto_sync(enabled: bool) -> bool {
+    AUTO_SYNC.swap(enabled, Ordering::Relaxed)
+}
+
+/// Iterator over the points of a PointCloud, yielding one dict per point
+#[pyclass(name = "PointIterator")]
+pub struct PyPointIterator {
+    cloud: Py<PyPointCloud>,
+    index: usize,
+}
+
+#[pymethods]
+impl PyPointIterator {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__<'py>(&mut self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyDict>>> {
+        let cloud = self.cloud.borrow(py);
+        if self.index >= cloud.pc.len() {
+            return Ok(None);
         }
+        let point = cloud.point_dict(py, self.index)?;
+        self.index += 1;
+        Ok(Some(point))
     }
 }
 
-/// Helper functions ///
+impl PyPointCloud {
+    /// Build a dict mapping each field name (in metadata order) to the values of point `idx`
+    fn point_dict<'py>(&self, py: Python<'py>, idx: usize) -> PyResult<Bound<'py, PyDict>> {
+        let point = PyDict::new(py);
+        let md = self.pc.metadata.read().unwrap();
+        for field_meta in md.fields.iter() {
+            let field_data = self.pc.fields.get(&field_meta.name)
+                .ok_or_else(|| PyKeyError::new_err(format!("No field named '{}'", field_meta.name)))?;
+            point.set_item(&field_meta.name, field_data.row_to_pyarray(py, idx)?)?;
+        }
+        Ok(point)
+    }
+
+    /// Extract image row and column indices from a (rows, cols) key where each entry is a slice
+    /// or an int and at least one is a slice. Returns None for any other key.
+    fn extract_grid(&self, key: &Bound<'_, PyAny>) -> PyResult<Option<(Vec<usize>, Vec<usize>)>> {
+        let Ok(tuple) = key.downcast::<PyTuple>() else {
+            return Ok(None);
+        };
+        if tuple.len() != 2 || !tuple.iter().any(|k| k.is_instance_of::<PySlice>()) {
+            return Ok(None);
+        }
+        let (width, height) = {
+            let md = self.pc.metadata.read().unwrap();
+            (md.width, md.height)
+        };
+        let rows = axis_indices(&tuple.get_item(0)?, height)?;
+        let cols = axis_indices(&tuple.get_item(1)?, width)?;
+        Ok(rows.zip(cols))
+    }
+
+    /// Return the points selected by a Python slice. Slices with a negative step reverse the
+    /// points, so their result is unorganized.
+    fn slice_points(&self, slice: &Bound<'_, PySlice>, organized: Option<bool>) -> PyResult<PointCloud> {
+        let indices = slice.indices(self.pc.len() as isize)?;
+        if indices.step > 0 {
+            return self.pc.slice(indices.start as usize, indices.stop.max(0) as usize, indices.step as usize, organized)
+                .map_err(value_error);
+        }
+        if organized == Some(true) {
+            return Err(PyValueError::new_err("Cannot keep a slice with a negative step organized"));
+        }
+        let points = (0..indices.slicelength)
+            .map(|i| (indices.start + i as isize * indices.step) as usize)
+            .collect::<Vec<usize>>();
+        self.pc.take_rows(&points)
+            .map_err(value_error)
+    }
+
+    /// Return the PointCloud with NaN points removed if `ignore_nan` is set, or a borrow of it otherwise
+    fn without_nan(&self, ignore_nan: bool) -> PyResult<std::borrow::Cow<'_, PointCloud>> {
+        if ignore_nan {
+            let (pc, _) = self.pc.remove_nan_points(None)
+                .map_err(value_error)?;
+            Ok(std::borrow::Cow::Owned(pc))
+        } else {
+            Ok(std::borrow::Cow::Borrowed(&self.pc))
+        }
+    }
+
+    /// Compute the statistics of a field and extract one value per component with `f`
+    fn field_stat<'py>(&self, py: Python<'py>, field: &str, f: impl Fn(&FieldStats) -> f64) -> PyResult<Bound<'py, PyAny>> {
+        let stats = self.pc.field_stats(field)
+            .map_err(|e| PyKeyError::new_err(e.to_string()))?;
+        stats_to_py(py, &stats, f)
+    }
+}
+
+// Helper functions //
+
+/// Convert one statistic per field component to a Python scalar for single-component
+/// fields, or a list otherwise
+fn stats_to_py<'py, T: IntoPyObject<'py>>(py: Python<'py>, stats: &[FieldStats], f: impl Fn(&FieldStats) -> T) -> PyResult<Bound<'py, PyAny>> {
+    if let [single] = stats {
+        f(single).into_bound_py_any(py)
+    } else {
+        stats.iter().map(f).collect::<Vec<T>>().into_bound_py_any(py)
+    }
+}
+
+/// The string in `file` if it is an s3:// or http(s):// URL, which `from_file` reads remotely
+fn extract_url(file: &Bound<'_, PyAny>) -> Option<String> {
+    file.extract::<String>().ok()
+        .filter(|path| ["s3://", "http://", "https://"].iter().any(|scheme| path.starts_with(scheme)))
+}
+
+/// Read a PointCloud from a URL for `PointCloud.from_file`
+#[cfg(feature = "remote")]
+fn read_url(py: Python<'_>, url: &str, options: &io::ReadOptions, progress: Option<PyObject>, cancel: Option<&PyCancellationToken>) -> PyResult<(PointCloud, Vec<String>)> {
+    with_progress(py, progress, cancel, |p| PointCloud::from_pcd_reader_with_progress(crate::io_remote::RemoteReader::open(url)?, options, p), io_error)
+}
+
+/// Without the "remote" feature, URLs cannot be read
+#[cfg(not(feature = "remote"))]
+fn read_url(_py: Python<'_>, url: &str, _options: &io::ReadOptions, _progress: Option<PyObject>, _cancel: Option<&PyCancellationToken>) -> PyResult<(PointCloud, Vec<String>)> {
+    Err(PyValueError::new_err(format!("Cannot read {}: pcdpy was built without the \"remote\" feature", url)))
+}
+
+/// Parse a cast policy name ("error", "saturate" or "wrap")
+fn parse_cast_policy(policy: &str) -> PyResult<CastPolicy> {
+    CastPolicy::from_str(policy)
+        .ok_or_else(|| PyValueError::new_err(format!("Invalid cast policy '{}', expected 'error', 'saturate' or 'wrap'", policy)))
+}
+
+/// Build the options for writing a PCD file from the arguments of `save` and `to_bytes`
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn write_options(legacy: bool, encoding: Option<&str>, float_format: Option<&str>, precision: Option<usize>, compression_level: Option<i32>, pcl_compatible: bool, large_compressed: bool, skip_padding: bool) -> PyResult<io::WriteOptions> {
+    let encoding = encoding
+        .map(|e| Encoding::from_str(&e.to_lowercase())
+            .ok_or_else(|| PyValueError::new_err(format!("Invalid encoding '{}', expected 'ascii', 'binary', 'binary_compressed', 'binary_zstd', 'binary_lz4' or 'binary_compressed_large'", e))))
+        .transpose()?;
+    let float_format = match (float_format, precision) {
+        (None, None) | (Some("shortest"), None) => io::FloatFormat::Shortest,
+        (Some("shortest"), Some(_)) => return Err(PyValueError::new_err("precision cannot be used with float_format='shortest'")),
+        (None, Some(p)) | (Some("fixed"), Some(p)) => io::FloatFormat::Fixed(p),
+        (Some("fixed"), None) => io::FloatFormat::Fixed(6),
+        (Some("scientific"), p) => io::FloatFormat::Scientific(p.unwrap_or(6)),
+        (Some(f), _) => return Err(PyValueError::new_err(format!("Invalid float_format '{}', expected 'shortest', 'fixed' or 'scientific'", f))),
+    };
+    Ok(io::WriteOptions {
+        encoding,
+        version: legacy.then(|| "0.6".to_string()),
+        float_format,
+        compression_level,
+        pcl_compatible,
+        large_compressed,
+        skip_padding,
+        ..Default::default()
+    })
+}
+
+/// Extract pinhole intrinsics given as (fx, fy, cx, cy) or a 3x3 camera matrix
+fn extract_intrinsics(intrinsics: &Bound<'_, PyAny>) -> PyResult<[f64; 4]> {
+    if let Ok(k) = intrinsics.extract::<[f64; 4]>() {
+        Ok(k)
+    } else if let Ok(k) = intrinsics.extract::<[[f64; 3]; 3]>() {
+        Ok([k[0][0], k[1][1], k[0][2], k[1][2]])
+    } else {
+        Err(PyValueError::new_err("Intrinsics must be (fx, fy, cx, cy) or a 3x3 camera matrix"))
+    }
+}
+
+/// Convert a field to a NumPy dtype name according to a cast policy name
+fn cast_field_data(field_data: &FieldData, dtype: &str, policy: &str) -> PyResult<FieldData> {
+    let dtype = Dtype::from_numpy_dtype(dtype)
+        .ok_or_else(|| unsupported_dtype(dtype))?;
+    field_data.cast(dtype, parse_cast_policy(policy)?)
+        .map_err(value_error)
+}
+
+/// Read a PointField given as a (name, offset, datatype, count) tuple, a dict, or an object
+/// with those attributes (e.g. sensor_msgs.msg.PointField)
+fn extract_ros_field(obj: &Bound<'_, PyAny>) -> PyResult<RosField> {
+    if let Ok((name, offset, datatype, count)) = obj.extract::<(String, usize, u8, usize)>() {
+        return Ok(RosField { name, offset, datatype, count });
+    }
+    let get = |key: &str| match obj.downcast::<PyDict>() {
+        Ok(dict) => dict.get_item(key)?
+            .ok_or_else(|| PyKeyError::new_err(format!("PointField dict is missing '{}'", key))),
+        Err(_) => obj.getattr(key),
+    };
+    Ok(RosField {
+        name: get("name")?.extract()?,
+        offset: get("offset")?.extract()?,
+        datatype: get("datatype")?.extract()?,
+        count: get("count")?.extract()?,
+    })
+}
 
 /// Infer dtype from Numpy array and store it in PointCloud fields
 fn infer_and_store_field<'py>(pc: &mut PointCloud, field_name: &str, pyarray:&Bound<'py, PyAny>) -> PyResult<()> {
@@ -274,39 +2258,42 @@ fn infer_and_store_field<'py>(pc: &mut PointCloud, field_name: &str, pyarray:&Bo
     
     let dtype_obj = pyarray.getattr("dtype")?;
     let dtype_name: String = dtype_obj.getattr("name")?.extract()?;
-    let shape = pyarray.getattr("shape")?.extract::<(usize, usize)>()?;
+    let shape: Vec<usize> = pyarray.getattr("shape")?.extract()?;
+    let shape = match shape[..] {
+        [n] => (n, 1),
+        [n, count] => (n, count),
+        _ => return Err(PyValueError::new_err(format!(
+            "Field '{}': expected an array of shape (npoints,) or (npoints, count), got {} dimensions",
+            field_name, shape.len()))),
+    };
 
     if shape.0 != npoints {
-        return Err(PyValueError::new_err(format!(
+        return Err(SchemaMismatchError::new_err(format!(
             "Array length mismatch: expected {}, got {}", 
             npoints, shape.0
         )));
     }
 
     let dtype = Dtype::from_numpy_dtype(&dtype_name)
-        .ok_or_else(|| PyValueError::new_err(format!("Unsupported dtype: {}", dtype_name)))?;
+        .ok_or_else(|| unsupported_dtype(&dtype_name))?;
 
     // Validate against existing field if present
     if let Some(field_meta) = existing_field_meta {
         if field_meta.dtype != dtype {
-            return Err(PyValueError::new_err(format!(
+            return Err(SchemaMismatchError::new_err(format!(
                 "Dtype mismatch: field has {}, array has {}", 
                 field_meta.dtype.as_numpy_dtype(), dtype_name
             )));
         }
         if field_meta.count != shape.1 {
-            return Err(PyValueError::new_err(format!(
+            return Err(SchemaMismatchError::new_err(format!(
                 "Count mismatch: field has {}, array has {}", 
                 field_meta.count, shape.1
             )));
         }
     } else {
         let mut md = pc.metadata.write().unwrap();
-        md.fields.0.push(FieldMeta {
-            name: field_name.to_string(),
-            dtype,
-            count: shape.1,
-        });
+        md.fields.0.push(FieldMeta::new(field_name, dtype, shape.1));
     }
 
     // Convert array to FieldData
@@ -315,4 +2302,52 @@ fn infer_and_store_field<'py>(pc: &mut PointCloud, field_name: &str, pyarray:&Bo
     pc.fields.insert(field_name.to_string(), field_data);
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Convert a slice or integer key along an axis of length `len` into indices.
+/// Returns None if the key is neither.
+fn axis_indices(key: &Bound<'_, PyAny>, len: usize) -> PyResult<Option<Vec<usize>>> {
+    if let Ok(slice) = key.downcast::<PySlice>() {
+        let indices = slice.indices(len as isize)?;
+        Ok(Some((0..indices.slicelength)
+            .map(|i| (indices.start + i as isize * indices.step) as usize)
+            .collect()))
+    } else if let Ok(i) = key.extract::<isize>() {
+        let idx = if i < 0 { i + len as isize } else { i };
+        if idx < 0 || idx >= len as isize {
+            return Err(PyIndexError::new_err(format!("Index {} is out of bounds for axis of length {}", i, len)));
+        }
+        Ok(Some(vec![idx as usize]))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Extract point indices from a 1D integer NumPy array or a list/tuple of ints.
+/// Negative indices count from the end. Returns None if the key is not an integer index array.
+fn extract_indices(key: &Bound<'_, PyAny>, npoints: usize) -> PyResult<Option<Vec<usize>>> {
+    let raw: Vec<i64> = if let Ok(arr) = key.downcast::<PyUntypedArray>() {
+        let kind = arr.dtype().kind();
+        if arr.ndim() != 1 || !(kind == b'i' || kind == b'u') {
+            return Ok(None);
+        }
+        let arr = key.call_method1("astype", ("int64",))?;
+        arr.extract::<PyReadonlyArray1<i64>>()?.as_array().to_vec()
+    } else if let Ok(list) = key.extract::<Vec<i64>>() {
+        list
+    } else {
+        return Ok(None);
+    };
+
+    raw.into_iter()
+        .map(|i| {
+            let idx = if i < 0 { i + npoints as i64 } else { i };
+            if idx < 0 || idx >= npoints as i64 {
+                Err(PyIndexError::new_err(format!("Index {} is out of bounds for {} points", i, npoints)))
+            } else {
+                Ok(idx as usize)
+            }
+        })
+        .collect::<PyResult<Vec<usize>>>()
+        .map(Some)
+}
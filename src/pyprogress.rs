@@ -0,0 +1,69 @@
+use pyo3::prelude::*;
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use crate::progress::{CancellationToken, Progress};
+
+create_exception!(pcdpy, CancelledError, PyException, "Raised when an operation is cancelled through a CancellationToken.");
+
+/// A flag for cancelling long operations (e.g. `PointCloud.from_file(..., cancel=token)`),
+/// typically from another thread such as a GUI thread. Cancelled operations raise
+/// `CancelledError` at their next progress check
+#[pyclass(name = "CancellationToken", frozen)]
+pub struct PyCancellationToken {
+    pub token: CancellationToken,
+}
+
+#[pymethods]
+impl PyCancellationToken {
+    #[new]
+    fn new() -> Self {
+        Self { token: CancellationToken::new() }
+    }
+
+    /// Request cancellation of the operations using this token
+    fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    /// Whether `cancel` was called
+    #[getter]
+    fn cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+}
+
+/// Run `f` without the GIL with a `Progress` calling the Python `callback` as
+/// `callback(done, total)` (reacquiring the GIL briefly each time) and checking `cancel`.
+/// An exception raised by the callback stops the operation and is raised again here, and
+/// cancellation through the token raises `CancelledError`. Other errors are mapped with
+/// `map_err`.
+pub fn with_progress<T: Send>(
+    py: Python<'_>,
+    callback: Option<PyObject>,
+    cancel: Option<&PyCancellationToken>,
+    f: impl FnOnce(&mut Progress) -> anyhow::Result<T> + Send,
+    map_err: impl FnOnce(anyhow::Error) -> PyErr,
+) -> PyResult<T> {
+    let mut raised: Option<PyErr> = None;
+    let (result, cancelled) = {
+        let raised = &mut raised;
+        let callback = callback.map(|callback| -> crate::progress::ProgressCallback {
+            Box::new(move |done, total| Python::with_gil(|py| match callback.call1(py, (done, total)) {
+                Ok(_) => true,
+                Err(e) => {
+                    *raised = Some(e);
+                    false
+                }
+            }))
+        });
+        let mut progress = Progress::new(callback, cancel.map(|c| c.token.clone()));
+        let result = py.allow_threads(|| f(&mut progress));
+        (result, progress.is_cancelled())
+    };
+    match (result, raised) {
+        (Ok(value), _) => Ok(value),
+        (Err(_), Some(e)) => Err(e),
+        (Err(_), None) if cancelled => Err(CancelledError::new_err("Operation cancelled")),
+        (Err(e), None) => Err(map_err(e)),
+    }
+}
@@ -0,0 +1,357 @@
+use pyo3::prelude::*;
+use pyo3::exceptions::{PyIndexError, PyIOError, PyValueError};
+use pyo3::types::{PyDict, PyTuple};
+use std::path::PathBuf;
+use crate::writer::PcdWriter;
+use crate::batch;
+use crate::frames::{FrameReader, FrameWriter};
+use crate::io::{ReadOptions, WriteOptions};
+use crate::metadata::{Metadata, Viewpoint};
+use crate::pointcloud::PcdReader;
+use crate::pylog::warn_read_issues;
+use crate::pymetadata::{extract_schema, PyMetadata};
+use crate::pypointcloud::{write_options, PyPointCloud};
+use crate::pyerrors::{io_error, to_pyerr, value_error};
+
+#[pyclass(name = "PcdReader")]
+pub struct PyPcdReader {
+    pub reader: PcdReader,
+}
+
+#[pymethods]
+impl PyPcdReader {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<PyPointCloud>> {
+        match py.allow_threads(|| self.reader.next()) {
+            Some(Ok(pc)) => Ok(Some(PyPointCloud { pc })),
+            Some(Err(e)) => Err(io_error(e)),
+            None => Ok(None),
+        }
+    }
+
+    /// Metadata parsed from the file header
+    #[getter]
+    fn metadata(&self) -> PyMetadata {
+        PyMetadata {
+            inner: std::sync::Arc::new(std::sync::RwLock::new(self.reader.metadata().clone())),
+            cloud: None,
+        }
+    }
+
+    /// Number of points that have not been read yet
+    #[getter]
+    fn remaining(&self) -> usize {
+        self.reader.remaining()
+    }
+}
+
+/// Open a PCD file for streaming, yielding PointClouds of at most `chunk_size` points
+#[pyfunction]
+#[pyo3(signature = (path, chunk_size=1_000_000))]
+pub fn open(py: Python<'_>, path: &str, chunk_size: usize) -> PyResult<PyPcdReader> {
+    let reader = py.allow_threads(|| PcdReader::open(path, chunk_size))
+        .map_err(io_error)?;
+    warn_read_issues(py, reader.warnings())?;
+    Ok(PyPcdReader { reader })
+}
+
+/// Read every PCD file in the directory `path` whose name matches the wildcard `pattern`,
+/// in parallel, returning a list of PointClouds sorted by file name. Uses `num_threads`
+/// threads, or one per CPU if None
+#[pyfunction]
+#[pyo3(signature = (path, pattern="*.pcd", num_threads=None))]
+pub fn load_dir(py: Python<'_>, path: PathBuf, pattern: &str, num_threads: Option<usize>) -> PyResult<Vec<PyPointCloud>> {
+    let (clouds, warnings) = py.allow_threads(|| batch::load_dir(&path, pattern, num_threads))
+        .map_err(|e| to_pyerr(e, |e| PyIOError::new_err(format!("{:#}", e))))?;
+    warn_read_issues(py, &warnings)?;
+    Ok(clouds.into_iter().map(|pc| PyPointCloud { pc }).collect())
+}
+
+/// Rewrite the PCD file `src` to `dst` in another encoding ("ascii", "binary",
+/// "binary_compressed", ...), streaming `chunk_size` points at a time instead of loading
+/// the whole cloud. Compressed output is assembled in a temporary file next to `dst`;
+/// binary_compressed and binary_lz4 are compressed as one block and so hold the point data
+/// (but not the source file) in memory. The other arguments are as in `PointCloud.save`
+#[pyfunction]
+#[pyo3(signature = (src, dst, encoding="binary", chunk_size=1_000_000, float_format=None, precision=None, compression_level=None, large_compressed=false, skip_padding=false))]
+#[allow(clippy::too_many_arguments)]
+pub fn convert(
+    py: Python<'_>,
+    src: &str,
+    dst: &str,
+    encoding: &str,
+    chunk_size: usize,
+    float_format: Option<&str>,
+    precision: Option<usize>,
+    compression_level: Option<i32>,
+    large_compressed: bool,
+    skip_padding: bool,
+) -> PyResult<()> {
+    let options = write_options(false, Some(encoding), float_format, precision, compression_level, false, large_compressed, skip_padding)?;
+    let warnings = py.allow_threads(|| crate::convert::convert(src, dst, &options, chunk_size))
+        .map_err(io_error)?;
+    warn_read_issues(py, &warnings)
+}
+
+/// Writes a PCD file incrementally, for points that arrive in chunks (e.g. from a sensor)
+/// without holding the whole cloud in memory. The file holds an unorganized cloud whose
+/// WIDTH and POINTS are filled in by `close`, which is called on leaving a `with` block.
+/// ASCII and binary data are written as chunks arrive; compressed encodings collect the data
+/// in temporary files next to `path` and compress it on `close`
+#[pyclass(name = "PcdWriter")]
+pub struct PyPcdWriter {
+    writer: Option<PcdWriter>,
+}
+
+#[pymethods]
+impl PyPcdWriter {
+    /// Create the PCD file at `path` for points with the fields of `schema` (a Schema, or a
+    /// list of FieldMeta or (name, dtype, count) tuples) and the 7 `viewpoint` values. The
+    /// other arguments are as in `PointCloud.save`
+    #[new]
+    #[pyo3(signature = (path, schema, encoding="binary", viewpoint=None, legacy=false, float_format=None, precision=None, compression_level=None, pcl_compatible=false, large_compressed=false, checksum=false))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        py: Python<'_>,
+        path: PathBuf,
+        schema: &Bound<'_, PyAny>,
+        encoding: &str,
+        viewpoint: Option<Vec<f32>>,
+        legacy: bool,
+        float_format: Option<&str>,
+        precision: Option<usize>,
+        compression_level: Option<i32>,
+        pcl_compatible: bool,
+        large_compressed: bool,
+        checksum: bool,
+    ) -> PyResult<Self> {
+        let mut md = Metadata { fields: extract_schema(schema)?, ..Metadata::default() };
+        if let Some(viewpoint) = viewpoint {
+            if viewpoint.len() != 7 {
+                return Err(PyValueError::new_err(format!("Viewpoint must have 7 values, got {}", viewpoint.len())));
+            }
+            md.viewpoint = Viewpoint::from(viewpoint);
+        }
+        let options = WriteOptions {
+            checksum,
+            ..write_options(legacy, Some(encoding), float_format, precision, compression_level, pcl_compatible, large_compressed, false)?
+        };
+        let path = path.to_str()
+            .ok_or_else(|| PyValueError::new_err("Path is not valid UTF-8"))?;
+        let writer = py.allow_threads(|| PcdWriter::create(path, &md, &options))
+            .map_err(io_error)?;
+        Ok(Self { writer: Some(writer) })
+    }
+
+    /// Append the points of a PointCloud, or of a dict mapping field names to arrays as in
+    /// `PointCloud.from_arrays`. The fields must have the types and counts of the schema and
+    /// may be in any order; other fields are not written. Raises SchemaMismatchError otherwise
+    fn write_chunk(&mut self, py: Python<'_>, points: &Bound<'_, PyAny>) -> PyResult<()> {
+        let writer = self.writer.as_mut()
+            .ok_or_else(|| PyValueError::new_err("I/O operation on closed PcdWriter"))?;
+        if let Ok(pc) = points.downcast::<PyPointCloud>() {
+            let pc = &pc.borrow().pc;
+            py.allow_threads(|| writer.write_chunk(pc))
+        } else {
+            let pc = PyPointCloud::from_arrays(points.downcast::<PyDict>()?)?;
+            py.allow_threads(|| writer.write_chunk(&pc.pc))
+        }.map_err(io_error)
+    }
+
+    /// Complete the file. Does nothing if the writer is already closed
+    fn close(&mut self, py: Python<'_>) -> PyResult<()> {
+        match self.writer.take() {
+            Some(writer) => py.allow_threads(|| writer.finish()).map_err(io_error),
+            None => Ok(()),
+        }
+    }
+
+    /// Number of points written so far
+    #[getter]
+    fn written(&self) -> usize {
+        self.writer.as_ref().map_or(0, |w| w.written())
+    }
+
+    /// Whether the writer is closed
+    #[getter]
+    fn closed(&self) -> bool {
+        self.writer.is_none()
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    #[pyo3(signature = (*_args))]
+    fn __exit__(&mut self, py: Python<'_>, _args: &Bound<'_, PyTuple>) -> PyResult<bool> {
+        self.close(py)?;
+        Ok(false)
+    }
+}
+
+/// Writes a sequence of PointClouds (frames, e.g. a lidar recording) with their timestamps
+/// to a single indexed file instead of one file per cloud. The index is written by `close`,
+/// which is called on leaving a `with` block; a file that was not closed cannot be read by
+/// FrameReader
+#[pyclass(name = "FrameWriter")]
+pub struct PyFrameWriter {
+    writer: Option<FrameWriter>,
+}
+
+#[pymethods]
+impl PyFrameWriter {
+    /// Create the frame file at `path`, writing every frame with the given options, as in
+    /// `PointCloud.save`
+    #[new]
+    #[pyo3(signature = (path, encoding="binary", float_format=None, precision=None, compression_level=None, pcl_compatible=false, large_compressed=false, skip_padding=false, checksum=false))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        path: &str,
+        encoding: &str,
+        float_format: Option<&str>,
+        precision: Option<usize>,
+        compression_level: Option<i32>,
+        pcl_compatible: bool,
+        large_compressed: bool,
+        skip_padding: bool,
+        checksum: bool,
+    ) -> PyResult<Self> {
+        let options = WriteOptions {
+            checksum,
+            ..write_options(false, Some(encoding), float_format, precision, compression_level, pcl_compatible, large_compressed, skip_padding)?
+        };
+        let writer = FrameWriter::create(path, &options)
+            .map_err(io_error)?;
+        Ok(Self { writer: Some(writer) })
+    }
+
+    /// Append a PointCloud as a frame recorded at `timestamp` (e.g. in nanoseconds since the
+    /// epoch), which cannot be before the timestamp of the previous frame
+    #[pyo3(signature = (pc, timestamp=0))]
+    fn write(&mut self, py: Python<'_>, pc: PyRef<'_, PyPointCloud>, timestamp: u64) -> PyResult<()> {
+        let writer = self.writer.as_mut()
+            .ok_or_else(|| PyValueError::new_err("I/O operation on closed FrameWriter"))?;
+        let pc = &pc.pc;
+        py.allow_threads(|| writer.write_frame(pc, timestamp))
+            .map_err(value_error)
+    }
+
+    /// Write the frame index, completing the file. Does nothing if the writer is already closed
+    fn close(&mut self, py: Python<'_>) -> PyResult<()> {
+        match self.writer.take() {
+            Some(writer) => py.allow_threads(|| writer.finish()).map_err(io_error),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether the writer is closed
+    #[getter]
+    fn closed(&self) -> bool {
+        self.writer.is_none()
+    }
+
+    /// Number of frames written so far
+    fn __len__(&self) -> usize {
+        self.writer.as_ref().map_or(0, |w| w.len())
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    #[pyo3(signature = (*_args))]
+    fn __exit__(&mut self, py: Python<'_>, _args: &Bound<'_, PyTuple>) -> PyResult<bool> {
+        self.close(py)?;
+        Ok(false)
+    }
+}
+
+/// Reads the frames of a file written by FrameWriter in any order: `reader[i]` reads frame
+/// `i` (negative indices count from the end), and iterating yields every frame in order
+#[pyclass(name = "FrameReader", sequence)]
+pub struct PyFrameReader {
+    reader: FrameReader,
+}
+
+#[pymethods]
+impl PyFrameReader {
+    /// Open the frame file at `path` and read its index
+    #[new]
+    fn new(py: Python<'_>, path: &str) -> PyResult<Self> {
+        let reader = py.allow_threads(|| FrameReader::open(path))
+            .map_err(io_error)?;
+        Ok(Self { reader })
+    }
+
+    /// Read frame `index`, with the reading options of `PointCloud.from_file`
+    #[pyo3(signature = (index, strict=true, fields=None, start=0, count=None, verify=false))]
+    #[allow(clippy::too_many_arguments)]
+    fn read(&mut self, py: Python<'_>, index: isize, strict: bool, fields: Option<Vec<String>>, start: usize, count: Option<usize>, verify: bool) -> PyResult<PyPointCloud> {
+        let len = self.reader.len();
+        let index = if index < 0 { index + len as isize } else { index };
+        if index < 0 || index as usize >= len {
+            return Err(PyIndexError::new_err(format!("Frame index out of range for {} frames", len)));
+        }
+        let options = ReadOptions { strict, fields, start, count, verify };
+        let (pc, warnings) = py.allow_threads(|| self.reader.read_frame(index as usize, &options))
+            .map_err(io_error)?;
+        warn_read_issues(py, &warnings)?;
+        Ok(PyPointCloud { pc })
+    }
+
+    fn __getitem__(&mut self, py: Python<'_>, index: isize) -> PyResult<PyPointCloud> {
+        self.read(py, index, true, None, 0, None, false)
+    }
+
+    fn __len__(&self) -> usize {
+        self.reader.len()
+    }
+
+    /// Timestamp of each frame, in order
+    #[getter]
+    fn timestamps(&self) -> Vec<u64> {
+        self.reader.entries().iter().map(|e| e.timestamp).collect()
+    }
+
+    /// Index of the last frame recorded at or before `timestamp`, or None if every frame is
+    /// later
+    fn frame_at(&self, timestamp: u64) -> Option<usize> {
+        self.reader.frame_at(timestamp)
+    }
+}
+
+#[cfg(feature = "rosbag")]
+#[pyclass(name = "BagReader")]
+pub struct PyBagReader {
+    pub reader: crate::io_bag::BagReader,
+}
+
+#[cfg(feature = "rosbag")]
+#[pymethods]
+impl PyBagReader {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<(u64, PyPointCloud)>> {
+        match py.allow_threads(|| self.reader.next()) {
+            Some(Ok(msg)) => Ok(Some((msg.timestamp, PyPointCloud { pc: msg.pc }))),
+            Some(Err(e)) => Err(io_error(e)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Open a ROS1 bag (.bag) or MCAP (.mcap) file for streaming the PointCloud2 messages of
+/// `topic`, yielding (timestamp, PointCloud) pairs where timestamp is the time the message
+/// was recorded, in nanoseconds since the epoch
+#[cfg(feature = "rosbag")]
+#[pyfunction]
+pub fn read_bag(py: Python<'_>, path: &str, topic: &str) -> PyResult<PyBagReader> {
+    let reader = py.allow_threads(|| crate::io_bag::BagReader::open(path, topic))
+        .map_err(io_error)?;
+    Ok(PyBagReader { reader })
+}
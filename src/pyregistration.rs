@@ -0,0 +1,99 @@
+use pyo3::prelude::*;
+use numpy::{PyArray2, ToPyArray};
+use ndarray::Array2;
+use crate::distance;
+use crate::pypointcloud::PyPointCloud;
+use crate::registration::{self, IcpOptions, IcpResult};
+use crate::pyerrors::value_error;
+
+#[pyclass(name = "IcpResult", frozen)]
+pub struct PyIcpResult {
+    pub result: IcpResult,
+}
+
+#[pymethods]
+impl PyIcpResult {
+    /// 4x4 transform mapping the source onto the target
+    #[getter]
+    fn transformation<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<f64>> {
+        let m = self.result.transformation;
+        Array2::from_shape_fn((4, 4), |(i, j)| m[i][j]).to_pyarray(py)
+    }
+
+    /// Fraction of source points with a correspondence within the maximum distance
+    #[getter]
+    fn fitness(&self) -> f64 {
+        self.result.fitness
+    }
+
+    /// Root mean square distance over the inlier correspondences
+    #[getter]
+    fn inlier_rmse(&self) -> f64 {
+        self.result.inlier_rmse
+    }
+
+    /// Number of iterations run
+    #[getter]
+    fn iterations(&self) -> usize {
+        self.result.iterations
+    }
+
+    /// True if the RMSE change fell below the tolerance
+    #[getter]
+    fn converged(&self) -> bool {
+        self.result.converged
+    }
+
+    fn __repr__(&self) -> String {
+        format!("IcpResult(fitness={}, inlier_rmse={}, iterations={}, converged={})",
+            self.result.fitness,
+            self.result.inlier_rmse,
+            self.result.iterations,
+            if self.result.converged { "True" } else { "False" },
+        )
+    }
+}
+
+/// Align `source` to `target` with point-to-point ICP, using the coordinate `fields` of both
+/// clouds (detected from `source` if not given)
+#[pyfunction]
+#[pyo3(signature = (source, target, max_iterations=30, tolerance=1e-6, max_correspondence_distance=f64::INFINITY, fields=None))]
+pub fn register_icp(
+    py: Python<'_>,
+    source: PyRef<'_, PyPointCloud>,
+    target: PyRef<'_, PyPointCloud>,
+    max_iterations: usize,
+    tolerance: f64,
+    max_correspondence_distance: f64,
+    fields: Option<(String, String, String)>,
+) -> PyResult<PyIcpResult> {
+    let fields = source.coordinate_names(fields);
+    let options = IcpOptions { max_iterations, tolerance, max_correspondence_distance };
+    let (source, target) = (&source.pc, &target.pc);
+    let result = py.allow_threads(|| registration::register_icp(source, target, &options, [&fields.0, &fields.1, &fields.2]))
+        .map_err(value_error)?;
+    Ok(PyIcpResult { result })
+}
+
+/// Chamfer distance between two clouds: the mean distance from each point of `a` to its
+/// nearest neighbor in `b`, plus the same from `b` to `a` (mean squared distances if
+/// `squared`). Points with a NaN coordinate are ignored
+#[pyfunction]
+#[pyo3(signature = (a, b, squared=false, fields=None))]
+pub fn chamfer_distance(py: Python<'_>, a: PyRef<'_, PyPointCloud>, b: PyRef<'_, PyPointCloud>, squared: bool, fields: Option<(String, String, String)>) -> PyResult<f64> {
+    let fields = a.coordinate_names(fields);
+    let (a, b) = (&a.pc, &b.pc);
+    py.allow_threads(|| distance::chamfer_distance(a, b, [&fields.0, &fields.1, &fields.2], squared))
+        .map_err(value_error)
+}
+
+/// Hausdorff distance between two clouds: the largest distance from a point of either cloud
+/// to its nearest neighbor in the other. Points with a NaN coordinate are ignored
+#[pyfunction]
+#[pyo3(signature = (a, b, fields=None))]
+pub fn hausdorff_distance(py: Python<'_>, a: PyRef<'_, PyPointCloud>, b: PyRef<'_, PyPointCloud>, fields: Option<(String, String, String)>) -> PyResult<f64> {
+    let fields = a.coordinate_names(fields);
+    let (a, b) = (&a.pc, &b.pc);
+    py.allow_threads(|| distance::hausdorff_distance(a, b, [&fields.0, &fields.1, &fields.2]))
+        .map_err(value_error)
+}
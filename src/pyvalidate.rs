@@ -0,0 +1,92 @@
+use pyo3::prelude::*;
+use std::collections::HashMap;
+use crate::pymetadata::PyMetadata;
+use crate::validate::{repair_file, validate_file, ValidationReport};
+use crate::pyerrors::io_error;
+
+#[pyclass(name = "ValidationReport", frozen)]
+pub struct PyValidationReport {
+    pub report: ValidationReport,
+}
+
+#[pymethods]
+impl PyValidationReport {
+    /// True if no issues were found. Recovered header defects are not issues
+    #[getter]
+    fn is_valid(&self) -> bool {
+        self.report.is_valid()
+    }
+
+    /// Problems that make the file invalid
+    #[getter]
+    fn issues(&self) -> Vec<String> {
+        self.report.issues.clone()
+    }
+
+    /// Defects in the header that were recovered when parsing it
+    #[getter]
+    fn header_warnings(&self) -> Vec<String> {
+        self.report.header_warnings.clone()
+    }
+
+    /// Metadata parsed from the header, or None if the header is invalid
+    #[getter]
+    fn metadata(&self) -> Option<PyMetadata> {
+        self.report.metadata.clone().map(PyMetadata::from_metadata)
+    }
+
+    /// Number of complete points found in the body
+    #[getter]
+    fn points_in_body(&self) -> usize {
+        self.report.points_in_body
+    }
+
+    /// True if the body ends in the middle of a point or of the compressed block
+    #[getter]
+    fn truncated(&self) -> bool {
+        self.report.truncated
+    }
+
+    /// Number of NaN values in each field of the points found
+    #[getter]
+    fn nan_counts(&self) -> HashMap<String, usize> {
+        self.report.nan_counts.iter().cloned().collect()
+    }
+
+    /// Whether the data matches its checksum footer, or None if the file has no footer
+    #[getter]
+    fn checksum(&self) -> Option<bool> {
+        self.report.checksum
+    }
+
+    fn __bool__(&self) -> bool {
+        self.report.is_valid()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("ValidationReport(is_valid={}, points_in_body={}, truncated={}, issues={:?})",
+            if self.report.is_valid() { "True" } else { "False" },
+            self.report.points_in_body,
+            if self.report.truncated { "True" } else { "False" },
+            self.report.issues)
+    }
+}
+
+/// Check a PCD file for defects (header consistency, POINTS against the body, WIDTH x HEIGHT,
+/// truncated data, malformed values) and count NaN values, without raising on them
+#[pyfunction]
+pub fn validate(py: Python<'_>, path: &str) -> PyResult<PyValidationReport> {
+    let report = py.allow_threads(|| validate_file(path))
+        .map_err(io_error)?;
+    Ok(PyValidationReport { report })
+}
+
+/// Write the points that can be recovered from the PCD file at `path` to `out_path`, fixing
+/// POINTS, WIDTH and HEIGHT, and return the report of the original file. Raises if no points
+/// can be recovered (invalid header or unreadable compressed data)
+#[pyfunction]
+pub fn repair(py: Python<'_>, path: &str, out_path: &str) -> PyResult<PyValidationReport> {
+    let report = py.allow_threads(|| repair_file(path, out_path))
+        .map_err(io_error)?;
+    Ok(PyValidationReport { report })
+}